@@ -279,6 +279,10 @@ generate_ws_random_key ephemeral_id_secret
 MRP_VERIFICATION_SERVER_EPHEMERAL_ID_SECRET=$(< "${TARGET_DIR}/mock_relying_party/ephemeral_id_secret.key" xxd -p | tr -d '\n')
 export MRP_VERIFICATION_SERVER_EPHEMERAL_ID_SECRET
 
+# Generate the key with which the pid_issuer encrypts the BSN that it keeps for refreshing the PID
+PID_ISSUER_REFRESH_SUBJECT_KEY=$(random_bytes 32 | xxd -p | tr -d '\n')
+export PID_ISSUER_REFRESH_SUBJECT_KEY
+
 # And the mrp's wallet_server config
 render_template "${DEVENV}/mrp_verification_server.toml.template" "${WALLET_SERVER_DIR}/verification_server.toml"
 
@@ -74,6 +74,14 @@ void wire_accept_pid_issuance(int64_t port_, struct wire_uint_8_list *pin);
 
 void wire_has_active_pid_issuance_session(int64_t port_);
 
+void wire_start_issuance_from_offer(int64_t port_, struct wire_uint_8_list *uri, struct wire_uint_8_list *tx_code);
+
+void wire_create_issuance_auth_url(int64_t port_, struct wire_uint_8_list *uri);
+
+void wire_continue_issuance_authorization(int64_t port_, struct wire_uint_8_list *uri);
+
+void wire_accept_issuance(int64_t port_, struct wire_uint_8_list *pin);
+
 void wire_start_disclosure(int64_t port_, struct wire_uint_8_list *uri, bool is_qr_code);
 
 void wire_cancel_disclosure(int64_t port_);
@@ -92,8 +100,16 @@ void wire_get_history(int64_t port_);
 
 void wire_get_history_for_card(int64_t port_, struct wire_uint_8_list *doc_type);
 
+void wire_delete_document(int64_t port_, struct wire_uint_8_list *id);
+
 void wire_reset_wallet(int64_t port_);
 
+void wire_check_pending_issuances(int64_t port_);
+
+void wire_refresh_expiring_cards(int64_t port_);
+
+void wire_replenish_cards(int64_t port_, struct wire_uint_8_list *pin);
+
 struct wire_uint_8_list *new_uint_8_list_0(int32_t len);
 
 void free_WireSyncReturn(WireSyncReturn ptr);
@@ -123,6 +139,10 @@ static int64_t dummy_method_to_enforce_bundling(void) {
     dummy_var ^= ((int64_t) (void*) wire_continue_pid_issuance);
     dummy_var ^= ((int64_t) (void*) wire_accept_pid_issuance);
     dummy_var ^= ((int64_t) (void*) wire_has_active_pid_issuance_session);
+    dummy_var ^= ((int64_t) (void*) wire_start_issuance_from_offer);
+    dummy_var ^= ((int64_t) (void*) wire_create_issuance_auth_url);
+    dummy_var ^= ((int64_t) (void*) wire_continue_issuance_authorization);
+    dummy_var ^= ((int64_t) (void*) wire_accept_issuance);
     dummy_var ^= ((int64_t) (void*) wire_start_disclosure);
     dummy_var ^= ((int64_t) (void*) wire_cancel_disclosure);
     dummy_var ^= ((int64_t) (void*) wire_accept_disclosure);
@@ -132,7 +152,11 @@ static int64_t dummy_method_to_enforce_bundling(void) {
     dummy_var ^= ((int64_t) (void*) wire_unlock_wallet_with_biometrics);
     dummy_var ^= ((int64_t) (void*) wire_get_history);
     dummy_var ^= ((int64_t) (void*) wire_get_history_for_card);
+    dummy_var ^= ((int64_t) (void*) wire_delete_document);
     dummy_var ^= ((int64_t) (void*) wire_reset_wallet);
+    dummy_var ^= ((int64_t) (void*) wire_check_pending_issuances);
+    dummy_var ^= ((int64_t) (void*) wire_refresh_expiring_cards);
+    dummy_var ^= ((int64_t) (void*) wire_replenish_cards);
     dummy_var ^= ((int64_t) (void*) new_uint_8_list_0);
     dummy_var ^= ((int64_t) (void*) free_WireSyncReturn);
     dummy_var ^= ((int64_t) (void*) store_dart_post_cobject);
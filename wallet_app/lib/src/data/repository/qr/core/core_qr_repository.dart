@@ -49,6 +49,9 @@ class CoreQrRepository implements QrRepository {
         return PidIssuanceNavigationRequest(rawValue);
       case IdentifyUriResult.Disclosure:
         return DisclosureNavigationRequest(rawValue, isQrCode: true);
+      case IdentifyUriResult.CredentialOffer:
+      case IdentifyUriResult.IssuanceAuthorization:
+        throw UnsupportedError('Issuance from a credential offer is not yet supported by the app');
     }
   }
 }
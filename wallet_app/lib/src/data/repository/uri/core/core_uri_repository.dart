@@ -23,6 +23,9 @@ class CoreUriRepository implements UriRepository {
         return PidIssuanceNavigationRequest(uri.toString());
       case IdentifyUriResult.Disclosure:
         return DisclosureNavigationRequest(uri.toString());
+      case IdentifyUriResult.CredentialOffer:
+      case IdentifyUriResult.IssuanceAuthorization:
+        throw UnsupportedError('Issuance from a credential offer is not yet supported by the app');
     }
   }
 }
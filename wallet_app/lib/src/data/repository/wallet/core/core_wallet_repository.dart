@@ -1,3 +1,4 @@
+import 'dart:async';
 import 'dart:io' as io;
 
 import 'package:fimber/fimber.dart';
@@ -59,13 +60,31 @@ class CoreWalletRepository implements WalletRepository {
   @override
   Future<WalletInstructionResult> unlockWallet(String pin) async {
     if (!(await isRegistered())) throw UnsupportedError('Wallet not yet registered!');
-    return _walletCore.unlockWallet(pin);
+    final result = await _walletCore.unlockWallet(pin);
+    if (result is WalletInstructionResult_Ok) unawaited(_updateCards());
+    return result;
   }
 
   @override
   Future<void> unlockWalletWithBiometrics() async {
     if (!(await isRegistered())) throw UnsupportedError('Wallet not yet registered!');
-    return _walletCore.unlockWithBiometrics();
+    await _walletCore.unlockWithBiometrics();
+    unawaited(_updateCards());
+  }
+
+  /// Retrieves the cards of which issuance was deferred and refreshes the cards that are about to expire, which is
+  /// done in the background after unlocking the wallet. As this is not triggered by the user, failures are only logged.
+  Future<void> _updateCards() async {
+    try {
+      await _walletCore.checkPendingIssuances();
+    } catch (exception) {
+      Fimber.e('Failed to check pending issuances', ex: exception);
+    }
+    try {
+      await _walletCore.refreshExpiringCards();
+    } catch (exception) {
+      Fimber.e('Failed to refresh expiring cards', ex: exception);
+    }
   }
 
   @override
@@ -22,6 +22,8 @@ class CardMapper extends Mapper<core.Card, WalletCard> {
     final String cardId = input.persistence.map(
       inMemory: (inMemory) => input.docType,
       stored: (stored) => stored.id,
+      // A deferred issuance can contain multiple cards, so the transaction id alone is not unique.
+      pending: (pending) => '${pending.transactionId}_${input.docType}',
     );
     return WalletCard(
       id: cardId,
@@ -115,6 +115,16 @@ class TypedWalletCore {
 
   Future<bool> hasActivePidIssuanceSession() => call((core) => core.hasActivePidIssuanceSession());
 
+  Future<List<Card>> startIssuanceFromOffer(String uri, {String? txCode}) =>
+      call((core) => core.startIssuanceFromOffer(uri: uri, txCode: txCode));
+
+  Future<String> createIssuanceAuthUrl(String uri) => call((core) => core.createIssuanceAuthUrl(uri: uri));
+
+  Future<List<Card>> continueIssuanceAuthorization(String uri) =>
+      call((core) => core.continueIssuanceAuthorization(uri: uri));
+
+  Future<WalletInstructionResult> acceptIssuance(String pin) => call((core) => core.acceptIssuance(pin: pin));
+
   Future<StartDisclosureResult> startDisclosure(String uri, {bool isQrCode = false}) =>
       call((core) => core.startDisclosure(uri: uri, isQrCode: isQrCode));
 
@@ -141,6 +151,12 @@ class TypedWalletCore {
 
   Future<void> unlockWithBiometrics() => call((core) => core.unlockWalletWithBiometrics());
 
+  Future<void> checkPendingIssuances() => call((core) => core.checkPendingIssuances());
+
+  Future<void> refreshExpiringCards() => call((core) => core.refreshExpiringCards());
+
+  Future<ReplenishCardsResult> replenishCards(String pin) => call((core) => core.replenishCards(pin: pin));
+
   /// This function should be used to call through to the core, as it makes sure potential exceptions are processed
   /// before they are (re)thrown.
   Future<T> call<T>(Future<T> Function(WalletCore) runnable) async {
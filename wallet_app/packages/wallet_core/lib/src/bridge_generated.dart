@@ -106,6 +106,22 @@ abstract class WalletCore {
 
   FlutterRustBridgeTaskConstMeta get kHasActivePidIssuanceSessionConstMeta;
 
+  Future<List<Card>> startIssuanceFromOffer({required String uri, String? txCode, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kStartIssuanceFromOfferConstMeta;
+
+  Future<String> createIssuanceAuthUrl({required String uri, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kCreateIssuanceAuthUrlConstMeta;
+
+  Future<List<Card>> continueIssuanceAuthorization({required String uri, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kContinueIssuanceAuthorizationConstMeta;
+
+  Future<WalletInstructionResult> acceptIssuance({required String pin, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kAcceptIssuanceConstMeta;
+
   Future<StartDisclosureResult> startDisclosure({required String uri, required bool isQrCode, dynamic hint});
 
   FlutterRustBridgeTaskConstMeta get kStartDisclosureConstMeta;
@@ -142,9 +158,25 @@ abstract class WalletCore {
 
   FlutterRustBridgeTaskConstMeta get kGetHistoryForCardConstMeta;
 
+  Future<void> deleteDocument({required String id, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kDeleteDocumentConstMeta;
+
   Future<void> resetWallet({dynamic hint});
 
   FlutterRustBridgeTaskConstMeta get kResetWalletConstMeta;
+
+  Future<void> checkPendingIssuances({dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kCheckPendingIssuancesConstMeta;
+
+  Future<void> refreshExpiringCards({dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kRefreshExpiringCardsConstMeta;
+
+  Future<ReplenishCardsResult> replenishCards({required String pin, dynamic hint});
+
+  FlutterRustBridgeTaskConstMeta get kReplenishCardsConstMeta;
 }
 
 @freezed
@@ -189,6 +221,9 @@ class CardPersistence with _$CardPersistence {
   const factory CardPersistence.stored({
     required String id,
   }) = CardPersistence_Stored;
+  const factory CardPersistence.pending({
+    required String transactionId,
+  }) = CardPersistence_Pending;
 }
 
 @freezed
@@ -257,6 +292,8 @@ enum GenderCardValue {
 enum IdentifyUriResult {
   PidIssuance,
   Disclosure,
+  CredentialOffer,
+  IssuanceAuthorization,
 }
 
 @freezed
@@ -342,6 +379,16 @@ class RequestPolicy {
   });
 }
 
+@freezed
+class ReplenishCardsResult with _$ReplenishCardsResult {
+  const factory ReplenishCardsResult.ok({
+    required List<String> reissuanceDocTypes,
+  }) = ReplenishCardsResult_Ok;
+  const factory ReplenishCardsResult.instructionError({
+    required WalletInstructionError error,
+  }) = ReplenishCardsResult_InstructionError;
+}
+
 @freezed
 class StartDisclosureResult with _$StartDisclosureResult {
   const factory StartDisclosureResult.request({
@@ -785,6 +832,75 @@ class WalletCoreImpl implements WalletCore {
         argNames: [],
       );
 
+  Future<List<Card>> startIssuanceFromOffer({required String uri, String? txCode, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(uri);
+    var arg1 = _platform.api2wire_opt_String(txCode);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_start_issuance_from_offer(port_, arg0, arg1),
+      parseSuccessData: _wire2api_list_card,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kStartIssuanceFromOfferConstMeta,
+      argValues: [uri, txCode],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kStartIssuanceFromOfferConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "start_issuance_from_offer",
+        argNames: ["uri", "txCode"],
+      );
+
+  Future<String> createIssuanceAuthUrl({required String uri, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(uri);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_create_issuance_auth_url(port_, arg0),
+      parseSuccessData: _wire2api_String,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kCreateIssuanceAuthUrlConstMeta,
+      argValues: [uri],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kCreateIssuanceAuthUrlConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "create_issuance_auth_url",
+        argNames: ["uri"],
+      );
+
+  Future<List<Card>> continueIssuanceAuthorization({required String uri, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(uri);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_continue_issuance_authorization(port_, arg0),
+      parseSuccessData: _wire2api_list_card,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kContinueIssuanceAuthorizationConstMeta,
+      argValues: [uri],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kContinueIssuanceAuthorizationConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "continue_issuance_authorization",
+        argNames: ["uri"],
+      );
+
+  Future<WalletInstructionResult> acceptIssuance({required String pin, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(pin);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_accept_issuance(port_, arg0),
+      parseSuccessData: _wire2api_wallet_instruction_result,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kAcceptIssuanceConstMeta,
+      argValues: [pin],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kAcceptIssuanceConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "accept_issuance",
+        argNames: ["pin"],
+      );
+
   Future<StartDisclosureResult> startDisclosure({required String uri, required bool isQrCode, dynamic hint}) {
     var arg0 = _platform.api2wire_String(uri);
     var arg1 = isQrCode;
@@ -934,6 +1050,23 @@ class WalletCoreImpl implements WalletCore {
         argNames: ["docType"],
       );
 
+  Future<void> deleteDocument({required String id, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(id);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_delete_document(port_, arg0),
+      parseSuccessData: _wire2api_unit,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kDeleteDocumentConstMeta,
+      argValues: [id],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kDeleteDocumentConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "delete_document",
+        argNames: ["id"],
+      );
+
   Future<void> resetWallet({dynamic hint}) {
     return _platform.executeNormal(FlutterRustBridgeTask(
       callFfi: (port_) => _platform.inner.wire_reset_wallet(port_),
@@ -950,6 +1083,55 @@ class WalletCoreImpl implements WalletCore {
         argNames: [],
       );
 
+  Future<void> checkPendingIssuances({dynamic hint}) {
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_check_pending_issuances(port_),
+      parseSuccessData: _wire2api_unit,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kCheckPendingIssuancesConstMeta,
+      argValues: [],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kCheckPendingIssuancesConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "check_pending_issuances",
+        argNames: [],
+      );
+
+  Future<void> refreshExpiringCards({dynamic hint}) {
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_refresh_expiring_cards(port_),
+      parseSuccessData: _wire2api_unit,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kRefreshExpiringCardsConstMeta,
+      argValues: [],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kRefreshExpiringCardsConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "refresh_expiring_cards",
+        argNames: [],
+      );
+
+  Future<ReplenishCardsResult> replenishCards({required String pin, dynamic hint}) {
+    var arg0 = _platform.api2wire_String(pin);
+    return _platform.executeNormal(FlutterRustBridgeTask(
+      callFfi: (port_) => _platform.inner.wire_replenish_cards(port_, arg0),
+      parseSuccessData: _wire2api_replenish_cards_result,
+      parseErrorData: _wire2api_FrbAnyhowException,
+      constMeta: kReplenishCardsConstMeta,
+      argValues: [pin],
+      hint: hint,
+    ));
+  }
+
+  FlutterRustBridgeTaskConstMeta get kReplenishCardsConstMeta => const FlutterRustBridgeTaskConstMeta(
+        debugName: "replenish_cards",
+        argNames: ["pin"],
+      );
+
   void dispose() {
     _platform.dispose();
   }
@@ -963,6 +1145,10 @@ class WalletCoreImpl implements WalletCore {
     return raw as String;
   }
 
+  List<String> _wire2api_StringList(dynamic raw) {
+    return (raw as List<dynamic>).cast<String>();
+  }
+
   AcceptDisclosureResult _wire2api_accept_disclosure_result(dynamic raw) {
     switch (raw[0]) {
       case 0:
@@ -1035,6 +1221,10 @@ class WalletCoreImpl implements WalletCore {
         return CardPersistence_Stored(
           id: _wire2api_String(raw[1]),
         );
+      case 2:
+        return CardPersistence_Pending(
+          transactionId: _wire2api_String(raw[1]),
+        );
       default:
         throw Exception("unreachable");
     }
@@ -1224,6 +1414,21 @@ class WalletCoreImpl implements WalletCore {
     );
   }
 
+  ReplenishCardsResult _wire2api_replenish_cards_result(dynamic raw) {
+    switch (raw[0]) {
+      case 0:
+        return ReplenishCardsResult_Ok(
+          reissuanceDocTypes: _wire2api_StringList(raw[1]),
+        );
+      case 1:
+        return ReplenishCardsResult_InstructionError(
+          error: _wire2api_box_autoadd_wallet_instruction_error(raw[1]),
+        );
+      default:
+        throw Exception("unreachable");
+    }
+  }
+
   StartDisclosureResult _wire2api_start_disclosure_result(dynamic raw) {
     switch (raw[0]) {
       case 0:
@@ -1349,6 +1554,11 @@ class WalletCorePlatform extends FlutterRustBridgeBase<WalletCoreWire> {
     return api2wire_uint_8_list(utf8.encoder.convert(raw));
   }
 
+  @protected
+  ffi.Pointer<wire_uint_8_list> api2wire_opt_String(String? raw) {
+    return raw == null ? ffi.nullptr : api2wire_String(raw);
+  }
+
   @protected
   ffi.Pointer<wire_uint_8_list> api2wire_uint_8_list(Uint8List raw) {
     final ans = inner.new_uint_8_list_0(raw.length);
@@ -1740,6 +1950,72 @@ class WalletCoreWire implements FlutterRustBridgeWireBase {
   late final _wire_has_active_pid_issuance_session =
       _wire_has_active_pid_issuance_sessionPtr.asFunction<void Function(int)>();
 
+  void wire_start_issuance_from_offer(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> uri,
+    ffi.Pointer<wire_uint_8_list> tx_code,
+  ) {
+    return _wire_start_issuance_from_offer(
+      port_,
+      uri,
+      tx_code,
+    );
+  }
+
+  late final _wire_start_issuance_from_offerPtr = _lookup<
+          ffi
+          .NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>, ffi.Pointer<wire_uint_8_list>)>>(
+      'wire_start_issuance_from_offer');
+  late final _wire_start_issuance_from_offer = _wire_start_issuance_from_offerPtr
+      .asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>, ffi.Pointer<wire_uint_8_list>)>();
+
+  void wire_create_issuance_auth_url(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> uri,
+  ) {
+    return _wire_create_issuance_auth_url(
+      port_,
+      uri,
+    );
+  }
+
+  late final _wire_create_issuance_auth_urlPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>)>>(
+          'wire_create_issuance_auth_url');
+  late final _wire_create_issuance_auth_url =
+      _wire_create_issuance_auth_urlPtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
+
+  void wire_continue_issuance_authorization(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> uri,
+  ) {
+    return _wire_continue_issuance_authorization(
+      port_,
+      uri,
+    );
+  }
+
+  late final _wire_continue_issuance_authorizationPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>)>>(
+          'wire_continue_issuance_authorization');
+  late final _wire_continue_issuance_authorization =
+      _wire_continue_issuance_authorizationPtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
+
+  void wire_accept_issuance(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> pin,
+  ) {
+    return _wire_accept_issuance(
+      port_,
+      pin,
+    );
+  }
+
+  late final _wire_accept_issuancePtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>)>>('wire_accept_issuance');
+  late final _wire_accept_issuance =
+      _wire_accept_issuancePtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
+
   void wire_start_disclosure(
     int port_,
     ffi.Pointer<wire_uint_8_list> uri,
@@ -1865,6 +2141,21 @@ class WalletCoreWire implements FlutterRustBridgeWireBase {
   late final _wire_get_history_for_card =
       _wire_get_history_for_cardPtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
 
+  void wire_delete_document(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> id,
+  ) {
+    return _wire_delete_document(
+      port_,
+      id,
+    );
+  }
+
+  late final _wire_delete_documentPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>)>>('wire_delete_document');
+  late final _wire_delete_document =
+      _wire_delete_documentPtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
+
   void wire_reset_wallet(
     int port_,
   ) {
@@ -1876,6 +2167,45 @@ class WalletCoreWire implements FlutterRustBridgeWireBase {
   late final _wire_reset_walletPtr = _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64)>>('wire_reset_wallet');
   late final _wire_reset_wallet = _wire_reset_walletPtr.asFunction<void Function(int)>();
 
+  void wire_check_pending_issuances(
+    int port_,
+  ) {
+    return _wire_check_pending_issuances(
+      port_,
+    );
+  }
+
+  late final _wire_check_pending_issuancesPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64)>>('wire_check_pending_issuances');
+  late final _wire_check_pending_issuances = _wire_check_pending_issuancesPtr.asFunction<void Function(int)>();
+
+  void wire_refresh_expiring_cards(
+    int port_,
+  ) {
+    return _wire_refresh_expiring_cards(
+      port_,
+    );
+  }
+
+  late final _wire_refresh_expiring_cardsPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64)>>('wire_refresh_expiring_cards');
+  late final _wire_refresh_expiring_cards = _wire_refresh_expiring_cardsPtr.asFunction<void Function(int)>();
+
+  void wire_replenish_cards(
+    int port_,
+    ffi.Pointer<wire_uint_8_list> pin,
+  ) {
+    return _wire_replenish_cards(
+      port_,
+      pin,
+    );
+  }
+
+  late final _wire_replenish_cardsPtr =
+      _lookup<ffi.NativeFunction<ffi.Void Function(ffi.Int64, ffi.Pointer<wire_uint_8_list>)>>('wire_replenish_cards');
+  late final _wire_replenish_cards =
+      _wire_replenish_cardsPtr.asFunction<void Function(int, ffi.Pointer<wire_uint_8_list>)>();
+
   ffi.Pointer<wire_uint_8_list> new_uint_8_list_0(
     int len,
   ) {
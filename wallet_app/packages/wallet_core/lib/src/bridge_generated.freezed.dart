@@ -376,18 +376,21 @@ mixin _$CardPersistence {
   TResult when<TResult extends Object?>({
     required TResult Function() inMemory,
     required TResult Function(String id) stored,
+    required TResult Function(String transactionId) pending,
   }) =>
       throw _privateConstructorUsedError;
   @optionalTypeArgs
   TResult? whenOrNull<TResult extends Object?>({
     TResult? Function()? inMemory,
     TResult? Function(String id)? stored,
+    TResult? Function(String transactionId)? pending,
   }) =>
       throw _privateConstructorUsedError;
   @optionalTypeArgs
   TResult maybeWhen<TResult extends Object?>({
     TResult Function()? inMemory,
     TResult Function(String id)? stored,
+    TResult Function(String transactionId)? pending,
     required TResult orElse(),
   }) =>
       throw _privateConstructorUsedError;
@@ -395,18 +398,21 @@ mixin _$CardPersistence {
   TResult map<TResult extends Object?>({
     required TResult Function(CardPersistence_InMemory value) inMemory,
     required TResult Function(CardPersistence_Stored value) stored,
+    required TResult Function(CardPersistence_Pending value) pending,
   }) =>
       throw _privateConstructorUsedError;
   @optionalTypeArgs
   TResult? mapOrNull<TResult extends Object?>({
     TResult? Function(CardPersistence_InMemory value)? inMemory,
     TResult? Function(CardPersistence_Stored value)? stored,
+    TResult? Function(CardPersistence_Pending value)? pending,
   }) =>
       throw _privateConstructorUsedError;
   @optionalTypeArgs
   TResult maybeMap<TResult extends Object?>({
     TResult Function(CardPersistence_InMemory value)? inMemory,
     TResult Function(CardPersistence_Stored value)? stored,
+    TResult Function(CardPersistence_Pending value)? pending,
     required TResult orElse(),
   }) =>
       throw _privateConstructorUsedError;
@@ -473,6 +479,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult when<TResult extends Object?>({
     required TResult Function() inMemory,
     required TResult Function(String id) stored,
+    required TResult Function(String transactionId) pending,
   }) {
     return inMemory();
   }
@@ -482,6 +489,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult? whenOrNull<TResult extends Object?>({
     TResult? Function()? inMemory,
     TResult? Function(String id)? stored,
+    TResult? Function(String transactionId)? pending,
   }) {
     return inMemory?.call();
   }
@@ -491,6 +499,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult maybeWhen<TResult extends Object?>({
     TResult Function()? inMemory,
     TResult Function(String id)? stored,
+    TResult Function(String transactionId)? pending,
     required TResult orElse(),
   }) {
     if (inMemory != null) {
@@ -504,6 +513,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult map<TResult extends Object?>({
     required TResult Function(CardPersistence_InMemory value) inMemory,
     required TResult Function(CardPersistence_Stored value) stored,
+    required TResult Function(CardPersistence_Pending value) pending,
   }) {
     return inMemory(this);
   }
@@ -513,6 +523,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult? mapOrNull<TResult extends Object?>({
     TResult? Function(CardPersistence_InMemory value)? inMemory,
     TResult? Function(CardPersistence_Stored value)? stored,
+    TResult? Function(CardPersistence_Pending value)? pending,
   }) {
     return inMemory?.call(this);
   }
@@ -522,6 +533,7 @@ class _$CardPersistence_InMemoryImpl implements CardPersistence_InMemory {
   TResult maybeMap<TResult extends Object?>({
     TResult Function(CardPersistence_InMemory value)? inMemory,
     TResult Function(CardPersistence_Stored value)? stored,
+    TResult Function(CardPersistence_Pending value)? pending,
     required TResult orElse(),
   }) {
     if (inMemory != null) {
@@ -605,6 +617,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult when<TResult extends Object?>({
     required TResult Function() inMemory,
     required TResult Function(String id) stored,
+    required TResult Function(String transactionId) pending,
   }) {
     return stored(id);
   }
@@ -614,6 +627,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult? whenOrNull<TResult extends Object?>({
     TResult? Function()? inMemory,
     TResult? Function(String id)? stored,
+    TResult? Function(String transactionId)? pending,
   }) {
     return stored?.call(id);
   }
@@ -623,6 +637,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult maybeWhen<TResult extends Object?>({
     TResult Function()? inMemory,
     TResult Function(String id)? stored,
+    TResult Function(String transactionId)? pending,
     required TResult orElse(),
   }) {
     if (stored != null) {
@@ -636,6 +651,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult map<TResult extends Object?>({
     required TResult Function(CardPersistence_InMemory value) inMemory,
     required TResult Function(CardPersistence_Stored value) stored,
+    required TResult Function(CardPersistence_Pending value) pending,
   }) {
     return stored(this);
   }
@@ -645,6 +661,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult? mapOrNull<TResult extends Object?>({
     TResult? Function(CardPersistence_InMemory value)? inMemory,
     TResult? Function(CardPersistence_Stored value)? stored,
+    TResult? Function(CardPersistence_Pending value)? pending,
   }) {
     return stored?.call(this);
   }
@@ -654,6 +671,7 @@ class _$CardPersistence_StoredImpl implements CardPersistence_Stored {
   TResult maybeMap<TResult extends Object?>({
     TResult Function(CardPersistence_InMemory value)? inMemory,
     TResult Function(CardPersistence_Stored value)? stored,
+    TResult Function(CardPersistence_Pending value)? pending,
     required TResult orElse(),
   }) {
     if (stored != null) {
@@ -675,6 +693,152 @@ abstract class CardPersistence_Stored implements CardPersistence {
       throw _privateConstructorUsedError;
 }
 
+/// @nodoc
+abstract class _$$CardPersistence_PendingImplCopyWith<$Res> {
+  factory _$$CardPersistence_PendingImplCopyWith(
+          _$CardPersistence_PendingImpl value, $Res Function(_$CardPersistence_PendingImpl) then) =
+      __$$CardPersistence_PendingImplCopyWithImpl<$Res>;
+  @useResult
+  $Res call({String transactionId});
+}
+
+/// @nodoc
+class __$$CardPersistence_PendingImplCopyWithImpl<$Res>
+    extends _$CardPersistenceCopyWithImpl<$Res, _$CardPersistence_PendingImpl>
+    implements _$$CardPersistence_PendingImplCopyWith<$Res> {
+  __$$CardPersistence_PendingImplCopyWithImpl(
+      _$CardPersistence_PendingImpl _value, $Res Function(_$CardPersistence_PendingImpl) _then)
+      : super(_value, _then);
+
+  /// Create a copy of CardPersistence
+  /// with the given fields replaced by the non-null parameter values.
+  @pragma('vm:prefer-inline')
+  @override
+  $Res call({
+    Object? transactionId = null,
+  }) {
+    return _then(_$CardPersistence_PendingImpl(
+      transactionId: null == transactionId
+          ? _value.transactionId
+          : transactionId // ignore: cast_nullable_to_non_nullable
+              as String,
+    ));
+  }
+}
+
+/// @nodoc
+
+class _$CardPersistence_PendingImpl implements CardPersistence_Pending {
+  const _$CardPersistence_PendingImpl({required this.transactionId});
+
+  @override
+  final String transactionId;
+
+  @override
+  String toString() {
+    return 'CardPersistence.pending(transactionId: $transactionId)';
+  }
+
+  @override
+  bool operator ==(Object other) {
+    return identical(this, other) ||
+        (other.runtimeType == runtimeType &&
+            other is _$CardPersistence_PendingImpl &&
+            (identical(other.transactionId, transactionId) || other.transactionId == transactionId));
+  }
+
+  @override
+  int get hashCode => Object.hash(runtimeType, transactionId);
+
+  /// Create a copy of CardPersistence
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  @override
+  @pragma('vm:prefer-inline')
+  _$$CardPersistence_PendingImplCopyWith<_$CardPersistence_PendingImpl> get copyWith =>
+      __$$CardPersistence_PendingImplCopyWithImpl<_$CardPersistence_PendingImpl>(this, _$identity);
+
+  @override
+  @optionalTypeArgs
+  TResult when<TResult extends Object?>({
+    required TResult Function() inMemory,
+    required TResult Function(String id) stored,
+    required TResult Function(String transactionId) pending,
+  }) {
+    return pending(transactionId);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? whenOrNull<TResult extends Object?>({
+    TResult? Function()? inMemory,
+    TResult? Function(String id)? stored,
+    TResult? Function(String transactionId)? pending,
+  }) {
+    return pending?.call(transactionId);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeWhen<TResult extends Object?>({
+    TResult Function()? inMemory,
+    TResult Function(String id)? stored,
+    TResult Function(String transactionId)? pending,
+    required TResult orElse(),
+  }) {
+    if (pending != null) {
+      return pending(transactionId);
+    }
+    return orElse();
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult map<TResult extends Object?>({
+    required TResult Function(CardPersistence_InMemory value) inMemory,
+    required TResult Function(CardPersistence_Stored value) stored,
+    required TResult Function(CardPersistence_Pending value) pending,
+  }) {
+    return pending(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? mapOrNull<TResult extends Object?>({
+    TResult? Function(CardPersistence_InMemory value)? inMemory,
+    TResult? Function(CardPersistence_Stored value)? stored,
+    TResult? Function(CardPersistence_Pending value)? pending,
+  }) {
+    return pending?.call(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeMap<TResult extends Object?>({
+    TResult Function(CardPersistence_InMemory value)? inMemory,
+    TResult Function(CardPersistence_Stored value)? stored,
+    TResult Function(CardPersistence_Pending value)? pending,
+    required TResult orElse(),
+  }) {
+    if (pending != null) {
+      return pending(this);
+    }
+    return orElse();
+  }
+}
+
+abstract class CardPersistence_Pending implements CardPersistence {
+  const factory CardPersistence_Pending({required final String transactionId}) = _$CardPersistence_PendingImpl;
+
+  String get transactionId;
+
+  /// Create a copy of CardPersistence
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  _$$CardPersistence_PendingImplCopyWith<_$CardPersistence_PendingImpl> get copyWith =>
+      throw _privateConstructorUsedError;
+}
+
 /// @nodoc
 mixin _$CardValue {
   Object get value => throw _privateConstructorUsedError;
@@ -2005,6 +2169,369 @@ abstract class Image_Asset implements Image {
   _$$Image_AssetImplCopyWith<_$Image_AssetImpl> get copyWith => throw _privateConstructorUsedError;
 }
 
+/// @nodoc
+mixin _$ReplenishCardsResult {
+  @optionalTypeArgs
+  TResult when<TResult extends Object?>({
+    required TResult Function(List<String> reissuanceDocTypes) ok,
+    required TResult Function(WalletInstructionError error) instructionError,
+  }) =>
+      throw _privateConstructorUsedError;
+  @optionalTypeArgs
+  TResult? whenOrNull<TResult extends Object?>({
+    TResult? Function(List<String> reissuanceDocTypes)? ok,
+    TResult? Function(WalletInstructionError error)? instructionError,
+  }) =>
+      throw _privateConstructorUsedError;
+  @optionalTypeArgs
+  TResult maybeWhen<TResult extends Object?>({
+    TResult Function(List<String> reissuanceDocTypes)? ok,
+    TResult Function(WalletInstructionError error)? instructionError,
+    required TResult orElse(),
+  }) =>
+      throw _privateConstructorUsedError;
+  @optionalTypeArgs
+  TResult map<TResult extends Object?>({
+    required TResult Function(ReplenishCardsResult_Ok value) ok,
+    required TResult Function(ReplenishCardsResult_InstructionError value) instructionError,
+  }) =>
+      throw _privateConstructorUsedError;
+  @optionalTypeArgs
+  TResult? mapOrNull<TResult extends Object?>({
+    TResult? Function(ReplenishCardsResult_Ok value)? ok,
+    TResult? Function(ReplenishCardsResult_InstructionError value)? instructionError,
+  }) =>
+      throw _privateConstructorUsedError;
+  @optionalTypeArgs
+  TResult maybeMap<TResult extends Object?>({
+    TResult Function(ReplenishCardsResult_Ok value)? ok,
+    TResult Function(ReplenishCardsResult_InstructionError value)? instructionError,
+    required TResult orElse(),
+  }) =>
+      throw _privateConstructorUsedError;
+}
+
+/// @nodoc
+abstract class $ReplenishCardsResultCopyWith<$Res> {
+  factory $ReplenishCardsResultCopyWith(ReplenishCardsResult value, $Res Function(ReplenishCardsResult) then) =
+      _$ReplenishCardsResultCopyWithImpl<$Res, ReplenishCardsResult>;
+}
+
+/// @nodoc
+class _$ReplenishCardsResultCopyWithImpl<$Res, $Val extends ReplenishCardsResult>
+    implements $ReplenishCardsResultCopyWith<$Res> {
+  _$ReplenishCardsResultCopyWithImpl(this._value, this._then);
+
+  // ignore: unused_field
+  final $Val _value;
+  // ignore: unused_field
+  final $Res Function($Val) _then;
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+}
+
+/// @nodoc
+abstract class _$$ReplenishCardsResult_OkImplCopyWith<$Res> {
+  factory _$$ReplenishCardsResult_OkImplCopyWith(
+          _$ReplenishCardsResult_OkImpl value, $Res Function(_$ReplenishCardsResult_OkImpl) then) =
+      __$$ReplenishCardsResult_OkImplCopyWithImpl<$Res>;
+  @useResult
+  $Res call({List<String> reissuanceDocTypes});
+}
+
+/// @nodoc
+class __$$ReplenishCardsResult_OkImplCopyWithImpl<$Res>
+    extends _$ReplenishCardsResultCopyWithImpl<$Res, _$ReplenishCardsResult_OkImpl>
+    implements _$$ReplenishCardsResult_OkImplCopyWith<$Res> {
+  __$$ReplenishCardsResult_OkImplCopyWithImpl(
+      _$ReplenishCardsResult_OkImpl _value, $Res Function(_$ReplenishCardsResult_OkImpl) _then)
+      : super(_value, _then);
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @pragma('vm:prefer-inline')
+  @override
+  $Res call({
+    Object? reissuanceDocTypes = null,
+  }) {
+    return _then(_$ReplenishCardsResult_OkImpl(
+      reissuanceDocTypes: null == reissuanceDocTypes
+          ? _value._reissuanceDocTypes
+          : reissuanceDocTypes // ignore: cast_nullable_to_non_nullable
+              as List<String>,
+    ));
+  }
+}
+
+/// @nodoc
+
+class _$ReplenishCardsResult_OkImpl implements ReplenishCardsResult_Ok {
+  const _$ReplenishCardsResult_OkImpl({required final List<String> reissuanceDocTypes})
+      : _reissuanceDocTypes = reissuanceDocTypes;
+
+  final List<String> _reissuanceDocTypes;
+  @override
+  List<String> get reissuanceDocTypes {
+    if (_reissuanceDocTypes is EqualUnmodifiableListView) return _reissuanceDocTypes;
+    // ignore: implicit_dynamic_type
+    return EqualUnmodifiableListView(_reissuanceDocTypes);
+  }
+
+  @override
+  String toString() {
+    return 'ReplenishCardsResult.ok(reissuanceDocTypes: $reissuanceDocTypes)';
+  }
+
+  @override
+  bool operator ==(Object other) {
+    return identical(this, other) ||
+        (other.runtimeType == runtimeType &&
+            other is _$ReplenishCardsResult_OkImpl &&
+            const DeepCollectionEquality().equals(other._reissuanceDocTypes, _reissuanceDocTypes));
+  }
+
+  @override
+  int get hashCode => Object.hash(runtimeType, const DeepCollectionEquality().hash(_reissuanceDocTypes));
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  @override
+  @pragma('vm:prefer-inline')
+  _$$ReplenishCardsResult_OkImplCopyWith<_$ReplenishCardsResult_OkImpl> get copyWith =>
+      __$$ReplenishCardsResult_OkImplCopyWithImpl<_$ReplenishCardsResult_OkImpl>(this, _$identity);
+
+  @override
+  @optionalTypeArgs
+  TResult when<TResult extends Object?>({
+    required TResult Function(List<String> reissuanceDocTypes) ok,
+    required TResult Function(WalletInstructionError error) instructionError,
+  }) {
+    return ok(reissuanceDocTypes);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? whenOrNull<TResult extends Object?>({
+    TResult? Function(List<String> reissuanceDocTypes)? ok,
+    TResult? Function(WalletInstructionError error)? instructionError,
+  }) {
+    return ok?.call(reissuanceDocTypes);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeWhen<TResult extends Object?>({
+    TResult Function(List<String> reissuanceDocTypes)? ok,
+    TResult Function(WalletInstructionError error)? instructionError,
+    required TResult orElse(),
+  }) {
+    if (ok != null) {
+      return ok(reissuanceDocTypes);
+    }
+    return orElse();
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult map<TResult extends Object?>({
+    required TResult Function(ReplenishCardsResult_Ok value) ok,
+    required TResult Function(ReplenishCardsResult_InstructionError value) instructionError,
+  }) {
+    return ok(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? mapOrNull<TResult extends Object?>({
+    TResult? Function(ReplenishCardsResult_Ok value)? ok,
+    TResult? Function(ReplenishCardsResult_InstructionError value)? instructionError,
+  }) {
+    return ok?.call(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeMap<TResult extends Object?>({
+    TResult Function(ReplenishCardsResult_Ok value)? ok,
+    TResult Function(ReplenishCardsResult_InstructionError value)? instructionError,
+    required TResult orElse(),
+  }) {
+    if (ok != null) {
+      return ok(this);
+    }
+    return orElse();
+  }
+}
+
+abstract class ReplenishCardsResult_Ok implements ReplenishCardsResult {
+  const factory ReplenishCardsResult_Ok({required final List<String> reissuanceDocTypes}) =
+      _$ReplenishCardsResult_OkImpl;
+
+  List<String> get reissuanceDocTypes;
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  _$$ReplenishCardsResult_OkImplCopyWith<_$ReplenishCardsResult_OkImpl> get copyWith =>
+      throw _privateConstructorUsedError;
+}
+
+/// @nodoc
+abstract class _$$ReplenishCardsResult_InstructionErrorImplCopyWith<$Res> {
+  factory _$$ReplenishCardsResult_InstructionErrorImplCopyWith(_$ReplenishCardsResult_InstructionErrorImpl value,
+          $Res Function(_$ReplenishCardsResult_InstructionErrorImpl) then) =
+      __$$ReplenishCardsResult_InstructionErrorImplCopyWithImpl<$Res>;
+  @useResult
+  $Res call({WalletInstructionError error});
+
+  $WalletInstructionErrorCopyWith<$Res> get error;
+}
+
+/// @nodoc
+class __$$ReplenishCardsResult_InstructionErrorImplCopyWithImpl<$Res>
+    extends _$ReplenishCardsResultCopyWithImpl<$Res, _$ReplenishCardsResult_InstructionErrorImpl>
+    implements _$$ReplenishCardsResult_InstructionErrorImplCopyWith<$Res> {
+  __$$ReplenishCardsResult_InstructionErrorImplCopyWithImpl(_$ReplenishCardsResult_InstructionErrorImpl _value,
+      $Res Function(_$ReplenishCardsResult_InstructionErrorImpl) _then)
+      : super(_value, _then);
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @pragma('vm:prefer-inline')
+  @override
+  $Res call({
+    Object? error = null,
+  }) {
+    return _then(_$ReplenishCardsResult_InstructionErrorImpl(
+      error: null == error
+          ? _value.error
+          : error // ignore: cast_nullable_to_non_nullable
+              as WalletInstructionError,
+    ));
+  }
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @override
+  @pragma('vm:prefer-inline')
+  $WalletInstructionErrorCopyWith<$Res> get error {
+    return $WalletInstructionErrorCopyWith<$Res>(_value.error, (value) {
+      return _then(_value.copyWith(error: value));
+    });
+  }
+}
+
+/// @nodoc
+
+class _$ReplenishCardsResult_InstructionErrorImpl implements ReplenishCardsResult_InstructionError {
+  const _$ReplenishCardsResult_InstructionErrorImpl({required this.error});
+
+  @override
+  final WalletInstructionError error;
+
+  @override
+  String toString() {
+    return 'ReplenishCardsResult.instructionError(error: $error)';
+  }
+
+  @override
+  bool operator ==(Object other) {
+    return identical(this, other) ||
+        (other.runtimeType == runtimeType &&
+            other is _$ReplenishCardsResult_InstructionErrorImpl &&
+            (identical(other.error, error) || other.error == error));
+  }
+
+  @override
+  int get hashCode => Object.hash(runtimeType, error);
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  @override
+  @pragma('vm:prefer-inline')
+  _$$ReplenishCardsResult_InstructionErrorImplCopyWith<_$ReplenishCardsResult_InstructionErrorImpl> get copyWith =>
+      __$$ReplenishCardsResult_InstructionErrorImplCopyWithImpl<_$ReplenishCardsResult_InstructionErrorImpl>(
+          this, _$identity);
+
+  @override
+  @optionalTypeArgs
+  TResult when<TResult extends Object?>({
+    required TResult Function(List<String> reissuanceDocTypes) ok,
+    required TResult Function(WalletInstructionError error) instructionError,
+  }) {
+    return instructionError(error);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? whenOrNull<TResult extends Object?>({
+    TResult? Function(List<String> reissuanceDocTypes)? ok,
+    TResult? Function(WalletInstructionError error)? instructionError,
+  }) {
+    return instructionError?.call(error);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeWhen<TResult extends Object?>({
+    TResult Function(List<String> reissuanceDocTypes)? ok,
+    TResult Function(WalletInstructionError error)? instructionError,
+    required TResult orElse(),
+  }) {
+    if (instructionError != null) {
+      return instructionError(error);
+    }
+    return orElse();
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult map<TResult extends Object?>({
+    required TResult Function(ReplenishCardsResult_Ok value) ok,
+    required TResult Function(ReplenishCardsResult_InstructionError value) instructionError,
+  }) {
+    return instructionError(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult? mapOrNull<TResult extends Object?>({
+    TResult? Function(ReplenishCardsResult_Ok value)? ok,
+    TResult? Function(ReplenishCardsResult_InstructionError value)? instructionError,
+  }) {
+    return instructionError?.call(this);
+  }
+
+  @override
+  @optionalTypeArgs
+  TResult maybeMap<TResult extends Object?>({
+    TResult Function(ReplenishCardsResult_Ok value)? ok,
+    TResult Function(ReplenishCardsResult_InstructionError value)? instructionError,
+    required TResult orElse(),
+  }) {
+    if (instructionError != null) {
+      return instructionError(this);
+    }
+    return orElse();
+  }
+}
+
+abstract class ReplenishCardsResult_InstructionError implements ReplenishCardsResult {
+  const factory ReplenishCardsResult_InstructionError({required final WalletInstructionError error}) =
+      _$ReplenishCardsResult_InstructionErrorImpl;
+
+  WalletInstructionError get error;
+
+  /// Create a copy of ReplenishCardsResult
+  /// with the given fields replaced by the non-null parameter values.
+  @JsonKey(includeFromJson: false, includeToJson: false)
+  _$$ReplenishCardsResult_InstructionErrorImplCopyWith<_$ReplenishCardsResult_InstructionErrorImpl> get copyWith =>
+      throw _privateConstructorUsedError;
+}
+
 /// @nodoc
 mixin _$StartDisclosureResult {
   Organization get relyingParty => throw _privateConstructorUsedError;
@@ -67,4 +67,10 @@ class Wallet {
     final newCardList = List.of(cardsToKeep)..addAll(cards);
     _cardsSubject.add(newCardList);
   }
+
+  /// Removes the stored card with the provided id from the wallet.
+  void remove(String id) {
+    final newCardList = _cards.whereNot((card) => card.persistence == CardPersistence.stored(id: id)).toList();
+    _cardsSubject.add(newCardList);
+  }
 }
@@ -195,6 +195,21 @@ class WalletCoreMock extends _FlutterRustBridgeTasksMeta implements WalletCore {
     _wallet.unlock();
   }
 
+  @override
+  Future<void> deleteDocument({required String id, hint}) async => _wallet.remove(id);
+
+  @override
+  Future<List<Card>> startIssuanceFromOffer({required String uri, String? txCode, hint}) async => kPidCards;
+
+  @override
+  Future<WalletInstructionResult> acceptIssuance({required String pin, hint}) async => _pinManager.checkPin(pin);
+
+  @override
+  Future<String> createIssuanceAuthUrl({required String uri, hint}) async => kMockPidIssuanceRedirectUri;
+
+  @override
+  Future<List<Card>> continueIssuanceAuthorization({required String uri, hint}) async => kPidCards;
+
   @override
   Future<void> resetWallet({hint}) async {
     await _pinManager.resetPin();
@@ -267,6 +282,21 @@ class WalletCoreMock extends _FlutterRustBridgeTasksMeta implements WalletCore {
 
   @override
   Future<WalletInstructionResult> checkPin({required String pin, hint}) async => _pinManager.checkPin(pin);
+
+  @override
+  Future<void> checkPendingIssuances({hint}) async {}
+
+  @override
+  Future<void> refreshExpiringCards({hint}) async {}
+
+  @override
+  Future<ReplenishCardsResult> replenishCards({required String pin, hint}) async {
+    final result = _pinManager.checkPin(pin);
+    if (result is WalletInstructionResult_InstructionError) {
+      return ReplenishCardsResult.instructionError(error: result.error);
+    }
+    return const ReplenishCardsResult.ok(reissuanceDocTypes: []);
+  }
 }
 
 /// Helper class to make [WalletCoreMock] satisfy [WalletCore]
@@ -308,6 +338,16 @@ class _FlutterRustBridgeTasksMeta {
 
   FlutterRustBridgeTaskConstMeta get kResetWalletConstMeta => throw UnimplementedError();
 
+  FlutterRustBridgeTaskConstMeta get kContinueIssuanceAuthorizationConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kCreateIssuanceAuthUrlConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kAcceptIssuanceConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kStartIssuanceFromOfferConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kDeleteDocumentConstMeta => throw UnimplementedError();
+
   FlutterRustBridgeTaskConstMeta get kSetCardsStreamConstMeta => throw UnimplementedError();
 
   FlutterRustBridgeTaskConstMeta get kSetConfigurationStreamConstMeta => throw UnimplementedError();
@@ -339,4 +379,10 @@ class _FlutterRustBridgeTasksMeta {
   FlutterRustBridgeTaskConstMeta get kChangePinConstMeta => throw UnimplementedError();
 
   FlutterRustBridgeTaskConstMeta get kCheckPinConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kCheckPendingIssuancesConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kRefreshExpiringCardsConstMeta => throw UnimplementedError();
+
+  FlutterRustBridgeTaskConstMeta get kReplenishCardsConstMeta => throw UnimplementedError();
 }
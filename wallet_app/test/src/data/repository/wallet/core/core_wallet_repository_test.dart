@@ -73,6 +73,28 @@ void main() {
     test('unlocking is not possible when not registered', () async {
       expect(() => repo.unlockWallet(_kValidPin), throwsUnsupportedError);
     });
+
+    test('cards are updated after the wallet is unlocked', () async {
+      await repo.createWallet(_kValidPin);
+      await repo.lockWallet();
+      clearInteractions(core);
+      await repo.unlockWallet(_kValidPin);
+      await pumpEventQueue();
+
+      verify(core.checkPendingIssuances()).called(1);
+      verify(core.refreshExpiringCards()).called(1);
+    });
+
+    test('cards are not updated when unlocking fails', () async {
+      await repo.createWallet(_kValidPin);
+      await repo.lockWallet();
+      clearInteractions(core);
+      await repo.unlockWallet('invalid');
+      await pumpEventQueue();
+
+      verifyNever(core.checkPendingIssuances());
+      verifyNever(core.refreshExpiringCards());
+    });
   });
 
   group('registered state', () {
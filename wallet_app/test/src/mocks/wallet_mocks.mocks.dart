@@ -284,6 +284,16 @@ class _FakeFlutterRustBridgeTaskConstMeta_15 extends _i1.SmartFake implements _i
         );
 }
 
+class _FakeReplenishCardsResult_16 extends _i1.SmartFake implements _i7.ReplenishCardsResult {
+  _FakeReplenishCardsResult_16(
+    Object parent,
+    Invocation parentInvocation,
+  ) : super(
+          parent,
+          parentInvocation,
+        );
+}
+
 /// A class which mocks [NavigatorState].
 ///
 /// See the documentation for Mockito's code generation for more information.
@@ -2169,6 +2179,48 @@ class MockTypedWalletCore extends _i1.Mock implements _i30.TypedWalletCore {
         returnValueForMissingStub: _i9.Future<void>.value(),
       ) as _i9.Future<void>);
 
+  @override
+  _i9.Future<void> checkPendingIssuances() => (super.noSuchMethod(
+        Invocation.method(
+          #checkPendingIssuances,
+          [],
+        ),
+        returnValue: _i9.Future<void>.value(),
+        returnValueForMissingStub: _i9.Future<void>.value(),
+      ) as _i9.Future<void>);
+
+  @override
+  _i9.Future<void> refreshExpiringCards() => (super.noSuchMethod(
+        Invocation.method(
+          #refreshExpiringCards,
+          [],
+        ),
+        returnValue: _i9.Future<void>.value(),
+        returnValueForMissingStub: _i9.Future<void>.value(),
+      ) as _i9.Future<void>);
+
+  @override
+  _i9.Future<_i7.ReplenishCardsResult> replenishCards(String? pin) => (super.noSuchMethod(
+        Invocation.method(
+          #replenishCards,
+          [pin],
+        ),
+        returnValue: _i9.Future<_i7.ReplenishCardsResult>.value(_FakeReplenishCardsResult_16(
+          this,
+          Invocation.method(
+            #replenishCards,
+            [pin],
+          ),
+        )),
+        returnValueForMissingStub: _i9.Future<_i7.ReplenishCardsResult>.value(_FakeReplenishCardsResult_16(
+          this,
+          Invocation.method(
+            #replenishCards,
+            [pin],
+          ),
+        )),
+      ) as _i9.Future<_i7.ReplenishCardsResult>);
+
   @override
   _i9.Future<T> call<T>(_i9.Future<T> Function(_i7.WalletCore)? runnable) => (super.noSuchMethod(
         Invocation.method(
@@ -3654,6 +3706,45 @@ class MockWalletCore extends _i1.Mock implements _i7.WalletCore {
         ),
       ) as _i7.FlutterRustBridgeTaskConstMeta);
 
+  @override
+  _i7.FlutterRustBridgeTaskConstMeta get kCheckPendingIssuancesConstMeta => (super.noSuchMethod(
+        Invocation.getter(#kCheckPendingIssuancesConstMeta),
+        returnValue: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kCheckPendingIssuancesConstMeta),
+        ),
+        returnValueForMissingStub: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kCheckPendingIssuancesConstMeta),
+        ),
+      ) as _i7.FlutterRustBridgeTaskConstMeta);
+
+  @override
+  _i7.FlutterRustBridgeTaskConstMeta get kRefreshExpiringCardsConstMeta => (super.noSuchMethod(
+        Invocation.getter(#kRefreshExpiringCardsConstMeta),
+        returnValue: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kRefreshExpiringCardsConstMeta),
+        ),
+        returnValueForMissingStub: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kRefreshExpiringCardsConstMeta),
+        ),
+      ) as _i7.FlutterRustBridgeTaskConstMeta);
+
+  @override
+  _i7.FlutterRustBridgeTaskConstMeta get kReplenishCardsConstMeta => (super.noSuchMethod(
+        Invocation.getter(#kReplenishCardsConstMeta),
+        returnValue: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kReplenishCardsConstMeta),
+        ),
+        returnValueForMissingStub: _FakeFlutterRustBridgeTaskConstMeta_15(
+          this,
+          Invocation.getter(#kReplenishCardsConstMeta),
+        ),
+      ) as _i7.FlutterRustBridgeTaskConstMeta);
+
   @override
   _i9.Future<void> init({dynamic hint}) => (super.noSuchMethod(
         Invocation.method(
@@ -4242,4 +4333,64 @@ class MockWalletCore extends _i1.Mock implements _i7.WalletCore {
         returnValue: _i9.Future<void>.value(),
         returnValueForMissingStub: _i9.Future<void>.value(),
       ) as _i9.Future<void>);
+
+  @override
+  _i9.Future<void> checkPendingIssuances({dynamic hint}) => (super.noSuchMethod(
+        Invocation.method(
+          #checkPendingIssuances,
+          [],
+          {#hint: hint},
+        ),
+        returnValue: _i9.Future<void>.value(),
+        returnValueForMissingStub: _i9.Future<void>.value(),
+      ) as _i9.Future<void>);
+
+  @override
+  _i9.Future<void> refreshExpiringCards({dynamic hint}) => (super.noSuchMethod(
+        Invocation.method(
+          #refreshExpiringCards,
+          [],
+          {#hint: hint},
+        ),
+        returnValue: _i9.Future<void>.value(),
+        returnValueForMissingStub: _i9.Future<void>.value(),
+      ) as _i9.Future<void>);
+
+  @override
+  _i9.Future<_i7.ReplenishCardsResult> replenishCards({
+    required String? pin,
+    dynamic hint,
+  }) =>
+      (super.noSuchMethod(
+        Invocation.method(
+          #replenishCards,
+          [],
+          {
+            #pin: pin,
+            #hint: hint,
+          },
+        ),
+        returnValue: _i9.Future<_i7.ReplenishCardsResult>.value(_FakeReplenishCardsResult_16(
+          this,
+          Invocation.method(
+            #replenishCards,
+            [],
+            {
+              #pin: pin,
+              #hint: hint,
+            },
+          ),
+        )),
+        returnValueForMissingStub: _i9.Future<_i7.ReplenishCardsResult>.value(_FakeReplenishCardsResult_16(
+          this,
+          Invocation.method(
+            #replenishCards,
+            [],
+            {
+              #pin: pin,
+              #hint: hint,
+            },
+          ),
+        )),
+      ) as _i9.Future<_i7.ReplenishCardsResult>);
 }
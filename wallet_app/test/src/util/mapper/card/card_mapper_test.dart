@@ -65,6 +65,16 @@ void main() {
       expect(mapper.map(input).id, 'id-987');
     });
 
+    test('card with `pending` persistence should return transaction id combined with docType as `id`', () {
+      const input = core.Card(
+        persistence: core.CardPersistence_Pending(transactionId: 'transaction-123'),
+        docType: _kSampleDocType,
+        attributes: [],
+        issuer: _kSampleIssuer,
+      );
+      expect(mapper.map(input).id, 'transaction-123_pid_id');
+    });
+
     test('all card attributes should be mapped by the attributeMapper', () {
       mapper.map(_kSampleCard);
 
@@ -9,8 +9,11 @@ use axum::{
 use axum_server::tls_rustls::RustlsConfig;
 use etag::EntityTag;
 use http::{header, HeaderMap, HeaderValue, StatusCode};
+use tokio::net::TcpListener;
 use tracing::{debug, info};
 
+use wallet_common::metrics;
+
 use super::settings::Settings;
 
 pub async fn serve(settings: Settings) -> Result<(), Box<dyn Error>> {
@@ -19,11 +22,24 @@ pub async fn serve(settings: Settings) -> Result<(), Box<dyn Error>> {
     let socket = SocketAddr::new(settings.ip, settings.port);
     debug!("listening on {}", socket);
 
-    let app = Router::new().nest("/", health_router()).nest(
-        "/config/v1",
-        Router::new()
-            .route("/wallet-config", get(configuration))
-            .with_state(settings.wallet_config_jwt.into_bytes()),
+    if let Some(metrics_server) = &settings.metrics_server {
+        let metrics_listener = TcpListener::bind((metrics_server.ip, metrics_server.port)).await?;
+        debug!("listening for metrics on {}:{}", metrics_server.ip, metrics_server.port);
+
+        tokio::spawn(async move {
+            axum::serve(metrics_listener, metrics::metrics_router())
+                .await
+                .expect("metrics server should be started");
+        });
+    }
+
+    let app = metrics::record_metrics(
+        Router::new().nest("/", health_router()).nest(
+            "/config/v1",
+            Router::new()
+                .route("/wallet-config", get(configuration))
+                .with_state(settings.wallet_config_jwt.into_bytes()),
+        ),
     );
 
     axum_server::bind_rustls(socket, config)
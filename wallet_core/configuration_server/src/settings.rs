@@ -15,9 +15,18 @@ pub struct Settings {
     pub config_server_cert: Vec<u8>,
     #[serde_as(as = "Base64")]
     pub config_server_key: Vec<u8>,
+    /// The server on which the metrics are served, which should only be reachable internally. If not configured,
+    /// the metrics are not served at all.
+    pub metrics_server: Option<Server>,
     pub sentry: Option<Sentry>,
 }
 
+#[derive(Clone, Deserialize)]
+pub struct Server {
+    pub ip: IpAddr,
+    pub port: u16,
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         // Look for a config file that is in the same directory as Cargo.toml if run through cargo,
@@ -10,7 +10,7 @@ use crate::{
     async_runtime::init_async_runtime,
     logging::init_logging,
     models::{
-        card::Card,
+        card::{Card, ReplenishCardsResult},
         config::FlutterConfiguration,
         disclosure::{AcceptDisclosureResult, StartDisclosureResult},
         instruction::WalletInstructionResult,
@@ -190,12 +190,9 @@ pub async fn check_pin(pin: String) -> Result<WalletInstructionResult> {
 #[async_runtime]
 #[flutter_api_error]
 pub async fn change_pin(old_pin: String, new_pin: String) -> Result<WalletInstructionResult> {
-    let wallet = wallet().read().await;
-
-    let result = wallet.check_pin(old_pin).await.try_into()?;
+    let mut wallet = wallet().write().await;
 
-    // TODO: Actually change the PIN
-    println!("Newly selected pin {new_pin}");
+    let result = wallet.change_pin(old_pin, new_pin).await.try_into()?;
 
     Ok(result)
 }
@@ -279,6 +276,56 @@ pub async fn has_active_pid_issuance_session() -> Result<bool> {
     Ok(has_active_session)
 }
 
+#[async_runtime]
+#[flutter_api_error]
+pub async fn start_issuance_from_offer(uri: String, tx_code: Option<String>) -> Result<Vec<Card>> {
+    let url = Url::parse(&uri)?;
+
+    let mut wallet = wallet().write().await;
+
+    let documents = wallet.start_issuance_from_offer(url, tx_code).await?;
+
+    let cards = documents.into_iter().map(Card::from).collect();
+
+    Ok(cards)
+}
+
+#[async_runtime]
+#[flutter_api_error]
+pub async fn create_issuance_auth_url(uri: String) -> Result<String> {
+    let url = Url::parse(&uri)?;
+
+    let mut wallet = wallet().write().await;
+
+    let auth_url = wallet.create_issuance_auth_url(url).await?;
+
+    Ok(auth_url.into())
+}
+
+#[async_runtime]
+#[flutter_api_error]
+pub async fn continue_issuance_authorization(uri: String) -> Result<Vec<Card>> {
+    let url = Url::parse(&uri)?;
+
+    let mut wallet = wallet().write().await;
+
+    let documents = wallet.continue_issuance_authorization(url).await?;
+
+    let cards = documents.into_iter().map(Card::from).collect();
+
+    Ok(cards)
+}
+
+#[async_runtime]
+#[flutter_api_error]
+pub async fn accept_issuance(pin: String) -> Result<WalletInstructionResult> {
+    let mut wallet = wallet().write().await;
+
+    let result = wallet.accept_issuance(pin).await.try_into()?;
+
+    Ok(result)
+}
+
 #[async_runtime]
 #[flutter_api_error]
 #[allow(unused_variables)]
@@ -385,6 +432,16 @@ pub async fn get_history_for_card(doc_type: String) -> Result<Vec<WalletEvent>>
     Ok(history)
 }
 
+#[async_runtime]
+#[flutter_api_error]
+pub async fn delete_document(id: String) -> Result<()> {
+    let mut wallet = wallet().write().await;
+
+    wallet.delete_document(&id).await?;
+
+    Ok(())
+}
+
 #[async_runtime]
 #[flutter_api_error]
 pub async fn reset_wallet() -> Result<()> {
@@ -393,6 +450,36 @@ pub async fn reset_wallet() -> Result<()> {
     Ok(())
 }
 
+#[async_runtime]
+#[flutter_api_error]
+pub async fn check_pending_issuances() -> Result<()> {
+    let mut wallet = wallet().write().await;
+
+    wallet.check_pending_issuances().await?;
+
+    Ok(())
+}
+
+#[async_runtime]
+#[flutter_api_error]
+pub async fn refresh_expiring_cards() -> Result<()> {
+    let mut wallet = wallet().write().await;
+
+    wallet.refresh_expiring_mdocs().await?;
+
+    Ok(())
+}
+
+#[async_runtime]
+#[flutter_api_error]
+pub async fn replenish_cards(pin: String) -> Result<ReplenishCardsResult> {
+    let mut wallet = wallet().write().await;
+
+    let result = wallet.replenish_mdoc_copies(pin).await.try_into()?;
+
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
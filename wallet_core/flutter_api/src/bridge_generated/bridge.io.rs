@@ -116,6 +116,30 @@ pub extern "C" fn wire_has_active_pid_issuance_session(port_: i64) {
     wire_has_active_pid_issuance_session_impl(port_)
 }
 
+#[no_mangle]
+pub extern "C" fn wire_start_issuance_from_offer(
+    port_: i64,
+    uri: *mut wire_uint_8_list,
+    tx_code: *mut wire_uint_8_list,
+) {
+    wire_start_issuance_from_offer_impl(port_, uri, tx_code)
+}
+
+#[no_mangle]
+pub extern "C" fn wire_create_issuance_auth_url(port_: i64, uri: *mut wire_uint_8_list) {
+    wire_create_issuance_auth_url_impl(port_, uri)
+}
+
+#[no_mangle]
+pub extern "C" fn wire_continue_issuance_authorization(port_: i64, uri: *mut wire_uint_8_list) {
+    wire_continue_issuance_authorization_impl(port_, uri)
+}
+
+#[no_mangle]
+pub extern "C" fn wire_accept_issuance(port_: i64, pin: *mut wire_uint_8_list) {
+    wire_accept_issuance_impl(port_, pin)
+}
+
 #[no_mangle]
 pub extern "C" fn wire_start_disclosure(port_: i64, uri: *mut wire_uint_8_list, is_qr_code: bool) {
     wire_start_disclosure_impl(port_, uri, is_qr_code)
@@ -161,11 +185,31 @@ pub extern "C" fn wire_get_history_for_card(port_: i64, doc_type: *mut wire_uint
     wire_get_history_for_card_impl(port_, doc_type)
 }
 
+#[no_mangle]
+pub extern "C" fn wire_delete_document(port_: i64, id: *mut wire_uint_8_list) {
+    wire_delete_document_impl(port_, id)
+}
+
 #[no_mangle]
 pub extern "C" fn wire_reset_wallet(port_: i64) {
     wire_reset_wallet_impl(port_)
 }
 
+#[no_mangle]
+pub extern "C" fn wire_check_pending_issuances(port_: i64) {
+    wire_check_pending_issuances_impl(port_)
+}
+
+#[no_mangle]
+pub extern "C" fn wire_refresh_expiring_cards(port_: i64) {
+    wire_refresh_expiring_cards_impl(port_)
+}
+
+#[no_mangle]
+pub extern "C" fn wire_replenish_cards(port_: i64, pin: *mut wire_uint_8_list) {
+    wire_replenish_cards_impl(port_, pin)
+}
+
 // Section: allocate functions
 
 #[no_mangle]
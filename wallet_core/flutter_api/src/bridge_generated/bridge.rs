@@ -26,6 +26,7 @@ use crate::models::card::CardPersistence;
 use crate::models::card::CardValue;
 use crate::models::card::GenderCardValue;
 use crate::models::card::LocalizedString;
+use crate::models::card::ReplenishCardsResult;
 use crate::models::config::FlutterConfiguration;
 use crate::models::disclosure::AcceptDisclosureResult;
 use crate::models::disclosure::DisclosureCard;
@@ -310,6 +311,63 @@ fn wire_has_active_pid_issuance_session_impl(port_: MessagePort) {
         move || move |task_callback| has_active_pid_issuance_session(),
     )
 }
+fn wire_start_issuance_from_offer_impl(
+    port_: MessagePort,
+    uri: impl Wire2Api<String> + UnwindSafe,
+    tx_code: impl Wire2Api<Option<String>> + UnwindSafe,
+) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, Vec<Card>, _>(
+        WrapInfo {
+            debug_name: "start_issuance_from_offer",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_uri = uri.wire2api();
+            let api_tx_code = tx_code.wire2api();
+            move |task_callback| start_issuance_from_offer(api_uri, api_tx_code)
+        },
+    )
+}
+fn wire_create_issuance_auth_url_impl(port_: MessagePort, uri: impl Wire2Api<String> + UnwindSafe) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, String, _>(
+        WrapInfo {
+            debug_name: "create_issuance_auth_url",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_uri = uri.wire2api();
+            move |task_callback| create_issuance_auth_url(api_uri)
+        },
+    )
+}
+fn wire_continue_issuance_authorization_impl(port_: MessagePort, uri: impl Wire2Api<String> + UnwindSafe) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, Vec<Card>, _>(
+        WrapInfo {
+            debug_name: "continue_issuance_authorization",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_uri = uri.wire2api();
+            move |task_callback| continue_issuance_authorization(api_uri)
+        },
+    )
+}
+fn wire_accept_issuance_impl(port_: MessagePort, pin: impl Wire2Api<String> + UnwindSafe) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, WalletInstructionResult, _>(
+        WrapInfo {
+            debug_name: "accept_issuance",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_pin = pin.wire2api();
+            move |task_callback| accept_issuance(api_pin)
+        },
+    )
+}
 fn wire_start_disclosure_impl(
     port_: MessagePort,
     uri: impl Wire2Api<String> + UnwindSafe,
@@ -417,6 +475,19 @@ fn wire_get_history_for_card_impl(port_: MessagePort, doc_type: impl Wire2Api<St
         },
     )
 }
+fn wire_delete_document_impl(port_: MessagePort, id: impl Wire2Api<String> + UnwindSafe) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, (), _>(
+        WrapInfo {
+            debug_name: "delete_document",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_id = id.wire2api();
+            move |task_callback| delete_document(api_id)
+        },
+    )
+}
 fn wire_reset_wallet_impl(port_: MessagePort) {
     FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, (), _>(
         WrapInfo {
@@ -427,6 +498,39 @@ fn wire_reset_wallet_impl(port_: MessagePort) {
         move || move |task_callback| reset_wallet(),
     )
 }
+fn wire_check_pending_issuances_impl(port_: MessagePort) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, (), _>(
+        WrapInfo {
+            debug_name: "check_pending_issuances",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || move |task_callback| check_pending_issuances(),
+    )
+}
+fn wire_refresh_expiring_cards_impl(port_: MessagePort) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, (), _>(
+        WrapInfo {
+            debug_name: "refresh_expiring_cards",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || move |task_callback| refresh_expiring_cards(),
+    )
+}
+fn wire_replenish_cards_impl(port_: MessagePort, pin: impl Wire2Api<String> + UnwindSafe) {
+    FLUTTER_RUST_BRIDGE_HANDLER.wrap::<_, _, _, ReplenishCardsResult, _>(
+        WrapInfo {
+            debug_name: "replenish_cards",
+            port: Some(port_),
+            mode: FfiCallMode::Normal,
+        },
+        move || {
+            let api_pin = pin.wire2api();
+            move |task_callback| replenish_cards(api_pin)
+        },
+    )
+}
 // Section: wrapper structs
 
 // Section: static checks
@@ -519,6 +623,7 @@ impl support::IntoDart for CardPersistence {
         match self {
             Self::InMemory => vec![0.into_dart()],
             Self::Stored { id } => vec![1.into_dart(), id.into_into_dart().into_dart()],
+            Self::Pending { transaction_id } => vec![2.into_dart(), transaction_id.into_into_dart().into_dart()],
         }
         .into_dart()
     }
@@ -654,6 +759,8 @@ impl support::IntoDart for IdentifyUriResult {
         match self {
             Self::PidIssuance => 0,
             Self::Disclosure => 1,
+            Self::CredentialOffer => 2,
+            Self::IssuanceAuthorization => 3,
         }
         .into_dart()
     }
@@ -754,6 +861,22 @@ impl rust2dart::IntoIntoDart<PinValidationResult> for PinValidationResult {
     }
 }
 
+impl support::IntoDart for ReplenishCardsResult {
+    fn into_dart(self) -> support::DartAbi {
+        match self {
+            Self::Ok { reissuance_doc_types } => vec![0.into_dart(), reissuance_doc_types.into_into_dart().into_dart()],
+            Self::InstructionError { error } => vec![1.into_dart(), error.into_into_dart().into_dart()],
+        }
+        .into_dart()
+    }
+}
+impl support::IntoDartExceptPrimitive for ReplenishCardsResult {}
+impl rust2dart::IntoIntoDart<ReplenishCardsResult> for ReplenishCardsResult {
+    fn into_into_dart(self) -> Self {
+        self
+    }
+}
+
 impl support::IntoDart for RequestPolicy {
     fn into_dart(self) -> support::DartAbi {
         vec![
@@ -7,10 +7,10 @@ use url::Url;
 
 use wallet::{
     errors::{
-        openid4vc::{IssuanceSessionError, OidcError, VpClientError, VpMessageClientErrorType},
-        reqwest, AccountProviderError, DigidSessionError, DisclosureError, HistoryError, InstructionError,
-        PidIssuanceError, ResetError, UriIdentificationError, WalletInitError, WalletRegistrationError,
-        WalletUnlockError,
+        openid4vc::{CredentialOfferError, IssuanceSessionError, OidcError, VpClientError, VpMessageClientErrorType},
+        reqwest, AccountProviderError, ChangePinError, DigidSessionError, DisclosureError, DocumentDeletionError,
+        HistoryError, InstructionError, PidIssuanceError, ResetError, UriIdentificationError, WalletInitError,
+        WalletRegistrationError, WalletUnlockError,
     },
     openid4vc::SessionType,
 };
@@ -100,10 +100,12 @@ impl TryFrom<anyhow::Error> for FlutterApiError {
             .map(Self::from)
             .or_else(|e| e.downcast::<WalletRegistrationError>().map(Self::from))
             .or_else(|e| e.downcast::<WalletUnlockError>().map(Self::from))
+            .or_else(|e| e.downcast::<ChangePinError>().map(Self::from))
             .or_else(|e| e.downcast::<UriIdentificationError>().map(Self::from))
             .or_else(|e| e.downcast::<PidIssuanceError>().map(Self::from))
             .or_else(|e| e.downcast::<DisclosureError>().map(Self::from))
             .or_else(|e| e.downcast::<HistoryError>().map(Self::from))
+            .or_else(|e| e.downcast::<DocumentDeletionError>().map(Self::from))
             .or_else(|e| e.downcast::<ResetError>().map(Self::from))
             .or_else(|e| e.downcast::<url::ParseError>().map(Self::from))
     }
@@ -148,6 +150,17 @@ impl FlutterApiErrorFields for WalletUnlockError {
             | WalletUnlockError::BiometricsUnlockingNotEnabled => FlutterApiErrorType::WalletState,
             WalletUnlockError::Instruction(e) => FlutterApiErrorType::from(e),
             WalletUnlockError::UnlockMethodStorage(_) => FlutterApiErrorType::Generic,
+            WalletUnlockError::ChangePin(e) => e.typ(),
+        }
+    }
+}
+
+impl FlutterApiErrorFields for ChangePinError {
+    fn typ(&self) -> FlutterApiErrorType {
+        match self {
+            ChangePinError::NotRegistered | ChangePinError::Locked => FlutterApiErrorType::WalletState,
+            ChangePinError::Instruction(e) => FlutterApiErrorType::from(e),
+            _ => FlutterApiErrorType::Generic,
         }
     }
 }
@@ -202,6 +215,10 @@ impl FlutterApiErrorFields for PidIssuanceError {
                     .into_iter()
                     .collect::<serde_json::Value>()
             }
+            // Describe the Transaction Code to the app, so that it can prompt the user to enter it.
+            Self::CredentialOffer(CredentialOfferError::MissingTxCode(tx_code)) => {
+                serde_json::json!({ "tx_code": tx_code })
+            }
             _ => serde_json::Value::Null,
         }
     }
@@ -311,6 +328,15 @@ impl FlutterApiErrorFields for HistoryError {
     }
 }
 
+impl FlutterApiErrorFields for DocumentDeletionError {
+    fn typ(&self) -> FlutterApiErrorType {
+        match self {
+            DocumentDeletionError::NotRegistered | DocumentDeletionError::Locked => FlutterApiErrorType::WalletState,
+            _ => FlutterApiErrorType::Generic,
+        }
+    }
+}
+
 impl FlutterApiErrorFields for ResetError {
     fn typ(&self) -> FlutterApiErrorType {
         match self {
@@ -1,8 +1,9 @@
 use wallet::{
-    self, Attribute, AttributeValue, Document, DocumentAttributes, DocumentPersistence, GenderAttributeValue,
+    self, errors::PidIssuanceError, Attribute, AttributeValue, Document, DocumentAttributes, DocumentPersistence,
+    GenderAttributeValue,
 };
 
-use super::disclosure::Organization;
+use super::{disclosure::Organization, instruction::WalletInstructionError};
 
 pub struct Card {
     pub issuer: Organization,
@@ -14,6 +15,7 @@ pub struct Card {
 pub enum CardPersistence {
     InMemory,
     Stored { id: String },
+    Pending { transaction_id: String },
 }
 
 pub struct CardAttribute {
@@ -36,11 +38,23 @@ pub enum GenderCardValue {
     NotApplicable,
 }
 
+pub enum ReplenishCardsResult {
+    /// The copies of the cards were replenished where possible. The cards of the doc types that are listed could not
+    /// be replenished, as this requires the user to authenticate to their issuer again by adding the cards once more.
+    Ok {
+        reissuance_doc_types: Vec<String>,
+    },
+    InstructionError {
+        error: WalletInstructionError,
+    },
+}
+
 impl From<DocumentPersistence> for CardPersistence {
     fn from(value: DocumentPersistence) -> Self {
         match value {
             DocumentPersistence::InMemory => CardPersistence::InMemory,
             DocumentPersistence::Stored(id) => CardPersistence::Stored { id },
+            DocumentPersistence::Pending(transaction_id) => CardPersistence::Pending { transaction_id },
         }
     }
 }
@@ -107,3 +121,24 @@ pub(crate) fn into_card_attributes(attributes: DocumentAttributes) -> Vec<CardAt
         .map(|(key, attribute)| CardAttribute::from((key.to_string(), attribute)))
         .collect()
 }
+
+/// This conversion distinguishes between 3 distinct cases:
+///
+/// 1. In case of a successful result, [`ReplenishCardsResult::Ok`] will be returned.
+/// 2. In case of an expected and/or specific error case a different variant of [`ReplenishCardsResult`] by mapping the
+///    nested [`InstructionError`](wallet::errors::InstructionError).
+/// 3. In any other cases, this is an unexpected and/or generic error and the [`PidIssuanceError`] will be returned
+///    unchanged.
+impl TryFrom<Result<Vec<String>, PidIssuanceError>> for ReplenishCardsResult {
+    type Error = PidIssuanceError;
+
+    fn try_from(value: Result<Vec<String>, PidIssuanceError>) -> Result<Self, Self::Error> {
+        match value {
+            Ok(reissuance_doc_types) => Ok(ReplenishCardsResult::Ok { reissuance_doc_types }),
+            Err(PidIssuanceError::Instruction(instruction_error)) => Ok(ReplenishCardsResult::InstructionError {
+                error: instruction_error.try_into().map_err(PidIssuanceError::Instruction)?,
+            }),
+            Err(error) => Err(error),
+        }
+    }
+}
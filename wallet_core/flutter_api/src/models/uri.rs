@@ -3,6 +3,8 @@ use wallet::{errors::UriIdentificationError, UriType};
 pub enum IdentifyUriResult {
     PidIssuance,
     Disclosure,
+    CredentialOffer,
+    IssuanceAuthorization,
 }
 
 impl TryFrom<Result<UriType, UriIdentificationError>> for IdentifyUriResult {
@@ -13,6 +15,8 @@ impl TryFrom<Result<UriType, UriIdentificationError>> for IdentifyUriResult {
             Ok(uri_type) => match uri_type {
                 UriType::PidIssuance(_) => Ok(Self::PidIssuance),
                 UriType::Disclosure(_) => Ok(Self::Disclosure),
+                UriType::CredentialOffer(_) => Ok(Self::CredentialOffer),
+                UriType::IssuanceAuthorization(_) => Ok(Self::IssuanceAuthorization),
             },
             Err(e) => Err(e),
         }
@@ -61,6 +61,8 @@ impl From<HistoryEvent> for WalletEvents {
                     r#type: r#type.into(),
                 }]
             }
+            // The app does not have a representation of deleted cards in its history yet.
+            HistoryEvent::Deletion { .. } => vec![],
         };
         WalletEvents(result)
     }
@@ -10,6 +10,7 @@ use crate::{
     identifiers::{AttributeIdentifier, AttributeIdentifierHolder},
     mdocs::DocType,
     utils::serialization::{self, CborSeq, TaggedBytes},
+    verifier::ItemsRequests,
     ItemsRequest,
 };
 
@@ -40,6 +41,38 @@ pub enum DisclosureRequestMatch<I> {
 }
 
 impl<I> DisclosureRequestMatch<I> {
+    /// Match [`ItemsRequests`], which may contain alternatives for some of its requirements, against all locally
+    /// stored documents. For every requirement, the first alternative that can be satisfied is selected, after which
+    /// the selected `ItemsRequest`s are matched using [`DisclosureRequestMatch::new()`]. If none of the alternatives
+    /// of a requirement can be satisfied, the first one is selected so that its missing attributes are reported.
+    pub async fn new_with_alternatives(
+        items_requests: &ItemsRequests,
+        mdoc_data_source: &impl MdocDataSource<MdocIdentifier = I>,
+        session_transcript: &SessionTranscript,
+    ) -> Result<DisclosureRequestMatch<I>> {
+        let mut selected_items_requests = Vec::with_capacity(items_requests.0.len());
+
+        for requirement in &items_requests.0 {
+            let alternatives = requirement.alternatives();
+            let mut selected = &alternatives[0];
+
+            if alternatives.len() > 1 {
+                for alternative in alternatives {
+                    let alternative_match = Self::new([alternative], mdoc_data_source, session_transcript).await?;
+
+                    if matches!(alternative_match, DisclosureRequestMatch::Candidates(_)) {
+                        selected = alternative;
+                        break;
+                    }
+                }
+            }
+
+            selected_items_requests.push(selected);
+        }
+
+        Self::new(selected_items_requests, mdoc_data_source, session_transcript).await
+    }
+
     pub async fn new<'a>(
         items_requests: impl IntoIterator<Item = &'a ItemsRequest> + Clone,
         mdoc_data_source: &impl MdocDataSource<MdocIdentifier = I>,
@@ -205,6 +238,7 @@ mod tests {
             data::{addr_street, empty, pid_family_name, pid_full_name, pid_given_name},
             TestDocument, TestDocuments,
         },
+        verifier::ItemsRequirement,
     };
 
     use super::*;
@@ -260,6 +294,49 @@ mod tests {
         assert_eq!(match_result, expected_match);
     }
 
+    #[rstest]
+    #[case(pid_full_name(), candidates(pid_full_name()))]
+    #[case(addr_street(), candidates(addr_street()))]
+    #[case(pid_full_name() + addr_street(), candidates(pid_full_name()))]
+    #[case(empty(), missing_attributes(pid_full_name()))]
+    #[case(pid_given_name(), missing_attributes(pid_family_name()))]
+    #[tokio::test]
+    async fn test_match_stored_documents_with_alternatives(
+        #[case] stored_documents: TestDocuments,
+        #[case] expected_match: ExpectedDisclosureRequestMatch,
+    ) {
+        let ca = KeyPair::generate_issuer_mock_ca().unwrap();
+        let key_factory = SoftwareKeyFactory::default();
+
+        let mdoc_data_source = MockMdocDataSource::new(
+            future::join_all(
+                stored_documents
+                    .into_iter()
+                    .map(|document| document.sign(&ca, &key_factory, NonZeroU8::new(1).unwrap())),
+            )
+            .await,
+        );
+
+        // Request either the full name from the PID or, alternatively, the street from the address.
+        let items_requests = ItemsRequests::from(vec![ItemsRequirement::OneOf {
+            one_of: (pid_full_name() + addr_street())
+                .into_iter()
+                .map(ItemsRequest::from)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+        }]);
+
+        let session_transcript = SessionTranscript::new_mock();
+        let match_result =
+            DisclosureRequestMatch::new_with_alternatives(&items_requests, &mdoc_data_source, &session_transcript)
+                .await
+                .expect("Could not match items requests with stored documents");
+
+        let match_result: ExpectedDisclosureRequestMatch = match_result.into();
+        assert_eq!(match_result, expected_match);
+    }
+
     #[derive(Debug, PartialEq)]
     enum ExpectedDisclosureRequestMatch {
         Candidates(TestDocuments),
@@ -1,6 +1,9 @@
 use p256::ecdsa::VerifyingKey;
 
-use crate::{errors::Result, iso::disclosure::IssuerSigned};
+use crate::{
+    errors::Result,
+    iso::{disclosure::IssuerSigned, mdocs::ValidityInfo},
+};
 
 impl IssuerSigned {
     pub fn public_key(&self) -> Result<VerifyingKey> {
@@ -12,6 +15,12 @@ impl IssuerSigned {
             .try_into()?;
         Ok(public_key)
     }
+
+    /// Returns the validity of the mdoc, as contained in its MSO. NB: this does not verify the MSO.
+    pub fn validity_info(&self) -> Result<ValidityInfo> {
+        let validity_info = self.issuer_auth.dangerous_parse_unverified()?.0.validity_info;
+        Ok(validity_info)
+    }
 }
 
 #[cfg(test)]
@@ -32,4 +41,18 @@ mod tests {
         // The example mdoc should contain the example static device key.
         assert_eq!(public_key, expected_public_key);
     }
+
+    #[tokio::test]
+    async fn test_issuer_signed_validity_info() {
+        let mdoc = Mdoc::new_example_mock();
+
+        let validity_info = mdoc
+            .issuer_signed
+            .validity_info()
+            .expect("Could not get validity info from IssuerSigned");
+
+        // The example mdoc is valid for one year, see ISO 18013-5 D.5.2.
+        assert_eq!(validity_info.valid_from.0 .0, "2020-10-01T13:30:02Z");
+        assert_eq!(validity_info.valid_until.0 .0, "2021-10-01T13:30:02Z");
+    }
 }
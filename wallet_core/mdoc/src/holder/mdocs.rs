@@ -94,6 +94,16 @@ impl Mdoc {
         self.issuer_signed.issuer_auth.signing_cert()
     }
 
+    /// Identifier of the mdoc's private key.
+    pub fn private_key_id(&self) -> &str {
+        &self.private_key_id
+    }
+
+    /// Get the validity of the mdoc from its MSO.
+    pub fn validity_info(&self) -> crate::Result<ValidityInfo> {
+        self.issuer_signed.validity_info()
+    }
+
     /// Check that the namespaces, attribute names and attribute values of this instance are equal to to the
     /// provided unsigned value.
     pub fn compare_unsigned(&self, unsigned: &UnsignedMdoc) -> Result<(), IssuedAttributesMismatch> {
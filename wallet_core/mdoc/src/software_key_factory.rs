@@ -1,11 +1,14 @@
 use std::{collections::HashMap, iter};
 
+use chrono::Utc;
 use futures::future;
 use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
 use parking_lot::Mutex;
 
 use rand_core::OsRng;
 use wallet_common::{
+    account::messages::auth::{KeyAttestation, KeyAttestationClaims},
+    jwt::Jwt,
     keys::{software::SoftwareEcdsaKey, EcdsaKey},
     utils,
 };
@@ -14,12 +17,14 @@ use crate::utils::keys::KeyFactory;
 
 /// The [`SoftwareKeyFactory`] type implements [`KeyFactory`] and has the option
 /// of returning [`SoftwareKeyFactoryError::Generating`] when generating multiple
-/// keys and [`SoftwareKeyFactoryError::Signing`] when signing multiple.
+/// keys and [`SoftwareKeyFactoryError::Signing`] when signing multiple. If `key_attester` contains a private key and a
+/// wallet ID, it attests the keys it generates for that wallet like the Wallet Provider does.
 #[derive(Debug)]
 pub struct SoftwareKeyFactory {
     signing_keys: Mutex<HashMap<String, SigningKey>>,
     pub has_generating_error: bool,
     pub has_multi_key_signing_error: bool,
+    pub key_attester: Option<(SigningKey, String)>,
 }
 
 impl SoftwareKeyFactory {
@@ -28,6 +33,7 @@ impl SoftwareKeyFactory {
             signing_keys: signing_keys.into(),
             has_generating_error: false,
             has_multi_key_signing_error: false,
+            key_attester: None,
         }
     }
 }
@@ -86,6 +92,36 @@ impl KeyFactory for SoftwareKeyFactory {
         Ok(keys)
     }
 
+    async fn generate_new_multiple_attested(
+        &self,
+        count: u64,
+    ) -> Result<(Vec<Self::Key>, Option<KeyAttestation>), Self::Error> {
+        let keys = self.generate_new_multiple(count).await?;
+
+        let Some((attester_key, wallet_id)) = &self.key_attester else {
+            return Ok((keys, None));
+        };
+
+        let attested_keys = future::try_join_all(keys.iter().map(|key| async {
+            let public_key = key.verifying_key().await.map_err(SoftwareKeyFactoryError::Ecdsa)?;
+            Ok(public_key.into())
+        }))
+        .await?;
+        let iat = Utc::now().timestamp() as u64;
+        let claims = KeyAttestationClaims {
+            wallet_id: wallet_id.clone(),
+            attested_keys,
+            iss: "wallet_provider".to_string(),
+            iat,
+            exp: iat + 60,
+        };
+        let key_attestation = Jwt::sign_with_sub(&claims, attester_key)
+            .await
+            .map_err(|_| SoftwareKeyFactoryError::Signing)?;
+
+        Ok((keys, Some(key_attestation)))
+    }
+
     fn generate_existing<I: Into<String>>(&self, identifier: I, public_key: VerifyingKey) -> Self::Key {
         let identifier = identifier.into();
         let signing_key = self
@@ -307,8 +307,8 @@ impl std::ops::Add for TestDocuments {
 }
 impl From<TestDocuments> for DeviceRequest {
     fn from(value: TestDocuments) -> Self {
-        let items_requests = ItemsRequests::from(value);
-        Self::from_items_requests(items_requests.0)
+        let items_requests = value.into_iter().map(ItemsRequest::from).collect();
+        Self::from_items_requests(items_requests)
     }
 }
 impl AttributeIdentifierHolder for TestDocuments {
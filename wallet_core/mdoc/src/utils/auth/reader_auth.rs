@@ -156,8 +156,7 @@ pub mod mock {
 
         pub fn new_mock_from_requests(authorized_requests: &ItemsRequests) -> Self {
             let attributes = authorized_requests
-                .0
-                .iter()
+                .items_requests()
                 .map(|items_request| {
                     let namespaces: IndexMap<_, _> = items_request
                         .name_spaces
@@ -3,7 +3,10 @@ use std::error::Error;
 use p256::ecdsa::{Signature, VerifyingKey};
 use serde::{Deserialize, Serialize};
 
-use wallet_common::keys::{SecureEcdsaKey, WithIdentifier};
+use wallet_common::{
+    account::messages::auth::KeyAttestation,
+    keys::{SecureEcdsaKey, WithIdentifier},
+};
 
 /// Contract for ECDSA private keys suitable for mdoc attestations.
 /// Should be sufficiently secured e.g. through a HSM, or Android's TEE/StrongBox or Apple's SE.
@@ -30,6 +33,16 @@ pub trait KeyFactory {
     }
 
     async fn generate_new_multiple(&self, count: u64) -> Result<Vec<Self::Key>, Self::Error>;
+
+    /// Generate new keys like [`KeyFactory::generate_new_multiple()`], along with an attestation of these keys by the
+    /// party that holds them, if it provides one.
+    async fn generate_new_multiple_attested(
+        &self,
+        count: u64,
+    ) -> Result<(Vec<Self::Key>, Option<KeyAttestation>), Self::Error> {
+        self.generate_new_multiple(count).await.map(|keys| (keys, None))
+    }
+
     fn generate_existing<I: Into<String>>(&self, identifier: I, public_key: VerifyingKey) -> Self::Key;
 
     async fn sign_with_new_keys(
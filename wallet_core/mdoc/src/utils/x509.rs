@@ -70,7 +70,7 @@ pub const OID_EXT_KEY_USAGE: &[u64] = &[2, 5, 29, 37];
 /// - parsing data: `x509_parser`
 /// - verification of certificate chains: `webpki`
 /// - signing and generating: `rcgen`
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Certificate(ByteBuf);
 
 // Use base64 when we (de)serialize to JSON
@@ -9,7 +9,7 @@ use serde_with::{serde_as, FromInto, IfIsHumanReadable};
 use tracing::{debug, warn};
 use webpki::TrustAnchor;
 
-use wallet_common::generator::Generator;
+use wallet_common::{generator::Generator, nonempty::NonEmpty};
 
 use crate::{
     identifiers::{AttributeIdentifier, AttributeIdentifierHolder},
@@ -66,31 +66,100 @@ pub enum VerificationError {
     UnexpectedIssuerCommonNameCount(usize),
 }
 
+/// A single requirement of a disclosure request, which is satisfied either by disclosing the requested document, or
+/// by disclosing any one of a list of alternatively requested documents. The alternatives are listed in order of
+/// preference of the verifier.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ItemsRequirement {
+    Single(ItemsRequest),
+    OneOf { one_of: NonEmpty<Vec<ItemsRequest>> },
+}
+
+impl ItemsRequirement {
+    /// Returns the [`ItemsRequest`]s, any one of which satisfies this requirement.
+    pub fn alternatives(&self) -> &[ItemsRequest] {
+        match self {
+            Self::Single(items_request) => std::slice::from_ref(items_request),
+            Self::OneOf { one_of } => one_of.as_ref(),
+        }
+    }
+
+    /// Returns requested attributes, if any, that are not present in the `device_response`. If none of the
+    /// alternatives is satisfied, the missing attributes are those of the first alternative for which a document was
+    /// disclosed, or of the first alternative if none of the requested documents was disclosed at all.
+    fn match_against_response(&self, device_response: &DeviceResponse) -> Vec<AttributeIdentifier> {
+        let documents = device_response.documents.as_deref().unwrap_or_default();
+
+        let mut missing_per_alternative = self
+            .alternatives()
+            .iter()
+            .map(|items_request| {
+                documents
+                    .iter()
+                    .find(|doc| doc.doc_type == items_request.doc_type)
+                    .map_or_else(
+                        // If the entire document is missing then all requested attributes are missing
+                        || (false, items_request.attribute_identifiers().into_iter().collect()),
+                        |doc| (true, items_request.match_against_issuer_signed(doc)),
+                    )
+            })
+            .collect::<Vec<(bool, Vec<_>)>>();
+
+        if missing_per_alternative.iter().any(|(_, missing)| missing.is_empty()) {
+            return vec![];
+        }
+
+        let index = missing_per_alternative
+            .iter()
+            .position(|(is_disclosed, _)| *is_disclosed)
+            .unwrap_or_default();
+
+        missing_per_alternative.swap_remove(index).1
+    }
+}
+
+impl From<ItemsRequest> for ItemsRequirement {
+    fn from(value: ItemsRequest) -> Self {
+        Self::Single(value)
+    }
+}
+
+/// The requirements of a disclosure request, all of which have to be satisfied by the disclosed documents.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, AsRef)]
-pub struct ItemsRequests(pub Vec<ItemsRequest>);
+pub struct ItemsRequests(pub Vec<ItemsRequirement>);
 impl From<Vec<ItemsRequest>> for ItemsRequests {
     fn from(value: Vec<ItemsRequest>) -> Self {
+        Self(value.into_iter().map(ItemsRequirement::from).collect())
+    }
+}
+
+impl From<Vec<ItemsRequirement>> for ItemsRequests {
+    fn from(value: Vec<ItemsRequirement>) -> Self {
         Self(value)
     }
 }
 
 impl ItemsRequests {
-    /// Checks that all `requested` attributes are disclosed in this [`DeviceResponse`].
+    /// Returns all requested documents, including every alternative of each requirement.
+    pub fn items_requests(&self) -> impl Iterator<Item = &ItemsRequest> + Clone {
+        self.0.iter().flat_map(ItemsRequirement::alternatives)
+    }
+
+    /// Returns `true` if any of the requirements can be satisfied by more than one document.
+    pub fn has_alternatives(&self) -> bool {
+        self.0
+            .iter()
+            .any(|requirement| matches!(requirement, ItemsRequirement::OneOf { .. }))
+    }
+
+    /// Checks that all `requested` attributes are disclosed in this [`DeviceResponse`], where it suffices for every
+    /// requirement that the attributes of one of its alternatives are disclosed.
     pub fn match_against_response(&self, device_response: &DeviceResponse) -> Result<()> {
         let not_found: Vec<_> = self
             .0
             .iter()
-            .flat_map(|items_request| {
-                device_response
-                    .documents
-                    .as_ref()
-                    .and_then(|docs| docs.iter().find(|doc| doc.doc_type == items_request.doc_type))
-                    .map_or_else(
-                        // If the entire document is missing then all requested attributes are missing
-                        || items_request.attribute_identifiers().into_iter().collect(),
-                        |doc| items_request.match_against_issuer_signed(doc),
-                    )
-            })
+            .flat_map(|requirement| requirement.match_against_response(device_response))
             .collect();
 
         if not_found.is_empty() {
@@ -439,6 +508,8 @@ mod tests {
     #[case(change_namespace())]
     #[case(remove_attribute())]
     #[case(multiple_doc_types_swapped())]
+    #[case(one_of_second_alternative())]
+    #[case(one_of_no_alternative())]
     fn match_disclosed_attributes(
         #[case] testcase: (DeviceResponse, ItemsRequests, Result<(), Vec<AttributeIdentifier>>),
     ) {
@@ -458,8 +529,7 @@ mod tests {
     /// Helper to compute all attribute identifiers contained in a bunch of [`ItemsRequest`]s.
     fn attribute_identifiers(items_requests: &ItemsRequests) -> Vec<AttributeIdentifier> {
         items_requests
-            .0
-            .iter()
+            .items_requests()
             .flat_map(AttributeIdentifierHolder::attribute_identifiers)
             .collect()
     }
@@ -557,13 +627,51 @@ mod tests {
         device_response.documents.as_mut().unwrap().push(cloned_doc);
 
         let mut items_requests = Examples::items_requests();
-        let mut cloned_items_request = items_requests.0[0].clone();
+        let mut cloned_items_request = items_requests.items_requests().next().unwrap().clone();
         cloned_items_request.doc_type = "a".to_string();
-        items_requests.0.push(cloned_items_request);
+        items_requests.0.push(cloned_items_request.into());
 
         // swap the document order in items_requests
         items_requests.0.reverse();
 
         (device_response, items_requests, Ok(()))
     }
+
+    // Request one of two doc types, of which only the second one is disclosed
+    fn one_of_second_alternative() -> (DeviceResponse, ItemsRequests, Result<(), Vec<AttributeIdentifier>>) {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut other_items_request = items_request.clone();
+        other_items_request.doc_type = "a".to_string();
+
+        let items_requests = vec![ItemsRequirement::OneOf {
+            one_of: vec![other_items_request, items_request].try_into().unwrap(),
+        }]
+        .into();
+
+        (DeviceResponse::example(), items_requests, Ok(()))
+    }
+
+    // Request one of two doc types, neither of which is disclosed completely, which reports the missing attributes
+    // of the alternative for which a document was disclosed
+    fn one_of_no_alternative() -> (DeviceResponse, ItemsRequests, Result<(), Vec<AttributeIdentifier>>) {
+        let mut device_response = DeviceResponse::example();
+        let first_document = device_response.documents.as_mut().unwrap().first_mut().unwrap();
+        let name_spaces = first_document.issuer_signed.name_spaces.as_mut().unwrap();
+
+        name_spaces.modify_first_attributes(|attributes| {
+            attributes.pop();
+        });
+
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut other_items_request = items_request.clone();
+        other_items_request.doc_type = "a".to_string();
+
+        let missing = vec![items_request.attribute_identifiers().last().unwrap().clone()];
+        let items_requests = vec![ItemsRequirement::OneOf {
+            one_of: vec![other_items_request, items_request].try_into().unwrap(),
+        }]
+        .into();
+
+        (device_response, items_requests, Err(missing))
+    }
 }
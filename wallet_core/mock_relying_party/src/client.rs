@@ -59,6 +59,9 @@ impl WalletServerClient {
                 usecase,
                 items_requests,
                 return_url_template,
+                callback_url: None,
+                expiration_seconds: None,
+                correlation_id: None,
             })
             .send()
             .map_err(anyhow::Error::from)
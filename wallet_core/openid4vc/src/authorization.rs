@@ -1,6 +1,6 @@
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
-use serde_with::{formats::SpaceSeparator, serde_as, skip_serializing_none, StringWithSeparator};
+use serde_with::{formats::SpaceSeparator, json::JsonString, serde_as, skip_serializing_none, StringWithSeparator};
 use url::Url;
 
 /// See
@@ -18,8 +18,15 @@ pub struct AuthorizationRequest {
     pub client_id: String,
     pub redirect_uri: Option<Url>,
     pub state: Option<String>,
+
+    /// https://www.rfc-editor.org/rfc/rfc9396.html. Sent as a JSON-encoded string when the request is URL-encoded.
+    #[serde_as(as = "Option<JsonString>")]
     pub authorization_details: Option<Vec<AuthorizationDetails>>,
 
+    /// Passed by the wallet to the authorization server if it was contained in the authorization code grant of the
+    /// Credential Offer, so that the issuer can relate the authorization request to the offer.
+    pub issuer_state: Option<String>,
+
     /// https://datatracker.ietf.org/doc/html/rfc9126. MUST NOT be sent in a PAR.
     /// This is a `String` and not a `Url`, because despite its name it need not be an actual URL;
     /// its contents is completely up to the server and to be considered opaque.
@@ -101,6 +108,16 @@ pub struct AuthorizationDetails {
     format_data: AuthorizationDetailsFormatData,
 }
 
+impl AuthorizationDetails {
+    pub fn new(format_data: AuthorizationDetailsFormatData) -> Self {
+        Self {
+            typ: AuthorizationDetailsType::default(),
+            credential_identifiers: None,
+            format_data,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum AuthorizationDetailsType {
@@ -120,6 +137,16 @@ pub struct AuthorizationResponse {
     pub state: Option<String>,
 }
 
+/// See <https://www.rfc-editor.org/rfc/rfc9126.html#section-2.2>.
+/// Returned by the authorization server in response to a Pushed Authorization Request. The wallet sends the user to the
+/// authorization endpoint with only the `request_uri` and its `client_id`, instead of the full
+/// [`AuthorizationRequest`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PushedAuthorizationResponse {
+    pub request_uri: String,
+    pub expires_in: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
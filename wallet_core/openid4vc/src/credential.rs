@@ -1,7 +1,17 @@
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use futures::future::try_join_all;
-use serde::{Deserialize, Serialize};
+use josekit::{
+    jwe::{
+        alg::ecdh_es::{EcdhEsJweAlgorithm, EcdhEsJweEncrypter},
+        JweHeader,
+    },
+    jwk::alg::ec::{EcCurve, EcKeyPair},
+    jwt::JwtPayload,
+    JoseError,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use error_category::ErrorCategory;
 use nl_wallet_mdoc::{
     utils::{
         keys::{KeyFactory, MdocEcdsaKey},
@@ -9,29 +19,179 @@ use nl_wallet_mdoc::{
     },
     IssuerSigned,
 };
-use wallet_common::{jwt::Jwt, nonempty::NonEmpty, urls::BaseUrl};
+use wallet_common::{account::messages::auth::KeyAttestation, jwt::Jwt, nonempty::NonEmpty, urls::BaseUrl};
 
 use crate::{
     issuance_session::IssuanceSessionError,
     jwt::{self, jwk_jwt_header},
+    openid4vp::{JwePublicKey, VpAlgValues, VpEncValues},
     Format,
 };
 
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(critical)]
+pub enum ResponseEncryptionError {
+    #[error("error (de)serializing JWE payload: {0}")]
+    #[category(pd)]
+    Json(#[from] serde_json::Error),
+    #[error("JWE payload did not serialize to a JSON object")]
+    PayloadNotAnObject,
+    #[error("error constructing JWT payload: {0}")]
+    #[category(pd)]
+    JwtPayload(#[source] JoseError),
+    #[error("error converting JWK: {0}")]
+    JwkConversion(#[source] JoseError),
+    #[error("error encrypting/decrypting JWE: {0}")]
+    #[category(pd)]
+    Jwe(#[source] JoseError),
+    #[error("unexpected JWE content encryption algorithm: expected {expected}, found {found:?}")]
+    ContentEncryptionMismatch {
+        expected: VpEncValues,
+        found: Option<String>,
+    },
+    #[error("credential response was not encrypted, while encryption was requested")]
+    UnencryptedResponse,
+    #[error("credential response was encrypted, while no encryption was requested")]
+    UnexpectedEncryptedResponse,
+}
+
 /// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#section-8.1.
 /// Sent JSON-encoded to `POST /batch_credential`.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CredentialRequests {
     pub credential_requests: NonEmpty<Vec<CredentialRequest>>,
+    /// If present, the issuer encrypts the [`CredentialResponses`] to the contained key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_response_encryption: Option<ResponseEncryption>,
 }
 
 /// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#section-7.2.
 /// Sent JSON-encoded to `POST /credential`.
-// TODO: add `wallet_attestation`, `wallet_attestation_pop`, and `proof_of_secure_combination` (PVW-2361, PVW-2362)
+// TODO: add `proof_of_secure_combination` (PVW-2362). The wallet attestation and its PoP are sent in HTTP headers,
+// see [`crate::wallet_attestation`].
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CredentialRequest {
     pub format: Format,
     pub doctype: Option<String>,
     pub proof: Option<CredentialRequestProof>,
+    /// Attests that the key of the `proof` is held by the Wallet Provider on behalf of the wallet, see
+    /// [`crate::wallet_attestation`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_attestation: Option<KeyAttestation>,
+    /// If present, the issuer encrypts the [`CredentialResponse`] to the contained key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_response_encryption: Option<ResponseEncryption>,
+}
+
+/// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#section-7.2.
+/// The (ephemeral) public key and the algorithms with which the issuer must encrypt its response into a JWE.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResponseEncryption {
+    pub jwk: JwePublicKey,
+    pub alg: VpAlgValues,
+    pub enc: VpEncValues,
+}
+
+impl ResponseEncryption {
+    /// Generate an ephemeral key pair, returning its private key along with the parameters that instruct the issuer
+    /// to encrypt its response to it using ECDH-ES and the specified content encryption algorithm.
+    pub fn new_ephemeral(enc: VpEncValues) -> Result<(EcKeyPair, Self), ResponseEncryptionError> {
+        let private_key = EcKeyPair::generate(EcCurve::P256).map_err(ResponseEncryptionError::JwkConversion)?;
+
+        // The public key of a freshly generated P-256 key pair is always a valid `JwePublicKey`.
+        let encryption = Self {
+            jwk: private_key.to_jwk_public_key().try_into().unwrap(),
+            alg: VpAlgValues::EcdhEs,
+            enc,
+        };
+
+        Ok((private_key, encryption))
+    }
+
+    /// Construct the encrypter, which also checks that the JWK can be used for encryption.
+    pub(crate) fn encrypter(&self) -> Result<EcdhEsJweEncrypter, ResponseEncryptionError> {
+        let encrypter = match self.alg {
+            VpAlgValues::EcdhEs => EcdhEsJweAlgorithm::EcdhEs,
+        }
+        .encrypter_from_jwk(self.jwk.as_ref())
+        .map_err(ResponseEncryptionError::JwkConversion)?;
+
+        Ok(encrypter)
+    }
+
+    fn encrypt<T: Serialize>(&self, payload: &T) -> Result<String, ResponseEncryptionError> {
+        let mut header = JweHeader::new();
+        header.set_token_type("JWT");
+        header.set_content_encryption(self.enc.to_string());
+
+        let serde_json::Value::Object(payload) = serde_json::to_value(payload)? else {
+            return Err(ResponseEncryptionError::PayloadNotAnObject);
+        };
+        let payload = JwtPayload::from_map(payload).map_err(ResponseEncryptionError::JwtPayload)?;
+
+        let jwe = josekit::jwt::encode_with_encrypter(&payload, &header, &self.encrypter()?)
+            .map_err(ResponseEncryptionError::Jwe)?;
+
+        Ok(jwe)
+    }
+
+    fn decrypt<T: DeserializeOwned>(&self, jwe: &str, private_key: &EcKeyPair) -> Result<T, ResponseEncryptionError> {
+        let decrypter = match self.alg {
+            VpAlgValues::EcdhEs => EcdhEsJweAlgorithm::EcdhEs,
+        }
+        .decrypter_from_jwk(&private_key.to_jwk_key_pair())
+        .map_err(ResponseEncryptionError::JwkConversion)?;
+        let (payload, header) =
+            josekit::jwt::decode_with_decrypter(jwe, &decrypter).map_err(ResponseEncryptionError::Jwe)?;
+
+        // Check that the issuer used the content encryption algorithm that we asked for.
+        let enc = header.content_encryption();
+        if enc != Some(self.enc.to_string().as_str()) {
+            return Err(ResponseEncryptionError::ContentEncryptionMismatch {
+                expected: self.enc.clone(),
+                found: enc.map(str::to_string),
+            });
+        }
+
+        let payload = serde_json::from_value(serde_json::Value::Object(payload.into()))?;
+        Ok(payload)
+    }
+}
+
+/// A response from one of the credential endpoints, which is sent as a JWE with content type `application/jwt` if
+/// the wallet included [`ResponseEncryption`] in its request, and as JSON otherwise.
+#[derive(Clone, Debug)]
+pub enum MaybeEncrypted<T> {
+    Plain(T),
+    Encrypted(String),
+}
+
+impl<T: Serialize> MaybeEncrypted<T> {
+    /// Encrypt the response if the wallet requested so.
+    pub fn new(response: T, encryption: Option<&ResponseEncryption>) -> Result<Self, ResponseEncryptionError> {
+        let response = match encryption {
+            Some(encryption) => Self::Encrypted(encryption.encrypt(&response)?),
+            None => Self::Plain(response),
+        };
+
+        Ok(response)
+    }
+}
+
+impl<T: DeserializeOwned> MaybeEncrypted<T> {
+    /// Decrypt the response using the private key, if the wallet requested encryption. A response that is not
+    /// encrypted while encryption was requested or vice versa is rejected.
+    pub fn into_inner(
+        self,
+        encryption: Option<(&ResponseEncryption, &EcKeyPair)>,
+    ) -> Result<T, ResponseEncryptionError> {
+        match (self, encryption) {
+            (Self::Plain(response), None) => Ok(response),
+            (Self::Encrypted(jwe), Some((encryption, private_key))) => encryption.decrypt(&jwe, private_key),
+            (Self::Plain(_), Some(_)) => Err(ResponseEncryptionError::UnencryptedResponse),
+            (Self::Encrypted(_), None) => Err(ResponseEncryptionError::UnexpectedEncryptedResponse),
+        }
+    }
 }
 
 /// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-credential-endpoint
@@ -50,7 +210,47 @@ pub struct CredentialResponses {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "format", rename_all = "snake_case")]
 pub enum CredentialResponse {
-    MsoMdoc { credential: CborBase64<IssuerSigned> },
+    MsoMdoc {
+        credential: Box<CborBase64<IssuerSigned>>,
+        /// Identifies the issued credential in a [`NotificationRequest`] to the notification endpoint.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notification_id: Option<String>,
+    },
+    /// Sent instead of the credential if the issuer defers its issuance. The wallet can then later retrieve the
+    /// credential by sending a [`DeferredCredentialRequest`] containing the `transaction_id`.
+    #[serde(untagged)]
+    Deferred { transaction_id: String },
+}
+
+/// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-deferred-credential-request.
+/// Sent JSON-encoded to `POST /deferred_credential`.
+/// Since this implementation always defers all credentials of a session at once, the issuer responds with
+/// [`CredentialResponses`] like the `/batch_credential` endpoint does, instead of with a single [`CredentialResponse`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeferredCredentialRequest {
+    pub transaction_id: String,
+    /// If present, the issuer encrypts the [`CredentialResponses`] to the contained key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_response_encryption: Option<ResponseEncryption>,
+}
+
+/// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-notification-request.
+/// Sent JSON-encoded to `POST /notification`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationRequest {
+    pub notification_id: String,
+    pub event: NotificationEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_description: Option<String>,
+}
+
+/// What the wallet did with the credentials it received, as sent to the notification endpoint.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    CredentialAccepted,
+    CredentialFailure,
+    CredentialDeleted,
 }
 
 // https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#section-7.2.1.1
@@ -66,15 +266,17 @@ pub struct CredentialRequestProofJwtPayload {
 pub const OPENID4VCI_VC_POP_JWT_TYPE: &str = "openid4vci-proof+jwt";
 
 impl CredentialRequestProof {
+    /// Generate new keys and a proof of possession for each of them, along with the attestation of the keys by the
+    /// party that holds them, if any.
     pub async fn new_multiple<K: MdocEcdsaKey>(
         nonce: String,
         wallet_client_id: String,
         credential_issuer_identifier: BaseUrl,
         number_of_keys: u64,
         key_factory: impl KeyFactory<Key = K>,
-    ) -> Result<Vec<(K, CredentialRequestProof)>, IssuanceSessionError> {
-        let keys = key_factory
-            .generate_new_multiple(number_of_keys)
+    ) -> Result<(Vec<(K, CredentialRequestProof)>, Option<KeyAttestation>), IssuanceSessionError> {
+        let (keys, key_attestation) = key_factory
+            .generate_new_multiple_attested(number_of_keys)
             .await
             .map_err(|e| IssuanceSessionError::PrivateKeyGeneration(Box::new(e)))?;
 
@@ -97,6 +299,78 @@ impl CredentialRequestProof {
             .map(|(key, jwt)| (key, CredentialRequestProof::Jwt { jwt }))
             .collect();
 
-        Ok(keys_and_proofs)
+        Ok((keys_and_proofs, key_attestation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use crate::openid4vp::VpEncValues;
+
+    use super::{CredentialResponse, CredentialResponses, MaybeEncrypted, ResponseEncryption, ResponseEncryptionError};
+
+    fn deferred_responses() -> CredentialResponses {
+        CredentialResponses {
+            credential_responses: vec![CredentialResponse::Deferred {
+                transaction_id: "transaction_id".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_response_encryption() {
+        let (private_key, encryption) = ResponseEncryption::new_ephemeral(VpEncValues::A256GCM).unwrap();
+
+        let encrypted = MaybeEncrypted::new(deferred_responses(), Some(&encryption)).unwrap();
+        assert_matches!(encrypted, MaybeEncrypted::Encrypted(_));
+
+        let decrypted = encrypted.into_inner(Some((&encryption, &private_key))).unwrap();
+        assert_matches!(
+            decrypted.credential_responses.as_slice(),
+            [CredentialResponse::Deferred { transaction_id }] if transaction_id == "transaction_id"
+        );
+    }
+
+    #[test]
+    fn test_response_encryption_mismatch() {
+        let (private_key, encryption) = ResponseEncryption::new_ephemeral(VpEncValues::A256GCM).unwrap();
+
+        // A plain response is rejected when encryption was requested.
+        let plain = MaybeEncrypted::new(deferred_responses(), None).unwrap();
+        let error = plain.into_inner(Some((&encryption, &private_key))).unwrap_err();
+        assert_matches!(error, ResponseEncryptionError::UnencryptedResponse);
+
+        // An encrypted response is rejected when no encryption was requested.
+        let encrypted = MaybeEncrypted::new(deferred_responses(), Some(&encryption)).unwrap();
+        let error = encrypted.clone().into_inner(None).unwrap_err();
+        assert_matches!(error, ResponseEncryptionError::UnexpectedEncryptedResponse);
+
+        // A response encrypted with another content encryption algorithm than requested is rejected.
+        let other_encryption = ResponseEncryption {
+            enc: VpEncValues::A128GCM,
+            ..encryption.clone()
+        };
+        let encrypted = MaybeEncrypted::new(deferred_responses(), Some(&other_encryption)).unwrap();
+        let error = encrypted
+            .into_inner(Some((&encryption, &private_key)))
+            .map(|_: CredentialResponses| ())
+            .unwrap_err();
+        assert_matches!(
+            error,
+            ResponseEncryptionError::ContentEncryptionMismatch {
+                expected: VpEncValues::A256GCM,
+                found: Some(found),
+            } if found == "A128GCM"
+        );
+    }
+
+    #[test]
+    fn test_response_encryption_payload_not_an_object() {
+        let (_, encryption) = ResponseEncryption::new_ephemeral(VpEncValues::A256GCM).unwrap();
+
+        let error = MaybeEncrypted::new("not an object", Some(&encryption)).unwrap_err();
+        assert_matches!(error, ResponseEncryptionError::PayloadNotAnObject);
     }
 }
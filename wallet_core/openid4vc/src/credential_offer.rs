@@ -0,0 +1,449 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use url::Url;
+
+use error_category::ErrorCategory;
+use wallet_common::urls::BaseUrl;
+
+use crate::{
+    metadata::{CredentialFormat, CredentialMetadata},
+    token::{AttestationPreview, AuthorizationCode, TokenRequest, TokenRequestGrantType},
+};
+
+/// The URI scheme with which a Credential Offer can be passed to any wallet, as opposed to a wallet-specific
+/// universal link.
+pub const CREDENTIAL_OFFER_URI_SCHEME: &str = "openid-credential-offer";
+
+/// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-credential-offer-parameters.
+/// Sent by the issuer to the wallet, either by value or by reference, to start an issuance session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CredentialOffer {
+    pub credential_issuer: BaseUrl,
+    pub credential_configuration_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grants: Option<Grants>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Grants {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization_code: Option<AuthorizationCodeGrant>,
+    #[serde(
+        rename = "urn:ietf:params:oauth:grant-type:pre-authorized_code",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pre_authorized_code: Option<PreAuthorizedCodeGrant>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationCodeGrant {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_state: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PreAuthorizedCodeGrant {
+    #[serde(rename = "pre-authorized_code")]
+    pub pre_authorized_code: AuthorizationCode,
+    /// If present, the wallet must include a Transaction Code in its token request, that the user received
+    /// out-of-band.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_code: Option<TxCode>,
+}
+
+/// Describes the Transaction Code that the wallet should ask the user to enter, so that it can render an appropriate
+/// input field.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct TxCode {
+    #[serde(default)]
+    pub input_mode: TxCodeInputMode,
+    pub length: Option<u32>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TxCodeInputMode {
+    #[default]
+    Numeric,
+    Text,
+}
+
+impl TxCode {
+    /// Describe the specified Transaction Code, so that the user can be prompted for it.
+    pub fn new_for(tx_code: &str, description: Option<String>) -> Self {
+        let input_mode = if tx_code.chars().all(|c| c.is_ascii_digit()) {
+            TxCodeInputMode::Numeric
+        } else {
+            TxCodeInputMode::Text
+        };
+
+        Self {
+            input_mode,
+            length: Some(tx_code.chars().count() as u32),
+            description,
+        }
+    }
+}
+
+/// A Credential Offer as it is contained in the query of a Credential Offer URI: either the offer itself, or a URL
+/// from which the offer can be retrieved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialOfferContainer {
+    Value(CredentialOffer),
+    Reference(BaseUrl),
+}
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+pub enum CredentialOfferError {
+    #[error("could not decode Credential Offer URI query: {0}")]
+    #[category(pd)]
+    QueryDecoding(#[from] serde_urlencoded::de::Error),
+    #[error("could not deserialize Credential Offer: {0}")]
+    #[category(pd)]
+    Deserialization(#[from] serde_json::Error),
+    #[error("Credential Offer URI should contain exactly one of credential_offer and credential_offer_uri")]
+    #[category(critical)]
+    InvalidParameters,
+    #[error("could not retrieve Credential Offer: {0}")]
+    #[category(expected)]
+    Retrieval(#[from] reqwest::Error),
+    #[error("Credential Offer contains no pre-authorized code grant")]
+    #[category(critical)]
+    MissingPreAuthorizedCode,
+    #[error("Credential Offer contains no authorization code grant")]
+    #[category(critical)]
+    MissingAuthorizationCode,
+    #[error("Credential Offer requires a transaction code")]
+    #[category(expected)]
+    MissingTxCode(TxCode),
+    #[error("credential configuration in Credential Offer not supported by issuer: {0}")]
+    #[category(pd)]
+    UnsupportedCredentialConfiguration(String),
+    #[error("issuer offered attestation of doctype not in Credential Offer: {0}")]
+    #[category(pd)]
+    UnexpectedAttestation(String),
+}
+
+/// The query parameters of a Credential Offer URI, of which exactly one must be present.
+#[derive(Serialize, Deserialize)]
+struct CredentialOfferParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential_offer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential_offer_uri: Option<BaseUrl>,
+}
+
+impl CredentialOfferContainer {
+    /// Parse a Credential Offer URI, ignoring its scheme and path.
+    pub fn from_uri(uri: &Url) -> Result<Self, CredentialOfferError> {
+        let parameters: CredentialOfferParameters = serde_urlencoded::from_str(uri.query().unwrap_or_default())?;
+
+        match parameters {
+            CredentialOfferParameters {
+                credential_offer: Some(offer),
+                credential_offer_uri: None,
+            } => Ok(Self::Value(serde_json::from_str(&offer)?)),
+            CredentialOfferParameters {
+                credential_offer: None,
+                credential_offer_uri: Some(offer_uri),
+            } => Ok(Self::Reference(offer_uri)),
+            _ => Err(CredentialOfferError::InvalidParameters),
+        }
+    }
+
+    /// Construct a Credential Offer URI by setting the query of `base_uri`.
+    pub fn to_uri(&self, mut base_uri: Url) -> Url {
+        let parameters = match self {
+            Self::Value(offer) => CredentialOfferParameters {
+                credential_offer: Some(serde_json::to_string(offer).unwrap()),
+                credential_offer_uri: None,
+            },
+            Self::Reference(offer_uri) => CredentialOfferParameters {
+                credential_offer: None,
+                credential_offer_uri: Some(offer_uri.clone()),
+            },
+        };
+
+        base_uri.set_query(Some(&serde_urlencoded::to_string(parameters).unwrap()));
+        base_uri
+    }
+
+    /// Return the Credential Offer, retrieving it using `http_client` if it was passed by reference.
+    pub async fn resolve(self, http_client: &reqwest::Client) -> Result<CredentialOffer, CredentialOfferError> {
+        match self {
+            Self::Value(offer) => Ok(offer),
+            Self::Reference(offer_uri) => {
+                let offer = http_client
+                    .get(offer_uri.into_inner())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                Ok(offer)
+            }
+        }
+    }
+}
+
+impl CredentialOffer {
+    fn pre_authorized_code_grant(&self) -> Result<&PreAuthorizedCodeGrant, CredentialOfferError> {
+        self.grants
+            .as_ref()
+            .and_then(|grants| grants.pre_authorized_code.as_ref())
+            .ok_or(CredentialOfferError::MissingPreAuthorizedCode)
+    }
+
+    /// Returns the authorization code grant of this offer, with which the wallet starts an Authorization Code Flow at
+    /// the authorization server of the issuer.
+    pub fn authorization_code_grant(&self) -> Result<&AuthorizationCodeGrant, CredentialOfferError> {
+        self.grants
+            .as_ref()
+            .and_then(|grants| grants.authorization_code.as_ref())
+            .ok_or(CredentialOfferError::MissingAuthorizationCode)
+    }
+
+    /// Returns the description of the Transaction Code that the user has to enter, if this offer requires one.
+    pub fn tx_code(&self) -> Option<&TxCode> {
+        self.pre_authorized_code_grant()
+            .ok()
+            .and_then(|grant| grant.tx_code.as_ref())
+    }
+
+    /// Construct the Token Request with which the wallet redeems the pre-authorized code in this offer. If the offer
+    /// requires a Transaction Code, `tx_code` must be specified.
+    pub fn token_request(&self, tx_code: Option<String>) -> Result<TokenRequest, CredentialOfferError> {
+        let grant = self.pre_authorized_code_grant()?;
+
+        if let (Some(tx_code_description), None) = (&grant.tx_code, &tx_code) {
+            return Err(CredentialOfferError::MissingTxCode(tx_code_description.clone()));
+        }
+
+        Ok(TokenRequest {
+            grant_type: TokenRequestGrantType::PreAuthorizedCode {
+                pre_authorized_code: grant.pre_authorized_code.clone(),
+                tx_code,
+            },
+            code_verifier: None,
+            client_id: None,
+            redirect_uri: None,
+        })
+    }
+
+    /// Check that each of the credential configurations in this offer is one that the issuer supports according to
+    /// `credential_configurations_supported` from its metadata, and that the attestations that the issuer previewed
+    /// are of those configurations only.
+    pub fn validate_credential_configurations(
+        &self,
+        credential_configurations_supported: &HashMap<String, CredentialMetadata>,
+        attestation_previews: &[AttestationPreview],
+    ) -> Result<(), CredentialOfferError> {
+        let offered_doctypes = self
+            .credential_configuration_ids
+            .iter()
+            .map(|id| {
+                match credential_configurations_supported
+                    .get(id)
+                    .map(|credential_metadata| &credential_metadata.format)
+                {
+                    Some(CredentialFormat::MsoMdoc { doctype, .. }) => Ok(doctype.as_str()),
+                    _ => Err(CredentialOfferError::UnsupportedCredentialConfiguration(id.clone())),
+                }
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        attestation_previews.iter().try_for_each(|preview| match preview {
+            AttestationPreview::MsoMdoc { unsigned_mdoc, .. } => {
+                if offered_doctypes.contains(unsigned_mdoc.doc_type.as_str()) {
+                    Ok(())
+                } else {
+                    Err(CredentialOfferError::UnexpectedAttestation(
+                        unsigned_mdoc.doc_type.clone(),
+                    ))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use serde_json::json;
+
+    use nl_wallet_mdoc::{
+        server_keys::KeyPair, test::data, unsigned::UnsignedMdoc, utils::issuer_auth::IssuerRegistration,
+    };
+
+    use crate::token::TokenRequestGrantType;
+
+    use super::*;
+
+    fn example_offer() -> CredentialOffer {
+        serde_json::from_value(json!({
+            "credential_issuer": "https://issuer.example.com",
+            "credential_configuration_ids": ["com.example.diploma"],
+            "grants": {
+                "urn:ietf:params:oauth:grant-type:pre-authorized_code": {
+                    "pre-authorized_code": "adhjhdjajkdkhjhdj"
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_credential_offer_uri_by_value() {
+        let offer = example_offer();
+        let uri = CredentialOfferContainer::Value(offer.clone())
+            .to_uri(format!("{CREDENTIAL_OFFER_URI_SCHEME}://").parse().unwrap());
+
+        assert!(uri
+            .as_str()
+            .starts_with("openid-credential-offer://?credential_offer=%7B"));
+        assert_eq!(
+            CredentialOfferContainer::from_uri(&uri).unwrap(),
+            CredentialOfferContainer::Value(offer)
+        );
+    }
+
+    #[test]
+    fn test_credential_offer_uri_by_reference() {
+        let uri = "openid-credential-offer://?credential_offer_uri=https%3A%2F%2Fissuer.example.com%2Foffer%2F123"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            CredentialOfferContainer::from_uri(&uri).unwrap(),
+            CredentialOfferContainer::Reference("https://issuer.example.com/offer/123".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_credential_offer_uri_invalid_parameters() {
+        for uri in [
+            "openid-credential-offer://",
+            "openid-credential-offer://?credential_offer=%7B%7D&credential_offer_uri=https%3A%2F%2Fexample.com",
+        ] {
+            let error = CredentialOfferContainer::from_uri(&uri.parse().unwrap()).unwrap_err();
+            assert_matches!(
+                error,
+                CredentialOfferError::InvalidParameters | CredentialOfferError::Deserialization(_)
+            );
+        }
+    }
+
+    #[test]
+    fn test_credential_offer_token_request() {
+        let token_request = example_offer().token_request(None).unwrap();
+
+        assert_matches!(
+            token_request.grant_type,
+            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code, tx_code: None }
+                if pre_authorized_code.as_ref() == "adhjhdjajkdkhjhdj"
+        );
+
+        let offer = CredentialOffer {
+            grants: None,
+            ..example_offer()
+        };
+        assert_matches!(
+            offer.token_request(None).unwrap_err(),
+            CredentialOfferError::MissingPreAuthorizedCode
+        );
+    }
+
+    #[test]
+    fn test_credential_offer_tx_code() {
+        let offer: CredentialOffer = serde_json::from_value(json!({
+            "credential_issuer": "https://issuer.example.com",
+            "credential_configuration_ids": ["com.example.diploma"],
+            "grants": {
+                "urn:ietf:params:oauth:grant-type:pre-authorized_code": {
+                    "pre-authorized_code": "adhjhdjajkdkhjhdj",
+                    "tx_code": {
+                        "length": 4,
+                        "description": "Please enter the code sent to your e-mail address"
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let expected_tx_code = TxCode {
+            input_mode: TxCodeInputMode::Numeric,
+            length: Some(4),
+            description: Some("Please enter the code sent to your e-mail address".to_string()),
+        };
+        assert_eq!(offer.tx_code(), Some(&expected_tx_code));
+        assert_eq!(example_offer().tx_code(), None);
+
+        assert_matches!(
+            offer.token_request(None).unwrap_err(),
+            CredentialOfferError::MissingTxCode(tx_code) if tx_code == expected_tx_code
+        );
+        assert_matches!(
+            offer.token_request(Some("1234".to_string())).unwrap().grant_type,
+            TokenRequestGrantType::PreAuthorizedCode { tx_code: Some(tx_code), .. } if tx_code == "1234"
+        );
+    }
+
+    #[test]
+    fn test_tx_code_new_for() {
+        assert_eq!(
+            TxCode::new_for("0123", None),
+            TxCode {
+                input_mode: TxCodeInputMode::Numeric,
+                length: Some(4),
+                description: None,
+            }
+        );
+        assert_eq!(TxCode::new_for("a1b2c3", None).input_mode, TxCodeInputMode::Text);
+    }
+
+    #[test]
+    fn test_credential_offer_validate_credential_configurations() {
+        let ca = KeyPair::generate_issuer_mock_ca().unwrap();
+        let issuance_key = ca.generate_issuer_mock(IssuerRegistration::new_mock().into()).unwrap();
+        let attestation_previews = [AttestationPreview::MsoMdoc {
+            unsigned_mdoc: UnsignedMdoc::from(data::pid_family_name().into_first().unwrap()),
+            issuer: issuance_key.certificate().clone(),
+        }];
+        let credential_configurations = |doctype: &str| {
+            let metadata: CredentialMetadata = serde_json::from_value(json!({
+                "format": "mso_mdoc",
+                "doctype": doctype,
+                "claims": {},
+            }))
+            .unwrap();
+
+            HashMap::from([("com.example.pid".to_string(), metadata)])
+        };
+
+        let mut offer = example_offer();
+        offer.credential_configuration_ids = vec!["com.example.pid".to_string()];
+
+        offer
+            .validate_credential_configurations(&credential_configurations("com.example.pid"), &attestation_previews)
+            .expect("offered credential configuration should be valid");
+
+        let error = offer
+            .validate_credential_configurations(&HashMap::new(), &attestation_previews)
+            .unwrap_err();
+        assert_matches!(error, CredentialOfferError::UnsupportedCredentialConfiguration(id) if id == "com.example.pid");
+
+        let error = offer
+            .validate_credential_configurations(
+                &credential_configurations("com.example.diploma"),
+                &attestation_previews,
+            )
+            .unwrap_err();
+        assert_matches!(error, CredentialOfferError::UnexpectedAttestation(doctype) if doctype == "com.example.pid");
+    }
+}
@@ -0,0 +1,353 @@
+//! An implementation of a subset of the [Digital Credentials Query Language][dcql] (DCQL) from OpenID4VP,
+//! implementing only the fields needed to request mdocs. Other fields are left out of the various structs for now.
+//!
+//! [dcql]: https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-digital-credentials-query-l
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use error_category::ErrorCategory;
+use nl_wallet_mdoc::{
+    verifier::{ItemsRequests, ItemsRequirement},
+    ItemsRequest,
+};
+
+use crate::Format;
+
+/// As specified in https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-dcql-query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub credentials: Vec<CredentialQuery>,
+    /// If absent, all Credential Queries are required. Otherwise, only the Credential Queries referenced by the
+    /// required Credential Set Queries are, any one of the options of each set being sufficient.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credential_sets: Vec<CredentialSetQuery>,
+}
+
+/// As specified in https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-credential-query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialQuery {
+    /// Identifies the Credential Query, and is used as the key of the corresponding presentation in the `vp_token`.
+    pub id: String,
+    pub format: Format,
+    pub meta: MdocMeta,
+    #[serde(default)]
+    pub claims: Vec<ClaimsQuery>,
+}
+
+/// As specified in
+/// <https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-parameters-in-the-meta-para>.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MdocMeta {
+    pub doctype_value: String,
+}
+
+/// As specified in https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-claims-query.
+/// For mdocs, the `path` consists of exactly two elements: the namespace and the attribute name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimsQuery {
+    pub path: Vec<String>,
+    #[serde(default)]
+    pub intent_to_retain: bool,
+}
+
+/// As specified in https://openid.net/specs/openid-4-verifiable-presentations-1_0-24.html#name-credential-set-query.
+/// Only options that consist of a single Credential Query are supported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialSetQuery {
+    pub options: Vec<Vec<String>>,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(critical)]
+pub enum DcqlConversionError {
+    #[error("unsupported credential format: {0:?}")]
+    UnsupportedFormat(Format),
+    #[error("unsupported claim path: {0:?}")]
+    UnsupportedClaimPath(Vec<String>),
+    #[error("duplicate credential query ID: {0}")]
+    DuplicateCredentialQueryId(String),
+    #[error("credential set references unknown credential query ID: {0}")]
+    UnknownCredentialQueryId(String),
+    #[error("unsupported credential set option: {0:?}")]
+    UnsupportedCredentialSetOption(Vec<String>),
+    #[error("credential set contains no options")]
+    EmptyCredentialSet,
+}
+
+impl ClaimsQuery {
+    fn parse_path(&self) -> Result<(String, String), DcqlConversionError> {
+        match self.path.as_slice() {
+            [namespace, attr] => Ok((namespace.clone(), attr.clone())),
+            _ => Err(DcqlConversionError::UnsupportedClaimPath(self.path.clone())),
+        }
+    }
+}
+
+impl CredentialQuery {
+    fn new(id: String, items_request: &ItemsRequest) -> Self {
+        CredentialQuery {
+            id,
+            format: Format::MsoMdoc,
+            meta: MdocMeta {
+                doctype_value: items_request.doc_type.clone(),
+            },
+            claims: items_request
+                .name_spaces
+                .iter()
+                .flat_map(|(namespace, attrs)| {
+                    attrs.iter().map(|(attr, intent_to_retain)| ClaimsQuery {
+                        path: vec![namespace.clone(), attr.clone()],
+                        intent_to_retain: *intent_to_retain,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<&CredentialQuery> for ItemsRequest {
+    type Error = DcqlConversionError;
+
+    fn try_from(credential_query: &CredentialQuery) -> Result<Self, Self::Error> {
+        if credential_query.format != Format::MsoMdoc {
+            return Err(DcqlConversionError::UnsupportedFormat(credential_query.format));
+        }
+
+        let mut name_spaces: IndexMap<String, IndexMap<String, bool>> = IndexMap::new();
+        for claim in &credential_query.claims {
+            let (namespace, attr) = claim.parse_path()?;
+            name_spaces
+                .entry(namespace)
+                .or_default()
+                .insert(attr, claim.intent_to_retain);
+        }
+
+        Ok(ItemsRequest {
+            doc_type: credential_query.meta.doctype_value.clone(),
+            request_info: None,
+            name_spaces,
+        })
+    }
+}
+
+impl From<&ItemsRequests> for Query {
+    fn from(items_requests: &ItemsRequests) -> Self {
+        let mut credentials = Vec::new();
+        let mut credential_sets = Vec::new();
+
+        // Every alternative gets its own Credential Query, which are offered as the options of a Credential Set Query.
+        for requirement in &items_requests.0 {
+            let options = requirement
+                .alternatives()
+                .iter()
+                .map(|items_request| {
+                    let id = format!("credential_{}", credentials.len());
+                    credentials.push(CredentialQuery::new(id.clone(), items_request));
+                    vec![id]
+                })
+                .collect();
+
+            credential_sets.push(CredentialSetQuery {
+                options,
+                required: true,
+            });
+        }
+
+        // Only use Credential Set Queries when necessary, as all Credential Queries are required in their absence.
+        if !items_requests.has_alternatives() {
+            credential_sets.clear();
+        }
+
+        Query {
+            credentials,
+            credential_sets,
+        }
+    }
+}
+
+impl Query {
+    /// Convert the Credential Queries and return them per requirement, along with their IDs. A requirement can be
+    /// satisfied by any one of its Credential Queries. The requirements and their alternatives are in the same order as
+    /// those of the [`ItemsRequests`] converted from this query, so that a disclosed document can be traced back to the
+    /// ID of the Credential Query it satisfies.
+    pub fn requirements(&self) -> Result<Vec<Vec<(&str, ItemsRequest)>>, DcqlConversionError> {
+        let mut items_requests = IndexMap::with_capacity(self.credentials.len());
+        for credential_query in &self.credentials {
+            let items_request = ItemsRequest::try_from(credential_query)?;
+            if items_requests
+                .insert(credential_query.id.as_str(), items_request)
+                .is_some()
+            {
+                return Err(DcqlConversionError::DuplicateCredentialQueryId(
+                    credential_query.id.clone(),
+                ));
+            }
+        }
+
+        if self.credential_sets.is_empty() {
+            return Ok(items_requests
+                .into_iter()
+                .map(|alternative| vec![alternative])
+                .collect());
+        }
+
+        // Credential Set Queries that are not required are ignored, as are the Credential Queries only they reference.
+        self.credential_sets
+            .iter()
+            .filter(|credential_set| credential_set.required)
+            .map(|credential_set| {
+                let alternatives = credential_set
+                    .options
+                    .iter()
+                    .map(|option| match option.as_slice() {
+                        [id] => items_requests
+                            .get_key_value(id.as_str())
+                            .map(|(id, items_request)| (*id, items_request.clone()))
+                            .ok_or_else(|| DcqlConversionError::UnknownCredentialQueryId(id.clone())),
+                        _ => Err(DcqlConversionError::UnsupportedCredentialSetOption(option.clone())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if alternatives.is_empty() {
+                    return Err(DcqlConversionError::EmptyCredentialSet);
+                }
+
+                Ok(alternatives)
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&Query> for ItemsRequests {
+    type Error = DcqlConversionError;
+
+    fn try_from(query: &Query) -> Result<Self, Self::Error> {
+        let requirements = query
+            .requirements()?
+            .into_iter()
+            .map(|alternatives| {
+                let mut alternatives = alternatives
+                    .into_iter()
+                    .map(|(_, items_request)| items_request)
+                    .collect::<Vec<_>>();
+
+                match alternatives.len() {
+                    1 => ItemsRequirement::Single(alternatives.pop().unwrap()),
+                    // Safe, as `Query::requirements()` never returns a requirement without alternatives.
+                    _ => ItemsRequirement::OneOf {
+                        one_of: alternatives.try_into().unwrap(),
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(requirements.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use serde_json::json;
+
+    use nl_wallet_mdoc::{
+        examples::Examples,
+        verifier::{ItemsRequests, ItemsRequirement},
+    };
+
+    use super::{DcqlConversionError, Query};
+
+    #[test]
+    fn convert_query_itemsrequests() {
+        let items_requests: ItemsRequests = Examples::items_requests();
+        let query: Query = (&items_requests).into();
+
+        assert!(query.credential_sets.is_empty());
+
+        let converted: ItemsRequests = (&query).try_into().unwrap();
+
+        assert_eq!(items_requests, converted);
+    }
+
+    #[test]
+    fn convert_query_itemsrequests_with_alternatives() {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut alternative = items_request.clone();
+        alternative.doc_type = "alternative_doc_type".to_string();
+
+        let items_requests = ItemsRequests::from(vec![
+            ItemsRequirement::Single(items_request.clone()),
+            ItemsRequirement::OneOf {
+                one_of: vec![items_request, alternative].try_into().unwrap(),
+            },
+        ]);
+        let query: Query = (&items_requests).into();
+
+        assert_eq!(query.credentials.len(), 3);
+        assert_eq!(query.credential_sets.len(), 2);
+        assert_eq!(query.credential_sets[0].options, [["credential_0"]]);
+        assert_eq!(query.credential_sets[1].options, [["credential_1"], ["credential_2"]]);
+
+        let converted: ItemsRequests = (&query).try_into().unwrap();
+
+        assert_eq!(items_requests, converted);
+    }
+
+    #[test]
+    fn convert_query_errors() {
+        let mut query: Query = (&Examples::items_requests()).into();
+        query.credentials[0].claims[0].path.pop();
+
+        let error = ItemsRequests::try_from(&query).expect_err("converting should fail");
+        assert_matches!(error, DcqlConversionError::UnsupportedClaimPath(_));
+
+        let mut query: Query = (&Examples::items_requests()).into();
+        query.credential_sets = serde_json::from_value(json!([{ "options": [["unknown"]] }])).unwrap();
+
+        let error = ItemsRequests::try_from(&query).expect_err("converting should fail");
+        assert_matches!(error, DcqlConversionError::UnknownCredentialQueryId(_));
+    }
+
+    #[test]
+    fn deserialize_example_query() {
+        let example_json = json!(
+            {
+                "credentials": [
+                    {
+                        "id": "my_credential",
+                        "format": "mso_mdoc",
+                        "meta": {
+                            "doctype_value": "org.iso.7367.1.mVRC"
+                        },
+                        "claims": [
+                            { "path": ["org.iso.7367.1", "vehicle_holder"] },
+                            { "path": ["org.iso.18013.5.1", "first_name"], "intent_to_retain": true }
+                        ]
+                    }
+                ],
+                "credential_sets": [
+                    {
+                        "options": [["my_credential"]]
+                    }
+                ]
+            }
+        );
+
+        let query: Query = serde_json::from_value(example_json).unwrap();
+        assert!(query.credential_sets[0].required);
+
+        let items_requests = ItemsRequests::try_from(&query).unwrap();
+        let ItemsRequirement::Single(items_request) = &items_requests.0[0] else {
+            panic!("expected a single requested document")
+        };
+        assert_eq!(items_request.doc_type, "org.iso.7367.1.mVRC");
+        assert_eq!(items_request.name_spaces.len(), 2);
+        assert!(items_request.name_spaces["org.iso.18013.5.1"]["first_name"]);
+    }
+}
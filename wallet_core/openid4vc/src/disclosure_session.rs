@@ -516,7 +516,7 @@ where
 
     /// Internal helper function for processing and checking the Authorization Request,
     /// including checking whether or not we have the requested attributes.
-    async fn process_request<'a, S>(
+    async fn process_request<S>(
         auth_request: &IsoVpAuthorizationRequest,
         certificate: &Certificate,
         session_transcript: &SessionTranscript,
@@ -542,12 +542,13 @@ where
         };
 
         // Verify that the requested attributes are included in the reader authentication.
-        reader_registration.verify_requested_attributes(auth_request.items_requests.as_ref().iter())?;
+        // Note that this includes all alternatives, even though only one of those will be disclosed.
+        reader_registration.verify_requested_attributes(auth_request.items_requests.items_requests())?;
 
         // Fetch documents from the database, calculate which ones satisfy the request and
         // formulate proposals for those documents. If there is a mismatch, return an error.
-        let candidates_by_doc_type = match DisclosureRequestMatch::new(
-            auth_request.items_requests.as_ref().iter(),
+        let candidates_by_doc_type = match DisclosureRequestMatch::new_with_alternatives(
+            &auth_request.items_requests,
             mdoc_data_source,
             session_transcript,
         )
@@ -717,6 +718,7 @@ mod tests {
             serialization::{cbor_deserialize, cbor_serialize, CborBase64, CborSeq, TaggedBytes},
             x509::CertificateError,
         },
+        verifier::ItemsRequirement,
         DeviceAuth, DeviceAuthenticationKeyed, ItemsRequest, MobileSecurityObject, SessionTranscript,
     };
     use wallet_common::{keys::software::SoftwareEcdsaKey, utils::random_string};
@@ -724,8 +726,8 @@ mod tests {
     use crate::{
         jwt::JwtX5cError,
         openid4vp::{
-            AuthRequestValidationError, VerifiablePresentation, VpAuthorizationResponse, VpClientMetadata, VpJwks,
-            VpRequestUriObject,
+            AuthRequestValidationError, QueryLanguage, VerifiablePresentation, VpAuthorizationResponse,
+            VpClientMetadata, VpJwks, VpPresentations, VpRequestUriObject,
         },
         test::{
             disclosure_session_start, iso_auth_request, test_disclosure_session_start_error_http_client,
@@ -741,14 +743,20 @@ mod tests {
     };
 
     // This is the full happy path test of `DisclosureSession`.
+    #[rstest]
     #[tokio::test]
-    async fn test_disclosure_session() {
+    async fn test_disclosure_session(
+        #[values(QueryLanguage::PresentationExchange, QueryLanguage::Dcql)] query_language: QueryLanguage,
+    ) {
         // Starting a disclosure session should succeed.
         let (disclosure_session, verifier_session) = disclosure_session_start(
             SessionType::SameDevice,
             DisclosureUriSource::Link,
             ReaderCertificateKind::WithReaderRegistration,
-            identity,
+            |mut verifier_session| {
+                verifier_session.query_language = query_language;
+                verifier_session
+            },
             identity,
             identity,
         )
@@ -758,8 +766,7 @@ mod tests {
         // Remember the `AttributeIdentifier`s that were in the request.
         let request_identifiers = verifier_session
             .items_requests
-            .as_ref()
-            .iter()
+            .items_requests()
             .flat_map(|items_request| items_request.attribute_identifiers())
             .collect::<IndexSet<_>>();
 
@@ -805,15 +812,21 @@ mod tests {
 
         // Decrypt the disclosure and extract the contained disclosed documents.
         let jwe = wallet_messages.last().unwrap().disclosure();
-        let (mut response, mdoc_nonce) =
+        let (response, mdoc_nonce) =
             VpAuthorizationResponse::decrypt(jwe, &verifier_session.encryption_keypair, &verifier_session.nonce)
                 .unwrap();
-        let device_response = match response.vp_token.pop().unwrap() {
-            VerifiablePresentation::MsoMdoc(CborBase64(device_response)) => device_response,
+        let presentations = match response.vp_token {
+            VpPresentations::PresentationExchange(presentations) => presentations,
+            VpPresentations::Dcql(presentations) => presentations.into_values().collect(),
         };
-        let documents = device_response
-            .documents
-            .expect("No documents contained in DeviceResponse");
+        let documents = presentations
+            .into_iter()
+            .flat_map(|VerifiablePresentation::MsoMdoc(CborBase64(device_response))| {
+                device_response
+                    .documents
+                    .expect("No documents contained in DeviceResponse")
+            })
+            .collect::<Vec<_>>();
         assert_eq!(documents.len(), public_keys.len());
 
         // Check that the attributes contained in the response match those in the request.
@@ -1313,11 +1326,12 @@ mod tests {
             DisclosureUriSource::Link,
             ReaderCertificateKind::WithReaderRegistration,
             |mut verifier_session| {
-                verifier_session
-                    .items_requests
-                    .0
-                    .first_mut()
-                    .unwrap()
+                let Some(ItemsRequirement::Single(items_request)) = verifier_session.items_requests.0.first_mut()
+                else {
+                    panic!("verifier session should request a single document");
+                };
+
+                items_request
                     .name_spaces
                     .get_mut(EXAMPLE_NAMESPACE)
                     .unwrap()
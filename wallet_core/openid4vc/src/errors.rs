@@ -9,7 +9,7 @@ use wallet_common::{
 };
 
 use crate::{
-    issuer::{CredentialRequestError, IssuanceError, TokenRequestError},
+    issuer::{CredentialRequestError, IssuanceError, NotificationError, TokenRequestError},
     verifier::{
         CancelSessionError, DisclosedAttributesError, GetAuthRequestError, NewSessionError, PostAuthResponseError,
         SessionError, SessionStatus, SessionStatusError, WithRedirectUri,
@@ -22,7 +22,7 @@ use crate::{
 pub struct ErrorResponse<T> {
     pub error: T,
     pub error_description: Option<String>,
-    pub error_uri: Option<Url>,
+    pub error_uri: Option<Box<Url>>,
 }
 
 /// Wrapper of [`ErrorResponse`] that has an optional redirect URI
@@ -50,6 +50,14 @@ pub enum CredentialErrorCode {
     InvalidProof,
     InvalidEncryptionParameters,
 
+    // From
+    // https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-deferred-credential-error-r
+    IssuancePending,
+    InvalidTransactionId,
+
+    // From https://datatracker.ietf.org/doc/draft-ietf-oauth-attestation-based-client-auth/
+    UseAttestationChallenge,
+
     // From https://www.rfc-editor.org/rfc/rfc6750.html#section-3.1
     InvalidRequest,
     InvalidToken,
@@ -71,10 +79,12 @@ impl From<CredentialRequestError> for ErrorResponse<CredentialErrorCode> {
                 | CredentialRequestError::MissingPrivateKey(_)
                 | CredentialRequestError::AttestationSigning(_)
                 | CredentialRequestError::CborSerialization(_)
-                | CredentialRequestError::JsonSerialization(_) => CredentialErrorCode::ServerError,
-                CredentialRequestError::IssuanceError(_) | CredentialRequestError::UseBatchIssuance => {
-                    CredentialErrorCode::InvalidRequest
-                }
+                | CredentialRequestError::JsonSerialization(_)
+                | CredentialRequestError::ResponseEncryption(_)
+                | CredentialRequestError::AttributeService(_) => CredentialErrorCode::ServerError,
+                CredentialRequestError::IssuanceError(_)
+                | CredentialRequestError::UseBatchIssuance
+                | CredentialRequestError::WalletAttestation(_) => CredentialErrorCode::InvalidRequest,
                 CredentialRequestError::Unauthorized | CredentialRequestError::MalformedToken => {
                     CredentialErrorCode::InvalidToken
                 }
@@ -84,12 +94,20 @@ impl From<CredentialRequestError> for ErrorResponse<CredentialErrorCode> {
                 | CredentialRequestError::JwtDecodingFailed(_)
                 | CredentialRequestError::JwkConversion(_)
                 | CredentialRequestError::MissingCredentialRequestPoP => CredentialErrorCode::InvalidProof,
-                CredentialRequestError::DoctypeMismatch | CredentialRequestError::DoctypeNotOffered(_) => {
+                CredentialRequestError::DoctypeMismatch
+                | CredentialRequestError::DoctypeNotOffered(_)
+                | CredentialRequestError::UnexpectedCredentialRequestCount { .. } => {
                     CredentialErrorCode::InvalidCredentialRequest
                 }
                 CredentialRequestError::UnsupportedCredentialFormat(_) => {
                     CredentialErrorCode::UnsupportedCredentialFormat
                 }
+                CredentialRequestError::IssuancePending => CredentialErrorCode::IssuancePending,
+                CredentialRequestError::InvalidTransactionId => CredentialErrorCode::InvalidTransactionId,
+                CredentialRequestError::InvalidEncryptionParameters(_) => {
+                    CredentialErrorCode::InvalidEncryptionParameters
+                }
+                CredentialRequestError::UseAttestationChallenge(_) => CredentialErrorCode::UseAttestationChallenge,
             },
             error_description: Some(description),
             error_uri: None,
@@ -105,6 +123,9 @@ impl ErrorStatusCode for CredentialErrorCode {
             | CredentialErrorCode::UnsupportedCredentialFormat
             | CredentialErrorCode::InvalidProof
             | CredentialErrorCode::InvalidEncryptionParameters
+            | CredentialErrorCode::IssuancePending
+            | CredentialErrorCode::InvalidTransactionId
+            | CredentialErrorCode::UseAttestationChallenge
             | CredentialErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
             CredentialErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
             CredentialErrorCode::InvalidToken => StatusCode::UNAUTHORIZED,
@@ -113,6 +134,51 @@ impl ErrorStatusCode for CredentialErrorCode {
     }
 }
 
+/// See
+/// <https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-notification-error-response>
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationErrorCode {
+    InvalidNotificationId,
+    InvalidNotificationRequest,
+
+    // From https://www.rfc-editor.org/rfc/rfc6750.html#section-3.1
+    InvalidToken,
+
+    /// This can be returned in case of internal server errors, i.e. with HTTP status code 5xx.
+    ServerError,
+}
+
+impl From<NotificationError> for ErrorResponse<NotificationErrorCode> {
+    fn from(err: NotificationError) -> Self {
+        let description = err.to_string();
+        ErrorResponse {
+            error: match err {
+                NotificationError::IssuanceError(IssuanceError::SessionStore(_)) => NotificationErrorCode::ServerError,
+                NotificationError::IssuanceError(_) => NotificationErrorCode::InvalidNotificationRequest,
+                NotificationError::Unauthorized | NotificationError::MalformedToken => {
+                    NotificationErrorCode::InvalidToken
+                }
+                NotificationError::InvalidNotificationId => NotificationErrorCode::InvalidNotificationId,
+            },
+            error_description: Some(description),
+            error_uri: None,
+        }
+    }
+}
+
+impl ErrorStatusCode for NotificationErrorCode {
+    fn status_code(&self) -> reqwest::StatusCode {
+        match self {
+            NotificationErrorCode::InvalidNotificationId | NotificationErrorCode::InvalidNotificationRequest => {
+                StatusCode::BAD_REQUEST
+            }
+            NotificationErrorCode::InvalidToken => StatusCode::UNAUTHORIZED,
+            NotificationErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 /// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#section-6.3
 /// and https://www.rfc-editor.org/rfc/rfc6749.html#section-5.2.
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -127,6 +193,9 @@ pub enum TokenErrorCode {
     AuthorizationPending, // OpenID4VCI-specific error type
     SlowDown,             // OpenID4VCI-specific error type
 
+    // From https://datatracker.ietf.org/doc/draft-ietf-oauth-attestation-based-client-auth/
+    UseAttestationChallenge,
+
     /// This can be returned in case of internal server errors, i.e. with HTTP status code 5xx.
     /// This error type is not defined in the specs, but then again the entire HTTP response in case
     /// 5xx status codes is not defined by the specs, so we have freedom to return what we want.
@@ -142,6 +211,11 @@ impl From<TokenRequestError> for ErrorResponse<TokenErrorCode> {
                 | TokenRequestError::AttributeService(_) => TokenErrorCode::ServerError,
                 TokenRequestError::IssuanceError(_) => TokenErrorCode::InvalidRequest,
                 TokenRequestError::UnsupportedTokenRequestType => TokenErrorCode::UnsupportedGrantType,
+                TokenRequestError::IncorrectTxCode | TokenRequestError::InvalidRefreshToken => {
+                    TokenErrorCode::InvalidGrant
+                }
+                TokenRequestError::WalletAttestation(_) => TokenErrorCode::InvalidClient,
+                TokenRequestError::UseAttestationChallenge(_) => TokenErrorCode::UseAttestationChallenge,
             },
             error_description: Some(description),
             error_uri: None,
@@ -158,7 +232,8 @@ impl ErrorStatusCode for TokenErrorCode {
             | TokenErrorCode::UnsupportedGrantType
             | TokenErrorCode::InvalidScope
             | TokenErrorCode::AuthorizationPending
-            | TokenErrorCode::SlowDown => StatusCode::BAD_REQUEST,
+            | TokenErrorCode::SlowDown
+            | TokenErrorCode::UseAttestationChallenge => StatusCode::BAD_REQUEST,
             TokenErrorCode::InvalidClient => StatusCode::UNAUTHORIZED,
             TokenErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -339,7 +414,9 @@ impl From<&NewSessionError> for VerificationErrorCode {
             NewSessionError::Session(session_error) => session_error.into(),
             NewSessionError::NoItemsRequests
             | NewSessionError::UnknownUseCase(_)
-            | NewSessionError::ReturnUrlConfigurationMismatch => VerificationErrorCode::InvalidRequest,
+            | NewSessionError::ReturnUrlConfigurationMismatch
+            | NewSessionError::ExpirationTooLong(_)
+            | NewSessionError::InvalidCorrelationId => VerificationErrorCode::InvalidRequest,
         }
     }
 }
@@ -371,6 +448,12 @@ impl From<&DisclosedAttributesError> for VerificationErrorCode {
     }
 }
 
+impl From<SessionError> for HttpJsonError<VerificationErrorCode> {
+    fn from(error: SessionError) -> Self {
+        HttpJsonError::from_error(&error)
+    }
+}
+
 impl From<NewSessionError> for HttpJsonError<VerificationErrorCode> {
     fn from(error: NewSessionError) -> Self {
         HttpJsonError::from_error(&error)
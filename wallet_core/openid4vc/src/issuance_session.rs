@@ -1,15 +1,21 @@
-use std::{collections::VecDeque, fmt::Debug};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+};
 
+use base64::prelude::*;
 use futures::{future::try_join_all, TryFutureExt};
 use itertools::Itertools;
+use josekit::jwk::alg::ec::EcKeyPair;
 use p256::{
     ecdsa::{SigningKey, VerifyingKey},
     elliptic_curve::rand_core::OsRng,
 };
 use reqwest::{
-    header::{ToStrError, AUTHORIZATION},
+    header::{ToStrError, AUTHORIZATION, CONTENT_TYPE},
     Method,
 };
+use serde::{Deserialize, Serialize};
 use url::Url;
 
 use error_category::ErrorCategory;
@@ -23,18 +29,35 @@ use nl_wallet_mdoc::{
     },
     ATTR_RANDOM_LENGTH,
 };
-use wallet_common::{generator::TimeGenerator, jwt::JwtError, urls::BaseUrl};
+use wallet_common::{
+    account::serialization::DerSigningKey, generator::TimeGenerator, jwt::JwtError, urls::BaseUrl, utils::random_bytes,
+};
 
 use crate::{
+    authorization::{
+        AuthorizationDetails, AuthorizationDetailsFormatData, AuthorizationRequest, AuthorizationResponse,
+        PkceCodeChallenge, PushedAuthorizationResponse, ResponseType,
+    },
     credential::{
         CredentialRequest, CredentialRequestProof, CredentialRequests, CredentialResponse, CredentialResponses,
+        DeferredCredentialRequest, MaybeEncrypted, NotificationEvent, NotificationRequest, ResponseEncryption,
+        ResponseEncryptionError,
     },
     dpop::{Dpop, DpopError, DPOP_HEADER_NAME, DPOP_NONCE_HEADER_NAME},
     jwt::JwkConversionError,
-    metadata::IssuerMetadata,
+    metadata::{CredentialFormat, CredentialMetadata, IssuerData, IssuerMetadata, IssuerMetadataError},
     oidc,
-    token::{AccessToken, AttestationPreview, TokenRequest, TokenResponseWithPreviews},
-    CredentialErrorCode, ErrorResponse, Format, TokenErrorCode, NL_WALLET_CLIENT_ID,
+    openid4vp::{VpAlgValues, VpEncValues},
+    pkce::{PkcePair, S256PkcePair},
+    token::{
+        AccessToken, AttestationPreview, RefreshToken, TokenRequest, TokenRequestGrantType, TokenResponseWithPreviews,
+    },
+    wallet_attestation::{
+        WalletAttestationWithPop, CLIENT_ATTESTATION_CHALLENGE_HEADER_NAME, CLIENT_ATTESTATION_HEADER_NAME,
+        CLIENT_ATTESTATION_POP_HEADER_NAME,
+    },
+    AuthorizationErrorCode, CredentialErrorCode, ErrorResponse, Format, NotificationErrorCode, TokenErrorCode,
+    NL_WALLET_CLIENT_ID,
 };
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
@@ -70,6 +93,12 @@ pub enum IssuanceSessionError {
     #[error("error requesting credentials: {0:?}")]
     #[category(pd)]
     CredentialRequest(ErrorResponse<CredentialErrorCode>),
+    #[error("issuer requires a wallet attestation PoP containing its challenge")]
+    #[category(expected)]
+    AttestationChallenge(String),
+    #[error("error sending notification: {0:?}")]
+    #[category(pd)]
+    NotificationRequest(ErrorResponse<NotificationErrorCode>),
     #[error("generating attestation private keys failed: {0}")]
     #[category(pd)]
     PrivateKeyGeneration(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
@@ -97,15 +126,61 @@ pub enum IssuanceSessionError {
     #[error("error discovering OpenID4VCI Credential Issuer metadata: {0}")]
     #[category(expected)]
     OpenId4vciDiscovery(#[source] reqwest::Error),
+    #[error("error verifying OpenID4VCI Credential Issuer metadata: {0}")]
+    IssuerMetadata(#[from] IssuerMetadataError),
     #[error("issuer has no batch credential endpoint")]
     #[category(critical)]
     NoBatchCredentialEndpoint,
+    #[error("issuer has no deferred credential endpoint")]
+    #[category(critical)]
+    NoDeferredCredentialEndpoint,
+    #[error("issuer deferred issuance of only some of the credentials, or under different transaction IDs")]
+    #[category(critical)]
+    UnexpectedDeferredResponse,
+    #[error("error with encrypted credential response: {0}")]
+    ResponseEncryption(#[from] ResponseEncryptionError),
     #[error("malformed attribute: random too short (was {0}; minimum {1}")]
     #[category(critical)]
     AttributeRandomLength(usize, usize),
+    #[error("authorization server has no pushed authorization request endpoint")]
+    #[category(critical)]
+    NoPushedAuthorizationRequestEndpoint,
+    #[error("credential configuration not supported by issuer: {0}")]
+    #[category(pd)]
+    UnsupportedCredentialConfiguration(String),
+    #[error("error pushing authorization request: {0:?}")]
+    #[category(pd)]
+    PushedAuthorizationRequest(ErrorResponse<AuthorizationErrorCode>),
+    #[error("error received in authorization response: {0:?}")]
+    #[category(pd)]
+    AuthorizationResponse(ErrorResponse<AuthorizationErrorCode>),
+    #[error("could not decode authorization response: {0}")]
+    #[category(pd)]
+    AuthorizationResponseDecoding(#[from] serde_urlencoded::de::Error),
+    #[error("invalid redirect URI received")]
+    #[category(critical)]
+    RedirectUriMismatch,
+    #[error("invalid state token received in redirect URI")]
+    #[category(critical)]
+    StateTokenMismatch,
+    #[error("no authorization code received in redirect URI")]
+    #[category(critical)]
+    NoAuthCode,
 }
 
 pub trait IssuanceSession<H = HttpVcMessageClient> {
+    /// Start an Authorization Code Flow at the authorization server of the issuer, by pushing an authorization request
+    /// for the specified credential configurations to it. Returns the state of the flow, along with the URL of the
+    /// authorization endpoint to which the user should be sent to authenticate.
+    async fn start_authorization(
+        message_client: H,
+        credential_issuer: BaseUrl,
+        credential_configuration_ids: &[String],
+        issuer_state: Option<String>,
+        redirect_uri: Url,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<(IssuanceAuthorization, Url), IssuanceSessionError>;
+
     async fn start_issuance(
         message_client: H,
         base_url: BaseUrl,
@@ -115,28 +190,268 @@ pub trait IssuanceSession<H = HttpVcMessageClient> {
     where
         Self: Sized;
 
+    /// The metadata that the issuer publishes about the credentials that it supports, which describes how the wallet
+    /// should display the attestations issued in this session.
+    /// These are keyed by the identifiers of the credential configurations, as used in a Credential Offer.
+    fn credential_metadata(&self) -> HashMap<String, CredentialMetadata>;
+
     async fn accept_issuance<K: MdocEcdsaKey>(
         &self,
         mdoc_trust_anchors: &[TrustAnchor<'_>],
         key_factory: impl KeyFactory<Key = K>,
         credential_issuer_identifier: BaseUrl,
-    ) -> Result<Vec<MdocCopies>, IssuanceSessionError>;
+    ) -> Result<AcceptedIssuance, IssuanceSessionError>;
+
+    /// Try to retrieve the mdocs of an issuance session that the issuer deferred. If issuance is still pending,
+    /// this returns [`AcceptedIssuance::Deferred`] again.
+    async fn accept_deferred_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        deferred_issuance: DeferredIssuance,
+        mdoc_trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<AcceptedIssuance, IssuanceSessionError>;
+
+    /// Obtain fresh copies of previously issued mdocs using the refresh token that the issuer handed out. The
+    /// refreshed mdocs are bound to the same keys as the original ones. Returns the mdocs in the order of the original
+    /// attestation previews, and the [`IssuanceRefresh`] with which they can be refreshed again.
+    async fn refresh_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        refresh: IssuanceRefresh,
+        mdoc_trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError>;
+
+    /// Obtain new copies of previously issued mdocs using the refresh token that the issuer handed out, like
+    /// [`IssuanceSession::refresh_issuance()`], but bound to new keys generated by `key_factory`. This replenishes the
+    /// copies that the wallet uses for unlinkable disclosures. Returns the new mdocs in the order of the original
+    /// attestation previews, and the [`IssuanceRefresh`] with which the new mdocs can be refreshed.
+    async fn replenish_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        refresh: IssuanceRefresh,
+        mdoc_trust_anchors: &[TrustAnchor<'_>],
+        key_factory: impl KeyFactory<Key = K>,
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError>;
+
+    /// Notify the issuer of what the wallet did with the mdocs it received, e.g. whether it succeeded in storing them.
+    async fn notify_issuance(
+        message_client: H,
+        notification: IssuanceNotification,
+        event: NotificationEvent,
+    ) -> Result<(), IssuanceSessionError>;
 
     async fn reject_issuance(self) -> Result<(), IssuanceSessionError>;
 }
 
+/// The result of accepting issuance: either the issued mdocs, along with what is needed to refresh them later and to
+/// notify the issuer about them if the issuer supports that, or the state with which they can be retrieved later if the
+/// issuer deferred their issuance.
+#[derive(Debug)]
+pub enum AcceptedIssuance {
+    Issued {
+        mdocs: Vec<MdocCopies>,
+        refresh: Option<Box<IssuanceRefresh>>,
+        notification: Option<Box<IssuanceNotification>>,
+    },
+    Deferred(Box<DeferredIssuance>),
+}
+
+/// Everything needed to notify the issuer about the mdocs it issued using [`IssuanceSession::notify_issuance()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuanceNotification {
+    notification_id: String,
+    notification_endpoint: Url,
+    session_state: IssuanceState,
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl IssuanceNotification {
+    pub fn new_mock(notification_id: String) -> Self {
+        Self {
+            notification_id,
+            notification_endpoint: "https://issuer.example.com/notification".parse().unwrap(),
+            session_state: DeferredIssuance::new_mock(String::new(), vec![]).session_state,
+        }
+    }
+}
+
+/// An Authorization Code Flow started using [`IssuanceSession::start_authorization()`]. When the authorization server
+/// redirects the user back to the wallet, the redirect URI can be converted into a [`TokenRequest`] with which
+/// issuance is started using [`IssuanceSession::start_issuance()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuanceAuthorization {
+    credential_issuer: BaseUrl,
+    client_id: String,
+    redirect_uri: Url,
+    state: String,
+    code_verifier: String,
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl IssuanceAuthorization {
+    pub fn new_mock(credential_issuer: BaseUrl, redirect_uri: Url, state: String) -> Self {
+        Self {
+            credential_issuer,
+            client_id: NL_WALLET_CLIENT_ID.to_string(),
+            redirect_uri,
+            state,
+            code_verifier: "code_verifier".to_string(),
+        }
+    }
+}
+
+impl IssuanceAuthorization {
+    pub fn credential_issuer(&self) -> &BaseUrl {
+        &self.credential_issuer
+    }
+
+    /// Create a [`TokenRequest`] from the authorization code contained in the redirect URI that the authorization
+    /// server sent the user back to.
+    pub fn into_token_request(self, received_redirect_uri: &Url) -> Result<TokenRequest, IssuanceSessionError> {
+        if !received_redirect_uri.as_str().starts_with(self.redirect_uri.as_str()) {
+            return Err(IssuanceSessionError::RedirectUriMismatch);
+        }
+
+        let query = received_redirect_uri.query().ok_or(IssuanceSessionError::NoAuthCode)?;
+
+        // First see if we received an error
+        if received_redirect_uri.query_pairs().any(|(key, _)| key == "error") {
+            let error_response: ErrorResponse<AuthorizationErrorCode> = serde_urlencoded::from_str(query)?;
+            return Err(IssuanceSessionError::AuthorizationResponse(error_response));
+        }
+
+        let auth_response: AuthorizationResponse = serde_urlencoded::from_str(query)?;
+        if auth_response.state.as_ref() != Some(&self.state) {
+            return Err(IssuanceSessionError::StateTokenMismatch);
+        }
+
+        let token_request = TokenRequest {
+            grant_type: TokenRequestGrantType::AuthorizationCode {
+                code: auth_response.code.into(),
+            },
+            code_verifier: Some(self.code_verifier),
+            client_id: Some(self.client_id),
+            redirect_uri: Some(self.redirect_uri),
+        };
+        Ok(token_request)
+    }
+}
+
+/// An issuance session of which the issuer deferred issuance. This contains everything needed to retrieve the mdocs
+/// later using [`IssuanceSession::accept_deferred_issuance()`], and is meant to be persisted by the wallet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredIssuance {
+    transaction_id: String,
+    session_state: IssuanceState,
+    /// For each mdoc copy to be issued, the public key and the identifier of its private key.
+    keys: Vec<(VerifyingKey, String)>,
+}
+
+impl DeferredIssuance {
+    pub fn transaction_id(&self) -> &str {
+        &self.transaction_id
+    }
+
+    pub fn attestation_previews(&self) -> &[AttestationPreview] {
+        &self.session_state.attestation_previews
+    }
+
+    pub fn issuer_url(&self) -> &BaseUrl {
+        &self.session_state.issuer_url
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl DeferredIssuance {
+    pub fn new_mock(transaction_id: String, attestation_previews: Vec<AttestationPreview>) -> Self {
+        Self {
+            transaction_id,
+            session_state: IssuanceState {
+                access_token: "access_token".to_string().into(),
+                c_nonce: "c_nonce".to_string(),
+                attestation_previews,
+                issuer_url: "https://issuer.example.com".parse().unwrap(),
+                dpop_private_key: SigningKey::random(&mut OsRng).into(),
+                dpop_nonce: None,
+                refresh_token: None,
+            },
+            keys: vec![],
+        }
+    }
+}
+
+/// Everything needed to refresh issued mdocs using [`IssuanceSession::refresh_issuance()`], meant to be persisted by
+/// the wallet. The refresh token is bound to the DPoP key that was used during issuance.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IssuanceRefresh {
+    refresh_token: RefreshToken,
+    issuer_url: BaseUrl,
+    dpop_private_key: DerSigningKey,
+    /// For each mdoc copy, the public key and the identifier of its private key.
+    keys: Vec<(VerifyingKey, String)>,
+    /// As the issuer keeps the session while the mdocs can be refreshed, this allows the wallet to notify the issuer
+    /// about the mdocs later on, e.g. when deleting them. This is replaced along with the refreshed mdocs.
+    #[serde(default)]
+    notification: Option<IssuanceNotification>,
+}
+
+impl IssuanceRefresh {
+    /// The identifiers of the private keys of the mdoc copies that can be refreshed.
+    pub fn key_identifiers(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|(_, key_id)| key_id.as_str())
+    }
+
+    pub fn issuer_url(&self) -> &BaseUrl {
+        &self.issuer_url
+    }
+
+    /// What is needed to notify the issuer about the mdocs that were last issued in the session, if it supports that.
+    pub fn notification(&self) -> Option<&IssuanceNotification> {
+        self.notification.as_ref()
+    }
+}
+
+impl Debug for IssuanceRefresh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IssuanceRefresh")
+            .field("issuer_url", &self.issuer_url)
+            .field("keys", &self.keys)
+            .finish_non_exhaustive() // don't show refresh_token and dpop_private_key
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl IssuanceRefresh {
+    pub fn new_mock(keys: Vec<(VerifyingKey, String)>) -> Self {
+        Self {
+            refresh_token: "refresh_token".to_string().into(),
+            issuer_url: "https://issuer.example.com".parse().unwrap(),
+            dpop_private_key: SigningKey::random(&mut OsRng).into(),
+            keys,
+            notification: Some(IssuanceNotification::new_mock("notification_id".to_string())),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct HttpIssuanceSession<H = HttpVcMessageClient> {
     message_client: H,
+    issuer_metadata: IssuerData,
     session_state: IssuanceState,
 }
 
 /// Contract for sending OpenID4VCI protocol messages.
 #[cfg_attr(test, mockall::automock)]
 pub trait VcMessageClient {
+    /// Whether Credential Issuer metadata must contain a verifiable `signed_metadata` JWT.
+    fn signed_metadata_required(&self) -> bool;
+
     async fn discover_metadata(&self, url: &BaseUrl) -> Result<IssuerMetadata, IssuanceSessionError>;
     async fn discover_oauth_metadata(&self, url: &BaseUrl) -> Result<oidc::Config, IssuanceSessionError>;
 
+    async fn push_authorization_request(
+        &self,
+        url: &Url,
+        authorization_request: &AuthorizationRequest,
+    ) -> Result<PushedAuthorizationResponse, IssuanceSessionError>;
+
     async fn request_token(
         &self,
         url: &Url,
@@ -150,7 +465,23 @@ pub trait VcMessageClient {
         credential_requests: &CredentialRequests,
         dpop_header: &str,
         access_token_header: &str,
-    ) -> Result<CredentialResponses, IssuanceSessionError>;
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError>;
+
+    async fn request_deferred_credentials(
+        &self,
+        url: &Url,
+        deferred_request: &DeferredCredentialRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError>;
+
+    async fn notify(
+        &self,
+        url: &Url,
+        notification_request: &NotificationRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<(), IssuanceSessionError>;
 
     async fn reject(&self, url: &Url, dpop_header: &str, access_token_header: &str)
         -> Result<(), IssuanceSessionError>;
@@ -158,15 +489,105 @@ pub trait VcMessageClient {
 
 pub struct HttpVcMessageClient {
     http_client: reqwest::Client,
+    signed_metadata_required: bool,
+    wallet_attestation: Option<WalletAttestationWithPop>,
 }
 
 impl From<reqwest::Client> for HttpVcMessageClient {
     fn from(http_client: reqwest::Client) -> Self {
-        Self { http_client }
+        Self::new(http_client, false)
     }
 }
 
+impl HttpVcMessageClient {
+    pub fn new(http_client: reqwest::Client, signed_metadata_required: bool) -> Self {
+        Self {
+            http_client,
+            signed_metadata_required,
+            wallet_attestation: None,
+        }
+    }
+
+    /// Include the wallet attestation in the token and credential requests to the issuer. Its PoP must have been
+    /// created for the issuer with which this client is used.
+    pub fn with_wallet_attestation(mut self, wallet_attestation: WalletAttestationWithPop) -> Self {
+        self.wallet_attestation = Some(wallet_attestation);
+        self
+    }
+
+    fn with_wallet_attestation_headers(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.wallet_attestation {
+            Some(wallet_attestation) => request
+                .header(CLIENT_ATTESTATION_HEADER_NAME, &wallet_attestation.attestation.0)
+                .header(CLIENT_ATTESTATION_POP_HEADER_NAME, &wallet_attestation.pop.0),
+            None => request,
+        }
+    }
+
+    async fn post_credential_request(
+        &self,
+        url: &Url,
+        body: &impl Serialize,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError> {
+        let request = self
+            .http_client
+            .post(url.as_ref())
+            .header(DPOP_HEADER_NAME, dpop_header)
+            .header(AUTHORIZATION, access_token_header);
+
+        self.with_wallet_attestation_headers(request)
+            .json(body)
+            .send()
+            .map_err(IssuanceSessionError::from)
+            .and_then(|response| async {
+                // If the HTTP response code is 4xx or 5xx, parse the JSON as an error
+                let status = response.status();
+                if status.is_client_error() || status.is_server_error() {
+                    let challenge = attestation_challenge(&response)?;
+                    let error = response.json::<ErrorResponse<CredentialErrorCode>>().await?;
+                    match (error.error, challenge) {
+                        (CredentialErrorCode::UseAttestationChallenge, Some(challenge)) => {
+                            Err(IssuanceSessionError::AttestationChallenge(challenge))
+                        }
+                        _ => Err(IssuanceSessionError::CredentialRequest(error)),
+                    }
+                } else {
+                    // An encrypted response is sent as a JWE with content type `application/jwt`.
+                    let encrypted = response
+                        .headers()
+                        .get(CONTENT_TYPE)
+                        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/jwt"));
+                    let credential_responses = if encrypted {
+                        MaybeEncrypted::Encrypted(response.text().await?)
+                    } else {
+                        MaybeEncrypted::Plain(response.json().await?)
+                    };
+                    Ok(credential_responses)
+                }
+            })
+            .await
+    }
+}
+
+/// Read the challenge for the wallet attestation PoP that the issuer sent along with its response, if any.
+fn attestation_challenge(response: &reqwest::Response) -> Result<Option<String>, IssuanceSessionError> {
+    let challenge = response
+        .headers()
+        .get(CLIENT_ATTESTATION_CHALLENGE_HEADER_NAME)
+        .map(|val| val.to_str())
+        .transpose()?
+        .map(str::to_string);
+
+    Ok(challenge)
+}
+
 impl VcMessageClient for HttpVcMessageClient {
+    fn signed_metadata_required(&self) -> bool {
+        self.signed_metadata_required
+    }
+
     async fn discover_metadata(&self, url: &BaseUrl) -> Result<IssuerMetadata, IssuanceSessionError> {
         let metadata = IssuerMetadata::discover(&self.http_client, url)
             .await
@@ -187,15 +608,43 @@ impl VcMessageClient for HttpVcMessageClient {
         Ok(metadata)
     }
 
+    async fn push_authorization_request(
+        &self,
+        url: &Url,
+        authorization_request: &AuthorizationRequest,
+    ) -> Result<PushedAuthorizationResponse, IssuanceSessionError> {
+        let request = self.http_client.post(url.as_ref());
+
+        self.with_wallet_attestation_headers(request)
+            .form(authorization_request)
+            .send()
+            .map_err(IssuanceSessionError::from)
+            .and_then(|response| async {
+                // If the HTTP response code is 4xx or 5xx, parse the JSON as an error
+                let status = response.status();
+                if status.is_client_error() || status.is_server_error() {
+                    let error = response.json::<ErrorResponse<AuthorizationErrorCode>>().await?;
+                    Err(IssuanceSessionError::PushedAuthorizationRequest(error))
+                } else {
+                    let par_response = response.json().await?;
+                    Ok(par_response)
+                }
+            })
+            .await
+    }
+
     async fn request_token(
         &self,
         url: &Url,
         token_request: &TokenRequest,
         dpop_header: &Dpop,
     ) -> Result<(TokenResponseWithPreviews, Option<String>), IssuanceSessionError> {
-        self.http_client
+        let request = self
+            .http_client
             .post(url.as_ref())
-            .header(DPOP_HEADER_NAME, dpop_header.as_ref())
+            .header(DPOP_HEADER_NAME, dpop_header.as_ref());
+
+        self.with_wallet_attestation_headers(request)
             .form(&token_request)
             .send()
             .map_err(IssuanceSessionError::from)
@@ -203,8 +652,14 @@ impl VcMessageClient for HttpVcMessageClient {
                 // If the HTTP response code is 4xx or 5xx, parse the JSON as an error
                 let status = response.status();
                 if status.is_client_error() || status.is_server_error() {
+                    let challenge = attestation_challenge(&response)?;
                     let error = response.json::<ErrorResponse<TokenErrorCode>>().await?;
-                    Err(IssuanceSessionError::TokenRequest(error))
+                    match (error.error, challenge) {
+                        (TokenErrorCode::UseAttestationChallenge, Some(challenge)) => {
+                            Err(IssuanceSessionError::AttestationChallenge(challenge))
+                        }
+                        _ => Err(IssuanceSessionError::TokenRequest(error)),
+                    }
                 } else {
                     let dpop_nonce = response
                         .headers()
@@ -225,23 +680,44 @@ impl VcMessageClient for HttpVcMessageClient {
         credential_requests: &CredentialRequests,
         dpop_header: &str,
         access_token_header: &str,
-    ) -> Result<CredentialResponses, IssuanceSessionError> {
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError> {
+        self.post_credential_request(url, credential_requests, dpop_header, access_token_header)
+            .await
+    }
+
+    async fn request_deferred_credentials(
+        &self,
+        url: &Url,
+        deferred_request: &DeferredCredentialRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError> {
+        self.post_credential_request(url, deferred_request, dpop_header, access_token_header)
+            .await
+    }
+
+    async fn notify(
+        &self,
+        url: &Url,
+        notification_request: &NotificationRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<(), IssuanceSessionError> {
         self.http_client
             .post(url.as_ref())
             .header(DPOP_HEADER_NAME, dpop_header)
             .header(AUTHORIZATION, access_token_header)
-            .json(credential_requests)
+            .json(notification_request)
             .send()
             .map_err(IssuanceSessionError::from)
             .and_then(|response| async {
                 // If the HTTP response code is 4xx or 5xx, parse the JSON as an error
                 let status = response.status();
                 if status.is_client_error() || status.is_server_error() {
-                    let error = response.json::<ErrorResponse<CredentialErrorCode>>().await?;
-                    Err(IssuanceSessionError::CredentialRequest(error))
+                    let error = response.json::<ErrorResponse<NotificationErrorCode>>().await?;
+                    Err(IssuanceSessionError::NotificationRequest(error))
                 } else {
-                    let credential_responses = response.json().await?;
-                    Ok(credential_responses)
+                    Ok(())
                 }
             })
             .await
@@ -274,13 +750,16 @@ impl VcMessageClient for HttpVcMessageClient {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 struct IssuanceState {
     access_token: AccessToken,
     c_nonce: String,
     attestation_previews: Vec<AttestationPreview>,
     issuer_url: BaseUrl,
-    dpop_private_key: SigningKey,
+    dpop_private_key: DerSigningKey,
     dpop_nonce: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<RefreshToken>,
 }
 
 impl Debug for IssuanceState {
@@ -291,125 +770,293 @@ impl Debug for IssuanceState {
             .field("attestation_previews", &self.attestation_previews)
             .field("issuer_url", &self.issuer_url)
             .field("dpop_nonce", &self.dpop_nonce)
-            .finish_non_exhaustive() // don't show dpop_private_key
+            .finish_non_exhaustive() // don't show dpop_private_key and refresh_token
     }
 }
 
 impl<H: VcMessageClient> HttpIssuanceSession<H> {
-    /// Discover the token endpoint from the OAuth server metadata.
-    async fn discover_token_endpoint(message_client: &H, base_url: &BaseUrl) -> Result<Url, IssuanceSessionError> {
-        let issuer_metadata = message_client.discover_metadata(base_url).await?;
+    /// Discover the Credential Issuer metadata and verify its `signed_metadata` JWT, if present, against the trust
+    /// anchors. Returns the metadata in which the signed fields take precedence over the unsigned ones.
+    async fn discover_issuer_metadata(
+        message_client: &H,
+        base_url: &BaseUrl,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<IssuerData, IssuanceSessionError> {
+        let issuer_metadata = message_client.discover_metadata(base_url).await?.verify(
+            base_url,
+            trust_anchors,
+            message_client.signed_metadata_required(),
+            &TimeGenerator,
+        )?;
+        Ok(issuer_metadata)
+    }
 
+    /// Discover the OAuth server metadata of the authorization server of the issuer.
+    async fn discover_authorization_server(
+        message_client: &H,
+        issuer_metadata: &IssuerData,
+    ) -> Result<oidc::Config, IssuanceSessionError> {
         // The issuer may announce multiple OAuth authorization servers the wallet may use. Which one the wallet
         // uses is left up to the wallet. We just take the first one.
         // authorization_servers() always returns a non-empty vec so the unwrap() is safe.
-        let authorization_servers = &issuer_metadata.issuer_config.authorization_servers();
+        let authorization_servers = &issuer_metadata.authorization_servers();
         let oauth_server = authorization_servers.first().unwrap();
-        let oauth_metadata = message_client.discover_oauth_metadata(oauth_server).await?;
+        message_client.discover_oauth_metadata(oauth_server).await
+    }
+
+    /// Discover the token endpoint from the OAuth server metadata.
+    async fn discover_token_endpoint(
+        message_client: &H,
+        issuer_metadata: &IssuerData,
+    ) -> Result<Url, IssuanceSessionError> {
+        let oauth_metadata = Self::discover_authorization_server(message_client, issuer_metadata).await?;
 
         let token_endpoint = oauth_metadata.token_endpoint.clone();
         Ok(token_endpoint)
     }
 
-    /// Discover the batch credential endpoint from the Credential Issuer metadata.
-    /// This function returns an `Option` because the batch credential is optional.
-    async fn discover_batch_credential_endpoint(
-        message_client: &H,
-        base_url: &BaseUrl,
-    ) -> Result<Option<Url>, IssuanceSessionError> {
-        let url = message_client
-            .discover_metadata(base_url)
-            .await?
-            .issuer_config
+    /// Get the batch credential endpoint from the Credential Issuer metadata.
+    fn batch_credential_endpoint(issuer_metadata: &IssuerData) -> Result<Url, IssuanceSessionError> {
+        issuer_metadata
             .batch_credential_endpoint
-            .map(|url| url.as_ref().clone());
-        Ok(url)
+            .as_ref()
+            .map(|url| url.as_ref().clone())
+            .ok_or(IssuanceSessionError::NoBatchCredentialEndpoint)
     }
-}
 
-impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
-    async fn start_issuance(
-        message_client: H,
-        base_url: BaseUrl,
-        token_request: TokenRequest,
+    /// Generate an ephemeral key to which the issuer should encrypt its credential responses, if the issuer supports
+    /// doing so using ECDH-ES and A256GCM.
+    fn response_encryption(
+        issuer_metadata: &IssuerData,
+    ) -> Result<Option<(EcKeyPair, ResponseEncryption)>, IssuanceSessionError> {
+        let supported = &issuer_metadata.credential_response_encryption;
+        if !supported.alg_values_supported.contains(&VpAlgValues::EcdhEs)
+            || !supported.enc_values_supported.contains(&VpEncValues::A256GCM)
+        {
+            return Ok(None);
+        }
+
+        let encryption = ResponseEncryption::new_ephemeral(VpEncValues::A256GCM)?;
+        Ok(Some(encryption))
+    }
+
+    /// Exchange the refresh token for a new access token, returning the Credential Issuer metadata and the state of
+    /// the refreshed session.
+    async fn exchange_refresh_token(
+        message_client: &H,
+        refresh: &IssuanceRefresh,
         trust_anchors: &[TrustAnchor<'_>],
-    ) -> Result<(Self, Vec<AttestationPreview>), IssuanceSessionError> {
-        let token_endpoint = Self::discover_token_endpoint(&message_client, &base_url).await?;
+    ) -> Result<(IssuerData, IssuanceState), IssuanceSessionError> {
+        let issuer_metadata =
+            Self::discover_issuer_metadata(message_client, &refresh.issuer_url, trust_anchors).await?;
+        let token_endpoint = Self::discover_token_endpoint(message_client, &issuer_metadata).await?;
 
-        let dpop_private_key = SigningKey::random(&mut OsRng);
-        let dpop_header = Dpop::new(&dpop_private_key, token_endpoint.clone(), Method::POST, None, None).await?;
+        // The refresh token is bound to the DPoP key that we used during issuance, so we have to use that key again.
+        let dpop_header = Dpop::new(
+            &refresh.dpop_private_key.0,
+            token_endpoint.clone(),
+            Method::POST,
+            None,
+            None,
+        )
+        .await?;
+        let token_request = TokenRequest {
+            grant_type: TokenRequestGrantType::RefreshToken {
+                refresh_token: refresh.refresh_token.clone(),
+            },
+            code_verifier: None,
+            client_id: Some(NL_WALLET_CLIENT_ID.to_string()),
+            redirect_uri: None,
+        };
 
         let (token_response, dpop_nonce) = message_client
             .request_token(&token_endpoint, &token_request, &dpop_header)
             .await?;
 
-        // Verify the issuer certificates that the issuer presents for each attestation to be issued.
-        // NB: this only proves the authenticity of the data inside the certificates (the [`IssuerRegistration`]s),
-        // but does not authenticate the issuer that presents them.
-        // Anyone that has ever seen these certificates (such as other wallets that received them during issuance)
-        // could present them here in the protocol without needing the corresponding issuer private key.
-        // This is not a problem, because at the end of the issuance protocol each mdoc is verified against the
-        // corresponding certificate in the attestation preview, which implicitly authenticates the issuer because
-        // only it could have produced an mdoc against that certificate.
-        token_response
-            .attestation_previews
+        let session_state = IssuanceState::from_token_response(
+            token_response,
+            dpop_nonce,
+            refresh.issuer_url.clone(),
+            refresh.dpop_private_key.clone(),
+            trust_anchors,
+        )?;
+
+        Ok((issuer_metadata, session_state))
+    }
+
+    /// Send the credential requests of a refreshed session to the batch credential endpoint.
+    async fn request_refreshed_credentials(
+        message_client: &H,
+        issuer_metadata: &IssuerData,
+        session_state: &IssuanceState,
+        credential_requests: Vec<CredentialRequest>,
+    ) -> Result<CredentialResponses, IssuanceSessionError> {
+        let url = Self::batch_credential_endpoint(issuer_metadata)?;
+        let (dpop_header, access_token_header) = session_state.auth_headers(url.clone(), Method::POST).await?;
+        let encryption = Self::response_encryption(issuer_metadata)?;
+
+        let responses = message_client
+            .request_credentials(
+                &url,
+                &CredentialRequests {
+                    // This `.unwrap()` is safe as long as the received
+                    // `TokenResponseWithPreviews.attestation_previews` is not empty.
+                    credential_requests: credential_requests.try_into().unwrap(),
+                    credential_response_encryption: encryption.as_ref().map(|(_, encryption)| encryption.clone()),
+                },
+                &dpop_header,
+                &access_token_header,
+            )
+            .await?
+            .into_inner(
+                encryption
+                    .as_ref()
+                    .map(|(private_key, encryption)| (encryption, private_key)),
+            )?;
+
+        Ok(responses)
+    }
+
+    /// Get the deferred credential endpoint from the Credential Issuer metadata.
+    fn deferred_credential_endpoint(issuer_metadata: &IssuerData) -> Result<Url, IssuanceSessionError> {
+        issuer_metadata
+            .deferred_credential_endpoint
             .as_ref()
+            .map(|url| url.as_ref().clone())
+            .ok_or(IssuanceSessionError::NoDeferredCredentialEndpoint)
+    }
+}
+
+impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
+    async fn start_authorization(
+        message_client: H,
+        credential_issuer: BaseUrl,
+        credential_configuration_ids: &[String],
+        issuer_state: Option<String>,
+        redirect_uri: Url,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<(IssuanceAuthorization, Url), IssuanceSessionError> {
+        let issuer_metadata =
+            Self::discover_issuer_metadata(&message_client, &credential_issuer, trust_anchors).await?;
+        let oauth_metadata = Self::discover_authorization_server(&message_client, &issuer_metadata).await?;
+        let par_endpoint = oauth_metadata
+            .pushed_authorization_request_endpoint
+            .as_ref()
+            .ok_or(IssuanceSessionError::NoPushedAuthorizationRequestEndpoint)?;
+
+        // Request authorization for each of the credential configurations, identified by their format-specific data.
+        let authorization_details = credential_configuration_ids
             .iter()
-            .try_for_each(|preview| {
-                let issuer: &Certificate = preview.as_ref();
-                issuer.verify(CertificateUsage::Mdl, &[], &TimeGenerator, trust_anchors)
-            })?;
+            .map(|id| {
+                match issuer_metadata
+                    .credential_configurations_supported
+                    .get(id)
+                    .map(|credential_metadata| &credential_metadata.format)
+                {
+                    Some(CredentialFormat::MsoMdoc { doctype, .. }) => {
+                        Ok(AuthorizationDetails::new(AuthorizationDetailsFormatData::MsoMdoc {
+                            doctype: doctype.clone(),
+                        }))
+                    }
+                    _ => Err(IssuanceSessionError::UnsupportedCredentialConfiguration(id.clone())),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pkce_pair = S256PkcePair::generate();
+        let state = BASE64_URL_SAFE_NO_PAD.encode(random_bytes(16));
+
+        let authorization_request = AuthorizationRequest {
+            response_type: ResponseType::Code.into(),
+            client_id: NL_WALLET_CLIENT_ID.to_string(),
+            redirect_uri: Some(redirect_uri.clone()),
+            state: Some(state.clone()),
+            authorization_details: Some(authorization_details),
+            issuer_state,
+            request_uri: None,
+            code_challenge: Some(PkceCodeChallenge::S256 {
+                code_challenge: pkce_pair.code_challenge().to_string(),
+            }),
+            scope: None,
+            nonce: None,
+            response_mode: None,
+        };
+        let par_response = message_client
+            .push_authorization_request(par_endpoint, &authorization_request)
+            .await?;
+
+        // Having pushed the authorization request, the wallet sends only its client ID and the request URI
+        // to the authorization endpoint, see https://www.rfc-editor.org/rfc/rfc9126.html#section-4.
+        let mut authorization_url = oauth_metadata.authorization_endpoint;
+        authorization_url
+            .query_pairs_mut()
+            .append_pair("client_id", NL_WALLET_CLIENT_ID)
+            .append_pair("request_uri", &par_response.request_uri);
+
+        let authorization = IssuanceAuthorization {
+            credential_issuer,
+            client_id: NL_WALLET_CLIENT_ID.to_string(),
+            redirect_uri,
+            state,
+            code_verifier: pkce_pair.into_code_verifier(),
+        };
+        Ok((authorization, authorization_url))
+    }
+
+    async fn start_issuance(
+        message_client: H,
+        base_url: BaseUrl,
+        token_request: TokenRequest,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<(Self, Vec<AttestationPreview>), IssuanceSessionError> {
+        let issuer_metadata = Self::discover_issuer_metadata(&message_client, &base_url, trust_anchors).await?;
+        let token_endpoint = Self::discover_token_endpoint(&message_client, &issuer_metadata).await?;
 
-        let attestation_previews = token_response.attestation_previews.into_inner();
+        let dpop_private_key = SigningKey::random(&mut OsRng);
+        let dpop_header = Dpop::new(&dpop_private_key, token_endpoint.clone(), Method::POST, None, None).await?;
 
-        let session_state = IssuanceState {
-            access_token: token_response.token_response.access_token,
-            c_nonce: token_response
-                .token_response
-                .c_nonce
-                .ok_or(IssuanceSessionError::MissingNonce)?,
-            attestation_previews: attestation_previews.clone(),
-            issuer_url: base_url,
-            dpop_private_key,
+        let (token_response, dpop_nonce) = message_client
+            .request_token(&token_endpoint, &token_request, &dpop_header)
+            .await?;
+
+        let session_state = IssuanceState::from_token_response(
+            token_response,
             dpop_nonce,
-        };
+            base_url,
+            dpop_private_key.into(),
+            trust_anchors,
+        )?;
+        let attestation_previews = session_state.attestation_previews.clone();
 
         let issuance_client = Self {
             message_client,
+            issuer_metadata,
             session_state,
         };
         Ok((issuance_client, attestation_previews))
     }
 
+    fn credential_metadata(&self) -> HashMap<String, CredentialMetadata> {
+        self.issuer_metadata.credential_configurations_supported.clone()
+    }
+
     async fn accept_issuance<K: MdocEcdsaKey>(
         &self,
         trust_anchors: &[TrustAnchor<'_>],
         key_factory: impl KeyFactory<Key = K>,
         credential_issuer_identifier: BaseUrl,
-    ) -> Result<Vec<MdocCopies>, IssuanceSessionError> {
+    ) -> Result<AcceptedIssuance, IssuanceSessionError> {
         // The OpenID4VCI `/batch_credential` endpoints supports issuance of multiple attestations, but the protocol
         // has no support (yet) for issuance of multiple copies of multiple attestations.
         // We implement this below by simply flattening the relevant nested iterators when communicating with the
         // issuer.
 
-        let doctypes = self
-            .session_state
-            .attestation_previews
-            .iter()
-            .flat_map(|preview| {
-                itertools::repeat_n(
-                    match preview {
-                        AttestationPreview::MsoMdoc { unsigned_mdoc, .. } => unsigned_mdoc.doc_type.clone(),
-                    },
-                    preview.copy_count().into(),
-                )
-            })
-            .collect_vec();
+        let doctypes = self.session_state.copy_doctypes();
 
         // Generate the PoPs to be sent to the issuer, and the private keys with which they were generated
         // (i.e., the private key of the future mdoc).
         // If N is the total amount of copies of attestations to be issued, then this returns N key/proof pairs.
-        let keys_and_proofs = CredentialRequestProof::new_multiple(
+        let (keys_and_proofs, key_attestation) = CredentialRequestProof::new_multiple(
             self.session_state.c_nonce.clone(),
             NL_WALLET_CLIENT_ID.to_string(),
             credential_issuer_identifier,
@@ -417,6 +1064,7 @@ impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
             key_factory,
         )
         .await?;
+        let key_attestation = &key_attestation;
 
         // Split into N keys and N credential requests, so we can send the credential request proofs separately
         // to the issuer.
@@ -434,6 +1082,8 @@ impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
                         format: Format::MsoMdoc,
                         doctype: Some(doctype),
                         proof: Some(response),
+                        key_attestation: key_attestation.clone(),
+                        credential_response_encryption: None,
                     };
                     Ok::<_, IssuanceSessionError>(((pubkey, id), cred_request))
                 }),
@@ -442,10 +1092,9 @@ impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
         .into_iter()
         .unzip();
 
-        let url = Self::discover_batch_credential_endpoint(&self.message_client, &self.session_state.issuer_url)
-            .await?
-            .ok_or(IssuanceSessionError::NoBatchCredentialEndpoint)?;
+        let url = Self::batch_credential_endpoint(&self.issuer_metadata)?;
         let (dpop_header, access_token_header) = self.session_state.auth_headers(url.clone(), Method::POST).await?;
+        let encryption = Self::response_encryption(&self.issuer_metadata)?;
 
         let responses = self
             .message_client
@@ -455,26 +1104,299 @@ impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
                     // This `.unwrap()` is safe as long as the received
                     // `TokenResponseWithPreviews.attestation_previews` is not empty.
                     credential_requests: credential_requests.try_into().unwrap(),
+                    credential_response_encryption: encryption.as_ref().map(|(_, encryption)| encryption.clone()),
+                },
+                &dpop_header,
+                &access_token_header,
+            )
+            .await?
+            .into_inner(
+                encryption
+                    .as_ref()
+                    .map(|(private_key, encryption)| (encryption, private_key)),
+            )?;
+
+        // If the issuer deferred issuance, we keep everything that is needed to retrieve the mdocs later.
+        if let Some(transaction_id) = responses.deferred_transaction_id()? {
+            let deferred_issuance = DeferredIssuance {
+                transaction_id,
+                session_state: self.session_state.clone(),
+                keys: pubkeys,
+            };
+            return Ok(AcceptedIssuance::Deferred(Box::new(deferred_issuance)));
+        }
+
+        let notification = self.session_state.notification(&responses, &self.issuer_metadata);
+        let refresh = self
+            .session_state
+            .refresh(pubkeys.clone(), notification.as_ref())
+            .map(Box::new);
+        let notification = notification.map(Box::new);
+        let mdocs = responses.into_mdocs::<K>(pubkeys, &self.session_state.attestation_previews, trust_anchors)?;
+
+        Ok(AcceptedIssuance::Issued {
+            mdocs,
+            refresh,
+            notification,
+        })
+    }
+
+    async fn accept_deferred_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        deferred_issuance: DeferredIssuance,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<AcceptedIssuance, IssuanceSessionError> {
+        let session_state = &deferred_issuance.session_state;
+
+        let issuer_metadata =
+            Self::discover_issuer_metadata(&message_client, &session_state.issuer_url, trust_anchors).await?;
+        let url = Self::deferred_credential_endpoint(&issuer_metadata)?;
+        let (dpop_header, access_token_header) = session_state.auth_headers(url.clone(), Method::POST).await?;
+        let encryption = Self::response_encryption(&issuer_metadata)?;
+
+        let result = message_client
+            .request_deferred_credentials(
+                &url,
+                &DeferredCredentialRequest {
+                    transaction_id: deferred_issuance.transaction_id.clone(),
+                    credential_response_encryption: encryption.as_ref().map(|(_, encryption)| encryption.clone()),
+                },
+                &dpop_header,
+                &access_token_header,
+            )
+            .await;
+
+        // If the issuer is not done yet, the wallet has to try again later.
+        let responses = match result {
+            Err(IssuanceSessionError::CredentialRequest(ErrorResponse {
+                error: CredentialErrorCode::IssuancePending,
+                ..
+            })) => return Ok(AcceptedIssuance::Deferred(Box::new(deferred_issuance))),
+            result => result?.into_inner(
+                encryption
+                    .as_ref()
+                    .map(|(private_key, encryption)| (encryption, private_key)),
+            )?,
+        };
+
+        if responses.deferred_transaction_id()?.is_some() {
+            return Err(IssuanceSessionError::UnexpectedDeferredResponse);
+        }
+
+        let notification = deferred_issuance
+            .session_state
+            .notification(&responses, &issuer_metadata);
+        let refresh = deferred_issuance
+            .session_state
+            .refresh(deferred_issuance.keys.clone(), notification.as_ref())
+            .map(Box::new);
+        let notification = notification.map(Box::new);
+        let mdocs = responses.into_mdocs::<K>(
+            deferred_issuance.keys,
+            &deferred_issuance.session_state.attestation_previews,
+            trust_anchors,
+        )?;
+
+        Ok(AcceptedIssuance::Issued {
+            mdocs,
+            refresh,
+            notification,
+        })
+    }
+
+    async fn refresh_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        refresh: IssuanceRefresh,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError> {
+        let (issuer_metadata, session_state) =
+            Self::exchange_refresh_token(&message_client, &refresh, trust_anchors).await?;
+
+        // The refreshed mdocs are bound to the keys of the original mdocs, so the credential requests don't contain
+        // proofs of possession of new keys.
+        let credential_requests = session_state
+            .copy_doctypes()
+            .into_iter()
+            .map(|doctype| CredentialRequest {
+                format: Format::MsoMdoc,
+                doctype: Some(doctype),
+                proof: None,
+                key_attestation: None,
+                credential_response_encryption: None,
+            })
+            .collect_vec();
+
+        let responses =
+            Self::request_refreshed_credentials(&message_client, &issuer_metadata, &session_state, credential_requests)
+                .await?;
+
+        let notification = session_state.notification(&responses, &issuer_metadata);
+        let mdocs =
+            responses.into_mdocs::<K>(refresh.keys.clone(), &session_state.attestation_previews, trust_anchors)?;
+
+        // If the issuer did not hand out a new refresh token, the current one remains valid.
+        let refresh = session_state
+            .refresh(refresh.keys.clone(), notification.as_ref())
+            .unwrap_or(IssuanceRefresh {
+                notification,
+                ..refresh
+            });
+
+        Ok((mdocs, refresh))
+    }
+
+    async fn replenish_issuance<K: MdocEcdsaKey>(
+        message_client: H,
+        refresh: IssuanceRefresh,
+        trust_anchors: &[TrustAnchor<'_>],
+        key_factory: impl KeyFactory<Key = K>,
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError> {
+        let (issuer_metadata, session_state) =
+            Self::exchange_refresh_token(&message_client, &refresh, trust_anchors).await?;
+
+        // Prove possession of a new key for each of the copies, to which the issuer then binds the new copies.
+        let doctypes = session_state.copy_doctypes();
+        let (keys_and_proofs, key_attestation) = CredentialRequestProof::new_multiple(
+            session_state.c_nonce.clone(),
+            NL_WALLET_CLIENT_ID.to_string(),
+            refresh.issuer_url.clone(),
+            doctypes.len().try_into().unwrap(),
+            key_factory,
+        )
+        .await?;
+        let key_attestation = &key_attestation;
+
+        let (pubkeys, credential_requests): (Vec<_>, Vec<_>) = try_join_all(
+            keys_and_proofs
+                .into_iter()
+                .zip(doctypes)
+                .map(|((key, proof), doctype)| async move {
+                    let pubkey = key
+                        .verifying_key()
+                        .await
+                        .map_err(|e| IssuanceSessionError::VerifyingKeyFromPrivateKey(e.into()))?;
+                    let cred_request = CredentialRequest {
+                        format: Format::MsoMdoc,
+                        doctype: Some(doctype),
+                        proof: Some(proof),
+                        key_attestation: key_attestation.clone(),
+                        credential_response_encryption: None,
+                    };
+                    Ok::<_, IssuanceSessionError>(((pubkey, key.identifier().to_string()), cred_request))
+                }),
+        )
+        .await?
+        .into_iter()
+        .unzip();
+
+        let responses =
+            Self::request_refreshed_credentials(&message_client, &issuer_metadata, &session_state, credential_requests)
+                .await?;
+
+        let notification = session_state.notification(&responses, &issuer_metadata);
+        let mdocs = responses.into_mdocs::<K>(pubkeys.clone(), &session_state.attestation_previews, trust_anchors)?;
+
+        // From now on, refreshing applies to the new copies. If the issuer did not hand out a new refresh token, the
+        // current one remains valid.
+        let refresh = session_state
+            .refresh(pubkeys.clone(), notification.as_ref())
+            .unwrap_or(IssuanceRefresh {
+                keys: pubkeys,
+                notification,
+                ..refresh
+            });
+
+        Ok((mdocs, refresh))
+    }
+
+    async fn notify_issuance(
+        message_client: H,
+        notification: IssuanceNotification,
+        event: NotificationEvent,
+    ) -> Result<(), IssuanceSessionError> {
+        let session_state = &notification.session_state;
+
+        let url = notification.notification_endpoint;
+        let (dpop_header, access_token_header) = session_state.auth_headers(url.clone(), Method::POST).await?;
+
+        message_client
+            .notify(
+                &url,
+                &NotificationRequest {
+                    notification_id: notification.notification_id,
+                    event,
+                    event_description: None,
                 },
                 &dpop_header,
                 &access_token_header,
             )
+            .await
+    }
+
+    async fn reject_issuance(self) -> Result<(), IssuanceSessionError> {
+        let url = Self::batch_credential_endpoint(&self.issuer_metadata)?;
+        let (dpop_header, access_token_header) = self.session_state.auth_headers(url.clone(), Method::DELETE).await?;
+
+        self.message_client
+            .reject(&url, &dpop_header, &access_token_header)
             .await?;
 
+        Ok(())
+    }
+}
+
+impl CredentialResponses {
+    /// Returns the transaction ID if the issuer deferred issuance of all credentials, or `None` if it issued all of
+    /// them. Returns an error if the issuer deferred only some of them.
+    fn deferred_transaction_id(&self) -> Result<Option<String>, IssuanceSessionError> {
+        let transaction_ids = self
+            .credential_responses
+            .iter()
+            .map(|response| match response {
+                CredentialResponse::Deferred { transaction_id } => Some(transaction_id),
+                CredentialResponse::MsoMdoc { .. } => None,
+            })
+            .all_equal_value()
+            .map_err(|_| IssuanceSessionError::UnexpectedDeferredResponse)?;
+
+        Ok(transaction_ids.cloned())
+    }
+
+    /// Returns the notification ID with which the wallet can notify the issuer about the issued credentials. As the
+    /// wallet notifies the issuer about all of them at once, this is only returned if all credential responses contain
+    /// the same notification ID.
+    fn notification_id(&self) -> Option<&str> {
+        self.credential_responses
+            .iter()
+            .map(|response| match response {
+                CredentialResponse::MsoMdoc { notification_id, .. } => notification_id.as_deref(),
+                CredentialResponse::Deferred { .. } => None,
+            })
+            .all_equal_value()
+            .ok()
+            .flatten()
+    }
+
+    /// Convert the credential responses into mdocs, given the public keys and key identifiers of the mdocs in the
+    /// same order as the credential requests, and the attestation previews these were requested for.
+    fn into_mdocs<K: MdocEcdsaKey>(
+        self,
+        pubkeys: Vec<(VerifyingKey, String)>,
+        attestation_previews: &[AttestationPreview],
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<Vec<MdocCopies>, IssuanceSessionError> {
         // The server must have responded with enough credential responses, N, so that we have exactly enough responses
         // for all copies of all mdocs constructed below.
-        if responses.credential_responses.len() != pubkeys.len() {
+        if self.credential_responses.len() != pubkeys.len() {
             return Err(IssuanceSessionError::UnexpectedCredentialResponseCount {
-                found: responses.credential_responses.len(),
+                found: self.credential_responses.len(),
                 expected: pubkeys.len(),
             });
         }
 
-        let mut responses_and_pubkeys: VecDeque<_> = responses.credential_responses.into_iter().zip(pubkeys).collect();
+        let mut responses_and_pubkeys: VecDeque<_> = self.credential_responses.into_iter().zip(pubkeys).collect();
 
-        let mdocs = self
-            .session_state
-            .attestation_previews
+        let mdocs = attestation_previews
             .iter()
             .map(|preview| {
                 let copy_count: usize = preview.copy_count().into();
@@ -496,19 +1418,6 @@ impl<H: VcMessageClient> IssuanceSession<H> for HttpIssuanceSession<H> {
 
         Ok(mdocs)
     }
-
-    async fn reject_issuance(self) -> Result<(), IssuanceSessionError> {
-        let url = Self::discover_batch_credential_endpoint(&self.message_client, &self.session_state.issuer_url)
-            .await?
-            .ok_or(IssuanceSessionError::NoBatchCredentialEndpoint)?;
-        let (dpop_header, access_token_header) = self.session_state.auth_headers(url.clone(), Method::DELETE).await?;
-
-        self.message_client
-            .reject(&url, &dpop_header, &access_token_header)
-            .await?;
-
-        Ok(())
-    }
 }
 
 impl CredentialResponse {
@@ -521,7 +1430,8 @@ impl CredentialResponse {
         trust_anchors: &[TrustAnchor<'_>],
     ) -> Result<Mdoc, IssuanceSessionError> {
         let issuer_signed = match self {
-            CredentialResponse::MsoMdoc { credential } => credential.0,
+            CredentialResponse::MsoMdoc { credential, .. } => credential.0,
+            CredentialResponse::Deferred { .. } => return Err(IssuanceSessionError::UnexpectedDeferredResponse),
         };
 
         if issuer_signed
@@ -572,9 +1482,103 @@ impl CredentialResponse {
 }
 
 impl IssuanceState {
+    /// Construct the session state out of the response to a token request, after verifying the issuer certificates in
+    /// the attestation previews.
+    fn from_token_response(
+        token_response: TokenResponseWithPreviews,
+        dpop_nonce: Option<String>,
+        issuer_url: BaseUrl,
+        dpop_private_key: DerSigningKey,
+        trust_anchors: &[TrustAnchor<'_>],
+    ) -> Result<Self, IssuanceSessionError> {
+        // Verify the issuer certificates that the issuer presents for each attestation to be issued.
+        // NB: this only proves the authenticity of the data inside the certificates (the [`IssuerRegistration`]s),
+        // but does not authenticate the issuer that presents them.
+        // Anyone that has ever seen these certificates (such as other wallets that received them during issuance)
+        // could present them here in the protocol without needing the corresponding issuer private key.
+        // This is not a problem, because at the end of the issuance protocol each mdoc is verified against the
+        // corresponding certificate in the attestation preview, which implicitly authenticates the issuer because
+        // only it could have produced an mdoc against that certificate.
+        token_response
+            .attestation_previews
+            .as_ref()
+            .iter()
+            .try_for_each(|preview| {
+                let issuer: &Certificate = preview.as_ref();
+                issuer.verify(CertificateUsage::Mdl, &[], &TimeGenerator, trust_anchors)
+            })?;
+
+        let session_state = IssuanceState {
+            access_token: token_response.token_response.access_token,
+            c_nonce: token_response
+                .token_response
+                .c_nonce
+                .ok_or(IssuanceSessionError::MissingNonce)?,
+            attestation_previews: token_response.attestation_previews.into_inner(),
+            issuer_url,
+            dpop_private_key,
+            dpop_nonce,
+            refresh_token: token_response.token_response.refresh_token,
+        };
+
+        Ok(session_state)
+    }
+
+    /// The doctype of each mdoc copy to be issued, in the order in which they are to be requested.
+    fn copy_doctypes(&self) -> Vec<String> {
+        self.attestation_previews
+            .iter()
+            .flat_map(|preview| {
+                itertools::repeat_n(
+                    match preview {
+                        AttestationPreview::MsoMdoc { unsigned_mdoc, .. } => unsigned_mdoc.doc_type.clone(),
+                    },
+                    preview.copy_count().into(),
+                )
+            })
+            .collect_vec()
+    }
+
+    /// Returns what is needed to refresh the mdocs bound to the specified keys, if the issuer handed out a refresh
+    /// token.
+    fn refresh(
+        &self,
+        keys: Vec<(VerifyingKey, String)>,
+        notification: Option<&IssuanceNotification>,
+    ) -> Option<IssuanceRefresh> {
+        self.refresh_token.as_ref().map(|refresh_token| IssuanceRefresh {
+            refresh_token: refresh_token.clone(),
+            issuer_url: self.issuer_url.clone(),
+            dpop_private_key: self.dpop_private_key.clone(),
+            keys,
+            notification: notification.cloned(),
+        })
+    }
+
+    /// Returns what is needed to notify the issuer about the credentials in the responses, if it included a
+    /// notification ID in them and has a notification endpoint.
+    fn notification(
+        &self,
+        responses: &CredentialResponses,
+        issuer_metadata: &IssuerData,
+    ) -> Option<IssuanceNotification> {
+        let notification_endpoint = issuer_metadata.notification_endpoint.as_ref()?;
+
+        responses.notification_id().map(|notification_id| IssuanceNotification {
+            notification_id: notification_id.to_string(),
+            notification_endpoint: notification_endpoint.as_ref().clone(),
+            // Only the authorization is needed for notifying, so the notification does not keep the attributes of the
+            // issued attestations.
+            session_state: IssuanceState {
+                attestation_previews: vec![],
+                ..self.clone()
+            },
+        })
+    }
+
     async fn auth_headers(&self, url: Url, method: reqwest::Method) -> Result<(String, String), IssuanceSessionError> {
         let dpop_header = Dpop::new(
-            &self.dpop_private_key,
+            &self.dpop_private_key.0,
             url,
             method,
             Some(&self.access_token),
@@ -590,8 +1594,11 @@ impl IssuanceState {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use assert_matches::assert_matches;
     use serde_bytes::ByteBuf;
+    use serde_json::json;
 
     use nl_wallet_mdoc::{
         server_keys::KeyPair,
@@ -609,12 +1616,13 @@ mod tests {
         nonempty::NonEmpty,
     };
 
-    use crate::token::TokenResponse;
+    use crate::{metadata::CredentialMetadata, token::TokenResponse};
 
     use super::*;
 
     fn mock_openid_message_client() -> MockVcMessageClient {
         let mut mock_msg_client = MockVcMessageClient::new();
+        mock_msg_client.expect_signed_metadata_required().return_const(false);
         mock_msg_client
             .expect_discover_metadata()
             .returning(|url| Ok(IssuerMetadata::new_mock(url.clone())));
@@ -641,7 +1649,8 @@ mod tests {
             .await
             .unwrap();
         let credential_response = CredentialResponse::MsoMdoc {
-            credential: issuer_signed.into(),
+            credential: Box::new(issuer_signed.into()),
+            notification_id: None,
         };
 
         (credential_response, preview, ca.certificate().clone(), mdoc_public_key)
@@ -713,9 +1722,9 @@ mod tests {
             });
         mock_msg_client.expect_request_credentials().return_once(
             |_url, _credential_requests, _dpop_header, _access_token_header| {
-                Ok(CredentialResponses {
+                Ok(MaybeEncrypted::Plain(CredentialResponses {
                     credential_responses: vec![cred_response], // return one credential response
-                })
+                }))
             },
         );
 
@@ -747,6 +1756,36 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_credential_responses_deferred_transaction_id() {
+        let (cred_response, _, _, _) = create_credential_response().await;
+        let deferred_response = || CredentialResponse::Deferred {
+            transaction_id: "transaction_id".to_string(),
+        };
+
+        let responses = CredentialResponses {
+            credential_responses: vec![deferred_response(), deferred_response()],
+        };
+        assert_eq!(
+            responses.deferred_transaction_id().unwrap().as_deref(),
+            Some("transaction_id")
+        );
+
+        let responses = CredentialResponses {
+            credential_responses: vec![cred_response.clone()],
+        };
+        assert_eq!(responses.deferred_transaction_id().unwrap(), None);
+
+        // The issuer must not defer only some of the credentials.
+        let responses = CredentialResponses {
+            credential_responses: vec![cred_response, deferred_response()],
+        };
+        assert_matches!(
+            responses.deferred_transaction_id().unwrap_err(),
+            IssuanceSessionError::UnexpectedDeferredResponse
+        );
+    }
+
     #[tokio::test]
     async fn test_credential_response_into_mdoc() {
         let (credential_response, preview, ca_cert, mdoc_public_key) = create_credential_response().await;
@@ -787,8 +1826,11 @@ mod tests {
         // Converting a `CredentialResponse` into an `Mdoc` from a response
         // that contains insufficient random data should fail.
         let credential_response = match credential_response {
-            CredentialResponse::MsoMdoc { mut credential } => {
-                let CborBase64(ref mut credential_inner) = credential;
+            CredentialResponse::MsoMdoc {
+                mut credential,
+                notification_id,
+            } => {
+                let CborBase64(ref mut credential_inner) = *credential;
                 let name_spaces = credential_inner.name_spaces.as_mut().unwrap();
 
                 name_spaces.modify_first_attributes(|attributes| {
@@ -797,8 +1839,12 @@ mod tests {
                     first_item.random = ByteBuf::from(b"12345");
                 });
 
-                CredentialResponse::MsoMdoc { credential }
+                CredentialResponse::MsoMdoc {
+                    credential,
+                    notification_id,
+                }
             }
+            CredentialResponse::Deferred { .. } => unreachable!(),
         };
 
         let error = credential_response
@@ -892,4 +1938,142 @@ mod tests {
                 if missing.len() == 1 && unexpected.is_empty()
         )
     }
+
+    fn mock_authorization_message_client() -> MockVcMessageClient {
+        let mut mock_msg_client = MockVcMessageClient::new();
+        mock_msg_client.expect_signed_metadata_required().return_const(false);
+        mock_msg_client.expect_discover_metadata().returning(|url| {
+            let mut metadata = IssuerMetadata::new_mock(url.clone());
+            metadata.issuer_config.credential_configurations_supported.insert(
+                "pid".to_string(),
+                CredentialMetadata {
+                    format: CredentialFormat::MsoMdoc {
+                        doctype: "com.example.pid".to_string(),
+                        claims: HashMap::new(),
+                        order: None,
+                    },
+                    scope: None,
+                    cryptographic_binding_methods_supported: None,
+                    credential_signing_alg_values_supported: None,
+                    proof_types_supported: None,
+                    display: None,
+                },
+            );
+            Ok(metadata)
+        });
+        mock_msg_client
+            .expect_discover_oauth_metadata()
+            .returning(|url| Ok(oidc::Config::new_mock(url)));
+        mock_msg_client
+    }
+
+    #[tokio::test]
+    async fn test_start_authorization() {
+        let mut mock_msg_client = mock_authorization_message_client();
+        mock_msg_client
+            .expect_push_authorization_request()
+            .return_once(|url, authorization_request| {
+                assert_eq!(url.as_str(), "https://example.com/par");
+                assert_eq!(authorization_request.issuer_state.as_deref(), Some("issuer_state"));
+                assert_matches!(
+                    authorization_request.code_challenge,
+                    Some(PkceCodeChallenge::S256 { .. })
+                );
+                assert_eq!(
+                    serde_json::to_value(&authorization_request.authorization_details).unwrap(),
+                    json!([{"type": "openid_credential", "format": "mso_mdoc", "doctype": "com.example.pid"}])
+                );
+
+                Ok(PushedAuthorizationResponse {
+                    request_uri: "urn:ietf:params:oauth:request_uri:123".to_string(),
+                    expires_in: 60,
+                })
+            });
+
+        let redirect_uri: Url = "https://wallet.example.com/return".parse().unwrap();
+        let (authorization, authorization_url) = HttpIssuanceSession::start_authorization(
+            mock_msg_client,
+            "https://example.com".parse().unwrap(),
+            &["pid".to_string()],
+            Some("issuer_state".to_string()),
+            redirect_uri.clone(),
+            &[],
+        )
+        .await
+        .unwrap();
+
+        // The user should be sent to the authorization endpoint with only the client ID and the request URI.
+        assert_eq!(authorization_url.path(), "/authorize");
+        let query = authorization_url.query_pairs().collect::<HashMap<_, _>>();
+        assert_eq!(query.len(), 2);
+        assert_eq!(query["client_id"], NL_WALLET_CLIENT_ID);
+        assert_eq!(query["request_uri"], "urn:ietf:params:oauth:request_uri:123");
+
+        // The authorization code in the redirect URI should end up in the token request.
+        let mut received_redirect_uri = redirect_uri.clone();
+        received_redirect_uri
+            .query_pairs_mut()
+            .append_pair("code", "auth_code")
+            .append_pair("state", &authorization.state);
+        let token_request = authorization.into_token_request(&received_redirect_uri).unwrap();
+
+        assert_matches!(
+            token_request.grant_type,
+            TokenRequestGrantType::AuthorizationCode { code } if code.as_ref() == "auth_code"
+        );
+        assert!(token_request.code_verifier.is_some());
+        assert_eq!(token_request.redirect_uri, Some(redirect_uri));
+    }
+
+    #[tokio::test]
+    async fn test_start_authorization_unsupported_credential_configuration() {
+        let error = HttpIssuanceSession::start_authorization(
+            mock_authorization_message_client(),
+            "https://example.com".parse().unwrap(),
+            &["mdl".to_string()],
+            None,
+            "https://wallet.example.com/return".parse().unwrap(),
+            &[],
+        )
+        .await
+        .expect_err("starting authorization for unknown credential configuration should fail");
+
+        assert_matches!(error, IssuanceSessionError::UnsupportedCredentialConfiguration(id) if id == "mdl");
+    }
+
+    #[test]
+    fn test_issuance_authorization_into_token_request_error() {
+        let authorization = IssuanceAuthorization::new_mock(
+            "https://example.com".parse().unwrap(),
+            "https://wallet.example.com/return".parse().unwrap(),
+            "state".to_string(),
+        );
+
+        let into_token_request_error = |redirect_uri: &str| {
+            authorization
+                .clone()
+                .into_token_request(&redirect_uri.parse().unwrap())
+                .expect_err("converting redirect URI to token request should fail")
+        };
+
+        assert_matches!(
+            into_token_request_error("https://other.example.com/return?code=auth_code&state=state"),
+            IssuanceSessionError::RedirectUriMismatch
+        );
+        assert_matches!(
+            into_token_request_error("https://wallet.example.com/return"),
+            IssuanceSessionError::NoAuthCode
+        );
+        assert_matches!(
+            into_token_request_error("https://wallet.example.com/return?code=auth_code&state=other_state"),
+            IssuanceSessionError::StateTokenMismatch
+        );
+        assert_matches!(
+            into_token_request_error("https://wallet.example.com/return?error=access_denied&state=state"),
+            IssuanceSessionError::AuthorizationResponse(ErrorResponse {
+                error: AuthorizationErrorCode::AccessDenied,
+                ..
+            })
+        );
+    }
 }
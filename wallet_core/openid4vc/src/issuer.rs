@@ -1,13 +1,16 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use futures::future::try_join_all;
 use jsonwebtoken::{Algorithm, Validation};
 use p256::ecdsa::VerifyingKey;
 use reqwest::Method;
+use ring::constant_time;
 use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
 use tokio::task::JoinHandle;
 use tracing::info;
 
@@ -17,27 +20,52 @@ use nl_wallet_mdoc::{
     utils::{crypto::CryptoError, serialization::CborError},
     IssuerSigned,
 };
-use wallet_common::{jwt::EcdsaDecodingKey, nonempty::NonEmpty, urls::BaseUrl, utils::random_string};
+use wallet_common::{
+    generator::TimeGenerator,
+    jwt::{EcdsaDecodingKey, JwtError},
+    nonempty::NonEmpty,
+    urls::BaseUrl,
+    utils::{random_bytes, random_string, sha256},
+};
 
 use crate::{
     credential::{
         CredentialRequest, CredentialRequestProof, CredentialRequestProofJwtPayload, CredentialRequests,
-        CredentialResponse, CredentialResponses, OPENID4VCI_VC_POP_JWT_TYPE,
+        CredentialResponse, CredentialResponses, DeferredCredentialRequest, MaybeEncrypted, NotificationEvent,
+        NotificationRequest, ResponseEncryption, ResponseEncryptionError, OPENID4VCI_VC_POP_JWT_TYPE,
     },
     dpop::{Dpop, DpopError},
     jwt::{jwk_to_p256, JwkConversionError},
     metadata::{self, CredentialResponseEncryption, IssuerMetadata},
     oidc,
+    openid4vp::{VpAlgValues, VpEncValues},
     server_state::{
-        Expirable, HasProgress, Progress, SessionState, SessionStore, SessionStoreError, CLEANUP_INTERVAL_SECONDS,
+        Expirable, HasProgress, Progress, SessionState, SessionStateLabels, SessionStore, SessionStoreError,
+        CLEANUP_INTERVAL_SECONDS, COUNT_INTERVAL_SECONDS,
     },
     token::{
-        AccessToken, AttestationPreview, AuthorizationCode, TokenRequest, TokenRequestGrantType, TokenResponse,
-        TokenResponseWithPreviews, TokenType,
+        AccessToken, AttestationPreview, AuthorizationCode, RefreshToken, TokenRequest, TokenRequestGrantType,
+        TokenResponse, TokenResponseWithPreviews, TokenType,
     },
+    wallet_attestation::{verify_key_attestation, WalletAttestationError, WalletAttestationWithPop},
     Format,
 };
 
+/// The session type with which issuance sessions are labeled in the metrics.
+const METRICS_SESSION_TYPE: &str = "issuance";
+
+/// The number of times the wallet may send an incorrect Transaction Code before the session fails.
+pub const MAX_TX_CODE_ATTEMPTS: u8 = 3;
+
+/// The amount of inactivity after which a session whose issuance was deferred expires. This is much longer than the
+/// expiration of other sessions, since approval of the issuance may involve manual work by the issuer.
+pub const DEFERRED_SESSION_EXPIRATION: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// The amount of inactivity after which a session whose attestations may be refreshed is removed, along with its
+/// refresh token. This equals the usual validity of attestations, which the wallet refreshes shortly before they
+/// expire. The session only contains the subject of the attestations, not their attributes.
+pub const REFRESHABLE_SESSION_EXPIRATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
 // Errors are structured as follow in this module: the handler for a token request on the one hand, and the handlers for
 // the other endpoints on the other hand, have specific error types. (There is also a general error type included by
 // both of them for errors that can occur in all endpoints.) The reason for this split in the errors is because per the
@@ -67,6 +95,24 @@ pub enum TokenRequestError {
     UnsupportedTokenRequestType,
     #[error("failed to get attributes to be issued: {0}")]
     AttributeService(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    #[error("missing or incorrect transaction code")]
+    IncorrectTxCode,
+    #[error("invalid refresh token")]
+    InvalidRefreshToken,
+    #[error("wallet attestation rejected: {0}")]
+    WalletAttestation(#[source] WalletAttestationError),
+    #[error("wallet attestation PoP must contain the challenge provided by the issuer")]
+    UseAttestationChallenge(String),
+}
+
+impl TokenRequestError {
+    /// The challenge that the wallet has to include in its wallet attestation PoP, if this error requires it to.
+    pub fn attestation_challenge(&self) -> Option<&str> {
+        match self {
+            Self::UseAttestationChallenge(challenge) => Some(challenge),
+            _ => None,
+        }
+    }
 }
 
 /// Errors that can occur during handling of the (batch) credential request.
@@ -112,11 +158,101 @@ pub enum CredentialRequestError {
     DoctypeMismatch,
     #[error("missing credential request proof of possession")]
     MissingCredentialRequestPoP,
+    #[error("issuance is pending approval")]
+    IssuancePending,
+    #[error("unknown transaction ID")]
+    InvalidTransactionId,
+    #[error("received {found} credential requests, expected {expected}")]
+    UnexpectedCredentialRequestCount { found: usize, expected: usize },
+    #[error("invalid credential response encryption parameters: {0}")]
+    InvalidEncryptionParameters(#[source] ResponseEncryptionError),
+    #[error("failed to encrypt credential response: {0}")]
+    ResponseEncryption(#[source] ResponseEncryptionError),
+    #[error("wallet attestation rejected: {0}")]
+    WalletAttestation(#[source] WalletAttestationError),
+    #[error("wallet attestation PoP must contain the challenge provided by the issuer")]
+    UseAttestationChallenge(String),
+    #[error("failed to get attributes to be issued: {0}")]
+    AttributeService(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl CredentialRequestError {
+    /// The challenge that the wallet has to include in its wallet attestation PoP, if this error requires it to.
+    pub fn attestation_challenge(&self) -> Option<&str> {
+        match self {
+            Self::UseAttestationChallenge(challenge) => Some(challenge),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can occur during handling of the notification request.
+#[derive(Debug, thiserror::Error)]
+pub enum NotificationError {
+    #[error("issuance error: {0}")]
+    IssuanceError(#[from] IssuanceError),
+    #[error("unauthorized: incorrect access token")]
+    Unauthorized,
+    #[error("malformed access token")]
+    MalformedToken,
+    #[error("unknown notification ID")]
+    InvalidNotificationId,
+}
+
+/// Errors that can occur when signing the Credential Issuer metadata.
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataSigningError {
+    #[error("no key pair found with identifier: {0}")]
+    MissingKeyPair(String),
+    #[error("failed to sign metadata: {0}")]
+    Jwt(#[from] JwtError),
+}
+
+/// A salted hash of a Transaction Code, so that the code itself is not kept in the session store.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxCodeHash {
+    #[serde_as(as = "Base64")]
+    salt: Vec<u8>,
+    #[serde_as(as = "Base64")]
+    hash: Vec<u8>,
+}
+
+impl TxCodeHash {
+    const SALT_LENGTH: usize = 32;
+
+    pub fn new(tx_code: &str) -> Self {
+        let salt = random_bytes(Self::SALT_LENGTH);
+        let hash = Self::hash(&salt, tx_code);
+
+        Self { salt, hash }
+    }
+
+    fn hash(salt: &[u8], tx_code: &str) -> Vec<u8> {
+        sha256(&[salt, tx_code.as_bytes()].concat())
+    }
+
+    /// Check the Transaction Code against the hash, comparing the hashes in constant time to prevent the code from
+    /// leaking through timing differences.
+    pub fn verify(&self, tx_code: &str) -> bool {
+        constant_time::verify_slices_are_equal(&self.hash, &Self::hash(&self.salt, tx_code)).is_ok()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Created {
     pub attestation_previews: Option<Vec<AttestationPreview>>,
+    /// The hash of the Transaction Code that the wallet must include in its token request, if the issuer requires one.
+    #[serde(default)]
+    pub tx_code: Option<TxCodeHash>,
+    #[serde(default)]
+    pub failed_tx_code_attempts: u8,
+    /// Whether issuance should be deferred until it is approved using [`Issuer::approve_deferred_issuance()`].
+    #[serde(default)]
+    pub deferred: bool,
+    /// The challenge that the wallet attestation PoP in the token request must contain, if the issuer requires one.
+    #[serde(default)]
+    pub attestation_challenge: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -126,26 +262,123 @@ pub struct WaitingForResponse {
     pub attestation_previews: Vec<AttestationPreview>,
     pub dpop_public_key: VerifyingKey,
     pub dpop_nonce: String,
+    #[serde(default)]
+    pub deferred: bool,
+    /// The refresh token handed out in the token response, with which the wallet can later refresh the attestations.
+    #[serde(default)]
+    pub refresh_token: Option<RefreshToken>,
+    /// The subject of the attestations, with which the [`AttributeService`] retrieves their current attributes when
+    /// the wallet refreshes them. Present if and only if a refresh token was handed out.
+    #[serde(default)]
+    pub refresh_subject: Option<String>,
+    /// The wallet instance that started the session according to its wallet attestation, if the issuer requires one.
+    #[serde(default)]
+    pub wallet_id: Option<String>,
+    /// The challenge that the wallet attestation PoPs in the credential requests of the wallet must contain.
+    #[serde(default)]
+    pub attestation_challenge: Option<String>,
+}
+
+/// The wallet has sent its credential requests, but issuance of the attestations has been deferred. The wallet
+/// retrieves them using the `transaction_id` once issuance is approved.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Deferred {
+    pub access_token: AccessToken,
+    pub dpop_public_key: VerifyingKey,
+    pub dpop_nonce: String,
+    pub transaction_id: String,
+    pub attestations: Vec<PendingAttestation>,
+    pub approved: bool,
+    #[serde(default)]
+    pub refresh_token: Option<RefreshToken>,
+    #[serde(default)]
+    pub refresh_subject: Option<String>,
+    #[serde(default)]
+    pub wallet_id: Option<String>,
+    #[serde(default)]
+    pub attestation_challenge: Option<String>,
+}
+
+/// The attestations have been issued, and the wallet may refresh them using its refresh token. This requires a DPoP
+/// proof of the same key that the wallet used during issuance, and results in attestations containing the current
+/// attributes of the subject, which are bound to the same holder public keys. As this session is kept for a long time,
+/// it does not contain the attributes themselves.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Refreshable {
+    pub refresh_token: RefreshToken,
+    pub dpop_public_key: VerifyingKey,
+    pub refresh_subject: String,
+    pub holder_public_keys: Vec<VerifyingKey>,
+    #[serde(default)]
+    pub notification: Option<NotificationData>,
+    /// Present while the wallet is refreshing the attestations. The current refresh token remains valid until the
+    /// refreshed attestations have been issued, so that the wallet can try again if refreshing fails.
+    #[serde(default)]
+    pub refresh: Option<Refresh>,
+    /// The wallet instance to which the attestations were issued, if the issuer requires a wallet attestation.
+    #[serde(default)]
+    pub wallet_id: Option<String>,
+    /// The challenge that the wallet attestation PoPs must contain while the wallet is refreshing the attestations.
+    #[serde(default)]
+    pub attestation_challenge: Option<String>,
+}
+
+/// The tokens and nonces handed out to the wallet in response to its refresh token, with which it retrieves the
+/// refreshed attestations from the batch credential endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Refresh {
+    pub access_token: AccessToken,
+    pub c_nonce: String,
+    pub dpop_nonce: String,
+    pub refresh_token: RefreshToken,
+}
+
+/// Allows the wallet to notify the issuer of what it did with the issued attestations, by sending the notification ID
+/// that it received in the credential responses to the notification endpoint, using the access token with which it
+/// retrieved the attestations.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotificationData {
+    pub notification_id: String,
+    pub access_token: AccessToken,
+    pub dpop_public_key: VerifyingKey,
+    pub dpop_nonce: String,
+    /// The events of which the wallet notified the issuer so far, in order of receipt.
+    pub events: Vec<NotificationEvent>,
+}
+
+/// An attestation to be issued, along with the public key of the wallet to which it is to be bound.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingAttestation {
+    pub unsigned_mdoc: UnsignedMdoc,
+    pub holder_public_key: VerifyingKey,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Done {
     pub session_result: SessionResult,
+    /// Present if attestations were issued in this session.
+    #[serde(default)]
+    pub notification: Option<NotificationData>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum IssuanceData {
     Created(Created),
     WaitingForResponse(WaitingForResponse),
+    Deferred(Deferred),
+    Refreshable(Refreshable),
     Done(Done),
 }
 
 impl HasProgress for IssuanceData {
     fn progress(&self) -> Progress {
         match self {
-            Self::Created(_) | Self::WaitingForResponse(_) => Progress::Active,
+            Self::Created(_) | Self::WaitingForResponse(_) | Self::Deferred(_) => Progress::Active,
+            // Issuance has succeeded, even though the session is kept so that the wallet can refresh its attestations.
+            Self::Refreshable(_) => Progress::Finished { has_succeeded: true },
             Self::Done(done) => Progress::Finished {
-                has_succeeded: matches!(done.session_result, SessionResult::Done { .. }),
+                has_succeeded: matches!(done.session_result, SessionResult::Done),
             },
         }
     }
@@ -156,7 +389,8 @@ impl Expirable for IssuanceData {
         matches!(
             self,
             Self::Done(Done {
-                session_result: SessionResult::Expired
+                session_result: SessionResult::Expired,
+                ..
             })
         )
     }
@@ -164,13 +398,24 @@ impl Expirable for IssuanceData {
     fn expire(&mut self) {
         *self = Self::Done(Done {
             session_result: SessionResult::Expired,
+            notification: None,
         })
     }
+
+    fn expiration(&self) -> Option<Duration> {
+        match self {
+            Self::Deferred(_) => Some(DEFERRED_SESSION_EXPIRATION),
+            Self::Refreshable(_) => Some(REFRESHABLE_SESSION_EXPIRATION),
+            _ => None,
+        }
+    }
 }
 
 pub trait IssuanceState {}
 impl IssuanceState for Created {}
 impl IssuanceState for WaitingForResponse {}
+impl IssuanceState for Deferred {}
+impl IssuanceState for Refreshable {}
 impl IssuanceState for Done {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -190,14 +435,13 @@ pub struct Session<S: IssuanceState> {
 /// Implementations of this trait are responsible for determine the attributes to be issued, given the session and
 /// the token request. See for example the [`BrpPidAttributeService`].
 ///
-/// A future implementation of this trait is expected to enable generic issuance of attributes as follows:
+/// Generic issuance of attributes is enabled by the [`PreregisteredAttributeService`] as follows:
 /// - The owner of the issuance server determines the attributes to be issued and sends those to the issuance server.
-/// - The issuance server creates a new `SessionState<Created>` instance, puts that in its session store, and returns an
-///   authorization code that is to be forwarded to the wallet.
-/// - The wallet contacts the issuance server with the authorization code.
-/// - The issuance server looks up the `SessionState<Created>` from its session store and feeds that to the future
-///   implementation of this trait.
-/// - That implementation of this trait returns the attributes to be issued out of the `SessionState<Created>`.
+/// - The issuance server creates a new `SessionState<Created>` instance using [`Issuer::new_session()`], puts that in
+///   its session store, and returns a pre-authorized code that is to be forwarded to the wallet.
+/// - The wallet contacts the issuance server with the pre-authorized code.
+/// - The issuance server looks up the `SessionState<Created>` from its session store and feeds that to the
+///   [`PreregisteredAttributeService`], which returns the attributes to be issued out of the `SessionState<Created>`.
 #[trait_variant::make(AttributeService: Send)]
 pub trait LocalAttributeService {
     type Error: std::error::Error + Send + Sync + 'static;
@@ -208,14 +452,68 @@ pub trait LocalAttributeService {
         token_request: TokenRequest,
     ) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error>;
 
+    /// Returns the subject of the attestations to be issued, with which [`AttributeService::refreshed_attributes()`]
+    /// can later retrieve their current attributes. The wallet only receives a refresh token if this returns a subject,
+    /// which the issuer keeps instead of the attributes for as long as the attestations may be refreshed. For that
+    /// reason the subject should not identify the holder by itself, e.g. by encrypting it with an issuer key.
+    fn refresh_subject(&self, attestation_previews: &[AttestationPreview]) -> Option<String>;
+
+    /// Retrieve the current attributes of the subject returned by [`AttributeService::refresh_subject()`], which are
+    /// issued when the wallet refreshes its attestations.
+    async fn refreshed_attributes(&self, subject: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error>;
+
     async fn oauth_metadata(&self, issuer_url: &BaseUrl) -> Result<oidc::Config, Self::Error>;
 }
 
+/// An [`AttributeService`] that issues the attestations that were registered in the session using
+/// [`Issuer::new_session()`], allowing the issuer to issue arbitrary doctypes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreregisteredAttributeService;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreregisteredAttributesError {
+    #[error("no attestations registered for session")]
+    NoAttestationsRegistered,
+    #[error("registered attestations cannot be refreshed")]
+    RefreshUnsupported,
+}
+
+impl AttributeService for PreregisteredAttributeService {
+    type Error = PreregisteredAttributesError;
+
+    async fn attributes(
+        &self,
+        session: &SessionState<Created>,
+        _token_request: TokenRequest,
+    ) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
+        session
+            .data
+            .attestation_previews
+            .clone()
+            .and_then(|previews| previews.try_into().ok())
+            .ok_or(PreregisteredAttributesError::NoAttestationsRegistered)
+    }
+
+    /// The registered attributes are not kept after issuance, so they cannot be refreshed.
+    fn refresh_subject(&self, _attestation_previews: &[AttestationPreview]) -> Option<String> {
+        None
+    }
+
+    async fn refreshed_attributes(&self, _subject: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
+        Err(PreregisteredAttributesError::RefreshUnsupported)
+    }
+
+    async fn oauth_metadata(&self, issuer_url: &BaseUrl) -> Result<oidc::Config, Self::Error> {
+        Ok(oidc::Config::new_pre_authorized_code(issuer_url))
+    }
+}
+
 pub struct Issuer<A, K, S> {
     sessions: Arc<S>,
     attr_service: A,
     issuer_data: IssuerData<K>,
     cleanup_task: JoinHandle<()>,
+    count_task: JoinHandle<()>,
     pub metadata: IssuerMetadata,
 }
 
@@ -232,12 +530,17 @@ pub struct IssuerData<K> {
 
     /// URL prefix of the `/token`, `/credential` and `/batch_crededential` endpoints.
     server_url: BaseUrl,
+
+    /// If set, the public key of the Wallet Provider against which the wallet attestations are verified that wallets
+    /// must include in their token and credential requests.
+    wallet_provider_pubkey: Option<EcdsaDecodingKey>,
 }
 
 impl<A, K, S> Drop for Issuer<A, K, S> {
     fn drop(&mut self) {
-        // Stop the task at the next .await
+        // Stop the tasks at the next .await
         self.cleanup_task.abort();
+        self.count_task.abort();
     }
 }
 
@@ -265,24 +568,27 @@ where
             // In this implementation, for now the Credential Issuer Identifier also always acts as
             // the public server URL.
             server_url: issuer_url.clone(),
+
+            wallet_provider_pubkey: None,
         };
 
         Self {
             sessions: Arc::clone(&sessions),
             attr_service,
             issuer_data,
-            cleanup_task: sessions.start_cleanup_task(CLEANUP_INTERVAL_SECONDS),
+            cleanup_task: Arc::clone(&sessions).start_cleanup_task(CLEANUP_INTERVAL_SECONDS, METRICS_SESSION_TYPE),
+            count_task: sessions.start_count_task(COUNT_INTERVAL_SECONDS, METRICS_SESSION_TYPE),
             metadata: IssuerMetadata {
                 issuer_config: metadata::IssuerData {
                     credential_issuer: issuer_url.clone(),
                     authorization_servers: None,
                     credential_endpoint: issuer_url.join_base_url("/credential"),
                     batch_credential_endpoint: Some(issuer_url.join_base_url("/batch_credential")),
-                    deferred_credential_endpoint: None,
-                    notification_endpoint: None,
+                    deferred_credential_endpoint: Some(issuer_url.join_base_url("/deferred_credential")),
+                    notification_endpoint: Some(issuer_url.join_base_url("/notification")),
                     credential_response_encryption: CredentialResponseEncryption {
-                        alg_values_supported: vec![],
-                        enc_values_supported: vec![],
+                        alg_values_supported: vec![VpAlgValues::EcdhEs],
+                        enc_values_supported: vec![VpEncValues::A256GCM],
                         encryption_required: false,
                     },
                     credential_identifiers_supported: Some(false),
@@ -293,6 +599,82 @@ where
             },
         }
     }
+
+    /// Sign the Credential Issuer metadata with the key pair from the key ring having the specified identifier, so
+    /// that wallets can verify it against their mdoc trust anchors.
+    pub async fn sign_metadata(&mut self, key_id: &str) -> Result<(), MetadataSigningError> {
+        let key_pair = self
+            .issuer_data
+            .private_keys
+            .key_pair(key_id)
+            .ok_or_else(|| MetadataSigningError::MissingKeyPair(key_id.to_string()))?;
+
+        self.metadata =
+            IssuerMetadata::new_signed(self.metadata.issuer_config.clone(), key_pair, &TimeGenerator).await?;
+
+        Ok(())
+    }
+
+    /// Require wallets to include a wallet attestation in their token and credential requests, which is verified
+    /// against the public key of the Wallet Provider. As wallet attestations are short-lived, this rejects wallets
+    /// that have been blocked by the Wallet Provider shortly after.
+    pub fn require_wallet_attestation(&mut self, wallet_provider_pubkey: EcdsaDecodingKey) {
+        self.issuer_data.wallet_provider_pubkey = Some(wallet_provider_pubkey);
+    }
+}
+
+impl<K> IssuerData<K> {
+    /// Verify the wallet attestation sent by the wallet, if the issuer requires one. Its PoP must contain the
+    /// `challenge` of the session and, if the session is already bound to a wallet instance, the attestation must be
+    /// for that `wallet_id`. Returns the wallet ID from the attestation.
+    fn verify_wallet_attestation(
+        &self,
+        wallet_attestation: Option<&WalletAttestationWithPop>,
+        challenge: Option<&str>,
+        wallet_id: Option<&str>,
+    ) -> Result<Option<String>, WalletAttestationError> {
+        let Some(wallet_provider_pubkey) = &self.wallet_provider_pubkey else {
+            return Ok(None);
+        };
+
+        let claims = wallet_attestation.ok_or(WalletAttestationError::Missing)?.verify(
+            wallet_provider_pubkey,
+            &self.accepted_wallet_client_ids,
+            &self.credential_issuer_identifier,
+            challenge,
+        )?;
+
+        if wallet_id.is_some_and(|wallet_id| wallet_id != claims.wallet_id) {
+            return Err(WalletAttestationError::WalletMismatch);
+        }
+
+        Ok(Some(claims.wallet_id))
+    }
+
+    /// Verify that the key to which an attestation is to be bound was attested by the Wallet Provider for the wallet
+    /// instance of the session, if the issuer requires a wallet attestation.
+    fn verify_key_attestation(
+        &self,
+        credential_request: &CredentialRequest,
+        holder_public_key: &VerifyingKey,
+        wallet_id: Option<&str>,
+    ) -> Result<(), CredentialRequestError> {
+        let Some(wallet_provider_pubkey) = &self.wallet_provider_pubkey else {
+            return Ok(());
+        };
+
+        wallet_id
+            .ok_or(WalletAttestationError::Missing)
+            .and_then(|wallet_id| {
+                verify_key_attestation(
+                    credential_request.key_attestation.as_ref(),
+                    wallet_provider_pubkey,
+                    wallet_id,
+                    holder_public_key,
+                )
+            })
+            .map_err(CredentialRequestError::WalletAttestation)
+    }
 }
 
 fn logged_issuance_result<T, E: std::error::Error>(result: Result<T, E>) -> Result<T, E> {
@@ -301,38 +683,135 @@ fn logged_issuance_result<T, E: std::error::Error>(result: Result<T, E>) -> Resu
         .inspect_err(|error| info!("Issuance error: {error}"))
 }
 
+/// Check that the response encryption parameters sent by the wallet, if any, are usable before processing the request.
+fn check_response_encryption(encryption: Option<&ResponseEncryption>) -> Result<(), CredentialRequestError> {
+    encryption
+        .map(ResponseEncryption::encrypter)
+        .transpose()
+        .map_err(CredentialRequestError::InvalidEncryptionParameters)?;
+
+    Ok(())
+}
+
+/// Encrypt the response to one of the credential endpoints, if the wallet requested so.
+fn encrypt_response<T: Serialize>(
+    response: T,
+    encryption: Option<&ResponseEncryption>,
+) -> Result<MaybeEncrypted<T>, CredentialRequestError> {
+    MaybeEncrypted::new(response, encryption).map_err(CredentialRequestError::ResponseEncryption)
+}
+
+/// Convert an error that occurred while writing a session into an [`IssuanceError`]. If another request for the same
+/// session was processed concurrently, the session is no longer in the state that this request started from.
+fn session_write_error(error: SessionStoreError) -> IssuanceError {
+    match error {
+        SessionStoreError::DuplicateToken(_) | SessionStoreError::Conflict(_) => IssuanceError::UnexpectedState,
+        error => IssuanceError::SessionStore(error),
+    }
+}
+
 impl<A, K, S> Issuer<A, K, S>
 where
     A: AttributeService,
     K: KeyRing,
     S: SessionStore<IssuanceData>,
 {
+    /// Create a new session containing the attestations to be issued, returning the pre-authorized code with which the
+    /// wallet can start issuance. The attestations are only issued when using an [`AttributeService`] that reads them
+    /// from the session, such as the [`PreregisteredAttributeService`]. If `tx_code` is specified, the wallet has to
+    /// include it in its token request, in at most [`MAX_TX_CODE_ATTEMPTS`] attempts. If `deferred` is set, the
+    /// attestations are only issued after [`Issuer::approve_deferred_issuance()`] is called.
+    pub async fn new_session(
+        &self,
+        attestation_previews: NonEmpty<Vec<AttestationPreview>>,
+        tx_code: Option<String>,
+        deferred: bool,
+    ) -> Result<AuthorizationCode, IssuanceError> {
+        let code = AuthorizationCode::from(random_string(32));
+        let session = SessionState::new(
+            code.clone().into(),
+            IssuanceData::Created(Created {
+                attestation_previews: Some(attestation_previews.into_inner()),
+                tx_code: tx_code.as_deref().map(TxCodeHash::new),
+                failed_tx_code_attempts: 0,
+                deferred,
+                attestation_challenge: None,
+            }),
+        );
+
+        self.write_session(session, true)
+            .await
+            .map_err(IssuanceError::SessionStore)?;
+
+        Ok(code)
+    }
+
     pub async fn process_token_request(
         &self,
         token_request: TokenRequest,
         dpop: Dpop,
+        wallet_attestation: Option<WalletAttestationWithPop>,
     ) -> Result<(TokenResponseWithPreviews, String), TokenRequestError> {
-        let session_token = token_request.code().clone().into();
+        if let TokenRequestGrantType::RefreshToken { refresh_token } = token_request.grant_type {
+            return self
+                .process_refresh_token_request(refresh_token, dpop, wallet_attestation)
+                .await;
+        }
+
+        let session_token = token_request
+            .code()
+            .ok_or(TokenRequestError::UnsupportedTokenRequestType)?
+            .clone()
+            .into();
 
         // Retrieve the session from the session store, if present. It need not be, depending on the implementation of
         // the attribute service.
-        let session = self
+        let existing_session = self
             .sessions
             .get(&session_token)
             .await
-            .map_err(IssuanceError::SessionStore)?
-            .unwrap_or(SessionState::<IssuanceData>::new(
-                session_token,
-                IssuanceData::Created(Created {
-                    attestation_previews: None,
-                }),
-            ));
+            .map_err(IssuanceError::SessionStore)?;
+        let is_new = existing_session.is_none();
+        let session = existing_session.unwrap_or(SessionState::<IssuanceData>::new(
+            session_token,
+            IssuanceData::Created(Created {
+                attestation_previews: None,
+                tx_code: None,
+                failed_tx_code_attempts: 0,
+                deferred: false,
+                attestation_challenge: None,
+            }),
+        ));
         let session: Session<Created> = session.try_into().map_err(TokenRequestError::IssuanceError)?;
 
+        // If the wallet attestation PoP does not contain the challenge of this session, provide the wallet with a new
+        // challenge so that it can try again.
+        let wallet_id = match self.issuer_data.verify_wallet_attestation(
+            wallet_attestation.as_ref(),
+            session.session_data().attestation_challenge.as_deref(),
+            None,
+        ) {
+            Ok(wallet_id) => wallet_id,
+            Err(WalletAttestationError::Challenge) => {
+                let challenge = random_string(32);
+                let data = Created {
+                    attestation_challenge: Some(challenge.clone()),
+                    ..session.session_data().clone()
+                };
+                self.write_session(session.transition(data).into(), is_new)
+                    .await
+                    .map_err(|e| TokenRequestError::IssuanceError(session_write_error(e)))?;
+
+                return Err(TokenRequestError::UseAttestationChallenge(challenge));
+            }
+            Err(error) => return Err(TokenRequestError::WalletAttestation(error)),
+        };
+
         let result = session
             .process_token_request(
                 token_request,
                 dpop,
+                wallet_id,
                 &self.attr_service,
                 &self.issuer_data.credential_issuer_identifier,
             )
@@ -340,21 +819,83 @@ where
 
         let (response, next) = match result {
             Ok((response, dpop_nonce, next)) => (Ok((response, dpop_nonce)), next.into()),
-            Err((err, next)) => (Err(err), next.into()),
+            Err((err, next)) => (Err(err), next),
         };
 
-        self.sessions
-            .write(next, true)
+        self.write_session(next, is_new)
             .await
-            .map_err(|e| TokenRequestError::IssuanceError(e.into()))?;
+            .map_err(|e| TokenRequestError::IssuanceError(session_write_error(e)))?;
 
         response
     }
 
-    async fn get_session(
+    /// Process a token request using a refresh token, after which the wallet can request the attestations of the
+    /// session again, containing the current attributes. If the refresh token or the DPoP proof is invalid, the session
+    /// is left untouched.
+    async fn process_refresh_token_request(
         &self,
-        code: AuthorizationCode,
-    ) -> Result<Session<WaitingForResponse>, CredentialRequestError> {
+        refresh_token: RefreshToken,
+        dpop: Dpop,
+        wallet_attestation: Option<WalletAttestationWithPop>,
+    ) -> Result<(TokenResponseWithPreviews, String), TokenRequestError> {
+        let code = refresh_token.code().ok_or(TokenRequestError::InvalidRefreshToken)?;
+
+        // If the session no longer exists or can no longer be refreshed, the refresh token is invalid. This lets the
+        // wallet know that it need not try again.
+        let session: Session<Refreshable> = self
+            .sessions
+            .get(&code.into())
+            .await
+            .map_err(IssuanceError::SessionStore)?
+            .ok_or(TokenRequestError::InvalidRefreshToken)?
+            .try_into()
+            .map_err(|_| TokenRequestError::InvalidRefreshToken)?;
+
+        // The attestations can only be refreshed by the wallet to which they were issued. As in the token request of
+        // a new session, the wallet attestation PoP must contain a challenge provided for refreshing.
+        let session_data = session.session_data();
+        let wallet_id = match self.issuer_data.verify_wallet_attestation(
+            wallet_attestation.as_ref(),
+            session_data.attestation_challenge.as_deref(),
+            session_data.wallet_id.as_deref(),
+        ) {
+            Ok(wallet_id) => wallet_id,
+            Err(WalletAttestationError::Challenge) => {
+                let challenge = random_string(32);
+                let data = Refreshable {
+                    attestation_challenge: Some(challenge.clone()),
+                    ..session_data.clone()
+                };
+                self.write_session(session.transition(data).into(), false)
+                    .await
+                    .map_err(|e| TokenRequestError::IssuanceError(session_write_error(e)))?;
+
+                return Err(TokenRequestError::UseAttestationChallenge(challenge));
+            }
+            Err(error) => return Err(TokenRequestError::WalletAttestation(error)),
+        };
+
+        let (response, dpop_nonce, next) = session
+            .process_refresh_token_request(
+                refresh_token,
+                dpop,
+                wallet_id,
+                &self.attr_service,
+                &self.issuer_data.credential_issuer_identifier,
+            )
+            .await?;
+
+        self.write_session(next.into(), false)
+            .await
+            .map_err(|e| TokenRequestError::IssuanceError(session_write_error(e)))?;
+
+        Ok((response, dpop_nonce))
+    }
+
+    async fn get_session<T: IssuanceState>(&self, code: AuthorizationCode) -> Result<Session<T>, CredentialRequestError>
+    where
+        Session<T>: TryFrom<SessionState<IssuanceData>, Error = IssuanceError>,
+    {
         self.sessions
             .get(&code.clone().into())
             .await
@@ -369,20 +910,28 @@ where
         access_token: AccessToken,
         dpop: Dpop,
         credential_request: CredentialRequest,
-    ) -> Result<CredentialResponse, CredentialRequestError> {
+        wallet_attestation: Option<WalletAttestationWithPop>,
+    ) -> Result<MaybeEncrypted<CredentialResponse>, CredentialRequestError> {
         let code = access_token.code().ok_or(CredentialRequestError::MalformedToken)?;
-        let session = self.get_session(code).await?;
+        let encryption = credential_request.credential_response_encryption.clone();
+        check_response_encryption(encryption.as_ref())?;
+        let session: Session<WaitingForResponse> = self.get_session(code).await?;
+        let session_data = session.session_data();
+        self.issuer_data
+            .verify_wallet_attestation(
+                wallet_attestation.as_ref(),
+                session_data.attestation_challenge.as_deref(),
+                session_data.wallet_id.as_deref(),
+            )
+            .map_err(CredentialRequestError::WalletAttestation)?;
 
         let (response, next) = session
             .process_credential(credential_request, access_token, dpop, &self.issuer_data)
             .await;
 
-        self.sessions
-            .write(next.into(), false)
-            .await
-            .map_err(IssuanceError::SessionStore)?;
+        self.write_session(next, false).await.map_err(session_write_error)?;
 
-        logged_issuance_result(response)
+        encrypt_response(logged_issuance_result(response)?, encryption.as_ref())
     }
 
     pub async fn process_batch_credential(
@@ -390,20 +939,145 @@ where
         access_token: AccessToken,
         dpop: Dpop,
         credential_requests: CredentialRequests,
-    ) -> Result<CredentialResponses, CredentialRequestError> {
+        wallet_attestation: Option<WalletAttestationWithPop>,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, CredentialRequestError> {
+        let code = access_token.code().ok_or(CredentialRequestError::MalformedToken)?;
+        let encryption = credential_requests.credential_response_encryption.clone();
+        check_response_encryption(encryption.as_ref())?;
+        let session = self
+            .sessions
+            .get(&code.clone().into())
+            .await
+            .map_err(IssuanceError::SessionStore)?
+            .ok_or(IssuanceError::UnknownSession(code))?;
+
+        let (attestation_challenge, wallet_id) = match &session.data {
+            IssuanceData::WaitingForResponse(WaitingForResponse {
+                attestation_challenge,
+                wallet_id,
+                ..
+            })
+            | IssuanceData::Refreshable(Refreshable {
+                attestation_challenge,
+                wallet_id,
+                ..
+            }) => (attestation_challenge.as_deref(), wallet_id.as_deref()),
+            _ => (None, None),
+        };
+        self.issuer_data
+            .verify_wallet_attestation(wallet_attestation.as_ref(), attestation_challenge, wallet_id)
+            .map_err(CredentialRequestError::WalletAttestation)?;
+
+        let (response, next) = match session.data {
+            // The wallet is refreshing its attestations, which contain the current attributes of the subject.
+            IssuanceData::Refreshable(_) => {
+                let session: Session<Refreshable> = session.try_into()?;
+                let attestation_previews = self
+                    .attr_service
+                    .refreshed_attributes(&session.session_data().refresh_subject)
+                    .await
+                    .map_err(|e| CredentialRequestError::AttributeService(Box::new(e)));
+
+                session
+                    .process_batch_credential(
+                        credential_requests,
+                        access_token,
+                        dpop,
+                        attestation_previews,
+                        &self.issuer_data,
+                    )
+                    .await
+            }
+            _ => {
+                let session: Session<WaitingForResponse> = session.try_into()?;
+                session
+                    .process_batch_credential(credential_requests, access_token, dpop, &self.issuer_data)
+                    .await
+            }
+        };
+
+        self.write_session(next, false).await.map_err(session_write_error)?;
+
+        encrypt_response(logged_issuance_result(response)?, encryption.as_ref())
+    }
+
+    pub async fn process_deferred_credential(
+        &self,
+        access_token: AccessToken,
+        dpop: Dpop,
+        deferred_request: DeferredCredentialRequest,
+        wallet_attestation: Option<WalletAttestationWithPop>,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, CredentialRequestError> {
         let code = access_token.code().ok_or(CredentialRequestError::MalformedToken)?;
-        let session = self.get_session(code).await?;
+        let encryption = deferred_request.credential_response_encryption.clone();
+        check_response_encryption(encryption.as_ref())?;
+        let session: Session<Deferred> = self.get_session(code).await?;
+
+        // The wallet retrieves the deferred attestations later using a new wallet attestation, so it needs a new
+        // challenge for its PoP.
+        let session_data = session.session_data();
+        match self.issuer_data.verify_wallet_attestation(
+            wallet_attestation.as_ref(),
+            session_data.attestation_challenge.as_deref(),
+            session_data.wallet_id.as_deref(),
+        ) {
+            Ok(_) => {}
+            Err(WalletAttestationError::Challenge) => {
+                let challenge = random_string(32);
+                let data = Deferred {
+                    attestation_challenge: Some(challenge.clone()),
+                    ..session_data.clone()
+                };
+                self.write_session(session.transition(data).into(), false)
+                    .await
+                    .map_err(session_write_error)?;
+
+                return Err(CredentialRequestError::UseAttestationChallenge(challenge));
+            }
+            Err(error) => return Err(CredentialRequestError::WalletAttestation(error)),
+        };
 
         let (response, next) = session
-            .process_batch_credential(credential_requests, access_token, dpop, &self.issuer_data)
+            .process_deferred_credential(deferred_request, access_token, dpop, &self.issuer_data)
             .await;
 
-        self.sessions
-            .write(next.into(), false)
-            .await
-            .map_err(IssuanceError::SessionStore)?;
+        // While issuance is pending, the session is left untouched so that the wallet can try again later.
+        if let Some(next) = next {
+            self.write_session(next, false).await.map_err(session_write_error)?;
+        }
+
+        encrypt_response(logged_issuance_result(response)?, encryption.as_ref())
+    }
+
+    /// Approve issuance of the attestations in a session that was created with deferred issuance. If the wallet has
+    /// already requested the attestations, it can now retrieve them; otherwise they will be issued immediately once
+    /// the wallet requests them.
+    pub async fn approve_deferred_issuance(&self, code: AuthorizationCode) -> Result<(), IssuanceError> {
+        let session = self
+            .sessions
+            .get(&code.clone().into())
+            .await?
+            .ok_or(IssuanceError::UnknownSession(code))?;
+
+        let data = match session.data.clone() {
+            IssuanceData::Created(created) => IssuanceData::Created(Created {
+                deferred: false,
+                ..created
+            }),
+            IssuanceData::WaitingForResponse(waiting) => IssuanceData::WaitingForResponse(WaitingForResponse {
+                deferred: false,
+                ..waiting
+            }),
+            IssuanceData::Deferred(deferred) => IssuanceData::Deferred(Deferred {
+                approved: true,
+                ..deferred
+            }),
+            IssuanceData::Refreshable(_) | IssuanceData::Done(_) => return Err(IssuanceError::UnexpectedState),
+        };
 
-        logged_issuance_result(response)
+        self.write_session(session.transition(data), false)
+            .await
+            .map_err(session_write_error)
     }
 
     pub async fn process_reject_issuance(
@@ -413,7 +1087,7 @@ where
         endpoint_name: &str,
     ) -> Result<(), CredentialRequestError> {
         let code = access_token.code().ok_or(CredentialRequestError::MalformedToken)?;
-        let session = self.get_session(code).await?;
+        let session: Session<WaitingForResponse> = self.get_session(code).await?;
 
         // Check authorization of the request
         let session_data = session.session_data();
@@ -432,12 +1106,49 @@ where
 
         let next = session.transition(Done {
             session_result: SessionResult::Cancelled,
+            notification: None,
         });
 
-        self.sessions
-            .write(next.into(), false)
+        self.write_session(next.into(), false)
             .await
-            .map_err(IssuanceError::SessionStore)?;
+            .map_err(session_write_error)?;
+
+        Ok(())
+    }
+
+    /// Process a notification of the wallet about what it did with the attestations issued in a session, recording its
+    /// event in the session.
+    pub async fn process_notification(
+        &self,
+        access_token: AccessToken,
+        dpop: Dpop,
+        notification_request: NotificationRequest,
+    ) -> Result<(), NotificationError> {
+        let code = access_token.code().ok_or(NotificationError::MalformedToken)?;
+        let session = self
+            .sessions
+            .get(&code.clone().into())
+            .await
+            .map_err(IssuanceError::SessionStore)?
+            .ok_or(IssuanceError::UnknownSession(code))?;
+
+        let mut data = session.data.clone();
+        data.notification_mut()
+            .ok_or(NotificationError::InvalidNotificationId)?
+            .process_notification_request(notification_request, access_token, dpop, &self.issuer_data.server_url)?;
+
+        self.write_session(session.transition(data), false)
+            .await
+            .map_err(session_write_error)?;
+
+        Ok(())
+    }
+
+    /// Write the session to the session store and record its new state in the metrics.
+    async fn write_session(&self, session: SessionState<IssuanceData>, is_new: bool) -> Result<(), SessionStoreError> {
+        let labels = SessionStateLabels::new(&session.data);
+        self.sessions.write(session, is_new).await?;
+        labels.record(METRICS_SESSION_TYPE);
 
         Ok(())
     }
@@ -461,6 +1172,7 @@ impl TryFrom<SessionState<IssuanceData>> for Session<Created> {
                 data: session_data,
                 token: value.token,
                 last_active: value.last_active,
+                version: value.version,
             },
         })
     }
@@ -471,42 +1183,79 @@ impl Session<Created> {
         self,
         token_request: TokenRequest,
         dpop: Dpop,
+        wallet_id: Option<String>,
         attr_service: &impl AttributeService,
         server_url: &BaseUrl,
-    ) -> Result<(TokenResponseWithPreviews, String, Session<WaitingForResponse>), (TokenRequestError, Session<Done>)>
-    {
+    ) -> Result<
+        (TokenResponseWithPreviews, String, Session<WaitingForResponse>),
+        (TokenRequestError, SessionState<IssuanceData>),
+    > {
         let result = self
             .process_token_request_inner(token_request, dpop, attr_service, server_url)
             .await;
 
         match result {
-            Ok((response, dpop_pubkey, dpop_nonce)) => {
+            Ok((response, dpop_pubkey, dpop_nonce, refresh_subject)) => {
+                let deferred = self.state.data.deferred;
+                let attestation_challenge = self.state.data.attestation_challenge.clone();
                 let next = self.transition(WaitingForResponse {
                     access_token: response.token_response.access_token.clone(),
                     c_nonce: response.token_response.c_nonce.as_ref().unwrap().clone(), // field is always set below
                     attestation_previews: response.attestation_previews.clone().into_inner(),
                     dpop_public_key: dpop_pubkey,
                     dpop_nonce: dpop_nonce.clone(),
+                    deferred,
+                    refresh_token: response.token_response.refresh_token.clone(),
+                    refresh_subject,
+                    wallet_id,
+                    attestation_challenge,
                 });
                 Ok((response, dpop_nonce, next))
             }
+            Err(TokenRequestError::IncorrectTxCode)
+                if self.state.data.failed_tx_code_attempts + 1 < MAX_TX_CODE_ATTEMPTS =>
+            {
+                // Keep the session open so that the wallet can retry with another transaction code
+                let data = Created {
+                    failed_tx_code_attempts: self.state.data.failed_tx_code_attempts + 1,
+                    ..self.state.data.clone()
+                };
+                let next = self.transition(data);
+                Err((TokenRequestError::IncorrectTxCode, next.into()))
+            }
             Err(err) => {
                 let next = self.transition_fail(&err);
-                Err((err, next))
+                Err((err, next.into()))
             }
         }
     }
 
+    /// Check that the token request contains the Transaction Code of this session, if it has one.
+    fn check_tx_code(&self, token_request: &TokenRequest) -> Result<(), TokenRequestError> {
+        let Some(tx_code_hash) = &self.state.data.tx_code else {
+            return Ok(());
+        };
+
+        let TokenRequestGrantType::PreAuthorizedCode { tx_code, .. } = &token_request.grant_type else {
+            return Err(TokenRequestError::UnsupportedTokenRequestType);
+        };
+
+        match tx_code {
+            Some(tx_code) if tx_code_hash.verify(tx_code) => Ok(()),
+            _ => Err(TokenRequestError::IncorrectTxCode),
+        }
+    }
+
     pub async fn process_token_request_inner(
         &self,
         token_request: TokenRequest,
         dpop: Dpop,
         attr_service: &impl AttributeService,
         server_url: &BaseUrl,
-    ) -> Result<(TokenResponseWithPreviews, VerifyingKey, String), TokenRequestError> {
+    ) -> Result<(TokenResponseWithPreviews, VerifyingKey, String, Option<String>), TokenRequestError> {
         if !matches!(
             token_request.grant_type,
-            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code: _ }
+            TokenRequestGrantType::PreAuthorizedCode { .. }
         ) {
             return Err(TokenRequestError::UnsupportedTokenRequestType);
         }
@@ -515,7 +1264,12 @@ impl Session<Created> {
             .verify(server_url.join("token"), Method::POST, None)
             .map_err(|err| TokenRequestError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
 
-        let code = token_request.code().clone();
+        self.check_tx_code(&token_request)?;
+
+        let code = token_request
+            .code()
+            .ok_or(TokenRequestError::UnsupportedTokenRequestType)?
+            .clone();
 
         let previews = attr_service
             .attributes(&self.state, token_request)
@@ -524,13 +1278,17 @@ impl Session<Created> {
 
         let c_nonce = random_string(32);
         let dpop_nonce = random_string(32);
+        let refresh_subject = attr_service.refresh_subject(previews.as_ref());
 
         let response = TokenResponseWithPreviews {
-            token_response: TokenResponse::new(AccessToken::new(&code), c_nonce),
+            token_response: TokenResponse {
+                refresh_token: refresh_subject.as_ref().map(|_| RefreshToken::new(&code)),
+                ..TokenResponse::new(AccessToken::new(&code), c_nonce)
+            },
             attestation_previews: previews,
         };
 
-        Ok((response, dpop_public_key, dpop_nonce))
+        Ok((response, dpop_public_key, dpop_nonce, refresh_subject))
     }
 }
 
@@ -549,12 +1307,24 @@ impl TokenResponse {
     }
 }
 
+impl From<Session<Created>> for SessionState<IssuanceData> {
+    fn from(value: Session<Created>) -> Self {
+        SessionState {
+            data: IssuanceData::Created(value.state.data),
+            token: value.state.token,
+            last_active: value.state.last_active,
+            version: value.state.version,
+        }
+    }
+}
+
 impl From<Session<WaitingForResponse>> for SessionState<IssuanceData> {
     fn from(value: Session<WaitingForResponse>) -> Self {
         SessionState {
             data: IssuanceData::WaitingForResponse(value.state.data),
             token: value.state.token,
             last_active: value.state.last_active,
+            version: value.state.version,
         }
     }
 }
@@ -571,6 +1341,7 @@ impl TryFrom<SessionState<IssuanceData>> for Session<WaitingForResponse> {
                 data: session_data,
                 token: value.token,
                 last_active: value.last_active,
+                version: value.version,
             },
         })
     }
@@ -583,31 +1354,29 @@ impl Session<WaitingForResponse> {
         access_token: AccessToken,
         dpop: Dpop,
         issuer_data: &IssuerData<impl KeyRing>,
-    ) -> (Result<CredentialResponse, CredentialRequestError>, Session<Done>) {
-        let result = self
-            .process_credential_inner(credential_request, access_token, dpop, issuer_data)
+    ) -> (
+        Result<CredentialResponse, CredentialRequestError>,
+        SessionState<IssuanceData>,
+    ) {
+        let attestation = self.process_credential_inner(credential_request, access_token, dpop, issuer_data);
+
+        let (result, next) = self
+            .issue_or_defer(attestation.map(|attestation| vec![attestation]), issuer_data)
             .await;
 
-        // In case of success, transition the session to done. This means the client won't be able to reuse its access
-        // token in more requests to this endpoint. (The OpenID4VCI and OAuth specs allow reuse of access tokens, but
-        // don't forbid that a server doesn't allow that.)
-        let next = match &result {
-            Ok(_) => self.transition(Done {
-                session_result: SessionResult::Done,
-            }),
-            Err(err) => self.transition_fail(err),
-        };
+        // We passed exactly one attestation above, so there is exactly one response.
+        let result = result.map(|responses| responses.into_iter().next().unwrap());
 
         (result, next)
     }
 
-    pub async fn process_credential_inner(
+    pub fn process_credential_inner(
         &self,
         credential_request: CredentialRequest,
         access_token: AccessToken,
         dpop: Dpop,
         issuer_data: &IssuerData<impl KeyRing>,
-    ) -> Result<CredentialResponse, CredentialRequestError> {
+    ) -> Result<PendingAttestation, CredentialRequestError> {
         let session_data = self.session_data();
 
         // Check authorization of the request
@@ -650,15 +1419,17 @@ impl Session<WaitingForResponse> {
             },
         }?;
 
-        let credential_response = verify_pop_and_sign_attestation(
-            &session_data.c_nonce,
+        let holder_public_key = verify_pop(&session_data.c_nonce, &credential_request, unsigned, issuer_data)?;
+        issuer_data.verify_key_attestation(
             &credential_request,
-            unsigned.clone(),
-            issuer_data,
-        )
-        .await?;
+            &holder_public_key,
+            session_data.wallet_id.as_deref(),
+        )?;
 
-        Ok(credential_response)
+        Ok(PendingAttestation {
+            unsigned_mdoc: unsigned.clone(),
+            holder_public_key,
+        })
     }
 
     pub async fn process_batch_credential(
@@ -667,29 +1438,215 @@ impl Session<WaitingForResponse> {
         access_token: AccessToken,
         dpop: Dpop,
         issuer_data: &IssuerData<impl KeyRing>,
-    ) -> (Result<CredentialResponses, CredentialRequestError>, Session<Done>) {
+    ) -> (
+        Result<CredentialResponses, CredentialRequestError>,
+        SessionState<IssuanceData>,
+    ) {
+        let attestations = self.process_batch_credential_inner(credential_requests, access_token, dpop, issuer_data);
+
+        let (result, next) = self.issue_or_defer(attestations, issuer_data).await;
+        let result = result.map(|credential_responses| CredentialResponses { credential_responses });
+
+        (result, next)
+    }
+
+    fn process_batch_credential_inner(
+        &self,
+        credential_requests: CredentialRequests,
+        access_token: AccessToken,
+        dpop: Dpop,
+        issuer_data: &IssuerData<impl KeyRing>,
+    ) -> Result<Vec<PendingAttestation>, CredentialRequestError> {
+        let session_data = self.session_data();
+
+        // Check authorization of the request
+        if session_data.access_token != access_token {
+            return Err(CredentialRequestError::Unauthorized);
+        }
+
+        dpop.verify_expecting_key(
+            &session_data.dpop_public_key,
+            &issuer_data.server_url.join("batch_credential"),
+            &Method::POST,
+            Some(&access_token),
+            Some(&session_data.dpop_nonce),
+        )
+        .map_err(|err| CredentialRequestError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
+
+        credential_requests
+            .credential_requests
+            .as_ref()
+            .iter()
+            .zip(unsigned_mdoc_copies(&session_data.attestation_previews))
+            .map(|(cred_req, unsigned_mdoc)| {
+                let holder_public_key = verify_pop(&session_data.c_nonce, cred_req, unsigned_mdoc, issuer_data)?;
+                issuer_data.verify_key_attestation(cred_req, &holder_public_key, session_data.wallet_id.as_deref())?;
+
+                Ok(PendingAttestation {
+                    unsigned_mdoc: unsigned_mdoc.clone(),
+                    holder_public_key,
+                })
+            })
+            .collect()
+    }
+
+    /// Issue the requested attestations, unless issuance is deferred, in which case the session transitions to
+    /// [`Deferred`] and each of the responses contains the same transaction ID.
+    async fn issue_or_defer(
+        self,
+        attestations: Result<Vec<PendingAttestation>, CredentialRequestError>,
+        issuer_data: &IssuerData<impl KeyRing>,
+    ) -> (
+        Result<Vec<CredentialResponse>, CredentialRequestError>,
+        SessionState<IssuanceData>,
+    ) {
+        let attestations = match attestations {
+            Ok(attestations) => attestations,
+            Err(err) => {
+                let next = self.transition_fail(&err);
+                return (Err(err), next.into());
+            }
+        };
+
+        if self.state.data.deferred {
+            let transaction_id = random_string(32);
+            let responses = attestations
+                .iter()
+                .map(|_| CredentialResponse::Deferred {
+                    transaction_id: transaction_id.clone(),
+                })
+                .collect();
+
+            let session_data = self.state.data.clone();
+            let next = self.transition(Deferred {
+                access_token: session_data.access_token,
+                dpop_public_key: session_data.dpop_public_key,
+                dpop_nonce: session_data.dpop_nonce,
+                transaction_id,
+                attestations,
+                approved: false,
+                refresh_token: session_data.refresh_token,
+                refresh_subject: session_data.refresh_subject,
+                wallet_id: session_data.wallet_id,
+                attestation_challenge: session_data.attestation_challenge,
+            });
+
+            return (Ok(responses), next.into());
+        }
+
+        let holder_public_keys = attestations
+            .iter()
+            .map(|attestation| attestation.holder_public_key)
+            .collect();
+        let notification_id = random_string(32);
+        let result = sign_attestations(attestations, &notification_id, issuer_data).await;
+
+        // In case of success, transition the session to refreshable, or to done if no refresh token was handed out.
+        // Either way the client won't be able to reuse its access token in more requests to this endpoint. (The
+        // OpenID4VCI and OAuth specs allow reuse of access tokens, but don't forbid that a server doesn't allow that.)
+        // It can only use it to send notifications about the issued attestations.
+        let next = match &result {
+            Ok(_) => {
+                let session_data = self.state.data.clone();
+                self.transition_issued(
+                    session_data.refresh_token.zip(session_data.refresh_subject),
+                    holder_public_keys,
+                    session_data.wallet_id,
+                    NotificationData::new(
+                        notification_id,
+                        session_data.access_token,
+                        session_data.dpop_public_key,
+                        session_data.dpop_nonce,
+                    ),
+                )
+            }
+            Err(err) => self.transition_fail(err).into(),
+        };
+
+        (result, next)
+    }
+}
+
+impl From<Session<Deferred>> for SessionState<IssuanceData> {
+    fn from(value: Session<Deferred>) -> Self {
+        SessionState {
+            data: IssuanceData::Deferred(value.state.data),
+            token: value.state.token,
+            last_active: value.state.last_active,
+            version: value.state.version,
+        }
+    }
+}
+
+impl TryFrom<SessionState<IssuanceData>> for Session<Deferred> {
+    type Error = IssuanceError;
+
+    fn try_from(value: SessionState<IssuanceData>) -> Result<Self, Self::Error> {
+        let IssuanceData::Deferred(session_data) = value.data else {
+            return Err(IssuanceError::UnexpectedState);
+        };
+        Ok(Session::<Deferred> {
+            state: SessionState {
+                data: session_data,
+                token: value.token,
+                last_active: value.last_active,
+                version: value.version,
+            },
+        })
+    }
+}
+
+impl Session<Deferred> {
+    /// Process the wallet's request for the deferred attestations. If issuance has not yet been approved, this
+    /// returns [`CredentialRequestError::IssuancePending`] and no next state, so that the wallet can try again later.
+    pub async fn process_deferred_credential(
+        self,
+        deferred_request: DeferredCredentialRequest,
+        access_token: AccessToken,
+        dpop: Dpop,
+        issuer_data: &IssuerData<impl KeyRing>,
+    ) -> (
+        Result<CredentialResponses, CredentialRequestError>,
+        Option<SessionState<IssuanceData>>,
+    ) {
+        let notification_id = random_string(32);
         let result = self
-            .process_batch_credential_inner(credential_requests, access_token, dpop, issuer_data)
+            .process_deferred_credential_inner(deferred_request, access_token, dpop, &notification_id, issuer_data)
             .await;
 
-        // In case of success, transition the session to done. This means the client won't be able to reuse its access
-        // token in more requests to this endpoint. (The OpenID4VCI and OAuth specs allow reuse of access tokens, but
-        // don't forbid that a server doesn't allow that.)
         let next = match &result {
-            Ok(_) => self.transition(Done {
-                session_result: SessionResult::Done,
-            }),
-            Err(err) => self.transition_fail(err),
+            Ok(_) => {
+                let session_data = self.state.data.clone();
+                let holder_public_keys = session_data
+                    .attestations
+                    .iter()
+                    .map(|attestation| attestation.holder_public_key)
+                    .collect();
+                Some(self.transition_issued(
+                    session_data.refresh_token.zip(session_data.refresh_subject),
+                    holder_public_keys,
+                    session_data.wallet_id,
+                    NotificationData::new(
+                        notification_id,
+                        session_data.access_token,
+                        session_data.dpop_public_key,
+                        session_data.dpop_nonce,
+                    ),
+                ))
+            }
+            Err(CredentialRequestError::IssuancePending) => None,
+            Err(err) => Some(self.transition_fail(err).into()),
         };
 
         (result, next)
     }
 
-    async fn process_batch_credential_inner(
+    async fn process_deferred_credential_inner(
         &self,
-        credential_requests: CredentialRequests,
+        deferred_request: DeferredCredentialRequest,
         access_token: AccessToken,
         dpop: Dpop,
+        notification_id: &str,
         issuer_data: &IssuerData<impl KeyRing>,
     ) -> Result<CredentialResponses, CredentialRequestError> {
         let session_data = self.session_data();
@@ -701,38 +1658,350 @@ impl Session<WaitingForResponse> {
 
         dpop.verify_expecting_key(
             &session_data.dpop_public_key,
-            &issuer_data.server_url.join("batch_credential"),
+            &issuer_data.server_url.join("deferred_credential"),
             &Method::POST,
             Some(&access_token),
             Some(&session_data.dpop_nonce),
         )
         .map_err(|err| CredentialRequestError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
 
-        let credential_responses = try_join_all(
-            credential_requests
-                .credential_requests
-                .as_ref()
-                .iter()
-                .zip(session_data.attestation_previews.iter().flat_map(|preview| {
-                    itertools::repeat_n::<&UnsignedMdoc>(preview.as_ref(), preview.copy_count().into())
-                }))
-                .map(|(cred_req, unsigned_mdoc)| async move {
-                    verify_pop_and_sign_attestation(&session_data.c_nonce, cred_req, unsigned_mdoc.clone(), issuer_data)
-                        .await
-                }),
-        )
-        .await?;
+        if deferred_request.transaction_id != session_data.transaction_id {
+            return Err(CredentialRequestError::InvalidTransactionId);
+        }
+
+        if !session_data.approved {
+            return Err(CredentialRequestError::IssuancePending);
+        }
+
+        let credential_responses =
+            sign_attestations(session_data.attestations.clone(), notification_id, issuer_data).await?;
 
         Ok(CredentialResponses { credential_responses })
     }
 }
 
+impl From<Session<Refreshable>> for SessionState<IssuanceData> {
+    fn from(value: Session<Refreshable>) -> Self {
+        SessionState {
+            data: IssuanceData::Refreshable(value.state.data),
+            token: value.state.token,
+            last_active: value.state.last_active,
+            version: value.state.version,
+        }
+    }
+}
+
+impl TryFrom<SessionState<IssuanceData>> for Session<Refreshable> {
+    type Error = IssuanceError;
+
+    fn try_from(value: SessionState<IssuanceData>) -> Result<Self, Self::Error> {
+        let IssuanceData::Refreshable(session_data) = value.data else {
+            return Err(IssuanceError::UnexpectedState);
+        };
+        Ok(Session::<Refreshable> {
+            state: SessionState {
+                data: session_data,
+                token: value.token,
+                last_active: value.last_active,
+                version: value.version,
+            },
+        })
+    }
+}
+
+impl Session<Refreshable> {
+    /// Process a token request using the refresh token, returning a token response containing the attestations with
+    /// the current attributes of the subject and a new refresh token. The wallet can then retrieve the attestations
+    /// from the batch credential endpoint. Until it does, the current refresh token remains valid.
+    pub async fn process_refresh_token_request(
+        self,
+        refresh_token: RefreshToken,
+        dpop: Dpop,
+        wallet_id: Option<String>,
+        attr_service: &impl AttributeService,
+        server_url: &BaseUrl,
+    ) -> Result<(TokenResponseWithPreviews, String, Session<Refreshable>), TokenRequestError> {
+        let session_data = self.session_data();
+
+        // Compare the refresh tokens in constant time, to prevent them from leaking through timing differences.
+        constant_time::verify_slices_are_equal(
+            session_data.refresh_token.as_ref().as_bytes(),
+            refresh_token.as_ref().as_bytes(),
+        )
+        .map_err(|_| TokenRequestError::InvalidRefreshToken)?;
+
+        // The refresh token is bound to the DPoP key that the wallet used during issuance.
+        dpop.verify_expecting_key(
+            &session_data.dpop_public_key,
+            &server_url.join("token"),
+            &Method::POST,
+            None,
+            None,
+        )
+        .map_err(|err| TokenRequestError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
+
+        let attestation_previews = attr_service
+            .refreshed_attributes(&session_data.refresh_subject)
+            .await
+            .map_err(|e| TokenRequestError::AttributeService(Box::new(e)))?;
+
+        let code = refresh_token.code().ok_or(TokenRequestError::InvalidRefreshToken)?;
+        let refresh = Refresh {
+            access_token: AccessToken::new(&code),
+            c_nonce: random_string(32),
+            dpop_nonce: random_string(32),
+            refresh_token: RefreshToken::new(&code),
+        };
+
+        let response = TokenResponseWithPreviews {
+            token_response: TokenResponse {
+                refresh_token: Some(refresh.refresh_token.clone()),
+                ..TokenResponse::new(refresh.access_token.clone(), refresh.c_nonce.clone())
+            },
+            attestation_previews,
+        };
+        let dpop_nonce = refresh.dpop_nonce.clone();
+
+        let session_data = session_data.clone();
+        let next = self.transition(Refreshable {
+            refresh: Some(refresh),
+            wallet_id: wallet_id.or(session_data.wallet_id),
+            ..session_data
+        });
+
+        Ok((response, dpop_nonce, next))
+    }
+
+    /// Issue the refreshed attestations, which contain the current attributes of the subject. If this fails, the
+    /// session remains refreshable using the current refresh token.
+    pub async fn process_batch_credential(
+        self,
+        credential_requests: CredentialRequests,
+        access_token: AccessToken,
+        dpop: Dpop,
+        attestation_previews: Result<NonEmpty<Vec<AttestationPreview>>, CredentialRequestError>,
+        issuer_data: &IssuerData<impl KeyRing>,
+    ) -> (
+        Result<CredentialResponses, CredentialRequestError>,
+        SessionState<IssuanceData>,
+    ) {
+        let attestations = attestation_previews.and_then(|attestation_previews| {
+            self.process_batch_credential_inner(
+                credential_requests,
+                access_token,
+                dpop,
+                attestation_previews.as_ref(),
+                issuer_data,
+            )
+        });
+
+        let session_data = self.state.data.clone();
+        let (attestations, refresh) = match (attestations, session_data.refresh) {
+            (Ok(attestations), Some(refresh)) => (attestations, refresh),
+            // Credential requests are only accepted while refreshing, see `process_batch_credential_inner()`.
+            (Ok(_), None) => unreachable!(),
+            (Err(err), _) => return (Err(err), self.transition_refresh_failed().into()),
+        };
+
+        let holder_public_keys = attestations
+            .iter()
+            .map(|attestation| attestation.holder_public_key)
+            .collect();
+        let notification_id = random_string(32);
+        let result = sign_attestations(attestations, &notification_id, issuer_data).await;
+
+        // Only now that the refreshed attestations have been issued does the new refresh token replace the current one.
+        // The events of which the wallet notified the issuer earlier in this session are kept, as they still apply.
+        let next = match result {
+            Ok(_) => self.transition_issued(
+                Some((refresh.refresh_token, session_data.refresh_subject)),
+                holder_public_keys,
+                session_data.wallet_id,
+                NotificationData {
+                    events: session_data
+                        .notification
+                        .map(|notification| notification.events)
+                        .unwrap_or_default(),
+                    ..NotificationData::new(
+                        notification_id,
+                        refresh.access_token,
+                        session_data.dpop_public_key,
+                        refresh.dpop_nonce,
+                    )
+                },
+            ),
+            Err(_) => self.transition_refresh_failed().into(),
+        };
+
+        (
+            result.map(|credential_responses| CredentialResponses { credential_responses }),
+            next,
+        )
+    }
+
+    fn process_batch_credential_inner(
+        &self,
+        credential_requests: CredentialRequests,
+        access_token: AccessToken,
+        dpop: Dpop,
+        attestation_previews: &[AttestationPreview],
+        issuer_data: &IssuerData<impl KeyRing>,
+    ) -> Result<Vec<PendingAttestation>, CredentialRequestError> {
+        let session_data = self.session_data();
+
+        // Check authorization of the request
+        let refresh = session_data
+            .refresh
+            .as_ref()
+            .ok_or(CredentialRequestError::Unauthorized)?;
+        if refresh.access_token != access_token {
+            return Err(CredentialRequestError::Unauthorized);
+        }
+
+        dpop.verify_expecting_key(
+            &session_data.dpop_public_key,
+            &issuer_data.server_url.join("batch_credential"),
+            &Method::POST,
+            Some(&access_token),
+            Some(&refresh.dpop_nonce),
+        )
+        .map_err(|err| CredentialRequestError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
+
+        // The refreshed attestations are bound to the keys to which they were bound originally, in the same order.
+        let request_count = credential_requests.credential_requests.as_ref().len();
+        let expected_count = session_data.holder_public_keys.len();
+        if request_count != expected_count || unsigned_mdoc_copies(attestation_previews).count() != expected_count {
+            return Err(CredentialRequestError::UnexpectedCredentialRequestCount {
+                found: request_count,
+                expected: expected_count,
+            });
+        }
+
+        // Unless the wallet proves possession of a new key, the attestation is bound to the original key. The former
+        // allows the wallet to replenish its copies of the attestations.
+        credential_requests
+            .credential_requests
+            .as_ref()
+            .iter()
+            .zip(unsigned_mdoc_copies(attestation_previews))
+            .zip(&session_data.holder_public_keys)
+            .map(|((cred_req, unsigned_mdoc), holder_public_key)| {
+                let holder_public_key = match cred_req.proof {
+                    Some(_) => {
+                        let holder_public_key = verify_pop(&refresh.c_nonce, cred_req, unsigned_mdoc, issuer_data)?;
+                        issuer_data.verify_key_attestation(
+                            cred_req,
+                            &holder_public_key,
+                            session_data.wallet_id.as_deref(),
+                        )?;
+                        holder_public_key
+                    }
+                    None => {
+                        check_credential_request(cred_req, unsigned_mdoc)?;
+                        *holder_public_key
+                    }
+                };
+
+                Ok(PendingAttestation {
+                    unsigned_mdoc: unsigned_mdoc.clone(),
+                    holder_public_key,
+                })
+            })
+            .collect()
+    }
+
+    /// Abandon the refresh that is in progress, keeping the current refresh token valid.
+    fn transition_refresh_failed(self) -> Session<Refreshable> {
+        let session_data = self.state.data.clone();
+        self.transition(Refreshable {
+            refresh: None,
+            ..session_data
+        })
+    }
+}
+
+impl NotificationData {
+    fn new(
+        notification_id: String,
+        access_token: AccessToken,
+        dpop_public_key: VerifyingKey,
+        dpop_nonce: String,
+    ) -> Self {
+        Self {
+            notification_id,
+            access_token,
+            dpop_public_key,
+            dpop_nonce,
+            events: vec![],
+        }
+    }
+
+    /// Check the authorization and contents of a notification request, and record its event.
+    fn process_notification_request(
+        &mut self,
+        notification_request: NotificationRequest,
+        access_token: AccessToken,
+        dpop: Dpop,
+        server_url: &BaseUrl,
+    ) -> Result<(), NotificationError> {
+        if self.access_token != access_token {
+            return Err(NotificationError::Unauthorized);
+        }
+
+        dpop.verify_expecting_key(
+            &self.dpop_public_key,
+            &server_url.join("notification"),
+            &Method::POST,
+            Some(&access_token),
+            Some(&self.dpop_nonce),
+        )
+        .map_err(|err| NotificationError::IssuanceError(IssuanceError::DpopInvalid(err)))?;
+
+        if notification_request.notification_id != self.notification_id {
+            return Err(NotificationError::InvalidNotificationId);
+        }
+
+        info!(
+            "Received issuance notification: {:?} ({})",
+            notification_request.event,
+            notification_request
+                .event_description
+                .as_deref()
+                .unwrap_or("no description")
+        );
+        self.events.push(notification_request.event);
+
+        Ok(())
+    }
+}
+
+impl IssuanceData {
+    /// The notification data of the session, if attestations were issued in it.
+    fn notification_mut(&mut self) -> Option<&mut NotificationData> {
+        match self {
+            Self::Refreshable(Refreshable { notification, .. }) | Self::Done(Done { notification, .. }) => {
+                notification.as_mut()
+            }
+            Self::Created(_) | Self::WaitingForResponse(_) | Self::Deferred(_) => None,
+        }
+    }
+}
+
+/// Returns the unsigned mdocs of the attestation previews, each repeated as many times as copies are to be issued. The
+/// wallet sends a credential request for each of these, in this order.
+fn unsigned_mdoc_copies(attestation_previews: &[AttestationPreview]) -> impl Iterator<Item = &UnsignedMdoc> {
+    attestation_previews
+        .iter()
+        .flat_map(|preview| itertools::repeat_n::<&UnsignedMdoc>(preview.as_ref(), preview.copy_count().into()))
+}
+
 impl From<Session<Done>> for SessionState<IssuanceData> {
     fn from(value: Session<Done>) -> Self {
         SessionState {
             data: IssuanceData::Done(value.state.data),
             token: value.state.token,
             last_active: value.state.last_active,
+            version: value.state.version,
         }
     }
 }
@@ -742,7 +2011,38 @@ impl<T: IssuanceState> Session<T> {
     /// Transition `self` to a new state, consuming the old state, also updating the `last_active` timestamp.
     pub fn transition<NewT: IssuanceState>(self, new_state: NewT) -> Session<NewT> {
         Session {
-            state: SessionState::new(self.state.token, new_state),
+            state: self.state.transition(new_state),
+        }
+    }
+
+    /// Transition `self` after the attestations have been issued: to [`Refreshable`] if a refresh token was handed out
+    /// to the wallet along with the subject of the attestations, and to [`Done`] otherwise.
+    fn transition_issued(
+        self,
+        refresh: Option<(RefreshToken, String)>,
+        holder_public_keys: Vec<VerifyingKey>,
+        wallet_id: Option<String>,
+        notification: NotificationData,
+    ) -> SessionState<IssuanceData> {
+        match refresh {
+            Some((refresh_token, refresh_subject)) => self
+                .transition(Refreshable {
+                    refresh_token,
+                    dpop_public_key: notification.dpop_public_key,
+                    refresh_subject,
+                    holder_public_keys,
+                    notification: Some(notification),
+                    refresh: None,
+                    wallet_id,
+                    attestation_challenge: None,
+                })
+                .into(),
+            None => self
+                .transition(Done {
+                    session_result: SessionResult::Done,
+                    notification: Some(notification),
+                })
+                .into(),
         }
     }
 
@@ -751,6 +2051,7 @@ impl<T: IssuanceState> Session<T> {
             session_result: SessionResult::Failed {
                 error: error.to_string(),
             },
+            notification: None,
         })
     }
 
@@ -759,12 +2060,11 @@ impl<T: IssuanceState> Session<T> {
     }
 }
 
-pub(crate) async fn verify_pop_and_sign_attestation(
-    c_nonce: &str,
+/// Check that the credential request matches the attestation to be issued.
+fn check_credential_request(
     cred_req: &CredentialRequest,
-    unsigned_mdoc: UnsignedMdoc,
-    issuer_data: &IssuerData<impl KeyRing>,
-) -> Result<CredentialResponse, CredentialRequestError> {
+    unsigned_mdoc: &UnsignedMdoc,
+) -> Result<(), CredentialRequestError> {
     if !matches!(cred_req.format, Format::MsoMdoc) {
         return Err(CredentialRequestError::UnsupportedCredentialFormat(cred_req.format));
     }
@@ -778,7 +2078,20 @@ pub(crate) async fn verify_pop_and_sign_attestation(
         return Err(CredentialRequestError::DoctypeMismatch);
     }
 
-    let pubkey = cred_req
+    Ok(())
+}
+
+/// Check the proof of possession of the wallet's private key for the attestation to be issued, returning the
+/// corresponding public key.
+fn verify_pop(
+    c_nonce: &str,
+    cred_req: &CredentialRequest,
+    unsigned_mdoc: &UnsignedMdoc,
+    issuer_data: &IssuerData<impl KeyRing>,
+) -> Result<VerifyingKey, CredentialRequestError> {
+    check_credential_request(cred_req, unsigned_mdoc)?;
+
+    cred_req
         .proof
         .as_ref()
         .ok_or(CredentialRequestError::MissingCredentialRequestPoP)?
@@ -786,8 +2099,35 @@ pub(crate) async fn verify_pop_and_sign_attestation(
             c_nonce,
             &issuer_data.accepted_wallet_client_ids,
             &issuer_data.credential_issuer_identifier,
-        )?;
-    let mdoc_public_key = (&pubkey)
+        )
+}
+
+/// Sign the attestations, including the same notification ID in each of the credential responses. Since all
+/// attestations of a session are issued at once, the wallet notifies the issuer about all of them at once.
+async fn sign_attestations(
+    attestations: Vec<PendingAttestation>,
+    notification_id: &str,
+    issuer_data: &IssuerData<impl KeyRing>,
+) -> Result<Vec<CredentialResponse>, CredentialRequestError> {
+    try_join_all(
+        attestations
+            .into_iter()
+            .map(|attestation| sign_attestation(attestation, notification_id, issuer_data)),
+    )
+    .await
+}
+
+async fn sign_attestation(
+    attestation: PendingAttestation,
+    notification_id: &str,
+    issuer_data: &IssuerData<impl KeyRing>,
+) -> Result<CredentialResponse, CredentialRequestError> {
+    let PendingAttestation {
+        unsigned_mdoc,
+        holder_public_key,
+    } = attestation;
+
+    let mdoc_public_key = (&holder_public_key)
         .try_into()
         .map_err(CredentialRequestError::CoseKeyConversion)?;
 
@@ -803,7 +2143,8 @@ pub(crate) async fn verify_pop_and_sign_attestation(
         .map_err(CredentialRequestError::AttestationSigning)?;
 
     Ok(CredentialResponse::MsoMdoc {
-        credential: issuer_signed.into(),
+        credential: Box::new(issuer_signed.into()),
+        notification_id: Some(notification_id.to_string()),
     })
 }
 
@@ -880,4 +2221,20 @@ mod tests {
         assert_eq!(result, input);
         assert!(logs_contain("Issuance error: MyError"));
     }
+
+    #[test]
+    fn test_tx_code_hash() {
+        let tx_code_hash = TxCodeHash::new("123456");
+
+        assert!(tx_code_hash.verify("123456"));
+        assert!(!tx_code_hash.verify("654321"));
+        assert!(!tx_code_hash.verify(""));
+
+        // The same code should result in a different hash, as it is salted.
+        assert_ne!(TxCodeHash::new("123456").hash, tx_code_hash.hash);
+
+        // The code itself should not be stored.
+        let serialized = serde_json::to_string(&tx_code_hash).unwrap();
+        assert!(!serialized.contains("123456"));
+    }
 }
@@ -169,10 +169,12 @@ pub enum JwtX5cError {
 }
 
 /// Verify the JWS against the provided trust anchors, using the X.509 certificate(s) present in the `x5c` JWT header.
+/// The leaf certificate must have the specified [`CertificateUsage`].
 pub fn verify_against_trust_anchors<T: DeserializeOwned, A: ToString>(
     jwt: &Jwt<T>,
     audience: &[A],
     trust_anchors: &[TrustAnchor],
+    usage: CertificateUsage,
     time: &impl Generator<DateTime<Utc>>,
 ) -> Result<(T, Certificate), JwtX5cError> {
     let header = jsonwebtoken::decode_header(&jwt.0).map_err(JwtError::Validation)?;
@@ -193,7 +195,7 @@ pub fn verify_against_trust_anchors<T: DeserializeOwned, A: ToString>(
     let leaf_cert = certs.pop().ok_or(JwtX5cError::MissingCertificates)?;
     let intermediate_certs = certs.iter().map(|cert| cert.as_bytes()).collect_vec();
     leaf_cert
-        .verify(CertificateUsage::ReaderAuth, &intermediate_certs, time, trust_anchors)
+        .verify(usage, &intermediate_certs, time, trust_anchors)
         .map_err(JwtX5cError::CertificateValidation)?;
 
     // The leaf certificate is trusted, we can now use its public key to verify the JWS.
@@ -248,7 +250,7 @@ mod tests {
         software_key_factory::SoftwareKeyFactory,
         utils::{
             keys::{KeyFactory, MdocEcdsaKey},
-            x509::CertificateError,
+            x509::{CertificateError, CertificateUsage},
         },
     };
     use wallet_common::{
@@ -269,9 +271,14 @@ mod tests {
         let jwt = sign_with_certificate(&payload, &keypair).await.unwrap();
 
         let audience: &[String] = &[];
-        let (deserialized, leaf_cert) =
-            verify_against_trust_anchors(&jwt, audience, &[ca.certificate().try_into().unwrap()], &TimeGenerator)
-                .unwrap();
+        let (deserialized, leaf_cert) = verify_against_trust_anchors(
+            &jwt,
+            audience,
+            &[ca.certificate().try_into().unwrap()],
+            CertificateUsage::ReaderAuth,
+            &TimeGenerator,
+        )
+        .unwrap();
 
         assert_eq!(deserialized, payload);
         assert_eq!(leaf_cert, *keypair.certificate());
@@ -292,6 +299,7 @@ mod tests {
             &jwt,
             audience,
             &[other_ca.certificate().try_into().unwrap()],
+            CertificateUsage::ReaderAuth,
             &TimeGenerator,
         )
         .unwrap_err();
@@ -3,12 +3,14 @@ use serde::{Deserialize, Serialize};
 // Data structures implemening OAuth/OpenID(4VCI) protocol messages.
 pub mod authorization;
 pub mod credential;
+pub mod credential_offer;
 pub mod token;
 
 // Cryptographic tools.
 pub mod dpop;
 pub mod jwt;
 pub mod pkce;
+pub mod wallet_attestation;
 
 // Issuance code for the server and client.
 pub mod issuance_session;
@@ -22,6 +24,7 @@ pub mod metadata;
 
 pub mod oidc;
 
+pub mod dcql;
 pub mod disclosure_session;
 pub mod openid4vp;
 pub mod presentation_exchange;
@@ -3,8 +3,31 @@ use std::collections::HashMap;
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use error_category::ErrorCategory;
+use nl_wallet_mdoc::{holder::TrustAnchor, server_keys::KeyPair, utils::x509::CertificateUsage};
 use serde_with::skip_serializing_none;
-use wallet_common::{jwt::Jwt, urls::BaseUrl};
+use wallet_common::{
+    generator::Generator,
+    jwt::{Jwt, JwtError},
+    urls::BaseUrl,
+};
+
+use crate::{
+    jwt::{self, JwtX5cError},
+    openid4vp::{VpAlgValues, VpEncValues},
+};
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(critical)]
+pub enum IssuerMetadataError {
+    #[error("error verifying signed metadata: {0}")]
+    #[category(defer)]
+    SignedMetadata(#[from] JwtX5cError),
+    #[error("metadata is not signed")]
+    UnsignedMetadata,
+    #[error("signed metadata is about Credential Issuer {found}, expected {expected}")]
+    CredentialIssuerMismatch { expected: BaseUrl, found: String },
+}
 
 /// Credential issuer metadata, as per
 /// https://openid.net/specs/openid-4-verifiable-credential-issuance-1_0.html#name-credential-issuer-metadata.
@@ -32,6 +55,75 @@ impl IssuerMetadata {
             .json()
             .await
     }
+
+    /// Construct metadata containing the specified [`IssuerData`] both as plain JSON and in the `signed_metadata` JWT,
+    /// signed with the specified key pair. The certificate of the key pair is included in the `x5c` JWT header.
+    pub async fn new_signed(
+        issuer_config: IssuerData,
+        key_pair: &KeyPair,
+        time: &impl Generator<DateTime<Utc>>,
+    ) -> Result<Self, JwtError> {
+        let credential_issuer = issuer_config.credential_issuer.as_ref().to_string();
+        let claims = IssuerDataClaims {
+            iss: credential_issuer.clone(),
+            sub: credential_issuer,
+            iat: time.generate(),
+            issuer_config: issuer_config.clone(),
+        };
+        let signed_metadata = jwt::sign_with_certificate(&claims, key_pair).await?;
+
+        Ok(Self {
+            issuer_config,
+            signed_metadata: Some(signed_metadata),
+        })
+    }
+
+    /// Verify the `signed_metadata` JWT if present against the trust anchors, and return the [`IssuerData`] that
+    /// results from giving the fields in the JWT precedence over the corresponding plain JSON fields.
+    ///
+    /// If `signed_metadata_required` is `true`, metadata without the `signed_metadata` JWT is rejected, and fields
+    /// that are only present as plain JSON are disregarded. The issuer certificate in the JWT must be valid for
+    /// mdoc issuance and be about the specified Credential Issuer.
+    pub fn verify(
+        self,
+        credential_issuer: &BaseUrl,
+        trust_anchors: &[TrustAnchor],
+        signed_metadata_required: bool,
+        time: &impl Generator<DateTime<Utc>>,
+    ) -> Result<IssuerData, IssuerMetadataError> {
+        let Some(signed_metadata) = self.signed_metadata else {
+            if signed_metadata_required {
+                return Err(IssuerMetadataError::UnsignedMetadata);
+            }
+
+            return Ok(self.issuer_config);
+        };
+
+        let audience: &[String] = &[];
+        let (claims, _) =
+            jwt::verify_against_trust_anchors(&signed_metadata, audience, trust_anchors, CertificateUsage::Mdl, time)?;
+
+        if claims.sub != credential_issuer.as_ref().as_str() {
+            return Err(IssuerMetadataError::CredentialIssuerMismatch {
+                expected: credential_issuer.clone(),
+                found: claims.sub,
+            });
+        }
+        if claims.issuer_config.credential_issuer != *credential_issuer {
+            return Err(IssuerMetadataError::CredentialIssuerMismatch {
+                expected: credential_issuer.clone(),
+                found: claims.issuer_config.credential_issuer.to_string(),
+            });
+        }
+
+        let issuer_data = if signed_metadata_required {
+            claims.issuer_config
+        } else {
+            claims.issuer_config.or(self.issuer_config)
+        };
+
+        Ok(issuer_data)
+    }
 }
 
 #[skip_serializing_none]
@@ -93,6 +185,25 @@ pub struct IssuerData {
 }
 
 impl IssuerData {
+    /// Merge the fields of `self` with those of `other`, taking the optional fields from `other` only if they are
+    /// absent in `self`. Mandatory fields are always taken from `self`.
+    fn or(self, other: IssuerData) -> IssuerData {
+        IssuerData {
+            credential_issuer: self.credential_issuer,
+            authorization_servers: self.authorization_servers.or(other.authorization_servers),
+            credential_endpoint: self.credential_endpoint,
+            batch_credential_endpoint: self.batch_credential_endpoint.or(other.batch_credential_endpoint),
+            deferred_credential_endpoint: self.deferred_credential_endpoint.or(other.deferred_credential_endpoint),
+            notification_endpoint: self.notification_endpoint.or(other.notification_endpoint),
+            credential_response_encryption: self.credential_response_encryption,
+            credential_identifiers_supported: self
+                .credential_identifiers_supported
+                .or(other.credential_identifiers_supported),
+            display: self.display.or(other.display),
+            credential_configurations_supported: self.credential_configurations_supported,
+        }
+    }
+
     /// Returns a non-empty Vec of authorization servers.
     pub fn authorization_servers(&self) -> Vec<BaseUrl> {
         match self.authorization_servers {
@@ -121,19 +232,16 @@ pub struct IssuerDataClaims {
 
 /// Information about whether the Credential Issuer supports encryption of the Credential and Batch Credential Response
 /// on top of TLS.
-// For now we use plain strings for the first two fields below so the wallet can deserialize values that the issuer
-// sends. When we implement credential response encryption we should replace these with an enum listing implemented
-// algorithms.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CredentialResponseEncryption {
     /// Array containing a list of the JWE [RFC7516] encryption algorithms (`alg` values) [RFC7518] supported by the
     /// Credential and Batch Credential Endpoint to encode the Credential or Batch Credential Response in a JWT
     /// [RFC7519].
-    pub alg_values_supported: Vec<String>,
+    pub alg_values_supported: Vec<VpAlgValues>,
     /// Array containing a list of the JWE [RFC7516] encryption algorithms (`enc` values) [RFC7518] supported by the
     /// Credential and Batch Credential Endpoint to encode the Credential or Batch Credential Response in a JWT
     /// [RFC7519].
-    pub enc_values_supported: Vec<String>,
+    pub enc_values_supported: Vec<VpEncValues>,
 
     /// Boolean value specifying whether the Credential Issuer requires the additional encryption on top of TLS for the
     /// Credential Response. If the value is true, the Credential Issuer requires encryption for every Credential
@@ -362,11 +470,108 @@ pub struct BackgroundImage {
 mod tests {
     use assert_matches::assert_matches;
 
+    use nl_wallet_mdoc::{server_keys::KeyPair, utils::issuer_auth::IssuerRegistration};
+    use wallet_common::{generator::TimeGenerator, urls::BaseUrl};
+
     use crate::metadata::{
-        CredentialMetadata, CredentialSigningAlg, CryptographicBindingMethod, ProofSigningAlg, ProofType,
+        CredentialMetadata, CredentialSigningAlg, CryptographicBindingMethod, IssuerDisplay, NameLocale,
+        ProofSigningAlg, ProofType,
     };
 
-    use super::{CredentialFormat, IssuerMetadata};
+    use super::{CredentialFormat, IssuerMetadata, IssuerMetadataError};
+
+    async fn signed_mock_metadata(url: &BaseUrl) -> (IssuerMetadata, KeyPair) {
+        let ca = KeyPair::generate_issuer_mock_ca().unwrap();
+        let key_pair = ca.generate_issuer_mock(IssuerRegistration::new_mock().into()).unwrap();
+
+        let metadata = IssuerMetadata::new_signed(
+            IssuerMetadata::new_mock(url.clone()).issuer_config,
+            &key_pair,
+            &TimeGenerator,
+        )
+        .await
+        .unwrap();
+
+        (metadata, ca)
+    }
+
+    #[tokio::test]
+    async fn verify_signed_metadata() {
+        let url: BaseUrl = "https://issuer.example.com/".parse().unwrap();
+        let (mut metadata, ca) = signed_mock_metadata(&url).await;
+
+        // Tamper with the unsigned metadata: the fields from the JWT should take precedence, while the fields that
+        // are absent from the JWT should only be used when signed metadata is not required.
+        let tampered_endpoint = url.join_base_url("/tampered");
+        metadata.issuer_config.batch_credential_endpoint = Some(tampered_endpoint.clone());
+        metadata.issuer_config.display = Some(vec![IssuerDisplay {
+            name_locale: NameLocale {
+                name: Some("Tampered".to_string()),
+                locale: None,
+            },
+            logo: None,
+        }]);
+
+        let trust_anchors = &[ca.certificate().try_into().unwrap()];
+
+        let issuer_data = metadata
+            .clone()
+            .verify(&url, trust_anchors, false, &TimeGenerator)
+            .unwrap();
+        assert_eq!(
+            issuer_data.batch_credential_endpoint,
+            Some(url.join_base_url("/batch_credential"))
+        );
+        assert!(issuer_data.display.is_some());
+
+        let issuer_data = metadata.verify(&url, trust_anchors, true, &TimeGenerator).unwrap();
+        assert_eq!(
+            issuer_data.batch_credential_endpoint,
+            Some(url.join_base_url("/batch_credential"))
+        );
+        assert!(issuer_data.display.is_none());
+    }
+
+    #[tokio::test]
+    async fn verify_signed_metadata_error() {
+        let url: BaseUrl = "https://issuer.example.com/".parse().unwrap();
+        let (metadata, ca) = signed_mock_metadata(&url).await;
+
+        let other_ca = KeyPair::generate_issuer_mock_ca().unwrap();
+        let error = metadata
+            .clone()
+            .verify(
+                &url,
+                &[other_ca.certificate().try_into().unwrap()],
+                false,
+                &TimeGenerator,
+            )
+            .unwrap_err();
+        assert_matches!(error, IssuerMetadataError::SignedMetadata(_));
+
+        let other_url = "https://other-issuer.example.com/".parse().unwrap();
+        let error = metadata
+            .verify(
+                &other_url,
+                &[ca.certificate().try_into().unwrap()],
+                false,
+                &TimeGenerator,
+            )
+            .unwrap_err();
+        assert_matches!(error, IssuerMetadataError::CredentialIssuerMismatch { .. });
+    }
+
+    #[test]
+    fn verify_unsigned_metadata() {
+        let url: BaseUrl = "https://issuer.example.com/".parse().unwrap();
+        let metadata = IssuerMetadata::new_mock(url.clone());
+
+        let issuer_data = metadata.clone().verify(&url, &[], false, &TimeGenerator).unwrap();
+        assert_eq!(issuer_data.credential_issuer, url);
+
+        let error = metadata.verify(&url, &[], true, &TimeGenerator).unwrap_err();
+        assert_matches!(error, IssuerMetadataError::UnsignedMetadata);
+    }
 
     #[test]
     fn deserialize_example() {
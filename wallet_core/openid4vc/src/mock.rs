@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use indexmap::IndexSet;
+use url::Url;
 
 use nl_wallet_mdoc::{
     holder::{MdocCopies, TrustAnchor},
@@ -9,8 +10,12 @@ use nl_wallet_mdoc::{
 use wallet_common::urls::BaseUrl;
 
 use crate::{
-    issuance_session::{HttpVcMessageClient, IssuanceSession, IssuanceSessionError},
-    metadata::{CredentialResponseEncryption, IssuerData, IssuerMetadata},
+    credential::NotificationEvent,
+    issuance_session::{
+        AcceptedIssuance, DeferredIssuance, HttpVcMessageClient, IssuanceAuthorization, IssuanceNotification,
+        IssuanceRefresh, IssuanceSession, IssuanceSessionError,
+    },
+    metadata::{CredentialMetadata, CredentialResponseEncryption, IssuerData, IssuerMetadata},
     oidc::Config,
     token::{AttestationPreview, TokenRequest, TokenRequestGrantType},
 };
@@ -20,19 +25,45 @@ use crate::{
 
 mockall::mock! {
     pub IssuanceSession {
+        pub fn authorize(
+            credential_issuer: BaseUrl,
+            issuer_state: Option<String>,
+        ) -> Result<(IssuanceAuthorization, Url), IssuanceSessionError>;
+
         pub fn start() -> Result<(Self, Vec<AttestationPreview>), IssuanceSessionError>
         where
             Self: Sized;
 
+        pub fn metadata(&self) -> HashMap<String, CredentialMetadata>;
+
         pub fn accept(
             &self,
-        ) -> Result<Vec<MdocCopies>, IssuanceSessionError>;
+        ) -> Result<AcceptedIssuance, IssuanceSessionError>;
+
+        pub fn accept_deferred(deferred_issuance: DeferredIssuance) -> Result<AcceptedIssuance, IssuanceSessionError>;
+
+        pub fn refresh(refresh: IssuanceRefresh) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError>;
+
+        pub fn replenish(refresh: IssuanceRefresh) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError>;
+
+        pub fn notify(notification: IssuanceNotification, event: NotificationEvent) -> Result<(), IssuanceSessionError>;
 
         pub fn reject(self) -> Result<(), IssuanceSessionError>;
     }
 }
 
 impl IssuanceSession for MockIssuanceSession {
+    async fn start_authorization(
+        _: HttpVcMessageClient,
+        credential_issuer: BaseUrl,
+        _: &[String],
+        issuer_state: Option<String>,
+        _: Url,
+        _: &[TrustAnchor<'_>],
+    ) -> Result<(IssuanceAuthorization, Url), IssuanceSessionError> {
+        Self::authorize(credential_issuer, issuer_state)
+    }
+
     async fn start_issuance(
         _: HttpVcMessageClient,
         _: BaseUrl,
@@ -45,15 +76,52 @@ impl IssuanceSession for MockIssuanceSession {
         Self::start()
     }
 
+    fn credential_metadata(&self) -> HashMap<String, CredentialMetadata> {
+        self.metadata()
+    }
+
     async fn accept_issuance<K: MdocEcdsaKey>(
         &self,
         _: &[TrustAnchor<'_>],
         _: impl KeyFactory<Key = K>,
         _: BaseUrl,
-    ) -> Result<Vec<MdocCopies>, IssuanceSessionError> {
+    ) -> Result<AcceptedIssuance, IssuanceSessionError> {
         self.accept()
     }
 
+    async fn accept_deferred_issuance<K: MdocEcdsaKey>(
+        _: HttpVcMessageClient,
+        deferred_issuance: DeferredIssuance,
+        _: &[TrustAnchor<'_>],
+    ) -> Result<AcceptedIssuance, IssuanceSessionError> {
+        Self::accept_deferred(deferred_issuance)
+    }
+
+    async fn refresh_issuance<K: MdocEcdsaKey>(
+        _: HttpVcMessageClient,
+        refresh: IssuanceRefresh,
+        _: &[TrustAnchor<'_>],
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError> {
+        Self::refresh(refresh)
+    }
+
+    async fn replenish_issuance<K: MdocEcdsaKey>(
+        _: HttpVcMessageClient,
+        refresh: IssuanceRefresh,
+        _: &[TrustAnchor<'_>],
+        _: impl KeyFactory<Key = K>,
+    ) -> Result<(Vec<MdocCopies>, IssuanceRefresh), IssuanceSessionError> {
+        Self::replenish(refresh)
+    }
+
+    async fn notify_issuance(
+        _: HttpVcMessageClient,
+        notification: IssuanceNotification,
+        event: NotificationEvent,
+    ) -> Result<(), IssuanceSessionError> {
+        Self::notify(notification, event)
+    }
+
     async fn reject_issuance(self) -> Result<(), IssuanceSessionError> {
         self.reject()
     }
@@ -101,6 +169,8 @@ impl Config {
             op_policy_uri: None,
             op_tos_uri: None,
             code_challenge_methods_supported: None,
+            pushed_authorization_request_endpoint: Some(issuer.join("/par")),
+            require_pushed_authorization_requests: false,
         }
     }
 }
@@ -113,8 +183,8 @@ impl IssuerMetadata {
                 authorization_servers: None,
                 credential_endpoint: url.join_base_url("/credential"),
                 batch_credential_endpoint: Some(url.join_base_url("/batch_credential")),
-                deferred_credential_endpoint: None,
-                notification_endpoint: None,
+                deferred_credential_endpoint: Some(url.join_base_url("/deferred_credential")),
+                notification_endpoint: Some(url.join_base_url("/notification")),
                 credential_response_encryption: CredentialResponseEncryption {
                     alg_values_supported: vec![],
                     enc_values_supported: vec![],
@@ -134,6 +204,7 @@ impl TokenRequest {
         TokenRequest {
             grant_type: TokenRequestGrantType::PreAuthorizedCode {
                 pre_authorized_code: "123".to_string().into(),
+                tx_code: None,
             },
             code_verifier: None,
             client_id: None,
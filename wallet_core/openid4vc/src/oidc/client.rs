@@ -123,7 +123,10 @@ impl<P: PkcePair> OidcClient for HttpOidcClient<P> {
     fn into_token_request(self, received_redirect_uri: &Url) -> Result<TokenRequest, OidcError> {
         let pre_authorized_code = self.authorization_code(received_redirect_uri)?;
         let token_request = TokenRequest {
-            grant_type: TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code },
+            grant_type: TokenRequestGrantType::PreAuthorizedCode {
+                pre_authorized_code,
+                tx_code: None,
+            },
             code_verifier: Some(self.pkce_pair.into_code_verifier()),
             client_id: Some(self.client_id),
             redirect_uri: Some(self.redirect_uri),
@@ -158,6 +161,7 @@ impl<P: PkcePair> HttpOidcClient<P> {
             redirect_uri: Some(self.redirect_uri.clone()),
             state: Some(self.state.clone()),
             authorization_details: None,
+            issuer_state: None,
             request_uri: None,
             code_challenge: Some(PkceCodeChallenge::S256 {
                 code_challenge: self.pkce_pair.code_challenge().to_string(),
@@ -368,7 +372,7 @@ mod tests {
         assert_eq!(token_request.redirect_uri, Some(REDIRECT_URI.parse().unwrap()));
         assert_matches!(
             token_request.grant_type,
-            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code } if pre_authorized_code.as_ref() == CODE
+            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code, .. } if pre_authorized_code.as_ref() == CODE
         );
     }
 
@@ -513,7 +517,8 @@ mod tests {
         assert_eq!(token_request.redirect_uri, Some(redirect_uri));
         assert_matches!(
             token_request.grant_type,
-            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code } if pre_authorized_code.as_ref() == "123"
+            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code, .. }
+                if pre_authorized_code.as_ref() == "123"
         );
     }
 
@@ -9,6 +9,8 @@ use wallet_common::urls::BaseUrl;
 
 use super::OidcError;
 
+const PRE_AUTHORIZED_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:pre-authorized_code";
+
 /// OpenID metadata as defind by https://openid.net/specs/openid-connect-discovery-1_0.html,
 /// to be published at `.well-known/openid-configuration`.
 #[skip_serializing_none]
@@ -93,9 +95,61 @@ pub struct Config {
     // This is a NONSTANDARD extension Google uses that is a part of the Oauth discovery draft
     #[serde(default)]
     pub code_challenge_methods_supported: Option<IndexSet<String>>,
+
+    // Defined in https://www.rfc-editor.org/rfc/rfc9126.html#section-5
+    #[serde(default)]
+    pub pushed_authorization_request_endpoint: Option<Url>,
+    #[serde(default)]
+    pub require_pushed_authorization_requests: bool,
 }
 
 impl Config {
+    /// Construct the metadata of an authorization server that only supports the pre-authorized code flow of
+    /// OpenID4VCI, in which the wallet only uses the token endpoint. Fields that are required by the spec but which
+    /// are not used in this flow are set to conventional values.
+    pub fn new_pre_authorized_code(issuer: &BaseUrl) -> Self {
+        Self {
+            issuer: issuer.clone(),
+            authorization_endpoint: issuer.join("/authorize"),
+            token_endpoint: issuer.join("/token"),
+            userinfo_endpoint: None,
+            jwks_uri: issuer.join("/jwks.json"),
+            registration_endpoint: None,
+            scopes_supported: None,
+            response_types_supported: IndexSet::new(),
+            response_modes_supported: None,
+            grant_types_supported: Some(IndexSet::from_iter([PRE_AUTHORIZED_CODE_GRANT_TYPE.to_string()])),
+            acr_values_supported: None,
+            subject_types_supported: IndexSet::new(),
+            id_token_signing_alg_values_supported: IndexSet::new(),
+            id_token_encryption_alg_values_supported: None,
+            id_token_encryption_enc_values_supported: None,
+            userinfo_signing_alg_values_supported: None,
+            userinfo_encryption_alg_values_supported: None,
+            userinfo_encryption_enc_values_supported: None,
+            request_object_signing_alg_values_supported: None,
+            request_object_encryption_alg_values_supported: None,
+            request_object_encryption_enc_values_supported: None,
+            token_endpoint_auth_methods_supported: None,
+            token_endpoint_auth_signing_alg_values_supported: None,
+            display_values_supported: None,
+            claim_types_supported: None,
+            claims_supported: None,
+            service_documentation: None,
+            claims_locales_supported: None,
+            ui_locales_supported: None,
+            claims_parameter_supported: false,
+            request_parameter_supported: false,
+            request_uri_parameter_supported: false,
+            require_request_uri_registration: false,
+            op_policy_uri: None,
+            op_tos_uri: None,
+            code_challenge_methods_supported: None,
+            pushed_authorization_request_endpoint: None,
+            require_pushed_authorization_requests: false,
+        }
+    }
+
     pub async fn discover(client: &reqwest::Client, issuer: &BaseUrl) -> Result<Self, OidcError> {
         // If the Issuer value contains a path component, any terminating / MUST be removed before
         // appending /.well-known/openid-configuration.
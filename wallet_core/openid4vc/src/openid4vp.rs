@@ -2,7 +2,7 @@ use std::string::FromUtf8Error;
 
 use base64::DecodeError;
 use chrono::{DateTime, Utc};
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use josekit::{
     jwe::{alg::ecdh_es::EcdhEsJweAlgorithm, JweHeader},
     jwk::{alg::ec::EcKeyPair, Jwk},
@@ -17,10 +17,10 @@ use nl_wallet_mdoc::{
     holder::TrustAnchor,
     utils::{
         serialization::CborBase64,
-        x509::{Certificate, CertificateError},
+        x509::{Certificate, CertificateError, CertificateUsage},
     },
     verifier::{DisclosedAttributes, ItemsRequests},
-    DeviceResponse, SessionTranscript,
+    DeviceResponse, DeviceResponseVersion, SessionTranscript,
 };
 use wallet_common::{
     generator::{Generator, TimeGenerator},
@@ -31,6 +31,7 @@ use wallet_common::{
 
 use crate::{
     authorization::{AuthorizationRequest, ResponseMode, ResponseType},
+    dcql::{self, DcqlConversionError},
     jwt::{self, JwtX5cError},
     presentation_exchange::{
         InputDescriptorMappingObject, PdConversionError, PresentationDefinition, PresentationSubmission, PsError,
@@ -78,7 +79,8 @@ pub struct VpAuthorizationRequest {
     #[serde(flatten)]
     pub oauth_request: AuthorizationRequest,
 
-    /// Contains requirements on the attestations and/or attributes to be disclosed.
+    /// Contains requirements on the attestations and/or attributes to be disclosed, either as a Presentation
+    /// Definition or as a DCQL query.
     #[serde(flatten)]
     pub presentation_definition: VpPresentationDefinition,
 
@@ -110,17 +112,74 @@ pub enum VpPresentationDefinition {
     Direct(PresentationDefinition),
     #[serde(rename = "presentation_definition_url")]
     Indirect(BaseUrl),
+    #[serde(rename = "dcql_query")]
+    Dcql(dcql::Query),
 }
 
 impl VpPresentationDefinition {
-    pub fn direct(self) -> Option<PresentationDefinition> {
+    pub fn direct(self) -> Option<VpQuery> {
         match self {
-            VpPresentationDefinition::Direct(pd) => Some(pd),
+            VpPresentationDefinition::Direct(pd) => Some(VpQuery::PresentationDefinition(pd)),
+            VpPresentationDefinition::Dcql(query) => Some(VpQuery::Dcql(query)),
             VpPresentationDefinition::Indirect(_) => None,
         }
     }
 }
 
+/// The query language that a verifier uses to request attributes in its Authorization Requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryLanguage {
+    /// Presentation Exchange, using the `presentation_definition` field.
+    #[default]
+    PresentationExchange,
+    /// Digital Credentials Query Language, using the `dcql_query` field.
+    Dcql,
+}
+
+/// The attributes requested in an [`IsoVpAuthorizationRequest`], in either of the supported query languages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VpQuery {
+    #[serde(rename = "presentation_definition")]
+    PresentationDefinition(PresentationDefinition),
+    #[serde(rename = "dcql_query")]
+    Dcql(dcql::Query),
+}
+
+impl VpQuery {
+    pub fn new(items_requests: &ItemsRequests, query_language: QueryLanguage) -> Self {
+        match query_language {
+            QueryLanguage::PresentationExchange => VpQuery::PresentationDefinition(items_requests.into()),
+            QueryLanguage::Dcql => VpQuery::Dcql(items_requests.into()),
+        }
+    }
+
+    pub fn query_language(&self) -> QueryLanguage {
+        match self {
+            VpQuery::PresentationDefinition(_) => QueryLanguage::PresentationExchange,
+            VpQuery::Dcql(_) => QueryLanguage::Dcql,
+        }
+    }
+
+    fn items_requests(&self) -> Result<ItemsRequests, AuthRequestValidationError> {
+        let items_requests = match self {
+            VpQuery::PresentationDefinition(pd) => pd.try_into()?,
+            VpQuery::Dcql(query) => query.try_into()?,
+        };
+
+        Ok(items_requests)
+    }
+}
+
+impl From<VpQuery> for VpPresentationDefinition {
+    fn from(value: VpQuery) -> Self {
+        match value {
+            VpQuery::PresentationDefinition(pd) => VpPresentationDefinition::Direct(pd),
+            VpQuery::Dcql(query) => VpPresentationDefinition::Dcql(query),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VpClientMetadata {
     #[serde(rename = "client_metadata")]
@@ -182,13 +241,13 @@ impl VpJwks {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VpAlgValues {
     #[serde(rename = "ECDH-ES")]
     EcdhEs,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, strum::Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
 pub enum VpEncValues {
     A128GCM,
     A192GCM,
@@ -276,6 +335,8 @@ pub enum AuthRequestValidationError {
     NoAttributesRequested,
     #[error("unsupported Presentation Definition: {0}")]
     UnsupportedPresentationDefinition(#[from] PdConversionError),
+    #[error("unsupported DCQL query: {0}")]
+    UnsupportedDcqlQuery(#[from] DcqlConversionError),
     #[error(
         "client_id from Authorization Request was {client_id}, should have been equal to SAN DNSName from X.509 \
          certificate ({dns_san})"
@@ -305,6 +366,7 @@ impl VpAuthorizationRequest {
             jws,
             &[VpAuthorizationRequestAudience::SelfIssued],
             trust_anchors,
+            CertificateUsage::ReaderAuth,
             &TimeGenerator,
         )?)
     }
@@ -354,7 +416,8 @@ pub struct IsoVpAuthorizationRequest {
     pub encryption_pubkey: Jwk,
     pub response_uri: BaseUrl,
     pub items_requests: ItemsRequests,
-    pub presentation_definition: PresentationDefinition,
+    #[serde(flatten)]
+    pub query: VpQuery,
     pub client_metadata: ClientMetadata,
     pub state: Option<String>,
     pub wallet_nonce: Option<String>,
@@ -363,6 +426,7 @@ pub struct IsoVpAuthorizationRequest {
 impl IsoVpAuthorizationRequest {
     pub fn new(
         items_requests: &ItemsRequests,
+        query_language: QueryLanguage,
         rp_certificate: &Certificate,
         nonce: String,
         encryption_pubkey: JwePublicKey,
@@ -379,7 +443,7 @@ impl IsoVpAuthorizationRequest {
             nonce,
             encryption_pubkey: encryption_pubkey.clone(),
             response_uri,
-            presentation_definition: items_requests.into(),
+            query: VpQuery::new(items_requests, query_language),
             items_requests: items_requests.clone(),
             client_metadata: ClientMetadata {
                 jwks: VpJwks::Direct {
@@ -409,11 +473,12 @@ impl From<IsoVpAuthorizationRequest> for VpAuthorizationRequest {
                 redirect_uri: None,
                 state: None,
                 authorization_details: None,
+                issuer_state: None,
                 request_uri: None,
                 code_challenge: None,
                 scope: None,
             },
-            presentation_definition: VpPresentationDefinition::Direct(value.presentation_definition),
+            presentation_definition: value.query.into(),
             client_metadata: Some(VpClientMetadata::Direct(value.client_metadata)),
             client_id_scheme: Some(ClientIdScheme::X509SanDns),
             response_uri: Some(value.response_uri),
@@ -484,7 +549,7 @@ impl TryFrom<VpAuthorizationRequest> for IsoVpAuthorizationRequest {
         }
 
         // Of fields that have an "_uri" variant, check that they are not used
-        let Some(presentation_definition) = vp_auth_request.presentation_definition.direct() else {
+        let Some(query) = vp_auth_request.presentation_definition.direct() else {
             return Err(AuthRequestValidationError::UriVariantNotSupported(
                 "presentation_definition",
             ));
@@ -505,10 +570,10 @@ impl TryFrom<VpAuthorizationRequest> for IsoVpAuthorizationRequest {
         let jwk = jwks.first().unwrap().clone();
         JwePublicKey::validate(&jwk)?;
 
-        if presentation_definition
-            .input_descriptors
-            .iter()
-            .all(|i| i.constraints.fields.is_empty())
+        let items_requests = query.items_requests()?;
+        if items_requests
+            .items_requests()
+            .all(|items_request| items_request.name_spaces.is_empty())
         {
             return Err(AuthRequestValidationError::NoAttributesRequested);
         }
@@ -517,9 +582,9 @@ impl TryFrom<VpAuthorizationRequest> for IsoVpAuthorizationRequest {
             client_id: vp_auth_request.oauth_request.client_id,
             nonce: vp_auth_request.oauth_request.nonce.unwrap(),
             encryption_pubkey: jwk,
-            items_requests: (&presentation_definition).try_into()?,
+            items_requests,
             response_uri: vp_auth_request.response_uri.unwrap(),
-            presentation_definition,
+            query,
             client_metadata,
             state: vp_auth_request.oauth_request.state,
             wallet_nonce: vp_auth_request.wallet_nonce,
@@ -558,19 +623,34 @@ pub enum AuthResponseError {
     UnexpectedVpCount(usize),
     #[error("error in Presentation Submission: {0}")]
     PresentationSubmission(#[from] PsError),
+    #[error("missing Presentation Submission")]
+    MissingPresentationSubmission,
+    #[error("vp_token does not match the query language of the Authorization Request")]
+    UnexpectedVpTokenKind,
+    #[error("received Verifiable Presentation for unknown credential query ID: {0}")]
+    UnknownCredentialQueryId(String),
+    #[error("unsupported DCQL query: {0}")]
+    Dcql(#[from] DcqlConversionError),
+    #[error("none of the credential queries is satisfied by the disclosed documents: {0:?}")]
+    UnsatisfiedCredentialQueries(Vec<String>),
+    #[error("received unexpected doctype for credential query ID {id}: expected {expected}, found {found}")]
+    UnexpectedDocType {
+        id: String,
+        expected: String,
+        found: String,
+    },
 }
 
 // We do not reuse or embed the `AuthorizationResponse` struct from `authorization.rs`, because in no variant
 // of OpenID4VP that we (plan to) support do we need the `code` field from that struct, which is its primary citizen.
 /// An OpenID4VP Authorization Response, with the wallet's disclosed attestations/attributes in the `vp_token`.
-#[serde_as]
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VpAuthorizationResponse {
-    /// One or more Verifiable Presentations.
-    #[serde_as(as = "OneOrMany<_, PreferOne>")]
-    pub vp_token: Vec<VerifiablePresentation>,
+    pub vp_token: VpPresentations,
 
-    pub presentation_submission: PresentationSubmission,
+    /// Only present when responding to a Presentation Definition.
+    pub presentation_submission: Option<PresentationSubmission>,
 
     /// MUST equal the `state` from the Authorization Request.
     /// May be used by the RP to link incoming Authorization Responses to its corresponding Authorization Request,
@@ -578,6 +658,18 @@ pub struct VpAuthorizationResponse {
     pub state: Option<String>,
 }
 
+/// The Verifiable Presentations in an Authorization Response, the shape of which depends on the query language
+/// of the Authorization Request.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VpPresentations {
+    /// Verifiable Presentations keyed by the ID of the DCQL credential query they respond to.
+    Dcql(IndexMap<String, VerifiablePresentation>),
+    /// One or more Verifiable Presentations, in response to a Presentation Definition.
+    PresentationExchange(#[serde_as(as = "OneOrMany<_, PreferOne>")] Vec<VerifiablePresentation>),
+}
+
 /// Disclosure of an attestation, generally containing the issuer-signed attestation itself, the disclosed attributes,
 /// and a holder signature over some nonce provided by the verifier.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -591,28 +683,76 @@ pub enum VerifiablePresentation {
 }
 
 impl VpAuthorizationResponse {
-    fn new(device_response: DeviceResponse, auth_request: &IsoVpAuthorizationRequest) -> Self {
-        let presentation_submission = PresentationSubmission {
-            id: random_string(16),
-            definition_id: auth_request.presentation_definition.id.clone(),
-            descriptor_map: device_response
-                .documents
-                .as_ref()
-                .unwrap() // we never produce DeviceResponse instances without documents in it
-                .iter()
-                .map(|doc| InputDescriptorMappingObject {
-                    id: doc.doc_type.clone(),
-                    format: Format::MsoMdoc,
-                    path: "$".to_string(),
-                })
-                .collect(),
+    fn new(
+        device_response: DeviceResponse,
+        auth_request: &IsoVpAuthorizationRequest,
+    ) -> Result<Self, AuthResponseError> {
+        // We never produce DeviceResponse instances without documents in it.
+        let documents = device_response.documents.as_deref().unwrap_or_default();
+
+        let (vp_token, presentation_submission) = match &auth_request.query {
+            VpQuery::PresentationDefinition(presentation_definition) => {
+                let presentation_submission = PresentationSubmission {
+                    id: random_string(16),
+                    definition_id: presentation_definition.id.clone(),
+                    descriptor_map: documents
+                        .iter()
+                        .map(|doc| InputDescriptorMappingObject {
+                            id: doc.doc_type.clone(),
+                            format: Format::MsoMdoc,
+                            path: "$".to_string(),
+                        })
+                        .collect(),
+                };
+                let vp_token = VpPresentations::PresentationExchange(vec![VerifiablePresentation::MsoMdoc(
+                    device_response.into(),
+                )]);
+
+                (vp_token, Some(presentation_submission))
+            }
+            VpQuery::Dcql(query) => {
+                // Every requirement is answered by its first Credential Query that a disclosed document satisfies,
+                // which is keyed by the ID of that query. As multiple queries may request the same doctype, a document
+                // can be disclosed under more than one ID. Every document is disclosed in its own DeviceResponse.
+                let mut presentations = IndexMap::new();
+                for alternatives in query.requirements()? {
+                    let (id, doc) = alternatives
+                        .iter()
+                        .find_map(|(id, items_request)| {
+                            documents
+                                .iter()
+                                .find(|doc| {
+                                    doc.doc_type == items_request.doc_type
+                                        && items_request.match_against_issuer_signed(doc).is_empty()
+                                })
+                                .map(|doc| (*id, doc))
+                        })
+                        .ok_or_else(|| {
+                            AuthResponseError::UnsatisfiedCredentialQueries(
+                                alternatives.iter().map(|(id, _)| id.to_string()).collect(),
+                            )
+                        })?;
+
+                    let device_response = DeviceResponse {
+                        version: device_response.version.clone(),
+                        documents: Some(vec![doc.clone()]),
+                        document_errors: None,
+                        status: device_response.status,
+                    };
+                    presentations.insert(id.to_string(), VerifiablePresentation::MsoMdoc(device_response.into()));
+                }
+
+                (VpPresentations::Dcql(presentations), None)
+            }
         };
 
-        VpAuthorizationResponse {
-            vp_token: vec![VerifiablePresentation::MsoMdoc(device_response.into())],
+        let response = VpAuthorizationResponse {
+            vp_token,
             presentation_submission,
             state: auth_request.state.clone(),
-        }
+        };
+
+        Ok(response)
     }
 
     /// Create a JWE containing a new encrypted Authorization Request.
@@ -621,7 +761,7 @@ impl VpAuthorizationResponse {
         auth_request: &IsoVpAuthorizationRequest,
         mdoc_nonce: &str,
     ) -> Result<String, AuthResponseError> {
-        Self::new(device_response, auth_request).encrypt(auth_request, mdoc_nonce)
+        Self::new(device_response, auth_request)?.encrypt(auth_request, mdoc_nonce)
     }
 
     fn encrypt(&self, auth_request: &IsoVpAuthorizationRequest, mdoc_nonce: &str) -> Result<String, AuthResponseError> {
@@ -688,13 +828,47 @@ impl VpAuthorizationResponse {
         Ok((payload, mdoc_nonce))
     }
 
-    fn device_response(&self) -> Result<&DeviceResponse, AuthResponseError> {
-        if self.vp_token.len() != 1 {
-            return Err(AuthResponseError::UnexpectedVpCount(self.vp_token.len()));
-        }
+    /// Returns the `DeviceResponse`s in the `vp_token`, checking that their shape matches the query of the
+    /// Authorization Request.
+    fn device_responses(&self, query: &VpQuery) -> Result<Vec<&DeviceResponse>, AuthResponseError> {
+        match (&self.vp_token, query) {
+            (VpPresentations::PresentationExchange(vps), VpQuery::PresentationDefinition(_)) => {
+                if vps.len() != 1 {
+                    return Err(AuthResponseError::UnexpectedVpCount(vps.len()));
+                }
+
+                let VerifiablePresentation::MsoMdoc(device_response) = vps.first().unwrap();
+                Ok(vec![&device_response.0])
+            }
+            (VpPresentations::Dcql(vps), VpQuery::Dcql(query)) => vps
+                .iter()
+                .map(|(id, VerifiablePresentation::MsoMdoc(device_response))| {
+                    let credential_query = query
+                        .credentials
+                        .iter()
+                        .find(|credential_query| credential_query.id == *id)
+                        .ok_or_else(|| AuthResponseError::UnknownCredentialQueryId(id.clone()))?;
+
+                    let expected = &credential_query.meta.doctype_value;
+                    if let Some(doc) = device_response
+                        .0
+                        .documents
+                        .iter()
+                        .flatten()
+                        .find(|doc| doc.doc_type != *expected)
+                    {
+                        return Err(AuthResponseError::UnexpectedDocType {
+                            id: id.clone(),
+                            expected: expected.clone(),
+                            found: doc.doc_type.clone(),
+                        });
+                    }
 
-        let VerifiablePresentation::MsoMdoc(device_response) = &self.vp_token.first().unwrap();
-        Ok(&device_response.0)
+                    Ok(&device_response.0)
+                })
+                .collect(),
+            _ => Err(AuthResponseError::UnexpectedVpTokenKind),
+        }
     }
 
     pub fn verify(
@@ -711,24 +885,40 @@ impl VpAuthorizationResponse {
             auth_request.nonce.clone(),
             mdoc_nonce,
         );
-        let device_response = self.device_response()?;
-        let disclosed_attrs = device_response
-            .verify(None, &session_transcript, time, trust_anchors)
-            .map_err(AuthResponseError::Verification)?;
+        let device_responses = self.device_responses(&auth_request.query)?;
+        let mut disclosed_attrs = DisclosedAttributes::new();
+        for device_response in &device_responses {
+            disclosed_attrs.extend(
+                device_response
+                    .verify(None, &session_transcript, time, trust_anchors)
+                    .map_err(AuthResponseError::Verification)?,
+            );
+        }
 
-        // Check that we received all attributes that we requested
+        // Check that we received all attributes that we requested, across all received documents.
+        let documents = device_responses
+            .iter()
+            .flat_map(|device_response| device_response.documents.iter().flatten().cloned())
+            .collect::<Vec<_>>();
+        let device_response = DeviceResponse {
+            version: DeviceResponseVersion::V1_0,
+            documents: Some(documents),
+            document_errors: None,
+            status: 0,
+        };
         auth_request
             .items_requests
-            .match_against_response(device_response)
+            .match_against_response(&device_response)
             .map_err(AuthResponseError::MissingAttributes)?;
 
-        // Safe: if we have found all requested items in the documents, then the documents are not absent.
-        let documents = device_response.documents.as_ref().unwrap();
-
         // Check that the Presentation Submission is what it should be per the Presentation Exchange spec and ISO
-        // 18013-7.
-        self.presentation_submission
-            .verify(documents, &auth_request.presentation_definition)?;
+        // 18013-7. When responding to a DCQL query, the `vp_token` itself maps the presentations to the query.
+        if let VpQuery::PresentationDefinition(presentation_definition) = &auth_request.query {
+            self.presentation_submission
+                .as_ref()
+                .ok_or(AuthResponseError::MissingPresentationSubmission)?
+                .verify(device_response.documents.as_ref().unwrap(), presentation_definition)?;
+        }
 
         // If `state` is provided it must equal the `state` from the Authorization Request.
         if self.state != auth_request.state {
@@ -751,8 +941,10 @@ pub struct VpResponse {
 mod tests {
     use std::borrow::Cow;
 
+    use assert_matches::assert_matches;
     use indexmap::IndexMap;
     use josekit::jwk::alg::ec::{EcCurve, EcKeyPair};
+    use rstest::rstest;
     use serde_json::json;
 
     use nl_wallet_mdoc::{
@@ -760,13 +952,17 @@ mod tests {
         server_keys::KeyPair,
         software_key_factory::SoftwareKeyFactory,
         utils::serialization::{cbor_serialize, CborBase64, CborSeq, TaggedBytes},
+        verifier::{ItemsRequests, ItemsRequirement},
         DeviceAuthenticationKeyed, DeviceResponse, DeviceResponseVersion, DeviceSigned, Document, SessionTranscript,
     };
     use wallet_common::keys::software::SoftwareEcdsaKey;
 
     use crate::{openid4vp::IsoVpAuthorizationRequest, AuthorizationErrorCode, VpAuthorizationErrorCode};
 
-    use super::{jwt, VerifiablePresentation, VpAuthorizationRequest, VpAuthorizationResponse};
+    use super::{
+        jwt, AuthResponseError, QueryLanguage, VerifiablePresentation, VpAuthorizationRequest, VpAuthorizationResponse,
+        VpPresentations, VpQuery,
+    };
 
     #[test]
     fn test_vp_authorization_error_code_serialization() {
@@ -781,7 +977,7 @@ mod tests {
         );
     }
 
-    fn setup() -> (KeyPair, KeyPair, EcKeyPair, VpAuthorizationRequest) {
+    fn setup(query_language: QueryLanguage) -> (KeyPair, KeyPair, EcKeyPair, VpAuthorizationRequest) {
         let ca = KeyPair::generate_ca("myca", Default::default()).unwrap();
         let rp_keypair = ca.generate_reader_mock(None).unwrap();
 
@@ -789,6 +985,7 @@ mod tests {
 
         let auth_request = IsoVpAuthorizationRequest::new(
             &Examples::items_requests(),
+            query_language,
             rp_keypair.certificate(),
             "nonce".to_string(),
             encryption_privkey.to_jwk_public_key().try_into().unwrap(),
@@ -801,9 +998,11 @@ mod tests {
         (ca, rp_keypair, encryption_privkey, auth_request)
     }
 
-    #[test]
-    fn test_encrypt_decrypt_authorization_response() {
-        let (_, _, encryption_privkey, auth_request) = setup();
+    #[rstest]
+    #[case(QueryLanguage::PresentationExchange)]
+    #[case(QueryLanguage::Dcql)]
+    fn test_encrypt_decrypt_authorization_response(#[case] query_language: QueryLanguage) {
+        let (_, _, encryption_privkey, auth_request) = setup(query_language);
 
         // NB: the example DeviceResponse verifies as an ISO 18013-5 DeviceResponse while here we use it in
         // an OpenID4VP setting, i.e. with different SessionTranscript contents, so it can't be verified.
@@ -812,7 +1011,7 @@ mod tests {
         let mdoc_nonce = "mdoc_nonce".to_string();
         let device_response = DeviceResponse::example();
         let auth_request = IsoVpAuthorizationRequest::try_from(auth_request).unwrap();
-        let auth_response = VpAuthorizationResponse::new(device_response, &auth_request);
+        let auth_response = VpAuthorizationResponse::new(device_response, &auth_request).unwrap();
         let jwe = auth_response.encrypt(&auth_request, &mdoc_nonce).unwrap();
 
         let (decrypted, jwe_mdoc_nonce) =
@@ -820,9 +1019,9 @@ mod tests {
         assert_eq!(mdoc_nonce, jwe_mdoc_nonce);
 
         let VerifiablePresentation::MsoMdoc(CborBase64(encrypted_device_response)) =
-            auth_response.vp_token.first().unwrap();
+            first_presentation(&auth_response.vp_token);
         let VerifiablePresentation::MsoMdoc(CborBase64(decrypted_device_response)) =
-            decrypted.vp_token.first().unwrap();
+            first_presentation(&decrypted.vp_token);
         let encrypted_document = encrypted_device_response.documents.as_ref().unwrap().first().unwrap();
         let decrypted_document = decrypted_device_response.documents.as_ref().unwrap().first().unwrap();
 
@@ -830,15 +1029,86 @@ mod tests {
         assert_eq!(decrypted_document.issuer_signed, encrypted_document.issuer_signed);
     }
 
+    fn first_presentation(vp_token: &VpPresentations) -> &VerifiablePresentation {
+        match vp_token {
+            VpPresentations::Dcql(vps) => vps.first().unwrap().1,
+            VpPresentations::PresentationExchange(vps) => vps.first().unwrap(),
+        }
+    }
+
+    /// Create a DCQL Authorization Request for the specified [`ItemsRequests`] and respond to it with the example
+    /// [`DeviceResponse`], returning the IDs of the Credential Queries in the `vp_token`.
+    fn dcql_response_ids(items_requests: ItemsRequests) -> Result<Vec<String>, AuthResponseError> {
+        let (_, rp_keypair, encryption_privkey, _) = setup(QueryLanguage::Dcql);
+        let auth_request = IsoVpAuthorizationRequest::new(
+            &items_requests,
+            QueryLanguage::Dcql,
+            rp_keypair.certificate(),
+            "nonce".to_string(),
+            encryption_privkey.to_jwk_public_key().try_into().unwrap(),
+            "https://example.com/response_uri".parse().unwrap(),
+            None,
+        )
+        .unwrap();
+
+        let auth_response = VpAuthorizationResponse::new(DeviceResponse::example(), &auth_request)?;
+        let VpPresentations::Dcql(vps) = auth_response.vp_token else {
+            panic!("expected DCQL presentations");
+        };
+
+        Ok(vps.into_keys().collect())
+    }
+
+    #[test]
+    fn test_dcql_authorization_response_credential_query_ids() {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut unsatisfiable = items_request.clone();
+        unsatisfiable
+            .name_spaces
+            .first_mut()
+            .unwrap()
+            .1
+            .insert("nonexistent".to_string(), false);
+
+        // The presentation should be keyed by the alternative that the disclosed document satisfies.
+        let ids = dcql_response_ids(ItemsRequests::from(vec![ItemsRequirement::OneOf {
+            one_of: vec![unsatisfiable.clone(), items_request.clone()].try_into().unwrap(),
+        }]))
+        .unwrap();
+
+        assert_eq!(ids, ["credential_1"]);
+
+        // Every required query for the same doctype should get its own presentation.
+        let ids = dcql_response_ids(ItemsRequests::from(vec![
+            ItemsRequirement::Single(items_request.clone()),
+            ItemsRequirement::Single(items_request),
+        ]))
+        .unwrap();
+
+        assert_eq!(ids, ["credential_0", "credential_1"]);
+
+        // A requirement that none of the documents satisfies should result in an error.
+        let error = dcql_response_ids(ItemsRequests::from(vec![ItemsRequirement::Single(unsatisfiable)]))
+            .expect_err("creating the Authorization Response should fail");
+
+        assert_matches!(error, AuthResponseError::UnsatisfiedCredentialQueries(ids) if ids == ["credential_0"]);
+    }
+
+    #[rstest]
+    #[case(QueryLanguage::PresentationExchange)]
+    #[case(QueryLanguage::Dcql)]
     #[tokio::test]
-    async fn test_authorization_request_jwt() {
-        let (ca, rp_keypair, _, auth_request) = setup();
+    async fn test_authorization_request_jwt(#[case] query_language: QueryLanguage) {
+        let (ca, rp_keypair, _, auth_request) = setup(query_language);
 
         let auth_request_jwt = jwt::sign_with_certificate(&auth_request, &rp_keypair).await.unwrap();
 
         let (auth_request, cert) =
             VpAuthorizationRequest::try_new(&auth_request_jwt, &[ca.certificate().try_into().unwrap()]).unwrap();
-        auth_request.validate(&cert, None).unwrap();
+        let auth_request = auth_request.validate(&cert, None).unwrap();
+
+        assert_eq!(auth_request.query.query_language(), query_language);
+        assert_eq!(auth_request.items_requests, Examples::items_requests());
     }
 
     #[test]
@@ -895,6 +1165,54 @@ mod tests {
         IsoVpAuthorizationRequest::try_from(auth_request).unwrap();
     }
 
+    #[test]
+    fn deserialize_dcql_authorization_request_example() {
+        let example_json = json!(
+            {
+                "aud": "https://self-issued.me/v2",
+                "response_type": "vp_token",
+                "response_mode": "direct_post.jwt",
+                "client_id_scheme": "x509_san_dns",
+                "client_id": "example.com",
+                "response_uri": "https://example.com/post",
+                "nonce": "%%2_fsd32434!==r",
+                "client_metadata": {
+                    "jwks": {
+                        "keys": [{
+                            "kty": "EC", "use": "enc", "crv": "P-256", "alg": "ECDH-ES",
+                            "x": "xVLtZaPPK-xvruh1fEClNVTR6RCZBsQai2-DrnyKkxg",
+                            "y": "-5-QtFqJqGwOjEL3Ut89nrE0MeaUp5RozksKHpBiyw0"
+                        }]
+                    },
+                    "authorization_encryption_alg_values_supported": "ECDH-ES",
+                    "authorization_encryption_enc_values_supported": "A256GCM",
+                    "vp_formats": {
+                        "mso_mdoc": {
+                            "alg": [ "ES256" ]
+                        }
+                    }
+                },
+                "dcql_query": {
+                    "credentials": [{
+                        "id": "mdl",
+                        "format": "mso_mdoc",
+                        "meta": { "doctype_value": "org.iso.18013.5.1.mDL" },
+                        "claims": [
+                            { "path": ["org.iso.18013.5.1", "family_name"], "intent_to_retain": false },
+                            { "path": ["org.iso.18013.5.1", "birth_date"], "intent_to_retain": false }
+                        ]
+                    }]
+                }
+            }
+        );
+
+        let auth_request: VpAuthorizationRequest = serde_json::from_value(example_json).unwrap();
+        let auth_request = IsoVpAuthorizationRequest::try_from(auth_request).unwrap();
+
+        assert_matches!(auth_request.query, VpQuery::Dcql(_));
+        assert_eq!(auth_request.items_requests.items_requests().count(), 1);
+    }
+
     #[test]
     fn deserialize_authorization_response_example() {
         let example_json = json!(
@@ -993,7 +1311,7 @@ mod tests {
         let auth_response: VpAuthorizationResponse = serde_json::from_value(example_json).unwrap();
 
         let VerifiablePresentation::MsoMdoc(CborBase64(decrypted_device_response)) =
-            auth_response.vp_token.first().unwrap();
+            first_presentation(&auth_response.vp_token);
         let decrypted_document = decrypted_device_response.documents.as_ref().unwrap().first().unwrap();
         assert_eq!(decrypted_document.doc_type, "org.iso.18013.5.1.mDL".to_string());
     }
@@ -1049,9 +1367,12 @@ mod tests {
         device_response
     }
 
+    #[rstest]
+    #[case(QueryLanguage::PresentationExchange)]
+    #[case(QueryLanguage::Dcql)]
     #[tokio::test]
-    async fn test_verify_authorization_response() {
-        let (_, _, _, auth_request) = setup();
+    async fn test_verify_authorization_response(#[case] query_language: QueryLanguage) {
+        let (_, _, _, auth_request) = setup(query_language);
         let mdoc_nonce = "mdoc_nonce";
 
         let auth_request = IsoVpAuthorizationRequest::try_from(auth_request).unwrap();
@@ -1062,7 +1383,7 @@ mod tests {
             mdoc_nonce,
         );
         let device_response = mock_device_response(&session_transcript).await;
-        let auth_response = VpAuthorizationResponse::new(device_response, &auth_request);
+        let auth_response = VpAuthorizationResponse::new(device_response, &auth_request).unwrap();
 
         auth_response
             .verify(
@@ -1073,4 +1394,34 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_verify_authorization_response_query_language_mismatch() {
+        let (_, _, _, pe_auth_request) = setup(QueryLanguage::PresentationExchange);
+        let (_, _, _, dcql_auth_request) = setup(QueryLanguage::Dcql);
+        let mdoc_nonce = "mdoc_nonce";
+
+        let pe_auth_request = IsoVpAuthorizationRequest::try_from(pe_auth_request).unwrap();
+        let dcql_auth_request = IsoVpAuthorizationRequest::try_from(dcql_auth_request).unwrap();
+        let session_transcript = SessionTranscript::new_oid4vp(
+            &dcql_auth_request.response_uri,
+            &dcql_auth_request.client_id,
+            dcql_auth_request.nonce.clone(),
+            mdoc_nonce,
+        );
+        let device_response = mock_device_response(&session_transcript).await;
+
+        // A response to a Presentation Definition should not be accepted for a DCQL query.
+        let auth_response = VpAuthorizationResponse::new(device_response, &pe_auth_request).unwrap();
+        let error = auth_response
+            .verify(
+                &dcql_auth_request,
+                mdoc_nonce,
+                &IsoCertTimeGenerator,
+                Examples::iaca_trust_anchors(),
+            )
+            .expect_err("verifying should fail");
+
+        assert_matches!(error, AuthResponseError::UnexpectedVpTokenKind);
+    }
 }
@@ -3,14 +3,17 @@
 //! implementing only the fields used by the OpenID4VP profile from ISO 18013-7.
 //! Other fields are left out of the various structs and enums for now, and some fields that are optional per
 //! Presentation Exchange that are always used by the ISO 18013-7 profile are mandatory here.
-use std::sync::LazyLock;
+use std::{collections::HashSet, sync::LazyLock};
 
 use indexmap::{IndexMap, IndexSet};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use error_category::ErrorCategory;
-use nl_wallet_mdoc::{verifier::ItemsRequests, Document, ItemsRequest};
+use nl_wallet_mdoc::{
+    verifier::{ItemsRequests, ItemsRequirement},
+    Document, ItemsRequest,
+};
 use wallet_common::utils::random_string;
 
 use crate::{
@@ -23,16 +26,43 @@ use crate::{
 pub struct PresentationDefinition {
     pub id: String,
     pub input_descriptors: Vec<InputDescriptor>,
+    /// If absent, all Input Descriptors are required. Otherwise, only the Input Descriptors in the groups referenced
+    /// by the Submission Requirements are, as determined by their rules.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submission_requirements: Vec<SubmissionRequirement>,
 }
 
 /// As specified in https://identity.foundation/presentation-exchange/spec/v2.0.0/#input-descriptor-object.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputDescriptor {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group: Vec<String>,
     pub format: VpFormat,
     pub constraints: Constraints,
 }
 
+/// As specified in https://identity.foundation/presentation-exchange/spec/v2.0.0/#submission-requirements.
+/// Only Submission Requirements that refer to a group of Input Descriptors using `from` are supported, i.e. nesting
+/// Submission Requirements using `from_nested` is not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionRequirement {
+    pub rule: SubmissionRequirementRule,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    pub from: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionRequirementRule {
+    /// All Input Descriptors in the group must be satisfied.
+    All,
+
+    /// The amount of Input Descriptors in the group specified by `count` must be satisfied.
+    Pick,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Constraints {
     pub fields: Vec<Field>,
@@ -62,6 +92,9 @@ pub enum PdConversionError {
     #[error("signature algorithms not supported")]
     #[category(critical)]
     UnsupportedAlgs,
+    #[error("unsupported submission requirement: {0:?}")]
+    #[category(critical)]
+    UnsupportedSubmissionRequirement(SubmissionRequirement),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,33 +126,107 @@ impl Field {
     }
 }
 
+impl From<&ItemsRequest> for InputDescriptor {
+    fn from(items_request: &ItemsRequest) -> Self {
+        InputDescriptor {
+            id: items_request.doc_type.clone(),
+            group: vec![],
+            format: VpFormat::MsoMdoc {
+                alg: IndexSet::from([FormatAlg::ES256]),
+            },
+            constraints: Constraints {
+                limit_disclosure: LimitDisclosure::Required,
+                fields: items_request
+                    .name_spaces
+                    .iter()
+                    .flat_map(|(namespace, attrs)| {
+                        attrs.iter().map(|(attr, intent_to_retain)| Field {
+                            path: vec![format!("$['{}']['{}']", namespace.as_str(), attr.as_str())],
+                            intent_to_retain: *intent_to_retain,
+                        })
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<&InputDescriptor> for ItemsRequest {
+    type Error = PdConversionError;
+
+    fn try_from(input_descriptor: &InputDescriptor) -> Result<Self, Self::Error> {
+        let VpFormat::MsoMdoc { alg } = &input_descriptor.format;
+        if !alg.contains(&FormatAlg::ES256) {
+            return Err(PdConversionError::UnsupportedAlgs);
+        }
+
+        let mut name_spaces: IndexMap<String, IndexMap<String, bool>> = IndexMap::new();
+        for field in &input_descriptor.constraints.fields {
+            let (namespace, attr) = field.parse_paths()?;
+            name_spaces
+                .entry(namespace)
+                .or_default()
+                .insert(attr, field.intent_to_retain);
+        }
+
+        Ok(ItemsRequest {
+            doc_type: input_descriptor.id.clone(),
+            request_info: None,
+            name_spaces,
+        })
+    }
+}
+
 impl From<&ItemsRequests> for PresentationDefinition {
     fn from(items_requests: &ItemsRequests) -> Self {
-        PresentationDefinition {
-            id: random_string(16),
-            input_descriptors: items_requests
-                .0
-                .iter()
-                .map(|items_request| InputDescriptor {
-                    id: items_request.doc_type.clone(),
-                    format: VpFormat::MsoMdoc {
-                        alg: IndexSet::from([FormatAlg::ES256]),
+        // Only use Submission Requirements when necessary, so that requests without alternatives are unchanged.
+        if !items_requests.has_alternatives() {
+            return PresentationDefinition {
+                id: random_string(16),
+                input_descriptors: items_requests.items_requests().map(InputDescriptor::from).collect(),
+                submission_requirements: vec![],
+            };
+        }
+
+        // Place the Input Descriptors of every requirement in their own group, from which all have to be submitted
+        // for a single requested document, or exactly one in case of alternatives.
+        let (input_descriptors, submission_requirements): (Vec<_>, Vec<_>) = items_requests
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, requirement)| {
+                let group = format!("requirement_{index}");
+
+                let input_descriptors = requirement
+                    .alternatives()
+                    .iter()
+                    .map(|items_request| InputDescriptor {
+                        group: vec![group.clone()],
+                        ..items_request.into()
+                    })
+                    .collect::<Vec<_>>();
+
+                let submission_requirement = match requirement {
+                    ItemsRequirement::Single(_) => SubmissionRequirement {
+                        rule: SubmissionRequirementRule::All,
+                        count: None,
+                        from: group,
                     },
-                    constraints: Constraints {
-                        limit_disclosure: LimitDisclosure::Required,
-                        fields: items_request
-                            .name_spaces
-                            .iter()
-                            .flat_map(|(namespace, attrs)| {
-                                attrs.iter().map(|(attr, intent_to_retain)| Field {
-                                    path: vec![format!("$['{}']['{}']", namespace.as_str(), attr.as_str())],
-                                    intent_to_retain: *intent_to_retain,
-                                })
-                            })
-                            .collect(),
+                    ItemsRequirement::OneOf { .. } => SubmissionRequirement {
+                        rule: SubmissionRequirementRule::Pick,
+                        count: Some(1),
+                        from: group,
                     },
-                })
-                .collect(),
+                };
+
+                (input_descriptors, submission_requirement)
+            })
+            .unzip();
+
+        PresentationDefinition {
+            id: random_string(16),
+            input_descriptors: input_descriptors.into_iter().flatten().collect(),
+            submission_requirements,
         }
     }
 }
@@ -128,33 +235,50 @@ impl TryFrom<&PresentationDefinition> for ItemsRequests {
     type Error = PdConversionError;
 
     fn try_from(pd: &PresentationDefinition) -> Result<Self, Self::Error> {
-        let items_requests = pd
-            .input_descriptors
+        if pd.submission_requirements.is_empty() {
+            let items_requests = pd
+                .input_descriptors
+                .iter()
+                .map(ItemsRequest::try_from)
+                .collect::<Result<Vec<_>, Self::Error>>()?;
+
+            return Ok(items_requests.into());
+        }
+
+        // Input Descriptors that are not in a group referenced by a Submission Requirement are not required, and are
+        // therefore ignored.
+        let requirements = pd
+            .submission_requirements
             .iter()
-            .map(|input_descriptor| {
-                let VpFormat::MsoMdoc { alg } = &input_descriptor.format;
-                if !alg.contains(&FormatAlg::ES256) {
-                    return Err(PdConversionError::UnsupportedAlgs);
-                }
-
-                let mut name_spaces: IndexMap<String, IndexMap<String, bool>> = IndexMap::new();
-                for field in &input_descriptor.constraints.fields {
-                    let (namespace, attr) = field.parse_paths()?;
-                    name_spaces
-                        .entry(namespace)
-                        .or_default()
-                        .insert(attr, field.intent_to_retain);
-                }
-
-                Ok(ItemsRequest {
-                    doc_type: input_descriptor.id.clone(),
-                    request_info: None,
-                    name_spaces,
-                })
+            .map(|submission_requirement| {
+                let items_requests = pd
+                    .input_descriptors
+                    .iter()
+                    .filter(|input_descriptor| input_descriptor.group.contains(&submission_requirement.from))
+                    .map(ItemsRequest::try_from)
+                    .collect::<Result<Vec<_>, Self::Error>>()?;
+
+                let requirements = match (submission_requirement.rule, submission_requirement.count) {
+                    (SubmissionRequirementRule::All, _) => {
+                        items_requests.into_iter().map(ItemsRequirement::Single).collect()
+                    }
+                    (SubmissionRequirementRule::Pick, Some(1)) => vec![ItemsRequirement::OneOf {
+                        one_of: items_requests.try_into().map_err(|_| {
+                            PdConversionError::UnsupportedSubmissionRequirement(submission_requirement.clone())
+                        })?,
+                    }],
+                    (SubmissionRequirementRule::Pick, _) => {
+                        return Err(PdConversionError::UnsupportedSubmissionRequirement(
+                            submission_requirement.clone(),
+                        ))
+                    }
+                };
+
+                Ok(requirements)
             })
             .collect::<Result<Vec<_>, Self::Error>>()?;
 
-        Ok(items_requests.into())
+        Ok(requirements.into_iter().flatten().collect::<Vec<_>>().into())
     }
 }
 
@@ -187,6 +311,15 @@ pub enum PsError {
     UnexpectedInputDescriptorPath(String),
     #[error("received unexpected Presentation Submission Input Descriptor ID: expected '{expected}', found '{found}'")]
     UnexpectedInputDescriptorId { expected: String, found: String },
+    #[error(
+        "unexpected amount of Presentation Submission descriptors from group '{group}': expected {expected}, found \
+         {found}"
+    )]
+    UnexpectedGroupDescriptorCount {
+        group: String,
+        expected: usize,
+        found: usize,
+    },
 }
 
 impl PresentationSubmission {
@@ -223,6 +356,36 @@ impl PresentationSubmission {
             }
         }
 
+        // Check that the amount of submitted Input Descriptors from the group of every Submission Requirement matches
+        // its rule, so that for example only a single alternative is disclosed when one of a group is requested.
+        for submission_requirement in &presentation_definition.submission_requirements {
+            let group_ids = presentation_definition
+                .input_descriptors
+                .iter()
+                .filter(|input_descriptor| input_descriptor.group.contains(&submission_requirement.from))
+                .map(|input_descriptor| input_descriptor.id.as_str())
+                .collect::<HashSet<_>>();
+
+            let expected = match (submission_requirement.rule, submission_requirement.count) {
+                (SubmissionRequirementRule::All, _) => group_ids.len(),
+                (SubmissionRequirementRule::Pick, Some(count)) => count,
+                (SubmissionRequirementRule::Pick, None) => continue,
+            };
+            let found = self
+                .descriptor_map
+                .iter()
+                .filter(|input_descriptor| group_ids.contains(input_descriptor.id.as_str()))
+                .count();
+
+            if found != expected {
+                return Err(PsError::UnexpectedGroupDescriptorCount {
+                    group: submission_requirement.from.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
         Ok(())
     }
 }
@@ -233,9 +396,18 @@ mod tests {
     use rstest::rstest;
     use serde_json::json;
 
-    use nl_wallet_mdoc::{examples::Examples, verifier::ItemsRequests};
+    use nl_wallet_mdoc::{
+        examples::{Example, Examples},
+        verifier::{ItemsRequests, ItemsRequirement},
+        DeviceResponse, Document,
+    };
 
-    use super::{FormatAlg, LimitDisclosure, PresentationDefinition, VpFormat, FIELD_PATH_REGEX};
+    use crate::Format;
+
+    use super::{
+        FormatAlg, InputDescriptorMappingObject, LimitDisclosure, PdConversionError, PresentationDefinition,
+        PresentationSubmission, PsError, SubmissionRequirementRule, VpFormat, FIELD_PATH_REGEX,
+    };
 
     #[rstest]
     #[case("$['namespace']['attribute_name']", true)]
@@ -259,6 +431,99 @@ mod tests {
         assert_eq!(items_requests, converted);
     }
 
+    #[test]
+    fn convert_pd_itemsrequests_with_alternatives() {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut alternative = items_request.clone();
+        alternative.doc_type = "alternative_doc_type".to_string();
+
+        let items_requests = ItemsRequests::from(vec![
+            ItemsRequirement::Single(items_request.clone()),
+            ItemsRequirement::OneOf {
+                one_of: vec![items_request, alternative].try_into().unwrap(),
+            },
+        ]);
+        let pd: PresentationDefinition = (&items_requests).into();
+
+        assert_eq!(pd.input_descriptors.len(), 3);
+        assert_eq!(pd.submission_requirements.len(), 2);
+        assert_eq!(pd.submission_requirements[0].rule, SubmissionRequirementRule::All);
+        assert_eq!(pd.submission_requirements[1].rule, SubmissionRequirementRule::Pick);
+        assert_eq!(pd.submission_requirements[1].count, Some(1));
+        assert!(pd.input_descriptors[1..]
+            .iter()
+            .all(|input_descriptor| input_descriptor.group == [pd.submission_requirements[1].from.clone()]));
+
+        let converted: ItemsRequests = (&pd).try_into().unwrap();
+
+        assert_eq!(items_requests, converted);
+    }
+
+    #[test]
+    fn convert_pd_unsupported_submission_requirement() {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let items_requests = ItemsRequests::from(vec![ItemsRequirement::OneOf {
+            one_of: vec![items_request.clone(), items_request].try_into().unwrap(),
+        }]);
+
+        let mut pd: PresentationDefinition = (&items_requests).into();
+        pd.submission_requirements[0].count = Some(2);
+
+        let error = ItemsRequests::try_from(&pd).expect_err("converting should fail");
+        assert_matches!(error, PdConversionError::UnsupportedSubmissionRequirement(_));
+    }
+
+    #[test]
+    fn verify_presentation_submission_with_alternatives() {
+        let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+        let mut alternative = items_request.clone();
+        alternative.doc_type = "alternative_doc_type".to_string();
+
+        let items_requests = ItemsRequests::from(vec![ItemsRequirement::OneOf {
+            one_of: vec![items_request, alternative.clone()].try_into().unwrap(),
+        }]);
+        let pd: PresentationDefinition = (&items_requests).into();
+
+        let document = DeviceResponse::example().documents.unwrap().pop().unwrap();
+        let mut alternative_document = document.clone();
+        alternative_document.doc_type = alternative.doc_type;
+
+        let submission = |documents: &[Document]| PresentationSubmission {
+            id: "submission".to_string(),
+            definition_id: pd.id.clone(),
+            descriptor_map: documents
+                .iter()
+                .map(|doc| InputDescriptorMappingObject {
+                    id: doc.doc_type.clone(),
+                    format: Format::MsoMdoc,
+                    path: "$".to_string(),
+                })
+                .collect(),
+        };
+
+        // Submitting either one of the alternatives should succeed.
+        for documents in [[document.clone()], [alternative_document.clone()]] {
+            submission(&documents)
+                .verify(&documents, &pd)
+                .expect("verifying submission should succeed");
+        }
+
+        // Submitting both alternatives should fail.
+        let documents = [document, alternative_document];
+        let error = submission(&documents)
+            .verify(&documents, &pd)
+            .expect_err("verifying submission should fail");
+
+        assert_matches!(
+            error,
+            PsError::UnexpectedGroupDescriptorCount {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        );
+    }
+
     #[test]
     fn deserialize_example_presentation_definition() {
         let example_json = json!(
@@ -1,9 +1,16 @@
-use std::{future::Future, sync::Arc, time::Duration};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
 use dashmap::{mapref::entry::Entry, DashMap};
+use metrics::{counter, gauge, histogram};
 use nutype::nutype;
+use serde::{Deserialize, Serialize};
 use tokio::{
+    sync::broadcast,
     task::JoinHandle,
     time::{self, MissedTickBehavior},
 };
@@ -17,6 +24,13 @@ use wallet_common::{
 /// The cleanup task that removes stale sessions runs every so often.
 pub const CLEANUP_INTERVAL_SECONDS: Duration = Duration::from_secs(120);
 
+/// The task that records the number of sessions per status in the metrics runs every so often. As counting may involve
+/// reading every session, this is done less often than cleaning up.
+pub const COUNT_INTERVAL_SECONDS: Duration = Duration::from_secs(600);
+
+/// The number of session updates that are buffered for every subscriber, see [`SessionStore::subscribe()`].
+pub const SESSION_UPDATES_CAPACITY: usize = 256;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Progress {
     Active,
@@ -25,11 +39,118 @@ pub enum Progress {
 
 pub trait HasProgress {
     fn progress(&self) -> Progress;
+
+    /// Whether something still has to be done for this session after it has finished, e.g. notifying a third party.
+    /// These sessions can be retrieved using [`SessionStore::list_pending()`].
+    fn is_pending(&self) -> bool {
+        false
+    }
+}
+
+/// The status of a session in a [`SessionStore`], which follows from its [`Progress`] and whether it has expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display, strum::EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ProgressStatus {
+    Active,
+    Succeeded,
+    Failed,
+    Expired,
+}
+
+impl ProgressStatus {
+    pub fn of(data: &(impl HasProgress + Expirable)) -> Self {
+        if data.is_expired() {
+            return Self::Expired;
+        }
+
+        match data.progress() {
+            Progress::Active => Self::Active,
+            Progress::Finished { has_succeeded: true } => Self::Succeeded,
+            Progress::Finished { has_succeeded: false } => Self::Failed,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Active)
+    }
+}
+
+/// The number of sessions in a [`SessionStore`] per [`ProgressStatus`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionCounts {
+    pub active: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub expired: u64,
+}
+
+impl SessionCounts {
+    pub fn add(&mut self, status: ProgressStatus, count: u64) {
+        let total = match status {
+            ProgressStatus::Active => &mut self.active,
+            ProgressStatus::Succeeded => &mut self.succeeded,
+            ProgressStatus::Failed => &mut self.failed,
+            ProgressStatus::Expired => &mut self.expired,
+        };
+        *total += count;
+    }
+
+    /// Record the number of sessions per status in the metrics.
+    fn record(&self, session_type: &'static str) {
+        for (status, count) in [
+            (ProgressStatus::Active, self.active),
+            (ProgressStatus::Succeeded, self.succeeded),
+            (ProgressStatus::Failed, self.failed),
+            (ProgressStatus::Expired, self.expired),
+        ] {
+            gauge!("session_store_sessions", "session_type" => session_type, "status" => status.to_string())
+                .set(count as f64);
+        }
+    }
+}
+
+/// The labels with which the state of a session is recorded in the metrics: the name of the state the session is in
+/// and its status.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionStateLabels {
+    state: &'static str,
+    status: ProgressStatus,
+}
+
+impl SessionStateLabels {
+    pub fn new<T>(data: &T) -> Self
+    where
+        T: HasProgress + Expirable,
+        for<'a> &'a T: Into<&'static str>,
+    {
+        Self {
+            state: data.into(),
+            status: ProgressStatus::of(data),
+        }
+    }
+
+    /// Record in the metrics that a session of `session_type` was written to a [`SessionStore`] in this state.
+    pub fn record(self, session_type: &'static str) {
+        counter!(
+            "session_state_transitions_total",
+            "session_type" => session_type,
+            "state" => self.state,
+            "status" => self.status.to_string(),
+        )
+        .increment(1);
+    }
 }
 
 pub trait Expirable {
     fn is_expired(&self) -> bool;
     fn expire(&mut self);
+
+    /// The amount of inactivity after which this particular session should be expired, if this should differ from
+    /// [`SessionStoreTimeouts::expiration`].
+    fn expiration(&self) -> Option<Duration> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,12 +158,17 @@ pub struct SessionState<T> {
     pub data: T,
     pub token: SessionToken,
     pub last_active: DateTime<Utc>,
+    /// The version of the session state as read from the store, which is incremented by every update. This is used to
+    /// detect concurrent updates of the same session, see [`SessionStore::write()`].
+    pub version: u64,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum SessionStoreError {
     #[error("token {0} already exists")]
     DuplicateToken(SessionToken),
+    #[error("session with token {0} was updated concurrently")]
+    Conflict(SessionToken),
     #[error("error while serializing: {0}")]
     Serialize(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     #[error("error while deserializing: {0}")]
@@ -61,6 +187,12 @@ where
         &self,
         token: &SessionToken,
     ) -> impl Future<Output = Result<Option<SessionState<T>>, SessionStoreError>> + Send;
+
+    /// Write session state to the store. If `is_new` is `true`, no session with the same token may be present in the
+    /// store yet. Otherwise, the session is only updated if the stored version still equals the version of `session`,
+    /// i.e. if it has not been updated since it was read. If that is not the case, [`SessionStoreError::Conflict`] is
+    /// returned. Note that this also holds when the session is no longer present in the store, e.g. because it was
+    /// removed by [`SessionStore::cleanup()`] in the meantime, so an update never recreates a removed session.
     fn write(
         &self,
         session: SessionState<T>,
@@ -68,7 +200,31 @@ where
     ) -> impl Future<Output = Result<(), SessionStoreError>> + Send;
     fn cleanup(&self) -> impl Future<Output = Result<(), SessionStoreError>> + Send;
 
-    fn start_cleanup_task(self: Arc<Self>, interval: Duration) -> JoinHandle<()>
+    /// List all sessions in the store or, if `status` is specified, only those with that status.
+    fn list(
+        &self,
+        status: Option<ProgressStatus>,
+    ) -> impl Future<Output = Result<Vec<SessionState<T>>, SessionStoreError>> + Send;
+
+    /// List the sessions in the store for which something still has to be done, see [`HasProgress::is_pending()`].
+    fn list_pending(&self) -> impl Future<Output = Result<Vec<SessionState<T>>, SessionStoreError>> + Send;
+
+    /// Count the sessions in the store per status.
+    fn count(&self) -> impl Future<Output = Result<SessionCounts, SessionStoreError>> + Send;
+
+    /// Remove all sessions that have finished, regardless of the timeouts that [`SessionStore::cleanup()`] adheres to.
+    /// Sessions that are still pending are kept, see [`HasProgress::is_pending()`]. Returns the number of sessions that
+    /// were removed.
+    fn purge_finished(&self) -> impl Future<Output = Result<u64, SessionStoreError>> + Send;
+
+    /// Subscribe to updates of the sessions in the store. From this moment on, the returned receiver yields the
+    /// token of every session that is written or expired. Note that a subscriber that lags behind by more than
+    /// [`SESSION_UPDATES_CAPACITY`] updates will miss some of them, which is signalled by the receiver.
+    fn subscribe(&self) -> broadcast::Receiver<SessionToken>;
+
+    /// Periodically call [`SessionStore::cleanup()`]. The duration and any errors of every cleanup are recorded in the
+    /// metrics, labeled with `session_type`.
+    fn start_cleanup_task(self: Arc<Self>, interval: Duration, session_type: &'static str) -> JoinHandle<()>
     where
         Self: Send + Sync + 'static,
     {
@@ -78,12 +234,40 @@ where
         tokio::spawn(async move {
             loop {
                 interval.tick().await;
-                if let Err(e) = self.cleanup().await {
+
+                let start = Instant::now();
+                let result = self.cleanup().await;
+                histogram!("session_store_cleanup_duration_seconds", "session_type" => session_type)
+                    .record(start.elapsed());
+
+                if let Err(e) = result {
+                    counter!("session_store_cleanup_errors_total", "session_type" => session_type).increment(1);
                     warn!("error during session cleanup: {e}");
                 }
             }
         })
     }
+
+    /// Periodically call [`SessionStore::count()`] and record the number of sessions per status in the metrics, labeled
+    /// with `session_type`.
+    fn start_count_task(self: Arc<Self>, interval: Duration, session_type: &'static str) -> JoinHandle<()>
+    where
+        Self: Send + Sync + 'static,
+    {
+        let mut interval = time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+
+                match self.count().await {
+                    Ok(counts) => counts.record(session_type),
+                    Err(e) => warn!("error counting sessions: {e}"),
+                }
+            }
+        })
+    }
 }
 
 /// Different timeout values that should be used by the [`SessionStore::cleanup()`] implementation.
@@ -97,12 +281,30 @@ pub struct SessionStoreTimeouts {
     pub failed_deletion: Duration,
 }
 
+impl SessionStoreTimeouts {
+    /// The amount of inactivity after which the session containing `data` should be expired, taking into account the
+    /// expiration that may have been set for that particular session.
+    pub fn expiration_for(&self, data: &impl Expirable) -> Duration {
+        data.expiration().unwrap_or(self.expiration)
+    }
+}
+
+/// Add `duration` to `time`, saturating at the maximum representable moment instead of panicking on overflow. This
+/// should be used whenever `duration` may be a per-session expiration, as that is ultimately provided by a caller.
+pub fn saturating_add(time: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| time.checked_add_signed(duration))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
 #[derive(Debug)]
 pub struct MemorySessionStore<T, G = TimeGenerator> {
     pub timeouts: SessionStoreTimeouts,
     time: G,
     // Store the session state and expired boolean as the value
     sessions: DashMap<SessionToken, SessionState<T>>,
+    updates: broadcast::Sender<SessionToken>,
 }
 
 impl<T> SessionState<T> {
@@ -111,6 +313,17 @@ impl<T> SessionState<T> {
             data,
             token,
             last_active: Utc::now(),
+            version: 0,
+        }
+    }
+
+    /// Replace the data of the session state, retaining its version and updating the `last_active` timestamp.
+    pub fn transition<U>(self, data: U) -> SessionState<U> {
+        SessionState {
+            data,
+            token: self.token,
+            last_active: Utc::now(),
+            version: self.version,
         }
     }
 }
@@ -131,6 +344,7 @@ impl<T, G> MemorySessionStore<T, G> {
             timeouts,
             time,
             sessions: DashMap::new(),
+            updates: broadcast::channel(SESSION_UPDATES_CAPACITY).0,
         }
     }
 }
@@ -164,11 +378,18 @@ where
         // since we do not have a mutable reference on the `DashMap`.
         let entry = self.sessions.entry(session.token.clone());
 
-        if matches!(entry, Entry::Occupied(_)) && is_new {
-            return Err(SessionStoreError::DuplicateToken(session.token));
-        }
+        let version = match (&entry, is_new) {
+            (Entry::Occupied(_), true) => return Err(SessionStoreError::DuplicateToken(session.token)),
+            (Entry::Vacant(_), true) => session.version,
+            (Entry::Occupied(stored), false) if stored.get().version == session.version => session.version + 1,
+            (_, false) => return Err(SessionStoreError::Conflict(session.token)),
+        };
+
+        let token = session.token.clone();
+        entry.insert(SessionState { version, ..session });
 
-        entry.insert(session);
+        // Sending only fails when there are no subscribers, which is fine.
+        let _ = self.updates.send(token);
 
         Ok(())
     }
@@ -177,13 +398,17 @@ where
         let now = self.time.generate();
         let succeeded_cutoff = now - self.timeouts.successful_deletion;
         let failed_cutoff = now - self.timeouts.failed_deletion;
-        let expiry_cutoff = now - self.timeouts.expiration;
 
         self.sessions.retain(|_, session| {
             match (session.data.progress(), session.data.is_expired()) {
-                // Remove all succeeded sessions that are older than the "successful_deletion" timeout.
+                // Remove all succeeded sessions that are older than the "successful_deletion" timeout, unless they have
+                // their own expiration that has not yet passed.
                 (Progress::Finished { has_succeeded }, false) if has_succeeded => {
                     session.last_active >= succeeded_cutoff
+                        || session
+                            .data
+                            .expiration()
+                            .is_some_and(|expiration| saturating_add(session.last_active, expiration) >= now)
                 }
                 // Remove all failed and expired sessions that are older than the "failed_deletion" timeout.
                 (Progress::Finished { .. }, false) | (_, true) => session.last_active >= failed_cutoff,
@@ -191,20 +416,74 @@ where
             }
         });
 
-        // For all active sessions that are older than the "expiration" timeout,
+        // For all active sessions that are older than the "expiration" timeout, or their own expiration if set,
         // update the last active time and set them to expired.
         self.sessions.iter_mut().for_each(|mut session| {
             if !session.data.is_expired()
                 && matches!(session.data.progress(), Progress::Active)
-                && session.last_active < expiry_cutoff
+                && saturating_add(session.last_active, self.timeouts.expiration_for(&session.data)) < now
             {
                 session.last_active = now;
+                session.version += 1;
                 session.data.expire();
+
+                let _ = self.updates.send(session.token.clone());
             }
         });
 
         Ok(())
     }
+
+    async fn list(&self, status: Option<ProgressStatus>) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        let sessions = self
+            .sessions
+            .iter()
+            .filter(|session| status.map_or(true, |status| ProgressStatus::of(&session.data) == status))
+            .map(|session| session.clone())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    async fn list_pending(&self) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        let sessions = self
+            .sessions
+            .iter()
+            .filter(|session| session.data.is_pending())
+            .map(|session| session.clone())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    async fn count(&self) -> Result<SessionCounts, SessionStoreError> {
+        let counts = self
+            .sessions
+            .iter()
+            .fold(SessionCounts::default(), |mut counts, session| {
+                counts.add(ProgressStatus::of(&session.data), 1);
+                counts
+            });
+
+        Ok(counts)
+    }
+
+    async fn purge_finished(&self) -> Result<u64, SessionStoreError> {
+        let mut count = 0;
+        self.sessions.retain(|_, session| {
+            let is_purged = ProgressStatus::of(&session.data).is_finished() && !session.data.is_pending();
+            if is_purged {
+                count += 1;
+            }
+            !is_purged
+        });
+
+        Ok(count)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SessionToken> {
+        self.updates.subscribe()
+    }
 }
 
 /// Identifies a session in a URL, as passed from the issuer/RP to the holder using the `url` field of
@@ -289,6 +568,7 @@ pub mod test {
             data: T::new_random(),
             token: token.clone(),
             last_active: session.last_active + Duration::from_secs(1),
+            version: session_read.version,
         };
 
         // Writing this as new data should return an error that indicates data for this token already exists.
@@ -321,26 +601,234 @@ pub mod test {
             session_read.last_active.timestamp_micros(),
             updated_session.last_active.timestamp_micros()
         );
+
+        // The version should have been incremented by the update.
+        assert_eq!(session_read.version, updated_session.version + 1);
+
+        // Writing an update based on the previous version should return an error that indicates a conflicting update.
+        assert_matches!(
+            session_store.write(updated_session.clone(), false).await.expect_err("should return error"),
+            SessionStoreError::Conflict(conflicting_token) if conflicting_token == token
+        );
+
+        // Updating a session that is not present should also result in a conflict.
+        let other_session = SessionState::new(SessionToken::new_random(), T::new_random());
+
+        assert_matches!(
+            session_store.write(other_session.clone(), false).await.expect_err("should return error"),
+            SessionStoreError::Conflict(conflicting_token) if conflicting_token == other_session.token
+        );
+
+        // The stored session state should not have been changed by the conflicting updates.
+        let session_read = session_store
+            .get(&token)
+            .await
+            .expect("should succeed")
+            .expect("should return session");
+
+        assert_eq!(session_read.data, updated_session.data);
+        assert_eq!(session_read.version, updated_session.version + 1);
+    }
+
+    /// Test that subscribers to a `SessionStore` implementation receive session updates.
+    pub async fn test_session_store_subscribe<T>(session_store: &impl SessionStore<T>)
+    where
+        T: Debug + Clone + HasProgress + Expirable + RandomData,
+    {
+        let mut updates = session_store.subscribe();
+
+        // Both writing new session state and updating it should result in an update.
+        let token = SessionToken::new_random();
+        let session = SessionState::new(token.clone(), T::new_random());
+
+        session_store
+            .write(session.clone(), true)
+            .await
+            .expect("should succeed");
+
+        let updated_token = time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("should receive update in time")
+            .expect("should receive update");
+
+        assert_eq!(updated_token, token);
+
+        session_store.write(session, false).await.expect("should succeed");
+
+        let updated_token = time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("should receive update in time")
+            .expect("should receive update");
+
+        assert_eq!(updated_token, token);
+    }
+
+    /// Test listing, counting and purging the sessions in a `SessionStore` implementation. As other sessions may be
+    /// present in the store, this only checks the sessions created by the test itself.
+    pub async fn test_session_store_list_count_purge<T>(session_store: &impl SessionStore<T>)
+    where
+        T: HasProgress + Expirable + From<Progress>,
+    {
+        // Start with a store that contains no finished sessions, apart from those that are pending.
+        session_store.purge_finished().await.expect("should succeed");
+        let counts = session_store.count().await.expect("should succeed");
+
+        // Write one session for every progress.
+        let active_token = SessionToken::new_random();
+        let succeeded_token = SessionToken::new_random();
+        let failed_token = SessionToken::new_random();
+
+        for (token, progress) in [
+            (&active_token, Progress::Active),
+            (&succeeded_token, Progress::Finished { has_succeeded: true }),
+            (&failed_token, Progress::Finished { has_succeeded: false }),
+        ] {
+            let session = SessionState::new(token.clone(), T::from(progress));
+            session_store.write(session, true).await.expect("should succeed");
+        }
+
+        // The counts should reflect the new sessions.
+        let new_counts = session_store.count().await.expect("should succeed");
+
+        assert_eq!(new_counts.active, counts.active + 1);
+        assert_eq!(new_counts.succeeded, counts.succeeded + 1);
+        assert_eq!(new_counts.failed, counts.failed + 1);
+
+        // All sessions should be listed, or only the matching ones if a status is specified.
+        let tokens =
+            |sessions: Vec<SessionState<T>>| sessions.into_iter().map(|session| session.token).collect::<Vec<_>>();
+
+        let all_tokens = tokens(session_store.list(None).await.expect("should succeed"));
+
+        assert!(all_tokens.contains(&active_token));
+        assert!(all_tokens.contains(&succeeded_token));
+        assert!(all_tokens.contains(&failed_token));
+
+        let succeeded_tokens = tokens(
+            session_store
+                .list(Some(ProgressStatus::Succeeded))
+                .await
+                .expect("should succeed"),
+        );
+
+        assert!(succeeded_tokens.contains(&succeeded_token));
+        assert!(!succeeded_tokens.contains(&active_token));
+        assert!(!succeeded_tokens.contains(&failed_token));
+
+        // Purging the finished sessions should only remove the succeeded and failed sessions.
+        let purged = session_store.purge_finished().await.expect("should succeed");
+
+        assert_eq!(purged, 2);
+        assert!(session_store
+            .get(&active_token)
+            .await
+            .expect("should succeed")
+            .is_some());
+        assert!(session_store
+            .get(&succeeded_token)
+            .await
+            .expect("should succeed")
+            .is_none());
+        assert!(session_store
+            .get(&failed_token)
+            .await
+            .expect("should succeed")
+            .is_none());
+    }
+
+    /// Test listing and purging the pending sessions in a `SessionStore` implementation, using finished session data
+    /// that is pending and finished session data that is not.
+    pub async fn test_session_store_list_pending<T>(session_store: &impl SessionStore<T>, pending: T, not_pending: T)
+    where
+        T: HasProgress + Expirable,
+    {
+        let pending_token = SessionToken::new_random();
+        let not_pending_token = SessionToken::new_random();
+
+        session_store
+            .write(SessionState::new(pending_token.clone(), pending), true)
+            .await
+            .expect("should succeed");
+        session_store
+            .write(SessionState::new(not_pending_token.clone(), not_pending), true)
+            .await
+            .expect("should succeed");
+
+        let pending_tokens = session_store
+            .list_pending()
+            .await
+            .expect("should succeed")
+            .into_iter()
+            .map(|session| session.token)
+            .collect::<Vec<_>>();
+
+        assert!(pending_tokens.contains(&pending_token));
+        assert!(!pending_tokens.contains(&not_pending_token));
+
+        let not_pending = session_store
+            .get(&not_pending_token)
+            .await
+            .expect("should succeed")
+            .expect("session should be present")
+            .data;
+
+        // Purging the finished sessions should only remove the session that is not pending.
+        session_store.purge_finished().await.expect("should succeed");
+
+        assert!(session_store
+            .get(&not_pending_token)
+            .await
+            .expect("should succeed")
+            .is_none());
+
+        // Once the session is no longer pending, it should no longer be listed.
+        let mut session = session_store
+            .get(&pending_token)
+            .await
+            .expect("should succeed")
+            .expect("session should be present");
+        session.data = not_pending;
+        session_store.write(session, false).await.expect("should succeed");
+
+        let pending_tokens = session_store
+            .list_pending()
+            .await
+            .expect("should succeed")
+            .into_iter()
+            .map(|session| session.token)
+            .collect::<Vec<_>>();
+
+        assert!(!pending_tokens.contains(&pending_token));
+
+        // It should then also be removed by purging the finished sessions.
+        session_store.purge_finished().await.expect("should succeed");
+
+        assert!(session_store
+            .get(&pending_token)
+            .await
+            .expect("should succeed")
+            .is_none());
     }
 
     pub async fn test_session_store_cleanup<T>(
         session_store: &impl SessionStore<T>,
         mock_time: &RwLock<DateTime<Utc>>,
         token: SessionToken,
-        session_progress: Progress,
+        session_data: T,
         max_time: Duration,
     ) -> Result<Option<SessionState<T>>, SessionStoreError>
     where
-        T: HasProgress + Expirable + From<Progress>,
+        T: HasProgress + Expirable,
     {
         // Get the mock time.
         let t1 = *mock_time.read();
 
         // Create new session state, this should be present in the store after cleanup, as the time has not advanced.
         let session = SessionState {
-            data: T::from(session_progress),
+            data: session_data,
             token: token.clone(),
             last_active: t1,
+            version: 0,
         };
 
         session_store.write(session, true).await.unwrap();
@@ -378,7 +866,7 @@ pub mod test {
             session_store,
             mock_time,
             token.clone(),
-            Progress::Active,
+            T::from(Progress::Active),
             timeouts.expiration,
         )
         .await
@@ -412,6 +900,59 @@ pub mod test {
         assert!(session.is_none());
     }
 
+    /// Test that an active session that has its own expiration set is expired according to that expiration, instead of
+    /// the "expiration" timeout. The expiration of `session_data` should be longer than that timeout.
+    pub async fn test_session_store_cleanup_expiration_override<T>(
+        session_store: &impl SessionStore<T>,
+        timeouts: &SessionStoreTimeouts,
+        mock_time: &RwLock<DateTime<Utc>>,
+        session_data: T,
+    ) where
+        T: HasProgress + Expirable,
+    {
+        let expiration = session_data
+            .expiration()
+            .expect("session data should have an expiration");
+        assert!(expiration > timeouts.expiration);
+
+        let t1 = *mock_time.read();
+        let token = SessionToken::new_random();
+        let session = SessionState {
+            data: session_data,
+            token: token.clone(),
+            last_active: t1,
+            version: 0,
+        };
+
+        session_store.write(session, true).await.unwrap();
+
+        // Advance the time to just after the "expiration" timeout, the session state should still be active.
+        *mock_time.write() = t1 + timeouts.expiration + chrono::Duration::milliseconds(1);
+
+        session_store.cleanup().await.unwrap();
+
+        let session = session_store
+            .get(&token)
+            .await
+            .expect("should succeed")
+            .expect("should return session");
+
+        assert!(!session.data.is_expired());
+
+        // Advance the time to just after the expiration of the session, which should then be expired.
+        *mock_time.write() = t1 + expiration + chrono::Duration::milliseconds(1);
+
+        session_store.cleanup().await.unwrap();
+
+        let session = session_store
+            .get(&token)
+            .await
+            .expect("should succeed")
+            .expect("should return session");
+
+        assert!(session.data.is_expired());
+    }
+
     pub async fn test_session_store_cleanup_successful_deletion<T>(
         session_store: &impl SessionStore<T>,
         timeouts: &SessionStoreTimeouts,
@@ -426,7 +967,7 @@ pub mod test {
             session_store,
             mock_time,
             token.clone(),
-            Progress::Finished { has_succeeded: true },
+            T::from(Progress::Finished { has_succeeded: true }),
             timeouts.successful_deletion,
         )
         .await
@@ -435,6 +976,57 @@ pub mod test {
         assert!(session.is_none());
     }
 
+    /// Test that a succeeded session that has its own expiration set is kept until that expiration, instead of being
+    /// deleted after the "successful_deletion" timeout. The expiration of `session_data` should be longer than that
+    /// timeout.
+    pub async fn test_session_store_cleanup_successful_deletion_override<T>(
+        session_store: &impl SessionStore<T>,
+        timeouts: &SessionStoreTimeouts,
+        mock_time: &RwLock<DateTime<Utc>>,
+        session_data: T,
+    ) where
+        T: HasProgress + Expirable,
+    {
+        assert_eq!(session_data.progress(), Progress::Finished { has_succeeded: true });
+        let expiration = session_data
+            .expiration()
+            .expect("session data should have an expiration");
+        assert!(expiration > timeouts.successful_deletion);
+
+        let t1 = *mock_time.read();
+        let token = SessionToken::new_random();
+        let session = SessionState {
+            data: session_data,
+            token: token.clone(),
+            last_active: t1,
+            version: 0,
+        };
+
+        session_store.write(session, true).await.unwrap();
+
+        // Advance the time to just after the "successful_deletion" timeout, the session state should still be present.
+        *mock_time.write() = t1 + timeouts.successful_deletion + chrono::Duration::milliseconds(1);
+
+        session_store.cleanup().await.unwrap();
+
+        let session = session_store
+            .get(&token)
+            .await
+            .expect("should succeed")
+            .expect("should return session");
+
+        assert!(!session.data.is_expired());
+
+        // Advance the time to just after the expiration of the session, which should then no longer be present.
+        *mock_time.write() = t1 + expiration + chrono::Duration::milliseconds(1);
+
+        session_store.cleanup().await.unwrap();
+
+        let session = session_store.get(&token).await.expect("should succeed");
+
+        assert!(session.is_none());
+    }
+
     pub async fn test_session_store_cleanup_failed_deletion<T>(
         session_store: &impl SessionStore<T>,
         timeouts: &SessionStoreTimeouts,
@@ -449,7 +1041,7 @@ pub mod test {
             session_store,
             mock_time,
             token.clone(),
-            Progress::Finished { has_succeeded: false },
+            T::from(Progress::Finished { has_succeeded: false }),
             timeouts.failed_deletion,
         )
         .await
@@ -475,6 +1067,8 @@ mod tests {
     struct MockSessionData {
         progress: Progress,
         is_expired: bool,
+        expiration: Option<Duration>,
+        is_pending: bool,
         data: Vec<u8>,
     }
 
@@ -483,6 +1077,8 @@ mod tests {
             Self {
                 progress,
                 is_expired: false,
+                expiration: None,
+                is_pending: false,
                 data: utils::random_bytes(32),
             }
         }
@@ -498,6 +1094,10 @@ mod tests {
         fn progress(&self) -> Progress {
             self.progress
         }
+
+        fn is_pending(&self) -> bool {
+            self.is_pending
+        }
     }
 
     impl Expirable for MockSessionData {
@@ -508,6 +1108,10 @@ mod tests {
         fn expire(&mut self) {
             self.is_expired = true;
         }
+
+        fn expiration(&self) -> Option<Duration> {
+            self.expiration
+        }
     }
 
     impl RandomData for MockSessionData {
@@ -522,6 +1126,33 @@ mod tests {
         test::test_session_store_get_write(&session_store).await;
     }
 
+    #[tokio::test]
+    async fn test_memory_session_store_list_count_purge() {
+        let session_store = MemorySessionStore::<MockSessionData, _>::default();
+        test::test_session_store_list_count_purge(&session_store).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_session_store_list_pending() {
+        let session_store = MemorySessionStore::<MockSessionData, _>::default();
+        let finished = Progress::Finished { has_succeeded: true };
+        test::test_session_store_list_pending(
+            &session_store,
+            MockSessionData {
+                is_pending: true,
+                ..MockSessionData::new(finished)
+            },
+            MockSessionData::new(finished),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_session_store_subscribe() {
+        let session_store = MemorySessionStore::<MockSessionData, _>::default();
+        test::test_session_store_subscribe(&session_store).await;
+    }
+
     fn memory_session_store_with_mock_time() -> (
         MemorySessionStore<MockSessionData, MockTimeGenerator>,
         Arc<RwLock<DateTime<Utc>>>,
@@ -540,6 +1171,23 @@ mod tests {
         test::test_session_store_cleanup_expiration(&session_store, &session_store.timeouts, mock_time.as_ref()).await;
     }
 
+    #[tokio::test]
+    async fn test_memory_session_store_cleanup_expiration_override() {
+        let (session_store, mock_time) = memory_session_store_with_mock_time();
+        let session_data = MockSessionData {
+            expiration: Some(session_store.timeouts.expiration * 2),
+            ..MockSessionData::new(Progress::Active)
+        };
+
+        test::test_session_store_cleanup_expiration_override(
+            &session_store,
+            &session_store.timeouts,
+            mock_time.as_ref(),
+            session_data,
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_memory_session_store_cleanup_successful_deletion() {
         let (session_store, mock_time) = memory_session_store_with_mock_time();
@@ -552,6 +1200,23 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_memory_session_store_cleanup_successful_deletion_override() {
+        let (session_store, mock_time) = memory_session_store_with_mock_time();
+        let session_data = MockSessionData {
+            expiration: Some(session_store.timeouts.successful_deletion * 2),
+            ..MockSessionData::new(Progress::Finished { has_succeeded: true })
+        };
+
+        test::test_session_store_cleanup_successful_deletion_override(
+            &session_store,
+            &session_store.timeouts,
+            mock_time.as_ref(),
+            session_data,
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_memory_session_store_cleanup_failed_deletion() {
         let (session_store, mock_time) = memory_session_store_with_mock_time();
@@ -22,7 +22,8 @@ use crate::{
     },
     jwt,
     openid4vp::{
-        IsoVpAuthorizationRequest, RequestUriMethod, VpAuthorizationRequest, VpRequestUriObject, WalletRequest,
+        IsoVpAuthorizationRequest, QueryLanguage, RequestUriMethod, VpAuthorizationRequest, VpRequestUriObject,
+        WalletRequest,
     },
     verifier::{SessionType, VerifierUrlParameters},
     AuthorizationErrorCode, ErrorResponse, VpAuthorizationErrorCode,
@@ -39,6 +40,7 @@ pub struct MockVerifierSession<F> {
     pub reader_registration: Option<ReaderRegistration>,
     pub trust_anchors: Vec<DerTrustAnchor>,
     pub items_requests: ItemsRequests,
+    pub query_language: QueryLanguage,
     pub nonce: String,
     pub encryption_keypair: EcKeyPair,
     pub request_uri_object: VpRequestUriObject,
@@ -58,6 +60,7 @@ impl<F> fmt::Debug for MockVerifierSession<F> {
             .field("reader_registration", &self.reader_registration)
             .field("trust_anchors", &self.trust_anchors)
             .field("items_requests", &self.items_requests)
+            .field("query_language", &self.query_language)
             .field("nonce", &self.nonce)
             .field("encryption_keypair", &self.encryption_keypair)
             .field("request_uri_object", &self.request_uri_object)
@@ -120,6 +123,7 @@ where
             reader_registration,
             key_pair,
             items_requests,
+            query_language: QueryLanguage::default(),
             transform_auth_request,
             nonce,
             encryption_keypair,
@@ -134,7 +138,7 @@ where
         self.key_pair.certificate().san_dns_name().unwrap().unwrap()
     }
 
-    fn trust_anchors(&self) -> Vec<TrustAnchor> {
+    fn trust_anchors(&self) -> Vec<TrustAnchor<'_>> {
         self.trust_anchors
             .iter()
             .map(|anchor| (&anchor.owned_trust_anchor).into())
@@ -151,6 +155,7 @@ where
     async fn auth_request(&self, wallet_request: WalletRequest) -> Jwt<VpAuthorizationRequest> {
         let request = IsoVpAuthorizationRequest::new(
             &self.items_requests,
+            self.query_language,
             self.key_pair.certificate(),
             self.nonce.clone(),
             self.encryption_keypair.to_jwk_public_key().try_into().unwrap(),
@@ -439,6 +444,7 @@ pub fn iso_auth_request() -> IsoVpAuthorizationRequest {
 
     IsoVpAuthorizationRequest::new(
         &vec![ItemsRequest::new_example()].into(),
+        QueryLanguage::default(),
         key_pair.certificate(),
         random_string(32),
         EcKeyPair::generate(EcCurve::P256)
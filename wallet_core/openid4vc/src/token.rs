@@ -22,7 +22,7 @@ use wallet_common::{
 
 use crate::{authorization::AuthorizationDetails, server_state::SessionToken};
 
-#[derive(Serialize, Deserialize, Debug, Clone, From)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, From)]
 pub struct AuthorizationCode(String);
 
 impl AsRef<str> for AuthorizationCode {
@@ -62,6 +62,30 @@ impl AccessToken {
     }
 }
 
+/// A refresh token with which the wallet can obtain fresh copies of the attestations it received, see
+/// <https://www.rfc-editor.org/rfc/rfc6749.html#section-6>. Like the [`AccessToken`], it has the authorization code of
+/// the session appended to it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, From)]
+pub struct RefreshToken(String);
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl RefreshToken {
+    /// Construct a new random refresh token, with the specified authorization code appended to it.
+    pub(crate) fn new(code: &AuthorizationCode) -> Self {
+        Self(random_string(32) + code.as_ref())
+    }
+
+    /// Returns the authorization code appended to this refresh token.
+    pub(crate) fn code(&self) -> Option<AuthorizationCode> {
+        self.as_ref().get(32..).map(|code| AuthorizationCode(code.to_string()))
+    }
+}
+
 /// https://openid.github.io/OpenID4VCI/openid-4-verifiable-credential-issuance-wg-draft.html#name-token-request
 /// and https://www.rfc-editor.org/rfc/rfc6749.html#section-4.1.3.
 /// Sent URL-encoded in request body to POST /token.
@@ -81,10 +105,14 @@ pub struct TokenRequest {
 
 impl TokenRequest {
     /// Retrieve either the authorization code or the pre-authorized code, depending on the authorization grant type.
-    pub fn code(&self) -> &AuthorizationCode {
+    /// Returns `None` for a refresh token request.
+    pub fn code(&self) -> Option<&AuthorizationCode> {
         match &self.grant_type {
-            TokenRequestGrantType::AuthorizationCode { code } => code,
-            TokenRequestGrantType::PreAuthorizedCode { pre_authorized_code } => pre_authorized_code,
+            TokenRequestGrantType::AuthorizationCode { code } => Some(code),
+            TokenRequestGrantType::PreAuthorizedCode {
+                pre_authorized_code, ..
+            } => Some(pre_authorized_code),
+            TokenRequestGrantType::RefreshToken { .. } => None,
         }
     }
 }
@@ -100,7 +128,12 @@ pub enum TokenRequestGrantType {
     PreAuthorizedCode {
         #[serde(rename = "pre-authorized_code")]
         pre_authorized_code: AuthorizationCode,
+        /// The Transaction Code that the issuer delivered to the user out-of-band, if the Credential Offer
+        /// required one.
+        tx_code: Option<String>,
     },
+    #[serde(rename = "refresh_token")]
+    RefreshToken { refresh_token: RefreshToken },
 }
 
 /// See
@@ -112,7 +145,7 @@ pub enum TokenRequestGrantType {
 pub struct TokenResponse {
     pub access_token: AccessToken,
     pub token_type: TokenType,
-    pub refresh_token: Option<String>,
+    pub refresh_token: Option<RefreshToken>,
     pub c_nonce: Option<String>,
 
     #[serde_as(as = "Option<StringWithSeparator::<SpaceSeparator, String>>")]
@@ -223,7 +256,8 @@ mod tests {
         assert_eq!(
             serde_urlencoded::to_string(TokenRequest {
                 grant_type: TokenRequestGrantType::PreAuthorizedCode {
-                    pre_authorized_code: "123".to_string().into()
+                    pre_authorized_code: "123".to_string().into(),
+                    tx_code: Some("4321".to_string()),
                 },
                 code_verifier: Some("myverifier".to_string()),
                 client_id: Some("myclient".to_string()),
@@ -232,6 +266,7 @@ mod tests {
             .unwrap(),
             "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Apre-authorized_code\
                 &pre-authorized_code=123\
+                &tx_code=4321\
                 &code_verifier=myverifier\
                 &client_id=myclient\
                 &redirect_uri=https%3A%2F%2Fexample.com%2F",
@@ -1,6 +1,11 @@
 //! RP software, for verifying mdoc disclosures, see [`DeviceResponse::verify()`].
 
-use std::{collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
 
 use chrono::{DateTime, SecondsFormat, Utc};
 use itertools::Itertools;
@@ -11,11 +16,17 @@ use josekit::{
     },
     JoseError,
 };
+use mime::Mime;
 use nutype::nutype;
+use reqwest::header::CONTENT_TYPE;
 use ring::hmac;
 use serde::{Deserialize, Serialize};
 use serde_with::{hex::Hex, serde_as, skip_serializing_none};
-use tokio::task::JoinHandle;
+use tokio::{
+    sync::broadcast,
+    task::JoinHandle,
+    time::{self, MissedTickBehavior},
+};
 use tracing::{debug, info, warn};
 
 use nl_wallet_mdoc::{
@@ -27,6 +38,7 @@ use nl_wallet_mdoc::{
 use wallet_common::{
     generator::Generator,
     jwt::{Jwt, JwtError},
+    reqwest::default_reqwest_client_builder,
     trust_anchor::OwnedTrustAnchor,
     urls::BaseUrl,
     utils::random_string,
@@ -35,19 +47,44 @@ use wallet_common::{
 use crate::{
     jwt,
     openid4vp::{
-        AuthRequestError, AuthResponseError, IsoVpAuthorizationRequest, RequestUriMethod, VpAuthorizationRequest,
-        VpAuthorizationResponse, VpRequestUriObject, VpResponse,
+        AuthRequestError, AuthResponseError, IsoVpAuthorizationRequest, QueryLanguage, RequestUriMethod,
+        VpAuthorizationRequest, VpAuthorizationResponse, VpRequestUriObject, VpResponse,
     },
     return_url::ReturnUrlTemplate,
     server_state::{
-        Expirable, HasProgress, Progress, SessionState, SessionStore, SessionStoreError, SessionToken,
-        CLEANUP_INTERVAL_SECONDS,
+        saturating_add, Expirable, HasProgress, Progress, ProgressStatus, SessionCounts, SessionState,
+        SessionStateLabels, SessionStore, SessionStoreError, SessionToken, CLEANUP_INTERVAL_SECONDS,
+        COUNT_INTERVAL_SECONDS,
     },
     AuthorizationErrorCode, ErrorResponse, VpAuthorizationErrorCode,
 };
 
 pub const EPHEMERAL_ID_VALIDITY_SECONDS: Duration = Duration::from_secs(10);
 
+/// The maximum expiration an RP may specify for a session in its [`SessionOptions`].
+pub const MAX_SESSION_EXPIRATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The maximum length of the correlation id an RP may specify for a session in its [`SessionOptions`].
+pub const MAX_CORRELATION_ID_LENGTH: usize = 256;
+
+/// The maximum number of times the verifier tries to deliver a session callback to the RP.
+pub const CALLBACK_MAX_ATTEMPTS: u32 = 5;
+/// The delay before retrying a failed callback delivery, which is doubled after every attempt.
+pub const CALLBACK_INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+/// The interval at which the callbacks of finished sessions that have not been delivered yet are (re)delivered.
+pub const CALLBACK_REDELIVERY_INTERVAL: Duration = Duration::from_secs(60);
+/// The time for which an instance claims the delivery of a callback, which exceeds the time taken by all delivery
+/// attempts. If the delivery has not finished by then, e.g. because the instance was stopped, another instance
+/// delivers the callback.
+pub const CALLBACK_CLAIM_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// The session type with which disclosure sessions are labeled in the metrics.
+const METRICS_SESSION_TYPE: &str = "disclosure";
+
+/// Content type of the signed session callback that is POSTed to the callback URL of the RP.
+pub static APPLICATION_JWT: LazyLock<Mime> =
+    LazyLock::new(|| "application/jwt".parse().expect("could not parse MIME type"));
+
 /// Errors that can occur during processing of any of the endpoints.
 #[derive(Debug, thiserror::Error)]
 pub enum SessionError {
@@ -70,6 +107,10 @@ pub enum NewSessionError {
     UnknownUseCase(String),
     #[error("presence or absence of return url template does not match configuration for the required use case")]
     ReturnUrlConfigurationMismatch,
+    #[error("session expiration of {0:?} exceeds the maximum of {MAX_SESSION_EXPIRATION:?}")]
+    ExpirationTooLong(Duration),
+    #[error("correlation id should consist of 1 to {MAX_CORRELATION_ID_LENGTH} visible ASCII characters")]
+    InvalidCorrelationId,
 }
 
 /// Errors returned by the session status endpoint, used by the web front-end.
@@ -185,20 +226,33 @@ pub struct Created {
     usecase_id: String,
     client_id: String,
     redirect_uri_template: Option<ReturnUrlTemplate>,
+    callback: Option<SessionCallback>,
+    #[serde(default)]
+    options: SessionOptions,
 }
 
 /// State for a session that is waiting for the user's disclosure, i.e., the device has contacted us at the session URL.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WaitingForResponse {
+    #[serde(default)]
+    usecase_id: String,
     auth_request: IsoVpAuthorizationRequest,
     encryption_key: EncryptionPrivateKey,
     redirect_uri: Option<RedirectUri>,
+    callback: Option<SessionCallback>,
+    #[serde(default)]
+    options: SessionOptions,
 }
 
 /// State for a session that has ended (for any reason).
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Done {
+    #[serde(default)]
+    usecase_id: String,
     session_result: SessionResult,
+    callback: Option<SessionCallback>,
+    #[serde(default)]
+    options: SessionOptions,
 }
 
 /// The outcome of a session: the disclosed attributes if they have been successfully received and verified.
@@ -216,6 +270,96 @@ enum SessionResult {
     Expired,
 }
 
+/// Options that the RP can specify per disclosure session.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionOptions {
+    /// The amount of time after which the session expires when it is not completed, which overrides the expiration
+    /// timeout of the session store.
+    pub expiration: Option<Duration>,
+    /// An opaque identifier chosen by the RP, which is returned along with the disclosed attributes. As it may be
+    /// returned in an HTTP header, it should consist of visible ASCII characters only.
+    pub correlation_id: Option<String>,
+}
+
+/// A summary of a disclosure session, as listed to the operator of the verifier. This intentionally does not contain
+/// any of the disclosed attributes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_token: SessionToken,
+    pub usecase_id: String,
+    #[serde(flatten)]
+    pub status: StatusResponse,
+    pub last_active: DateTime<Utc>,
+}
+
+impl From<SessionState<DisclosureData>> for SessionSummary {
+    fn from(value: SessionState<DisclosureData>) -> Self {
+        let status = match &value.data {
+            DisclosureData::Created(_) => StatusResponse::Created { ul: None },
+            DisclosureData::WaitingForResponse(_) => StatusResponse::WaitingForResponse,
+            DisclosureData::Done(Done { session_result, .. }) => StatusResponse::from(session_result),
+        };
+
+        Self {
+            usecase_id: value.data.usecase_id().to_string(),
+            session_token: value.token,
+            status,
+            last_active: value.last_active,
+        }
+    }
+}
+
+/// The URL the RP wants to be notified at when the session is done, along with the delivery status of that
+/// notification. The use case is included so that the notification can be signed with the matching key pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCallback {
+    url: BaseUrl,
+    usecase_id: String,
+    #[serde(default)]
+    status: CallbackStatus,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", tag = "status")]
+pub enum CallbackStatus {
+    #[default]
+    Pending,
+    /// The callback is being delivered by one of the instances sharing the session store, which has claimed the
+    /// delivery until the specified moment.
+    Delivering {
+        until: DateTime<Utc>,
+    },
+    Delivered {
+        attempts: u32,
+    },
+    Failed {
+        attempts: u32,
+    },
+}
+
+impl CallbackStatus {
+    /// Whether the callback should still be delivered, which is the case if it is pending or if the delivery was
+    /// claimed but not finished in time.
+    fn is_claimable(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            Self::Pending => true,
+            Self::Delivering { until } => *until < now,
+            Self::Delivered { .. } | Self::Failed { .. } => false,
+        }
+    }
+}
+
+/// The notification that is sent to the callback URL of the RP as a JWT, signed with the key pair of the use case.
+/// The RP can use the session token to retrieve the disclosed attributes, if the session has succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCallbackClaims {
+    pub session_token: SessionToken,
+    #[serde(flatten)]
+    pub status: StatusResponse,
+    pub iat: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedirectUri {
     uri: BaseUrl,
@@ -264,17 +408,60 @@ pub enum WalletAuthResponse {
 }
 
 /// Disclosure session states for use as `T` in `Session<T>`.
-pub trait DisclosureState {}
+pub trait DisclosureState {
+    fn usecase_id(&self) -> &str;
+    fn callback(&self) -> Option<&SessionCallback>;
+    fn options(&self) -> &SessionOptions;
+}
+
+impl DisclosureState for Created {
+    fn usecase_id(&self) -> &str {
+        &self.usecase_id
+    }
+
+    fn callback(&self) -> Option<&SessionCallback> {
+        self.callback.as_ref()
+    }
+
+    fn options(&self) -> &SessionOptions {
+        &self.options
+    }
+}
+
+impl DisclosureState for WaitingForResponse {
+    fn usecase_id(&self) -> &str {
+        &self.usecase_id
+    }
+
+    fn callback(&self) -> Option<&SessionCallback> {
+        self.callback.as_ref()
+    }
+
+    fn options(&self) -> &SessionOptions {
+        &self.options
+    }
+}
+
+impl DisclosureState for Done {
+    fn usecase_id(&self) -> &str {
+        &self.usecase_id
+    }
+
+    fn callback(&self) -> Option<&SessionCallback> {
+        self.callback.as_ref()
+    }
 
-impl DisclosureState for Created {}
-impl DisclosureState for WaitingForResponse {}
-impl DisclosureState for Done {}
+    fn options(&self) -> &SessionOptions {
+        &self.options
+    }
+}
 
 /// Disclosure-specific session data, of any state, for storing in a session store.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum DisclosureData {
     Created(Created),
-    WaitingForResponse(WaitingForResponse),
+    WaitingForResponse(Box<WaitingForResponse>),
     Done(Done),
 }
 
@@ -287,6 +474,20 @@ impl HasProgress for DisclosureData {
             },
         }
     }
+
+    /// A finished session is pending as long as its callback has not been delivered.
+    fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::Done(Done {
+                callback: Some(SessionCallback {
+                    status: CallbackStatus::Pending | CallbackStatus::Delivering { .. },
+                    ..
+                }),
+                ..
+            })
+        )
+    }
 }
 
 impl Expirable for DisclosureData {
@@ -294,16 +495,60 @@ impl Expirable for DisclosureData {
         matches!(
             self,
             Self::Done(Done {
-                session_result: SessionResult::Expired
+                session_result: SessionResult::Expired,
+                ..
             })
         )
     }
 
     fn expire(&mut self) {
+        // Retain the use case, so that expired sessions can still be attributed to it, as well as the callback and
+        // options, so that the RP is notified of the expiration.
+        let (usecase_id, callback, options) = match self {
+            Self::WaitingForResponse(waiting) => (
+                std::mem::take(&mut waiting.usecase_id),
+                waiting.callback.take(),
+                std::mem::take(&mut waiting.options),
+            ),
+            Self::Created(Created {
+                usecase_id,
+                callback,
+                options,
+                ..
+            })
+            | Self::Done(Done {
+                usecase_id,
+                callback,
+                options,
+                ..
+            }) => (std::mem::take(usecase_id), callback.take(), std::mem::take(options)),
+        };
+
         *self = Self::Done(Done {
+            usecase_id,
             session_result: SessionResult::Expired,
+            callback,
+            options,
         })
     }
+
+    fn expiration(&self) -> Option<Duration> {
+        match self {
+            Self::Created(Created { options, .. }) => options.expiration,
+            Self::WaitingForResponse(waiting) => waiting.options.expiration,
+            Self::Done(_) => None,
+        }
+    }
+}
+
+impl DisclosureData {
+    fn usecase_id(&self) -> &str {
+        match self {
+            Self::Created(created) => created.usecase_id(),
+            Self::WaitingForResponse(waiting) => waiting.usecase_id(),
+            Self::Done(done) => done.usecase_id(),
+        }
+    }
 }
 
 // From/TryFrom converters for the various state structs to the `DisclosureData` enum
@@ -314,6 +559,7 @@ impl From<Session<Created>> for SessionState<DisclosureData> {
             data: DisclosureData::Created(value.state.data),
             token: value.state.token,
             last_active: value.state.last_active,
+            version: value.state.version,
         }
     }
 }
@@ -332,6 +578,7 @@ impl TryFrom<SessionState<DisclosureData>> for Session<Created> {
                 data: session_data,
                 token: value.token,
                 last_active: value.last_active,
+                version: value.version,
             },
         })
     }
@@ -340,9 +587,10 @@ impl TryFrom<SessionState<DisclosureData>> for Session<Created> {
 impl From<Session<WaitingForResponse>> for SessionState<DisclosureData> {
     fn from(value: Session<WaitingForResponse>) -> Self {
         SessionState {
-            data: DisclosureData::WaitingForResponse(value.state.data),
+            data: DisclosureData::WaitingForResponse(Box::new(value.state.data)),
             token: value.state.token,
             last_active: value.state.last_active,
+            version: value.state.version,
         }
     }
 }
@@ -352,7 +600,7 @@ impl TryFrom<SessionState<DisclosureData>> for Session<WaitingForResponse> {
 
     fn try_from(value: SessionState<DisclosureData>) -> Result<Self, Self::Error> {
         let session_data = match value.data {
-            DisclosureData::WaitingForResponse(session_data) => Ok(session_data),
+            DisclosureData::WaitingForResponse(session_data) => Ok(*session_data),
             data => Err(SessionError::UnexpectedState(data.into())),
         }?;
 
@@ -361,6 +609,7 @@ impl TryFrom<SessionState<DisclosureData>> for Session<WaitingForResponse> {
                 data: session_data,
                 token: value.token,
                 last_active: value.last_active,
+                version: value.version,
             },
         })
     }
@@ -372,6 +621,7 @@ impl From<Session<Done>> for SessionState<DisclosureData> {
             data: DisclosureData::Done(value.state.data),
             token: value.state.token,
             last_active: value.state.last_active,
+            version: value.state.version,
         }
     }
 }
@@ -406,12 +656,23 @@ pub enum SessionStatus {
     Expired,
 }
 
+impl From<&SessionResult> for StatusResponse {
+    fn from(value: &SessionResult) -> Self {
+        match value {
+            SessionResult::Done { .. } => Self::Done,
+            SessionResult::Failed { .. } => Self::Failed,
+            SessionResult::Cancelled => Self::Cancelled,
+            SessionResult::Expired => Self::Expired,
+        }
+    }
+}
+
 impl From<DisclosureData> for SessionStatus {
     fn from(value: DisclosureData) -> Self {
         match value {
             DisclosureData::Created(_) => Self::Created,
             DisclosureData::WaitingForResponse(_) => Self::WaitingForResponse,
-            DisclosureData::Done(Done { session_result }) => match session_result {
+            DisclosureData::Done(Done { session_result, .. }) => match session_result {
                 SessionResult::Done { .. } => Self::Done,
                 SessionResult::Failed { error } => Self::Failed { error },
                 SessionResult::Cancelled => Self::Cancelled,
@@ -440,7 +701,7 @@ pub enum SessionTypeReturnUrl {
     Both,
 }
 
-#[nutype(derive(Debug, From, AsRef))]
+#[nutype(derive(Debug, From, AsRef, Deref))]
 pub struct UseCases(HashMap<String, UseCase>);
 
 #[derive(Debug)]
@@ -448,12 +709,14 @@ pub struct UseCase {
     pub key_pair: KeyPair,
     pub client_id: String,
     pub session_type_return_url: SessionTypeReturnUrl,
+    pub query_language: QueryLanguage,
 }
 
 impl UseCase {
     pub fn try_new(
         key_pair: KeyPair,
         session_type_return_url: SessionTypeReturnUrl,
+        query_language: QueryLanguage,
     ) -> Result<Self, UseCaseCertificateError> {
         let client_id = key_pair
             .certificate()
@@ -463,6 +726,7 @@ impl UseCase {
             key_pair,
             client_id,
             session_type_return_url,
+            query_language,
         };
 
         Ok(use_case)
@@ -471,23 +735,40 @@ impl UseCase {
 
 #[derive(Debug)]
 pub struct Verifier<S> {
-    use_cases: UseCases,
+    use_cases: Arc<UseCases>,
     sessions: Arc<S>,
+    callbacks: Arc<CallbackSender<S>>,
     cleanup_task: JoinHandle<()>,
+    count_task: JoinHandle<()>,
+    callback_task: JoinHandle<()>,
     trust_anchors: Vec<OwnedTrustAnchor>,
     ephemeral_id_secret: hmac::Key,
 }
 
 impl<S> Drop for Verifier<S> {
     fn drop(&mut self) {
-        // Stop the task at the next .await
+        // Stop the tasks at the next .await
         self.cleanup_task.abort();
+        self.count_task.abort();
+        self.callback_task.abort();
     }
 }
 
+/// Delivers the session callbacks to the RPs. This is shared between the [`Verifier`] and its background task, which
+/// periodically delivers the callbacks that are still pending. These include the callbacks of sessions that were
+/// expired by the session store and of deliveries that were interrupted by a restart. Before delivering a callback, it
+/// is claimed in the session store, so that it is delivered by only one of the instances sharing that store. Note that
+/// an interrupted delivery is retried, so the RP may still receive the same callback more than once.
+#[derive(Debug)]
+struct CallbackSender<S> {
+    sessions: Arc<S>,
+    use_cases: Arc<UseCases>,
+    http_client: reqwest::Client,
+}
+
 impl<S> Verifier<S>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     /// Create a new [`Verifier`].
     ///
@@ -503,15 +784,26 @@ where
         sessions: S,
         trust_anchors: Vec<OwnedTrustAnchor>,
         ephemeral_id_secret: hmac::Key,
-    ) -> Self
-    where
-        S: Send + Sync + 'static,
-    {
+    ) -> Self {
         let sessions = Arc::new(sessions);
+        let use_cases = Arc::new(use_cases);
+        let callbacks = Arc::new(CallbackSender {
+            sessions: Arc::clone(&sessions),
+            use_cases: Arc::clone(&use_cases),
+            http_client: default_reqwest_client_builder()
+                .build()
+                .expect("Could not build reqwest HTTP client"),
+        });
+
         Self {
             use_cases,
-            cleanup_task: sessions.clone().start_cleanup_task(CLEANUP_INTERVAL_SECONDS),
+            cleanup_task: sessions
+                .clone()
+                .start_cleanup_task(CLEANUP_INTERVAL_SECONDS, METRICS_SESSION_TYPE),
+            count_task: Arc::clone(&sessions).start_count_task(COUNT_INTERVAL_SECONDS, METRICS_SESSION_TYPE),
+            callback_task: Arc::clone(&callbacks).start_redelivery_task(CALLBACK_REDELIVERY_INTERVAL),
             sessions,
+            callbacks,
             trust_anchors,
             ephemeral_id_secret,
         }
@@ -523,11 +815,15 @@ where
     /// - `items_requests` contains the attributes to be requested.
     /// - `usecase_id` should point to an existing item in the `certificates` parameter.
     /// - `return_url_template` is the return URL the user should be returned to, if present.
+    /// - `callback_url` is the URL to which a signed notification is sent when the session is done, if present.
+    /// - `options` contains further options for this particular session, see [`SessionOptions`].
     pub async fn new_session(
         &self,
         items_requests: ItemsRequests,
         usecase_id: String,
         return_url_template: Option<ReturnUrlTemplate>,
+        callback_url: Option<BaseUrl>,
+        options: SessionOptions,
     ) -> Result<SessionToken, NewSessionError> {
         info!("create verifier session: {usecase_id}");
 
@@ -535,7 +831,7 @@ where
             return Err(NewSessionError::NoItemsRequests);
         }
 
-        let use_case = match self.use_cases.as_ref().get(&usecase_id) {
+        let use_case = match self.use_cases.get(&usecase_id) {
             Some(use_case) => use_case,
             None => return Err(NewSessionError::UnknownUseCase(usecase_id)),
         };
@@ -549,18 +845,43 @@ where
             return Err(NewSessionError::ReturnUrlConfigurationMismatch);
         }
 
+        if let Some(expiration) = options
+            .expiration
+            .filter(|expiration| *expiration > MAX_SESSION_EXPIRATION)
+        {
+            return Err(NewSessionError::ExpirationTooLong(expiration));
+        }
+
+        if options.correlation_id.as_ref().is_some_and(|correlation_id| {
+            correlation_id.is_empty()
+                || correlation_id.len() > MAX_CORRELATION_ID_LENGTH
+                || !correlation_id.bytes().all(|byte| byte.is_ascii_graphic())
+        }) {
+            return Err(NewSessionError::InvalidCorrelationId);
+        }
+
+        let callback = callback_url.map(|url| SessionCallback {
+            url,
+            usecase_id: usecase_id.clone(),
+            status: CallbackStatus::Pending,
+        });
         let session_state = Session::<Created>::new(
             items_requests,
             usecase_id,
             use_case.client_id.clone(),
             return_url_template,
+            callback,
+            options,
         );
         let session_token = session_state.state.token.clone();
+        let session_state = SessionState::from(session_state);
+        let labels = SessionStateLabels::new(&session_state.data);
 
         self.sessions
-            .write(session_state.into(), true)
+            .write(session_state, true)
             .await
             .map_err(SessionError::SessionStore)?;
+        labels.record(METRICS_SESSION_TYPE);
 
         info!("Session({session_token}): session created");
         Ok(session_token)
@@ -647,10 +968,10 @@ where
             }
         };
 
-        self.sessions
-            .write(next, false)
-            .await
-            .map_err(|err| WithRedirectUri::new(SessionError::SessionStore(err).into(), redirect_uri))?;
+        if let Err(err) = self.write_session(next).await {
+            let error = self.session_write_error(session_token, err).await;
+            return Err(WithRedirectUri::new(error.into(), redirect_uri));
+        }
 
         result
     }
@@ -673,15 +994,14 @@ where
                 .as_slice(),
         );
 
-        self.sessions.write(next.into(), false).await.map_err(|err| {
-            WithRedirectUri::new(
-                SessionError::SessionStore(err).into(),
-                match &result {
-                    Ok(response) => response.redirect_uri.clone(),
-                    Err(err) => err.redirect_uri.clone(),
-                },
-            )
-        })?;
+        if let Err(err) = self.write_session(next.into()).await {
+            let error = self.session_write_error(session_token, err).await;
+            let redirect_uri = match &result {
+                Ok(response) => response.redirect_uri.clone(),
+                Err(err) => err.redirect_uri.clone(),
+            };
+            return Err(WithRedirectUri::new(error.into(), redirect_uri));
+        }
 
         result
     }
@@ -713,52 +1033,114 @@ where
                 StatusResponse::Created { ul }
             }
             DisclosureData::WaitingForResponse(_) => StatusResponse::WaitingForResponse,
-            DisclosureData::Done(Done {
-                session_result: SessionResult::Done { .. },
-            }) => StatusResponse::Done,
-            DisclosureData::Done(Done {
-                session_result: SessionResult::Failed { .. },
-            }) => StatusResponse::Failed,
-            DisclosureData::Done(Done {
-                session_result: SessionResult::Cancelled { .. },
-            }) => StatusResponse::Cancelled,
-            DisclosureData::Done(Done {
-                session_result: SessionResult::Expired { .. },
-            }) => StatusResponse::Expired,
+            DisclosureData::Done(Done { session_result, .. }) => StatusResponse::from(&session_result),
         };
 
         Ok(response)
     }
 
     pub async fn cancel(&self, session_token: &SessionToken) -> Result<(), CancelSessionError> {
-        let SessionState { data, token, .. } = self.get_session_state(session_token).await?;
+        // Retry when the session was updated concurrently, so that the cancellation is applied to the latest state.
+        loop {
+            let session_state = self.get_session_state(session_token).await?;
+
+            // Create a new `SessionState<DisclosureData>` if the session
+            // is in the `CREATED` or `WAITING_FOR_RESPONSE` state.
+            let (callback, options) = match &session_state.data {
+                DisclosureData::Created(created) => (created.callback().cloned(), created.options().clone()),
+                DisclosureData::WaitingForResponse(waiting) => (waiting.callback().cloned(), waiting.options().clone()),
+                DisclosureData::Done(_) => return Err(SessionError::UnexpectedState(session_state.data.into()).into()),
+            };
+            let usecase_id = session_state.data.usecase_id().to_string();
+            let cancelled_session_state = session_state.transition(DisclosureData::Done(Done {
+                usecase_id,
+                session_result: SessionResult::Cancelled,
+                callback,
+                options,
+            }));
+
+            match self.write_session(cancelled_session_state).await {
+                Ok(()) => return Ok(()),
+                Err(SessionStoreError::Conflict(_)) => {
+                    info!("Session({session_token}): session updated concurrently, retrying cancel");
+                }
+                Err(err) => return Err(SessionError::SessionStore(err).into()),
+            }
+        }
+    }
 
-        // Create a new `SessionState<DisclosureData>` if the session
-        // is in the `CREATED` or `WAITING_FOR_RESPONSE` state.
-        let cancelled_session_state = match data {
-            DisclosureData::Created(_) | DisclosureData::WaitingForResponse(_) => SessionState::new(
-                token,
-                DisclosureData::Done(Done {
-                    session_result: SessionResult::Cancelled,
-                }),
-            ),
-            DisclosureData::Done(_) => return Err(SessionError::UnexpectedState(data.into()).into()),
-        };
+    /// Subscribe to updates of the disclosure sessions, which yields the token of every session that changes.
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionToken> {
+        self.sessions.subscribe()
+    }
 
-        self.sessions
-            .write(cancelled_session_state, false)
-            .await
-            .map_err(SessionError::SessionStore)?;
+    /// List the sessions in the session store, optionally only those with the specified status and/or use case.
+    pub async fn sessions(
+        &self,
+        status: Option<ProgressStatus>,
+        usecase_id: Option<&str>,
+    ) -> Result<Vec<SessionSummary>, SessionError> {
+        let sessions = self
+            .sessions
+            .list(status)
+            .await?
+            .into_iter()
+            .filter(|session| usecase_id.map_or(true, |usecase_id| session.data.usecase_id() == usecase_id))
+            .map(SessionSummary::from)
+            .collect();
 
-        Ok(())
+        Ok(sessions)
+    }
+
+    /// Count the sessions in the session store per status.
+    pub async fn session_counts(&self) -> Result<SessionCounts, SessionError> {
+        let counts = self.sessions.count().await?;
+
+        Ok(counts)
+    }
+
+    /// Expire an active session on demand, regardless of its expiration timeout.
+    pub async fn expire(&self, session_token: &SessionToken) -> Result<(), SessionError> {
+        // Retry when the session was updated concurrently, so that the expiration is applied to the latest state.
+        loop {
+            let session_state = self.get_session_state(session_token).await?;
+
+            if !matches!(session_state.data.progress(), Progress::Active) {
+                return Err(SessionError::UnexpectedState(session_state.data.into()));
+            }
+
+            let mut data = session_state.data.clone();
+            data.expire();
+
+            match self.write_session(session_state.transition(data)).await {
+                Ok(()) => {
+                    info!("Session({session_token}): session expired by operator");
+                    return Ok(());
+                }
+                Err(SessionStoreError::Conflict(_)) => {
+                    info!("Session({session_token}): session updated concurrently, retrying expire");
+                }
+                Err(err) => return Err(SessionError::SessionStore(err)),
+            }
+        }
+    }
+
+    /// Remove all sessions that have finished from the session store, except those of which the callback has not been
+    /// delivered yet. Returns the number of removed sessions.
+    pub async fn purge_finished(&self) -> Result<u64, SessionError> {
+        let count = self.sessions.purge_finished().await?;
+        info!("purged {count} finished sessions");
+
+        Ok(count)
     }
 
-    /// Returns the disclosed attributes for a session with status `Done` and an error otherwise
+    /// Returns the disclosed attributes for a session with status `Done` and an error otherwise. These are returned
+    /// along with the correlation id the RP may have provided when starting the session.
     pub async fn disclosed_attributes(
         &self,
         session_token: &SessionToken,
         redirect_uri_nonce: Option<String>,
-    ) -> Result<DisclosedAttributes, DisclosedAttributesError> {
+    ) -> Result<(DisclosedAttributes, Option<String>), DisclosedAttributesError> {
         let disclosure_data = self.get_session_state(session_token).await?.data;
 
         match disclosure_data {
@@ -768,15 +1150,245 @@ where
                         redirect_uri_nonce: expected_nonce,
                         disclosed_attributes,
                     },
-            }) => match (redirect_uri_nonce, expected_nonce) {
-                (_, None) => Ok(disclosed_attributes),
-                (None, Some(_)) => Err(DisclosedAttributesError::RedirectUriNonceMissing),
-                (Some(received), Some(expected)) if received == expected => Ok(disclosed_attributes),
-                (Some(received), Some(_)) => Err(DisclosedAttributesError::RedirectUriNonceMismatch(received)),
-            },
+                options,
+                ..
+            }) => {
+                match (redirect_uri_nonce, expected_nonce) {
+                    (_, None) => {}
+                    (None, Some(_)) => return Err(DisclosedAttributesError::RedirectUriNonceMissing),
+                    (Some(received), Some(expected)) if received == expected => {}
+                    (Some(received), Some(_)) => {
+                        return Err(DisclosedAttributesError::RedirectUriNonceMismatch(received))
+                    }
+                };
+
+                Ok((disclosed_attributes, options.correlation_id))
+            }
             data => Err(SessionError::UnexpectedState(data.into()))?,
         }
     }
+
+    /// Convert an error that occurred while writing a session into a [`SessionError`]. If the session was updated
+    /// concurrently, the state it was changed into is reported, as the session can no longer proceed from the state
+    /// that was read.
+    async fn session_write_error(&self, session_token: &SessionToken, error: SessionStoreError) -> SessionError {
+        match error {
+            SessionStoreError::Conflict(_) => match self.get_session_state(session_token).await {
+                Ok(current) => SessionError::UnexpectedState(current.data.into()),
+                Err(error) => error,
+            },
+            error => SessionError::SessionStore(error),
+        }
+    }
+
+    /// Write an existing session to the session store. If the session is done and the RP provided a callback URL,
+    /// the RP is subsequently notified of the outcome in the background.
+    async fn write_session(&self, session: SessionState<DisclosureData>) -> Result<(), SessionStoreError> {
+        let is_pending = session.data.is_pending();
+        let session_token = session.token.clone();
+        let labels = SessionStateLabels::new(&session.data);
+
+        self.sessions.write(session, false).await?;
+        labels.record(METRICS_SESSION_TYPE);
+
+        if is_pending {
+            self.callbacks.send(session_token).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S> CallbackSender<S>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    /// Periodically deliver the callbacks of finished sessions that are still pending, starting immediately.
+    fn start_redelivery_task(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let mut interval = time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+
+                if let Err(error) = self.send_pending().await {
+                    warn!("error while delivering pending callbacks: {error}");
+                }
+            }
+        })
+    }
+
+    /// Deliver the callbacks of all finished sessions of which the callback has not been delivered yet.
+    async fn send_pending(self: &Arc<Self>) -> Result<(), SessionStoreError> {
+        let now = Utc::now();
+        let pending = self
+            .sessions
+            .list_pending()
+            .await?
+            .into_iter()
+            .filter_map(|session| match session.data {
+                DisclosureData::Done(Done {
+                    callback: Some(callback),
+                    ..
+                }) if callback.status.is_claimable(now) => Some(session.token),
+                _ => None,
+            });
+
+        for session_token in pending {
+            self.send(session_token).await;
+        }
+
+        Ok(())
+    }
+
+    /// Claim the delivery of the callback of a finished session, sign the callback notification and spawn a task that
+    /// delivers it to the RP. Nothing is sent if the callback has already been claimed, by this or another instance.
+    async fn send(self: &Arc<Self>, session_token: SessionToken) {
+        let (callback, status) = match self.claim(&session_token).await {
+            Ok(Some(claimed)) => claimed,
+            Ok(None) => return,
+            Err(error) => {
+                warn!("Session({session_token}): claiming callback failed: {error}");
+                return;
+            }
+        };
+
+        let Some(use_case) = self.use_cases.get(&callback.usecase_id) else {
+            // This should not happen except when the configuration has changed during this session.
+            warn!(
+                "configuration inconsistency: existing session referenced nonexisting usecase '{}'",
+                callback.usecase_id
+            );
+            self.record_status(&session_token, CallbackStatus::Failed { attempts: 0 })
+                .await;
+            return;
+        };
+
+        let claims = SessionCallbackClaims {
+            session_token: session_token.clone(),
+            status,
+            iat: Utc::now().timestamp(),
+        };
+        let jwt = match jwt::sign_with_certificate(&claims, &use_case.key_pair).await {
+            Ok(jwt) => jwt,
+            Err(error) => {
+                // Release the claim, so that delivery is attempted again later.
+                warn!("Session({session_token}): signing callback failed: {error}");
+                self.record_status(&session_token, CallbackStatus::Pending).await;
+                return;
+            }
+        };
+
+        tokio::spawn(Arc::clone(self).deliver(session_token, callback.url, jwt));
+    }
+
+    /// Claim the delivery of the callback of a finished session by marking it as being delivered, if it should still
+    /// be delivered. As the session is updated using a versioned write, only one of the instances sharing the session
+    /// store succeeds in claiming the callback. Returns the claimed callback along with the status of the session.
+    async fn claim(
+        &self,
+        session_token: &SessionToken,
+    ) -> Result<Option<(SessionCallback, StatusResponse)>, SessionStoreError> {
+        loop {
+            let Some(mut session) = self.sessions.get(session_token).await? else {
+                return Ok(None);
+            };
+
+            let DisclosureData::Done(Done {
+                session_result,
+                callback: Some(callback),
+                ..
+            }) = &mut session.data
+            else {
+                return Ok(None);
+            };
+
+            let now = Utc::now();
+            if !callback.status.is_claimable(now) {
+                return Ok(None);
+            }
+
+            callback.status = CallbackStatus::Delivering {
+                until: saturating_add(now, CALLBACK_CLAIM_DURATION),
+            };
+            let claimed = (callback.clone(), StatusResponse::from(&*session_result));
+
+            match self.sessions.write(session, false).await {
+                Ok(()) => return Ok(Some(claimed)),
+                Err(SessionStoreError::Conflict(_)) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Deliver the callback notification to the RP, retrying with exponential backoff when this fails.
+    /// The eventual outcome is recorded in the session store.
+    async fn deliver(self: Arc<Self>, session_token: SessionToken, url: BaseUrl, jwt: Jwt<SessionCallbackClaims>) {
+        let mut backoff = CALLBACK_INITIAL_BACKOFF;
+        let mut attempts = 0;
+
+        let status = loop {
+            attempts += 1;
+
+            let result = self
+                .http_client
+                .post(url.clone().into_inner())
+                .header(CONTENT_TYPE, APPLICATION_JWT.as_ref())
+                .body(jwt.0.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match result {
+                Ok(_) => {
+                    info!("Session({session_token}): callback delivered after {attempts} attempt(s)");
+                    break CallbackStatus::Delivered { attempts };
+                }
+                Err(error) if attempts < CALLBACK_MAX_ATTEMPTS => {
+                    warn!("Session({session_token}): delivering callback failed, retrying: {error}");
+                    time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(error) => {
+                    warn!("Session({session_token}): delivering callback failed, giving up: {error}");
+                    break CallbackStatus::Failed { attempts };
+                }
+            }
+        };
+
+        self.record_status(&session_token, status).await;
+    }
+
+    /// Record the delivery status of the callback of a finished session in the session store.
+    async fn record_status(&self, session_token: &SessionToken, status: CallbackStatus) {
+        let result = async {
+            loop {
+                let Some(mut session) = self.sessions.get(session_token).await? else {
+                    return Ok(());
+                };
+
+                let DisclosureData::Done(Done {
+                    callback: Some(callback),
+                    ..
+                }) = &mut session.data
+                else {
+                    return Ok(());
+                };
+
+                callback.status = status;
+                match self.sessions.write(session, false).await {
+                    Err(SessionStoreError::Conflict(_)) => continue,
+                    result => return result,
+                }
+            }
+        }
+        .await;
+
+        if let Err(error) = result {
+            warn!("Session({session_token}): recording callback status failed: {error}");
+        }
+    }
 }
 
 impl<S> Verifier<S> {
@@ -846,17 +1458,28 @@ pub struct VerifierUrlParameters {
 // Transitioning functions and helpers valid for any state
 impl<T: DisclosureState> Session<T> {
     fn transition_fail(self, error: &impl ToString) -> Session<Done> {
+        self.transition_done(SessionResult::Failed {
+            error: error.to_string(),
+        })
+    }
+
+    /// Transition `self` to the `Done` state, retaining the use case, callback and options of the session.
+    fn transition_done(self, session_result: SessionResult) -> Session<Done> {
+        let usecase_id = self.state().usecase_id().to_string();
+        let callback = self.state().callback().cloned();
+        let options = self.state().options().clone();
         self.transition(Done {
-            session_result: SessionResult::Failed {
-                error: error.to_string(),
-            },
+            usecase_id,
+            session_result,
+            callback,
+            options,
         })
     }
 
     /// Transition `self` to a new state, consuming the old state, also updating the `last_active` timestamp.
     fn transition<NewT: DisclosureState>(self, new_state: NewT) -> Session<NewT> {
         Session {
-            state: SessionState::new(self.state.token, new_state),
+            state: self.state.transition(new_state),
         }
     }
 
@@ -872,6 +1495,8 @@ impl Session<Created> {
         usecase_id: String,
         client_id: String,
         return_url_template: Option<ReturnUrlTemplate>,
+        callback: Option<SessionCallback>,
+        options: SessionOptions,
     ) -> Session<Created> {
         Session::<Created> {
             state: SessionState::new(
@@ -881,6 +1506,8 @@ impl Session<Created> {
                     usecase_id,
                     client_id,
                     redirect_uri_template: return_url_template,
+                    callback,
+                    options,
                 },
             ),
         }
@@ -907,9 +1534,12 @@ impl Session<Created> {
         {
             Ok((jws, auth_request, redirect_uri, enc_keypair)) => {
                 let next = WaitingForResponse {
+                    usecase_id: self.state().usecase_id.clone(),
                     auth_request,
                     encryption_key: EncryptionPrivateKey::from(enc_keypair),
                     redirect_uri,
+                    callback: self.state().callback.clone(),
+                    options: self.state().options.clone(),
                 };
                 let next = self.transition(next);
                 Ok((jws, next))
@@ -966,6 +1596,7 @@ impl Session<Created> {
             .map_err(|err| WithRedirectUri::new(err.into(), uri_from_option(&redirect_uri)))?;
         let auth_request = IsoVpAuthorizationRequest::new(
             &self.state.data.items_requests,
+            usecase.query_language,
             usecase.key_pair.certificate(),
             nonce.clone(),
             encryption_keypair.to_jwk_public_key().try_into().unwrap(), // safe because we just constructed this key
@@ -1091,18 +1722,14 @@ impl Session<WaitingForResponse> {
     }
 
     fn transition_finish(self, disclosed_attributes: DisclosedAttributes, nonce: Option<String>) -> Session<Done> {
-        self.transition(Done {
-            session_result: SessionResult::Done {
-                disclosed_attributes,
-                redirect_uri_nonce: nonce,
-            },
+        self.transition_done(SessionResult::Done {
+            disclosed_attributes,
+            redirect_uri_nonce: nonce,
         })
     }
 
     fn transition_abort(self) -> Session<Done> {
-        self.transition(Done {
-            session_result: SessionResult::Cancelled,
-        })
+        self.transition_done(SessionResult::Cancelled)
     }
 }
 
@@ -1115,19 +1742,29 @@ mod tests {
     use ring::{hmac, rand};
     use rstest::rstest;
 
+    use tokio::time;
+    use wiremock::{
+        matchers::{header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
     use nl_wallet_mdoc::{server_keys::KeyPair, utils::reader_auth::ReaderRegistration, ItemsRequest};
     use wallet_common::{
         generator::{Generator, TimeGenerator},
+        jwt::{validations, EcdsaDecodingKey, Jwt},
         trust_anchor::DerTrustAnchor,
+        urls::BaseUrl,
     };
 
     use crate::server_state::{MemorySessionStore, SessionToken};
 
     use super::{
-        AuthorizationErrorCode, DisclosedAttributesError, DisclosureData, Done, ErrorResponse, GetAuthRequestError,
-        HashMap, ItemsRequests, NewSessionError, SessionError, SessionResult, SessionState, SessionStatus,
-        SessionStore, SessionType, SessionTypeReturnUrl, StatusResponse, UseCase, Verifier, VpAuthorizationErrorCode,
-        VpRequestUriObject, WalletAuthResponse, EPHEMERAL_ID_VALIDITY_SECONDS,
+        AuthorizationErrorCode, CallbackStatus, DisclosedAttributesError, DisclosureData, Done, ErrorResponse,
+        Expirable, GetAuthRequestError, HashMap, ItemsRequests, NewSessionError, QueryLanguage, SessionCallback,
+        SessionCallbackClaims, SessionError, SessionOptions, SessionResult, SessionState, SessionStatus, SessionStore,
+        SessionType, SessionTypeReturnUrl, StatusResponse, UseCase, Verifier, VpAuthorizationErrorCode,
+        VpRequestUriObject, WalletAuthResponse, APPLICATION_JWT, EPHEMERAL_ID_VALIDITY_SECONDS,
+        MAX_CORRELATION_ID_LENGTH,
     };
 
     const DISCLOSURE_DOC_TYPE: &str = "example_doctype";
@@ -1171,6 +1808,7 @@ mod tests {
                     key_pair: ca.generate_reader_mock(reader_registration.clone()).unwrap(),
                     session_type_return_url: SessionTypeReturnUrl::Neither,
                     client_id: "client_id".to_string(),
+                    query_language: QueryLanguage::PresentationExchange,
                 },
             ),
             (
@@ -1179,6 +1817,7 @@ mod tests {
                     key_pair: ca.generate_reader_mock(reader_registration.clone()).unwrap(),
                     session_type_return_url: SessionTypeReturnUrl::SameDevice,
                     client_id: "client_id".to_string(),
+                    query_language: QueryLanguage::PresentationExchange,
                 },
             ),
             (
@@ -1187,6 +1826,7 @@ mod tests {
                     key_pair: ca.generate_reader_mock(reader_registration).unwrap(),
                     session_type_return_url: SessionTypeReturnUrl::Both,
                     client_id: "client_id".to_string(),
+                    query_language: QueryLanguage::PresentationExchange,
                 },
             ),
         ])
@@ -1219,7 +1859,13 @@ mod tests {
         let return_url_template = has_return_url.then(|| "https://example.com/{session_token}".parse().unwrap());
 
         let result = verifier
-            .new_session(new_disclosure_request(), usecase_id.to_string(), return_url_template)
+            .new_session(
+                new_disclosure_request(),
+                usecase_id.to_string(),
+                return_url_template,
+                None,
+                Default::default(),
+            )
             .await;
 
         if should_succeed {
@@ -1230,6 +1876,53 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_verifier_new_session_expiration_too_long() {
+        let verifier = create_verifier();
+
+        let error = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                None,
+                SessionOptions {
+                    expiration: Some(std::time::Duration::from_secs(u64::MAX)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect_err("creating a new session should not succeed");
+
+        assert_matches!(error, NewSessionError::ExpirationTooLong(_));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("correlation id")]
+    #[case("correlation-id\n")]
+    #[case(&"a".repeat(MAX_CORRELATION_ID_LENGTH + 1))]
+    #[tokio::test]
+    async fn test_verifier_new_session_invalid_correlation_id(#[case] correlation_id: &str) {
+        let verifier = create_verifier();
+
+        let error = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                None,
+                SessionOptions {
+                    correlation_id: Some(correlation_id.to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect_err("creating a new session should not succeed");
+
+        assert_matches!(error, NewSessionError::InvalidCorrelationId);
+    }
+
     async fn init_and_start_disclosure(
         time: &impl Generator<DateTime<Utc>>,
     ) -> (
@@ -1245,6 +1938,8 @@ mod tests {
                 new_disclosure_request(),
                 DISCLOSURE_USECASE.to_string(),
                 Some("https://example.com/{session_token}".parse().unwrap()),
+                None,
+                Default::default(),
             )
             .await
             .unwrap();
@@ -1388,6 +2083,274 @@ mod tests {
         ));
     }
 
+    /// Wait for the callback of a session to be delivered in the background and recorded in the session store.
+    async fn wait_for_callback(
+        verifier: &Verifier<MemorySessionStore<DisclosureData>>,
+        session_token: &SessionToken,
+    ) -> SessionCallback {
+        time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                if let DisclosureData::Done(Done {
+                    callback: Some(callback),
+                    ..
+                }) = verifier.sessions.get(session_token).await.unwrap().unwrap().data
+                {
+                    if matches!(
+                        callback.status,
+                        CallbackStatus::Delivered { .. } | CallbackStatus::Failed { .. }
+                    ) {
+                        return callback;
+                    }
+                }
+                time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("callback should be delivered")
+    }
+
+    #[tokio::test]
+    async fn test_verifier_cancel_callback() {
+        let verifier = create_verifier();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("content-type", APPLICATION_JWT.as_ref()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session_token = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                Some(server.uri().parse().unwrap()),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        verifier.cancel(&session_token).await.unwrap();
+
+        let callback = wait_for_callback(&verifier, &session_token).await;
+
+        assert_eq!(callback.status, CallbackStatus::Delivered { attempts: 1 });
+
+        // The RP should be able to verify the callback using the certificate of the use case.
+        let request = server.received_requests().await.unwrap().pop().unwrap();
+        let jwt: Jwt<SessionCallbackClaims> = String::from_utf8(request.body).unwrap().into();
+        let public_key = verifier.use_cases[DISCLOSURE_USECASE_NO_REDIRECT_URI]
+            .key_pair
+            .certificate()
+            .public_key()
+            .unwrap();
+        let claims = jwt
+            .parse_and_verify(&EcdsaDecodingKey::from(&public_key), &validations())
+            .expect("callback JWT should verify");
+
+        assert_eq!(claims.session_token, session_token);
+        assert_matches!(claims.status, StatusResponse::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_verifier_expired_callback() {
+        let verifier = create_verifier();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session_token = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                Some(server.uri().parse().unwrap()),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        // Expire the session directly in the session store, as its cleanup would, which does not deliver the callback.
+        let session = verifier.sessions.get(&session_token).await.unwrap().unwrap();
+        let mut data = session.data.clone();
+        data.expire();
+        verifier.sessions.write(session.transition(data), false).await.unwrap();
+
+        // The callback should be retained and delivered as pending callback.
+        verifier.callbacks.send_pending().await.unwrap();
+        let callback = wait_for_callback(&verifier, &session_token).await;
+
+        assert_eq!(callback.status, CallbackStatus::Delivered { attempts: 1 });
+
+        let request = server.received_requests().await.unwrap().pop().unwrap();
+        let jwt: Jwt<SessionCallbackClaims> = String::from_utf8(request.body).unwrap().into();
+        let public_key = verifier.use_cases[DISCLOSURE_USECASE_NO_REDIRECT_URI]
+            .key_pair
+            .certificate()
+            .public_key()
+            .unwrap();
+        let claims = jwt
+            .parse_and_verify(&EcdsaDecodingKey::from(&public_key), &validations())
+            .expect("callback JWT should verify");
+
+        assert_eq!(claims.session_token, session_token);
+        assert_matches!(claims.status, StatusResponse::Expired);
+
+        // Delivered callbacks should not be delivered again.
+        verifier.callbacks.send_pending().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verifier_expire_callback() {
+        let verifier = create_verifier();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session_token = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                Some(server.uri().parse().unwrap()),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        // Expiring the session on demand should deliver the callback immediately.
+        verifier.expire(&session_token).await.unwrap();
+        let callback = wait_for_callback(&verifier, &session_token).await;
+
+        assert_eq!(callback.status, CallbackStatus::Delivered { attempts: 1 });
+    }
+
+    /// Cancel a new session with a callback, without delivering the callback, and set the status of the callback.
+    async fn cancelled_session_with_callback(
+        verifier: &Verifier<MemorySessionStore<DisclosureData>>,
+        callback_url: BaseUrl,
+        usecase_id: &str,
+        status: CallbackStatus,
+    ) -> SessionToken {
+        let session_token = verifier
+            .new_session(
+                new_disclosure_request(),
+                DISCLOSURE_USECASE_NO_REDIRECT_URI.to_string(),
+                None,
+                Some(callback_url),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        let session = verifier.sessions.get(&session_token).await.unwrap().unwrap();
+        let DisclosureData::Created(created) = session.data.clone() else {
+            panic!("session should be created");
+        };
+        let mut callback = created.callback.unwrap();
+        callback.usecase_id = usecase_id.to_string();
+        callback.status = status;
+        let data = DisclosureData::Done(Done {
+            usecase_id: created.usecase_id,
+            session_result: SessionResult::Cancelled,
+            callback: Some(callback),
+            options: created.options,
+        });
+        verifier.sessions.write(session.transition(data), false).await.unwrap();
+
+        session_token
+    }
+
+    #[tokio::test]
+    async fn test_verifier_callback_claim() {
+        let verifier = create_verifier();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let callback_url: BaseUrl = server.uri().parse().unwrap();
+
+        // A callback that is being delivered by another instance should not be delivered again.
+        let claimed_token = cancelled_session_with_callback(
+            &verifier,
+            callback_url.clone(),
+            DISCLOSURE_USECASE_NO_REDIRECT_URI,
+            CallbackStatus::Delivering {
+                until: Utc::now() + chrono::Duration::minutes(1),
+            },
+        )
+        .await;
+
+        // Unless that instance did not finish delivering it in time.
+        let lapsed_token = cancelled_session_with_callback(
+            &verifier,
+            callback_url,
+            DISCLOSURE_USECASE_NO_REDIRECT_URI,
+            CallbackStatus::Delivering {
+                until: Utc::now() - chrono::Duration::minutes(1),
+            },
+        )
+        .await;
+
+        verifier.callbacks.send_pending().await.unwrap();
+        let callback = wait_for_callback(&verifier, &lapsed_token).await;
+
+        assert_eq!(callback.status, CallbackStatus::Delivered { attempts: 1 });
+
+        let session = verifier.sessions.get(&claimed_token).await.unwrap().unwrap();
+        assert_matches!(
+            session.data,
+            DisclosureData::Done(Done {
+                callback: Some(SessionCallback {
+                    status: CallbackStatus::Delivering { .. },
+                    ..
+                }),
+                ..
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verifier_callback_unknown_usecase() {
+        let verifier = create_verifier();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        // A callback for a use case that no longer exists cannot be signed, so it should fail instead of remaining
+        // pending.
+        let session_token = cancelled_session_with_callback(
+            &verifier,
+            server.uri().parse().unwrap(),
+            "nonexisting_usecase",
+            CallbackStatus::Pending,
+        )
+        .await;
+
+        verifier.callbacks.send_pending().await.unwrap();
+        let callback = wait_for_callback(&verifier, &session_token).await;
+
+        assert_eq!(callback.status, CallbackStatus::Failed { attempts: 0 });
+        assert!(verifier.sessions.list_pending().await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_verifier_disclosed_attributes() {
         let verifier = create_verifier();
@@ -1399,25 +2362,37 @@ mod tests {
         let session1 = SessionState::new(
             "token1".into(),
             DisclosureData::Done(Done {
+                usecase_id: DISCLOSURE_USECASE.to_string(),
                 session_result: SessionResult::Done {
                     disclosed_attributes: Default::default(),
                     redirect_uri_nonce: None,
                 },
+                callback: None,
+                options: Default::default(),
             }),
         );
         let session2 = SessionState::new(
             "token2".into(),
             DisclosureData::Done(Done {
+                usecase_id: DISCLOSURE_USECASE.to_string(),
                 session_result: SessionResult::Done {
                     disclosed_attributes: Default::default(),
                     redirect_uri_nonce: "this-is-the-nonce".to_string().into(),
                 },
+                callback: None,
+                options: SessionOptions {
+                    correlation_id: Some("correlation-id".to_string()),
+                    ..Default::default()
+                },
             }),
         );
         let session3 = SessionState::new(
             "token3".into(),
             DisclosureData::Done(Done {
+                usecase_id: DISCLOSURE_USECASE.to_string(),
                 session_result: SessionResult::Expired,
+                callback: None,
+                options: Default::default(),
             }),
         );
 
@@ -1427,24 +2402,28 @@ mod tests {
 
         // The finished session without a return URL should return the
         // attributes, regardless of the return URL nonce provided.
-        assert!(verifier
+        let (disclosed_attributes, correlation_id) = verifier
             .disclosed_attributes(&"token1".into(), None)
             .await
-            .expect("should return disclosed attributes")
-            .is_empty());
+            .expect("should return disclosed attributes");
+        assert!(disclosed_attributes.is_empty());
+        assert!(correlation_id.is_none());
         assert!(verifier
             .disclosed_attributes(&"token1".into(), "nonsense".to_string().into())
             .await
             .expect("should return disclosed attributes")
+            .0
             .is_empty());
 
         // The finished session with a return URL should only return the
         // disclosed attributes when given the correct return URL nonce.
-        assert!(verifier
+        // The correlation id provided when starting the session should be included.
+        let (disclosed_attributes, correlation_id) = verifier
             .disclosed_attributes(&"token2".into(), "this-is-the-nonce".to_string().into())
             .await
-            .expect("should return disclosed attributes")
-            .is_empty());
+            .expect("should return disclosed attributes");
+        assert!(disclosed_attributes.is_empty());
+        assert_eq!(correlation_id.as_deref(), Some("correlation-id"));
         assert_matches!(
             verifier
                 .disclosed_attributes(&"token2".into(), "incorrect".to_string().into())
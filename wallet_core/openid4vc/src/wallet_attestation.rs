@@ -0,0 +1,329 @@
+//! Implements wallet attestations as in
+//! <https://datatracker.ietf.org/doc/draft-ietf-oauth-attestation-based-client-auth/>.
+//!
+//! A wallet attestation allows an issuer to establish that a wallet is a genuine instance of the wallet app, that is
+//! registered at the Wallet Provider and that has not been blocked by it. The Wallet Provider issues a short-lived
+//! [`WalletAttestation`] JWT to the wallet, that is bound to the hardware key of the wallet. The wallet includes the
+//! attestation in its token and credential requests in the `OAuth-Client-Attestation` HTTP header, along with a
+//! [`WalletAttestationPop`] JWT in the `OAuth-Client-Attestation-PoP` HTTP header. The latter is signed by the hardware
+//! key, and has the issuer as its audience, so that the issuer can check that the attestation is presented by the
+//! wallet to which it was issued.
+//!
+//! The PoP contains a challenge provided by the issuer, which binds it to a single issuance session. If the wallet
+//! sends a PoP without the current challenge of the session, the issuer responds with a `use_attestation_challenge`
+//! error, along with a new challenge in the `OAuth-Client-Attestation-Challenge` HTTP header. The issuer also binds
+//! the session to the wallet instance of the attestation, and requires the keys to which it binds the issued
+//! attestations to be attested by the Wallet Provider in a [`KeyAttestation`].
+
+use std::collections::HashSet;
+
+use error_category::ErrorCategory;
+use p256::ecdsa::VerifyingKey;
+use wallet_common::{
+    account::messages::auth::{
+        KeyAttestation, KeyAttestationClaims, WalletAttestation, WalletAttestationClaims, WalletAttestationPop,
+        WalletAttestationPopClaims,
+    },
+    jwt::{validations, EcdsaDecodingKey, JwtError, JwtSubject},
+    keys::SecureEcdsaKey,
+    urls::BaseUrl,
+};
+
+pub const CLIENT_ATTESTATION_HEADER_NAME: &str = "OAuth-Client-Attestation";
+pub const CLIENT_ATTESTATION_POP_HEADER_NAME: &str = "OAuth-Client-Attestation-PoP";
+pub const CLIENT_ATTESTATION_CHALLENGE_HEADER_NAME: &str = "OAuth-Client-Attestation-Challenge";
+
+/// The wallet uses the same PoP for the token request and the credential requests of an issuance session, so it has to
+/// remain valid while the user reviews the attestations to be issued. As it contains the challenge of that session,
+/// it cannot be used in other sessions.
+pub const WALLET_ATTESTATION_POP_VALIDITY_SECS: u64 = 30 * 60;
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(critical)]
+pub enum WalletAttestationError {
+    #[error("missing wallet attestation")]
+    Missing,
+    #[error("invalid wallet attestation: {0}")]
+    #[category(pd)]
+    Attestation(#[source] JwtError),
+    #[error("invalid wallet attestation PoP: {0}")]
+    #[category(pd)]
+    Pop(#[source] JwtError),
+    #[error("unknown client ID in wallet attestation PoP: {0}")]
+    #[category(pd)]
+    UnknownClientId(String),
+    #[error("missing or incorrect challenge in wallet attestation PoP")]
+    Challenge,
+    #[error("wallet attestation is for another wallet than the one that started the session")]
+    WalletMismatch,
+    #[error("missing key attestation")]
+    MissingKeyAttestation,
+    #[error("invalid key attestation: {0}")]
+    #[category(pd)]
+    KeyAttestation(#[source] JwtError),
+    #[error("key is not attested by the Wallet Provider")]
+    UnattestedKey,
+}
+
+/// A [`WalletAttestation`] along with the proof of possession of the hardware key to which it is bound.
+#[derive(Debug, Clone)]
+pub struct WalletAttestationWithPop {
+    pub attestation: WalletAttestation,
+    pub pop: WalletAttestationPop,
+}
+
+impl WalletAttestationWithPop {
+    pub async fn new(
+        attestation: WalletAttestation,
+        client_id: &str,
+        audience: &BaseUrl,
+        challenge: Option<String>,
+        hw_privkey: &impl SecureEcdsaKey,
+    ) -> Result<Self, JwtError> {
+        let pop = WalletAttestationPop::new_signed(
+            client_id,
+            audience.as_ref().as_str(),
+            challenge,
+            WALLET_ATTESTATION_POP_VALIDITY_SECS,
+            hw_privkey,
+        )
+        .await?;
+
+        Ok(Self { attestation, pop })
+    }
+
+    /// Verify the attestation against the public key of the Wallet Provider, and the PoP against the hardware key
+    /// to which the attestation is bound and the `challenge` that the audience provided. If the audience has not
+    /// provided a challenge yet, the PoP is always rejected with [`WalletAttestationError::Challenge`]. Returns the
+    /// claims of the attestation.
+    pub fn verify(
+        &self,
+        wallet_provider_pubkey: &EcdsaDecodingKey,
+        accepted_client_ids: &[String],
+        audience: &BaseUrl,
+        challenge: Option<&str>,
+    ) -> Result<WalletAttestationClaims, WalletAttestationError> {
+        let mut validation_options = validations();
+        validation_options.required_spec_claims = HashSet::from(["sub".to_string(), "exp".to_string()]);
+        validation_options.sub = Some(WalletAttestationClaims::SUB.to_string());
+        let attestation = self
+            .attestation
+            .parse_and_verify(wallet_provider_pubkey, &validation_options)
+            .map_err(WalletAttestationError::Attestation)?;
+
+        let mut validation_options = validations();
+        validation_options.required_spec_claims =
+            HashSet::from(["sub".to_string(), "exp".to_string(), "aud".to_string()]);
+        validation_options.sub = Some(WalletAttestationPopClaims::SUB.to_string());
+        validation_options.set_audience(&[audience.as_ref().as_str()]);
+        let pop = self
+            .pop
+            .parse_and_verify(&(&attestation.hw_pubkey.0).into(), &validation_options)
+            .map_err(WalletAttestationError::Pop)?;
+
+        if !accepted_client_ids.contains(&pop.iss) {
+            return Err(WalletAttestationError::UnknownClientId(pop.iss));
+        }
+
+        if challenge.is_none() || pop.challenge.as_deref() != challenge {
+            return Err(WalletAttestationError::Challenge);
+        }
+
+        Ok(attestation)
+    }
+}
+
+/// Verify that `key_attestation` was signed by the Wallet Provider for the wallet instance identified by `wallet_id`,
+/// and attests `key`.
+pub fn verify_key_attestation(
+    key_attestation: Option<&KeyAttestation>,
+    wallet_provider_pubkey: &EcdsaDecodingKey,
+    wallet_id: &str,
+    key: &VerifyingKey,
+) -> Result<(), WalletAttestationError> {
+    let mut validation_options = validations();
+    validation_options.required_spec_claims = HashSet::from(["sub".to_string(), "exp".to_string()]);
+    validation_options.sub = Some(KeyAttestationClaims::SUB.to_string());
+    let claims = key_attestation
+        .ok_or(WalletAttestationError::MissingKeyAttestation)?
+        .parse_and_verify(wallet_provider_pubkey, &validation_options)
+        .map_err(WalletAttestationError::KeyAttestation)?;
+
+    if claims.wallet_id != wallet_id {
+        return Err(WalletAttestationError::WalletMismatch);
+    }
+
+    if !claims.attested_keys.iter().any(|attested_key| &attested_key.0 == key) {
+        return Err(WalletAttestationError::UnattestedKey);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use p256::ecdsa::SigningKey;
+    use rand_core::OsRng;
+
+    use wallet_common::{
+        account::messages::auth::{KeyAttestationClaims, WalletAttestation, WalletAttestationClaims},
+        jwt::{Jwt, JwtError},
+        urls::BaseUrl,
+    };
+
+    use super::{verify_key_attestation, WalletAttestationError, WalletAttestationWithPop};
+
+    const CLIENT_ID: &str = "https://wallet.example.com";
+    const CHALLENGE: &str = "challenge";
+
+    fn challenge() -> Option<String> {
+        Some(CHALLENGE.to_string())
+    }
+
+    async fn wallet_attestation(wp_privkey: &SigningKey, hw_privkey: &SigningKey, exp: u64) -> WalletAttestation {
+        let claims = WalletAttestationClaims {
+            wallet_id: "wallet_id".to_string(),
+            hw_pubkey: (*hw_privkey.verifying_key()).into(),
+            iss: "wallet_provider".to_string(),
+            iat: jsonwebtoken::get_current_timestamp(),
+            exp,
+        };
+
+        Jwt::sign_with_sub(&claims, wp_privkey).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wallet_attestation() {
+        let wp_privkey = SigningKey::random(&mut OsRng);
+        let hw_privkey = SigningKey::random(&mut OsRng);
+        let audience: BaseUrl = "https://issuer.example.com/".parse().unwrap();
+        let exp = jsonwebtoken::get_current_timestamp() + 60;
+
+        let attestation = wallet_attestation(&wp_privkey, &hw_privkey, exp).await;
+        let attestation_with_pop =
+            WalletAttestationWithPop::new(attestation.clone(), CLIENT_ID, &audience, challenge(), &hw_privkey)
+                .await
+                .unwrap();
+
+        let wp_pubkey = wp_privkey.verifying_key().into();
+        let accepted_client_ids = [CLIENT_ID.to_string()];
+        let claims = attestation_with_pop
+            .verify(&wp_pubkey, &accepted_client_ids, &audience, Some(CHALLENGE))
+            .unwrap();
+        assert_eq!(claims.wallet_id, "wallet_id");
+
+        // The attestation must be signed by the Wallet Provider.
+        let error = attestation_with_pop
+            .verify(
+                &hw_privkey.verifying_key().into(),
+                &accepted_client_ids,
+                &audience,
+                Some(CHALLENGE),
+            )
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Attestation(_));
+
+        // The PoP must be meant for this issuer.
+        let other_audience = "https://other-issuer.example.com/".parse().unwrap();
+        let error = attestation_with_pop
+            .verify(&wp_pubkey, &accepted_client_ids, &other_audience, Some(CHALLENGE))
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Pop(JwtError::Validation(_)));
+
+        // The PoP must be signed by the hardware key to which the attestation is bound.
+        let other_privkey = SigningKey::random(&mut OsRng);
+        let other_pop = WalletAttestationWithPop::new(attestation, CLIENT_ID, &audience, challenge(), &other_privkey)
+            .await
+            .unwrap()
+            .pop;
+        let error = WalletAttestationWithPop {
+            pop: other_pop,
+            ..attestation_with_pop.clone()
+        }
+        .verify(&wp_pubkey, &accepted_client_ids, &audience, Some(CHALLENGE))
+        .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Pop(_));
+
+        // The client ID must be accepted by the issuer.
+        let error = attestation_with_pop
+            .verify(
+                &wp_pubkey,
+                &["https://other-wallet.example.com".to_string()],
+                &audience,
+                Some(CHALLENGE),
+            )
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::UnknownClientId(_));
+
+        // The PoP must contain the challenge that the issuer provided.
+        let error = attestation_with_pop
+            .verify(&wp_pubkey, &accepted_client_ids, &audience, Some("other_challenge"))
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Challenge);
+
+        let error = attestation_with_pop
+            .verify(&wp_pubkey, &accepted_client_ids, &audience, None)
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Challenge);
+    }
+
+    #[tokio::test]
+    async fn test_wallet_attestation_expired() {
+        let wp_privkey = SigningKey::random(&mut OsRng);
+        let hw_privkey = SigningKey::random(&mut OsRng);
+        let audience: BaseUrl = "https://issuer.example.com/".parse().unwrap();
+
+        // Wallet attestations expire quickly, so that a blocked wallet can no longer use them.
+        let exp = jsonwebtoken::get_current_timestamp() - 3600;
+        let attestation = wallet_attestation(&wp_privkey, &hw_privkey, exp).await;
+        let attestation_with_pop =
+            WalletAttestationWithPop::new(attestation, CLIENT_ID, &audience, challenge(), &hw_privkey)
+                .await
+                .unwrap();
+
+        let error = attestation_with_pop
+            .verify(
+                &wp_privkey.verifying_key().into(),
+                &[CLIENT_ID.to_string()],
+                &audience,
+                Some(CHALLENGE),
+            )
+            .unwrap_err();
+        assert_matches!(error, WalletAttestationError::Attestation(_));
+    }
+
+    #[tokio::test]
+    async fn test_key_attestation() {
+        let wp_privkey = SigningKey::random(&mut OsRng);
+        let wp_pubkey = wp_privkey.verifying_key().into();
+        let key = *SigningKey::random(&mut OsRng).verifying_key();
+        let now = jsonwebtoken::get_current_timestamp();
+        let claims = KeyAttestationClaims {
+            wallet_id: "wallet_id".to_string(),
+            attested_keys: vec![key.into()],
+            iss: "wallet_provider".to_string(),
+            iat: now,
+            exp: now + 60,
+        };
+        let key_attestation = Jwt::sign_with_sub(&claims, &wp_privkey).await.unwrap();
+
+        verify_key_attestation(Some(&key_attestation), &wp_pubkey, "wallet_id", &key).unwrap();
+
+        let error = verify_key_attestation(None, &wp_pubkey, "wallet_id", &key).unwrap_err();
+        assert_matches!(error, WalletAttestationError::MissingKeyAttestation);
+
+        // The key attestation must be signed by the Wallet Provider.
+        let other_pubkey = SigningKey::random(&mut OsRng).verifying_key().into();
+        let error = verify_key_attestation(Some(&key_attestation), &other_pubkey, "wallet_id", &key).unwrap_err();
+        assert_matches!(error, WalletAttestationError::KeyAttestation(_));
+
+        // The keys must have been generated for the wallet that started the session.
+        let error = verify_key_attestation(Some(&key_attestation), &wp_pubkey, "other_wallet_id", &key).unwrap_err();
+        assert_matches!(error, WalletAttestationError::WalletMismatch);
+
+        let other_key = *SigningKey::random(&mut OsRng).verifying_key();
+        let error = verify_key_attestation(Some(&key_attestation), &wp_pubkey, "wallet_id", &other_key).unwrap_err();
+        assert_matches!(error, WalletAttestationError::UnattestedKey);
+    }
+}
@@ -27,7 +27,7 @@ use nl_wallet_mdoc::{
         TestDocuments,
     },
     utils::reader_auth::ReaderRegistration,
-    verifier::ItemsRequests,
+    verifier::{ItemsRequests, ItemsRequirement},
     DeviceResponse, DocType, SessionTranscript,
 };
 use openid4vc::{
@@ -35,7 +35,9 @@ use openid4vc::{
         DisclosureSession, DisclosureUriSource, VpClientError, VpMessageClient, VpMessageClientError,
     },
     jwt,
-    openid4vp::{IsoVpAuthorizationRequest, VpAuthorizationRequest, VpAuthorizationResponse, VpRequestUriObject},
+    openid4vp::{
+        IsoVpAuthorizationRequest, QueryLanguage, VpAuthorizationRequest, VpAuthorizationResponse, VpRequestUriObject,
+    },
     return_url::ReturnUrlTemplate,
     server_state::{MemorySessionStore, SessionToken},
     verifier::{
@@ -46,8 +48,26 @@ use openid4vc::{
 };
 use wallet_common::{generator::TimeGenerator, jwt::Jwt, trust_anchor::OwnedTrustAnchor, urls::BaseUrl};
 
+/// Request the example mdoc, or alternatively a document that the wallet does not have, which it should skip.
+fn example_items_requests_with_alternative() -> ItemsRequests {
+    let items_request = Examples::items_requests().items_requests().next().unwrap().clone();
+    let mut alternative = items_request.clone();
+    alternative.doc_type = "com.example.unknown_doc_type".to_string();
+
+    vec![ItemsRequirement::OneOf {
+        one_of: vec![alternative, items_request].try_into().unwrap(),
+    }]
+    .into()
+}
+
+#[rstest]
+#[case(Examples::items_requests())]
+#[case(example_items_requests_with_alternative())]
 #[tokio::test]
-async fn disclosure_direct() {
+async fn disclosure_direct(
+    #[case] items_requests: ItemsRequests,
+    #[values(QueryLanguage::PresentationExchange, QueryLanguage::Dcql)] query_language: QueryLanguage,
+) {
     let ca = KeyPair::generate_ca("myca", Default::default()).unwrap();
     let auth_keypair = ca.generate_reader_mock(None).unwrap();
 
@@ -56,7 +76,8 @@ async fn disclosure_direct() {
     let response_uri: BaseUrl = "https://example.com/response_uri".parse().unwrap();
     let encryption_keypair = EcKeyPair::generate(EcCurve::P256).unwrap();
     let iso_auth_request = IsoVpAuthorizationRequest::new(
-        &Examples::items_requests(),
+        &items_requests,
+        query_language,
         auth_keypair.certificate(),
         nonce.clone(),
         encryption_keypair.to_jwk_public_key().try_into().unwrap(),
@@ -104,7 +125,7 @@ async fn disclosure_jwe(auth_request: Jwt<VpAuthorizationRequest>, trust_anchors
         &mdoc_nonce,
     );
     let DisclosureRequestMatch::Candidates(candidates) =
-        DisclosureRequestMatch::new(auth_request.items_requests.as_ref().iter(), &mdocs, &session_transcript)
+        DisclosureRequestMatch::new_with_alternatives(&auth_request.items_requests, &mdocs, &session_transcript)
             .await
             .unwrap()
     else {
@@ -190,6 +211,7 @@ impl DirectMockVpMessageClient {
 
         let auth_request = IsoVpAuthorizationRequest::new(
             &Examples::items_requests(),
+            QueryLanguage::PresentationExchange,
             auth_keypair.certificate(),
             nonce.clone(),
             encryption_keypair.to_jwk_public_key().try_into().unwrap(),
@@ -271,6 +293,7 @@ impl VpMessageClient for DirectMockVpMessageClient {
 const NO_RETURN_URL_USE_CASE: &str = "no_return_url";
 const DEFAULT_RETURN_URL_USE_CASE: &str = "default_return_url";
 const ALL_RETURN_URL_USE_CASE: &str = "all_return_url";
+const DCQL_USE_CASE: &str = "dcql";
 
 struct MockMdocDataSource(HashMap<DocType, MdocCopies>);
 
@@ -418,6 +441,22 @@ impl MdocDataSource for MockMdocDataSource {
     (pid_given_name() + pid_given_name()).into(),
     pid_given_name()
 )]
+#[case(
+    SessionType::SameDevice,
+    None,
+    DCQL_USE_CASE,
+    pid_full_name(),
+    pid_full_name().into(),
+    pid_full_name()
+)]
+#[case(
+    SessionType::CrossDevice,
+    None,
+    DCQL_USE_CASE,
+    pid_given_name() + addr_street(),
+    (pid_given_name() + addr_street()).into(),
+    pid_given_name() + addr_street()
+)]
 #[tokio::test]
 async fn test_client_and_server(
     #[case] session_type: SessionType,
@@ -431,7 +470,13 @@ async fn test_client_and_server(
 
     // Start the session
     let session_token = verifier
-        .new_session(requested_documents, use_case.to_string(), return_url_template)
+        .new_session(
+            requested_documents,
+            use_case.to_string(),
+            return_url_template,
+            None,
+            Default::default(),
+        )
         .await
         .unwrap();
 
@@ -465,7 +510,7 @@ async fn test_client_and_server(
 
     // Check if we received a redirect URI when we should have, based on the use case and session type.
     let should_have_redirect_uri = match (use_case, session_type) {
-        (use_case, _) if use_case == NO_RETURN_URL_USE_CASE => false,
+        (use_case, _) if use_case == NO_RETURN_URL_USE_CASE || use_case == DCQL_USE_CASE => false,
         (use_case, _) if use_case == ALL_RETURN_URL_USE_CASE => true,
         (_, SessionType::SameDevice) => true,
         (_, SessionType::CrossDevice) => false,
@@ -497,7 +542,7 @@ async fn test_client_and_server(
     }
 
     // Retrieve the attributes disclosed by the wallet
-    let disclosed_documents = verifier
+    let (disclosed_documents, _) = verifier
         .disclosed_attributes(&session_token, redirect_uri_nonce)
         .await
         .unwrap();
@@ -519,6 +564,8 @@ async fn test_client_and_server_cancel_after_created() {
             items_requests,
             DEFAULT_RETURN_URL_USE_CASE.to_string(),
             Some(ReturnUrlTemplate::from_str("https://example.com/redirect_uri/{session_token}").unwrap()),
+            None,
+            Default::default(),
         )
         .await
         .unwrap();
@@ -571,6 +618,8 @@ async fn test_client_and_server_cancel_after_wallet_start() {
             items_requests,
             DEFAULT_RETURN_URL_USE_CASE.to_string(),
             Some(ReturnUrlTemplate::from_str("https://example.com/redirect_uri/{session_token}").unwrap()),
+            None,
+            Default::default(),
         )
         .await
         .unwrap();
@@ -633,6 +682,7 @@ fn setup_verifier(items_requests: &ItemsRequests) -> (Arc<MockVerifier>, OwnedTr
             UseCase::try_new(
                 rp_ca.generate_reader_mock(reader_registration.clone()).unwrap(),
                 SessionTypeReturnUrl::Neither,
+                QueryLanguage::PresentationExchange,
             )
             .unwrap(),
         ),
@@ -641,14 +691,25 @@ fn setup_verifier(items_requests: &ItemsRequests) -> (Arc<MockVerifier>, OwnedTr
             UseCase::try_new(
                 rp_ca.generate_reader_mock(reader_registration.clone()).unwrap(),
                 SessionTypeReturnUrl::SameDevice,
+                QueryLanguage::PresentationExchange,
             )
             .unwrap(),
         ),
         (
             ALL_RETURN_URL_USE_CASE.to_string(),
             UseCase::try_new(
-                rp_ca.generate_reader_mock(reader_registration).unwrap(),
+                rp_ca.generate_reader_mock(reader_registration.clone()).unwrap(),
                 SessionTypeReturnUrl::Both,
+                QueryLanguage::PresentationExchange,
+            )
+            .unwrap(),
+        ),
+        (
+            DCQL_USE_CASE.to_string(),
+            UseCase::try_new(
+                rp_ca.generate_reader_mock(reader_registration).unwrap(),
+                SessionTypeReturnUrl::Neither,
+                QueryLanguage::Dcql,
             )
             .unwrap(),
         ),
@@ -1,8 +1,12 @@
-use std::{num::NonZeroU8, ops::Add};
+use std::{num::NonZeroU8, ops::Add, sync::Arc};
 
+use assert_matches::assert_matches;
 use chrono::{Days, Utc};
 use ciborium::Value;
 use indexmap::IndexMap;
+use p256::ecdsa::SigningKey;
+use rand_core::OsRng;
+use reqwest::Method;
 use url::Url;
 
 use nl_wallet_mdoc::{
@@ -13,30 +17,54 @@ use nl_wallet_mdoc::{
     Tdate,
 };
 use openid4vc::{
-    credential::{CredentialRequestProof, CredentialRequests, CredentialResponses},
+    authorization::{AuthorizationRequest, PushedAuthorizationResponse},
+    credential::{
+        CredentialRequestProof, CredentialRequests, CredentialResponses, DeferredCredentialRequest, MaybeEncrypted,
+        NotificationEvent, NotificationRequest,
+    },
     dpop::Dpop,
-    issuance_session::{HttpIssuanceSession, IssuanceSession, IssuanceSessionError, VcMessageClient},
-    issuer::{AttributeService, Created, IssuanceData, Issuer},
-    metadata::IssuerMetadata,
+    issuance_session::{
+        AcceptedIssuance, DeferredIssuance, HttpIssuanceSession, IssuanceNotification, IssuanceRefresh,
+        IssuanceSession, IssuanceSessionError, VcMessageClient,
+    },
+    issuer::{
+        AttributeService, Created, CredentialRequestError, IssuanceData, IssuanceError, Issuer,
+        PreregisteredAttributeService, TokenRequestError, MAX_TX_CODE_ATTEMPTS,
+    },
+    metadata::{IssuerMetadata, IssuerMetadataError},
     oidc,
     server_state::{MemorySessionStore, SessionState},
-    token::{AccessToken, AttestationPreview, TokenRequest, TokenResponseWithPreviews},
-    CredentialErrorCode,
+    token::{
+        AccessToken, AttestationPreview, AuthorizationCode, TokenRequest, TokenRequestGrantType,
+        TokenResponseWithPreviews,
+    },
+    wallet_attestation::WalletAttestationWithPop,
+    CredentialErrorCode, NotificationErrorCode, TokenErrorCode,
+};
+use wallet_common::{
+    account::messages::auth::{WalletAttestation, WalletAttestationClaims},
+    jwt::Jwt,
+    keys::software::SoftwareEcdsaKey,
+    nonempty::NonEmpty,
+    urls::BaseUrl,
 };
-use wallet_common::{nonempty::NonEmpty, urls::BaseUrl};
 
-type MockIssuer = Issuer<MockAttributeService, SingleKeyRing, MemorySessionStore<IssuanceData>>;
+type MockIssuer<A = MockAttributeService> = Issuer<A, SingleKeyRing, MemorySessionStore<IssuanceData>>;
 
 fn setup() -> (MockIssuer, Certificate, BaseUrl) {
+    setup_with_attr_service(|issuer_cert| MockAttributeService { issuer_cert })
+}
+
+fn setup_with_attr_service<A: AttributeService>(
+    attr_service: impl FnOnce(Certificate) -> A,
+) -> (MockIssuer<A>, Certificate, BaseUrl) {
     let ca = KeyPair::generate_issuer_mock_ca().unwrap();
     let keypair = ca.generate_issuer_mock(IssuerRegistration::new_mock().into()).unwrap();
     let server_url: BaseUrl = "https://example.com/".parse().unwrap();
 
     let issuer = MockIssuer::new(
         MemorySessionStore::default(),
-        MockAttributeService {
-            issuer_cert: keypair.certificate().clone(),
-        },
+        attr_service(keypair.certificate().clone()),
         SingleKeyRing(keypair),
         &server_url,
         vec!["https://wallet.edi.rijksoverheid.nl".to_string()],
@@ -59,10 +87,11 @@ async fn accept_issuance() {
     .await
     .unwrap();
 
-    let mdoc_copies = session
+    let accepted = session
         .accept_issuance(&[(&ca).try_into().unwrap()], SoftwareKeyFactory::default(), server_url)
         .await
         .unwrap();
+    let mdoc_copies = assert_matches!(accepted, AcceptedIssuance::Issued { mdocs, .. } => mdocs);
 
     assert_eq!(mdoc_copies.len(), 2);
     assert_eq!(mdoc_copies[0].cred_copies.len(), 2);
@@ -77,6 +106,538 @@ async fn accept_issuance() {
     });
 }
 
+#[tokio::test]
+async fn accept_issuance_signed_metadata() {
+    let (mut issuer, ca, server_url) = setup();
+    issuer.sign_metadata(MOCK_PID_DOCTYPE).await.unwrap();
+    let message_client = MockOpenidMessageClient {
+        signed_metadata_required: true,
+        ..MockOpenidMessageClient::new(issuer)
+    };
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        &[(&ca).try_into().unwrap()],
+    )
+    .await
+    .unwrap();
+
+    let accepted = session
+        .accept_issuance(&[(&ca).try_into().unwrap()], SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    assert_matches!(accepted, AcceptedIssuance::Issued { .. });
+}
+
+#[tokio::test]
+async fn unsigned_metadata_rejected() {
+    let (issuer, ca, server_url) = setup();
+    let message_client = MockOpenidMessageClient {
+        signed_metadata_required: true,
+        ..MockOpenidMessageClient::new(issuer)
+    };
+
+    let error = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url,
+        TokenRequest::new_mock(),
+        &[(&ca).try_into().unwrap()],
+    )
+    .await
+    .map(|_| ())
+    .expect_err("starting issuance with unsigned metadata should fail");
+
+    assert_matches!(
+        error,
+        IssuanceSessionError::IssuerMetadata(IssuerMetadataError::UnsignedMetadata)
+    );
+}
+
+#[tokio::test]
+async fn untrusted_signed_metadata_rejected() {
+    let (mut issuer, _ca, server_url) = setup();
+    issuer.sign_metadata(MOCK_PID_DOCTYPE).await.unwrap();
+    let message_client = MockOpenidMessageClient::new(issuer);
+
+    let other_ca = KeyPair::generate_issuer_mock_ca().unwrap();
+    let error = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url,
+        TokenRequest::new_mock(),
+        &[other_ca.certificate().try_into().unwrap()],
+    )
+    .await
+    .map(|_| ())
+    .expect_err("starting issuance with untrusted signed metadata should fail");
+
+    assert_matches!(
+        error,
+        IssuanceSessionError::IssuerMetadata(IssuerMetadataError::SignedMetadata(_))
+    );
+}
+
+#[tokio::test]
+async fn accept_preregistered_issuance() {
+    let mut issuer_cert = None;
+    let (issuer, ca, server_url) = setup_with_attr_service(|cert| {
+        issuer_cert = Some(cert);
+        PreregisteredAttributeService
+    });
+
+    // Register the attestations to be issued, as the owner of the issuance server would.
+    let code = issuer
+        .new_session(mock_attestation_previews(&issuer_cert.unwrap()), None, false)
+        .await
+        .unwrap();
+
+    let message_client = MockOpenidMessageClient::new(issuer);
+    let token_request = TokenRequest {
+        grant_type: TokenRequestGrantType::PreAuthorizedCode {
+            pre_authorized_code: code,
+            tx_code: None,
+        },
+        code_verifier: None,
+        client_id: None,
+        redirect_uri: None,
+    };
+
+    let (session, previews) = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url.clone(),
+        token_request,
+        &[(&ca).try_into().unwrap()],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(previews.len(), 2);
+
+    let accepted = session
+        .accept_issuance(&[(&ca).try_into().unwrap()], SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let mdoc_copies = assert_matches!(accepted, AcceptedIssuance::Issued { mdocs, .. } => mdocs);
+
+    assert_eq!(mdoc_copies.len(), 2);
+}
+
+fn tx_code_token_request(code: &AuthorizationCode, tx_code: Option<&str>) -> TokenRequest {
+    TokenRequest {
+        grant_type: TokenRequestGrantType::PreAuthorizedCode {
+            pre_authorized_code: code.clone(),
+            tx_code: tx_code.map(str::to_string),
+        },
+        code_verifier: None,
+        client_id: None,
+        redirect_uri: None,
+    }
+}
+
+#[tokio::test]
+async fn preregistered_issuance_tx_code() {
+    let mut issuer_cert = None;
+    let (issuer, _, server_url) = setup_with_attr_service(|cert| {
+        issuer_cert = Some(cert);
+        PreregisteredAttributeService
+    });
+
+    let code = issuer
+        .new_session(
+            mock_attestation_previews(&issuer_cert.unwrap()),
+            Some("1234".to_string()),
+            false,
+        )
+        .await
+        .unwrap();
+    let dpop = Dpop::new(
+        &SigningKey::random(&mut OsRng),
+        server_url.join("token"),
+        Method::POST,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // A missing or incorrect transaction code is rejected, but the wallet may try again.
+    for tx_code in [None, Some("4321")] {
+        let error = issuer
+            .process_token_request(tx_code_token_request(&code, tx_code), dpop.clone(), None)
+            .await
+            .expect_err("token request with incorrect transaction code should fail");
+        assert_matches!(error, TokenRequestError::IncorrectTxCode);
+    }
+
+    let (response, _) = issuer
+        .process_token_request(tx_code_token_request(&code, Some("1234")), dpop, None)
+        .await
+        .expect("token request with correct transaction code should succeed");
+    assert_eq!(response.attestation_previews.as_ref().len(), 2);
+}
+
+#[tokio::test]
+async fn preregistered_issuance_tx_code_attempts_exceeded() {
+    let mut issuer_cert = None;
+    let (issuer, _, server_url) = setup_with_attr_service(|cert| {
+        issuer_cert = Some(cert);
+        PreregisteredAttributeService
+    });
+
+    let code = issuer
+        .new_session(
+            mock_attestation_previews(&issuer_cert.unwrap()),
+            Some("1234".to_string()),
+            false,
+        )
+        .await
+        .unwrap();
+    let dpop = Dpop::new(
+        &SigningKey::random(&mut OsRng),
+        server_url.join("token"),
+        Method::POST,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    for _ in 0..MAX_TX_CODE_ATTEMPTS {
+        let error = issuer
+            .process_token_request(tx_code_token_request(&code, Some("4321")), dpop.clone(), None)
+            .await
+            .expect_err("token request with incorrect transaction code should fail");
+        assert_matches!(error, TokenRequestError::IncorrectTxCode);
+    }
+
+    // After too many incorrect attempts the session has failed, so that even the correct code is rejected.
+    let error = issuer
+        .process_token_request(tx_code_token_request(&code, Some("1234")), dpop, None)
+        .await
+        .expect_err("token request after too many attempts should fail");
+    assert_matches!(error, TokenRequestError::IssuanceError(IssuanceError::UnexpectedState));
+}
+
+#[tokio::test]
+async fn accept_deferred_issuance() {
+    let mut issuer_cert = None;
+    let (issuer, ca, server_url) = setup_with_attr_service(|cert| {
+        issuer_cert = Some(cert);
+        PreregisteredAttributeService
+    });
+    let trust_anchors = &[(&ca).try_into().unwrap()];
+
+    let code = issuer
+        .new_session(mock_attestation_previews(&issuer_cert.unwrap()), None, true)
+        .await
+        .unwrap();
+    let issuer = Arc::new(issuer);
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        server_url.clone(),
+        tx_code_token_request(&code, None),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    // The issuer defers issuance, so instead of mdocs the wallet receives a transaction ID.
+    let accepted = session
+        .accept_issuance(trust_anchors, SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let deferred = assert_matches!(accepted, AcceptedIssuance::Deferred(deferred) => deferred);
+    assert_eq!(deferred.attestation_previews().len(), 2);
+
+    // The wallet persists the deferred issuance, so it must survive a serialization roundtrip.
+    let deferred: DeferredIssuance = serde_json::from_value(serde_json::to_value(*deferred).unwrap()).unwrap();
+
+    // As long as the issuer has not approved issuance, the wallet receives the same transaction ID back.
+    let accepted = HttpIssuanceSession::accept_deferred_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        deferred.clone(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+    let still_deferred = assert_matches!(accepted, AcceptedIssuance::Deferred(deferred) => deferred);
+    assert_eq!(still_deferred.transaction_id(), deferred.transaction_id());
+
+    issuer.approve_deferred_issuance(code).await.unwrap();
+
+    let accepted = HttpIssuanceSession::accept_deferred_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        deferred,
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+    let mdoc_copies = assert_matches!(accepted, AcceptedIssuance::Issued { mdocs, .. } => mdocs);
+    assert_eq!(mdoc_copies.len(), 2);
+    assert_eq!(mdoc_copies[0].cred_copies.len(), 2);
+}
+
+#[tokio::test]
+async fn refresh_issuance() {
+    let (issuer, ca, server_url) = setup();
+    let trust_anchors = &[(&ca).try_into().unwrap()];
+    let issuer = Arc::new(issuer);
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    let accepted = session
+        .accept_issuance(trust_anchors, SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let (mdoc_copies, refresh) = assert_matches!(
+        accepted,
+        AcceptedIssuance::Issued { mdocs, refresh: Some(refresh), .. } => (mdocs, refresh)
+    );
+
+    // The wallet persists the refresh, so it must survive a serialization roundtrip.
+    let refresh: IssuanceRefresh = serde_json::from_value(serde_json::to_value(refresh).unwrap()).unwrap();
+
+    let (refreshed_copies, new_refresh) = HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        refresh.clone(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    // The refreshed mdocs contain the same attributes and are bound to the same keys as the original ones.
+    assert_eq!(refreshed_copies.len(), mdoc_copies.len());
+    for (refreshed, original) in refreshed_copies.iter().zip(&mdoc_copies) {
+        assert_eq!(refreshed.cred_copies.len(), original.cred_copies.len());
+        for (refreshed, original) in refreshed.cred_copies.iter().zip(&original.cred_copies) {
+            assert_eq!(refreshed.private_key_id(), original.private_key_id());
+            assert_eq!(refreshed.attributes(), original.attributes());
+        }
+    }
+    assert!(new_refresh.key_identifiers().eq(refresh.key_identifiers()));
+
+    // The refresh contains the notification of the refreshed mdocs, with which the wallet can notify the issuer about
+    // them later on, while the notification of the original mdocs can no longer be used.
+    HttpIssuanceSession::notify_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        new_refresh.notification().unwrap().clone(),
+        NotificationEvent::CredentialDeleted,
+    )
+    .await
+    .expect("notifying the issuer of the refreshed mdocs should succeed");
+    HttpIssuanceSession::notify_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        refresh.notification().unwrap().clone(),
+        NotificationEvent::CredentialDeleted,
+    )
+    .await
+    .expect_err("notifying the issuer of the original mdocs should fail");
+
+    // The refresh token is rotated, so the old one can no longer be used.
+    let error = HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        refresh,
+        trust_anchors,
+    )
+    .await
+    .expect_err("refreshing with a rotated refresh token should fail");
+    assert_matches!(
+        error,
+        IssuanceSessionError::TokenRequest(err) if matches!(err.error, TokenErrorCode::InvalidGrant)
+    );
+
+    HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        new_refresh,
+        trust_anchors,
+    )
+    .await
+    .expect("refreshing with the new refresh token should succeed");
+}
+
+#[tokio::test]
+async fn refresh_issuance_failure_keeps_refresh_token() {
+    let (issuer, ca, server_url) = setup();
+    let trust_anchors = &[(&ca).try_into().unwrap()];
+    let issuer = Arc::new(issuer);
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    let accepted = session
+        .accept_issuance(trust_anchors, SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let refresh = assert_matches!(accepted, AcceptedIssuance::Issued { refresh: Some(refresh), .. } => refresh);
+
+    // The token request succeeds, but the credential requests fail after the wallet received a new refresh token.
+    let message_client = MockOpenidMessageClient {
+        wrong_access_token: true,
+        ..MockOpenidMessageClient::new_shared(Arc::clone(&issuer))
+    };
+    HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(message_client, (*refresh).clone(), trust_anchors)
+        .await
+        .expect_err("refreshing with the wrong access token should fail");
+
+    // Since no attestations were issued, the refresh token that the wallet has is still valid.
+    HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        *refresh,
+        trust_anchors,
+    )
+    .await
+    .expect("refreshing with the current refresh token should succeed");
+}
+
+#[tokio::test]
+async fn replenish_issuance() {
+    let (issuer, ca, server_url) = setup();
+    let trust_anchors = &[(&ca).try_into().unwrap()];
+    let issuer = Arc::new(issuer);
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    let accepted = session
+        .accept_issuance(trust_anchors, SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let (mdoc_copies, refresh) = assert_matches!(
+        accepted,
+        AcceptedIssuance::Issued { mdocs, refresh: Some(refresh), .. } => (mdocs, refresh)
+    );
+
+    let (replenished_copies, new_refresh) = HttpIssuanceSession::replenish_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        *refresh,
+        trust_anchors,
+        SoftwareKeyFactory::default(),
+    )
+    .await
+    .unwrap();
+
+    // The replenished mdocs contain the same attributes as the original ones, but are bound to new keys.
+    assert_eq!(replenished_copies.len(), mdoc_copies.len());
+    for (replenished, original) in replenished_copies.iter().zip(&mdoc_copies) {
+        assert_eq!(replenished.cred_copies.len(), original.cred_copies.len());
+        for (replenished, original) in replenished.cred_copies.iter().zip(&original.cred_copies) {
+            assert_ne!(replenished.private_key_id(), original.private_key_id());
+            assert_eq!(replenished.attributes(), original.attributes());
+        }
+    }
+
+    // The new refresh applies to the replenished mdocs.
+    assert!(new_refresh.key_identifiers().eq(replenished_copies
+        .iter()
+        .flat_map(|copies| copies.cred_copies.iter().map(|mdoc| mdoc.private_key_id()))));
+
+    let (refreshed_copies, _) = HttpIssuanceSession::refresh_issuance::<SoftwareEcdsaKey>(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        new_refresh,
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+    for (refreshed, replenished) in refreshed_copies.iter().zip(&replenished_copies) {
+        for (refreshed, replenished) in refreshed.cred_copies.iter().zip(&replenished.cred_copies) {
+            assert_eq!(refreshed.private_key_id(), replenished.private_key_id());
+        }
+    }
+}
+
+#[tokio::test]
+async fn notify_issuance() {
+    let (issuer, ca, server_url) = setup();
+    let issuer = Arc::new(issuer);
+    let trust_anchors = &[(&ca).try_into().unwrap()];
+
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        trust_anchors,
+    )
+    .await
+    .unwrap();
+
+    let accepted = session
+        .accept_issuance(trust_anchors, SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap();
+    let notification = assert_matches!(
+        accepted,
+        AcceptedIssuance::Issued { notification: Some(notification), .. } => *notification
+    );
+
+    // Notifying the issuer with the wrong access token fails.
+    let error = HttpIssuanceSession::notify_issuance(
+        MockOpenidMessageClient {
+            wrong_access_token: true,
+            ..MockOpenidMessageClient::new_shared(Arc::clone(&issuer))
+        },
+        notification.clone(),
+        NotificationEvent::CredentialAccepted,
+    )
+    .await
+    .unwrap_err();
+    assert_matches!(
+        error,
+        IssuanceSessionError::NotificationRequest(response) if response.error == NotificationErrorCode::InvalidToken
+    );
+
+    // Notifying the issuer with an unknown notification ID fails.
+    let mut unknown_notification = serde_json::to_value(&notification).unwrap();
+    unknown_notification["notification_id"] = "unknown".into();
+    let unknown_notification: IssuanceNotification = serde_json::from_value(unknown_notification).unwrap();
+    let error = HttpIssuanceSession::notify_issuance(
+        MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+        unknown_notification,
+        NotificationEvent::CredentialAccepted,
+    )
+    .await
+    .unwrap_err();
+    assert_matches!(
+        error,
+        IssuanceSessionError::NotificationRequest(response)
+            if response.error == NotificationErrorCode::InvalidNotificationId
+    );
+
+    // The wallet can notify the issuer of both the acceptance and the later deletion of the mdocs.
+    for event in [
+        NotificationEvent::CredentialAccepted,
+        NotificationEvent::CredentialDeleted,
+    ] {
+        HttpIssuanceSession::notify_issuance(
+            MockOpenidMessageClient::new_shared(Arc::clone(&issuer)),
+            notification.clone(),
+            event,
+        )
+        .await
+        .unwrap();
+    }
+}
+
 #[tokio::test]
 async fn reject_issuance() {
     let (issuer, ca, server_url) = setup();
@@ -150,6 +711,202 @@ async fn invalid_dpop() {
     ));
 }
 
+async fn mock_wallet_attestation(
+    wp_privkey: &SigningKey,
+    hw_privkey: &SigningKey,
+    wallet_id: &str,
+) -> WalletAttestation {
+    let now = jsonwebtoken::get_current_timestamp();
+    let claims = WalletAttestationClaims {
+        wallet_id: wallet_id.to_string(),
+        hw_pubkey: (*hw_privkey.verifying_key()).into(),
+        iss: "wallet_provider".to_string(),
+        iat: now,
+        exp: now + 60,
+    };
+
+    Jwt::sign_with_sub(&claims, wp_privkey).await.unwrap()
+}
+
+/// Create a wallet attestation for `wallet_id` along with a PoP containing `challenge`.
+async fn mock_wallet_attestation_with_pop(
+    wp_privkey: &SigningKey,
+    wallet_id: &str,
+    server_url: &BaseUrl,
+    challenge: Option<String>,
+) -> WalletAttestationWithPop {
+    let hw_privkey = SigningKey::random(&mut OsRng);
+    WalletAttestationWithPop::new(
+        mock_wallet_attestation(wp_privkey, &hw_privkey, wallet_id).await,
+        "https://wallet.edi.rijksoverheid.nl",
+        server_url,
+        challenge,
+        &hw_privkey,
+    )
+    .await
+    .unwrap()
+}
+
+/// Start issuance with a wallet attestation for `wallet_id`, using the challenge that the issuer provides. The
+/// credential requests are sent with a wallet attestation for `credential_wallet_id`.
+async fn start_issuance_wallet_attestation(
+    issuer: MockIssuer,
+    wp_privkey: &SigningKey,
+    wallet_id: &str,
+    credential_wallet_id: &str,
+    ca: &Certificate,
+    server_url: &BaseUrl,
+) -> HttpIssuanceSession<MockOpenidMessageClient> {
+    let issuer = Arc::new(issuer);
+
+    // Without the challenge of the session, the issuer responds with a challenge.
+    let message_client = MockOpenidMessageClient {
+        wallet_attestation: Some(mock_wallet_attestation_with_pop(wp_privkey, wallet_id, server_url, None).await),
+        ..MockOpenidMessageClient::new_shared(Arc::clone(&issuer))
+    };
+    let error = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        &[ca.try_into().unwrap()],
+    )
+    .await
+    .map(|_| ())
+    .unwrap_err();
+    let challenge = assert_matches!(error, IssuanceSessionError::AttestationChallenge(challenge) => challenge);
+
+    let message_client = MockOpenidMessageClient {
+        wallet_attestation: Some(
+            mock_wallet_attestation_with_pop(wp_privkey, wallet_id, server_url, Some(challenge.clone())).await,
+        ),
+        credential_wallet_attestation: Some(
+            mock_wallet_attestation_with_pop(wp_privkey, credential_wallet_id, server_url, Some(challenge)).await,
+        ),
+        ..MockOpenidMessageClient::new_shared(issuer)
+    };
+    let (session, _previews) = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url.clone(),
+        TokenRequest::new_mock(),
+        &[ca.try_into().unwrap()],
+    )
+    .await
+    .unwrap();
+
+    session
+}
+
+#[tokio::test]
+async fn accept_issuance_wallet_attestation() {
+    let (mut issuer, ca, server_url) = setup();
+    let wp_privkey = SigningKey::random(&mut OsRng);
+    issuer.require_wallet_attestation(wp_privkey.verifying_key().into());
+
+    let session =
+        start_issuance_wallet_attestation(issuer, &wp_privkey, "wallet_id", "wallet_id", &ca, &server_url).await;
+
+    let mut key_factory = SoftwareKeyFactory::default();
+    key_factory.key_attester = Some((wp_privkey, "wallet_id".to_string()));
+    let accepted = session
+        .accept_issuance(&[(&ca).try_into().unwrap()], key_factory, server_url)
+        .await
+        .unwrap();
+    assert_matches!(accepted, AcceptedIssuance::Issued { .. });
+}
+
+#[tokio::test]
+async fn wallet_attestation_challenge_bound_to_session() {
+    let (mut issuer, ca, server_url) = setup();
+    let wp_privkey = SigningKey::random(&mut OsRng);
+    issuer.require_wallet_attestation(wp_privkey.verifying_key().into());
+
+    // A PoP containing a challenge of which the issuer has no knowledge is rejected.
+    let message_client = MockOpenidMessageClient {
+        wallet_attestation: Some(
+            mock_wallet_attestation_with_pop(&wp_privkey, "wallet_id", &server_url, Some("challenge".to_string()))
+                .await,
+        ),
+        ..MockOpenidMessageClient::new(issuer)
+    };
+    let error = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url,
+        TokenRequest::new_mock(),
+        &[(&ca).try_into().unwrap()],
+    )
+    .await
+    .map(|_| ())
+    .unwrap_err();
+
+    assert_matches!(error, IssuanceSessionError::AttestationChallenge(challenge) if challenge != "challenge");
+}
+
+#[tokio::test]
+async fn wallet_attestation_other_wallet_rejected() {
+    let (mut issuer, ca, server_url) = setup();
+    let wp_privkey = SigningKey::random(&mut OsRng);
+    issuer.require_wallet_attestation(wp_privkey.verifying_key().into());
+
+    // The credential requests are sent with a wallet attestation for another wallet.
+    let session =
+        start_issuance_wallet_attestation(issuer, &wp_privkey, "wallet_id", "other_wallet_id", &ca, &server_url).await;
+
+    let mut key_factory = SoftwareKeyFactory::default();
+    key_factory.key_attester = Some((wp_privkey, "other_wallet_id".to_string()));
+    let error = session
+        .accept_issuance(&[(&ca).try_into().unwrap()], key_factory, server_url)
+        .await
+        .unwrap_err();
+
+    assert_matches!(
+        error,
+        IssuanceSessionError::CredentialRequest(err) if matches!(err.error, CredentialErrorCode::InvalidRequest)
+    );
+}
+
+#[tokio::test]
+async fn unattested_keys_rejected() {
+    let (mut issuer, ca, server_url) = setup();
+    let wp_privkey = SigningKey::random(&mut OsRng);
+    issuer.require_wallet_attestation(wp_privkey.verifying_key().into());
+
+    let session =
+        start_issuance_wallet_attestation(issuer, &wp_privkey, "wallet_id", "wallet_id", &ca, &server_url).await;
+
+    // The keys to which the attestations are to be bound are not attested by the Wallet Provider.
+    let error = session
+        .accept_issuance(&[(&ca).try_into().unwrap()], SoftwareKeyFactory::default(), server_url)
+        .await
+        .unwrap_err();
+
+    assert_matches!(
+        error,
+        IssuanceSessionError::CredentialRequest(err) if matches!(err.error, CredentialErrorCode::InvalidRequest)
+    );
+}
+
+#[tokio::test]
+async fn missing_wallet_attestation_rejected() {
+    let (mut issuer, ca, server_url) = setup();
+    issuer.require_wallet_attestation(SigningKey::random(&mut OsRng).verifying_key().into());
+    let message_client = MockOpenidMessageClient::new(issuer);
+
+    let error = HttpIssuanceSession::start_issuance(
+        message_client,
+        server_url,
+        TokenRequest::new_mock(),
+        &[(&ca).try_into().unwrap()],
+    )
+    .await
+    .map(|_| ())
+    .expect_err("starting issuance without wallet attestation should fail");
+
+    assert_matches!(
+        error,
+        IssuanceSessionError::TokenRequest(err) if matches!(err.error, TokenErrorCode::InvalidClient)
+    );
+}
+
 #[tokio::test]
 async fn invalid_pop() {
     let (issuer, ca, server_url) = setup();
@@ -189,26 +946,37 @@ async fn invalid_pop() {
 /// Using this mock implementation of `OpenidMessageClient` means that that part of the standard is not used,
 /// since it bypasses HTTP altogether. Therefore, using this struct to test the OpenID4VCI implementation means
 /// that the transport part of this implementation of the protocol is not tested.
-struct MockOpenidMessageClient {
-    issuer: MockIssuer,
+struct MockOpenidMessageClient<A = MockAttributeService> {
+    issuer: Arc<MockIssuer<A>>,
 
+    signed_metadata_required: bool,
     wrong_access_token: bool,
     invalidate_dpop: bool,
     invalidate_pop: bool,
+    wallet_attestation: Option<WalletAttestationWithPop>,
+    /// If set, this is sent in the credential requests instead of `wallet_attestation`.
+    credential_wallet_attestation: Option<WalletAttestationWithPop>,
 }
 
-impl MockOpenidMessageClient {
-    fn new(issuer: MockIssuer) -> Self {
+impl<A> MockOpenidMessageClient<A> {
+    fn new(issuer: MockIssuer<A>) -> Self {
+        Self::new_shared(Arc::new(issuer))
+    }
+
+    fn new_shared(issuer: Arc<MockIssuer<A>>) -> Self {
         Self {
             issuer,
+            signed_metadata_required: false,
             wrong_access_token: false,
             invalidate_dpop: false,
             invalidate_pop: false,
+            wallet_attestation: None,
+            credential_wallet_attestation: None,
         }
     }
 }
 
-impl MockOpenidMessageClient {
+impl<A> MockOpenidMessageClient<A> {
     fn access_token(&self, access_token_header: &str) -> AccessToken {
         if self.wrong_access_token {
             let code = &access_token_header[32 + 5..]; // Strip "DPoP "
@@ -226,6 +994,13 @@ impl MockOpenidMessageClient {
         }
     }
 
+    fn credential_wallet_attestation(&self) -> Option<WalletAttestationWithPop> {
+        self.credential_wallet_attestation
+            .as_ref()
+            .or(self.wallet_attestation.as_ref())
+            .cloned()
+    }
+
     fn credential_requests(&self, mut credential_requests: CredentialRequests) -> CredentialRequests {
         if self.invalidate_pop {
             let invalidated_proof = match credential_requests.credential_requests.first().proof.as_ref().unwrap() {
@@ -246,15 +1021,28 @@ impl MockOpenidMessageClient {
     }
 }
 
+fn credential_request_error(err: CredentialRequestError) -> IssuanceSessionError {
+    match err {
+        CredentialRequestError::UseAttestationChallenge(challenge) => {
+            IssuanceSessionError::AttestationChallenge(challenge)
+        }
+        err => IssuanceSessionError::CredentialRequest(err.into()),
+    }
+}
+
 /// Invalidate a JWT by modifying the last character of its signature
 fn invalidate_jwt(jwt: &str) -> String {
     let new_char = if !jwt.ends_with('A') { 'A' } else { 'B' };
     jwt[..jwt.len() - 1].to_string() + &new_char.to_string()
 }
 
-impl VcMessageClient for MockOpenidMessageClient {
-    async fn discover_metadata(&self, url: &BaseUrl) -> Result<IssuerMetadata, IssuanceSessionError> {
-        Ok(IssuerMetadata::new_mock(url.clone()))
+impl<A: AttributeService> VcMessageClient for MockOpenidMessageClient<A> {
+    fn signed_metadata_required(&self) -> bool {
+        self.signed_metadata_required
+    }
+
+    async fn discover_metadata(&self, _url: &BaseUrl) -> Result<IssuerMetadata, IssuanceSessionError> {
+        Ok(self.issuer.metadata.clone())
     }
 
     async fn discover_oauth_metadata(&self, url: &BaseUrl) -> Result<oidc::Config, IssuanceSessionError> {
@@ -262,6 +1050,14 @@ impl VcMessageClient for MockOpenidMessageClient {
         Ok(metadata)
     }
 
+    async fn push_authorization_request(
+        &self,
+        _url: &Url,
+        _authorization_request: &AuthorizationRequest,
+    ) -> Result<PushedAuthorizationResponse, IssuanceSessionError> {
+        unimplemented!("the mock issuer only supports the pre-authorized code flow")
+    }
+
     async fn request_token(
         &self,
         _url: &Url,
@@ -270,9 +1066,18 @@ impl VcMessageClient for MockOpenidMessageClient {
     ) -> Result<(TokenResponseWithPreviews, Option<String>), IssuanceSessionError> {
         let (token_response, dpop_nonce) = self
             .issuer
-            .process_token_request(token_request.clone(), dpop_header.clone())
+            .process_token_request(
+                token_request.clone(),
+                dpop_header.clone(),
+                self.wallet_attestation.clone(),
+            )
             .await
-            .map_err(|err| IssuanceSessionError::TokenRequest(err.into()))?;
+            .map_err(|err| match err {
+                TokenRequestError::UseAttestationChallenge(challenge) => {
+                    IssuanceSessionError::AttestationChallenge(challenge)
+                }
+                err => IssuanceSessionError::TokenRequest(err.into()),
+            })?;
         Ok((token_response, Some(dpop_nonce)))
     }
 
@@ -282,15 +1087,51 @@ impl VcMessageClient for MockOpenidMessageClient {
         credential_requests: &CredentialRequests,
         dpop_header: &str,
         access_token_header: &str,
-    ) -> Result<CredentialResponses, IssuanceSessionError> {
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError> {
         self.issuer
             .process_batch_credential(
                 self.access_token(access_token_header),
                 self.dpop(dpop_header),
                 self.credential_requests(credential_requests.clone()),
+                self.credential_wallet_attestation(),
             )
             .await
-            .map_err(|err| IssuanceSessionError::CredentialRequest(err.into()))
+            .map_err(credential_request_error)
+    }
+
+    async fn request_deferred_credentials(
+        &self,
+        _url: &Url,
+        deferred_request: &DeferredCredentialRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<MaybeEncrypted<CredentialResponses>, IssuanceSessionError> {
+        self.issuer
+            .process_deferred_credential(
+                self.access_token(access_token_header),
+                self.dpop(dpop_header),
+                deferred_request.clone(),
+                self.credential_wallet_attestation(),
+            )
+            .await
+            .map_err(credential_request_error)
+    }
+
+    async fn notify(
+        &self,
+        _url: &Url,
+        notification_request: &NotificationRequest,
+        dpop_header: &str,
+        access_token_header: &str,
+    ) -> Result<(), IssuanceSessionError> {
+        self.issuer
+            .process_notification(
+                self.access_token(access_token_header),
+                self.dpop(dpop_header),
+                notification_request.clone(),
+            )
+            .await
+            .map_err(|err| IssuanceSessionError::NotificationRequest(err.into()))
     }
 
     async fn reject(
@@ -326,54 +1167,66 @@ impl AttributeService for MockAttributeService {
         _session: &SessionState<Created>,
         _token_request: TokenRequest,
     ) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
-        let previews = vec![
-            AttestationPreview::MsoMdoc {
-                unsigned_mdoc: UnsignedMdoc {
-                    doc_type: MOCK_PID_DOCTYPE.to_string(),
-                    copy_count: NonZeroU8::new(2).unwrap(),
-                    valid_from: Tdate::now(),
-                    valid_until: Utc::now().add(Days::new(365)).into(),
-                    attributes: IndexMap::from([(
-                        MOCK_PID_DOCTYPE.to_string(),
-                        MOCK_ATTRS
-                            .iter()
-                            .map(|(key, val)| Entry {
-                                name: key.to_string(),
-                                value: Value::Text(val.to_string()),
-                            })
-                            .collect(),
-                    )])
-                    .try_into()
-                    .unwrap(),
-                },
-                issuer: self.issuer_cert.clone(),
-            },
-            AttestationPreview::MsoMdoc {
-                unsigned_mdoc: UnsignedMdoc {
-                    doc_type: MOCK_ADDRESS_DOCTYPE.to_string(),
-                    copy_count: NonZeroU8::new(2).unwrap(),
-                    valid_from: Tdate::now(),
-                    valid_until: Utc::now().add(Days::new(365)).into(),
-                    attributes: IndexMap::from([(
-                        MOCK_ADDRESS_DOCTYPE.to_string(),
-                        MOCK_ATTRS
-                            .iter()
-                            .map(|(key, val)| Entry {
-                                name: key.to_string(),
-                                value: Value::Text(val.to_string()),
-                            })
-                            .collect(),
-                    )])
-                    .try_into()
-                    .unwrap(),
-                },
-                issuer: self.issuer_cert.clone(),
-            },
-        ];
-        Ok(previews.try_into().unwrap())
+        Ok(mock_attestation_previews(&self.issuer_cert))
+    }
+
+    fn refresh_subject(&self, _attestation_previews: &[AttestationPreview]) -> Option<String> {
+        Some("subject".to_string())
+    }
+
+    async fn refreshed_attributes(&self, _subject: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
+        Ok(mock_attestation_previews(&self.issuer_cert))
     }
 
     async fn oauth_metadata(&self, issuer_url: &BaseUrl) -> Result<oidc::Config, Self::Error> {
         Ok(oidc::Config::new_mock(issuer_url))
     }
 }
+
+fn mock_attestation_previews(issuer_cert: &Certificate) -> NonEmpty<Vec<AttestationPreview>> {
+    let previews = vec![
+        AttestationPreview::MsoMdoc {
+            unsigned_mdoc: UnsignedMdoc {
+                doc_type: MOCK_PID_DOCTYPE.to_string(),
+                copy_count: NonZeroU8::new(2).unwrap(),
+                valid_from: Tdate::now(),
+                valid_until: Utc::now().add(Days::new(365)).into(),
+                attributes: IndexMap::from([(
+                    MOCK_PID_DOCTYPE.to_string(),
+                    MOCK_ATTRS
+                        .iter()
+                        .map(|(key, val)| Entry {
+                            name: key.to_string(),
+                            value: Value::Text(val.to_string()),
+                        })
+                        .collect(),
+                )])
+                .try_into()
+                .unwrap(),
+            },
+            issuer: issuer_cert.clone(),
+        },
+        AttestationPreview::MsoMdoc {
+            unsigned_mdoc: UnsignedMdoc {
+                doc_type: MOCK_ADDRESS_DOCTYPE.to_string(),
+                copy_count: NonZeroU8::new(2).unwrap(),
+                valid_from: Tdate::now(),
+                valid_until: Utc::now().add(Days::new(365)).into(),
+                attributes: IndexMap::from([(
+                    MOCK_ADDRESS_DOCTYPE.to_string(),
+                    MOCK_ATTRS
+                        .iter()
+                        .map(|(key, val)| Entry {
+                            name: key.to_string(),
+                            value: Value::Text(val.to_string()),
+                        })
+                        .collect(),
+                )])
+                .try_into()
+                .unwrap(),
+            },
+            issuer: issuer_cert.clone(),
+        },
+    ];
+    previews.try_into().unwrap()
+}
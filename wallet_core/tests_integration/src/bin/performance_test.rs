@@ -113,6 +113,9 @@ async fn main() {
         }]
         .into(),
         return_url_template: Some(relying_party_url.parse().unwrap()),
+        callback_url: None,
+        expiration_seconds: None,
+        correlation_id: None,
     };
 
     let internal_mrp_url: Url = internal_wallet_server_url.parse().unwrap();
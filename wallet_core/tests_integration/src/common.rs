@@ -254,10 +254,16 @@ pub async fn start_wallet_server<A: AttributeService + Send + Sync + 'static>(se
         .await
         .unwrap();
     let issuance_sessions = disclosure_sessions.clone_into();
+    let health_checkers = vec![disclosure_sessions.health_checker()];
     tokio::spawn(async move {
-        if let Err(error) =
-            wallet_server::server::wallet_server::serve(attr_service, settings, disclosure_sessions, issuance_sessions)
-                .await
+        if let Err(error) = wallet_server::server::wallet_server::serve(
+            attr_service,
+            settings,
+            disclosure_sessions,
+            issuance_sessions,
+            health_checkers,
+        )
+        .await
         {
             println!("Could not start wallet_server: {:?}", error);
 
@@ -350,6 +356,8 @@ pub async fn do_pid_issuance(mut wallet: WalletWithMocks, pin: String) -> Wallet
     wallet
 }
 
+const MOCK_BSN: &str = "999991772";
+
 pub struct MockAttributeService(pub IndexMap<String, x509::Certificate>);
 
 impl AttributeService for MockAttributeService {
@@ -360,8 +368,16 @@ impl AttributeService for MockAttributeService {
         _session: &SessionState<Created>,
         _token_request: TokenRequest,
     ) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
+        self.refreshed_attributes(MOCK_BSN).await
+    }
+
+    fn refresh_subject(&self, _attestation_previews: &[AttestationPreview]) -> Option<String> {
+        Some(MOCK_BSN.to_string())
+    }
+
+    async fn refreshed_attributes(&self, subject: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Self::Error> {
         let attributes = MockAttributesLookup::default()
-            .attributes("999991772")
+            .attributes(subject)
             .unwrap()
             .into_iter()
             .map(|unsigned_mdoc| AttestationPreview::MsoMdoc {
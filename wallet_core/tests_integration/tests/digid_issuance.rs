@@ -1,5 +1,5 @@
 use openid4vc::{
-    issuance_session::{HttpIssuanceSession, HttpVcMessageClient, IssuanceSession},
+    issuance_session::{AcceptedIssuance, HttpIssuanceSession, HttpVcMessageClient, IssuanceSession},
     oidc::HttpOidcClient,
 };
 
@@ -30,12 +30,14 @@ use wallet_server::pid::{attributes::BrpPidAttributeService, brp::client::HttpBr
 #[tokio::test]
 async fn test_pid_issuance_digid_bridge() {
     let settings = wallet_server_settings();
+    let digid = settings.issuer.digid.as_ref().unwrap();
     let attr_service = BrpPidAttributeService::new(
-        HttpBrpClient::new(settings.issuer.brp_server.clone()),
-        settings.issuer.digid.issuer_url.clone(),
-        settings.issuer.digid.bsn_privkey.clone(),
-        settings.issuer.digid.trust_anchors.clone(),
+        HttpBrpClient::new(settings.issuer.brp_server.clone().unwrap()),
+        digid.issuer_url.clone(),
+        digid.bsn_privkey.clone(),
+        digid.trust_anchors.clone(),
         settings.issuer.certificates(),
+        settings.issuer.refresh_subject_key.as_ref().unwrap().into(),
     )
     .unwrap();
     start_wallet_server(settings.clone(), attr_service).await;
@@ -74,7 +76,7 @@ async fn test_pid_issuance_digid_bridge() {
     .await
     .unwrap();
 
-    let mdocs = pid_issuer_client
+    let accepted = pid_issuer_client
         .accept_issuance(
             &trust_anchors(&default_configuration()),
             SoftwareKeyFactory::default(),
@@ -82,6 +84,9 @@ async fn test_pid_issuance_digid_bridge() {
         )
         .await
         .unwrap();
+    let AcceptedIssuance::Issued { mdocs, .. } = accepted else {
+        panic!("PID issuance should not be deferred")
+    };
 
     assert_eq!(2, mdocs.len());
     assert_eq!(2, mdocs[0].cred_copies.len())
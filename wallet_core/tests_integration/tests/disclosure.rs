@@ -108,6 +108,9 @@ async fn test_disclosure_usecases_ok(
         // The setup script is hardcoded to include "http://localhost:3004/" in the `ReaderRegistration`
         // contained in the certificate, so we have to specify a return URL prefixed with that.
         return_url_template,
+        callback_url: None,
+        expiration_seconds: None,
+        correlation_id: None,
     };
 
     let digid_context = MockDigidSession::start_context();
@@ -118,6 +121,7 @@ async fn test_disclosure_usecases_ok(
             Ok(TokenRequest {
                 grant_type: openid4vc::token::TokenRequestGrantType::PreAuthorizedCode {
                     pre_authorized_code: utils::random_string(32).into(),
+                    tx_code: None,
                 },
                 code_verifier: Some("my_code_verifier".to_string()),
                 client_id: Some("my_client_id".to_string()),
@@ -283,6 +287,9 @@ async fn test_disclosure_without_pid() {
         }]
         .into(),
         return_url_template: None,
+        callback_url: None,
+        expiration_seconds: None,
+        correlation_id: None,
     };
     let response = client
         .post(ws_internal_url.join("disclosure/sessions"))
@@ -338,7 +345,7 @@ async fn test_disclosure_without_pid() {
             ..
         } if attrs
             .iter()
-            .flat_map(|attr| attr.attributes.keys().map(|k| k.to_owned()).collect::<Vec<&str>>())
+            .flat_map(|attr| attr.attributes.keys().map(String::as_str).collect::<Vec<&str>>())
             .collect::<Vec<&str>>() == vec!["given_name", "family_name"]
     );
 
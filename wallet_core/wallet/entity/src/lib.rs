@@ -1,3 +1,5 @@
+pub mod deletion_history_event;
+pub mod deletion_history_event_doc_type;
 pub mod disclosure_history_event;
 pub mod disclosure_history_event_doc_type;
 pub mod history_doc_type;
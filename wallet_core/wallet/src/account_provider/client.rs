@@ -6,7 +6,10 @@ use url::Url;
 use wallet_common::{
     account::{
         messages::{
-            auth::{Certificate, Challenge, Registration, WalletCertificate},
+            auth::{
+                Certificate, Challenge, Registration, WalletAttestation, WalletAttestationRequestMessage,
+                WalletAttestationResponse, WalletCertificate,
+            },
             errors::{AccountError, AccountErrorType},
             instructions::{
                 Instruction, InstructionChallengeRequestMessage, InstructionEndpoint, InstructionResult,
@@ -160,6 +163,17 @@ impl AccountProviderClient for HttpAccountProviderClient {
 
         Ok(message.result)
     }
+
+    async fn wallet_attestation(
+        &self,
+        base_url: &BaseUrl,
+        attestation_request: WalletAttestationRequestMessage,
+    ) -> Result<WalletAttestation, AccountProviderError> {
+        let url = base_url.join("wallet_attestation");
+        let response: WalletAttestationResponse = self.send_json_post_request(url, &attestation_request).await?;
+
+        Ok(response.attestation)
+    }
 }
 
 #[cfg(test)]
@@ -7,7 +7,7 @@ use error_category::ErrorCategory;
 use wallet_common::{
     account::{
         messages::{
-            auth::{Registration, WalletCertificate},
+            auth::{Registration, WalletAttestation, WalletAttestationRequestMessage, WalletCertificate},
             errors::{AccountError, AccountErrorType},
             instructions::{Instruction, InstructionChallengeRequestMessage, InstructionEndpoint, InstructionResult},
         },
@@ -67,4 +67,10 @@ pub trait AccountProviderClient {
     ) -> Result<InstructionResult<I::Result>, AccountProviderError>
     where
         I: InstructionEndpoint + 'static;
+
+    async fn wallet_attestation(
+        &self,
+        base_url: &BaseUrl,
+        attestation_request: WalletAttestationRequestMessage,
+    ) -> Result<WalletAttestation, AccountProviderError>;
 }
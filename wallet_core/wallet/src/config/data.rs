@@ -6,8 +6,8 @@ use reqwest::Certificate;
 
 use wallet_common::{
     config::wallet_config::{
-        AccountServerConfiguration, DisclosureConfiguration, LockTimeoutConfiguration, PidIssuanceConfiguration,
-        WalletConfiguration,
+        AccountServerConfiguration, DisclosureConfiguration, LockTimeoutConfiguration, MdocReplenishmentConfiguration,
+        PidIssuanceConfiguration, WalletConfiguration,
     },
     trust_anchor::DerTrustAnchor,
     urls::{BaseUrl, DEFAULT_UNIVERSAL_LINK_BASE},
@@ -156,5 +156,8 @@ pub fn default_configuration() -> WalletConfiguration {
             rp_trust_anchors: parse_trust_anchors(config_default!(RP_TRUST_ANCHORS)),
         },
         mdoc_trust_anchors: parse_trust_anchors(config_default!(MDOC_TRUST_ANCHORS)),
+        require_signed_issuer_metadata: false,
+        send_wallet_attestation: false,
+        mdoc_replenishment: MdocReplenishmentConfiguration::default(),
     }
 }
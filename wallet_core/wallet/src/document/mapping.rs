@@ -1,138 +1,271 @@
 use std::{collections::HashMap, sync::LazyLock};
 
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use nl_wallet_mdoc::{DataElementIdentifier, NameSpace};
+use openid4vc::metadata::{CredentialFormat, CredentialMetadata, MsoMdocClaim};
 
 use super::{mdoc::AttributeValueType, AttributeKey, AttributeLabels, ADDRESS_DOCTYPE, PID_DOCTYPE};
 
-#[derive(Debug, Clone)]
-pub(super) struct DataElementValueMapping {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataElementValueMapping {
     pub key: AttributeKey,
     pub is_mandatory: bool,
     pub key_labels: AttributeLabels,
-    pub value_type: AttributeValueType,
+    /// The type of the attribute value. If absent, the type is inferred from the value itself.
+    pub value_type: Option<AttributeValueType>,
+}
+
+pub type AttributeMapping = IndexMap<(NameSpace, DataElementIdentifier), DataElementValueMapping>;
+
+/// The attribute mappings of doc types, derived from the metadata of a single issuer of these doc types. Doc types
+/// that have a built-in mapping in [`MDOC_DOCUMENT_MAPPING`] always use that mapping, so these are never included.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentMappings(#[serde_as(as = "HashMap<_, Vec<(_, _)>>")] HashMap<String, AttributeMapping>);
+
+impl DocumentMappings {
+    /// Derive the attribute mappings of the specified mdoc doc types from the credential metadata of an issuer. Doc
+    /// types that have a built-in mapping, as well as those for which the issuer does not describe any claims, are
+    /// skipped so that their built-in or fallback mapping is used.
+    pub fn from_credential_metadata<'a>(
+        metadata: impl IntoIterator<Item = &'a CredentialMetadata>,
+        doc_types: &[&str],
+    ) -> Self {
+        let mappings = metadata
+            .into_iter()
+            .filter_map(|metadata| match &metadata.format {
+                CredentialFormat::MsoMdoc { doctype, claims, order }
+                    if !claims.is_empty()
+                        && doc_types.contains(&doctype.as_str())
+                        && !MDOC_DOCUMENT_MAPPING.contains_key(doctype.as_str()) =>
+                {
+                    Some((doctype.clone(), attribute_mapping_from_claims(claims, order.as_deref())))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self(mappings)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add the mappings in `other` to these mappings, replacing existing mappings for the same doc types.
+    pub fn extend(&mut self, other: DocumentMappings) {
+        self.0.extend(other.0)
+    }
+
+    /// Get the attribute mapping for `doc_type`, preferring the built-in mapping over one derived from issuer
+    /// metadata. For unknown doc types an empty mapping is returned, which means that all of their attributes are
+    /// rendered using the fallback rendering.
+    pub(super) fn attribute_mapping(&self, doc_type: &str) -> &AttributeMapping {
+        static EMPTY_MAPPING: LazyLock<AttributeMapping> = LazyLock::new(AttributeMapping::new);
+
+        MDOC_DOCUMENT_MAPPING
+            .get(doc_type)
+            .or_else(|| self.0.get(doc_type))
+            .unwrap_or(&EMPTY_MAPPING)
+    }
+}
+
+/// Convert the claims of an mdoc doc type to an [`AttributeMapping`]. The claims are ordered as specified by the
+/// issuer, followed by any claims that are absent from that order sorted by name space and name.
+fn attribute_mapping_from_claims(
+    claims: &HashMap<NameSpace, HashMap<DataElementIdentifier, MsoMdocClaim>>,
+    order: Option<&[String]>,
+) -> AttributeMapping {
+    let position = |name_space: &str, name: &str| {
+        order
+            .and_then(|order| {
+                order
+                    .iter()
+                    .position(|entry| entry.split_once('~') == Some((name_space, name)))
+            })
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut claims = claims
+        .iter()
+        .flat_map(|(name_space, claims)| claims.iter().map(move |(name, claim)| (name_space, name, claim)))
+        .collect::<Vec<_>>();
+    claims.sort_by_cached_key(|(name_space, name, _)| (position(name_space, name), *name_space, *name));
+
+    claims
+        .into_iter()
+        .map(|(name_space, name, claim)| {
+            let value_mapping = DataElementValueMapping {
+                key: name.clone(),
+                is_mandatory: claim.mandatory.unwrap_or_default(),
+                key_labels: claim_labels(name, claim),
+                value_type: claim
+                    .value_type
+                    .as_deref()
+                    .and_then(AttributeValueType::from_claim_value_type),
+            };
+
+            ((name_space.clone(), name.clone()), value_mapping)
+        })
+        .collect()
 }
 
-pub(super) type MappingNameSpace = &'static str;
-pub(super) type MappingDataElementIdentifier = &'static str;
-pub(super) type AttributeMapping = IndexMap<(MappingNameSpace, MappingDataElementIdentifier), DataElementValueMapping>;
+/// Get the labels of a claim from its display metadata, keyed by the language of their locale.
+fn claim_labels(name: &str, claim: &MsoMdocClaim) -> AttributeLabels {
+    let labels = claim
+        .display
+        .iter()
+        .flatten()
+        .filter_map(|display| {
+            let language = display
+                .locale
+                .as_deref()
+                .and_then(|locale| locale.split('-').next())
+                .unwrap_or_default();
+
+            display.name.clone().map(|name| (language.to_string(), name))
+        })
+        .collect::<AttributeLabels>();
+
+    if labels.is_empty() {
+        return fallback_labels(name);
+    }
 
-pub(super) type MappingDocType = &'static str;
-pub(super) type MdocDocumentMapping = HashMap<MappingDocType, AttributeMapping>;
+    labels
+}
+
+/// The labels of an attribute of which no display metadata is known, which consist of its name. This label does not
+/// have a language, so that it is used regardless of the language of the app.
+pub(super) fn fallback_labels(name: &str) -> AttributeLabels {
+    HashMap::from([(String::new(), name.to_string())])
+}
+
+fn labels(labels: [(&str, &str); 2]) -> AttributeLabels {
+    labels
+        .into_iter()
+        .map(|(language, label)| (language.to_string(), label.to_string()))
+        .collect()
+}
 
-pub(super) static MDOC_DOCUMENT_MAPPING: LazyLock<MdocDocumentMapping> = LazyLock::new(|| {
+/// The built-in mappings of the doc types that are known to the wallet, which are used regardless of any metadata
+/// that their issuer provides about them.
+static MDOC_DOCUMENT_MAPPING: LazyLock<HashMap<&str, AttributeMapping>> = LazyLock::new(|| {
     HashMap::from([
         (
             PID_DOCTYPE,
             IndexMap::from([
                 (
-                    (PID_DOCTYPE, "given_name"),
+                    (PID_DOCTYPE.to_string(), "given_name".to_string()),
                     DataElementValueMapping {
-                        key: "given_name",
+                        key: "given_name".to_string(),
                         is_mandatory: true,
-                        key_labels: HashMap::from([("en", "First names"), ("nl", "Voornamen")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "First names"), ("nl", "Voornamen")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "family_name"),
+                    (PID_DOCTYPE.to_string(), "family_name".to_string()),
                     DataElementValueMapping {
-                        key: "family_name",
+                        key: "family_name".to_string(),
                         is_mandatory: true,
-                        key_labels: HashMap::from([("en", "Surname"), ("nl", "Achternaam")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Surname"), ("nl", "Achternaam")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "given_name_birth"),
+                    (PID_DOCTYPE.to_string(), "given_name_birth".to_string()),
                     DataElementValueMapping {
-                        key: "given_name_birth",
+                        key: "given_name_birth".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "First names at birth"), ("nl", "Voornamen bij geboorte")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "First names at birth"), ("nl", "Voornamen bij geboorte")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "family_name_birth"),
+                    (PID_DOCTYPE.to_string(), "family_name_birth".to_string()),
                     DataElementValueMapping {
-                        key: "family_name_birth",
+                        key: "family_name_birth".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Birth name"), ("nl", "Geboortenaam")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Birth name"), ("nl", "Geboortenaam")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "gender"),
+                    (PID_DOCTYPE.to_string(), "gender".to_string()),
                     DataElementValueMapping {
-                        key: "gender",
+                        key: "gender".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Gender"), ("nl", "Geslacht")]),
-                        value_type: AttributeValueType::Gender,
+                        key_labels: labels([("en", "Gender"), ("nl", "Geslacht")]),
+                        value_type: Some(AttributeValueType::Gender),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "birth_date"),
+                    (PID_DOCTYPE.to_string(), "birth_date".to_string()),
                     DataElementValueMapping {
-                        key: "birth_date",
+                        key: "birth_date".to_string(),
                         is_mandatory: true,
-                        key_labels: HashMap::from([("en", "Birth date"), ("nl", "Geboortedatum")]),
-                        value_type: AttributeValueType::Date,
+                        key_labels: labels([("en", "Birth date"), ("nl", "Geboortedatum")]),
+                        value_type: Some(AttributeValueType::Date),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "age_over_18"),
+                    (PID_DOCTYPE.to_string(), "age_over_18".to_string()),
                     DataElementValueMapping {
-                        key: "age_over_18",
+                        key: "age_over_18".to_string(),
                         is_mandatory: true,
-                        key_labels: HashMap::from([("en", "Older than 18"), ("nl", "Ouder dan 18")]),
-                        value_type: AttributeValueType::Bool,
+                        key_labels: labels([("en", "Older than 18"), ("nl", "Ouder dan 18")]),
+                        value_type: Some(AttributeValueType::Bool),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "birth_place"),
+                    (PID_DOCTYPE.to_string(), "birth_place".to_string()),
                     DataElementValueMapping {
-                        key: "birth_place",
+                        key: "birth_place".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Place of birth"), ("nl", "Geboorteplaats")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Place of birth"), ("nl", "Geboorteplaats")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "birth_city"),
+                    (PID_DOCTYPE.to_string(), "birth_city".to_string()),
                     DataElementValueMapping {
-                        key: "birth_city",
+                        key: "birth_city".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "City, town or village of birth"), ("nl", "Geboortestad")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "City, town or village of birth"), ("nl", "Geboortestad")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "birth_state"),
+                    (PID_DOCTYPE.to_string(), "birth_state".to_string()),
                     DataElementValueMapping {
-                        key: "birth_state",
+                        key: "birth_state".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([
+                        key_labels: labels([
                             ("en", "State or province of birth"),
                             ("nl", "Geboortestaat of -provincie"),
                         ]),
-                        value_type: AttributeValueType::String,
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "birth_country"),
+                    (PID_DOCTYPE.to_string(), "birth_country".to_string()),
                     DataElementValueMapping {
-                        key: "birth_country",
+                        key: "birth_country".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Country of birth"), ("nl", "Geboorteland")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Country of birth"), ("nl", "Geboorteland")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (PID_DOCTYPE, "bsn"),
+                    (PID_DOCTYPE.to_string(), "bsn".to_string()),
                     DataElementValueMapping {
-                        key: "bsn",
+                        key: "bsn".to_string(),
                         is_mandatory: true,
-                        key_labels: HashMap::from([("en", "BSN"), ("nl", "BSN")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "BSN"), ("nl", "BSN")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
             ]),
@@ -141,66 +274,66 @@ pub(super) static MDOC_DOCUMENT_MAPPING: LazyLock<MdocDocumentMapping> = LazyLoc
             ADDRESS_DOCTYPE,
             IndexMap::from([
                 (
-                    (ADDRESS_DOCTYPE, "resident_address"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_address".to_string()),
                     DataElementValueMapping {
-                        key: "resident_address",
+                        key: "resident_address".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Address"), ("nl", "Adres")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Address"), ("nl", "Adres")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_street"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_street".to_string()),
                     DataElementValueMapping {
-                        key: "resident_street",
+                        key: "resident_street".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Street"), ("nl", "Straatnaam")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Street"), ("nl", "Straatnaam")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_house_number"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_house_number".to_string()),
                     DataElementValueMapping {
-                        key: "resident_house_number",
+                        key: "resident_house_number".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "House number"), ("nl", "Huisnummer")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "House number"), ("nl", "Huisnummer")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_postal_code"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_postal_code".to_string()),
                     DataElementValueMapping {
-                        key: "resident_postal_code",
+                        key: "resident_postal_code".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Postal code"), ("nl", "Postcode")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Postal code"), ("nl", "Postcode")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_city"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_city".to_string()),
                     DataElementValueMapping {
-                        key: "resident_city",
+                        key: "resident_city".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "City, town or village"), ("nl", "Woonplaats")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "City, town or village"), ("nl", "Woonplaats")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_state"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_state".to_string()),
                     DataElementValueMapping {
-                        key: "resident_state",
+                        key: "resident_state".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "State or province"), ("nl", "Staat of provincie")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "State or province"), ("nl", "Staat of provincie")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
                 (
-                    (ADDRESS_DOCTYPE, "resident_country"),
+                    (ADDRESS_DOCTYPE.to_string(), "resident_country".to_string()),
                     DataElementValueMapping {
-                        key: "resident_country",
+                        key: "resident_country".to_string(),
                         is_mandatory: false,
-                        key_labels: HashMap::from([("en", "Country"), ("nl", "Land")]),
-                        value_type: AttributeValueType::String,
+                        key_labels: labels([("en", "Country"), ("nl", "Land")]),
+                        value_type: Some(AttributeValueType::String),
                     },
                 ),
             ]),
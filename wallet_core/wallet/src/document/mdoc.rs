@@ -1,7 +1,8 @@
 use chrono::NaiveDate;
-use ciborium::value::Integer;
+use ciborium::{value::Integer, Value};
 use indexmap::IndexMap;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use error_category::ErrorCategory;
 use nl_wallet_mdoc::{
@@ -16,17 +17,17 @@ use nl_wallet_mdoc::{
 };
 
 use super::{
-    mapping::{AttributeMapping, DataElementValueMapping, MappingDocType, MDOC_DOCUMENT_MAPPING},
+    mapping::{self, DataElementValueMapping, DocumentMappings},
     Attribute, AttributeValue, DisclosureDocument, Document, DocumentAttributes, DocumentPersistence,
     GenderAttributeValue, MissingDisclosureAttributes, PID_DOCTYPE,
 };
 
+/// The CBOR tag of a full-date, see RFC 8943.
+const FULL_DATE_TAG: u64 = 1004;
+
 #[derive(Debug, thiserror::Error, ErrorCategory)]
 #[category(pd)]
 pub enum DocumentMdocError {
-    #[error("unknown doc type \"{doc_type}\"")]
-    #[category(critical)]
-    UnknownDocType { doc_type: String },
     #[error("mandatory attributes for \"{doc_type}\" not found at \"{name_space} / {name}\"")]
     #[category(critical)]
     MissingAttribute {
@@ -45,12 +46,15 @@ pub enum DocumentMdocError {
         expected_type: AttributeValueType,
         value: DataElementValue,
     },
-    #[error("unknown attribute for \"{doc_type}\" encounted at \"{name_space} / {name}\": {value:?}")]
-    UnknownAttribute {
+    #[error(
+        "attribute for \"{doc_type}\" encountered at \"{name_space} / {name}\" has a value of which the type cannot \
+         be displayed: {value:?}"
+    )]
+    UnsupportedAttributeValue {
         doc_type: String,
         name_space: NameSpace,
         name: DataElementIdentifier,
-        value: Option<DataElementValue>,
+        value: DataElementValue,
     },
     #[error("certificate error for \"{doc_type}\": {error}")]
     #[category(defer)]
@@ -61,7 +65,8 @@ pub enum DocumentMdocError {
     },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AttributeValueType {
     String,
     Bool,
@@ -69,11 +74,25 @@ pub enum AttributeValueType {
     Gender,
 }
 
+impl AttributeValueType {
+    /// Interpret the `value_type` of a claim in the credential metadata of an issuer. Types that the wallet has no
+    /// specific support for result in `None`, in which case the type is inferred from the attribute value.
+    pub(super) fn from_claim_value_type(value_type: &str) -> Option<Self> {
+        match value_type {
+            "string" => Some(Self::String),
+            "bool" | "boolean" => Some(Self::Bool),
+            "date" | "full-date" => Some(Self::Date),
+            _ => None,
+        }
+    }
+}
+
 // TODO: Think about refactoring/renaming DisclosureType. We currently have
 // DisclosureType here, *and* in disclosure_history_event.rs, EventType, *and*
 // in flutter_api's disclosure.rs again as DisclosureType. Things to think about
 // when refactoring: why this many and not just one.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DisclosureType {
     Login,
     Regular,
@@ -108,30 +127,19 @@ impl DisclosureType {
     }
 }
 
-/// Get the correct `AttributeMapping` or return an error if it cannot be found for the `doc_type`.
-fn mapping_for_doc_type(doc_type: &str) -> Result<(MappingDocType, &'static AttributeMapping), DocumentMdocError> {
-    let (doc_type, attribute_mapping) =
-        MDOC_DOCUMENT_MAPPING
-            .get_key_value(doc_type)
-            .ok_or_else(|| DocumentMdocError::UnknownDocType {
-                doc_type: doc_type.to_string(),
-            })?;
-
-    Ok((*doc_type, attribute_mapping))
-}
-
 fn document_attributes_from_mdoc_attributes(
     doc_type: &str,
     mut attributes: IndexMap<NameSpace, Vec<Entry>>,
+    mappings: &DocumentMappings,
     error_on_missing: bool,
-) -> Result<(MappingDocType, DocumentAttributes), DocumentMdocError> {
-    let (doc_type, attribute_mapping) = mapping_for_doc_type(doc_type)?;
+) -> Result<DocumentAttributes, DocumentMdocError> {
+    let attribute_mapping = mappings.attribute_mapping(doc_type);
 
     // Loop through the attributes in the mapping in order and find
     // the corresponding entry in the input attributes, based on the
     // name space and the entry name. If found, move the entry value
     // out of the input attributes and try to convert it to an `Attribute`.
-    let document_attributes = attribute_mapping
+    let mut document_attributes = attribute_mapping
         .iter()
         // Loop through the all the mapped attributes in order and remove any
         // returned instances of `None` for non-mandatory attributes.
@@ -139,12 +147,12 @@ fn document_attributes_from_mdoc_attributes(
             // Get a mutable reference to the `Vec<Entry>` for the name space,
             // then find the index within the vector for the entry that has the
             // matching name. If found, remove the `Entry` at that index so that
-            // we have ownership over it.
-            let entry = attributes.get_mut(*name_space).and_then(|entries| {
+            // we have ownership over it, while keeping the remaining entries in order.
+            let entry = attributes.get_mut(name_space).and_then(|entries| {
                 entries
                     .iter()
                     .position(|entry| entry.name == *element_id)
-                    .map(|index| entries.swap_remove(index))
+                    .map(|index| entries.remove(index))
             });
 
             // If the entry is not found in the mdoc attributes, but it is not
@@ -159,51 +167,59 @@ fn document_attributes_from_mdoc_attributes(
             let attribute_result = entry
                 .ok_or_else(|| DocumentMdocError::MissingAttribute {
                     doc_type: doc_type.to_string(),
-                    name_space: (*name_space).to_string(),
-                    name: (*element_id).to_string(),
+                    name_space: name_space.clone(),
+                    name: element_id.clone(),
                 })
                 .and_then(|entry| {
                     // If the entry is found, try to to convert it to a document
                     // attribute, which could also result in an error.
                     let Entry { name, value } = entry;
 
-                    Attribute::try_from((value, value_mapping)).map_err(|value| {
-                        DocumentMdocError::AttributeValueTypeMismatch {
+                    Attribute::try_from((value, value_mapping)).map_err(|value| match value_mapping.value_type {
+                        Some(expected_type) => DocumentMdocError::AttributeValueTypeMismatch {
                             doc_type: doc_type.to_string(),
-                            name_space: (*name_space).to_string(),
+                            name_space: name_space.clone(),
                             name,
-                            expected_type: value_mapping.value_type,
+                            expected_type,
                             value,
-                        }
+                        },
+                        None => DocumentMdocError::UnsupportedAttributeValue {
+                            doc_type: doc_type.to_string(),
+                            name_space: name_space.clone(),
+                            name,
+                            value,
+                        },
                     })
                 })
                 // Finally, make sure the attribute is returned with the key,
                 // so that we can create an `IndexMap<>` for it.
-                .map(|attribute| (value_mapping.key, attribute));
+                .map(|attribute| (value_mapping.key.clone(), attribute));
 
             Some(attribute_result)
         })
-        .collect::<Result<_, _>>()?;
-
-    // Find the first remaining mdoc attributes and convert it to an error.
-    let unknown_error = attributes
-        .into_iter()
-        .flat_map(|(name_space, mut entries)| {
-            entries.pop().map(|entry| DocumentMdocError::UnknownAttribute {
-                doc_type: doc_type.to_string(),
-                name_space,
-                name: entry.name,
-                value: entry.value.into(),
-            })
-        })
-        .next();
+        .collect::<Result<DocumentAttributes, _>>()?;
+
+    // Any remaining mdoc attributes are not described by the mapping. Render these after the mapped attributes,
+    // using their name as label and inferring the type of their value.
+    for (name_space, entries) in attributes {
+        for Entry { name, value } in entries {
+            let value =
+                AttributeValue::try_from(value).map_err(|value| DocumentMdocError::UnsupportedAttributeValue {
+                    doc_type: doc_type.to_string(),
+                    name_space: name_space.clone(),
+                    name: name.clone(),
+                    value,
+                })?;
+            let attribute = Attribute {
+                key_labels: mapping::fallback_labels(&name),
+                value,
+            };
 
-    // Return the error if at least one mdoc attributes still remained.
-    if let Some(missing_error) = unknown_error {
-        return Err(missing_error);
+            document_attributes.entry(name).or_insert(attribute);
+        }
     }
 
-    Ok((doc_type, document_attributes))
+    Ok(document_attributes)
 }
 
 impl Document {
@@ -212,12 +228,13 @@ impl Document {
         doc_type: &str,
         attributes: IndexMap<NameSpace, Vec<Entry>>,
         issuer_registration: IssuerRegistration,
+        mappings: &DocumentMappings,
     ) -> Result<Self, DocumentMdocError> {
-        let (doc_type, document_attributes) = document_attributes_from_mdoc_attributes(doc_type, attributes, true)?;
+        let document_attributes = document_attributes_from_mdoc_attributes(doc_type, attributes, mappings, true)?;
 
         let document = Document {
             persistence,
-            doc_type,
+            doc_type: doc_type.to_string(),
             attributes: document_attributes,
             issuer_registration,
         };
@@ -228,12 +245,14 @@ impl Document {
     pub(crate) fn from_unsigned_mdoc(
         mdoc: UnsignedMdoc,
         issuer_registration: IssuerRegistration,
+        mappings: &DocumentMappings,
     ) -> Result<Self, DocumentMdocError> {
         Document::from_mdoc_attributes(
             DocumentPersistence::InMemory,
             &mdoc.doc_type,
             mdoc.attributes.into_inner(),
             issuer_registration,
+            mappings,
         )
     }
 }
@@ -242,7 +261,10 @@ impl TryFrom<(DataElementValue, &DataElementValueMapping)> for Attribute {
     type Error = DataElementValue;
 
     fn try_from((value, value_mapping): (DataElementValue, &DataElementValueMapping)) -> Result<Self, Self::Error> {
-        let value = (value_mapping.value_type, value).try_into()?;
+        let value = match value_mapping.value_type {
+            Some(value_type) => (value_type, value).try_into()?,
+            None => value.try_into()?,
+        };
 
         let attribute = Attribute {
             key_labels: value_mapping.key_labels.clone(),
@@ -275,6 +297,28 @@ impl TryFrom<(AttributeValueType, DataElementValue)> for AttributeValue {
     }
 }
 
+/// Infer the type of an attribute value of which the type is not known from the mapping. Values of a type that
+/// cannot be displayed as one of the [`AttributeValue`] variants are returned as error.
+impl TryFrom<DataElementValue> for AttributeValue {
+    type Error = DataElementValue;
+
+    fn try_from(value: DataElementValue) -> Result<Self, Self::Error> {
+        match value {
+            Value::Text(s) => Ok(Self::String(s)),
+            Value::Bool(b) => Ok(Self::Boolean(b)),
+            Value::Integer(i) => Ok(Self::String(i128::from(i).to_string())),
+            Value::Float(f) => Ok(Self::String(f.to_string())),
+            Value::Tag(FULL_DATE_TAG, ref tagged) => match tagged.as_ref() {
+                Value::Text(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(Self::Date)
+                    .map_err(|_| value),
+                _ => Err(value),
+            },
+            _ => Err(value),
+        }
+    }
+}
+
 impl TryFrom<Integer> for GenderAttributeValue {
     type Error = ();
 
@@ -291,38 +335,41 @@ impl TryFrom<Integer> for GenderAttributeValue {
 
 impl MissingDisclosureAttributes {
     // Use the Mdoc document mapping to translate a `Vec<AttributeIdentifier>` to
-    // a `Vec<MissingDisclosureAttributes>`. If any attribute cannot be found a
-    // `DocumentMdocError` is returned.
+    // a `Vec<MissingDisclosureAttributes>`. Attributes that are not described by
+    // the mapping are labeled using their name.
     pub(crate) fn from_mdoc_missing_attributes(
         missing_attributes: Vec<AttributeIdentifier>,
-    ) -> Result<Vec<Self>, DocumentMdocError> {
+        mappings: &DocumentMappings,
+    ) -> Vec<Self> {
         // Create an `IndexMap` that contains `IndexMap`s of attributes per doc type.
-        let attributes_by_doc_type =
-            missing_attributes
-                .into_iter()
-                .try_fold(IndexMap::<_, IndexMap<_, _>>::new(), {
-                    |mut attributes_by_doc_type, missing_attribute| {
-                        let (doc_type, attribute_mapping) = mapping_for_doc_type(missing_attribute.doc_type.as_str())?;
-                        let value_mapping = attribute_mapping
-                            .get(&(
-                                missing_attribute.namespace.as_str(),
-                                missing_attribute.attribute.as_str(),
-                            ))
-                            .ok_or_else(|| DocumentMdocError::UnknownAttribute {
-                                doc_type: missing_attribute.doc_type,
-                                name_space: missing_attribute.namespace.clone(),
-                                name: missing_attribute.attribute.clone(),
-                                value: None,
-                            })?;
-
-                        attributes_by_doc_type
-                            .entry(doc_type)
-                            .or_default()
-                            .insert(value_mapping.key, value_mapping.key_labels.clone());
-
-                        Ok(attributes_by_doc_type)
+        let attributes_by_doc_type = missing_attributes.into_iter().fold(
+            IndexMap::<_, IndexMap<_, _>>::new(),
+            |mut attributes_by_doc_type, missing_attribute| {
+                let AttributeIdentifier {
+                    doc_type,
+                    namespace,
+                    attribute,
+                } = missing_attribute;
+
+                let (key, key_labels) = match mappings
+                    .attribute_mapping(&doc_type)
+                    .get(&(namespace, attribute.clone()))
+                {
+                    Some(value_mapping) => (value_mapping.key.clone(), value_mapping.key_labels.clone()),
+                    None => {
+                        let key_labels = mapping::fallback_labels(&attribute);
+                        (attribute, key_labels)
                     }
-                })?;
+                };
+
+                attributes_by_doc_type
+                    .entry(doc_type)
+                    .or_default()
+                    .insert(key, key_labels);
+
+                attributes_by_doc_type
+            },
+        );
 
         // Convert these `IndexMap`s to a `Vec<MissingDisclosureAttributes>`.
         let mut missing_disclosure_attributes = attributes_by_doc_type
@@ -331,9 +378,9 @@ impl MissingDisclosureAttributes {
             .collect::<Vec<_>>();
 
         // Make sure that the resulting doc types are sorted canonically.
-        missing_disclosure_attributes.sort_by_key(|attributes| super::doc_type_priority(attributes.doc_type));
+        missing_disclosure_attributes.sort_by_key(|attributes| super::doc_type_priority(&attributes.doc_type));
 
-        Ok(missing_disclosure_attributes)
+        missing_disclosure_attributes
     }
 }
 
@@ -341,6 +388,7 @@ impl DisclosureDocument {
     pub(crate) fn from_mdoc_attributes(
         doc_type: &str,
         attributes: ProposedDocumentAttributes,
+        mappings: &DocumentMappings,
     ) -> Result<Self, DocumentMdocError> {
         let issuer_registration = IssuerRegistration::from_certificate(&attributes.issuer)
             .map_err(|error| DocumentMdocError::Certificate {
@@ -348,12 +396,12 @@ impl DisclosureDocument {
                 error,
             })?
             .expect("IssuerRegistration must exist after successful issuance");
-        let (doc_type, document_attributes) =
-            document_attributes_from_mdoc_attributes(doc_type, attributes.attributes, false)?;
+        let document_attributes =
+            document_attributes_from_mdoc_attributes(doc_type, attributes.attributes, mappings, false)?;
 
         let document = DisclosureDocument {
             issuer_registration,
-            doc_type,
+            doc_type: doc_type.to_string(),
             attributes: document_attributes,
         };
 
@@ -368,8 +416,10 @@ pub mod tests {
     use assert_matches::assert_matches;
     use chrono::{Days, Utc};
     use rstest::rstest;
+    use serde_json::json;
 
     use nl_wallet_mdoc::{server_keys::KeyPair, Tdate};
+    use openid4vc::metadata::{CredentialFormat, CredentialMetadata};
 
     use super::{
         super::{ADDRESS_DOCTYPE, PID_DOCTYPE},
@@ -532,8 +582,12 @@ pub mod tests {
     fn test_minimal_unsigned_mdoc_to_document_mapping() {
         let unsigned_mdoc = create_minimal_unsigned_pid_mdoc();
 
-        let document = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock())
-            .expect("Could not convert minimal mdoc to document");
+        let document = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert minimal mdoc to document");
 
         assert_matches!(document.persistence, DocumentPersistence::InMemory);
         assert_eq!(document.doc_type, PID_DOCTYPE);
@@ -546,7 +600,10 @@ pub mod tests {
             Attribute {
                 key_labels,
                 value: AttributeValue::String(given_name),
-            } if key_labels == &HashMap::from([("en", "First names"), ("nl", "Voornamen")]) &&
+            } if key_labels == &HashMap::from([
+                ("en".to_string(), "First names".to_string()),
+                ("nl".to_string(), "Voornamen".to_string())
+            ]) &&
                  given_name == "Willeke Liselotte"
         );
         assert_matches!(
@@ -583,8 +640,12 @@ pub mod tests {
     fn test_full_unsigned_mdoc_to_document_mapping() {
         let unsigned_mdoc = create_full_unsigned_pid_mdoc();
 
-        let document = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock())
-            .expect("Could not convert full mdoc to document");
+        let document = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert full mdoc to document");
 
         assert_matches!(
             document.attributes.get("gender").unwrap(),
@@ -596,16 +657,97 @@ pub mod tests {
     }
 
     #[test]
-    fn test_unsigned_mdoc_to_document_mapping_doc_type_error() {
-        // Test changing the doc_type.
+    fn test_unsigned_mdoc_to_document_mapping_unknown_doc_type() {
+        // Test changing the doc_type, all attributes should be rendered using the fallback rendering.
         let mut unsigned_mdoc = create_minimal_unsigned_pid_mdoc();
         unsigned_mdoc.doc_type = "com.example.foobar".to_string();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let document = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert mdoc with unknown doc_type to document");
 
+        assert_eq!(document.doc_type, "com.example.foobar");
+        assert_eq!(
+            document.attributes.keys().cloned().collect::<Vec<_>>(),
+            vec!["bsn", "family_name", "given_name", "birth_date", "age_over_18"]
+        );
         assert_matches!(
-            result,
-            Err(DocumentMdocError::UnknownDocType { doc_type }) if doc_type == "com.example.foobar"
+            document.attributes.get("given_name").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::String(given_name),
+            } if key_labels == &HashMap::from([("".to_string(), "given_name".to_string())]) &&
+                 given_name == "Willeke Liselotte"
+        );
+        assert_matches!(
+            document.attributes.get("age_over_18").unwrap(),
+            Attribute {
+                key_labels: _,
+                value: AttributeValue::Boolean(true),
+            }
+        );
+    }
+
+    #[test]
+    fn test_unsigned_mdoc_to_document_mapping_from_credential_metadata() {
+        let mut unsigned_mdoc = create_minimal_unsigned_pid_mdoc();
+        unsigned_mdoc.doc_type = "com.example.foobar".to_string();
+
+        // Describe only some of the claims in the metadata, the others should use the fallback rendering.
+        let metadata: CredentialMetadata = serde_json::from_value(json!({
+            "format": "mso_mdoc",
+            "doctype": "com.example.foobar",
+            "claims": {
+                PID_DOCTYPE: {
+                    "given_name": {
+                        "value_type": "string",
+                    },
+                    "birth_date": {
+                        "value_type": "full-date",
+                        "display": [{ "name": "Date of birth", "locale": "en-US" }],
+                    },
+                },
+            },
+        }))
+        .unwrap();
+
+        // Only the requested doc types should be included.
+        assert!(DocumentMappings::from_credential_metadata([&metadata], &["com.example.other"]).is_empty());
+
+        // Metadata describing a doc type with a built-in mapping should never be used.
+        let mut pid_metadata = metadata.clone();
+        let CredentialFormat::MsoMdoc { doctype, .. } = &mut pid_metadata.format else {
+            panic!("metadata should describe an mdoc");
+        };
+        *doctype = PID_DOCTYPE.to_string();
+        assert!(DocumentMappings::from_credential_metadata([&pid_metadata], &[PID_DOCTYPE]).is_empty());
+
+        let mappings = DocumentMappings::from_credential_metadata([&metadata], &["com.example.foobar"]);
+
+        let document = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock(), &mappings)
+            .expect("Could not convert mdoc to document using metadata");
+
+        assert_eq!(
+            document.attributes.keys().cloned().collect::<Vec<_>>(),
+            vec!["birth_date", "given_name", "bsn", "family_name", "age_over_18"]
+        );
+        assert_matches!(
+            document.attributes.get("birth_date").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::Date(birth_date),
+            } if key_labels == &HashMap::from([("en".to_string(), "Date of birth".to_string())]) &&
+                 birth_date == &NaiveDate::parse_from_str("1997-05-10", "%Y-%m-%d").unwrap()
+        );
+        assert_matches!(
+            document.attributes.get("given_name").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::String(_),
+            } if key_labels == &HashMap::from([("".to_string(), "given_name".to_string())])
         );
     }
 
@@ -617,7 +759,11 @@ pub mod tests {
         attributes.get_mut(PID_DOCTYPE).unwrap().pop();
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let result = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        );
 
         assert_matches!(
             result,
@@ -634,8 +780,12 @@ pub mod tests {
         attributes.get_mut(PID_DOCTYPE).unwrap().pop();
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        _ = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock())
-            .expect("Could not convert full mdoc to document");
+        _ = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert full mdoc to document");
     }
 
     #[test]
@@ -652,7 +802,11 @@ pub mod tests {
         );
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let result = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        );
 
         assert_matches!(
             result,
@@ -678,7 +832,11 @@ pub mod tests {
         );
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let result = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        );
 
         assert_matches!(
             result,
@@ -704,7 +862,11 @@ pub mod tests {
         );
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let result = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        );
 
         assert_matches!(
             result,
@@ -720,8 +882,8 @@ pub mod tests {
     }
 
     #[test]
-    fn test_unsigned_mdoc_to_document_mapping_unknown_attribute_error() {
-        // Test adding an unknown entry.
+    fn test_unsigned_mdoc_to_document_mapping_unknown_attribute() {
+        // Test adding an unknown entry, which should be rendered after the known attributes.
         let mut unsigned_mdoc = create_minimal_unsigned_pid_mdoc();
         let mut attributes = unsigned_mdoc.attributes.into_inner();
         attributes.get_mut(PID_DOCTYPE).unwrap().push(Entry {
@@ -730,17 +892,56 @@ pub mod tests {
         });
         unsigned_mdoc.attributes = attributes.try_into().unwrap();
 
-        let result = Document::from_unsigned_mdoc(unsigned_mdoc, IssuerRegistration::new_mock());
+        let document = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert mdoc with unknown attribute to document");
+
+        assert_eq!(
+            document.attributes.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                "given_name",
+                "family_name",
+                "birth_date",
+                "age_over_18",
+                "bsn",
+                "foobar"
+            ]
+        );
+        assert_matches!(
+            document.attributes.get("foobar").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::String(foobar),
+            } if key_labels == &HashMap::from([("".to_string(), "foobar".to_string())]) && foobar == "Foo Bar"
+        );
+
+        // Test adding an unknown entry with a value that cannot be displayed.
+        let mut unsigned_mdoc = create_minimal_unsigned_pid_mdoc();
+        let mut attributes = unsigned_mdoc.attributes.into_inner();
+        attributes.get_mut(PID_DOCTYPE).unwrap().push(Entry {
+            name: "foobar".to_string(),
+            value: DataElementValue::Array(vec![]),
+        });
+        unsigned_mdoc.attributes = attributes.try_into().unwrap();
+
+        let result = Document::from_unsigned_mdoc(
+            unsigned_mdoc,
+            IssuerRegistration::new_mock(),
+            &DocumentMappings::default(),
+        );
 
         assert_matches!(
             result,
-            Err(DocumentMdocError::UnknownAttribute {
+            Err(DocumentMdocError::UnsupportedAttributeValue {
                 doc_type,
                 name_space,
                 name,
                 value,
             }) if doc_type == PID_DOCTYPE && name_space == PID_DOCTYPE &&
-                  name == "foobar" && value == Some(DataElementValue::Text("Foo Bar".to_string()))
+                  name == "foobar" && value == DataElementValue::Array(vec![])
         );
     }
 
@@ -754,6 +955,7 @@ pub mod tests {
                 attributes: unsigned_mdoc.attributes.into_inner(),
                 issuer: ISSUER_KEY.certificate().clone(),
             },
+            &DocumentMappings::default(),
         )
         .expect("Could not convert attributes to proposed disclosure document");
 
@@ -767,7 +969,10 @@ pub mod tests {
             Attribute {
                 key_labels,
                 value: AttributeValue::String(given_name),
-            } if key_labels == &HashMap::from([("en", "First names"), ("nl", "Voornamen")]) &&
+            } if key_labels == &HashMap::from([
+                ("en".to_string(), "First names".to_string()),
+                ("nl".to_string(), "Voornamen".to_string())
+            ]) &&
                  given_name == "Willeke Liselotte"
         );
         assert_matches!(
@@ -817,6 +1022,7 @@ pub mod tests {
                 attributes,
                 issuer: ISSUER_KEY.certificate().clone(),
             },
+            &DocumentMappings::default(),
         )
         .expect("Could not convert attributes to proposed disclosure document");
 
@@ -835,7 +1041,7 @@ pub mod tests {
     }
 
     #[test]
-    fn test_mdoc_to_proposed_disclosure_document_mapping_error_unknown_doc_type() {
+    fn test_mdoc_to_proposed_disclosure_document_mapping_unknown_doc_type() {
         let attributes = IndexMap::from([(
             PID_DOCTYPE.to_string(),
             vec![Entry {
@@ -844,17 +1050,23 @@ pub mod tests {
             }],
         )]);
 
-        let result = DisclosureDocument::from_mdoc_attributes(
+        let disclosure_document = DisclosureDocument::from_mdoc_attributes(
             "com.example.foobar",
             ProposedDocumentAttributes {
                 attributes,
                 issuer: ISSUER_KEY.certificate().clone(),
             },
-        );
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert attributes with unknown doc_type to proposed disclosure document");
 
+        assert_eq!(disclosure_document.doc_type, "com.example.foobar");
         assert_matches!(
-            result,
-            Err(DocumentMdocError::UnknownDocType { doc_type }) if doc_type == "com.example.foobar"
+            disclosure_document.attributes.get("age_over_18").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::Boolean(true),
+            } if key_labels == &HashMap::from([("".to_string(), "age_over_18".to_string())])
         );
     }
 
@@ -874,6 +1086,7 @@ pub mod tests {
                 attributes,
                 issuer: ISSUER_KEY.certificate().clone(),
             },
+            &DocumentMappings::default(),
         );
 
         assert_matches!(
@@ -890,7 +1103,7 @@ pub mod tests {
     }
 
     #[test]
-    fn test_mdoc_to_proposed_disclosure_document_mapping_error_unknown_attribute() {
+    fn test_mdoc_to_proposed_disclosure_document_mapping_unknown_attribute() {
         let attributes = IndexMap::from([(
             PID_DOCTYPE.to_string(),
             vec![Entry {
@@ -899,32 +1112,31 @@ pub mod tests {
             }],
         )]);
 
-        let result = DisclosureDocument::from_mdoc_attributes(
+        let disclosure_document = DisclosureDocument::from_mdoc_attributes(
             PID_DOCTYPE,
             ProposedDocumentAttributes {
                 attributes,
                 issuer: ISSUER_KEY.certificate().clone(),
             },
-        );
+            &DocumentMappings::default(),
+        )
+        .expect("Could not convert attributes with unknown attribute to proposed disclosure document");
 
         assert_matches!(
-            result,
-            Err(DocumentMdocError::UnknownAttribute {
-                doc_type,
-                name_space,
-                name,
-                value,
-            }) if doc_type == PID_DOCTYPE && name_space == PID_DOCTYPE &&
-                  name == "favourite_colour" && value == Some(DataElementValue::Text("Red".to_string()))
+            disclosure_document.attributes.get("favourite_colour").unwrap(),
+            Attribute {
+                key_labels,
+                value: AttributeValue::String(colour),
+            } if key_labels == &HashMap::from([("".to_string(), "favourite_colour".to_string())]) && colour == "Red"
         );
     }
 
     #[rstest]
-    #[case(vec![], vec![].into())]
-    #[case(vec!["com.example.pid/com.example.pid/bsn"], vec![("com.example.pid", vec!["bsn"])].into())]
+    #[case(vec![], vec![])]
+    #[case(vec!["com.example.pid/com.example.pid/bsn"], vec![("com.example.pid", vec!["bsn"])])]
     #[case(
         vec!["com.example.pid/com.example.pid/bsn", "com.example.pid/com.example.pid/age_over_18"],
-        vec![("com.example.pid", vec!["bsn", "age_over_18"])].into())
+        vec![("com.example.pid", vec!["bsn", "age_over_18"])])
     ]
     #[case(
         vec![
@@ -936,13 +1148,19 @@ pub mod tests {
         vec![
             ("com.example.pid", vec!["bsn", "gender"]),
             ("com.example.address", vec!["resident_country", "resident_state"])
-        ].into())
+        ])
     ]
-    #[case(vec!["com.example.foo/com.example.bar/something"], None)] // DocumentMdocError::UnknownDocType
-    #[case(vec!["com.example.pid/com.example.pid/favorite_colour"], None)] // DocumentMdocError::UnknownAttribute
+    #[case(
+        vec!["com.example.foo/com.example.bar/something"],
+        vec![("com.example.foo", vec!["something"])]
+    )]
+    #[case(
+        vec!["com.example.pid/com.example.pid/favorite_colour", "com.example.pid/com.example.pid/bsn"],
+        vec![("com.example.pid", vec!["favorite_colour", "bsn"])]
+    )]
     fn test_missing_disclosure_attributes_from_mdoc_missing_attributes(
         #[case] attribute_identifiers: Vec<&str>,
-        #[case] expected_result: Option<Vec<(&str, Vec<&str>)>>,
+        #[case] expected_result: Vec<(&str, Vec<&str>)>,
     ) {
         // Convert the input attribute identifier strings to actual `AttributeIdentifier`s.
         let attribute_identifiers: Vec<AttributeIdentifier> = attribute_identifiers
@@ -950,30 +1168,23 @@ pub mod tests {
             .map(|attribute| attribute.parse().unwrap())
             .collect();
 
-        // Attempt to convert the identifiers to a `Vec<MissingDisclosureAttributes>`.
-        let result = MissingDisclosureAttributes::from_mdoc_missing_attributes(attribute_identifiers);
-
-        // If `expected_result` contains a `Vec`, match the expected `doc_type` and keys against the result.
-        // Note that the returned order is relevant.
-        if let Some(expected_result) = expected_result {
-            let missing = result.expect("Could not convert attribute identifiers to missing disclosure attributes");
-
-            assert_eq!(missing.len(), expected_result.len());
-            missing.into_iter().zip(expected_result).for_each(
-                |(missing_attributes, (expected_doc_type, expected_attributes))| {
-                    assert_eq!(missing_attributes.doc_type, expected_doc_type);
-                    assert_eq!(
-                        missing_attributes.attributes.into_keys().collect::<Vec<_>>(),
-                        expected_attributes
-                    );
-                },
-            );
-
-            return;
-        }
+        // Convert the identifiers to a `Vec<MissingDisclosureAttributes>`.
+        let missing = MissingDisclosureAttributes::from_mdoc_missing_attributes(
+            attribute_identifiers,
+            &DocumentMappings::default(),
+        );
 
-        // If `expected_result` is None, the result should be an error.
-        assert!(result.is_err());
+        // Match the expected `doc_type` and keys against the result. Note that the returned order is relevant.
+        assert_eq!(missing.len(), expected_result.len());
+        missing.into_iter().zip(expected_result).for_each(
+            |(missing_attributes, (expected_doc_type, expected_attributes))| {
+                assert_eq!(missing_attributes.doc_type, expected_doc_type);
+                assert_eq!(
+                    missing_attributes.attributes.into_keys().collect::<Vec<_>>(),
+                    expected_attributes
+                );
+            },
+        );
     }
 
     #[rstest]
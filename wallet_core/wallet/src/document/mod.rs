@@ -11,6 +11,7 @@ use nl_wallet_mdoc::utils::issuer_auth::IssuerRegistration;
 #[cfg(feature = "snapshot_test")]
 use serde::Serialize;
 
+pub use mapping::DocumentMappings;
 pub use mdoc::{AttributeValueType, DisclosureType, DocumentMdocError};
 
 #[cfg(test)]
@@ -22,8 +23,8 @@ pub use mdoc::tests::{
 pub const PID_DOCTYPE: &str = "com.example.pid";
 const ADDRESS_DOCTYPE: &str = "com.example.address";
 
-pub type DocumentType = &'static str;
-pub type AttributeKey = &'static str;
+pub type DocumentType = String;
+pub type AttributeKey = String;
 pub type DocumentAttributes = IndexMap<AttributeKey, Attribute>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,10 +39,12 @@ pub struct Document {
 pub enum DocumentPersistence {
     InMemory,
     Stored(String),
+    /// The issuer deferred issuance of the document, which is identified by the transaction ID of its issuance.
+    Pending(String),
 }
 
-pub type AttributeLabelLanguage = &'static str;
-pub type AttributeLabel = &'static str;
+pub type AttributeLabelLanguage = String;
+pub type AttributeLabel = String;
 pub type AttributeLabels = HashMap<AttributeLabelLanguage, AttributeLabel>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -93,7 +96,7 @@ fn doc_type_priority(doc_type: &str) -> usize {
 impl Document {
     /// A lower priority means that this [`Document`] should be displayed above others.
     pub fn priority(&self) -> usize {
-        doc_type_priority(self.doc_type)
+        doc_type_priority(&self.doc_type)
     }
 }
 
@@ -104,7 +107,7 @@ mod tests {
     fn empty_document(doc_type: &'static str) -> Document {
         Document {
             persistence: DocumentPersistence::InMemory,
-            doc_type,
+            doc_type: doc_type.to_string(),
             attributes: Default::default(),
             issuer_registration: IssuerRegistration::new_mock(),
         }
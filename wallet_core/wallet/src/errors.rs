@@ -5,6 +5,7 @@ pub mod reqwest {
 
 pub mod openid4vc {
     pub use openid4vc::{
+        credential_offer::CredentialOfferError,
         disclosure_session::{VpClientError, VpMessageClientError, VpMessageClientErrorType},
         issuance_session::IssuanceSessionError,
         oidc::OidcError,
@@ -19,9 +20,10 @@ pub use crate::{
     instruction::{InstructionError, RemoteEcdsaKeyError},
     issuance::DigidSessionError,
     pin::{key::PinKeyError, validation::PinValidationError},
-    storage::{KeyFileError, StorageError},
+    storage::{BackupError, KeyFileError, StorageError},
     wallet::{
-        DisclosureError, EventConversionError, EventStorageError, HistoryError, PidIssuanceError, ResetError,
-        UriIdentificationError, WalletInitError, WalletRegistrationError, WalletUnlockError,
+        ChangePinError, DisclosureError, DocumentDeletionError, EventConversionError, EventStorageError, HistoryError,
+        PidIssuanceError, ResetError, UriIdentificationError, WalletBackupError, WalletInitError,
+        WalletRegistrationError, WalletUnlockError,
     },
 };
@@ -5,7 +5,10 @@ use p256::ecdsa::{signature, signature::Verifier, Signature, VerifyingKey};
 use nl_wallet_mdoc::utils::keys::{KeyFactory, MdocEcdsaKey, MdocKeyType};
 use platform_support::hw_keystore::PlatformEcdsaKey;
 use wallet_common::{
-    account::messages::instructions::{GenerateKey, GenerateKeyResult, Sign},
+    account::messages::{
+        auth::KeyAttestation,
+        instructions::{GenerateKey, GenerateKeyResult, Sign},
+    },
     keys::{EcdsaKey, SecureEcdsaKey, WithIdentifier},
     utils::random_string,
 };
@@ -52,6 +55,13 @@ where
     type Error = RemoteEcdsaKeyError;
 
     async fn generate_new_multiple(&self, count: u64) -> Result<Vec<Self::Key>, Self::Error> {
+        self.generate_new_multiple_attested(count).await.map(|(keys, _)| keys)
+    }
+
+    async fn generate_new_multiple_attested(
+        &self,
+        count: u64,
+    ) -> Result<(Vec<Self::Key>, Option<KeyAttestation>), Self::Error> {
         let identifiers = iter::repeat_with(|| random_string(32)).take(count as usize).collect();
         let result: GenerateKeyResult = self.instruction_client.send(GenerateKey { identifiers }).await?;
 
@@ -65,7 +75,7 @@ where
             })
             .collect();
 
-        Ok(keys)
+        Ok((keys, result.key_attestation))
     }
 
     fn generate_existing<I: Into<String>>(&self, identifier: I, public_key: VerifyingKey) -> Self::Key {
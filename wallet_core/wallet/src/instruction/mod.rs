@@ -11,7 +11,7 @@ use crate::{
 
 pub use self::{
     client::InstructionClient,
-    keys::{RemoteEcdsaKeyError, RemoteEcdsaKeyFactory},
+    keys::{RemoteEcdsaKey, RemoteEcdsaKeyError, RemoteEcdsaKeyFactory},
 };
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
@@ -498,6 +498,7 @@ mod test {
             Ok(TokenRequest {
                 grant_type: TokenRequestGrantType::PreAuthorizedCode {
                     pre_authorized_code: "".to_owned().into(),
+                    tx_code: None,
                 },
                 code_verifier: Default::default(),
                 client_id: Default::default(),
@@ -612,6 +613,7 @@ mod test {
             Ok(TokenRequest {
                 grant_type: TokenRequestGrantType::PreAuthorizedCode {
                     pre_authorized_code: "".to_owned().into(),
+                    tx_code: None,
                 },
                 code_verifier: Default::default(),
                 client_id: Default::default(),
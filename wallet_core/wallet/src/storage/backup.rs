@@ -0,0 +1,284 @@
+//! Encrypted backups of the wallet storage, which can be restored into a fresh [`Storage`] on a new device.
+//!
+//! A backup contains the history events, the [`UnlockData`], the [`DocumentMappingData`] and the metadata of the
+//! stored documents. The mdocs themselves are not included, as their private keys are bound to the device (and
+//! registration) to which they were issued. The same holds for [`RegistrationData`](super::RegistrationData) and
+//! [`InstructionData`](super::InstructionData), so the new device needs to register with the Wallet Provider before
+//! a backup can be restored. The document metadata is restored as [`PendingReissuanceData`], so that the documents
+//! can be issued again to the new device.
+//!
+//! The backup is encrypted using AES-256-GCM, with a key that is derived from a passphrase using PBKDF2. Note that
+//! a PIN has too little entropy to protect a backup that leaves the device, so a passphrase should be preferred.
+
+use std::{convert::Infallible, num::NonZeroU32};
+
+use aes_gcm::{aead::KeyInit, Aes256Gcm};
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+use error_category::ErrorCategory;
+use nl_wallet_mdoc::utils::cose::CoseError;
+use wallet_common::{keys::EncryptionKey, spawn, utils::random_bytes};
+
+use super::{
+    DocumentMappingData, EventDocuments, KeyedDataEntry, PendingReissuanceData, Storage, StorageError, StoredMdocCopy,
+    UnlockData, WalletEvent,
+};
+
+/// The version of the archive format produced by [`export_backup`]. This should be incremented whenever the
+/// contents of [`BackupContents`] or the encryption change in an incompatible way.
+const BACKUP_VERSION: u32 = 1;
+
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LENGTH: usize = 32;
+const KEY_LENGTH: usize = 32;
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(defer)]
+pub enum BackupError {
+    #[error("unsupported backup version: {0}")]
+    #[category(critical)]
+    UnsupportedVersion(u32),
+    #[error("unsupported number of key derivation iterations in backup: {0}")]
+    #[category(critical)]
+    UnsupportedIterations(u32),
+    #[error("could not encrypt backup: {0}")]
+    #[category(critical)]
+    Encryption(#[source] aes_gcm::Error),
+    #[error("could not decrypt backup, the passphrase may be incorrect: {0}")]
+    #[category(expected)]
+    Decryption(#[source] aes_gcm::Error),
+    #[error("could not (de)serialize backup: {0}")]
+    #[category(pd)]
+    Json(#[from] serde_json::Error),
+    #[error("could not read issuer certificate of document: {0}")]
+    IssuerCertificate(#[from] CoseError),
+    #[error("backup can only be restored into empty storage")]
+    #[category(critical)]
+    StorageNotEmpty,
+    #[error("storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// The (JSON serialized) archive produced by [`export_backup`]. Apart from the ciphertext, it contains the
+/// parameters needed to derive the decryption key from the passphrase.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupArchive {
+    version: u32,
+    iterations: NonZeroU32,
+    #[serde_as(as = "Base64")]
+    salt: Vec<u8>,
+    /// The encrypted [`BackupContents`], prefixed with the nonce.
+    #[serde_as(as = "Base64")]
+    ciphertext: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupContents {
+    unlock: Option<UnlockData>,
+    #[serde(default)]
+    document_mappings: Option<DocumentMappingData>,
+    documents: EventDocuments,
+    events: Vec<WalletEvent>,
+}
+
+/// Derive the encryption key from the passphrase. As PBKDF2 is deliberately slow, this is done on a blocking thread.
+async fn derive_key(passphrase: &str, salt: &[u8], iterations: NonZeroU32) -> Aes256Gcm {
+    let passphrase = passphrase.to_string();
+    let salt = salt.to_vec();
+
+    spawn::blocking(move || {
+        let mut key = [0u8; KEY_LENGTH];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+
+        Ok::<_, Infallible>(Aes256Gcm::new(&key.into()))
+    })
+    .await
+    .unwrap_or_else(|never| match never {})
+}
+
+/// Export the contents of `storage` that can be restored on another device, encrypted using a key derived from
+/// `passphrase`.
+pub async fn export_backup(storage: &impl Storage, passphrase: &str) -> Result<Vec<u8>, BackupError> {
+    let mdocs = storage
+        .fetch_unique_mdocs()
+        .await?
+        .into_iter()
+        .map(|StoredMdocCopy { mdoc, .. }| mdoc)
+        .collect::<Vec<_>>();
+
+    let contents = BackupContents {
+        unlock: storage.fetch_data::<UnlockData>().await?,
+        document_mappings: storage.fetch_data::<DocumentMappingData>().await?,
+        documents: mdocs.try_into()?,
+        events: storage.fetch_wallet_events().await?,
+    };
+
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
+    let salt = random_bytes(SALT_LENGTH);
+    let key = derive_key(passphrase, &salt, iterations).await;
+    let ciphertext = key
+        .encrypt(&serde_json::to_vec(&contents)?)
+        .await
+        .map_err(BackupError::Encryption)?;
+
+    let archive = BackupArchive {
+        version: BACKUP_VERSION,
+        iterations,
+        salt,
+        ciphertext,
+    };
+
+    Ok(serde_json::to_vec(&archive)?)
+}
+
+/// Restore a backup produced by [`export_backup`] into `storage`, which should be opened and should not contain any
+/// documents or history events yet.
+pub async fn restore_backup(storage: &mut impl Storage, archive: &[u8], passphrase: &str) -> Result<(), BackupError> {
+    let archive: BackupArchive = serde_json::from_slice(archive)?;
+    if archive.version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(archive.version));
+    }
+
+    // The number of iterations is read from the archive, so it is checked to prevent an archive from making the key
+    // derivation arbitrarily expensive.
+    if archive.iterations.get() != PBKDF2_ITERATIONS {
+        return Err(BackupError::UnsupportedIterations(archive.iterations.get()));
+    }
+
+    let key = derive_key(passphrase, &archive.salt, archive.iterations).await;
+    let plaintext = key
+        .decrypt(&archive.ciphertext)
+        .await
+        .map_err(BackupError::Decryption)?;
+    let contents: BackupContents = serde_json::from_slice(&plaintext)?;
+
+    if !storage.fetch_unique_mdocs().await?.is_empty() || !storage.fetch_wallet_events().await?.is_empty() {
+        return Err(BackupError::StorageNotEmpty);
+    }
+
+    let mut data = Vec::new();
+
+    if let Some(unlock) = contents.unlock {
+        data.push(KeyedDataEntry::new(&unlock)?);
+    }
+
+    if let Some(document_mappings) = contents.document_mappings {
+        data.push(KeyedDataEntry::new(&document_mappings)?);
+    }
+
+    if !contents.documents.0.is_empty() {
+        data.push(KeyedDataEntry::new(&PendingReissuanceData {
+            documents: contents.documents,
+        })?);
+    }
+
+    // Store all of the restored data at once, so that a failure does not leave a partially restored backup.
+    storage.upsert_data_and_log_events(data, contents.events).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use chrono::{TimeZone, Utc};
+
+    use nl_wallet_mdoc::holder::{Mdoc, MdocCopies};
+
+    use crate::storage::{database_storage::tests::open_test_database_storage, UnlockMethod};
+
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    async fn export_test_backup() -> (Vec<u8>, Vec<WalletEvent>) {
+        let mut storage = open_test_database_storage().await;
+
+        let mdoc = Mdoc::new_example_mock();
+        let issuer_certificate = mdoc.issuer_certificate().unwrap();
+        storage.insert_mdocs(vec![MdocCopies::from(vec![mdoc])]).await.unwrap();
+        storage
+            .upsert_data(&UnlockData {
+                method: UnlockMethod::PinCodeAndBiometrics,
+            })
+            .await
+            .unwrap();
+
+        let timestamp = Utc.with_ymd_and_hms(2023, 11, 29, 10, 50, 45).unwrap();
+        let event = WalletEvent::issuance_from_str(vec!["com.example.pid"], timestamp, issuer_certificate);
+        storage.log_wallet_event(event).await.unwrap();
+        let events = storage.fetch_wallet_events().await.unwrap();
+
+        let archive = export_backup(&storage, PASSPHRASE).await.unwrap();
+
+        (archive, events)
+    }
+
+    #[tokio::test]
+    async fn test_backup_round_trip() {
+        let (archive, events) = export_test_backup().await;
+
+        let mut storage = open_test_database_storage().await;
+        restore_backup(&mut storage, &archive, PASSPHRASE).await.unwrap();
+
+        // The history events and unlock method should be restored as is.
+        assert_eq!(storage.fetch_wallet_events().await.unwrap(), events);
+        assert_matches!(
+            storage.fetch_data::<UnlockData>().await.unwrap(),
+            Some(UnlockData {
+                method: UnlockMethod::PinCodeAndBiometrics
+            })
+        );
+
+        // The mdocs should not be restored, but should be marked for reissuance instead.
+        assert!(storage.fetch_unique_mdocs().await.unwrap().is_empty());
+        let reissuance = storage.fetch_data::<PendingReissuanceData>().await.unwrap().unwrap();
+        assert_eq!(
+            reissuance.documents.0.keys().collect::<Vec<_>>(),
+            vec![&Mdoc::new_example_mock().doc_type]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup_error() {
+        let (archive, _) = export_test_backup().await;
+
+        // A backup cannot be decrypted using the wrong passphrase.
+        let mut storage = open_test_database_storage().await;
+        let error = restore_backup(&mut storage, &archive, "wrong passphrase")
+            .await
+            .unwrap_err();
+        assert_matches!(error, BackupError::Decryption(_));
+        assert!(storage.fetch_data::<UnlockData>().await.unwrap().is_none());
+
+        // A backup with an unknown version is rejected.
+        let mut unknown_version: BackupArchive = serde_json::from_slice(&archive).unwrap();
+        unknown_version.version = BACKUP_VERSION + 1;
+        let error = restore_backup(&mut storage, &serde_json::to_vec(&unknown_version).unwrap(), PASSPHRASE)
+            .await
+            .unwrap_err();
+        assert_matches!(error, BackupError::UnsupportedVersion(version) if version == BACKUP_VERSION + 1);
+
+        // A backup with a different number of key derivation iterations is rejected.
+        let mut iterations: BackupArchive = serde_json::from_slice(&archive).unwrap();
+        iterations.iterations = NonZeroU32::new(u32::MAX).unwrap();
+        let error = restore_backup(&mut storage, &serde_json::to_vec(&iterations).unwrap(), PASSPHRASE)
+            .await
+            .unwrap_err();
+        assert_matches!(error, BackupError::UnsupportedIterations(u32::MAX));
+
+        // A backup cannot be restored into storage that already contains history.
+        restore_backup(&mut storage, &archive, PASSPHRASE).await.unwrap();
+        let error = restore_backup(&mut storage, &archive, PASSPHRASE).await.unwrap_err();
+        assert_matches!(error, BackupError::StorageNotEmpty);
+    }
+}
@@ -1,12 +1,38 @@
+use std::{collections::HashMap, sync::LazyLock};
+
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::{base64::Base64, serde_as};
 
+use nl_wallet_mdoc::utils::x509::Certificate;
+use openid4vc::issuance_session::{DeferredIssuance, IssuanceRefresh};
 use wallet_common::account::messages::auth::WalletCertificate;
 
+use crate::document::DocumentMappings;
+
+use super::EventDocuments;
+
 pub trait KeyedData: Serialize + DeserializeOwned {
     const KEY: &'static str;
 }
 
+/// A serialized [`KeyedData`] value along with its key, so that values of different types can be stored at once.
+#[derive(Debug, Clone)]
+pub struct KeyedDataEntry {
+    pub key: &'static str,
+    pub data: serde_json::Value,
+}
+
+impl KeyedDataEntry {
+    pub fn new<D: KeyedData>(data: &D) -> Result<Self, serde_json::Error> {
+        let entry = Self {
+            key: D::KEY,
+            data: serde_json::to_value(data)?,
+        };
+
+        Ok(entry)
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegistrationData {
@@ -15,6 +41,18 @@ pub struct RegistrationData {
     pub wallet_certificate: WalletCertificate,
 }
 
+/// The progress of a PIN change, which is stored so that a PIN change that was interrupted, e.g. because the app was
+/// terminated, can be completed or rolled back later on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ChangePinData {
+    /// The new PIN has been sent to the Wallet Provider, which may or may not have accepted it.
+    Begin,
+    /// The Wallet Provider has accepted the new PIN, which still needs to be committed. This contains the
+    /// registration data belonging to the new PIN.
+    Commit { registration: RegistrationData },
+}
+
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct InstructionData {
     pub instruction_sequence_number: u64,
@@ -42,10 +80,57 @@ pub struct UnlockData {
     pub method: UnlockMethod,
 }
 
+/// Issuance sessions of which the issuer deferred issuance, to be retrieved later.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingIssuanceData {
+    pub issuances: Vec<DeferredIssuance>,
+}
+
+/// Issued attestations that can be refreshed by their issuer before they expire.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssuanceRefreshData {
+    pub refreshes: Vec<IssuanceRefresh>,
+}
+
+/// Documents restored from a backup, which need to be issued again as the mdocs themselves are bound to the device
+/// to which they were issued.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingReissuanceData {
+    pub documents: EventDocuments,
+}
+
+/// The attribute mappings of the doc types of issued documents, as derived from the metadata of their issuers. These
+/// are kept per issuer, keyed by the certificate with which the issuer signed the documents, so that an issuer cannot
+/// change how the documents of another issuer are displayed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentMappingData {
+    pub mappings: HashMap<Certificate, DocumentMappings>,
+}
+
+impl DocumentMappingData {
+    /// Get the mappings derived from the metadata of the issuer with certificate `issuer`, which are empty if that
+    /// issuer has not provided any.
+    pub fn issuer_mappings(&self, issuer: &Certificate) -> &DocumentMappings {
+        static EMPTY_MAPPINGS: LazyLock<DocumentMappings> = LazyLock::new(DocumentMappings::default);
+
+        self.mappings.get(issuer).unwrap_or(&EMPTY_MAPPINGS)
+    }
+
+    /// Add the mappings derived from the metadata of the issuer with certificate `issuer`, replacing any existing
+    /// mappings of that issuer for the same doc types.
+    pub fn extend(&mut self, issuer: Certificate, mappings: DocumentMappings) {
+        self.mappings.entry(issuer).or_default().extend(mappings);
+    }
+}
+
 impl KeyedData for RegistrationData {
     const KEY: &'static str = "registration";
 }
 
+impl KeyedData for ChangePinData {
+    const KEY: &'static str = "change_pin";
+}
+
 impl KeyedData for InstructionData {
     const KEY: &'static str = "instructions";
 }
@@ -53,3 +138,19 @@ impl KeyedData for InstructionData {
 impl KeyedData for UnlockData {
     const KEY: &'static str = "unlock";
 }
+
+impl KeyedData for PendingIssuanceData {
+    const KEY: &'static str = "pending_issuance";
+}
+
+impl KeyedData for IssuanceRefreshData {
+    const KEY: &'static str = "issuance_refresh";
+}
+
+impl KeyedData for PendingReissuanceData {
+    const KEY: &'static str = "pending_reissuance";
+}
+
+impl KeyedData for DocumentMappingData {
+    const KEY: &'static str = "document_mapping";
+}
@@ -13,6 +13,7 @@ use tracing::warn;
 use uuid::Uuid;
 
 use entity::{
+    deletion_history_event, deletion_history_event_doc_type,
     disclosure_history_event::{self, EventStatus},
     disclosure_history_event_doc_type, history_doc_type, issuance_history_event, issuance_history_event_doc_type,
     keyed_data, mdoc, mdoc_copy,
@@ -24,7 +25,7 @@ use nl_wallet_mdoc::{
 use platform_support::hw_keystore::PlatformEncryptionKey;
 
 use super::{
-    data::KeyedData,
+    data::{KeyedData, KeyedDataEntry},
     database::{Database, SqliteUrl},
     event_log::{WalletEvent, WalletEventModel},
     key_file,
@@ -219,9 +220,108 @@ impl<K> DatabaseStorage<K> {
         Ok(())
     }
 
+    async fn upsert_data_entry(connection: &impl ConnectionTrait, entry: KeyedDataEntry) -> StorageResult<()> {
+        let model = keyed_data::ActiveModel {
+            key: Set(entry.key.to_string()),
+            data: Set(entry.data),
+        };
+        keyed_data::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(keyed_data::Column::Key)
+                    .update_column(keyed_data::Column::Data)
+                    .to_owned(),
+            )
+            .exec(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert_wallet_event(connection: &impl ConnectionTrait, event: WalletEvent) -> StorageResult<()> {
+        let event_doc_types = event.associated_doc_types();
+
+        // Find existing doc_type entities
+        let existing_doc_type_entities = history_doc_type::Entity::find()
+            .filter(history_doc_type::Column::DocType.is_in(event_doc_types.clone()))
+            .all(connection)
+            .await?;
+
+        // Get Vec of existing doc_types
+        let existing_doc_types = existing_doc_type_entities
+            .iter()
+            .map(|e| e.doc_type.as_str())
+            .collect::<Vec<_>>();
+
+        // Determine what new doc_type entries need to be inserted
+        let new_doc_type_entities = event_doc_types
+            .into_iter()
+            .filter(|doc_type| !existing_doc_types.contains(doc_type))
+            .map(|doc_type| history_doc_type::Model {
+                id: Uuid::new_v4(),
+                doc_type: doc_type.to_owned(),
+            })
+            .collect::<Vec<_>>();
+
+        // Insert the history event
+        match WalletEventModel::try_from(event)? {
+            WalletEventModel::Issuance(event_entity) => {
+                Self::insert_history_event_and_doc_type_mappings(
+                    connection,
+                    issuance_history_event::ActiveModel::from(event_entity),
+                    new_doc_type_entities,
+                    existing_doc_type_entities,
+                    |(event, doc_type_id)| issuance_history_event_doc_type::ActiveModel {
+                        issuance_history_event_id: event.id.clone(),
+                        history_doc_type_id: Set(doc_type_id),
+                    },
+                )
+                .await?;
+            }
+            WalletEventModel::Disclosure(event_entity) => {
+                Self::insert_history_event_and_doc_type_mappings(
+                    connection,
+                    disclosure_history_event::ActiveModel::from(event_entity),
+                    new_doc_type_entities,
+                    existing_doc_type_entities,
+                    |(event, doc_type_id)| disclosure_history_event_doc_type::ActiveModel {
+                        disclosure_history_event_id: event.id.clone(),
+                        history_doc_type_id: Set(doc_type_id),
+                    },
+                )
+                .await?;
+            }
+            WalletEventModel::Deletion(event_entity) => {
+                Self::insert_history_event_and_doc_type_mappings(
+                    connection,
+                    deletion_history_event::ActiveModel::from(event_entity),
+                    new_doc_type_entities,
+                    existing_doc_type_entities,
+                    |(event, doc_type_id)| deletion_history_event_doc_type::ActiveModel {
+                        deletion_history_event_id: event.id.clone(),
+                        history_doc_type_id: Set(doc_type_id),
+                    },
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_mdoc_and_copies(connection: &impl ConnectionTrait, mdoc_id: Uuid) -> StorageResult<()> {
+        mdoc_copy::Entity::delete_many()
+            .filter(mdoc_copy::Column::MdocId.eq(mdoc_id))
+            .exec(connection)
+            .await?;
+        mdoc::Entity::delete_by_id(mdoc_id).exec(connection).await?;
+
+        Ok(())
+    }
+
     fn combine_history_events(
         issuance_events: Vec<issuance_history_event::Model>,
         disclosure_events: Vec<disclosure_history_event::Model>,
+        deletion_events: Vec<deletion_history_event::Model>,
     ) -> StorageResult<Vec<WalletEvent>> {
         let mut issuance_events: Vec<WalletEvent> = issuance_events
             .into_iter()
@@ -231,8 +331,13 @@ impl<K> DatabaseStorage<K> {
             .into_iter()
             .map(WalletEvent::try_from)
             .collect::<Result<Vec<_>, _>>()?;
+        let mut deletion_events: Vec<WalletEvent> = deletion_events
+            .into_iter()
+            .map(WalletEvent::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
 
         issuance_events.append(&mut disclosure_events);
+        issuance_events.append(&mut deletion_events);
         issuance_events.sort_by(|a, b| b.timestamp().cmp(a.timestamp()));
         Ok(issuance_events)
     }
@@ -355,24 +460,41 @@ where
     /// Update data entry in the key-value table using the provided key,
     /// inserting the data if it is not already present.
     async fn upsert_data<D: KeyedData>(&mut self, data: &D) -> StorageResult<()> {
+        let entry = KeyedDataEntry::new(data)?;
+
+        Self::upsert_data_entry(self.database()?.connection(), entry).await
+    }
+
+    /// Delete the data entry in the key-value table using the provided key, if it is present.
+    async fn delete_data<D: KeyedData>(&mut self) -> StorageResult<()> {
         let database = self.database()?;
 
-        let model = keyed_data::ActiveModel {
-            key: Set(D::KEY.to_string()),
-            data: Set(serde_json::to_value(data)?),
-        };
-        keyed_data::Entity::insert(model)
-            .on_conflict(
-                OnConflict::column(keyed_data::Column::Key)
-                    .update_column(keyed_data::Column::Data)
-                    .to_owned(),
-            )
+        keyed_data::Entity::delete_by_id(D::KEY)
             .exec(database.connection())
             .await?;
 
         Ok(())
     }
 
+    async fn upsert_data_and_log_events(
+        &mut self,
+        data: Vec<KeyedDataEntry>,
+        events: Vec<WalletEvent>,
+    ) -> StorageResult<()> {
+        let transaction = self.database()?.connection().begin().await?;
+
+        for entry in data {
+            Self::upsert_data_entry(&transaction, entry).await?;
+        }
+        for event in events {
+            Self::insert_wallet_event(&transaction, event).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
     async fn insert_mdocs(&mut self, mdocs: Vec<MdocCopies>) -> StorageResult<()> {
         // Construct a vec of tuples of 1 `mdoc` and 1 or more `mdoc_copy` models,
         // based on the unique `MdocCopies`, to be inserted into the database.
@@ -423,6 +545,68 @@ where
         Ok(())
     }
 
+    async fn replace_mdocs(&mut self, mdocs: Vec<(Uuid, MdocCopies)>) -> StorageResult<()> {
+        let mdoc_ids = mdocs.iter().map(|(mdoc_id, _)| *mdoc_id).collect::<Vec<_>>();
+        let copy_models = mdocs
+            .into_iter()
+            .flat_map(|(mdoc_id, mdoc_copies)| {
+                mdoc_copies.cred_copies.into_iter().map(move |mdoc| {
+                    let model = mdoc_copy::ActiveModel {
+                        id: Set(Uuid::new_v4()),
+                        mdoc_id: Set(mdoc_id),
+                        mdoc: Set(cbor_serialize(&mdoc)?),
+                        ..Default::default()
+                    };
+
+                    Ok(model)
+                })
+            })
+            .collect::<Result<Vec<_>, CborError>>()?;
+
+        // Delete the old copies and insert the new ones within one transaction, so that the mdocs are never absent.
+        let transaction = self.database()?.connection().begin().await?;
+
+        mdoc_copy::Entity::delete_many()
+            .filter(mdoc_copy::Column::MdocId.is_in(mdoc_ids))
+            .exec(&transaction)
+            .await?;
+        mdoc_copy::Entity::insert_many(copy_models).exec(&transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_mdoc(&mut self, mdoc_id: Uuid) -> StorageResult<()> {
+        let transaction = self.database()?.connection().begin().await?;
+
+        Self::delete_mdoc_and_copies(&transaction, mdoc_id).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_mdoc_and_log_event(&mut self, mdoc_id: Uuid, event: WalletEvent) -> StorageResult<()> {
+        let transaction = self.database()?.connection().begin().await?;
+
+        Self::delete_mdoc_and_copies(&transaction, mdoc_id).await?;
+        Self::insert_wallet_event(&transaction, event).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_mdoc_copies(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()> {
+        mdoc_copy::Entity::delete_many()
+            .filter(mdoc_copy::Column::Id.is_in(mdoc_copy_ids))
+            .exec(self.database()?.connection())
+            .await?;
+
+        Ok(())
+    }
+
     async fn increment_mdoc_copies_usage_count(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()> {
         mdoc_copy::Entity::update_many()
             .col_expr(
@@ -436,6 +620,25 @@ where
         Ok(())
     }
 
+    async fn fetch_mdoc_copies(&self) -> StorageResult<Vec<(StoredMdocCopy, u32)>> {
+        let mdoc_copies = mdoc_copy::Entity::find()
+            .all(self.database()?.connection())
+            .await?
+            .into_iter()
+            .map(|model| {
+                let stored_mdoc_copy = StoredMdocCopy {
+                    mdoc_id: model.mdoc_id,
+                    mdoc_copy_id: model.id,
+                    mdoc: cbor_deserialize(model.mdoc.as_slice())?,
+                };
+
+                Ok((stored_mdoc_copy, model.disclosure_count))
+            })
+            .collect::<Result<_, CborError>>()?;
+
+        Ok(mdoc_copies)
+    }
+
     async fn fetch_unique_mdocs(&self) -> StorageResult<Vec<StoredMdocCopy>> {
         self.query_unique_mdocs(|select| select).await
     }
@@ -459,59 +662,7 @@ where
     async fn log_wallet_event(&mut self, event: WalletEvent) -> StorageResult<()> {
         let transaction = self.database()?.connection().begin().await?;
 
-        let event_doc_types = event.associated_doc_types();
-
-        // Find existing doc_type entities
-        let existing_doc_type_entities = history_doc_type::Entity::find()
-            .filter(history_doc_type::Column::DocType.is_in(event_doc_types.clone()))
-            .all(&transaction)
-            .await?;
-
-        // Get Vec of existing doc_types
-        let existing_doc_types = existing_doc_type_entities
-            .iter()
-            .map(|e| e.doc_type.as_str())
-            .collect::<Vec<_>>();
-
-        // Determine what new doc_type entries need to be inserted
-        let new_doc_type_entities = event_doc_types
-            .into_iter()
-            .filter(|doc_type| !existing_doc_types.contains(doc_type))
-            .map(|doc_type| history_doc_type::Model {
-                id: Uuid::new_v4(),
-                doc_type: doc_type.to_owned(),
-            })
-            .collect::<Vec<_>>();
-
-        // Insert the history event
-        match WalletEventModel::try_from(event)? {
-            WalletEventModel::Issuance(event_entity) => {
-                Self::insert_history_event_and_doc_type_mappings(
-                    &transaction,
-                    issuance_history_event::ActiveModel::from(event_entity),
-                    new_doc_type_entities,
-                    existing_doc_type_entities,
-                    |(event, doc_type_id)| issuance_history_event_doc_type::ActiveModel {
-                        issuance_history_event_id: event.id.clone(),
-                        history_doc_type_id: Set(doc_type_id),
-                    },
-                )
-                .await?;
-            }
-            WalletEventModel::Disclosure(event_entity) => {
-                Self::insert_history_event_and_doc_type_mappings(
-                    &transaction,
-                    disclosure_history_event::ActiveModel::from(event_entity),
-                    new_doc_type_entities,
-                    existing_doc_type_entities,
-                    |(event, doc_type_id)| disclosure_history_event_doc_type::ActiveModel {
-                        disclosure_history_event_id: event.id.clone(),
-                        history_doc_type_id: Set(doc_type_id),
-                    },
-                )
-                .await?;
-            }
-        }
+        Self::insert_wallet_event(&transaction, event).await?;
 
         transaction.commit().await?;
 
@@ -529,9 +680,14 @@ where
             .order_by_desc(disclosure_history_event::Column::Timestamp)
             .all(connection);
 
-        let (issuance_events, disclosure_events) = try_join!(fetch_issuance_events, fetch_disclosure_events)?;
+        let fetch_deletion_events = deletion_history_event::Entity::find()
+            .order_by_desc(deletion_history_event::Column::Timestamp)
+            .all(connection);
+
+        let (issuance_events, disclosure_events, deletion_events) =
+            try_join!(fetch_issuance_events, fetch_disclosure_events, fetch_deletion_events)?;
 
-        Self::combine_history_events(issuance_events, disclosure_events)
+        Self::combine_history_events(issuance_events, disclosure_events, deletion_events)
     }
 
     async fn fetch_recent_wallet_events(&self) -> StorageResult<Vec<WalletEvent>> {
@@ -547,9 +703,15 @@ where
             .order_by_desc(disclosure_history_event::Column::Timestamp)
             .all(connection);
 
-        let (issuance_events, disclosure_events) = try_join!(fetch_issuance_events, fetch_disclosure_events)?;
+        let fetch_deletion_events = deletion_history_event::Entity::find()
+            .filter(Self::newer_than_31_days(deletion_history_event::Column::Timestamp))
+            .order_by_desc(deletion_history_event::Column::Timestamp)
+            .all(connection);
+
+        let (issuance_events, disclosure_events, deletion_events) =
+            try_join!(fetch_issuance_events, fetch_disclosure_events, fetch_deletion_events)?;
 
-        Self::combine_history_events(issuance_events, disclosure_events)
+        Self::combine_history_events(issuance_events, disclosure_events, deletion_events)
     }
 
     async fn fetch_wallet_events_by_doc_type(&self, doc_type: &str) -> StorageResult<Vec<WalletEvent>> {
@@ -569,10 +731,18 @@ where
             disclosure_history_event_doc_type::Relation::HistoryDocType.def(),
             disclosure_history_event::Column::Timestamp,
         );
+        let fetch_deletion_events = Self::query_history_events_by_doc_type(
+            doc_type,
+            connection,
+            deletion_history_event_doc_type::Relation::HistoryEvent.def(),
+            deletion_history_event_doc_type::Relation::HistoryDocType.def(),
+            deletion_history_event::Column::Timestamp,
+        );
 
-        let (issuance_events, disclosure_events) = try_join!(fetch_issuance_events, fetch_disclosure_events)?;
+        let (issuance_events, disclosure_events, deletion_events) =
+            try_join!(fetch_issuance_events, fetch_disclosure_events, fetch_deletion_events)?;
 
-        Self::combine_history_events(issuance_events, disclosure_events)
+        Self::combine_history_events(issuance_events, disclosure_events, deletion_events)
     }
 
     async fn did_share_data_with_relying_party(
@@ -705,7 +875,7 @@ pub(crate) mod tests {
         assert!(!SoftwareEncryptionKey::identifier_exists(&key_file_identifier));
     }
 
-    async fn open_test_database_storage() -> DatabaseStorage<SoftwareEncryptionKey> {
+    pub(crate) async fn open_test_database_storage() -> DatabaseStorage<SoftwareEncryptionKey> {
         let mut storage =
             DatabaseStorage::<SoftwareEncryptionKey>::new(SoftwareUtilities::storage_path().await.unwrap());
 
@@ -794,6 +964,18 @@ pub(crate) mod tests {
             registration.wallet_certificate.0
         );
 
+        // Delete registration, after which it should no longer be present.
+        storage
+            .delete_data::<RegistrationData>()
+            .await
+            .expect("Could not delete registration");
+
+        let fetched_after_delete_registration = storage
+            .fetch_data::<RegistrationData>()
+            .await
+            .expect("Could not get registration");
+        assert!(fetched_after_delete_registration.is_none());
+
         // Clear database, state should be uninitialized.
         storage.clear().await;
 
@@ -900,6 +1082,64 @@ pub(crate) mod tests {
 
         // No entries should be returned
         assert!(fetched_unique_doctype_mismatch.is_empty());
+
+        // Fetch all copies, which should include their usage counts.
+        let fetched_copies = storage.fetch_mdoc_copies().await.expect("Could not fetch mdoc copies");
+        let mut usage_counts = fetched_copies
+            .iter()
+            .map(|(_, usage_count)| *usage_count)
+            .collect::<Vec<_>>();
+        usage_counts.sort();
+        assert_eq!(usage_counts, vec![0, 1, 1]);
+
+        // Delete the copies that have been used.
+        storage
+            .delete_mdoc_copies(vec![mdoc_copy1.mdoc_copy_id, mdoc_copy2.mdoc_copy_id])
+            .await
+            .expect("Could not delete mdoc copies");
+
+        let fetched_copies = storage.fetch_mdoc_copies().await.expect("Could not fetch mdoc copies");
+        assert_eq!(fetched_copies.len(), 1);
+        assert_eq!(fetched_copies[0].0.mdoc_copy_id, remaning_mdoc_copy_id1);
+
+        // Delete the mdoc, which should delete all of its copies.
+        storage
+            .delete_mdoc(mdoc_copy1.mdoc_id)
+            .await
+            .expect("Could not delete mdoc");
+
+        let fetched_unique_after_delete = storage
+            .fetch_unique_mdocs()
+            .await
+            .expect("Could not fetch unique mdocs");
+
+        assert!(fetched_unique_after_delete.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_mdoc_and_log_event() {
+        let mut storage = open_test_database_storage().await;
+
+        let mdoc = Mdoc::new_example_mock();
+        let timestamp = Utc.with_ymd_and_hms(2023, 11, 29, 10, 50, 45).unwrap();
+        let event =
+            WalletEvent::deletion_from_str(vec!["com.example.pid"], timestamp, mdoc.issuer_certificate().unwrap());
+
+        storage
+            .insert_mdocs(vec![MdocCopies::from(vec![mdoc.clone(), mdoc])])
+            .await
+            .expect("Could not insert mdocs");
+
+        let mdoc_id = storage.fetch_unique_mdocs().await.unwrap().first().unwrap().mdoc_id;
+
+        // Delete the mdoc and log the deletion event in one go.
+        storage
+            .delete_mdoc_and_log_event(mdoc_id, event.clone())
+            .await
+            .expect("Could not delete mdoc and log event");
+
+        assert!(storage.fetch_mdoc_copies().await.unwrap().is_empty());
+        assert_eq!(storage.fetch_wallet_events().await.unwrap(), vec![event]);
     }
 
     #[tokio::test]
@@ -1103,6 +1343,7 @@ pub(crate) mod tests {
         let timestamp = Utc.with_ymd_and_hms(2023, 11, 11, 11, 11, 00).unwrap();
         let timestamp_newer = Utc.with_ymd_and_hms(2023, 11, 21, 13, 37, 00).unwrap();
         let timestamp_newest = Utc.with_ymd_and_hms(2023, 11, 29, 10, 50, 45).unwrap();
+        let timestamp_latest = Utc.with_ymd_and_hms(2023, 12, 1, 9, 0, 0).unwrap();
 
         // Log Issuance of pid and address cards
         let issuance = WalletEvent::issuance_from_str(
@@ -1133,6 +1374,14 @@ pub(crate) mod tests {
         );
         storage.log_wallet_event(disclosure_pid_only.clone()).await.unwrap();
 
+        // Log Deletion of address card
+        let deletion_address = WalletEvent::deletion_from_str(
+            vec![ADDRESS_DOCTYPE],
+            timestamp_latest,
+            ISSUER_KEY.certificate().clone(),
+        );
+        storage.log_wallet_event(deletion_address.clone()).await.unwrap();
+
         // Fetch event by pid and verify events contain issuance of pid, and both full disclosure transactions with pid
         assert_eq!(
             storage.fetch_wallet_events_by_doc_type(PID_DOCTYPE).await.unwrap(),
@@ -1142,11 +1391,11 @@ pub(crate) mod tests {
                 issuance.clone(),
             ]
         );
-        // Fetch event by address and verify events contain issuance of address, and one full disclosure transactions
-        // with address
+        // Fetch event by address and verify events contain issuance of address, one full disclosure transactions
+        // with address and the deletion of address
         assert_eq!(
             storage.fetch_wallet_events_by_doc_type(ADDRESS_DOCTYPE).await.unwrap(),
-            vec![disclosure_pid_and_address, issuance,]
+            vec![deletion_address, disclosure_pid_and_address, issuance,]
         );
     }
 }
@@ -3,7 +3,7 @@ use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-pub use entity::{disclosure_history_event, issuance_history_event};
+pub use entity::{deletion_history_event, disclosure_history_event, issuance_history_event};
 use nl_wallet_mdoc::{
     holder::{Mdoc, ProposedAttributes, ProposedDocumentAttributes},
     unsigned::Entry,
@@ -15,7 +15,8 @@ use crate::document::DisclosureType;
 
 // TODO: Think about refactoring/renaming EventStatus.
 // For rationale, see comment for DisclosureType in mdoc.rs.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EventStatus {
     Success,
     Error,
@@ -60,7 +61,8 @@ impl From<&disclosure_history_event::Model> for DisclosureType {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum WalletEvent {
     Issuance {
         id: Uuid,
@@ -75,6 +77,11 @@ pub enum WalletEvent {
         status: EventStatus,
         r#type: DisclosureType,
     },
+    Deletion {
+        id: Uuid,
+        mdocs: EventDocuments,
+        timestamp: DateTime<Utc>,
+    },
 }
 
 impl WalletEvent {
@@ -102,6 +109,14 @@ impl WalletEvent {
         }
     }
 
+    pub fn new_deletion(mdocs: EventDocuments) -> Self {
+        Self::Deletion {
+            id: Uuid::new_v4(),
+            mdocs,
+            timestamp: Utc::now(),
+        }
+    }
+
     /// Returns the associated doc_types for this event. Will return an empty set if there are no attributes.
     pub fn associated_doc_types(&self) -> IndexSet<&str> {
         match self {
@@ -112,6 +127,10 @@ impl WalletEvent {
             | Self::Disclosure {
                 documents: Some(EventDocuments(mdocs)),
                 ..
+            }
+            | Self::Deletion {
+                mdocs: EventDocuments(mdocs),
+                ..
             } => mdocs.keys().map(String::as_str).collect(),
             Self::Disclosure { documents: None, .. } => Default::default(),
         }
@@ -121,6 +140,7 @@ impl WalletEvent {
         match self {
             Self::Issuance { timestamp, .. } => timestamp,
             Self::Disclosure { timestamp, .. } => timestamp,
+            Self::Deletion { timestamp, .. } => timestamp,
         }
     }
 }
@@ -152,10 +172,23 @@ impl TryFrom<issuance_history_event::Model> for WalletEvent {
     }
 }
 
+impl TryFrom<deletion_history_event::Model> for WalletEvent {
+    type Error = serde_json::Error;
+    fn try_from(event: deletion_history_event::Model) -> Result<Self, Self::Error> {
+        let result = Self::Deletion {
+            id: event.id,
+            mdocs: serde_json::from_value(event.attributes)?,
+            timestamp: event.timestamp,
+        };
+        Ok(result)
+    }
+}
+
 /// Enumerates the different database models for a [`WalletEvent`].
 pub(crate) enum WalletEventModel {
     Issuance(issuance_history_event::Model),
     Disclosure(disclosure_history_event::Model),
+    Deletion(deletion_history_event::Model),
 }
 
 impl TryFrom<WalletEvent> for WalletEventModel {
@@ -182,6 +215,11 @@ impl TryFrom<WalletEvent> for WalletEventModel {
                 status: status.into(),
                 r#type: r#type.into(),
             }),
+            WalletEvent::Deletion { id, mdocs, timestamp } => Self::Deletion(deletion_history_event::Model {
+                attributes: serde_json::to_value(mdocs)?,
+                id,
+                timestamp,
+            }),
         };
         Ok(result)
     }
@@ -307,6 +345,20 @@ mod test {
             }
         }
 
+        pub fn deletion_from_str(
+            doc_types: Vec<&str>,
+            timestamp: DateTime<Utc>,
+            issuer_certificate: Certificate,
+        ) -> Self {
+            let docs = vec![create_full_unsigned_pid_mdoc(), create_full_unsigned_address_mdoc()];
+            let mdocs = from_unsigned_mdocs_filtered(docs, &doc_types, &issuer_certificate);
+            Self::Deletion {
+                id: Uuid::new_v4(),
+                mdocs,
+                timestamp,
+            }
+        }
+
         pub fn disclosure_from_str(
             doc_types: Vec<&str>,
             timestamp: DateTime<Utc>,
@@ -10,7 +10,7 @@ use nl_wallet_mdoc::{holder::MdocCopies, utils::x509::Certificate, DocType};
 use crate::storage::event_log::WalletEventModel;
 
 use super::{
-    data::{KeyedData, RegistrationData},
+    data::{KeyedData, KeyedDataEntry, RegistrationData},
     event_log::WalletEvent,
     Storage, StorageResult, StorageState, StoredMdocCopy,
 };
@@ -24,6 +24,8 @@ pub struct MockStorage {
     pub mdoc_copies_usage_counts: HashMap<Uuid, u32>,
     pub event_log: Vec<WalletEvent>,
     pub has_query_error: bool,
+    /// Keys of the keyed data for which queries return an error, regardless of `has_query_error`.
+    pub data_query_error_keys: HashSet<&'static str>,
 }
 
 impl MockStorage {
@@ -41,6 +43,7 @@ impl MockStorage {
             mdoc_copies_usage_counts: HashMap::new(),
             event_log: vec![],
             has_query_error: false,
+            data_query_error_keys: HashSet::new(),
         }
     }
 
@@ -51,6 +54,16 @@ impl MockStorage {
 
         Ok(())
     }
+
+    fn check_data_query_error<D: KeyedData>(&self) -> StorageResult<()> {
+        self.check_query_error()?;
+
+        if self.data_query_error_keys.contains(D::KEY) {
+            return Err(DbErr::Custom("Mock error".to_string()).into());
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for MockStorage {
@@ -76,7 +89,7 @@ impl Storage for MockStorage {
     }
 
     async fn fetch_data<D: KeyedData>(&self) -> StorageResult<Option<D>> {
-        self.check_query_error()?;
+        self.check_data_query_error::<D>()?;
 
         let data = self.data.get(D::KEY).map(|s| serde_json::from_str(s).unwrap());
 
@@ -84,7 +97,7 @@ impl Storage for MockStorage {
     }
 
     async fn insert_data<D: KeyedData>(&mut self, data: &D) -> StorageResult<()> {
-        self.check_query_error()?;
+        self.check_data_query_error::<D>()?;
 
         if self.data.contains_key(D::KEY) {
             panic!("Registration already present");
@@ -96,13 +109,38 @@ impl Storage for MockStorage {
     }
 
     async fn upsert_data<D: KeyedData>(&mut self, data: &D) -> StorageResult<()> {
-        self.check_query_error()?;
+        self.check_data_query_error::<D>()?;
 
         self.data.insert(D::KEY, serde_json::to_string(&data).unwrap());
 
         Ok(())
     }
 
+    async fn delete_data<D: KeyedData>(&mut self) -> StorageResult<()> {
+        self.check_data_query_error::<D>()?;
+
+        self.data.remove(D::KEY);
+
+        Ok(())
+    }
+
+    async fn upsert_data_and_log_events(
+        &mut self,
+        data: Vec<KeyedDataEntry>,
+        events: Vec<WalletEvent>,
+    ) -> StorageResult<()> {
+        self.check_query_error()?;
+
+        for KeyedDataEntry { key, data } in data {
+            self.data.insert(key, data.to_string());
+        }
+        for event in events {
+            self.log_wallet_event(event).await?;
+        }
+
+        Ok(())
+    }
+
     async fn insert_mdocs(&mut self, mdocs: Vec<MdocCopies>) -> StorageResult<()> {
         self.check_query_error()?;
 
@@ -118,6 +156,66 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    async fn replace_mdocs(&mut self, mdocs: Vec<(Uuid, MdocCopies)>) -> StorageResult<()> {
+        self.check_query_error()?;
+
+        let mut replacements: HashMap<_, _> = mdocs.into_iter().collect();
+        self.mdocs
+            .values_mut()
+            .flatten()
+            .enumerate()
+            .for_each(|(index, mdoc_copies)| {
+                if let Some(replacement) = replacements.remove(&Uuid::from_u128(index as u128)) {
+                    *mdoc_copies = replacement;
+                }
+            });
+
+        Ok(())
+    }
+
+    async fn delete_mdoc(&mut self, mdoc_id: Uuid) -> StorageResult<()> {
+        self.check_query_error()?;
+
+        // The `Uuid` of every unique Mdoc is derived from its position, see `fetch_unique_mdocs()` below.
+        let mut index = mdoc_id.as_u128() as usize;
+        for mdoc_copies in self.mdocs.values_mut() {
+            if index < mdoc_copies.len() {
+                mdoc_copies.remove(index);
+                break;
+            }
+            index -= mdoc_copies.len();
+        }
+        self.mdocs.retain(|_, mdoc_copies| !mdoc_copies.is_empty());
+
+        Ok(())
+    }
+
+    async fn delete_mdoc_and_log_event(&mut self, mdoc_id: Uuid, event: WalletEvent) -> StorageResult<()> {
+        self.delete_mdoc(mdoc_id).await?;
+        self.log_wallet_event(event).await
+    }
+
+    async fn delete_mdoc_copies(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()> {
+        self.check_query_error()?;
+
+        // The `Uuid` of every copy is derived from its position, see `fetch_mdoc_copies()` below.
+        let mdoc_copy_ids = mdoc_copy_ids.into_iter().collect::<HashSet<_>>();
+        self.mdocs
+            .values_mut()
+            .flatten()
+            .enumerate()
+            .for_each(|(index, mdoc_copies)| {
+                let mut copy_index = 0;
+                mdoc_copies.cred_copies.retain(|_| {
+                    let mdoc_copy_id = Uuid::from_u64_pair(index as u64, copy_index);
+                    copy_index += 1;
+                    !mdoc_copy_ids.contains(&mdoc_copy_id)
+                });
+            });
+
+        Ok(())
+    }
+
     async fn increment_mdoc_copies_usage_count(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()> {
         mdoc_copy_ids.into_iter().for_each(|mdoc_copy_id| {
             self.mdoc_copies_usage_counts
@@ -129,19 +227,62 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    async fn fetch_mdoc_copies(&self) -> StorageResult<Vec<(StoredMdocCopy, u32)>> {
+        self.check_query_error()?;
+
+        // Get every copy of every unique Mdoc, along with a `Uuid` derived from the position of the Mdoc, a `Uuid`
+        // derived from the position of the copy and its usage count.
+        let mdoc_copies = self
+            .mdocs
+            .values()
+            .flatten()
+            .enumerate()
+            .flat_map(|(index, mdoc_copies)| {
+                mdoc_copies
+                    .cred_copies
+                    .iter()
+                    .enumerate()
+                    .map(move |(copy_index, mdoc)| {
+                        let mdoc_copy_id = Uuid::from_u64_pair(index as u64, copy_index as u64);
+                        let stored_mdoc_copy = StoredMdocCopy {
+                            mdoc_id: Uuid::from_u128(index as u128),
+                            mdoc_copy_id,
+                            mdoc: mdoc.clone(),
+                        };
+
+                        (stored_mdoc_copy, mdoc_copy_id)
+                    })
+            })
+            .map(|(stored_mdoc_copy, mdoc_copy_id)| {
+                let usage_count = self
+                    .mdoc_copies_usage_counts
+                    .get(&mdoc_copy_id)
+                    .copied()
+                    .unwrap_or_default();
+
+                (stored_mdoc_copy, usage_count)
+            })
+            .collect();
+
+        Ok(mdoc_copies)
+    }
+
     async fn fetch_unique_mdocs(&self) -> StorageResult<Vec<StoredMdocCopy>> {
         self.check_query_error()?;
 
-        // Get a single copy of every unique Mdoc, along with a random `Uuid`.
+        // Get a single copy of every unique Mdoc, along with a `Uuid` derived from its position and a random `Uuid`
+        // for the copy.
         let mdocs = self
             .mdocs
             .values()
             .flatten()
-            .flat_map(|mdoc_copies| mdoc_copies.cred_copies.first())
-            .map(|mdoc| StoredMdocCopy {
-                mdoc_id: Uuid::new_v4(),
-                mdoc_copy_id: Uuid::new_v4(),
-                mdoc: mdoc.clone(),
+            .enumerate()
+            .flat_map(|(index, mdoc_copies)| {
+                mdoc_copies.cred_copies.first().map(|mdoc| StoredMdocCopy {
+                    mdoc_id: Uuid::from_u128(index as u128),
+                    mdoc_copy_id: Uuid::new_v4(),
+                    mdoc: mdoc.clone(),
+                })
             })
             .collect();
 
@@ -175,6 +316,7 @@ impl Storage for MockStorage {
         let converted_event = match WalletEventModel::try_from(event.clone())? {
             WalletEventModel::Issuance(entity) => entity.try_into()?,
             WalletEventModel::Disclosure(entity) => entity.try_into()?,
+            WalletEventModel::Deletion(entity) => entity.try_into()?,
         };
         assert_eq!(event, converted_event);
         self.event_log.push(converted_event);
@@ -219,7 +361,7 @@ impl Storage for MockStorage {
         self.check_query_error()?;
 
         let exists = self.event_log.iter().any(|event| match event {
-            WalletEvent::Issuance { .. } => false,
+            WalletEvent::Issuance { .. } | WalletEvent::Deletion { .. } => false,
             WalletEvent::Disclosure { reader_certificate, .. } => reader_certificate == certificate,
         });
         Ok(exists)
@@ -1,3 +1,4 @@
+mod backup;
 mod data;
 mod database;
 mod database_storage;
@@ -20,7 +21,11 @@ use nl_wallet_mdoc::{
 };
 
 pub use self::{
-    data::{InstructionData, KeyedData, RegistrationData, UnlockData, UnlockMethod},
+    backup::{export_backup, restore_backup, BackupError},
+    data::{
+        ChangePinData, DocumentMappingData, InstructionData, IssuanceRefreshData, KeyedData, KeyedDataEntry,
+        PendingIssuanceData, PendingReissuanceData, RegistrationData, UnlockData, UnlockMethod,
+    },
     database_storage::DatabaseStorage,
     event_log::{EventDocuments, EventStatus, WalletEvent},
     key_file::KeyFileError,
@@ -86,9 +91,26 @@ pub trait Storage {
     async fn fetch_data<D: KeyedData>(&self) -> StorageResult<Option<D>>;
     async fn insert_data<D: KeyedData>(&mut self, data: &D) -> StorageResult<()>;
     async fn upsert_data<D: KeyedData>(&mut self, data: &D) -> StorageResult<()>;
+    async fn delete_data<D: KeyedData>(&mut self) -> StorageResult<()>;
+    /// Atomically upsert the specified data entries and log the specified events, e.g. when restoring a backup.
+    async fn upsert_data_and_log_events(
+        &mut self,
+        data: Vec<KeyedDataEntry>,
+        events: Vec<WalletEvent>,
+    ) -> StorageResult<()>;
 
     async fn insert_mdocs(&mut self, mdocs: Vec<MdocCopies>) -> StorageResult<()>;
+    /// Atomically replace all copies of the specified stored mdocs with new copies, e.g. when they have been refreshed.
+    async fn replace_mdocs(&mut self, mdocs: Vec<(Uuid, MdocCopies)>) -> StorageResult<()>;
+    /// Atomically delete the specified stored mdoc, including all of its copies.
+    async fn delete_mdoc(&mut self, mdoc_id: Uuid) -> StorageResult<()>;
+    /// Atomically delete the specified stored mdoc, including all of its copies, and log the accompanying event.
+    async fn delete_mdoc_and_log_event(&mut self, mdoc_id: Uuid, event: WalletEvent) -> StorageResult<()>;
+    /// Delete the specified copies of stored mdocs, e.g. because they have expired or have been disclosed too often.
+    async fn delete_mdoc_copies(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()>;
     async fn increment_mdoc_copies_usage_count(&mut self, mdoc_copy_ids: Vec<Uuid>) -> StorageResult<()>;
+    /// Fetch all copies of the stored mdocs, along with the number of times each of them has been disclosed.
+    async fn fetch_mdoc_copies(&self) -> StorageResult<Vec<(StoredMdocCopy, u32)>>;
     async fn fetch_unique_mdocs(&self) -> StorageResult<Vec<StoredMdocCopy>>;
     async fn fetch_unique_mdocs_by_doctypes(&self, doc_types: &HashSet<&str>) -> StorageResult<Vec<StoredMdocCopy>>;
     async fn has_any_mdocs_with_doctype(&self, doc_type: &str) -> StorageResult<bool>;
@@ -0,0 +1,223 @@
+use tracing::{info, instrument};
+
+use error_category::{sentry_capture_error, ErrorCategory};
+
+use crate::{
+    storage::{self, BackupError, PendingReissuanceData, Storage, StorageError},
+    Document,
+};
+
+use super::{
+    history::{documents_from_event_documents, fetch_document_mappings, EventConversionError, EventStorageError},
+    Wallet,
+};
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(defer)]
+pub enum WalletBackupError {
+    #[error("wallet is not registered")]
+    #[category(expected)]
+    NotRegistered,
+    #[error("wallet is locked")]
+    #[category(expected)]
+    Locked,
+    #[error("{0}")]
+    Backup(#[from] BackupError),
+    #[error("could not read restored documents from database storage: {0}")]
+    Storage(#[from] StorageError),
+    #[error("could not emit restored history: {0}")]
+    EventStorage(#[from] EventStorageError),
+    #[error("could not convert restored documents for display: {0}")]
+    Conversion(#[from] EventConversionError),
+}
+
+impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
+where
+    S: Storage,
+{
+    fn check_backup_preconditions(&self) -> Result<(), WalletBackupError> {
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(WalletBackupError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(WalletBackupError::Locked);
+        }
+
+        Ok(())
+    }
+
+    /// Export an encrypted backup of the wallet, using a key derived from `passphrase`. See [`storage::export_backup`]
+    /// for what is included in the backup.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn export_backup(&self, passphrase: &str) -> Result<Vec<u8>, WalletBackupError> {
+        self.check_backup_preconditions()?;
+
+        info!("Exporting backup from storage");
+        let archive = storage::export_backup(&*self.storage.read().await, passphrase).await?;
+
+        Ok(archive)
+    }
+
+    /// Restore a backup produced by [`Wallet::export_backup`] into a freshly registered wallet. The documents that
+    /// were contained in the backup have to be issued again, as the mdocs themselves are not included. These are
+    /// available through [`Wallet::pending_reissuance_documents`].
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn restore_backup(&mut self, archive: &[u8], passphrase: &str) -> Result<(), WalletBackupError> {
+        self.check_backup_preconditions()?;
+
+        info!("Restoring backup into storage");
+        let storage = self.storage.get_mut();
+        storage::restore_backup(storage, archive, passphrase).await?;
+
+        let reissuance_count = storage
+            .fetch_data::<PendingReissuanceData>()
+            .await?
+            .map(|data| data.documents.0.len())
+            .unwrap_or_default();
+        info!("Restored backup, {} documents need to be reissued", reissuance_count);
+
+        self.emit_recent_history().await?;
+
+        Ok(())
+    }
+
+    /// Return the documents from a restored backup that have not been issued again yet. The app should offer to
+    /// start issuance for these documents, as each of them is removed from this list once a document of the same
+    /// doc type has been issued to the wallet.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn pending_reissuance_documents(&self) -> Result<Vec<Document>, WalletBackupError> {
+        self.check_backup_preconditions()?;
+
+        info!("Fetching documents pending reissuance from storage");
+        let storage = self.storage.read().await;
+        let Some(pending) = storage.fetch_data::<PendingReissuanceData>().await? else {
+            return Ok(Vec::new());
+        };
+        let mappings = fetch_document_mappings(&*storage).await?;
+
+        let documents = documents_from_event_documents(pending.documents, &mappings)?;
+
+        Ok(documents)
+    }
+
+    /// Remove the documents with the specified doc types from the documents that are pending reissuance, as these
+    /// have just been issued.
+    pub(super) async fn remove_pending_reissuances(&mut self, doc_types: &[String]) -> Result<(), StorageError> {
+        let storage = self.storage.get_mut();
+        let Some(mut pending) = storage.fetch_data::<PendingReissuanceData>().await? else {
+            return Ok(());
+        };
+
+        let count = pending.documents.0.len();
+        pending.documents.0.retain(|doc_type, _| !doc_types.contains(doc_type));
+
+        if pending.documents.0.len() == count {
+            return Ok(());
+        }
+
+        info!("Removing reissued documents from pending reissuances");
+        if pending.documents.0.is_empty() {
+            storage.delete_data::<PendingReissuanceData>().await
+        } else {
+            storage.upsert_data(&pending).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use chrono::Utc;
+
+    use nl_wallet_mdoc::holder::MdocCopies;
+
+    use crate::storage::{MockStorage, StorageState, WalletEvent};
+
+    use super::{
+        super::test::{self, WalletWithMocks},
+        *,
+    };
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    #[tokio::test]
+    async fn test_wallet_backup_round_trip() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        let mdoc = test::create_full_pid_mdoc().await;
+        let doc_type = mdoc.doc_type.clone();
+        let event =
+            WalletEvent::issuance_from_str(vec!["com.example.pid"], Utc::now(), mdoc.issuer_certificate().unwrap());
+        {
+            let mut storage = wallet.storage.write().await;
+            storage.insert_mdocs(vec![MdocCopies::from(vec![mdoc])]).await.unwrap();
+            storage.log_wallet_event(event.clone()).await.unwrap();
+        }
+
+        let archive = wallet.export_backup(PASSPHRASE).await.unwrap();
+
+        // Replace the storage with empty storage, as on a new device.
+        let registration = wallet.registration.as_ref().unwrap().data.clone();
+        *wallet.storage.get_mut() = MockStorage::new(StorageState::Opened, Some(registration));
+
+        // Restoring the backup should emit the restored history.
+        let events = test::setup_mock_recent_history_callback(&mut wallet)
+            .await
+            .expect("Failed to set mock recent history callback");
+
+        wallet.restore_backup(&archive, PASSPHRASE).await.unwrap();
+
+        assert_eq!(
+            wallet.storage.read().await.fetch_wallet_events().await.unwrap(),
+            vec![event]
+        );
+        {
+            let events = events.lock();
+
+            assert_eq!(events.len(), 2);
+            assert_eq!(events[1].len(), 1);
+        }
+
+        // The document contained in the backup should be pending reissuance, until it has been issued again.
+        let documents = wallet.pending_reissuance_documents().await.unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].doc_type, doc_type);
+
+        wallet.remove_pending_reissuances(&[doc_type]).await.unwrap();
+
+        assert!(wallet.pending_reissuance_documents().await.unwrap().is_empty());
+        assert!(wallet
+            .storage
+            .read()
+            .await
+            .fetch_data::<PendingReissuanceData>()
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_backup_error() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let archive = wallet.export_backup(PASSPHRASE).await.unwrap();
+
+        let error = wallet.restore_backup(&archive, "wrong passphrase").await.unwrap_err();
+        assert_matches!(error, WalletBackupError::Backup(BackupError::Decryption(_)));
+
+        wallet.lock.lock();
+        let error = wallet.export_backup(PASSPHRASE).await.unwrap_err();
+        assert_matches!(error, WalletBackupError::Locked);
+        let error = wallet.pending_reissuance_documents().await.unwrap_err();
+        assert_matches!(error, WalletBackupError::Locked);
+
+        let wallet = WalletWithMocks::new_unregistered().await;
+        let error = wallet.export_backup(PASSPHRASE).await.unwrap_err();
+        assert_matches!(error, WalletBackupError::NotRegistered);
+    }
+}
@@ -0,0 +1,469 @@
+use tracing::{info, instrument, warn};
+
+use error_category::{sentry_capture_error, ErrorCategory};
+use platform_support::hw_keystore::PlatformEcdsaKey;
+use wallet_common::{
+    account::messages::instructions::{ChangePin, ChangePinCommit, ChangePinRollback, InstructionEndpoint},
+    jwt::JwtError,
+};
+
+use crate::{
+    account_provider::AccountProviderClient,
+    config::ConfigurationRepository,
+    instruction::{InstructionClient, InstructionError},
+    pin::{
+        key::{self as pin_key, PinKey, PinKeyError},
+        validation::{validate_pin, PinValidationError},
+    },
+    storage::{ChangePinData, RegistrationData, Storage, StorageError},
+};
+
+use super::Wallet;
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(defer)]
+pub enum ChangePinError {
+    #[error("wallet is not registered")]
+    #[category(expected)]
+    NotRegistered,
+    #[error("wallet is locked")]
+    #[category(expected)]
+    Locked,
+    #[error("new PIN does not adhere to requirements: {0}")]
+    InvalidPin(#[from] PinValidationError),
+    #[error("could not derive new PIN public key: {0}")]
+    #[category(critical)]
+    PinKey(#[from] PinKeyError),
+    #[error("error sending instruction to Wallet Provider: {0}")]
+    Instruction(#[from] InstructionError),
+    #[error("could not validate wallet certificate received from Wallet Provider: {0}")]
+    CertificateValidation(#[source] JwtError),
+    #[error("could not write or read PIN change state to or from database: {0}")]
+    Storage(#[from] StorageError),
+}
+
+impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
+where
+    CR: ConfigurationRepository,
+    S: Storage,
+    PEK: PlatformEcdsaKey,
+    APC: AccountProviderClient,
+{
+    async fn send_change_pin_instruction<I>(&self, pin: String, instruction: I) -> Result<I::Result, ChangePinError>
+    where
+        I: InstructionEndpoint + 'static,
+    {
+        let registration = self.registration.as_ref().ok_or(ChangePinError::NotRegistered)?;
+
+        let config = self.config_repository.config();
+        let instruction_result_public_key = config.account_server.instruction_result_public_key.clone().into();
+
+        let remote_instruction = InstructionClient::new(
+            pin,
+            &self.storage,
+            &registration.hw_privkey,
+            &self.account_provider_client,
+            &registration.data,
+            &config.account_server.base_url,
+            &instruction_result_public_key,
+        );
+
+        let result = remote_instruction.send(instruction).await?;
+
+        Ok(result)
+    }
+
+    /// Store the registration data received from the Wallet Provider after a successful [`ChangePin`] instruction,
+    /// then commit the PIN change using the new PIN.
+    async fn commit_pin_change(
+        &mut self,
+        new_pin: String,
+        registration: RegistrationData,
+    ) -> Result<(), ChangePinError> {
+        let storage = self.storage.get_mut();
+        storage.upsert_data(&registration).await?;
+
+        self.registration.as_mut().ok_or(ChangePinError::NotRegistered)?.data = registration;
+
+        info!("Sending change pin commit instruction to Wallet Provider");
+
+        self.send_change_pin_instruction(new_pin, ChangePinCommit).await?;
+        self.storage.get_mut().delete_data::<ChangePinData>().await?;
+
+        Ok(())
+    }
+
+    /// Finish a PIN change that was interrupted, using the PIN that matches the currently stored registration data.
+    /// A PIN change that was not yet accepted by the Wallet Provider is rolled back, while a PIN change that was
+    /// accepted is committed. Returns `true` if there was an interrupted PIN change.
+    pub(super) async fn finish_pending_pin_change(&mut self, pin: String) -> Result<bool, ChangePinError> {
+        if self.registration.is_none() {
+            return Err(ChangePinError::NotRegistered);
+        }
+
+        let change_pin_data = self.storage.read().await.fetch_data::<ChangePinData>().await?;
+
+        match change_pin_data {
+            None => return Ok(false),
+            Some(ChangePinData::Begin) => {
+                info!("Sending change pin rollback instruction to Wallet Provider");
+
+                self.send_change_pin_instruction(pin, ChangePinRollback).await?;
+                self.storage.get_mut().delete_data::<ChangePinData>().await?;
+            }
+            Some(ChangePinData::Commit { registration }) => {
+                self.commit_pin_change(pin, registration).await?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn change_pin(&mut self, old_pin: String, new_pin: String) -> Result<(), ChangePinError> {
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(ChangePinError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(ChangePinError::Locked);
+        }
+
+        info!("Validating new PIN");
+        validate_pin(&new_pin)?;
+
+        // Make sure that a previous PIN change is no longer in progress, as the Wallet Provider will refuse to
+        // change the PIN again until it is either committed or rolled back.
+        self.finish_pending_pin_change(old_pin.clone()).await?;
+
+        info!("Storing PIN change state");
+
+        // Record that a PIN change has started, so that it can be rolled back if the wallet does not receive the
+        // response of the Wallet Provider.
+        self.storage.get_mut().upsert_data(&ChangePinData::Begin).await?;
+
+        let pin_salt = pin_key::new_pin_salt();
+        let pin_pubkey = PinKey::new(&new_pin, &pin_salt).verifying_key()?;
+
+        info!("Sending change pin instruction to Wallet Provider");
+
+        let wallet_certificate = self
+            .send_change_pin_instruction(
+                old_pin,
+                ChangePin {
+                    pin_pubkey: pin_pubkey.into(),
+                },
+            )
+            .await?;
+
+        info!("New wallet certificate received from Wallet Provider, verifying contents");
+
+        let certificate_public_key = self
+            .config_repository
+            .config()
+            .account_server
+            .certificate_public_key
+            .clone();
+        wallet_certificate
+            .parse_and_verify_with_sub(&certificate_public_key.into())
+            .map_err(ChangePinError::CertificateValidation)?;
+
+        info!("Storing new registration data");
+
+        // From here on, the Wallet Provider only accepts the new PIN. Store the new registration data as part of the
+        // PIN change state first, so that the PIN change can be committed later if the wallet is interrupted.
+        let registration = RegistrationData {
+            pin_salt,
+            wallet_certificate,
+        };
+        self.storage
+            .get_mut()
+            .upsert_data(&ChangePinData::Commit {
+                registration: registration.clone(),
+            })
+            .await?;
+
+        // The new PIN is in effect at this point, so a failure to commit the PIN change is not reported to the user.
+        // The commit will be retried when the wallet is unlocked.
+        if let Err(error) = self.commit_pin_change(new_pin, registration).await {
+            warn!("Could not commit PIN change: {error}");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use http::StatusCode;
+    use parking_lot::Mutex;
+
+    use serde::{de::DeserializeOwned, Serialize};
+    use wallet_common::{
+        account::{
+            messages::{
+                auth::{WalletCertificate, WalletCertificateClaims},
+                instructions::{Instruction, InstructionResultClaims},
+            },
+            signed::SequenceNumberComparison,
+        },
+        jwt::Jwt,
+        keys::EcdsaKey,
+        utils,
+    };
+
+    use crate::account_provider::{AccountProviderError, AccountProviderResponseError};
+
+    use super::{
+        super::test::{WalletWithMocks, ACCOUNT_SERVER_KEYS},
+        *,
+    };
+
+    const OLD_PIN: &str = "051097";
+    const NEW_PIN: &str = "112233";
+
+    async fn instruction_result<T: Serialize + DeserializeOwned>(result: T) -> Jwt<InstructionResultClaims<T>> {
+        let result_claims = InstructionResultClaims {
+            result,
+            iss: "wallet_unit_test".to_string(),
+            iat: jsonwebtoken::get_current_timestamp(),
+        };
+
+        Jwt::sign_with_sub(&result_claims, &ACCOUNT_SERVER_KEYS.instruction_result_signing_key)
+            .await
+            .unwrap()
+    }
+
+    /// Generates a new certificate for the hardware key of the `Wallet`, as the Wallet Provider does when the PIN
+    /// is changed.
+    async fn new_wallet_certificate(wallet: &WalletWithMocks) -> WalletCertificate {
+        let registration = wallet.registration.as_ref().unwrap();
+        let claims = WalletCertificateClaims {
+            pin_pubkey_hash: utils::random_bytes(32),
+            iat: jsonwebtoken::get_current_timestamp(),
+            ..registration
+                .data
+                .wallet_certificate
+                .parse_and_verify_with_sub(&ACCOUNT_SERVER_KEYS.certificate_signing_key.verifying_key().into())
+                .unwrap()
+        };
+
+        Jwt::sign_with_sub(&claims, &ACCOUNT_SERVER_KEYS.certificate_signing_key)
+            .await
+            .unwrap()
+    }
+
+    fn setup_instruction_challenges(wallet: &mut WalletWithMocks, count: usize) -> Vec<u8> {
+        let challenge = utils::random_bytes(32);
+
+        let challenge_response = challenge.clone();
+        wallet
+            .account_provider_client
+            .expect_instruction_challenge()
+            .times(count)
+            .returning(move |_, _| Ok(challenge_response.clone()));
+
+        challenge
+    }
+
+    #[tokio::test]
+    async fn test_wallet_change_pin() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let challenge = setup_instruction_challenges(&mut wallet, 2);
+
+        let registration = wallet.registration.as_ref().unwrap();
+        let old_pin_pubkey = PinKey::new(OLD_PIN, &registration.data.pin_salt)
+            .verifying_key()
+            .unwrap();
+        let hw_pubkey = registration.hw_privkey.verifying_key().await.unwrap();
+        let new_certificate = new_wallet_certificate(&wallet).await;
+
+        // The new PIN public key is sent to the Wallet Provider in an instruction signed with the old PIN.
+        let new_pin_pubkey = Arc::new(Mutex::new(None));
+
+        let result = instruction_result(new_certificate.clone()).await;
+        let change_pin_challenge = challenge.clone();
+        let change_pin_new_pin_pubkey = Arc::clone(&new_pin_pubkey);
+        wallet.account_provider_client.expect_instruction().return_once(
+            move |_, instruction: Instruction<ChangePin>| {
+                let payload = instruction
+                    .instruction
+                    .parse_and_verify(
+                        &change_pin_challenge,
+                        SequenceNumberComparison::LargerThan(1),
+                        &hw_pubkey,
+                        &old_pin_pubkey,
+                    )
+                    .expect("Could not verify change pin instruction");
+                change_pin_new_pin_pubkey.lock().replace(payload.payload.pin_pubkey.0);
+
+                Ok(result)
+            },
+        );
+
+        // The PIN change is committed with an instruction signed with the new PIN.
+        let result = instruction_result(()).await;
+        wallet.account_provider_client.expect_instruction().return_once(
+            move |_, instruction: Instruction<ChangePinCommit>| {
+                let new_pin_pubkey = new_pin_pubkey.lock().take().unwrap();
+                instruction
+                    .instruction
+                    .parse_and_verify(
+                        &challenge,
+                        SequenceNumberComparison::LargerThan(3),
+                        &hw_pubkey,
+                        &new_pin_pubkey,
+                    )
+                    .expect("Could not verify change pin commit instruction");
+
+                Ok(result)
+            },
+        );
+
+        wallet
+            .change_pin(OLD_PIN.to_string(), NEW_PIN.to_string())
+            .await
+            .expect("Could not change PIN");
+
+        // The new registration data should be stored, both in memory and in the database.
+        let registration = &wallet.registration.as_ref().unwrap().data;
+        assert_eq!(registration.wallet_certificate.0, new_certificate.0);
+
+        let storage = wallet.storage.read().await;
+        let stored_registration = storage.fetch_data::<RegistrationData>().await.unwrap().unwrap();
+        assert_eq!(stored_registration.pin_salt, registration.pin_salt);
+        assert!(storage.fetch_data::<ChangePinData>().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_change_pin_error_locked() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet.lock();
+
+        let error = wallet
+            .change_pin(OLD_PIN.to_string(), NEW_PIN.to_string())
+            .await
+            .expect_err("Changing PIN should fail");
+
+        assert_matches!(error, ChangePinError::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_wallet_change_pin_error_invalid_pin() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        let error = wallet
+            .change_pin(OLD_PIN.to_string(), "111111".to_string())
+            .await
+            .expect_err("Changing PIN should fail");
+
+        assert_matches!(error, ChangePinError::InvalidPin(_));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_change_pin_error_instruction_rollback() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        setup_instruction_challenges(&mut wallet, 2);
+
+        let old_registration = wallet.registration.as_ref().unwrap().data.clone();
+
+        wallet
+            .account_provider_client
+            .expect_instruction()
+            .return_once(|_, _: Instruction<ChangePin>| {
+                Err(AccountProviderError::Response(AccountProviderResponseError::Status(
+                    StatusCode::BAD_GATEWAY,
+                )))
+            });
+
+        let error = wallet
+            .change_pin(OLD_PIN.to_string(), NEW_PIN.to_string())
+            .await
+            .expect_err("Changing PIN should fail");
+
+        assert_matches!(error, ChangePinError::Instruction(_));
+
+        // The PIN change state should be kept, as the wallet does not know if the Wallet Provider changed the PIN.
+        assert_matches!(
+            wallet.storage.read().await.fetch_data::<ChangePinData>().await.unwrap(),
+            Some(ChangePinData::Begin)
+        );
+
+        // Unlocking the wallet with the old PIN should roll back the PIN change.
+        wallet.lock();
+
+        let result = instruction_result(()).await;
+        wallet
+            .account_provider_client
+            .expect_instruction()
+            .return_once(move |_, _: Instruction<ChangePinRollback>| Ok(result));
+
+        wallet
+            .unlock(OLD_PIN.to_string())
+            .await
+            .expect("Could not unlock wallet");
+
+        assert!(!wallet.is_locked());
+        assert_eq!(
+            wallet.registration.as_ref().unwrap().data.pin_salt,
+            old_registration.pin_salt
+        );
+        assert!(wallet
+            .storage
+            .read()
+            .await
+            .fetch_data::<ChangePinData>()
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_change_pin_commit_on_unlock() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        setup_instruction_challenges(&mut wallet, 1);
+
+        // Simulate a PIN change that was accepted by the Wallet Provider, but not yet committed.
+        let registration = RegistrationData {
+            pin_salt: pin_key::new_pin_salt(),
+            wallet_certificate: new_wallet_certificate(&wallet).await,
+        };
+        wallet
+            .storage
+            .get_mut()
+            .upsert_data(&ChangePinData::Commit {
+                registration: registration.clone(),
+            })
+            .await
+            .unwrap();
+        wallet.lock();
+
+        let result = instruction_result(()).await;
+        wallet
+            .account_provider_client
+            .expect_instruction()
+            .return_once(move |_, _: Instruction<ChangePinCommit>| Ok(result));
+
+        wallet
+            .unlock(NEW_PIN.to_string())
+            .await
+            .expect("Could not unlock wallet");
+
+        assert!(!wallet.is_locked());
+        assert_eq!(
+            wallet.registration.as_ref().unwrap().data.pin_salt,
+            registration.pin_salt
+        );
+
+        let storage = wallet.storage.read().await;
+        let stored_registration = storage.fetch_data::<RegistrationData>().await.unwrap().unwrap();
+        assert_eq!(stored_registration.pin_salt, registration.pin_salt);
+        assert!(storage.fetch_data::<ChangePinData>().await.unwrap().is_none());
+    }
+}
@@ -21,9 +21,9 @@ use crate::{
         DisclosureUriError, DisclosureUriSource, MdocDisclosureError, MdocDisclosureMissingAttributes,
         MdocDisclosureProposal, MdocDisclosureSession, MdocDisclosureSessionState,
     },
-    document::{DisclosureDocument, DisclosureType, DocumentMdocError, MissingDisclosureAttributes},
+    document::{DisclosureDocument, DisclosureType, DocumentMappings, DocumentMdocError, MissingDisclosureAttributes},
     instruction::{InstructionClient, InstructionError, RemoteEcdsaKeyError, RemoteEcdsaKeyFactory},
-    storage::{EventStatus, Storage, StorageError, StoredMdocCopy, WalletEvent},
+    storage::{DocumentMappingData, EventStatus, Storage, StorageError, StoredMdocCopy, WalletEvent},
 };
 
 use super::{history::EventStorageError, Wallet};
@@ -55,6 +55,8 @@ pub enum DisclosureError {
     VpDisclosureSession(#[from] VpClientError),
     #[error("could not fetch if attributes were shared before: {0}")]
     HistoryRetrieval(#[source] StorageError),
+    #[error("could not fetch document mappings from database: {0}")]
+    DocumentMappingRetrieval(#[source] StorageError),
     #[error("not all requested attributes are available, missing: {missing_attributes:?}")]
     #[category(pd)] // Might reveal information about what attributes are stored in the Wallet
     AttributesNotAvailable {
@@ -145,49 +147,45 @@ where
         // Start the disclosure session based on the parsed disclosure URI.
         let session = MDS::start(disclosure_uri, source, self, &config.rp_trust_anchors()).await?;
 
-        let shared_data_with_relying_party_before = self
-            .storage
-            .read()
-            .await
+        let storage = self.storage.read().await;
+        let shared_data_with_relying_party_before = storage
             .did_share_data_with_relying_party(session.rp_certificate())
             .await
             .map_err(DisclosureError::HistoryRetrieval)?;
+        let mappings = storage
+            .fetch_data::<DocumentMappingData>()
+            .await
+            .map_err(DisclosureError::DocumentMappingRetrieval)?
+            .unwrap_or_default();
+        drop(storage);
 
         let proposal_session = match session.session_state() {
             MdocDisclosureSessionState::MissingAttributes(missing_attr_session) => {
                 // Translate the missing attributes into a `Vec<MissingDisclosureAttributes>`.
-                // If this fails, return `DisclosureError::AttributeMdoc` instead.
                 info!(
-                    "At least one attribute is missing in order to satisfy the disclosure request, attempting to \
-                     translate to MissingDisclosureAttributes"
+                    "At least one attribute is missing in order to satisfy the disclosure request, translating to \
+                     MissingDisclosureAttributes"
                 );
 
                 let missing_attributes = missing_attr_session.missing_attributes().to_vec();
+                // As the issuer of missing attributes is not known, only the built-in mappings can be used for them.
+                let attributes = MissingDisclosureAttributes::from_mdoc_missing_attributes(
+                    missing_attributes,
+                    &DocumentMappings::default(),
+                );
+
+                // Store the session so that it will only be terminated on user interaction.
+                // This prevents gleaning of missing attributes by a verifier.
                 let session_type = session.session_type();
-                let error = match MissingDisclosureAttributes::from_mdoc_missing_attributes(missing_attributes) {
-                    Ok(attributes) => {
-                        // If the missing attributes can be translated and shown to the user,
-                        // store the session so that it will only be terminated on user interaction.
-                        // This prevents gleaning of missing attributes by a verifier.
-                        let reader_registration = session.reader_registration().clone().into();
-                        self.disclosure_session.replace(session);
-
-                        DisclosureError::AttributesNotAvailable {
-                            reader_registration,
-                            missing_attributes: attributes,
-                            shared_data_with_relying_party_before,
-                            session_type,
-                        }
-                    }
-                    // NB: It is a known limitation that, if the missing attributes cannot be
-                    //     translated, the missing attributes cannot be presented to the user,
-                    //     thus user interaction cannot terminate the disclosure session. As
-                    //     a precaution to prevent gleaning of missing attributes we will
-                    //     simply never respond to the verifier in this case.
-                    Err(error) => DisclosureError::MdocAttributes(error),
-                };
-
-                return Err(error);
+                let reader_registration = session.reader_registration().clone().into();
+                self.disclosure_session.replace(session);
+
+                return Err(DisclosureError::AttributesNotAvailable {
+                    reader_registration,
+                    missing_attributes: attributes,
+                    shared_data_with_relying_party_before,
+                    session_type,
+                });
             }
             MdocDisclosureSessionState::Proposal(proposal_session) => proposal_session,
         };
@@ -202,7 +200,10 @@ where
         // Prepare a `Vec<ProposedDisclosureDocument>` to report to the caller.
         let documents: Vec<DisclosureDocument> = proposed_attributes
             .into_iter()
-            .map(|(doc_type, attributes)| DisclosureDocument::from_mdoc_attributes(&doc_type, attributes))
+            .map(|(doc_type, attributes)| {
+                let issuer_mappings = mappings.issuer_mappings(&attributes.issuer);
+                DisclosureDocument::from_mdoc_attributes(&doc_type, attributes, issuer_mappings)
+            })
             .collect::<Result<_, _>>()
             .map_err(DisclosureError::MdocAttributes)?;
 
@@ -499,7 +500,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{atomic::Ordering, Arc, LazyLock};
+    use std::{
+        collections::HashMap,
+        sync::{atomic::Ordering, Arc, LazyLock},
+    };
 
     use assert_matches::assert_matches;
     use itertools::Itertools;
@@ -522,6 +526,7 @@ mod tests {
     use crate::{
         config::UNIVERSAL_LINK_BASE_URL,
         disclosure::{MockMdocDisclosureMissingAttributes, MockMdocDisclosureProposal, MockMdocDisclosureSession},
+        document::AttributeValueType,
         Attribute, AttributeValue, EventStatus, HistoryEvent,
     };
 
@@ -588,12 +593,12 @@ mod tests {
         assert_matches!(
             document.attributes.first().unwrap(),
             (
-                &"age_over_18",
+                key,
                 Attribute {
                     key_labels: _,
                     value: AttributeValue::Boolean(true)
                 }
-            )
+            ) if key == "age_over_18"
         );
 
         // Starting disclosure should not cause mdoc copy usage counts to be incremented.
@@ -776,7 +781,8 @@ mod tests {
         );
 
         // Starting disclosure where an attribute that is both unavailable
-        // and unknown is requested should result in an error.
+        // and unknown is requested should result in an error that labels
+        // the attribute using its name.
         let error = wallet
             .start_disclosure(&DISCLOSURE_URI, DisclosureUriSource::Link)
             .await
@@ -784,14 +790,14 @@ mod tests {
 
         assert_matches!(
             error,
-            DisclosureError::MdocAttributes(DocumentMdocError::UnknownAttribute {
-                doc_type,
-                name_space,
-                name,
-                value: None,
-            }) if doc_type == "com.example.pid" && name_space == "com.example.pid" && name == "foobar"
+            DisclosureError::AttributesNotAvailable {
+                missing_attributes,
+                ..
+            } if missing_attributes[0].doc_type == "com.example.pid" &&
+                 missing_attributes[0].attributes.get("foobar") ==
+                    Some(&HashMap::from([("".to_string(), "foobar".to_string())]))
         );
-        assert!(wallet.disclosure_session.is_none());
+        assert!(wallet.disclosure_session.is_some());
     }
 
     #[tokio::test]
@@ -806,8 +812,8 @@ mod tests {
                 attributes: IndexMap::from([(
                     "com.example.pid".to_string(),
                     vec![Entry {
-                        name: "foo".to_string(),
-                        value: DataElementValue::Text("bar".to_string()),
+                        name: "given_name".to_string(),
+                        value: DataElementValue::Bool(true),
                     }],
                 )]),
                 issuer: ISSUER_KEY.issuance_key.certificate().clone(),
@@ -824,7 +830,7 @@ mod tests {
             None,
         );
 
-        // Starting disclosure where unknown attributes are requested should result in an error.
+        // Starting disclosure where attributes of the wrong type are requested should result in an error.
         let error = wallet
             .start_disclosure(&DISCLOSURE_URI, DisclosureUriSource::Link)
             .await
@@ -832,12 +838,13 @@ mod tests {
 
         assert_matches!(
             error,
-            DisclosureError::MdocAttributes(DocumentMdocError::UnknownAttribute {
+            DisclosureError::MdocAttributes(DocumentMdocError::AttributeValueTypeMismatch {
                 doc_type,
                 name_space,
                 name,
-                value: Some(DataElementValue::Text(value)),
-            }) if doc_type == "com.example.pid" && name_space == "com.example.pid" && name == "foo" && value == "bar"
+                expected_type: AttributeValueType::String,
+                value: DataElementValue::Bool(true),
+            }) if doc_type == "com.example.pid" && name_space == "com.example.pid" && name == "given_name"
         );
         assert!(wallet.disclosure_session.is_none());
     }
@@ -1,4 +1,5 @@
-use tracing::info;
+use tracing::{info, instrument};
+use uuid::Uuid;
 
 use error_category::{sentry_capture_error, ErrorCategory};
 use nl_wallet_mdoc::utils::{
@@ -6,13 +7,20 @@ use nl_wallet_mdoc::utils::{
     issuer_auth::IssuerRegistration,
     x509::{CertificateError, MdocCertificateExtension},
 };
+use openid4vc::{
+    issuance_session::IssuanceSession,
+    token::{AttestationPreview, AttestationPreviewError},
+};
 
 use crate::{
-    document::{Document, DocumentPersistence},
-    storage::{Storage, StorageError, StoredMdocCopy},
+    document::{Document, DocumentMdocError, DocumentPersistence},
+    storage::{
+        DocumentMappingData, IssuanceRefreshData, PendingIssuanceData, Storage, StorageError, StoredMdocCopy,
+        WalletEvent,
+    },
 };
 
-use super::Wallet;
+use super::{history::EventStorageError, Wallet};
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
 #[category(defer)]
@@ -26,6 +34,32 @@ pub enum DocumentsError {
     #[error("X.509 certificate does not contain IssuerRegistration")]
     #[category(critical)]
     MissingIssuerRegistration,
+    #[error("could not read issuer registration of pending document: {0}")]
+    AttestationPreview(#[from] AttestationPreviewError),
+    #[error("could not interpret attributes of pending document: {0}")]
+    PendingDocument(#[source] DocumentMdocError),
+}
+
+#[derive(Debug, thiserror::Error, ErrorCategory)]
+#[category(defer)]
+pub enum DocumentDeletionError {
+    #[error("wallet is not registered")]
+    #[category(expected)]
+    NotRegistered,
+    #[error("wallet is locked")]
+    #[category(expected)]
+    Locked,
+    #[error("document not found: {0}")]
+    #[category(critical)]
+    NotFound(String),
+    #[error("could not delete document from database storage: {0}")]
+    Storage(#[from] StorageError),
+    #[error("could not read issuer certificate of document: {0}")]
+    IssuerCertificate(#[source] CoseError),
+    #[error("could not store history in database: {0}")]
+    EventStorage(#[from] EventStorageError),
+    #[error("could not emit documents after deletion: {0}")]
+    Documents(#[from] DocumentsError),
 }
 
 pub type DocumentsCallback = Box<dyn FnMut(Vec<Document>) + Send + Sync>;
@@ -38,11 +72,11 @@ where
         info!("Emit mdocs from storage");
 
         let storage = self.storage.read().await;
+        let mappings = storage.fetch_data::<DocumentMappingData>().await?.unwrap_or_default();
 
         // Note that this currently panics whenever conversion from Mdoc to Documents fails,
-        // as we assume that the (hardcoded) mapping will always be backwards compatible.
-        // This is particularly important when this mapping comes from a trusted registry
-        // in the near future!
+        // as we assume that the mapping, which is either built-in or derived from the metadata
+        // of the issuer at the time of issuance, will always be backwards compatible.
         let mut documents = storage
             .fetch_unique_mdocs()
             .await?
@@ -56,12 +90,36 @@ where
                     &mdoc.doc_type,
                     mdoc.attributes(),
                     issuer_registration,
+                    mappings.issuer_mappings(&issuer_certificate),
                 )
                 .expect("Could not interpret stored mdoc attributes");
                 Ok(document)
             })
             .collect::<Result<Vec<_>, DocumentsError>>()?;
 
+        // Documents of which the issuer deferred issuance are included based on their attestation previews,
+        // so that they can be shown as pending.
+        let pending_issuances = storage
+            .fetch_data::<PendingIssuanceData>()
+            .await?
+            .unwrap_or_default()
+            .issuances;
+        for pending_issuance in pending_issuances {
+            for preview in pending_issuance.attestation_previews() {
+                let AttestationPreview::MsoMdoc { issuer, .. } = preview;
+                let issuer_mappings = mappings.issuer_mappings(issuer);
+                let (unsigned_mdoc, issuer_registration) = preview.clone().try_into()?;
+                // Unlike stored mdocs, the previews are not checked against the mappings when the issuer defers
+                // issuance, so failing to interpret them is an error instead of a panic.
+                let document = Document {
+                    persistence: DocumentPersistence::Pending(pending_issuance.transaction_id().to_string()),
+                    ..Document::from_unsigned_mdoc(unsigned_mdoc, *issuer_registration, issuer_mappings)
+                        .map_err(DocumentsError::PendingDocument)?
+                };
+                documents.push(document);
+            }
+        }
+
         documents.sort_by_key(Document::priority);
 
         if let Some(ref mut callback) = self.documents_callback {
@@ -90,14 +148,96 @@ where
     }
 }
 
+impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
+where
+    S: Storage,
+    IS: IssuanceSession,
+{
+    /// Delete a stored document, identified by the ID contained in its [`DocumentPersistence::Stored`], along with
+    /// all of its copies. The private keys of the copies are held by the Wallet Provider and can only be used by
+    /// referring to their identifiers, which are stored within the copies. Note that these keys are only forgotten by
+    /// the wallet as part of the deletion, they are not deleted at the Wallet Provider.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn delete_document(&mut self, id: &str) -> Result<(), DocumentDeletionError> {
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(DocumentDeletionError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(DocumentDeletionError::Locked);
+        }
+
+        let mdoc_id = Uuid::parse_str(id).map_err(|_| DocumentDeletionError::NotFound(id.to_string()))?;
+
+        info!("Fetching document to be deleted from storage");
+        let mdoc = self
+            .storage
+            .read()
+            .await
+            .fetch_unique_mdocs()
+            .await?
+            .into_iter()
+            .find(|stored_mdoc| stored_mdoc.mdoc_id == mdoc_id)
+            .map(|StoredMdocCopy { mdoc, .. }| mdoc)
+            .ok_or_else(|| DocumentDeletionError::NotFound(id.to_string()))?;
+
+        let key_id = mdoc.private_key_id().to_string();
+
+        // Prepare the event before deleting the document, as the attributes are needed for it.
+        let event = WalletEvent::new_deletion(
+            vec![mdoc]
+                .try_into()
+                .map_err(DocumentDeletionError::IssuerCertificate)?,
+        );
+
+        let refreshes = self
+            .storage
+            .read()
+            .await
+            .fetch_data::<IssuanceRefreshData>()
+            .await?
+            .unwrap_or_default()
+            .refreshes;
+
+        info!("Deleting document from storage and storing history event");
+        self.storage.get_mut().delete_mdoc_and_log_event(mdoc_id, event).await?;
+
+        Self::notify_issuer_of_deletion(&refreshes, &key_id).await;
+
+        self.emit_recent_history().await?;
+        self.emit_documents().await?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use assert_matches::assert_matches;
+    use mockall::predicate::{always, eq};
+    use p256::{ecdsa::SigningKey, elliptic_curve::rand_core::OsRng};
+    use serial_test::serial;
+
+    use nl_wallet_mdoc::DataElementValue;
+    use openid4vc::{
+        credential::NotificationEvent,
+        issuance_session::{DeferredIssuance, IssuanceRefresh},
+        mock::MockIssuanceSession,
+        token::AttestationPreview,
+    };
+
+    use crate::{
+        document::{self, PID_DOCTYPE},
+        wallet::HistoryEvent,
+    };
 
     use super::{
-        super::test::{self, WalletWithMocks},
+        super::test::{self, WalletWithMocks, ISSUER_KEY},
         *,
     };
 
@@ -196,6 +336,46 @@ mod tests {
         assert_eq!(Arc::strong_count(&documents), 2);
     }
 
+    #[tokio::test]
+    async fn test_wallet_set_documents_callback_pending_document_error() {
+        let mut wallet = Wallet::new_registered_and_unlocked().await;
+
+        // The database contains a pending issuance, of which the preview has an attribute of the wrong type.
+        let mut unsigned_mdoc = document::create_full_unsigned_pid_mdoc();
+        let mut attributes = unsigned_mdoc.attributes.into_inner();
+        attributes
+            .get_mut(PID_DOCTYPE)
+            .unwrap()
+            .iter_mut()
+            .find(|entry| entry.name == "given_name")
+            .unwrap()
+            .value = DataElementValue::Bool(true);
+        unsigned_mdoc.attributes = attributes.try_into().unwrap();
+        wallet
+            .storage
+            .get_mut()
+            .upsert_data(&PendingIssuanceData {
+                issuances: vec![DeferredIssuance::new_mock(
+                    "transaction_id".to_string(),
+                    vec![AttestationPreview::MsoMdoc {
+                        unsigned_mdoc,
+                        issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+                    }],
+                )],
+            })
+            .await
+            .unwrap();
+
+        // Confirm that setting the callback returns an error instead of panicking.
+        let error = wallet
+            .set_documents_callback(Box::new(|_| {}))
+            .await
+            .map(|_| ())
+            .expect_err("Setting documents callback should have resulted in an error");
+
+        assert_matches!(error, DocumentsError::PendingDocument(_));
+    }
+
     #[tokio::test]
     async fn test_wallet_set_documents_callback_error() {
         let mut wallet = Wallet::new_registered_and_unlocked().await;
@@ -212,4 +392,116 @@ mod tests {
 
         assert_matches!(error, DocumentsError::Storage(_));
     }
+
+    #[tokio::test]
+    async fn test_wallet_delete_document() {
+        let mut wallet = Wallet::new_registered_and_unlocked().await;
+
+        // The database contains a single `Mdoc`.
+        let mdoc = test::create_full_pid_mdoc().await;
+        let mdoc_doc_type = mdoc.doc_type.clone();
+        wallet
+            .storage
+            .get_mut()
+            .mdocs
+            .insert(mdoc.doc_type.clone(), vec![vec![mdoc.clone(), mdoc].into()]);
+
+        let documents = test::setup_mock_documents_callback(&mut wallet)
+            .await
+            .expect("Failed to set mock documents callback");
+        let events = test::setup_mock_recent_history_callback(&mut wallet)
+            .await
+            .expect("Failed to set mock recent history callback");
+
+        let id = match &documents.lock().first().unwrap().first().unwrap().persistence {
+            DocumentPersistence::Stored(id) => id.clone(),
+            _ => panic!("Document should be stored"),
+        };
+
+        wallet.delete_document(&id).await.expect("Could not delete document");
+
+        // The `Mdoc` and all of its copies should be removed from the database.
+        assert!(wallet.storage.get_mut().mdocs.is_empty());
+
+        // The documents callback should have been called with no documents.
+        assert!(documents.lock().last().unwrap().is_empty());
+
+        // The deletion should have been logged and emitted as recent history.
+        let events = events.lock();
+        let event = events.last().unwrap().first().unwrap();
+        assert_matches!(event, HistoryEvent::Deletion { mdocs, .. } if mdocs[0].doc_type == mdoc_doc_type);
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_wallet_delete_document_notify_issuer() {
+        let mut wallet = Wallet::new_registered_and_unlocked().await;
+
+        // The database contains a single `Mdoc`, along with a refresh that contains a notification for it.
+        let mdoc = test::create_full_pid_mdoc().await;
+        let refresh = IssuanceRefresh::new_mock(vec![(
+            *SigningKey::random(&mut OsRng).verifying_key(),
+            mdoc.private_key_id().to_string(),
+        )]);
+        let storage = wallet.storage.get_mut();
+        storage.mdocs.insert(mdoc.doc_type.clone(), vec![vec![mdoc].into()]);
+        storage
+            .upsert_data(&IssuanceRefreshData {
+                refreshes: vec![refresh],
+            })
+            .await
+            .unwrap();
+
+        let documents = test::setup_mock_documents_callback(&mut wallet)
+            .await
+            .expect("Failed to set mock documents callback");
+        let id = match &documents.lock().first().unwrap().first().unwrap().persistence {
+            DocumentPersistence::Stored(id) => id.clone(),
+            _ => panic!("Document should be stored"),
+        };
+
+        // The issuer should be notified of the deletion.
+        let notify_context = MockIssuanceSession::notify_context();
+        notify_context
+            .expect()
+            .with(always(), eq(NotificationEvent::CredentialDeleted))
+            .times(1)
+            .return_once(|_, _| Ok(()));
+
+        wallet.delete_document(&id).await.expect("Could not delete document");
+
+        assert!(wallet.storage.get_mut().mdocs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_delete_document_error_locked() {
+        let mut wallet = Wallet::new_registered_and_unlocked().await;
+        wallet.lock();
+
+        let error = wallet
+            .delete_document(&Uuid::from_u128(0).to_string())
+            .await
+            .expect_err("Deleting document should have resulted in an error");
+
+        assert_matches!(error, DocumentDeletionError::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_wallet_delete_document_error_not_found() {
+        let mut wallet = Wallet::new_registered_and_unlocked().await;
+
+        let error = wallet
+            .delete_document(&Uuid::from_u128(0).to_string())
+            .await
+            .expect_err("Deleting document should have resulted in an error");
+
+        assert_matches!(error, DocumentDeletionError::NotFound(_));
+
+        let error = wallet
+            .delete_document("not_a_uuid")
+            .await
+            .expect_err("Deleting document should have resulted in an error");
+
+        assert_matches!(error, DocumentDeletionError::NotFound(_));
+    }
 }
@@ -15,7 +15,7 @@ pub use crate::storage::EventStatus;
 use crate::{
     document::{DisclosureType, DocumentMdocError},
     errors::StorageError,
-    storage::{EventDocuments, Storage, WalletEvent},
+    storage::{DocumentMappingData, EventDocuments, Storage, WalletEvent},
     DisclosureDocument, Document, DocumentPersistence,
 };
 
@@ -72,6 +72,12 @@ impl From<EventConversionError> for HistoryError {
 
 type HistoryResult<T> = Result<T, HistoryError>;
 
+pub(super) async fn fetch_document_mappings(storage: &impl Storage) -> Result<DocumentMappingData, StorageError> {
+    let mappings = storage.fetch_data::<DocumentMappingData>().await?.unwrap_or_default();
+
+    Ok(mappings)
+}
+
 pub type RecentHistoryCallback = Box<dyn FnMut(Vec<HistoryEvent>) + Send + Sync>;
 
 impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
@@ -103,8 +109,12 @@ where
 
         info!("Retrieving history from storage");
         let storage = self.storage.read().await;
+        let mappings = fetch_document_mappings(&*storage).await?;
         let events = storage.fetch_wallet_events().await?;
-        let result = events.into_iter().map(TryFrom::try_from).collect::<Result<_, _>>()?;
+        let result = events
+            .into_iter()
+            .map(|event| (event, &mappings).try_into())
+            .collect::<Result<_, _>>()?;
         Ok(result)
     }
 
@@ -125,20 +135,25 @@ where
 
         info!("Retrieving Card history from storage");
         let storage = self.storage.read().await;
+        let mappings = fetch_document_mappings(&*storage).await?;
         let events = storage.fetch_wallet_events_by_doc_type(doc_type).await?;
-        let result = events.into_iter().map(TryFrom::try_from).collect::<Result<_, _>>()?;
+        let result = events
+            .into_iter()
+            .map(|event| (event, &mappings).try_into())
+            .collect::<Result<_, _>>()?;
         Ok(result)
     }
 
-    async fn emit_recent_history(&mut self) -> Result<(), EventStorageError> {
+    pub(super) async fn emit_recent_history(&mut self) -> Result<(), EventStorageError> {
         info!("Emit recent history from storage");
 
         let storage = self.storage.read().await;
+        let mappings = fetch_document_mappings(&*storage).await?;
         let events: Vec<HistoryEvent> = storage
             .fetch_recent_wallet_events()
             .await?
             .into_iter()
-            .map(HistoryEvent::try_from)
+            .map(|event| (event, &mappings).try_into())
             .collect::<Result<_, _>>()?;
 
         if let Some(ref mut recent_history_callback) = self.recent_history_callback {
@@ -182,12 +197,39 @@ pub enum HistoryEvent {
         reader_registration: Box<ReaderRegistration>,
         attributes: Option<Vec<DisclosureDocument>>,
     },
+    Deletion {
+        timestamp: DateTime<Utc>,
+        mdocs: Vec<Document>,
+    },
+}
+
+pub(super) fn documents_from_event_documents(
+    EventDocuments(mdocs): EventDocuments,
+    mappings: &DocumentMappingData,
+) -> Result<Vec<Document>, EventConversionError> {
+    mdocs
+        .into_iter()
+        .map(|(doc_type, proposed_card)| {
+            let issuer_registration = IssuerRegistration::from_certificate(&proposed_card.issuer)?
+                .ok_or(EventConversionError::NoIssuerRegistrationFound)?;
+
+            let issuer_mappings = mappings.issuer_mappings(&proposed_card.issuer);
+            let document = Document::from_mdoc_attributes(
+                DocumentPersistence::InMemory,
+                &doc_type,
+                proposed_card.into(),
+                issuer_registration,
+                issuer_mappings,
+            )?;
+            Ok(document)
+        })
+        .collect()
 }
 
-impl TryFrom<WalletEvent> for HistoryEvent {
+impl TryFrom<(WalletEvent, &DocumentMappingData)> for HistoryEvent {
     type Error = EventConversionError;
 
-    fn try_from(source: WalletEvent) -> Result<Self, Self::Error> {
+    fn try_from((source, mappings): (WalletEvent, &DocumentMappingData)) -> Result<Self, Self::Error> {
         let result = match source {
             WalletEvent::Issuance {
                 id: _,
@@ -195,22 +237,7 @@ impl TryFrom<WalletEvent> for HistoryEvent {
                 mdocs,
             } => Self::Issuance {
                 timestamp,
-                mdocs: mdocs
-                    .0
-                    .into_iter()
-                    .map(|(doc_type, proposed_card)| {
-                        let issuer_registration = IssuerRegistration::from_certificate(&proposed_card.issuer)?
-                            .ok_or(EventConversionError::NoIssuerRegistrationFound)?;
-
-                        let document = Document::from_mdoc_attributes(
-                            DocumentPersistence::InMemory,
-                            &doc_type,
-                            proposed_card.into(),
-                            issuer_registration,
-                        )?;
-                        Ok(document)
-                    })
-                    .collect::<Result<_, EventConversionError>>()?,
+                mdocs: documents_from_event_documents(mdocs, mappings)?,
             },
             WalletEvent::Disclosure {
                 id: _,
@@ -228,12 +255,14 @@ impl TryFrom<WalletEvent> for HistoryEvent {
                         mdocs
                             .into_iter()
                             .map(|(doc_type, namespaces)| {
+                                let issuer_mappings = mappings.issuer_mappings(&namespaces.issuer);
                                 DisclosureDocument::from_mdoc_attributes(
                                     &doc_type,
                                     ProposedDocumentAttributes {
                                         issuer: namespaces.issuer.clone(),
                                         attributes: namespaces.into(),
                                     },
+                                    issuer_mappings,
                                 )
                             })
                             .collect::<Result<Vec<_>, _>>()
@@ -245,6 +274,14 @@ impl TryFrom<WalletEvent> for HistoryEvent {
                     Box::new(reader_registration)
                 },
             },
+            WalletEvent::Deletion {
+                id: _,
+                timestamp,
+                mdocs,
+            } => Self::Deletion {
+                timestamp,
+                mdocs: documents_from_event_documents(mdocs, mappings)?,
+            },
         };
         Ok(result)
     }
@@ -267,7 +304,7 @@ mod tests {
         HistoryEvent,
     };
 
-    use super::{EventStorageError, HistoryError};
+    use super::{DocumentMappingData, EventStorageError, HistoryError};
 
     const PID_DOCTYPE: &str = "com.example.pid";
     const ADDRESS_DOCTYPE: &str = "com.example.address";
@@ -357,23 +394,24 @@ mod tests {
             .unwrap();
 
         // get history should return both events, in correct order, newest first
+        let mappings = DocumentMappingData::default();
         let history = wallet.get_history().await.unwrap();
         assert_eq!(
             history,
             vec![
-                address_doc_type_event.clone().try_into().unwrap(),
-                disclosure_error_event.try_into().unwrap(),
-                disclosure_cancelled_event.try_into().unwrap(),
-                pid_doc_type_event.clone().try_into().unwrap()
+                (address_doc_type_event.clone(), &mappings).try_into().unwrap(),
+                (disclosure_error_event, &mappings).try_into().unwrap(),
+                (disclosure_cancelled_event, &mappings).try_into().unwrap(),
+                (pid_doc_type_event.clone(), &mappings).try_into().unwrap()
             ]
         );
 
         // get history for card should return single event
         let history = wallet.get_history_for_card(PID_DOCTYPE).await.unwrap();
-        assert_eq!(history, vec![pid_doc_type_event.try_into().unwrap()]);
+        assert_eq!(history, vec![(pid_doc_type_event, &mappings).try_into().unwrap()]);
 
         let history = wallet.get_history_for_card(ADDRESS_DOCTYPE).await.unwrap();
-        assert_eq!(history, vec![address_doc_type_event.try_into().unwrap()]);
+        assert_eq!(history, vec![(address_doc_type_event, &mappings).try_into().unwrap()]);
     }
 
     // Tests both setting and clearing the recent_history callback on an unregistered `Wallet`.
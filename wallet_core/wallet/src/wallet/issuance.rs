@@ -1,31 +1,74 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+};
+
+use chrono::{DateTime, Duration, Utc};
 use http::{header, HeaderMap, HeaderValue};
 use p256::ecdsa::signature;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 use url::Url;
+use uuid::Uuid;
 
 use error_category::{sentry_capture_error, ErrorCategory};
-use nl_wallet_mdoc::utils::{cose::CoseError, issuer_auth::IssuerRegistration, x509::MdocCertificateExtension};
+use nl_wallet_mdoc::{
+    holder::{Mdoc, MdocCopies},
+    utils::{
+        cose::CoseError,
+        issuer_auth::IssuerRegistration,
+        x509::{Certificate, MdocCertificateExtension},
+    },
+};
 use openid4vc::{
-    issuance_session::{HttpIssuanceSession, IssuanceSession, IssuanceSessionError},
-    token::AttestationPreviewError,
+    credential::NotificationEvent,
+    credential_offer::{CredentialOfferContainer, CredentialOfferError},
+    issuance_session::{
+        AcceptedIssuance, HttpIssuanceSession, HttpVcMessageClient, IssuanceAuthorization, IssuanceNotification,
+        IssuanceRefresh, IssuanceSession, IssuanceSessionError,
+    },
+    metadata::CredentialMetadata,
+    token::{AttestationPreview, AttestationPreviewError},
+    wallet_attestation::WalletAttestationWithPop,
+    ErrorResponse, TokenErrorCode, NL_WALLET_CLIENT_ID,
 };
 use platform_support::hw_keystore::PlatformEcdsaKey;
-use wallet_common::{jwt::JwtError, reqwest::trusted_reqwest_client_builder, urls};
+use wallet_common::{
+    account::{
+        errors::Error as AccountError,
+        messages::auth::{WalletAttestation, WalletAttestationRequest, WalletAttestationRequestMessage},
+    },
+    jwt::JwtError,
+    reqwest::{default_reqwest_client_builder, trusted_reqwest_client_builder},
+    urls::{self, BaseUrl},
+};
 
 use crate::{
-    account_provider::AccountProviderClient,
+    account_provider::{AccountProviderClient, AccountProviderError},
     config::{ConfigurationRepository, UNIVERSAL_LINK_BASE_URL},
-    document::{Document, DocumentMdocError, PID_DOCTYPE},
-    instruction::{InstructionClient, InstructionError, RemoteEcdsaKeyError, RemoteEcdsaKeyFactory},
+    document::{Document, DocumentMappings, DocumentMdocError, PID_DOCTYPE},
+    instruction::{InstructionClient, InstructionError, RemoteEcdsaKey, RemoteEcdsaKeyError, RemoteEcdsaKeyFactory},
     issuance::{DigidSession, DigidSessionError, HttpDigidSession},
-    storage::{Storage, StorageError, WalletEvent},
+    storage::{
+        DocumentMappingData, IssuanceRefreshData, PendingIssuanceData, Storage, StorageError, StoredMdocCopy,
+        WalletEvent,
+    },
 };
 
 use super::{documents::DocumentsError, history::EventStorageError, Wallet};
 
+/// The number of days before an mdoc expires from which on the wallet tries to refresh it.
+const MDOC_REFRESH_DAYS: i64 = 30;
+
 pub(super) enum PidIssuanceSession<DS = HttpDigidSession, IS = HttpIssuanceSession> {
     Digid(DS),
     Openid4vci(IS),
+    /// An Authorization Code Flow at the authorization server of an arbitrary issuer, started by a Credential Offer.
+    Authorization(IssuanceAuthorization),
+    /// An issuance session with an arbitrary issuer, started by a Credential Offer.
+    CredentialOffer {
+        session: IS,
+        credential_issuer: BaseUrl,
+    },
 }
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
@@ -61,6 +104,14 @@ pub enum PidIssuanceError {
     MdocDocument(#[from] DocumentMdocError),
     #[error("could not insert mdocs in database: {0}")]
     MdocStorage(#[source] StorageError),
+    #[error("could not access pending issuances in database: {0}")]
+    PendingIssuanceStorage(#[source] StorageError),
+    #[error("could not access pending reissuances in database: {0}")]
+    PendingReissuanceStorage(#[source] StorageError),
+    #[error("could not access issuance refreshes in database: {0}")]
+    IssuanceRefreshStorage(#[source] StorageError),
+    #[error("could not store document mappings in database: {0}")]
+    DocumentMappingStorage(#[source] StorageError),
     #[error("could not store event in history database: {0}")]
     EventStorage(#[source] EventStorageError),
     #[error("key '{0}' not found in Wallet Provider")]
@@ -75,6 +126,81 @@ pub enum PidIssuanceError {
     Document(#[source] DocumentsError),
     #[error("failed to read issuer registration from issuer certificate: {0}")]
     AttestationPreview(#[from] AttestationPreviewError),
+    #[error("could not process credential offer: {0}")]
+    CredentialOffer(#[from] CredentialOfferError),
+    #[error("incorrect transaction code")]
+    #[category(expected)]
+    IncorrectTxCode,
+    #[error("could not sign wallet attestation request: {0}")]
+    WalletAttestationRequest(#[source] AccountError),
+    #[error("could not retrieve wallet attestation from Wallet Provider: {0}")]
+    WalletAttestation(#[source] AccountProviderError),
+    #[error("could not sign wallet attestation PoP: {0}")]
+    WalletAttestationPop(#[source] JwtError),
+}
+
+fn accept_json_headers() -> HeaderMap {
+    HeaderMap::from_iter([(
+        header::ACCEPT,
+        HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+    )])
+}
+
+fn preview_documents(
+    attestation_previews: Vec<AttestationPreview>,
+    credential_metadata: &HashMap<String, CredentialMetadata>,
+) -> Result<Vec<Document>, PidIssuanceError> {
+    let mut documents = attestation_previews
+        .into_iter()
+        .map(|preview| {
+            let (unsigned_mdoc, issuer) = preview.try_into()?;
+            let mappings = DocumentMappings::from_credential_metadata(
+                credential_metadata.values(),
+                &[unsigned_mdoc.doc_type.as_str()],
+            );
+            Ok(Document::from_unsigned_mdoc(unsigned_mdoc, *issuer, &mappings)?)
+        })
+        .collect::<Result<Vec<_>, PidIssuanceError>>()?;
+    documents.sort_by_key(Document::priority);
+
+    Ok(documents)
+}
+
+/// Read the end of the validity period of `mdoc`, if possible.
+fn mdoc_valid_until(mdoc: &Mdoc) -> Option<DateTime<Utc>> {
+    mdoc.validity_info()
+        .ok()
+        .and_then(|validity| DateTime::<Utc>::try_from(&validity.valid_until).ok())
+}
+
+/// Whether the issuer rejected the refresh token, in which case it will never accept it again. Other errors, such as
+/// server errors, may be temporary.
+fn is_refresh_rejected(error: &IssuanceSessionError) -> bool {
+    matches!(
+        error,
+        IssuanceSessionError::TokenRequest(ErrorResponse {
+            error: TokenErrorCode::InvalidGrant,
+            ..
+        })
+    )
+}
+
+/// Convert an error that occurred during issuance using a [`RemoteEcdsaKeyFactory`] to a [`PidIssuanceError`],
+/// extracting the errors that were caused by the remote keys.
+fn remote_key_issuance_error(error: IssuanceSessionError) -> PidIssuanceError {
+    match error {
+        // We knowingly call unwrap() on the downcast to `RemoteEcdsaKeyError` here because we know
+        // that it is the error type of the `RemoteEcdsaKeyFactory` used during issuance.
+        IssuanceSessionError::PrivateKeyGeneration(error) | IssuanceSessionError::Jwt(JwtError::Signing(error)) => {
+            match *error.downcast::<RemoteEcdsaKeyError>().unwrap() {
+                RemoteEcdsaKeyError::Instruction(error) => PidIssuanceError::Instruction(error),
+                RemoteEcdsaKeyError::Signature(error) => PidIssuanceError::Signature(error),
+                RemoteEcdsaKeyError::KeyNotFound(identifier) => PidIssuanceError::KeyNotFound(identifier),
+                RemoteEcdsaKeyError::MissingSignature => PidIssuanceError::MissingSignature,
+            }
+        }
+        _ => PidIssuanceError::PidIssuer(error),
+    }
 }
 
 impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
@@ -167,9 +293,16 @@ where
         info!("Checking if there is an active issuance session");
         let issuance_session = self.issuance_session.take().ok_or(PidIssuanceError::SessionState)?;
 
-        if let PidIssuanceSession::Openid4vci(pid_issuer) = issuance_session {
-            info!("Rejecting PID");
-            pid_issuer.reject_issuance().await?;
+        match issuance_session {
+            PidIssuanceSession::Digid(_) | PidIssuanceSession::Authorization(_) => {}
+            PidIssuanceSession::Openid4vci(pid_issuer) => {
+                info!("Rejecting PID");
+                pid_issuer.reject_issuance().await?;
+            }
+            PidIssuanceSession::CredentialOffer { session, .. } => {
+                info!("Rejecting offered attestations");
+                session.reject_issuance().await?;
+            }
         }
 
         Ok(())
@@ -177,7 +310,11 @@ where
 
     #[instrument(skip_all)]
     #[sentry_capture_error]
-    pub async fn continue_pid_issuance(&mut self, redirect_uri: Url) -> Result<Vec<Document>, PidIssuanceError> {
+    pub async fn continue_pid_issuance(&mut self, redirect_uri: Url) -> Result<Vec<Document>, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
         info!("Received DigiD redirect URI, processing URI and retrieving access token");
 
         info!("Checking if registered");
@@ -198,7 +335,7 @@ where
         // Take ownership of the active session, now that we know that it exists.
         let session = match self.issuance_session.take().unwrap() {
             PidIssuanceSession::Digid(session) => session,
-            PidIssuanceSession::Openid4vci(_) => panic!(),
+            _ => panic!(),
         };
 
         let token_request = session
@@ -208,31 +345,31 @@ where
 
         let pid_issuance_config = &self.config_repository.config().pid_issuance;
         let http_client = trusted_reqwest_client_builder(pid_issuance_config.digid_trust_anchors())
-            .default_headers(HeaderMap::from_iter([(
-                header::ACCEPT,
-                HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
-            )]))
+            .default_headers(accept_json_headers())
             .build()
             .expect("Could not build reqwest HTTP client");
         let config = self.config_repository.config();
 
-        let (pid_issuer, attestation_previews) = IS::start_issuance(
-            http_client.into(),
-            config.pid_issuance.pid_issuer_url.clone(),
-            token_request,
-            &config.mdoc_trust_anchors(),
-        )
-        .await?;
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let (pid_issuer, attestation_previews) = self
+            .with_vc_message_client(
+                http_client,
+                wallet_attestation.as_ref(),
+                &config.pid_issuance.pid_issuer_url,
+                |message_client| {
+                    IS::start_issuance(
+                        message_client,
+                        config.pid_issuance.pid_issuer_url.clone(),
+                        token_request.clone(),
+                        &mdoc_trust_anchors,
+                    )
+                },
+            )
+            .await??;
 
         info!("PID received successfully from issuer, returning preview documents");
-        let mut documents = attestation_previews
-            .into_iter()
-            .map(|preview| {
-                let (unsigned_mdoc, issuer) = preview.try_into()?;
-                Ok(Document::from_unsigned_mdoc(unsigned_mdoc, *issuer)?)
-            })
-            .collect::<Result<Vec<_>, PidIssuanceError>>()?;
-        documents.sort_by_key(Document::priority);
+        let documents = preview_documents(attestation_previews, &pid_issuer.credential_metadata())?;
 
         self.issuance_session
             .replace(PidIssuanceSession::Openid4vci(pid_issuer));
@@ -251,10 +388,9 @@ where
         info!("Accepting PID issuance");
 
         info!("Checking if registered");
-        let registration = self
-            .registration
-            .as_ref()
-            .ok_or_else(|| PidIssuanceError::NotRegistered)?;
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
 
         info!("Checking if locked");
         if self.lock.is_locked() {
@@ -262,13 +398,297 @@ where
         }
 
         info!("Checking if there is an active PID issuance session");
-        let pid_issuer = match self.issuance_session.as_ref().ok_or(PidIssuanceError::SessionState)? {
-            PidIssuanceSession::Digid(_) => Err(PidIssuanceError::SessionState)?,
-            PidIssuanceSession::Openid4vci(pid_issuer) => pid_issuer,
+        if !matches!(self.issuance_session, Some(PidIssuanceSession::Openid4vci(_))) {
+            return Err(PidIssuanceError::SessionState);
+        }
+
+        self.accept_issuance_session(pin).await
+    }
+
+    /// Start issuance of the attestations offered in the Credential Offer contained in `offer_uri`, returning preview
+    /// documents of those attestations. If the offer requires a Transaction Code and `tx_code` is `None`, this returns
+    /// [`CredentialOfferError::MissingTxCode`] describing the code, so that the user can be prompted to enter it.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn start_issuance_from_offer(
+        &mut self,
+        offer_uri: Url,
+        tx_code: Option<String>,
+    ) -> Result<Vec<Document>, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Received credential offer URI, starting issuance");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        info!("Checking if there is an active issuance session");
+        if self.issuance_session.is_some() {
+            return Err(PidIssuanceError::SessionState);
+        }
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+
+        let offer = CredentialOfferContainer::from_uri(&offer_uri)?
+            .resolve(&http_client)
+            .await?;
+        let has_tx_code = tx_code.is_some();
+        let token_request = offer.token_request(tx_code)?;
+
+        let config = self.config_repository.config();
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let (session, attestation_previews) = self
+            .with_vc_message_client(
+                http_client,
+                wallet_attestation.as_ref(),
+                &offer.credential_issuer,
+                |message_client| {
+                    IS::start_issuance(
+                        message_client,
+                        offer.credential_issuer.clone(),
+                        token_request.clone(),
+                        &mdoc_trust_anchors,
+                    )
+                },
+            )
+            .await?
+            .map_err(|error| match error {
+                IssuanceSessionError::TokenRequest(ErrorResponse {
+                    error: TokenErrorCode::InvalidGrant,
+                    ..
+                }) if has_tx_code => PidIssuanceError::IncorrectTxCode,
+                error => PidIssuanceError::PidIssuer(error),
+            })?;
+
+        info!("Validating offered attestations against issuer metadata");
+        if let Err(error) =
+            offer.validate_credential_configurations(&session.credential_metadata(), &attestation_previews)
+        {
+            // The issuer is informed that the session will not continue, though the offer is invalid regardless.
+            if let Err(reject_error) = session.reject_issuance().await {
+                warn!("Could not reject issuance of invalid credential offer: {reject_error}");
+            }
+
+            return Err(error.into());
+        }
+
+        info!("Attestations received successfully from issuer, returning preview documents");
+        let documents = preview_documents(attestation_previews, &session.credential_metadata())?;
+
+        self.issuance_session.replace(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer: offer.credential_issuer,
+        });
+
+        Ok(documents)
+    }
+
+    /// Start an Authorization Code Flow for the attestations offered in the Credential Offer contained in `offer_uri`,
+    /// returning the URL of the authorization server of the issuer to which the user should be sent to authenticate.
+    /// The authorization server then redirects the user back to the wallet, after which issuance continues using
+    /// [`Self::continue_issuance_authorization`].
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn create_issuance_auth_url(&mut self, offer_uri: Url) -> Result<Url, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Received credential offer URI, starting authorization");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        info!("Checking if there is an active issuance session");
+        if self.issuance_session.is_some() {
+            return Err(PidIssuanceError::SessionState);
+        }
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+
+        let offer = CredentialOfferContainer::from_uri(&offer_uri)?
+            .resolve(&http_client)
+            .await?;
+        let issuer_state = offer.authorization_code_grant()?.issuer_state.clone();
+
+        let config = self.config_repository.config();
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+
+        info!("Pushing authorization request to authorization server");
+        let (authorization, auth_url) = self
+            .with_vc_message_client(
+                http_client,
+                wallet_attestation.as_ref(),
+                &offer.credential_issuer,
+                |message_client| {
+                    IS::start_authorization(
+                        message_client,
+                        offer.credential_issuer.clone(),
+                        &offer.credential_configuration_ids,
+                        issuer_state.clone(),
+                        urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).into_inner(),
+                        &mdoc_trust_anchors,
+                    )
+                },
+            )
+            .await??;
+
+        info!("Authorization URL generated");
+        self.issuance_session
+            .replace(PidIssuanceSession::Authorization(authorization));
+
+        Ok(auth_url)
+    }
+
+    /// Continue the Authorization Code Flow started by [`Self::create_issuance_auth_url`] with the redirect URI that
+    /// the authorization server sent the user back to, by exchanging the authorization code for an access token at the
+    /// issuer. Returns preview documents of the attestations, which can be accepted using [`Self::accept_issuance`].
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn continue_issuance_authorization(
+        &mut self,
+        redirect_uri: Url,
+    ) -> Result<Vec<Document>, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Received redirect URI from authorization server, retrieving access token");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        info!("Checking if there is an active authorization");
+        if !matches!(self.issuance_session, Some(PidIssuanceSession::Authorization(_))) {
+            return Err(PidIssuanceError::SessionState);
+        }
+
+        // Take ownership of the active authorization, now that we know that it exists.
+        let authorization = match self.issuance_session.take().unwrap() {
+            PidIssuanceSession::Authorization(authorization) => authorization,
+            _ => panic!(),
         };
 
+        let credential_issuer = authorization.credential_issuer().clone();
+        let token_request = authorization.into_token_request(&redirect_uri)?;
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+
+        let config = self.config_repository.config();
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let (session, attestation_previews) = self
+            .with_vc_message_client(
+                http_client,
+                wallet_attestation.as_ref(),
+                &credential_issuer,
+                |message_client| {
+                    IS::start_issuance(
+                        message_client,
+                        credential_issuer.clone(),
+                        token_request.clone(),
+                        &mdoc_trust_anchors,
+                    )
+                },
+            )
+            .await??;
+
+        info!("Attestations received successfully from issuer, returning preview documents");
+        let documents = preview_documents(attestation_previews, &session.credential_metadata())?;
+
+        self.issuance_session.replace(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer,
+        });
+
+        Ok(documents)
+    }
+
+    /// Accept the attestations of an issuance session started by [`Self::start_issuance_from_offer`] or
+    /// [`Self::continue_issuance_authorization`].
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn accept_issuance(&mut self, pin: String) -> Result<(), PidIssuanceError>
+    where
+        S: Storage,
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Accepting issuance");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        info!("Checking if there is an active credential offer issuance session");
+        if !matches!(self.issuance_session, Some(PidIssuanceSession::CredentialOffer { .. })) {
+            return Err(PidIssuanceError::SessionState);
+        }
+
+        self.accept_issuance_session(pin).await
+    }
+
+    /// Accept the active OpenID4VCI session, which the caller should have checked to be present.
+    async fn accept_issuance_session(&mut self, pin: String) -> Result<(), PidIssuanceError>
+    where
+        S: Storage,
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        let registration = self.registration.as_ref().ok_or(PidIssuanceError::NotRegistered)?;
         let config = self.config_repository.config();
 
+        let (session, credential_issuer) = match self.issuance_session.as_ref() {
+            Some(PidIssuanceSession::Openid4vci(session)) => (session, config.pid_issuance.pid_issuer_url.clone()),
+            Some(PidIssuanceSession::CredentialOffer {
+                session,
+                credential_issuer,
+            }) => (session, credential_issuer.clone()),
+            Some(PidIssuanceSession::Digid(_) | PidIssuanceSession::Authorization(_)) | None => {
+                return Err(PidIssuanceError::SessionState)
+            }
+        };
+
         let instruction_result_public_key = config.account_server.instruction_result_public_key.clone().into();
 
         let remote_instruction = InstructionClient::new(
@@ -282,31 +702,13 @@ where
         );
         let remote_key_factory = RemoteEcdsaKeyFactory::new(&remote_instruction);
 
-        info!("Accepting PID by signing mdoc using Wallet Provider");
+        info!("Accepting attestations by signing mdoc using Wallet Provider");
 
-        let mdocs_result = pid_issuer
-            .accept_issuance(
-                &config.mdoc_trust_anchors(),
-                &remote_key_factory,
-                config.pid_issuance.pid_issuer_url.clone(),
-            )
+        let mdocs_result = session
+            .accept_issuance(&config.mdoc_trust_anchors(), &remote_key_factory, credential_issuer)
             .await
-            .map_err(|error| {
-                match error {
-                    // We knowingly call unwrap() on the downcast to `RemoteEcdsaKeyError` here because we know
-                    // that it is the error type of the `RemoteEcdsaKeyFactory` we provide above.
-                    IssuanceSessionError::PrivateKeyGeneration(error)
-                    | IssuanceSessionError::Jwt(JwtError::Signing(error)) => {
-                        match *error.downcast::<RemoteEcdsaKeyError>().unwrap() {
-                            RemoteEcdsaKeyError::Instruction(error) => PidIssuanceError::Instruction(error),
-                            RemoteEcdsaKeyError::Signature(error) => PidIssuanceError::Signature(error),
-                            RemoteEcdsaKeyError::KeyNotFound(identifier) => PidIssuanceError::KeyNotFound(identifier),
-                            RemoteEcdsaKeyError::MissingSignature => PidIssuanceError::MissingSignature,
-                        }
-                    }
-                    _ => PidIssuanceError::PidIssuer(error),
-                }
-            });
+            .map_err(remote_key_issuance_error);
+        let credential_metadata = session.credential_metadata();
 
         // If the Wallet Provider returns either a PIN timeout or a permanent block,
         // wipe the contents of the wallet and return it to its initial state.
@@ -318,304 +720,1866 @@ where
         ) {
             self.reset_to_initial_state().await;
         }
-        let mdocs = mdocs_result?;
+        let accepted_issuance = mdocs_result?;
 
         info!("Isuance succeeded; removing issuance session state");
         self.issuance_session.take();
 
-        // Prepare events before storing mdocs, to avoid cloning mdocs
-        let event = {
-            // Extract first copy from cred_copies
-            let mdocs = mdocs
-                .iter()
-                .flat_map(|mdoc| mdoc.cred_copies.first())
-                .cloned()
-                .collect::<Vec<_>>();
-
-            // Validate all issuer_certificates
-            for mdoc in mdocs.iter() {
-                let certificate = mdoc
-                    .issuer_certificate()
-                    .map_err(PidIssuanceError::InvalidIssuerCertificate)?;
-
-                // Verify that the certificate contains IssuerRegistration
-                if matches!(IssuerRegistration::from_certificate(&certificate), Err(_) | Ok(None)) {
-                    return Err(PidIssuanceError::MissingIssuerRegistration);
+        match accepted_issuance {
+            AcceptedIssuance::Issued {
+                mdocs,
+                refresh,
+                notification,
+            } => {
+                // Store the mappings derived from the issuer metadata before storing the mdocs, so that both the
+                // documents and the history event are displayed as described by the issuer. The issuer is only told
+                // that the mdocs were accepted once all of these have been stored.
+                let result = async {
+                    let issued_documents = mdocs
+                        .iter()
+                        .flat_map(|mdoc| mdoc.cred_copies.first())
+                        .map(|mdoc| Ok((mdoc.issuer_certificate()?, mdoc.doc_type.clone())))
+                        .collect::<Result<Vec<_>, CoseError>>()
+                        .map_err(PidIssuanceError::InvalidIssuerCertificate)?;
+                    self.store_document_mappings(&credential_metadata, issued_documents)
+                        .await?;
+                    self.store_issued_mdocs(mdocs).await?;
+                    self.add_issuance_refreshes(refresh.map(|refresh| *refresh)).await
                 }
+                .await;
+                Self::notify_issuers(notification.map(|notification| *notification), &result).await;
+                result?;
             }
-            WalletEvent::new_issuance(mdocs.try_into().map_err(PidIssuanceError::InvalidIssuerCertificate)?)
-        };
-
-        info!("PID accepted, storing mdoc in database");
-        self.storage
-            .get_mut()
-            .insert_mdocs(mdocs)
-            .await
-            .map_err(PidIssuanceError::MdocStorage)?;
-
-        self.store_history_event(event)
-            .await
-            .map_err(PidIssuanceError::EventStorage)?;
+            AcceptedIssuance::Deferred(deferred_issuance) => {
+                info!("Issuer deferred issuance, storing pending issuance in database");
+
+                let previewed_documents = deferred_issuance
+                    .attestation_previews()
+                    .iter()
+                    .map(|preview| {
+                        let AttestationPreview::MsoMdoc { unsigned_mdoc, issuer } = preview;
+                        (issuer.clone(), unsigned_mdoc.doc_type.clone())
+                    })
+                    .collect::<Vec<_>>();
+                self.store_document_mappings(&credential_metadata, previewed_documents)
+                    .await?;
+
+                let storage = self.storage.get_mut();
+                let mut pending = storage
+                    .fetch_data::<PendingIssuanceData>()
+                    .await
+                    .map_err(PidIssuanceError::PendingIssuanceStorage)?
+                    .unwrap_or_default();
+                pending.issuances.push(*deferred_issuance);
+                storage
+                    .upsert_data(&pending)
+                    .await
+                    .map_err(PidIssuanceError::PendingIssuanceStorage)?;
+            }
+        }
 
         self.emit_documents().await.map_err(PidIssuanceError::Document)?;
 
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use assert_matches::assert_matches;
-    use mockall::predicate::*;
-    use openid4vc::{
-        mock::MockIssuanceSession,
-        oidc::OidcError,
-        token::{AttestationPreview, TokenRequest, TokenRequestGrantType},
-    };
-    use rstest::rstest;
-    use serial_test::serial;
-    use url::Url;
 
-    use crate::{
-        document::{self, DocumentPersistence},
-        issuance::MockDigidSession,
-        storage::StorageState,
-        wallet::history::HistoryEvent,
-    };
+    /// Store the document mappings derived from the metadata of an issuer for the accepted documents, each of which is
+    /// identified by its issuer certificate and doc type. This replaces any previously stored mappings of the same
+    /// issuer certificate and doc type.
+    async fn store_document_mappings(
+        &mut self,
+        credential_metadata: &HashMap<String, CredentialMetadata>,
+        documents: Vec<(Certificate, String)>,
+    ) -> Result<(), PidIssuanceError> {
+        let mappings = documents
+            .into_iter()
+            .map(|(issuer, doc_type)| {
+                let mappings =
+                    DocumentMappings::from_credential_metadata(credential_metadata.values(), &[doc_type.as_str()]);
+                (issuer, mappings)
+            })
+            .filter(|(_, mappings)| !mappings.is_empty())
+            .collect::<Vec<_>>();
 
-    use super::{
-        super::test::{self, WalletWithMocks, ISSUER_KEY},
-        *,
-    };
+        if mappings.is_empty() {
+            return Ok(());
+        }
 
-    #[tokio::test]
-    #[serial(MockDigidSession)]
-    async fn test_create_pid_issuance_auth_url() {
-        const AUTH_URL: &str = "http://example.com/auth";
+        info!("Storing document mappings derived from issuer metadata");
 
-        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let storage = self.storage.get_mut();
+        let mut data = storage
+            .fetch_data::<DocumentMappingData>()
+            .await
+            .map_err(PidIssuanceError::DocumentMappingStorage)?
+            .unwrap_or_default();
+        for (issuer, mappings) in mappings {
+            data.extend(issuer, mappings);
+        }
+        storage
+            .upsert_data(&data)
+            .await
+            .map_err(PidIssuanceError::DocumentMappingStorage)?;
 
-        assert!(wallet.issuance_session.is_none());
+        Ok(())
+    }
 
-        // Set up a mock DigiD session.
-        let session_start_context = MockDigidSession::start_context();
-        session_start_context.expect().returning(|_, _| {
-            let client = MockDigidSession::default();
-            Ok((client, Url::parse(AUTH_URL).unwrap()))
+    /// Try to retrieve the attestations of the issuance sessions of which the issuer deferred issuance, storing those
+    /// that have been issued in the meantime. Sessions that the issuer rejects are removed, while sessions for which
+    /// an other error occurs are kept so that they can be retried later.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn check_pending_issuances(&mut self) -> Result<(), PidIssuanceError>
+    where
+        S: Storage,
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Checking pending issuances");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        let pending_issuances = self
+            .storage
+            .get_mut()
+            .fetch_data::<PendingIssuanceData>()
+            .await
+            .map_err(PidIssuanceError::PendingIssuanceStorage)?
+            .unwrap_or_default()
+            .issuances;
+        if pending_issuances.is_empty() {
+            return Ok(());
+        }
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+        let config = self.config_repository.config();
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+
+        let mut issued_mdocs = Vec::new();
+        let mut refreshes = Vec::new();
+        let mut notifications = Vec::new();
+        let mut still_pending = Vec::new();
+        for pending_issuance in pending_issuances {
+            let result = self
+                .with_vc_message_client(
+                    http_client.clone(),
+                    wallet_attestation.as_ref(),
+                    pending_issuance.issuer_url(),
+                    |message_client| {
+                        IS::accept_deferred_issuance::<RemoteEcdsaKey<'_, S, PEK, APC>>(
+                            message_client,
+                            pending_issuance.clone(),
+                            &mdoc_trust_anchors,
+                        )
+                    },
+                )
+                .await?;
+
+            match result {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs,
+                    refresh,
+                    notification,
+                }) => {
+                    issued_mdocs.extend(mdocs);
+                    refreshes.extend(refresh.map(|refresh| *refresh));
+                    notifications.extend(notification.map(|notification| *notification));
+                }
+                Ok(AcceptedIssuance::Deferred(pending_issuance)) => still_pending.push(*pending_issuance),
+                Err(error @ IssuanceSessionError::CredentialRequest(_)) => {
+                    warn!("issuer refused pending issuance, removing it: {error}");
+                }
+                Err(error) => {
+                    warn!("could not check pending issuance, retrying later: {error}");
+                    still_pending.push(pending_issuance);
+                }
+            }
+        }
+
+        // The issuers are only told that the mdocs were accepted once these have been stored, along with their
+        // refreshes and the remaining pending issuances.
+        let result = async {
+            if !issued_mdocs.is_empty() {
+                info!("Pending attestations were issued");
+                self.store_issued_mdocs(issued_mdocs).await?;
+                self.add_issuance_refreshes(refreshes).await?;
+            }
+
+            self.storage
+                .get_mut()
+                .upsert_data(&PendingIssuanceData {
+                    issuances: still_pending,
+                })
+                .await
+                .map_err(PidIssuanceError::PendingIssuanceStorage)
+        }
+        .await;
+        Self::notify_issuers(notifications, &result).await;
+        result?;
+
+        self.emit_documents().await.map_err(PidIssuanceError::Document)?;
+
+        Ok(())
+    }
+
+    /// Refresh the stored mdocs that are about to expire, using the refresh tokens that their issuers handed out during
+    /// issuance. The refreshed mdocs are bound to the same keys, so this does not require the PIN. Refreshes that the
+    /// issuer rejects are removed, while refreshes for which an other error occurs are kept so that they can be retried
+    /// later.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn refresh_expiring_mdocs(&mut self) -> Result<(), PidIssuanceError>
+    where
+        S: Storage,
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Checking for expiring mdocs");
+
+        info!("Checking if registered");
+        if self.registration.is_none() {
+            return Err(PidIssuanceError::NotRegistered);
+        }
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        let storage = self.storage.get_mut();
+        let refreshes = storage
+            .fetch_data::<IssuanceRefreshData>()
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?
+            .unwrap_or_default()
+            .refreshes;
+        if refreshes.is_empty() {
+            return Ok(());
+        }
+        let stored_mdocs = storage
+            .fetch_unique_mdocs()
+            .await
+            .map_err(PidIssuanceError::MdocStorage)?;
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+        let config = self.config_repository.config();
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let refresh_before = Utc::now() + Duration::days(MDOC_REFRESH_DAYS);
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+
+        let mut replacements = Vec::new();
+        let mut remaining_refreshes = Vec::new();
+        for refresh in refreshes {
+            // Find the stored mdocs that can be refreshed using this refresh, by their private keys.
+            let key_ids = refresh.key_identifiers().collect::<HashSet<_>>();
+            let refreshable_mdocs = stored_mdocs
+                .iter()
+                .filter(|stored| key_ids.contains(stored.mdoc.private_key_id()))
+                .collect::<Vec<_>>();
+
+            // If none of the mdocs are present anymore, there is nothing left to refresh.
+            if refreshable_mdocs.is_empty() {
+                continue;
+            }
+
+            // If the validity of an mdoc cannot be read, we consider it to be expiring.
+            let is_expiring = refreshable_mdocs
+                .iter()
+                .any(|stored| mdoc_valid_until(&stored.mdoc).map_or(true, |valid_until| valid_until < refresh_before));
+            if !is_expiring {
+                remaining_refreshes.push(refresh);
+                continue;
+            }
+
+            let result = self
+                .with_vc_message_client(
+                    http_client.clone(),
+                    wallet_attestation.as_ref(),
+                    refresh.issuer_url(),
+                    |message_client| {
+                        IS::refresh_issuance::<RemoteEcdsaKey<'_, S, PEK, APC>>(
+                            message_client,
+                            refresh.clone(),
+                            &mdoc_trust_anchors,
+                        )
+                    },
+                )
+                .await?;
+
+            match result {
+                Ok((mdocs, new_refresh)) => {
+                    // Replace each stored mdoc by the refreshed mdoc that is bound to the same keys.
+                    replacements.extend(mdocs.into_iter().filter_map(|mdoc_copies| {
+                        refreshable_mdocs
+                            .iter()
+                            .find(|stored| {
+                                mdoc_copies
+                                    .cred_copies
+                                    .iter()
+                                    .any(|mdoc| mdoc.private_key_id() == stored.mdoc.private_key_id())
+                            })
+                            .map(|stored| (stored.mdoc_id, mdoc_copies))
+                    }));
+                    remaining_refreshes.push(new_refresh);
+                }
+                Err(error) if is_refresh_rejected(&error) => {
+                    warn!("issuer refused to refresh mdocs, removing refresh: {error}");
+                }
+                Err(error) => {
+                    warn!("could not refresh mdocs, retrying later: {error}");
+                    remaining_refreshes.push(refresh);
+                }
+            }
+        }
+
+        let storage = self.storage.get_mut();
+
+        // Store the refreshes first, since the issuer has rotated the refresh tokens of the refreshed mdocs.
+        storage
+            .upsert_data(&IssuanceRefreshData {
+                refreshes: remaining_refreshes,
+            })
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?;
+
+        if !replacements.is_empty() {
+            info!("Mdocs were refreshed, replacing them in database");
+            storage
+                .replace_mdocs(replacements)
+                .await
+                .map_err(PidIssuanceError::MdocStorage)?;
+
+            self.emit_documents().await.map_err(PidIssuanceError::Document)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replenish the single-use copies of the stored mdocs, so that the wallet does not have to disclose the same copy
+    /// more than once. Copies that have expired or that have been disclosed too often according to the configured
+    /// policy are pruned first, always keeping at least one copy of each mdoc. Mdocs that have fewer unused copies than
+    /// configured are then re-issued in a batch by their original issuer, using the refresh tokens that it handed out
+    /// during issuance. As the new copies are bound to new keys, this requires the PIN.
+    ///
+    /// Returns the doctypes of the mdocs that need to be replenished but cannot be re-issued without the user
+    /// authenticating to the issuer again, e.g. because the issuer rejected the refresh token.
+    #[instrument(skip_all)]
+    #[sentry_capture_error]
+    pub async fn replenish_mdoc_copies(&mut self, pin: String) -> Result<Vec<String>, PidIssuanceError>
+    where
+        S: Storage,
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        info!("Checking for mdocs to replenish");
+
+        info!("Checking if registered");
+        let registration = self.registration.as_ref().ok_or(PidIssuanceError::NotRegistered)?;
+
+        info!("Checking if locked");
+        if self.lock.is_locked() {
+            return Err(PidIssuanceError::Locked);
+        }
+
+        let config = self.config_repository.config();
+        let policy = &config.mdoc_replenishment;
+        let now = Utc::now();
+
+        // If the validity of an mdoc cannot be read, we consider it to be expired.
+        let is_prunable = |(copy, usage_count): &(StoredMdocCopy, u32)| {
+            *usage_count >= policy.max_copy_usage_count
+                || mdoc_valid_until(&copy.mdoc).map_or(true, |valid_until| valid_until < now)
+        };
+
+        let storage = self.storage.get_mut();
+        let mut mdocs: HashMap<Uuid, Vec<(StoredMdocCopy, u32)>> = HashMap::new();
+        for copy in storage
+            .fetch_mdoc_copies()
+            .await
+            .map_err(PidIssuanceError::MdocStorage)?
+        {
+            mdocs.entry(copy.0.mdoc_id).or_default().push(copy);
+        }
+
+        // Prune the copies of each mdoc, keeping the least used copy that has not expired (if any) so that the mdoc
+        // itself is never removed by pruning.
+        let mut pruned_copy_ids = Vec::new();
+        for copies in mdocs.values_mut() {
+            copies.sort_by_key(|copy| (is_prunable(copy), copy.1));
+            let (pruned, kept): (Vec<_>, Vec<_>) = copies.drain(1..).partition(is_prunable);
+            pruned_copy_ids.extend(pruned.into_iter().map(|(copy, _)| copy.mdoc_copy_id));
+            copies.extend(kept);
+        }
+        if !pruned_copy_ids.is_empty() {
+            info!("Pruning {} expired or used mdoc copies", pruned_copy_ids.len());
+            storage
+                .delete_mdoc_copies(pruned_copy_ids)
+                .await
+                .map_err(PidIssuanceError::MdocStorage)?;
+        }
+
+        let depleted_mdoc_ids = mdocs
+            .iter()
+            .filter(|(_, copies)| {
+                let unused_count = copies.iter().filter(|copy| copy.1 == 0 && !is_prunable(copy)).count();
+                unused_count < usize::from(policy.min_unused_copies)
+            })
+            .map(|(mdoc_id, _)| *mdoc_id)
+            .collect::<HashSet<_>>();
+        if depleted_mdoc_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let refreshes = storage
+            .fetch_data::<IssuanceRefreshData>()
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?
+            .unwrap_or_default()
+            .refreshes;
+
+        // Finds the stored mdoc of which one of the copies is bound to one of the keys in `key_ids`.
+        let find_mdoc_id = |key_ids: &HashSet<&str>| {
+            mdocs
+                .iter()
+                .find(|(_, copies)| {
+                    copies
+                        .iter()
+                        .any(|(copy, _)| key_ids.contains(copy.mdoc.private_key_id()))
+                })
+                .map(|(mdoc_id, _)| *mdoc_id)
+        };
+
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+        let mdoc_trust_anchors = config.mdoc_trust_anchors();
+        let wallet_attestation = self.fetch_wallet_attestation().await?;
+
+        let instruction_result_public_key = config.account_server.instruction_result_public_key.clone().into();
+        let remote_instruction = InstructionClient::new(
+            pin,
+            &self.storage,
+            &registration.hw_privkey,
+            &self.account_provider_client,
+            &registration.data,
+            &config.account_server.base_url,
+            &instruction_result_public_key,
+        );
+        let remote_key_factory = RemoteEcdsaKeyFactory::new(&remote_instruction);
+
+        let mut replacements = Vec::new();
+        let mut remaining_refreshes = Vec::new();
+        let mut result = Ok(());
+        for refresh in refreshes {
+            // Only replenish the mdocs issued using this refresh if any of them are depleted. Stop contacting issuers
+            // after an error has occurred while using the PIN, as the PIN may be incorrect.
+            let key_ids = refresh.key_identifiers().collect::<HashSet<_>>();
+            let is_depleted = find_mdoc_id(&key_ids).is_some_and(|mdoc_id| depleted_mdoc_ids.contains(&mdoc_id));
+            if !is_depleted || result.is_err() {
+                remaining_refreshes.push(refresh);
+                continue;
+            }
+
+            let replenish_result = self
+                .with_vc_message_client(
+                    http_client.clone(),
+                    wallet_attestation.as_ref(),
+                    refresh.issuer_url(),
+                    |message_client| {
+                        IS::replenish_issuance::<RemoteEcdsaKey<'_, S, PEK, APC>>(
+                            message_client,
+                            refresh.clone(),
+                            &mdoc_trust_anchors,
+                            &remote_key_factory,
+                        )
+                    },
+                )
+                .await?;
+
+            match replenish_result {
+                Ok((mdocs, new_refresh)) => {
+                    // The keys of the refresh are those of the copies of each of the issued mdocs, in order. Use these
+                    // to find the stored mdoc that each of the replenished mdocs replaces.
+                    let mut original_key_ids = refresh.key_identifiers();
+                    for mdoc_copies in mdocs {
+                        let copy_key_ids = original_key_ids
+                            .by_ref()
+                            .take(mdoc_copies.cred_copies.len())
+                            .collect::<HashSet<_>>();
+                        // If the mdoc is not present anymore, the user has deleted it and it should not be replaced.
+                        if let Some(mdoc_id) = find_mdoc_id(&copy_key_ids) {
+                            replacements.push((mdoc_id, mdoc_copies));
+                        }
+                    }
+                    remaining_refreshes.push(new_refresh);
+                }
+                Err(error) if is_refresh_rejected(&error) => {
+                    warn!("issuer refused to replenish mdocs, removing refresh: {error}");
+                }
+                Err(error) => match remote_key_issuance_error(error) {
+                    PidIssuanceError::PidIssuer(error) => {
+                        warn!("could not replenish mdocs, retrying later: {error}");
+                        remaining_refreshes.push(refresh);
+                    }
+                    error => {
+                        remaining_refreshes.push(refresh);
+                        result = Err(error);
+                    }
+                },
+            }
+        }
+
+        // Depleted mdocs that were not replenished and for which no refresh remains need to be issued again by the
+        // user.
+        let reissuable_key_ids = remaining_refreshes
+            .iter()
+            .flat_map(|refresh| refresh.key_identifiers())
+            .collect::<HashSet<_>>();
+        let mut reissuance_doc_types = depleted_mdoc_ids
+            .iter()
+            .filter(|mdoc_id| !replacements.iter().any(|(replaced_id, _)| replaced_id == *mdoc_id))
+            .filter(|mdoc_id| find_mdoc_id(&reissuable_key_ids) != Some(**mdoc_id))
+            .map(|mdoc_id| mdocs[mdoc_id][0].0.mdoc.doc_type.clone())
+            .collect::<Vec<_>>();
+        reissuance_doc_types.sort();
+        reissuance_doc_types.dedup();
+
+        // If the Wallet Provider returns either a PIN timeout or a permanent block,
+        // wipe the contents of the wallet and return it to its initial state.
+        if matches!(
+            result,
+            Err(PidIssuanceError::Instruction(
+                InstructionError::Timeout { .. } | InstructionError::Blocked
+            ))
+        ) {
+            self.reset_to_initial_state().await;
+            return result.map(|_| Vec::new());
+        }
+
+        let storage = self.storage.get_mut();
+
+        // Store the refreshes first, since the issuer has rotated the refresh tokens of the replenished mdocs.
+        storage
+            .upsert_data(&IssuanceRefreshData {
+                refreshes: remaining_refreshes,
+            })
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?;
+
+        if !replacements.is_empty() {
+            info!("Mdocs were replenished, replacing them in database");
+            storage
+                .replace_mdocs(replacements)
+                .await
+                .map_err(PidIssuanceError::MdocStorage)?;
+
+            self.emit_documents().await.map_err(PidIssuanceError::Document)?;
+        }
+
+        result?;
+
+        Ok(reissuance_doc_types)
+    }
+
+    /// Retrieve a wallet attestation from the Wallet Provider, if the configuration requires the wallet to send one to
+    /// issuers. The attestation is short-lived, so a new one is retrieved for every issuance.
+    async fn fetch_wallet_attestation(&self) -> Result<Option<WalletAttestation>, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        APC: AccountProviderClient,
+    {
+        let config = self.config_repository.config();
+        if !config.send_wallet_attestation {
+            return Ok(None);
+        }
+
+        let registration = self.registration.as_ref().ok_or(PidIssuanceError::NotRegistered)?;
+
+        info!("Retrieving wallet attestation from Wallet Provider");
+
+        let message = WalletAttestationRequest::new_signed("wallet", &registration.hw_privkey)
+            .await
+            .map_err(PidIssuanceError::WalletAttestationRequest)?;
+        let attestation = self
+            .account_provider_client
+            .wallet_attestation(
+                &config.account_server.base_url,
+                WalletAttestationRequestMessage {
+                    message,
+                    certificate: registration.data.wallet_certificate.clone(),
+                },
+            )
+            .await
+            .map_err(PidIssuanceError::WalletAttestation)?;
+
+        Ok(Some(attestation))
+    }
+
+    /// Perform `operation` using a client for sending OpenID4VCI messages to `credential_issuer`, see
+    /// [`Self::vc_message_client()`]. If the issuer requires the wallet attestation PoP to contain a challenge that it
+    /// provides, the operation is performed once more using a PoP containing that challenge. Errors of the operation
+    /// itself are returned in the inner result, so that the caller can handle them.
+    async fn with_vc_message_client<T, F, Fut>(
+        &self,
+        http_client: reqwest::Client,
+        wallet_attestation: Option<&WalletAttestation>,
+        credential_issuer: &BaseUrl,
+        operation: F,
+    ) -> Result<Result<T, IssuanceSessionError>, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+        F: Fn(HttpVcMessageClient) -> Fut,
+        Fut: Future<Output = Result<T, IssuanceSessionError>>,
+    {
+        let message_client = self
+            .vc_message_client(http_client.clone(), wallet_attestation, credential_issuer, None)
+            .await?;
+
+        match operation(message_client).await {
+            Err(IssuanceSessionError::AttestationChallenge(challenge)) if wallet_attestation.is_some() => {
+                info!("Issuer requires a challenge in the wallet attestation PoP, retrying");
+
+                let message_client = self
+                    .vc_message_client(http_client, wallet_attestation, credential_issuer, Some(challenge))
+                    .await?;
+                Ok(operation(message_client).await)
+            }
+            result => Ok(result),
+        }
+    }
+
+    /// Create the client for sending OpenID4VCI messages to `credential_issuer`, that includes the wallet attestation
+    /// along with a PoP for this issuer in its requests, if present. The PoP contains the `challenge` that the issuer
+    /// provided, if any.
+    async fn vc_message_client(
+        &self,
+        http_client: reqwest::Client,
+        wallet_attestation: Option<&WalletAttestation>,
+        credential_issuer: &BaseUrl,
+        challenge: Option<String>,
+    ) -> Result<HttpVcMessageClient, PidIssuanceError>
+    where
+        PEK: PlatformEcdsaKey,
+    {
+        let message_client = HttpVcMessageClient::new(
+            http_client,
+            self.config_repository.config().require_signed_issuer_metadata,
+        );
+
+        let Some(wallet_attestation) = wallet_attestation else {
+            return Ok(message_client);
+        };
+
+        let registration = self.registration.as_ref().ok_or(PidIssuanceError::NotRegistered)?;
+        let wallet_attestation = WalletAttestationWithPop::new(
+            wallet_attestation.clone(),
+            NL_WALLET_CLIENT_ID,
+            credential_issuer,
+            challenge,
+            &registration.hw_privkey,
+        )
+        .await
+        .map_err(PidIssuanceError::WalletAttestationPop)?;
+
+        Ok(message_client.with_wallet_attestation(wallet_attestation))
+    }
+
+    /// Store refreshes for issued mdocs in the database, so that they can be refreshed before they expire.
+    async fn add_issuance_refreshes(
+        &mut self,
+        refreshes: impl IntoIterator<Item = IssuanceRefresh>,
+    ) -> Result<(), PidIssuanceError> {
+        let mut refreshes = refreshes.into_iter().peekable();
+        if refreshes.peek().is_none() {
+            return Ok(());
+        }
+
+        let storage = self.storage.get_mut();
+        let mut data = storage
+            .fetch_data::<IssuanceRefreshData>()
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?
+            .unwrap_or_default();
+        data.refreshes.extend(refreshes);
+        storage
+            .upsert_data(&data)
+            .await
+            .map_err(PidIssuanceError::IssuanceRefreshStorage)?;
+
+        Ok(())
+    }
+
+    /// Store issued mdocs in the database, along with the corresponding history event.
+    async fn store_issued_mdocs(&mut self, mdocs: Vec<MdocCopies>) -> Result<(), PidIssuanceError> {
+        // Prepare events before storing mdocs, to avoid cloning mdocs
+        let event = {
+            // Extract first copy from cred_copies
+            let mdocs = mdocs
+                .iter()
+                .flat_map(|mdoc| mdoc.cred_copies.first())
+                .cloned()
+                .collect::<Vec<_>>();
+
+            // Validate all issuer_certificates
+            for mdoc in mdocs.iter() {
+                let certificate = mdoc
+                    .issuer_certificate()
+                    .map_err(PidIssuanceError::InvalidIssuerCertificate)?;
+
+                // Verify that the certificate contains IssuerRegistration
+                if matches!(IssuerRegistration::from_certificate(&certificate), Err(_) | Ok(None)) {
+                    return Err(PidIssuanceError::MissingIssuerRegistration);
+                }
+            }
+            WalletEvent::new_issuance(mdocs.try_into().map_err(PidIssuanceError::InvalidIssuerCertificate)?)
+        };
+        let doc_types = event
+            .associated_doc_types()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        info!("Attestations accepted, storing mdocs in database");
+        self.storage
+            .get_mut()
+            .insert_mdocs(mdocs)
+            .await
+            .map_err(PidIssuanceError::MdocStorage)?;
+
+        self.store_history_event(event)
+            .await
+            .map_err(PidIssuanceError::EventStorage)?;
+
+        // Documents restored from a backup no longer need to be reissued once they have been issued again.
+        self.remove_pending_reissuances(&doc_types)
+            .await
+            .map_err(PidIssuanceError::PendingReissuanceStorage)?;
+
+        Ok(())
+    }
+}
+
+impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS>
+where
+    IS: IssuanceSession,
+{
+    /// Notify the issuers of whether the mdocs they issued were stored successfully. As the mdocs have already been
+    /// issued at this point, errors are only logged.
+    async fn notify_issuers<T>(
+        notifications: impl IntoIterator<Item = IssuanceNotification>,
+        storage_result: &Result<T, PidIssuanceError>,
+    ) {
+        let event = match storage_result {
+            Ok(_) => NotificationEvent::CredentialAccepted,
+            Err(_) => NotificationEvent::CredentialFailure,
+        };
+
+        Self::send_notifications(notifications, event).await;
+    }
+
+    /// Notify the issuer of a deleted mdoc, identified by the private key of one of its copies, using the notification
+    /// contained in the stored refresh of that mdoc. Issuers only keep sessions of refreshable mdocs, so there is no
+    /// one to notify if the mdoc cannot be refreshed. As the mdoc has already been deleted at this point, errors are
+    /// only logged.
+    pub(super) async fn notify_issuer_of_deletion(refreshes: &[IssuanceRefresh], key_id: &str) {
+        let notifications = refreshes
+            .iter()
+            .filter(|refresh| refresh.key_identifiers().any(|refresh_key_id| refresh_key_id == key_id))
+            .filter_map(|refresh| refresh.notification().cloned());
+
+        Self::send_notifications(notifications, NotificationEvent::CredentialDeleted).await;
+    }
+
+    async fn send_notifications(
+        notifications: impl IntoIterator<Item = IssuanceNotification>,
+        event: NotificationEvent,
+    ) {
+        let http_client = default_reqwest_client_builder()
+            .default_headers(accept_json_headers())
+            .build()
+            .expect("Could not build reqwest HTTP client");
+
+        for notification in notifications {
+            if let Err(error) = IS::notify_issuance(http_client.clone().into(), notification, event).await {
+                warn!("could not notify issuer of {event:?}: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use mockall::predicate::*;
+    use nl_wallet_mdoc::{holder::Mdoc, unsigned::UnsignedMdoc, DataElementValue};
+    use openid4vc::{
+        credential_offer::{AuthorizationCodeGrant, CredentialOffer, Grants, PreAuthorizedCodeGrant, TxCode},
+        issuance_session::DeferredIssuance,
+        metadata::CredentialMetadata,
+        mock::MockIssuanceSession,
+        oidc::OidcError,
+        token::{AttestationPreview, TokenRequest, TokenRequestGrantType},
+        CredentialErrorCode, NotificationErrorCode,
+    };
+    use p256::{ecdsa::SigningKey, elliptic_curve::rand_core::OsRng};
+    use rstest::rstest;
+    use serde_json::json;
+    use serial_test::serial;
+    use url::Url;
+
+    use crate::{
+        document::{self, DocumentPersistence},
+        issuance::MockDigidSession,
+        storage::StorageState,
+        wallet::history::HistoryEvent,
+    };
+
+    use super::{
+        super::test::{self, WalletWithMocks, ISSUER_KEY},
+        *,
+    };
+
+    /// Create a [`MockIssuanceSession`] of an issuer that does not provide metadata about its credentials, so that
+    /// the built-in document mappings are used.
+    fn mock_issuance_session() -> MockIssuanceSession {
+        let mut client = MockIssuanceSession::new();
+        client.expect_metadata().return_const(HashMap::new());
+        client
+    }
+
+    #[tokio::test]
+    #[serial(MockDigidSession)]
+    async fn test_create_pid_issuance_auth_url() {
+        const AUTH_URL: &str = "http://example.com/auth";
+
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        assert!(wallet.issuance_session.is_none());
+
+        // Set up a mock DigiD session.
+        let session_start_context = MockDigidSession::start_context();
+        session_start_context.expect().returning(|_, _| {
+            let client = MockDigidSession::default();
+            Ok((client, Url::parse(AUTH_URL).unwrap()))
+        });
+
+        // Have the `Wallet` generate a DigiD authentication URL and test it.
+        let auth_url = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect("Could not generate PID issuance auth URL");
+
+        assert_eq!(auth_url.as_str(), AUTH_URL);
+        assert!(wallet.issuance_session.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_create_pid_issuance_auth_url_error_locked() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        wallet.lock();
+
+        // Creating a DigiD authentication URL on
+        // a locked wallet should result in an error.
+        let error = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("PID issuance auth URL generation should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_create_pid_issuance_auth_url_error_unregistered() {
+        // Prepare an unregistered wallet.
+        let mut wallet = WalletWithMocks::new_unregistered().await;
+
+        // Creating a DigiD authentication URL on an
+        // unregistered wallet should result in an error.
+        let error = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("PID issuance auth URL generation should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::NotRegistered);
+    }
+
+    #[tokio::test]
+    async fn test_create_pid_issuance_auth_url_error_session_state_digid() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock DigiD session.
+        wallet.issuance_session = Some(PidIssuanceSession::Digid(MockDigidSession::default()));
+
+        // Creating a DigiD authentication URL on a `Wallet` that
+        // has an active DigiD session should return an error.
+        let error = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("PID issuance auth URL generation should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::SessionState);
+    }
+
+    #[tokio::test]
+    async fn test_create_pid_issuance_auth_url_error_session_state_pid_issuer() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Setup a mock OpenID4VCI session.
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(MockIssuanceSession::default()));
+
+        // Creating a DigiD authentication URL on a `Wallet` that has
+        // an active OpenID4VCI session should return an error.
+        let error = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("PID issuance auth URL generation should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::SessionState);
+    }
+
+    #[tokio::test]
+    #[serial(MockDigidSession)]
+    async fn test_create_pid_issuance_auth_url_error_digid_session_start() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Make DigiD session starting return an error.
+        let session_start_context = MockDigidSession::start_context();
+        session_start_context
+            .expect()
+            .return_once(|_, _| Err(OidcError::NoAuthCode.into()));
+
+        // The error should be forwarded when attempting to create a DigiD authentication URL.
+        let error = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("PID issuance auth URL generation should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::DigidSessionStart(_));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_digid() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock DigiD session.
+        wallet.issuance_session = Some(PidIssuanceSession::Digid(MockDigidSession::default()));
+
+        assert!(wallet.issuance_session.is_some());
+
+        // Cancelling PID issuance should clear this session.
+        wallet
+            .cancel_pid_issuance()
+            .await
+            .expect("Could not cancel PID issuance");
+
+        assert!(wallet.issuance_session.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_pid() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up the `PidIssuerClient`
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client.expect_reject().return_once(|| Ok(()));
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+
+        // Cancelling PID issuance should not fail.
+        wallet
+            .cancel_pid_issuance()
+            .await
+            .expect("Could not cancel PID issuance");
+
+        assert!(wallet.issuance_session.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_error_locked() {
+        // Prepare a registered and locked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        wallet.lock();
+
+        // Cancelling PID issuance on a locked wallet should result in an error.
+        let error = wallet
+            .cancel_pid_issuance()
+            .await
+            .expect_err("Cancelling PID issuance should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_error_unregistered() {
+        // Prepare an unregistered wallet.
+        let mut wallet = WalletWithMocks::new_unregistered().await;
+
+        // Cancelling PID issuance on an unregistered wallet should result in an error.
+        let error = wallet
+            .cancel_pid_issuance()
+            .await
+            .expect_err("Cancelling PID issuance should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::NotRegistered);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_error_session_state() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Cancelling PID issuance on a wallet with no
+        // active DigiD session should result in an error.
+        let error = wallet
+            .cancel_pid_issuance()
+            .await
+            .expect_err("Cancelling PID issuance should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::SessionState);
+    }
+
+    const REDIRECT_URI: &str = "redirect://here";
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_continue_pid_issuance() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock DigiD session that returns a token request.
+        let issuance_session = {
+            let mut session = MockDigidSession::default();
+
+            session.expect_into_token_request().return_once(|_uri| {
+                Ok(TokenRequest {
+                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
+                        pre_authorized_code: "123".to_string().into(),
+                        tx_code: None,
+                    },
+                    code_verifier: None,
+                    client_id: None,
+                    redirect_uri: None,
+                })
+            });
+
+            session
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Digid(issuance_session));
+
+        // Set up the `MockIssuanceSession` to return one `AttestationPreview`.
+        let start_context = MockIssuanceSession::start_context();
+        start_context.expect().return_once(|| {
+            Ok((
+                mock_issuance_session(),
+                vec![AttestationPreview::MsoMdoc {
+                    unsigned_mdoc: document::create_full_unsigned_pid_mdoc(),
+                    issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+                }],
+            ))
         });
 
-        // Have the `Wallet` generate a DigiD authentication URL and test it.
-        let auth_url = wallet
-            .create_pid_issuance_auth_url()
+        // Continuing PID issuance should result in one preview `Document`.
+        let documents = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
             .await
-            .expect("Could not generate PID issuance auth URL");
+            .expect("Could not continue PID issuance");
 
-        assert_eq!(auth_url.as_str(), AUTH_URL);
-        assert!(wallet.issuance_session.is_some());
+        assert_eq!(documents.len(), 1);
+        assert_matches!(documents[0].persistence, DocumentPersistence::InMemory);
+    }
+
+    #[tokio::test]
+    async fn test_continue_pid_issuance_error_locked() {
+        // Prepare a registered and locked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        wallet.lock();
+
+        // Continuing PID issuance on a locked wallet should result in an error.
+        let error = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .await
+            .expect_err("Continuing PID issuance should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::Locked);
+    }
+
+    #[tokio::test]
+    async fn test_continue_pid_issuance_error_unregistered() {
+        // Prepare an unregistered wallet.
+        let mut wallet = WalletWithMocks::new_unregistered().await;
+
+        // Continuing PID issuance on an unregistered wallet should result in an error.
+        let error = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .await
+            .expect_err("Continuing PID issuance should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::NotRegistered);
+    }
+
+    #[tokio::test]
+    async fn test_continue_pid_issuance_error_session_state() {
+        // Prepare a registered wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Continuing PID issuance on a wallet with no active `DigidSession` should result in an error.
+        let error = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .await
+            .expect_err("Continuing PID issuance should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::SessionState);
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_continue_pid_issuance_error_pid_issuer() {
+        // Prepare a registered wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock DigiD session that returns a token request.
+        let digid_session = {
+            let mut session = MockDigidSession::default();
+
+            session.expect_into_token_request().return_once(|_uri| {
+                Ok(TokenRequest {
+                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
+                        pre_authorized_code: "123".to_string().into(),
+                        tx_code: None,
+                    },
+                    code_verifier: None,
+                    client_id: None,
+                    redirect_uri: None,
+                })
+            });
+
+            session
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Digid(digid_session));
+
+        // Set up the `MockIssuanceSession` to return an error.
+        let start_context = MockIssuanceSession::start_context();
+        start_context
+            .expect()
+            .return_once(|| Err(IssuanceSessionError::MissingNonce));
+
+        // Continuing PID issuance on a wallet should forward this error.
+        let error = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .await
+            .expect_err("Continuing PID issuance should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_continue_pid_issuance_error_document() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock DigiD session that returns a token request.
+        let digid_session = {
+            let mut session = MockDigidSession::default();
+
+            session.expect_into_token_request().return_once(|_uri| {
+                Ok(TokenRequest {
+                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
+                        pre_authorized_code: "123".to_string().into(),
+                        tx_code: None,
+                    },
+                    code_verifier: None,
+                    client_id: None,
+                    redirect_uri: None,
+                })
+            });
+
+            session
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Digid(digid_session));
+
+        // Set up the `MockIssuanceSession` to return an `AttestationPreview` with an attribute of the wrong type.
+        let start_context = MockIssuanceSession::start_context();
+        start_context.expect().return_once(|| {
+            let mut unsigned_mdoc = document::create_full_unsigned_pid_mdoc();
+            let mut attributes = unsigned_mdoc.attributes.into_inner();
+            attributes
+                .get_mut(PID_DOCTYPE)
+                .unwrap()
+                .iter_mut()
+                .find(|entry| entry.name == "given_name")
+                .unwrap()
+                .value = DataElementValue::Bool(true);
+            unsigned_mdoc.attributes = attributes.try_into().unwrap();
+
+            Ok((
+                mock_issuance_session(),
+                vec![AttestationPreview::MsoMdoc {
+                    unsigned_mdoc,
+                    issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+                }],
+            ))
+        });
+
+        // Continuing PID issuance when receiving an invalid mdoc should result in an error.
+        let error = wallet
+            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .await
+            .expect_err("Continuing PID issuance should have resulted in error");
+
+        assert_matches!(error, PidIssuanceError::MdocDocument(_));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pid_issuance_error_pid_issuer() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up a mock OpenID4VCI session that expects to be rejected, which returns an error.
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client
+                .expect_reject()
+                .return_once(|| Err(IssuanceSessionError::MissingNonce));
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+
+        // Canceling PID issuance on a wallet should forward this error.
+        let error = wallet
+            .cancel_pid_issuance()
+            .await
+            .expect_err("Rejecting PID issuance should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+    }
+
+    const PIN: &str = "051097";
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_accept_pid_issuance() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
+
+        // Register mock recent_history_callback
+        let events = test::setup_mock_recent_history_callback(&mut wallet).await.unwrap();
+
+        // Create a mock OpenID4VCI session that accepts the PID with a single
+        // instance of `MdocCopies`, which contains a single valid `Mdoc`,
+        // along with a refresh for it and a notification ID.
+        let mdoc = test::create_full_pid_mdoc().await;
+        let refresh = mock_issuance_refresh(&mdoc);
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc].into()],
+                    refresh: Some(Box::new(refresh)),
+                    notification: Some(Box::new(IssuanceNotification::new_mock("notification_id".to_string()))),
+                })
+            });
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+
+        // The issuer should be notified that the PID was stored.
+        let notify_context = MockIssuanceSession::notify_context();
+        notify_context
+            .expect()
+            .with(always(), eq(NotificationEvent::CredentialAccepted))
+            .times(1)
+            .return_once(|_, _| Ok(()));
+
+        // Accept the PID issuance with the PIN.
+        wallet
+            .accept_pid_issuance(PIN.to_string())
+            .await
+            .expect("Could not accept PID issuance");
+
+        // The refresh should be stored, so that the PID can be refreshed before it expires.
+        let refresh_data = wallet
+            .storage
+            .get_mut()
+            .fetch_data::<IssuanceRefreshData>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refresh_data.refreshes.len(), 1);
+
+        {
+            // Test which `Document` instances we have received through the callback.
+            let documents = documents.lock();
+
+            // The first entry should be empty, because there are no mdocs in the database.
+            assert_eq!(documents.len(), 2);
+            assert!(documents[0].is_empty());
+
+            // The second entry should contain a single document with the PID.
+            assert_eq!(documents[1].len(), 1);
+            let document = &documents[1][0];
+            assert_matches!(document.persistence, DocumentPersistence::Stored(_));
+            assert_eq!(document.doc_type, "com.example.pid");
+
+            // Test that one successful issuance event is logged
+            let events = events.lock();
+            assert_eq!(events.len(), 2);
+            assert!(events[0].is_empty());
+            assert_eq!(events[1].len(), 1);
+            assert_matches!(&events[1][0], HistoryEvent::Issuance { .. });
+
+            assert!(wallet.has_registration());
+            assert!(!wallet.is_locked());
+        }
+
+        // Starting another PID issuance should fail
+        const AUTH_URL: &str = "http://example.com/auth";
+        // Set up a mock DigiD session.
+        let session_start_context = MockDigidSession::start_context();
+        session_start_context.expect().returning(|_, _| {
+            let client = MockDigidSession::default();
+            Ok((client, Url::parse(AUTH_URL).unwrap()))
+        });
+
+        let err = wallet
+            .create_pid_issuance_auth_url()
+            .await
+            .expect_err("creating new PID issuance auth URL when there already is a PID should fail");
+        assert_matches!(err, PidIssuanceError::PidAlreadyPresent);
     }
 
     #[tokio::test]
-    async fn test_create_pid_issuance_auth_url_error_locked() {
+    async fn test_accept_pid_issuance_missing_issuer_registration() {
+        // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        wallet.lock();
+        // Create a mock OpenID4VCI session that accepts the PID with a single instance of `MdocCopies`, which contains
+        // a single valid `Mdoc`, but signed with a Certificate that is missing IssuerRegistration
+        let mdoc = test::create_full_pid_mdoc_unauthenticated().await;
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc].into()],
+                    refresh: None,
+                    notification: None,
+                })
+            });
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
 
-        // Creating a DigiD authentication URL on
-        // a locked wallet should result in an error.
+        // Accept the PID issuance with the PIN.
         let error = wallet
-            .create_pid_issuance_auth_url()
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("PID issuance auth URL generation should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::Locked);
+        assert_matches!(error, PidIssuanceError::MissingIssuerRegistration);
+
+        // No issuance event is logged
+        let events = wallet.storage.read().await.fetch_wallet_events().await.unwrap();
+        assert!(events.is_empty());
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
     }
 
     #[tokio::test]
-    async fn test_create_pid_issuance_auth_url_error_unregistered() {
+    async fn test_accept_pid_issuance_unregistered() {
         // Prepare an unregistered wallet.
         let mut wallet = WalletWithMocks::new_unregistered().await;
 
-        // Creating a DigiD authentication URL on an
-        // unregistered wallet should result in an error.
+        // Accepting PID issuance on an unregistered wallet should result in an error.
         let error = wallet
-            .create_pid_issuance_auth_url()
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("PID issuance auth URL generation should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
         assert_matches!(error, PidIssuanceError::NotRegistered);
     }
 
     #[tokio::test]
-    async fn test_create_pid_issuance_auth_url_error_session_state_digid() {
+    async fn test_accept_pid_issuance_locked() {
+        // Prepare a registered and locked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Set up a mock DigiD session.
-        wallet.issuance_session = Some(PidIssuanceSession::Digid(MockDigidSession::default()));
+        wallet.lock();
 
-        // Creating a DigiD authentication URL on a `Wallet` that
-        // has an active DigiD session should return an error.
+        // Accepting PID issuance on a locked wallet should result in an error.
         let error = wallet
-            .create_pid_issuance_auth_url()
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("PID issuance auth URL generation should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::SessionState);
+        assert_matches!(error, PidIssuanceError::Locked);
+
+        assert!(wallet.has_registration());
+        assert!(wallet.is_locked());
     }
 
     #[tokio::test]
-    async fn test_create_pid_issuance_auth_url_error_session_state_pid_issuer() {
+    async fn test_accept_pid_issuance_session_state() {
+        // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Setup a mock OpenID4VCI session.
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(MockIssuanceSession::default()));
-
-        // Creating a DigiD authentication URL on a `Wallet` that has
-        // an active OpenID4VCI session should return an error.
+        // Accepting PID issuance on a `Wallet` with a `PidIssuerClient`
+        // that has no session should result in an error.
         let error = wallet
-            .create_pid_issuance_auth_url()
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("PID issuance auth URL generation should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
         assert_matches!(error, PidIssuanceError::SessionState);
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
     }
 
-    #[tokio::test]
-    #[serial(MockDigidSession)]
-    async fn test_create_pid_issuance_auth_url_error_digid_session_start() {
+    async fn test_accept_pid_issuance_error_remote_key(
+        key_error: RemoteEcdsaKeyError,
+    ) -> (WalletWithMocks, PidIssuanceError) {
+        // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Make DigiD session starting return an error.
-        let session_start_context = MockDigidSession::start_context();
-        session_start_context
-            .expect()
-            .return_once(|_, _| Err(OidcError::NoAuthCode.into()));
+        // Have the mock OpenID4VCI session return a particular `RemoteEcdsaKeyError` upon accepting.
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client
+                .expect_accept()
+                .return_once(|| Err(IssuanceSessionError::Jwt(JwtError::Signing(Box::new(key_error)))));
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
 
-        // The error should be forwarded when attempting to create a DigiD authentication URL.
+        // Accepting PID issuance should result in an error.
         let error = wallet
-            .create_pid_issuance_auth_url()
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("PID issuance auth URL generation should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::DigidSessionStart(_));
+        (wallet, error)
     }
 
+    #[rstest]
+    #[case(InstructionError::IncorrectPin { attempts_left_in_round: 1, is_final_round: false }, false)]
+    #[case(InstructionError::Timeout { timeout_millis: 10_000 }, true)]
+    #[case(InstructionError::Blocked, true)]
+    #[case(InstructionError::InstructionValidation, false)]
     #[tokio::test]
-    async fn test_cancel_pid_issuance_digid() {
-        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+    async fn test_accept_pid_issuance_error_instruction(
+        #[case] instruction_error: InstructionError,
+        #[case] expect_reset: bool,
+    ) {
+        let (mut wallet, error) =
+            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::from(instruction_error)).await;
 
-        // Set up a mock DigiD session.
-        wallet.issuance_session = Some(PidIssuanceSession::Digid(MockDigidSession::default()));
+        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
+        assert_matches!(error, PidIssuanceError::Instruction(_));
 
-        assert!(wallet.issuance_session.is_some());
+        // Test the state of the Wallet, based on if we expect a reset for this InstructionError.
+        if expect_reset {
+            assert!(!wallet.has_registration());
+            assert!(wallet.is_locked());
+            assert_matches!(
+                wallet.storage.get_mut().state().await.unwrap(),
+                StorageState::Uninitialized
+            );
+        } else {
+            assert!(wallet.has_registration());
+            assert!(!wallet.is_locked());
+            assert_matches!(wallet.storage.get_mut().state().await.unwrap(), StorageState::Opened);
+        }
+    }
 
-        // Cancelling PID issuance should clear this session.
-        wallet
-            .cancel_pid_issuance()
+    #[tokio::test]
+    async fn test_accept_pid_issuance_error_signature() {
+        let (wallet, error) =
+            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::from(signature::Error::default())).await;
+
+        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
+        assert_matches!(error, PidIssuanceError::Signature(_));
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
+    }
+
+    #[tokio::test]
+    async fn test_accept_pid_issuance_error_key_not_found() {
+        let (wallet, error) =
+            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::KeyNotFound("not found".to_string())).await;
+
+        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
+        assert_matches!(error, PidIssuanceError::KeyNotFound(_));
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
+    }
+
+    #[tokio::test]
+    async fn test_accept_pid_issuance_error_pid_issuer() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Have the mock OpenID4VCI session return an error upon accepting.
+        let pid_issuer = {
+            let mut client = mock_issuance_session();
+            client
+                .expect_accept()
+                .return_once(|| Err(IssuanceSessionError::MissingNonce));
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+
+        // Accepting PID issuance should result in an error.
+        let error = wallet
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect("Could not cancel PID issuance");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
-        assert!(wallet.issuance_session.is_none());
+        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
     }
 
     #[tokio::test]
-    async fn test_cancel_pid_issuance_pid() {
+    #[serial(MockIssuanceSession)]
+    async fn test_accept_pid_issuance_error_storage() {
         // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Set up the `PidIssuerClient`
+        // Have the mock OpenID4VCI session report some mdocs upon accepting.
+        let mdoc = test::create_full_pid_mdoc().await;
         let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client.expect_reject().return_once(|| Ok(()));
+            let mut client = mock_issuance_session();
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc].into()],
+                    refresh: None,
+                    notification: Some(Box::new(IssuanceNotification::new_mock("notification_id".to_string()))),
+                })
+            });
             client
         };
         wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
 
-        // Cancelling PID issuance should not fail.
-        wallet
-            .cancel_pid_issuance()
+        // The issuer should be notified that the PID could not be stored.
+        let notify_context = MockIssuanceSession::notify_context();
+        notify_context
+            .expect()
+            .with(always(), eq(NotificationEvent::CredentialFailure))
+            .times(1)
+            .return_once(|_, _| {
+                Err(IssuanceSessionError::NotificationRequest(ErrorResponse {
+                    error: NotificationErrorCode::ServerError,
+                    error_description: None,
+                    error_uri: None,
+                }))
+            });
+
+        // Have the mdoc storage return an error on query.
+        wallet.storage.get_mut().has_query_error = true;
+
+        // Accepting PID issuance should result in an error.
+        let error = wallet
+            .accept_pid_issuance(PIN.to_string())
+            .await
+            .expect_err("Accepting PID issuance should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::MdocStorage(_));
+
+        assert!(wallet.has_registration());
+        assert!(!wallet.is_locked());
+    }
+
+    fn credential_offer_uri() -> Url {
+        credential_offer_uri_with_tx_code(None)
+    }
+
+    fn credential_offer_uri_with_tx_code(tx_code: Option<TxCode>) -> Url {
+        let offer = CredentialOffer {
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
+            credential_configuration_ids: vec!["com.example.pid".to_string()],
+            grants: Some(Grants {
+                authorization_code: None,
+                pre_authorized_code: Some(PreAuthorizedCodeGrant {
+                    pre_authorized_code: "123".to_string().into(),
+                    tx_code,
+                }),
+            }),
+        };
+
+        CredentialOfferContainer::Value(offer).to_uri(Url::parse("openid-credential-offer://").unwrap())
+    }
+
+    /// Create a [`MockIssuanceSession`] of an issuer that supports the credential configuration offered by
+    /// [`credential_offer_uri()`].
+    fn mock_credential_offer_session() -> MockIssuanceSession {
+        let metadata: CredentialMetadata = serde_json::from_value(json!({
+            "format": "mso_mdoc",
+            "doctype": PID_DOCTYPE,
+            "claims": {},
+        }))
+        .unwrap();
+
+        let mut client = MockIssuanceSession::new();
+        client
+            .expect_metadata()
+            .return_const(HashMap::from([("com.example.pid".to_string(), metadata)]));
+        client
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_start_issuance_from_offer() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Set up the `MockIssuanceSession` to return one `AttestationPreview`.
+        let start_context = MockIssuanceSession::start_context();
+        start_context.expect().return_once(|| {
+            Ok((
+                mock_credential_offer_session(),
+                vec![AttestationPreview::MsoMdoc {
+                    unsigned_mdoc: document::create_full_unsigned_pid_mdoc(),
+                    issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+                }],
+            ))
+        });
+
+        // Starting issuance from the offer should result in one preview `Document`.
+        let documents = wallet
+            .start_issuance_from_offer(credential_offer_uri(), None)
+            .await
+            .expect("Could not start issuance from credential offer");
+
+        assert_eq!(documents.len(), 1);
+        assert_matches!(documents[0].persistence, DocumentPersistence::InMemory);
+        assert!(matches!(
+            &wallet.issuance_session,
+            Some(PidIssuanceSession::CredentialOffer { credential_issuer, .. })
+                if credential_issuer.as_ref().as_str() == "https://issuer.example.com/"
+        ));
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_start_issuance_from_offer_tx_code() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let offer_uri = credential_offer_uri_with_tx_code(Some(TxCode::new_for("1234", None)));
+
+        // Starting issuance without a transaction code should result in an error describing the code.
+        let error = wallet
+            .start_issuance_from_offer(offer_uri.clone(), None)
+            .await
+            .expect_err("Starting issuance without transaction code should have resulted in an error");
+
+        assert_matches!(
+            error,
+            PidIssuanceError::CredentialOffer(CredentialOfferError::MissingTxCode(TxCode { length: Some(4), .. }))
+        );
+
+        // Set up the `MockIssuanceSession` to reject the transaction code.
+        let start_context = MockIssuanceSession::start_context();
+        start_context.expect().return_once(|| {
+            Err(IssuanceSessionError::TokenRequest(ErrorResponse {
+                error: TokenErrorCode::InvalidGrant,
+                error_description: None,
+                error_uri: None,
+            }))
+        });
+
+        // Starting issuance with an incorrect transaction code should result in an error.
+        let error = wallet
+            .start_issuance_from_offer(offer_uri, Some("0000".to_string()))
             .await
-            .expect("Could not cancel PID issuance");
+            .expect_err("Starting issuance with incorrect transaction code should have resulted in an error");
 
+        assert_matches!(error, PidIssuanceError::IncorrectTxCode);
         assert!(wallet.issuance_session.is_none());
     }
 
     #[tokio::test]
-    async fn test_cancel_pid_issuance_error_locked() {
-        // Prepare a registered and locked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_start_issuance_from_offer_error_unsupported_credential_configuration() {
+        // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        wallet.lock();
+        // Set up the `MockIssuanceSession` of an issuer that does not support the offered credential configuration,
+        // which should be rejected.
+        let start_context = MockIssuanceSession::start_context();
+        start_context.expect().return_once(|| {
+            let mut client = mock_issuance_session();
+            client.expect_reject().return_once(|| Ok(()));
 
-        // Cancelling PID issuance on a locked wallet should result in an error.
+            Ok((
+                client,
+                vec![AttestationPreview::MsoMdoc {
+                    unsigned_mdoc: document::create_full_unsigned_pid_mdoc(),
+                    issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+                }],
+            ))
+        });
+
+        // Starting issuance from the offer should result in an error.
         let error = wallet
-            .cancel_pid_issuance()
+            .start_issuance_from_offer(credential_offer_uri(), None)
             .await
-            .expect_err("Cancelling PID issuance should have resulted in an error");
+            .expect_err("Starting issuance from credential offer should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::Locked);
+        assert_matches!(
+            error,
+            PidIssuanceError::CredentialOffer(CredentialOfferError::UnsupportedCredentialConfiguration(id))
+                if id == "com.example.pid"
+        );
+        assert!(wallet.issuance_session.is_none());
     }
 
     #[tokio::test]
-    async fn test_cancel_pid_issuance_error_unregistered() {
-        // Prepare an unregistered wallet.
-        let mut wallet = WalletWithMocks::new_unregistered().await;
+    async fn test_start_issuance_from_offer_error_session_state() {
+        // Prepare a registered and unlocked wallet with an active issuance session.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(MockIssuanceSession::default()));
 
-        // Cancelling PID issuance on an unregistered wallet should result in an error.
+        // Starting issuance from an offer should result in an error.
         let error = wallet
-            .cancel_pid_issuance()
+            .start_issuance_from_offer(credential_offer_uri(), None)
             .await
-            .expect_err("Cancelling PID issuance should have resulted in an error");
+            .expect_err("Starting issuance from credential offer should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::NotRegistered);
+        assert_matches!(error, PidIssuanceError::SessionState);
     }
 
     #[tokio::test]
-    async fn test_cancel_pid_issuance_error_session_state() {
+    async fn test_start_issuance_from_offer_error_credential_offer() {
         // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Cancelling PID issuance on a wallet with no
-        // active DigiD session should result in an error.
+        // Starting issuance from a URI without an offer should result in an error.
         let error = wallet
-            .cancel_pid_issuance()
+            .start_issuance_from_offer(Url::parse("openid-credential-offer://").unwrap(), None)
             .await
-            .expect_err("Cancelling PID issuance should have resulted in an error");
+            .expect_err("Starting issuance from credential offer should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::SessionState);
+        assert_matches!(
+            error,
+            PidIssuanceError::CredentialOffer(CredentialOfferError::InvalidParameters)
+        );
+        assert!(wallet.issuance_session.is_none());
     }
 
-    const REDIRECT_URI: &str = "redirect://here";
+    fn authorization_code_offer_uri() -> Url {
+        let offer = CredentialOffer {
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
+            credential_configuration_ids: vec!["com.example.pid".to_string()],
+            grants: Some(Grants {
+                authorization_code: Some(AuthorizationCodeGrant {
+                    issuer_state: Some("issuer_state".to_string()),
+                }),
+                pre_authorized_code: None,
+            }),
+        };
+
+        CredentialOfferContainer::Value(offer).to_uri(Url::parse("openid-credential-offer://").unwrap())
+    }
+
+    fn mock_issuance_authorization() -> IssuanceAuthorization {
+        IssuanceAuthorization::new_mock(
+            "https://issuer.example.com".parse().unwrap(),
+            urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).into_inner(),
+            "state".to_string(),
+        )
+    }
 
     #[tokio::test]
     #[serial(MockIssuanceSession)]
-    async fn test_continue_pid_issuance() {
+    async fn test_create_issuance_auth_url() {
+        const AUTH_URL: &str = "https://issuer.example.com/authorize?client_id=wallet&request_uri=123";
+
         // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Set up a mock DigiD session that returns a token request.
-        let issuance_session = {
-            let mut session = MockDigidSession::default();
+        // Set up the `MockIssuanceSession` to start an authorization at the issuer from the offer.
+        let authorize_context = MockIssuanceSession::authorize_context();
+        authorize_context
+            .expect()
+            .return_once(|credential_issuer, issuer_state| {
+                assert_eq!(credential_issuer.as_ref().as_str(), "https://issuer.example.com/");
+                assert_eq!(issuer_state.as_deref(), Some("issuer_state"));
 
-            session.expect_into_token_request().return_once(|_uri| {
-                Ok(TokenRequest {
-                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
-                        pre_authorized_code: "123".to_string().into(),
-                    },
-                    code_verifier: None,
-                    client_id: None,
-                    redirect_uri: None,
-                })
+                Ok((mock_issuance_authorization(), Url::parse(AUTH_URL).unwrap()))
             });
 
-            session
-        };
-        wallet.issuance_session = Some(PidIssuanceSession::Digid(issuance_session));
+        // Starting authorization from the offer should result in the authorization URL.
+        let auth_url = wallet
+            .create_issuance_auth_url(authorization_code_offer_uri())
+            .await
+            .expect("Could not generate issuance auth URL");
+
+        assert_eq!(auth_url.as_str(), AUTH_URL);
+        assert!(matches!(
+            wallet.issuance_session,
+            Some(PidIssuanceSession::Authorization(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_issuance_auth_url_error_credential_offer() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        // Starting authorization from an offer that only contains a pre-authorized code should result in an error.
+        let error = wallet
+            .create_issuance_auth_url(credential_offer_uri())
+            .await
+            .expect_err("Starting authorization should have resulted in an error");
+
+        assert_matches!(
+            error,
+            PidIssuanceError::CredentialOffer(CredentialOfferError::MissingAuthorizationCode)
+        );
+        assert!(wallet.issuance_session.is_none());
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_continue_issuance_authorization() {
+        // Prepare a registered and unlocked wallet with an active authorization.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet.issuance_session = Some(PidIssuanceSession::Authorization(mock_issuance_authorization()));
 
         // Set up the `MockIssuanceSession` to return one `AttestationPreview`.
         let start_context = MockIssuanceSession::start_context();
         start_context.expect().return_once(|| {
             Ok((
-                MockIssuanceSession::new(),
+                mock_issuance_session(),
                 vec![AttestationPreview::MsoMdoc {
                     unsigned_mdoc: document::create_full_unsigned_pid_mdoc(),
                     issuer: ISSUER_KEY.issuance_key.certificate().clone(),
@@ -623,458 +2587,718 @@ mod tests {
             ))
         });
 
-        // Continuing PID issuance should result in one preview `Document`.
+        // Continuing with the redirect URI of the authorization server should result in one preview `Document`.
+        let mut redirect_uri = urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).into_inner();
+        redirect_uri.set_query(Some("code=auth_code&state=state"));
         let documents = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .continue_issuance_authorization(redirect_uri)
             .await
-            .expect("Could not continue PID issuance");
+            .expect("Could not continue issuance authorization");
 
         assert_eq!(documents.len(), 1);
-        assert_matches!(documents[0].persistence, DocumentPersistence::InMemory);
+        assert!(matches!(
+            &wallet.issuance_session,
+            Some(PidIssuanceSession::CredentialOffer { credential_issuer, .. })
+                if credential_issuer.as_ref().as_str() == "https://issuer.example.com/"
+        ));
     }
 
     #[tokio::test]
-    async fn test_continue_pid_issuance_error_locked() {
-        // Prepare a registered and locked wallet.
+    async fn test_continue_issuance_authorization_error_state_token() {
+        // Prepare a registered and unlocked wallet with an active authorization.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet.issuance_session = Some(PidIssuanceSession::Authorization(mock_issuance_authorization()));
 
-        wallet.lock();
-
-        // Continuing PID issuance on a locked wallet should result in an error.
+        // Continuing with a redirect URI containing a different state should result in an error.
+        let mut redirect_uri = urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).into_inner();
+        redirect_uri.set_query(Some("code=auth_code&state=other_state"));
         let error = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .continue_issuance_authorization(redirect_uri)
             .await
-            .expect_err("Continuing PID issuance should have resulted in error");
+            .expect_err("Continuing issuance authorization should have resulted in an error");
 
-        assert_matches!(error, PidIssuanceError::Locked);
+        assert_matches!(
+            error,
+            PidIssuanceError::PidIssuer(IssuanceSessionError::StateTokenMismatch)
+        );
+        assert!(wallet.issuance_session.is_none());
     }
 
     #[tokio::test]
-    async fn test_continue_pid_issuance_error_unregistered() {
-        // Prepare an unregistered wallet.
-        let mut wallet = WalletWithMocks::new_unregistered().await;
-
-        // Continuing PID issuance on an unregistered wallet should result in an error.
-        let error = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
-            .await
-            .expect_err("Continuing PID issuance should have resulted in error");
+    async fn test_accept_issuance() {
+        // Prepare a registered and unlocked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        assert_matches!(error, PidIssuanceError::NotRegistered);
-    }
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
 
-    #[tokio::test]
-    async fn test_continue_pid_issuance_error_session_state() {
-        // Prepare a registered wallet.
-        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        // Create a mock OpenID4VCI session, started from a credential offer, that accepts a single `Mdoc`.
+        let mdoc = test::create_full_pid_mdoc().await;
+        let session = {
+            let mut client = mock_issuance_session();
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc].into()],
+                    refresh: None,
+                    notification: None,
+                })
+            });
+            client
+        };
+        wallet.issuance_session = Some(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
+        });
 
-        // Continuing PID issuance on a wallet with no active `DigidSession` should result in an error.
+        // Accepting PID issuance should not be possible for this session.
         let error = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+            .accept_pid_issuance(PIN.to_string())
             .await
-            .expect_err("Continuing PID issuance should have resulted in error");
+            .expect_err("Accepting PID issuance should have resulted in an error");
 
         assert_matches!(error, PidIssuanceError::SessionState);
+
+        // Accept the issuance with the PIN.
+        wallet
+            .accept_issuance(PIN.to_string())
+            .await
+            .expect("Could not accept issuance");
+
+        assert!(wallet.issuance_session.is_none());
+
+        let documents = documents.lock();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].len(), 1);
+        assert_matches!(documents[1][0].persistence, DocumentPersistence::Stored(_));
     }
 
     #[tokio::test]
-    #[serial(MockIssuanceSession)]
-    async fn test_continue_pid_issuance_error_pid_issuer() {
-        // Prepare a registered wallet.
+    async fn test_accept_issuance_document_mappings() {
+        // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Set up a mock DigiD session that returns a token request.
-        let digid_session = {
-            let mut session = MockDigidSession::default();
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
 
-            session.expect_into_token_request().return_once(|_uri| {
-                Ok(TokenRequest {
-                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
-                        pre_authorized_code: "123".to_string().into(),
+        // Create a mock OpenID4VCI session of an issuer that describes one of the claims of a custom doc type in its
+        // metadata. The issuer also describes the PID, which should be ignored as it has a built-in mapping.
+        let metadata: CredentialMetadata = serde_json::from_value(json!({
+            "format": "mso_mdoc",
+            "doctype": "com.example.foobar",
+            "claims": {
+                PID_DOCTYPE: {
+                    "given_name": {
+                        "display": [{ "name": "Given name", "locale": "en" }],
                     },
-                    code_verifier: None,
-                    client_id: None,
-                    redirect_uri: None,
+                },
+            },
+        }))
+        .unwrap();
+        let pid_metadata: CredentialMetadata = serde_json::from_value(json!({
+            "format": "mso_mdoc",
+            "doctype": PID_DOCTYPE,
+            "claims": {
+                PID_DOCTYPE: {
+                    "bsn": {
+                        "display": [{ "name": "Citizen service number", "locale": "en" }],
+                    },
+                },
+            },
+        }))
+        .unwrap();
+        let mut mdoc = test::create_full_pid_mdoc().await;
+        mdoc.doc_type = "com.example.foobar".to_string();
+        let issuer_certificate = mdoc.issuer_certificate().unwrap();
+        let session = {
+            let mut client = MockIssuanceSession::new();
+            client.expect_metadata().return_const(HashMap::from([
+                ("com.example.foobar".to_string(), metadata.clone()),
+                (PID_DOCTYPE.to_string(), pid_metadata),
+            ]));
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc].into()],
+                    refresh: None,
+                    notification: None,
                 })
             });
-
-            session
+            client
         };
-        wallet.issuance_session = Some(PidIssuanceSession::Digid(digid_session));
+        wallet.issuance_session = Some(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
+        });
 
-        // Set up the `MockIssuanceSession` to return an error.
-        let start_context = MockIssuanceSession::start_context();
-        start_context
-            .expect()
-            .return_once(|| Err(IssuanceSessionError::MissingNonce));
+        wallet
+            .accept_issuance(PIN.to_string())
+            .await
+            .expect("Could not accept issuance");
 
-        // Continuing PID issuance on a wallet should forward this error.
-        let error = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+        // Only the mappings of the issued doc type should be stored, for the certificate of its issuer.
+        let mappings = wallet
+            .storage
+            .read()
             .await
-            .expect_err("Continuing PID issuance should have resulted in error");
+            .fetch_data::<DocumentMappingData>()
+            .await
+            .unwrap()
+            .expect("Document mappings should have been stored")
+            .mappings;
+        assert_eq!(
+            mappings,
+            HashMap::from([(
+                issuer_certificate,
+                DocumentMappings::from_credential_metadata(&[metadata], &["com.example.foobar"])
+            )])
+        );
 
-        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+        // The emitted document should be rendered using these mappings, with the first attribute being the one
+        // that is described by the metadata.
+        let documents = documents.lock();
+        let (key, attribute) = documents[1][0].attributes.first().unwrap();
+        assert_eq!(key, "given_name");
+        assert_eq!(
+            attribute.key_labels,
+            HashMap::from([("en".to_string(), "Given name".to_string())])
+        );
+    }
+
+    fn mock_deferred_issuance(transaction_id: &str) -> DeferredIssuance {
+        DeferredIssuance::new_mock(
+            transaction_id.to_string(),
+            vec![AttestationPreview::MsoMdoc {
+                unsigned_mdoc: document::create_full_unsigned_pid_mdoc(),
+                issuer: ISSUER_KEY.issuance_key.certificate().clone(),
+            }],
+        )
     }
 
     #[tokio::test]
-    #[serial(MockIssuanceSession)]
-    async fn test_continue_pid_issuance_error_document() {
+    async fn test_accept_issuance_deferred() {
         // Prepare a registered and unlocked wallet.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Set up a mock DigiD session that returns a token request.
-        let digid_session = {
-            let mut session = MockDigidSession::default();
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
 
-            session.expect_into_token_request().return_once(|_uri| {
-                Ok(TokenRequest {
-                    grant_type: TokenRequestGrantType::PreAuthorizedCode {
-                        pre_authorized_code: "123".to_string().into(),
-                    },
-                    code_verifier: None,
-                    client_id: None,
-                    redirect_uri: None,
-                })
+        // Create a mock OpenID4VCI session, started from a credential offer, of which the issuer defers issuance.
+        let session = {
+            let mut client = mock_issuance_session();
+            client.expect_accept().return_once(|| {
+                Ok(AcceptedIssuance::Deferred(Box::new(mock_deferred_issuance(
+                    "transaction_id",
+                ))))
             });
-
-            session
+            client
         };
-        wallet.issuance_session = Some(PidIssuanceSession::Digid(digid_session));
-
-        // Set up the `MockIssuanceSession` to return an `AttestationPreview` with an unknown doctype.
-        let start_context = MockIssuanceSession::start_context();
-        start_context.expect().return_once(|| {
-            let mut unsigned_mdoc = document::create_full_unsigned_pid_mdoc();
-            unsigned_mdoc.doc_type = "foobar".to_string();
-
-            Ok((
-                MockIssuanceSession::new(),
-                vec![AttestationPreview::MsoMdoc {
-                    unsigned_mdoc,
-                    issuer: ISSUER_KEY.issuance_key.certificate().clone(),
-                }],
-            ))
+        wallet.issuance_session = Some(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
         });
 
-        // Continuing PID issuance when receiving an unknown mdoc should result in an error.
-        let error = wallet
-            .continue_pid_issuance(Url::parse(REDIRECT_URI).unwrap())
+        // Accept the issuance with the PIN.
+        wallet
+            .accept_issuance(PIN.to_string())
             .await
-            .expect_err("Continuing PID issuance should have resulted in error");
+            .expect("Could not accept issuance");
 
-        assert_matches!(error, PidIssuanceError::MdocDocument(_));
+        assert!(wallet.issuance_session.is_none());
+
+        // The pending issuance should be stored, and no mdocs.
+        let pending = wallet
+            .storage
+            .get_mut()
+            .fetch_data::<PendingIssuanceData>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.issuances.len(), 1);
+        assert!(wallet.storage.get_mut().mdocs.is_empty());
+
+        // The pending document should be emitted.
+        let documents = documents.lock();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].len(), 1);
+        assert_matches!(
+            &documents[1][0].persistence,
+            DocumentPersistence::Pending(transaction_id) if transaction_id == "transaction_id"
+        );
     }
 
     #[tokio::test]
-    async fn test_cancel_pid_issuance_error_pid_issuer() {
-        // Prepare a registered and unlocked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_check_pending_issuances() {
+        // Prepare a registered and unlocked wallet with three pending issuances.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet
+            .storage
+            .get_mut()
+            .upsert_data(&PendingIssuanceData {
+                issuances: ["issued", "pending", "refused"]
+                    .into_iter()
+                    .map(mock_deferred_issuance)
+                    .collect(),
+            })
+            .await
+            .unwrap();
 
-        // Set up a mock OpenID4VCI session that expects to be rejected, which returns an error.
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client
-                .expect_reject()
-                .return_once(|| Err(IssuanceSessionError::MissingNonce));
-            client
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
+
+        // The issuer issues the first, still has to approve the second and refuses the third.
+        let mdoc = test::create_full_pid_mdoc().await;
+        let accept_deferred_context = MockIssuanceSession::accept_deferred_context();
+        accept_deferred_context
+            .expect()
+            .returning(move |deferred_issuance| match deferred_issuance.transaction_id() {
+                "issued" => Ok(AcceptedIssuance::Issued {
+                    mdocs: vec![vec![mdoc.clone()].into()],
+                    refresh: None,
+                    notification: None,
+                }),
+                "pending" => Ok(AcceptedIssuance::Deferred(Box::new(deferred_issuance))),
+                _ => Err(IssuanceSessionError::CredentialRequest(ErrorResponse {
+                    error: CredentialErrorCode::InvalidTransactionId,
+                    error_description: None,
+                    error_uri: None,
+                })),
+            });
+
+        wallet
+            .check_pending_issuances()
+            .await
+            .expect("Could not check pending issuances");
+
+        // Only the issuance that is still pending should be kept.
+        let pending = wallet
+            .storage
+            .get_mut()
+            .fetch_data::<PendingIssuanceData>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.issuances.len(), 1);
+        assert_eq!(pending.issuances[0].transaction_id(), "pending");
+
+        // The issued mdoc should be stored, and an event should be recorded.
+        assert_eq!(wallet.storage.get_mut().mdocs.len(), 1);
+        assert_eq!(wallet.get_history().await.unwrap().len(), 1);
+
+        // Both the issued and the pending document should be emitted.
+        let documents = documents.lock();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].len(), 3);
+        assert_eq!(documents[1].len(), 2);
+        assert!(documents[1]
+            .iter()
+            .any(|document| matches!(document.persistence, DocumentPersistence::Stored(_))));
+        assert!(documents[1]
+            .iter()
+            .any(|document| matches!(&document.persistence, DocumentPersistence::Pending(id) if id == "pending")));
+    }
+
+    fn mock_issuance_refresh(mdoc: &Mdoc) -> IssuanceRefresh {
+        IssuanceRefresh::new_mock(vec![(
+            *SigningKey::random(&mut OsRng).verifying_key(),
+            mdoc.private_key_id().to_string(),
+        )])
+    }
+
+    /// Store a PID that expires in the specified number of days in the wallet, along with a refresh for it.
+    async fn store_refreshable_pid(wallet: &mut WalletWithMocks, valid_days: i64) -> Mdoc {
+        let unsigned_mdoc = UnsignedMdoc {
+            valid_until: (Utc::now() + Duration::days(valid_days)).into(),
+            ..document::create_full_unsigned_pid_mdoc()
         };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+        let mdoc = test::mdoc_from_unsigned(unsigned_mdoc, &ISSUER_KEY).await;
 
-        // Canceling PID issuance on a wallet should forward this error.
-        let error = wallet
-            .cancel_pid_issuance()
+        let storage = wallet.storage.get_mut();
+        storage.insert_mdocs(vec![vec![mdoc.clone()].into()]).await.unwrap();
+        storage
+            .upsert_data(&IssuanceRefreshData {
+                refreshes: vec![mock_issuance_refresh(&mdoc)],
+            })
             .await
-            .expect_err("Rejecting PID issuance should have resulted in an error");
+            .unwrap();
 
-        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+        mdoc
     }
 
-    const PIN: &str = "051097";
-
     #[tokio::test]
-    async fn test_accept_pid_issuance() {
-        // Prepare a registered and unlocked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_refresh_expiring_mdocs() {
+        // Prepare a registered and unlocked wallet with a PID that expires soon.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdoc = store_refreshable_pid(&mut wallet, MDOC_REFRESH_DAYS - 1).await;
 
         // Register mock document_callback
         let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
 
-        // Register mock recent_history_callback
-        let events = test::setup_mock_recent_history_callback(&mut wallet).await.unwrap();
-
-        // Create a mock OpenID4VCI session that accepts the PID with a single
-        // instance of `MdocCopies`, which contains a single valid `Mdoc`.
-        let mdoc = test::create_full_pid_mdoc().await;
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client.expect_accept().return_once(|| Ok(vec![vec![mdoc].into()]));
-            client
-        };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
+        // The issuer returns a refreshed PID, bound to the same key.
+        let refreshed_mdoc = test::mdoc_from_unsigned_with_key_id(
+            document::create_full_unsigned_pid_mdoc(),
+            &ISSUER_KEY,
+            mdoc.private_key_id(),
+        )
+        .await;
+        let refresh_context = MockIssuanceSession::refresh_context();
+        refresh_context.expect().return_once({
+            let refreshed_mdoc = refreshed_mdoc.clone();
+            move |refresh| Ok((vec![vec![refreshed_mdoc].into()], refresh))
+        });
 
-        // Accept the PID issuance with the PIN.
         wallet
-            .accept_pid_issuance(PIN.to_string())
+            .refresh_expiring_mdocs()
             .await
-            .expect("Could not accept PID issuance");
+            .expect("Could not refresh expiring mdocs");
 
-        {
-            // Test which `Document` instances we have received through the callback.
-            let documents = documents.lock();
+        // The stored PID should be replaced by the refreshed one, and the refresh should be kept.
+        let storage = wallet.storage.get_mut();
+        let stored_mdocs = storage.fetch_unique_mdocs().await.unwrap();
+        assert_eq!(stored_mdocs.len(), 1);
+        assert_eq!(stored_mdocs[0].mdoc, refreshed_mdoc);
 
-            // The first entry should be empty, because there are no mdocs in the database.
-            assert_eq!(documents.len(), 2);
-            assert!(documents[0].is_empty());
+        let refresh_data = storage.fetch_data::<IssuanceRefreshData>().await.unwrap().unwrap();
+        assert_eq!(refresh_data.refreshes.len(), 1);
 
-            // The second entry should contain a single document with the PID.
-            assert_eq!(documents[1].len(), 1);
-            let document = &documents[1][0];
-            assert_matches!(document.persistence, DocumentPersistence::Stored(_));
-            assert_eq!(document.doc_type, "com.example.pid");
+        // The refreshed document should be emitted.
+        let documents = documents.lock();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].len(), 1);
+    }
 
-            // Test that one successful issuance event is logged
-            let events = events.lock();
-            assert_eq!(events.len(), 2);
-            assert!(events[0].is_empty());
-            assert_eq!(events[1].len(), 1);
-            assert_matches!(&events[1][0], HistoryEvent::Issuance { .. });
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_refresh_expiring_mdocs_not_expiring() {
+        // Prepare a registered and unlocked wallet with a PID that does not expire soon.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdoc = store_refreshable_pid(&mut wallet, MDOC_REFRESH_DAYS + 1).await;
 
-            assert!(wallet.has_registration());
-            assert!(!wallet.is_locked());
-        }
+        // The issuer should not be contacted.
+        let refresh_context = MockIssuanceSession::refresh_context();
+        refresh_context.expect().never();
 
-        // Starting another PID issuance should fail
-        const AUTH_URL: &str = "http://example.com/auth";
-        // Set up a mock DigiD session.
-        let session_start_context = MockDigidSession::start_context();
-        session_start_context.expect().returning(|_, _| {
-            let client = MockDigidSession::default();
-            Ok((client, Url::parse(AUTH_URL).unwrap()))
+        wallet
+            .refresh_expiring_mdocs()
+            .await
+            .expect("Could not refresh expiring mdocs");
+
+        let stored_mdocs = wallet.storage.get_mut().fetch_unique_mdocs().await.unwrap();
+        assert_eq!(stored_mdocs.len(), 1);
+        assert_eq!(stored_mdocs[0].mdoc, mdoc);
+    }
+
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_refresh_expiring_mdocs_refused() {
+        // Prepare a registered and unlocked wallet with a PID that expires soon.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdoc = store_refreshable_pid(&mut wallet, MDOC_REFRESH_DAYS - 1).await;
+
+        // The issuer refuses the refresh token.
+        let refresh_context = MockIssuanceSession::refresh_context();
+        refresh_context.expect().return_once(|_| {
+            Err(IssuanceSessionError::TokenRequest(ErrorResponse {
+                error: TokenErrorCode::InvalidGrant,
+                error_description: None,
+                error_uri: None,
+            }))
         });
 
-        let err = wallet
-            .create_pid_issuance_auth_url()
+        wallet
+            .refresh_expiring_mdocs()
             .await
-            .expect_err("creating new PID issuance auth URL when there already is a PID should fail");
-        assert_matches!(err, PidIssuanceError::PidAlreadyPresent);
+            .expect("Could not refresh expiring mdocs");
+
+        // The PID should be left untouched, but the refresh should be removed.
+        let storage = wallet.storage.get_mut();
+        let stored_mdocs = storage.fetch_unique_mdocs().await.unwrap();
+        assert_eq!(stored_mdocs.len(), 1);
+        assert_eq!(stored_mdocs[0].mdoc, mdoc);
+
+        let refresh_data = storage.fetch_data::<IssuanceRefreshData>().await.unwrap().unwrap();
+        assert!(refresh_data.refreshes.is_empty());
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_missing_issuer_registration() {
-        // Prepare a registered and unlocked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_refresh_expiring_mdocs_server_error() {
+        // Prepare a registered and unlocked wallet with a PID that expires soon.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdoc = store_refreshable_pid(&mut wallet, MDOC_REFRESH_DAYS - 1).await;
+
+        // The issuer fails to process the refresh token.
+        let refresh_context = MockIssuanceSession::refresh_context();
+        refresh_context.expect().return_once(|_| {
+            Err(IssuanceSessionError::TokenRequest(ErrorResponse {
+                error: TokenErrorCode::ServerError,
+                error_description: None,
+                error_uri: None,
+            }))
+        });
 
-        // Create a mock OpenID4VCI session that accepts the PID with a single instance of `MdocCopies`, which contains
-        // a single valid `Mdoc`, but signed with a Certificate that is missing IssuerRegistration
-        let mdoc = test::create_full_pid_mdoc_unauthenticated().await;
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client.expect_accept().return_once(|| Ok(vec![vec![mdoc].into()]));
-            client
-        };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
-
-        // Accept the PID issuance with the PIN.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+        wallet
+            .refresh_expiring_mdocs()
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
-
-        assert_matches!(error, PidIssuanceError::MissingIssuerRegistration);
+            .expect("Could not refresh expiring mdocs");
 
-        // No issuance event is logged
-        let events = wallet.storage.read().await.fetch_wallet_events().await.unwrap();
-        assert!(events.is_empty());
+        // Both the PID and the refresh should be kept, so that refreshing can be retried later.
+        let storage = wallet.storage.get_mut();
+        let stored_mdocs = storage.fetch_unique_mdocs().await.unwrap();
+        assert_eq!(stored_mdocs.len(), 1);
+        assert_eq!(stored_mdocs[0].mdoc, mdoc);
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        let refresh_data = storage.fetch_data::<IssuanceRefreshData>().await.unwrap().unwrap();
+        assert_eq!(refresh_data.refreshes.len(), 1);
     }
 
-    #[tokio::test]
-    async fn test_accept_pid_issuance_unregistered() {
-        // Prepare an unregistered wallet.
-        let mut wallet = WalletWithMocks::new_unregistered().await;
+    /// Store a PID in the wallet that has a copy for each of the specified usage counts, along with a refresh for it.
+    async fn store_replenishable_pid(wallet: &mut WalletWithMocks, usage_counts: &[u32]) -> Vec<Mdoc> {
+        let mut mdocs = Vec::new();
+        for _ in usage_counts {
+            mdocs.push(test::create_full_pid_mdoc().await);
+        }
 
-        // Accepting PID issuance on an unregistered wallet should result in an error.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+        let storage = wallet.storage.get_mut();
+        storage.insert_mdocs(vec![mdocs.clone().into()]).await.unwrap();
+        for ((copy, _), usage_count) in storage.fetch_mdoc_copies().await.unwrap().into_iter().zip(usage_counts) {
+            for _ in 0..*usage_count {
+                storage
+                    .increment_mdoc_copies_usage_count(vec![copy.mdoc_copy_id])
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let keys = mdocs
+            .iter()
+            .map(|mdoc| {
+                (
+                    *SigningKey::random(&mut OsRng).verifying_key(),
+                    mdoc.private_key_id().to_string(),
+                )
+            })
+            .collect();
+        storage
+            .upsert_data(&IssuanceRefreshData {
+                refreshes: vec![IssuanceRefresh::new_mock(keys)],
+            })
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
+            .unwrap();
 
-        assert_matches!(error, PidIssuanceError::NotRegistered);
+        mdocs
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_locked() {
-        // Prepare a registered and locked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_replenish_mdoc_copies() {
+        // Prepare a registered and unlocked wallet with a PID of which both copies have been disclosed, so that no
+        // unused copy remains.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdocs = store_replenishable_pid(&mut wallet, &[3, 1]).await;
 
-        wallet.lock();
+        // Register mock document_callback
+        let documents = test::setup_mock_documents_callback(&mut wallet).await.unwrap();
 
-        // Accepting PID issuance on a locked wallet should result in an error.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+        // The issuer returns two new copies of the PID, bound to new keys.
+        let replenished_mdocs = vec![test::create_full_pid_mdoc().await, test::create_full_pid_mdoc().await];
+        let replenish_context = MockIssuanceSession::replenish_context();
+        replenish_context.expect().return_once({
+            let replenished_mdocs = replenished_mdocs.clone();
+            move |refresh| Ok((vec![replenished_mdocs.into()], refresh))
+        });
+
+        let reissuance_doc_types = wallet
+            .replenish_mdoc_copies(PIN.to_string())
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
+            .expect("Could not replenish mdoc copies");
 
-        assert_matches!(error, PidIssuanceError::Locked);
+        assert!(reissuance_doc_types.is_empty());
 
-        assert!(wallet.has_registration());
-        assert!(wallet.is_locked());
+        // The copies of the stored PID should be replaced by the new ones, and the refresh should be kept.
+        let storage = wallet.storage.get_mut();
+        let copies = storage.fetch_mdoc_copies().await.unwrap();
+        assert_eq!(
+            copies.iter().map(|(copy, _)| &copy.mdoc).collect::<Vec<_>>(),
+            replenished_mdocs.iter().collect::<Vec<_>>()
+        );
+        assert!(!copies.iter().any(|(copy, _)| copy.mdoc == mdocs[1]));
+
+        let refresh_data = storage.fetch_data::<IssuanceRefreshData>().await.unwrap().unwrap();
+        assert_eq!(refresh_data.refreshes.len(), 1);
+
+        // The replenished document should be emitted.
+        let documents = documents.lock();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].len(), 1);
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_session_state() {
-        // Prepare a registered and unlocked wallet.
+    #[serial(MockIssuanceSession)]
+    async fn test_replenish_mdoc_copies_not_depleted() {
+        // Prepare a registered and unlocked wallet with a PID of which one copy has been disclosed too often and the
+        // other copy is unused.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        let mdocs = store_replenishable_pid(&mut wallet, &[3, 0]).await;
 
-        // Accepting PID issuance on a `Wallet` with a `PidIssuerClient`
-        // that has no session should result in an error.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+        // The issuer should not be contacted.
+        let replenish_context = MockIssuanceSession::replenish_context();
+        replenish_context.expect().never();
+
+        let reissuance_doc_types = wallet
+            .replenish_mdoc_copies(PIN.to_string())
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
+            .expect("Could not replenish mdoc copies");
 
-        assert_matches!(error, PidIssuanceError::SessionState);
+        assert!(reissuance_doc_types.is_empty());
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        // Only the copy that was disclosed too often should be pruned.
+        let copies = wallet.storage.get_mut().fetch_mdoc_copies().await.unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].0.mdoc, mdocs[1]);
     }
 
-    async fn test_accept_pid_issuance_error_remote_key(
-        key_error: RemoteEcdsaKeyError,
-    ) -> (WalletWithMocks, PidIssuanceError) {
-        // Prepare a registered and unlocked wallet.
+    #[tokio::test]
+    #[serial(MockIssuanceSession)]
+    async fn test_replenish_mdoc_copies_refused() {
+        // Prepare a registered and unlocked wallet with a PID of which both copies have been disclosed too often.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        store_replenishable_pid(&mut wallet, &[3, 4]).await;
+
+        // The issuer refuses the refresh token.
+        let replenish_context = MockIssuanceSession::replenish_context();
+        replenish_context.expect().return_once(|_| {
+            Err(IssuanceSessionError::TokenRequest(ErrorResponse {
+                error: TokenErrorCode::InvalidGrant,
+                error_description: None,
+                error_uri: None,
+            }))
+        });
 
-        // Have the mock OpenID4VCI session return a particular `RemoteEcdsaKeyError` upon accepting.
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client
-                .expect_accept()
-                .return_once(|| Err(IssuanceSessionError::Jwt(JwtError::Signing(Box::new(key_error)))));
-            client
-        };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
-
-        // Accepting PID issuance should result in an error.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+        let reissuance_doc_types = wallet
+            .replenish_mdoc_copies(PIN.to_string())
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
+            .expect("Could not replenish mdoc copies");
 
-        (wallet, error)
+        // The PID should be issued again by the user, as the refresh has been removed.
+        assert_eq!(reissuance_doc_types, vec![PID_DOCTYPE.to_string()]);
+
+        // A single copy of the PID should be kept, even though it has been disclosed too often.
+        let storage = wallet.storage.get_mut();
+        assert_eq!(storage.fetch_mdoc_copies().await.unwrap().len(), 1);
+
+        let refresh_data = storage.fetch_data::<IssuanceRefreshData>().await.unwrap().unwrap();
+        assert!(refresh_data.refreshes.is_empty());
     }
 
     #[rstest]
     #[case(InstructionError::IncorrectPin { attempts_left_in_round: 1, is_final_round: false }, false)]
-    #[case(InstructionError::Timeout { timeout_millis: 10_000 }, true)]
     #[case(InstructionError::Blocked, true)]
-    #[case(InstructionError::InstructionValidation, false)]
     #[tokio::test]
-    async fn test_accept_pid_issuance_error_instruction(
+    #[serial(MockIssuanceSession)]
+    async fn test_replenish_mdoc_copies_error_instruction(
         #[case] instruction_error: InstructionError,
         #[case] expect_reset: bool,
     ) {
-        let (mut wallet, error) =
-            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::from(instruction_error)).await;
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        store_replenishable_pid(&mut wallet, &[1]).await;
+
+        // Generating the new keys fails because of the PIN.
+        let replenish_context = MockIssuanceSession::replenish_context();
+        replenish_context.expect().return_once(|_| {
+            Err(IssuanceSessionError::PrivateKeyGeneration(Box::new(
+                RemoteEcdsaKeyError::from(instruction_error),
+            )))
+        });
+
+        let error = wallet
+            .replenish_mdoc_copies(PIN.to_string())
+            .await
+            .expect_err("Replenishing mdoc copies should have resulted in an error");
 
-        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
         assert_matches!(error, PidIssuanceError::Instruction(_));
 
-        // Test the state of the Wallet, based on if we expect a reset for this InstructionError.
         if expect_reset {
             assert!(!wallet.has_registration());
-            assert!(wallet.is_locked());
             assert_matches!(
                 wallet.storage.get_mut().state().await.unwrap(),
                 StorageState::Uninitialized
             );
         } else {
-            assert!(wallet.has_registration());
-            assert!(!wallet.is_locked());
-            assert_matches!(wallet.storage.get_mut().state().await.unwrap(), StorageState::Opened);
+            // The refresh should be kept, so that replenishing can be retried.
+            let refresh_data = wallet
+                .storage
+                .get_mut()
+                .fetch_data::<IssuanceRefreshData>()
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(refresh_data.refreshes.len(), 1);
         }
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_error_signature() {
-        let (wallet, error) =
-            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::from(signature::Error::default())).await;
+    async fn test_replenish_mdoc_copies_error_locked() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
-        assert_matches!(error, PidIssuanceError::Signature(_));
+        wallet.lock();
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        let error = wallet
+            .replenish_mdoc_copies(PIN.to_string())
+            .await
+            .expect_err("Replenishing mdoc copies should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::Locked);
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_error_key_not_found() {
-        let (wallet, error) =
-            test_accept_pid_issuance_error_remote_key(RemoteEcdsaKeyError::KeyNotFound("not found".to_string())).await;
+    async fn test_check_pending_issuances_error_locked() {
+        // Prepare a registered and locked wallet.
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Test that this error is converted to the appropriate variant of `PidIssuanceError`.
-        assert_matches!(error, PidIssuanceError::KeyNotFound(_));
+        wallet.lock();
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        let error = wallet
+            .check_pending_issuances()
+            .await
+            .expect_err("Checking pending issuances should have resulted in an error");
+
+        assert_matches!(error, PidIssuanceError::Locked);
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_error_pid_issuer() {
-        // Prepare a registered and unlocked wallet.
+    async fn test_accept_issuance_session_state() {
+        // Prepare a registered and unlocked wallet with an active PID issuance session.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(MockIssuanceSession::default()));
 
-        // Have the mock OpenID4VCI session return an error upon accepting.
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client
-                .expect_accept()
-                .return_once(|| Err(IssuanceSessionError::MissingNonce));
-            client
-        };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
-
-        // Accepting PID issuance should result in an error.
+        // Accepting issuance from a credential offer should result in an error.
         let error = wallet
-            .accept_pid_issuance(PIN.to_string())
+            .accept_issuance(PIN.to_string())
             .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
-
-        assert_matches!(error, PidIssuanceError::PidIssuer(_));
+            .expect_err("Accepting issuance should have resulted in an error");
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        assert_matches!(error, PidIssuanceError::SessionState);
     }
 
     #[tokio::test]
-    async fn test_accept_pid_issuance_error_storage() {
-        // Prepare a registered and unlocked wallet.
+    async fn test_cancel_issuance_credential_offer() {
+        // Prepare a registered and unlocked wallet with a session started from a credential offer.
         let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
 
-        // Have the mock OpenID4VCI session report some mdocs upon accepting.
-        let mdoc = test::create_full_pid_mdoc().await;
-        let pid_issuer = {
-            let mut client = MockIssuanceSession::new();
-            client.expect_accept().return_once(|| Ok(vec![vec![mdoc].into()]));
+        let session = {
+            let mut client = mock_issuance_session();
+            client.expect_reject().return_once(|| Ok(()));
             client
         };
-        wallet.issuance_session = Some(PidIssuanceSession::Openid4vci(pid_issuer));
-
-        // Have the mdoc storage return an error on query.
-        wallet.storage.get_mut().has_query_error = true;
-
-        // Accepting PID issuance should result in an error.
-        let error = wallet
-            .accept_pid_issuance(PIN.to_string())
-            .await
-            .expect_err("Accepting PID issuance should have resulted in an error");
+        wallet.issuance_session = Some(PidIssuanceSession::CredentialOffer {
+            session,
+            credential_issuer: "https://issuer.example.com".parse().unwrap(),
+        });
 
-        assert_matches!(error, PidIssuanceError::MdocStorage(_));
+        // Cancelling should reject the issuance and clear the session.
+        wallet.cancel_pid_issuance().await.expect("Could not cancel issuance");
 
-        assert!(wallet.has_registration());
-        assert!(!wallet.is_locked());
+        assert!(wallet.issuance_session.is_none());
     }
 }
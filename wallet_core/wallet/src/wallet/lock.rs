@@ -14,7 +14,7 @@ use crate::{
     storage::{Storage, UnlockData},
 };
 
-use super::Wallet;
+use super::{ChangePinError, Wallet};
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
 pub enum WalletUnlockError {
@@ -33,6 +33,19 @@ pub enum WalletUnlockError {
     #[error("could not write or read unlock method to or from database: {0}")]
     #[category(defer)]
     UnlockMethodStorage(#[source] StorageError),
+    #[error("could not finish PIN change: {0}")]
+    #[category(defer)]
+    ChangePin(#[source] ChangePinError),
+}
+
+impl From<ChangePinError> for WalletUnlockError {
+    fn from(value: ChangePinError) -> Self {
+        match value {
+            ChangePinError::NotRegistered => Self::NotRegistered,
+            ChangePinError::Instruction(error) => Self::Instruction(error),
+            error => Self::ChangePin(error),
+        }
+    }
 }
 
 impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS> {
@@ -145,7 +158,10 @@ impl<CR, S, PEK, APC, DS, IS, MDS> Wallet<CR, S, PEK, APC, DS, IS, MDS> {
             return Err(WalletUnlockError::NotLocked);
         }
 
-        self.send_check_pin_instruction(pin).await?;
+        // If a PIN change was interrupted, finishing it also checks the PIN.
+        if !self.finish_pending_pin_change(pin.clone()).await? {
+            self.send_check_pin_instruction(pin).await?;
+        }
 
         info!("Unlock instruction successful, unlocking wallet");
 
@@ -214,7 +230,11 @@ mod tests {
         utils,
     };
 
-    use crate::{account_provider::AccountProviderResponseError, pin::key::PinKey};
+    use crate::{
+        account_provider::AccountProviderResponseError,
+        pin::key::PinKey,
+        storage::{InstructionData, KeyedData},
+    };
 
     use super::{
         super::test::{WalletWithMocks, ACCOUNT_SERVER_KEYS},
@@ -550,7 +570,11 @@ mod tests {
         wallet.lock();
 
         // Have the database return an error when fetching the sequence number.
-        wallet.storage.get_mut().has_query_error = true;
+        wallet
+            .storage
+            .get_mut()
+            .data_query_error_keys
+            .insert(InstructionData::KEY);
 
         // Unlocking the wallet should now result in an
         // `InstructionError::StoreInstructionSequenceNumber` error.
@@ -564,4 +588,22 @@ mod tests {
             WalletUnlockError::Instruction(InstructionError::StoreInstructionSequenceNumber(_))
         );
     }
+
+    #[tokio::test]
+    async fn test_wallet_unlock_error_change_pin_storage() {
+        let mut wallet = WalletWithMocks::new_registered_and_unlocked().await;
+
+        wallet.lock();
+
+        // Have the database return an error when checking for an interrupted PIN change.
+        wallet.storage.get_mut().has_query_error = true;
+
+        // Unlocking the wallet should now result in a `ChangePinError::Storage` error.
+        let error = wallet
+            .unlock(PIN.to_string())
+            .await
+            .expect_err("Wallet unlocking should have resulted in error");
+
+        assert_matches!(error, WalletUnlockError::ChangePin(ChangePinError::Storage(_)));
+    }
 }
@@ -1,3 +1,5 @@
+mod backup;
+mod change_pin;
 mod config;
 mod disclosure;
 mod documents;
@@ -30,9 +32,11 @@ use crate::{
 };
 
 pub use self::{
+    backup::WalletBackupError,
+    change_pin::ChangePinError,
     config::ConfigCallback,
     disclosure::{DisclosureError, DisclosureProposal},
-    documents::DocumentsCallback,
+    documents::{DocumentDeletionError, DocumentsCallback},
     history::{
         EventConversionError, EventStatus, EventStorageError, HistoryError, HistoryEvent, RecentHistoryCallback,
     },
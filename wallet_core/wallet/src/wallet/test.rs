@@ -118,7 +118,16 @@ pub async fn create_full_pid_mdoc_unauthenticated() -> Mdoc {
 
 /// Generates a valid `Mdoc`, based on an `UnsignedMdoc` and issuer key.
 pub async fn mdoc_from_unsigned(unsigned_mdoc: UnsignedMdoc, issuer_key: &IssuerKey) -> Mdoc {
-    let private_key_id = utils::random_string(16);
+    mdoc_from_unsigned_with_key_id(unsigned_mdoc, issuer_key, &utils::random_string(16)).await
+}
+
+/// Generates a valid `Mdoc` from an `UnsignedMdoc` and issuer key, bound to a new key with the specified identifier.
+pub async fn mdoc_from_unsigned_with_key_id(
+    unsigned_mdoc: UnsignedMdoc,
+    issuer_key: &IssuerKey,
+    private_key_id: &str,
+) -> Mdoc {
+    let private_key_id = private_key_id.to_string();
     let mdoc_public_key = (&SoftwareEcdsaKey::new_random(private_key_id.clone())
         .verifying_key()
         .await
@@ -2,6 +2,7 @@ use tracing::{info, instrument};
 use url::Url;
 
 use error_category::{sentry_capture_error, ErrorCategory};
+use openid4vc::credential_offer::CREDENTIAL_OFFER_URI_SCHEME;
 use wallet_common::urls;
 
 use crate::{
@@ -15,7 +16,9 @@ use super::Wallet;
 #[derive(Debug)]
 pub enum UriType {
     PidIssuance(Url),
+    IssuanceAuthorization(Url),
     Disclosure(Url),
+    CredentialOffer(Url),
 }
 
 #[derive(Debug, thiserror::Error, ErrorCategory)]
@@ -40,12 +43,15 @@ where
 
         let uri = Url::parse(uri_str)?;
 
-        if matches!(self.issuance_session, Some(PidIssuanceSession::Digid(_)))
-            && uri
-                .as_str()
-                .starts_with(urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).as_ref().as_str())
+        if uri
+            .as_str()
+            .starts_with(urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).as_ref().as_str())
         {
-            return Ok(UriType::PidIssuance(uri));
+            match self.issuance_session {
+                Some(PidIssuanceSession::Digid(_)) => return Ok(UriType::PidIssuance(uri)),
+                Some(PidIssuanceSession::Authorization(_)) => return Ok(UriType::IssuanceAuthorization(uri)),
+                _ => {}
+            }
         }
 
         if uri
@@ -55,6 +61,16 @@ where
             return Ok(UriType::Disclosure(uri));
         }
 
+        if uri.scheme() == CREDENTIAL_OFFER_URI_SCHEME
+            || uri.as_str().starts_with(
+                urls::credential_offer_base_uri(&UNIVERSAL_LINK_BASE_URL)
+                    .as_ref()
+                    .as_str(),
+            )
+        {
+            return Ok(UriType::CredentialOffer(uri));
+        }
+
         Err(UriIdentificationError::Unknown)
     }
 }
@@ -63,6 +79,8 @@ where
 mod tests {
     use assert_matches::assert_matches;
 
+    use openid4vc::issuance_session::IssuanceAuthorization;
+
     use crate::{config::UNIVERSAL_LINK_BASE_URL, issuance::MockDigidSession, wallet::PidIssuanceSession};
 
     use super::{super::test::WalletWithMocks, *};
@@ -82,6 +100,11 @@ mod tests {
 
         let disclosure_uri = disclosure_uri_base.join("abcd");
 
+        let credential_offer_uri = urls::credential_offer_base_uri(&UNIVERSAL_LINK_BASE_URL)
+            .join("?credential_offer_uri=https%3A%2F%2Fissuer.example.com%2Foffer");
+        let credential_offer_scheme_uri =
+            "openid-credential-offer://?credential_offer_uri=https%3A%2F%2Fissuer.example.com%2Foffer";
+
         // The example URI should not be recognised.
         assert_matches!(
             wallet.identify_uri(example_uri).unwrap_err(),
@@ -100,7 +123,19 @@ mod tests {
         // The wallet should now recognise the DigiD URI.
         assert_matches!(wallet.identify_uri(digid_uri).unwrap(), UriType::PidIssuance(_));
 
-        // After clearing the `DigidSession`, the URI should not be recognised again.
+        // With an active authorization instead, the URI should be recognised as its redirect URI.
+        wallet.issuance_session = Some(PidIssuanceSession::Authorization(IssuanceAuthorization::new_mock(
+            "https://issuer.example.com".parse().unwrap(),
+            urls::issuance_base_uri(&UNIVERSAL_LINK_BASE_URL).into_inner(),
+            "state".to_string(),
+        )));
+
+        assert_matches!(
+            wallet.identify_uri(digid_uri).unwrap(),
+            UriType::IssuanceAuthorization(_)
+        );
+
+        // After clearing the session, the URI should not be recognised again.
         wallet.issuance_session = None;
 
         assert_matches!(
@@ -113,5 +148,15 @@ mod tests {
             wallet.identify_uri(disclosure_uri.as_str()).unwrap(),
             UriType::Disclosure(_)
         );
+
+        // Credential offer URIs should be recognised, both as universal link and using the generic scheme.
+        assert_matches!(
+            wallet.identify_uri(credential_offer_uri.as_str()).unwrap(),
+            UriType::CredentialOffer(_)
+        );
+        assert_matches!(
+            wallet.identify_uri(credential_offer_scheme_uri).unwrap(),
+            UriType::CredentialOffer(_)
+        );
     }
 }
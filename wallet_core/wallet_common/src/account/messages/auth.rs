@@ -10,6 +10,7 @@ use crate::{
     },
     jwt::{Jwt, JwtSubject},
     keys::{EphemeralEcdsaKey, SecureEcdsaKey},
+    utils::random_string,
 };
 
 // Registration challenge response
@@ -77,6 +78,122 @@ pub struct Certificate {
     pub certificate: WalletCertificate,
 }
 
+// Wallet attestation request and response
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletAttestationRequestClaims {
+    pub iss: String,
+    pub iat: u64,
+}
+
+impl JwtSubject for WalletAttestationRequestClaims {
+    const SUB: &'static str = "wallet_attestation_request";
+}
+
+pub type WalletAttestationRequest = Jwt<WalletAttestationRequestClaims>;
+
+impl WalletAttestationRequest {
+    pub async fn new_signed(issuer: &str, hw_privkey: &impl SecureEcdsaKey) -> Result<Self> {
+        let claims = WalletAttestationRequestClaims {
+            iss: issuer.to_string(),
+            iat: jsonwebtoken::get_current_timestamp(),
+        };
+
+        Ok(Jwt::sign_with_sub(&claims, hw_privkey).await?)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletAttestationRequestMessage {
+    pub message: WalletAttestationRequest,
+    pub certificate: WalletCertificate,
+}
+
+/// Attests that the wallet instance identified by `wallet_id` is registered at the Wallet Provider and has not been
+/// blocked, at least until `exp`. It is bound to the hardware key of the wallet, so that only that wallet can present
+/// it to an issuer along with a [`WalletAttestationPop`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletAttestationClaims {
+    pub wallet_id: String,
+    pub hw_pubkey: DerVerifyingKey,
+
+    pub iss: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl JwtSubject for WalletAttestationClaims {
+    const SUB: &'static str = "wallet_attestation";
+}
+
+pub type WalletAttestation = Jwt<WalletAttestationClaims>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletAttestationResponse {
+    pub attestation: WalletAttestation,
+}
+
+/// Attests that the keys in `attested_keys` were generated by the Wallet Provider for the wallet instance identified
+/// by `wallet_id`, and are held by the Wallet Provider on its behalf. The wallet presents it to an issuer along with
+/// the proofs of possession of these keys, to which the issuer binds the attestations it issues.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyAttestationClaims {
+    pub wallet_id: String,
+    pub attested_keys: Vec<DerVerifyingKey>,
+
+    pub iss: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl JwtSubject for KeyAttestationClaims {
+    const SUB: &'static str = "key_attestation";
+}
+
+pub type KeyAttestation = Jwt<KeyAttestationClaims>;
+
+/// Proof of possession of the hardware key to which a [`WalletAttestation`] is bound, for the audience `aud`. The
+/// `challenge` is provided by the audience, so that the proof can only be used in the session for which the audience
+/// provided it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletAttestationPopClaims {
+    pub iss: String,
+    pub aud: String,
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl JwtSubject for WalletAttestationPopClaims {
+    const SUB: &'static str = "wallet_attestation_pop";
+}
+
+pub type WalletAttestationPop = Jwt<WalletAttestationPopClaims>;
+
+impl WalletAttestationPop {
+    pub async fn new_signed(
+        issuer: &str,
+        audience: &str,
+        challenge: Option<String>,
+        validity_in_sec: u64,
+        hw_privkey: &impl SecureEcdsaKey,
+    ) -> crate::jwt::Result<Self> {
+        let iat = jsonwebtoken::get_current_timestamp();
+        let claims = WalletAttestationPopClaims {
+            iss: issuer.to_string(),
+            aud: audience.to_string(),
+            jti: random_string(32),
+            challenge,
+            iat,
+            exp: iat + validity_in_sec,
+        };
+
+        Jwt::sign_with_sub(&claims, hw_privkey).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::account::signed::SequenceNumberComparison;
@@ -10,7 +10,7 @@ use crate::{
     keys::{EphemeralEcdsaKey, SecureEcdsaKey},
 };
 
-use super::auth::WalletCertificate;
+use super::auth::{KeyAttestation, WalletCertificate};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Instruction<T> {
@@ -21,6 +21,20 @@ pub struct Instruction<T> {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CheckPin;
 
+/// Replaces the PIN public key of the wallet with a new one and returns a new [`WalletCertificate`] for it. The
+/// previous PIN public key is retained until the change is confirmed with [`ChangePinCommit`], which should be signed
+/// with the new PIN. Until then, the change can be undone with [`ChangePinRollback`], signed with the old PIN.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChangePin {
+    pub pin_pubkey: DerVerifyingKey,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChangePinCommit;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChangePinRollback;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GenerateKey {
     pub identifiers: Vec<String>,
@@ -29,6 +43,9 @@ pub struct GenerateKey {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GenerateKeyResult {
     pub public_keys: Vec<(String, DerVerifyingKey)>,
+    /// Attests the generated keys to issuers, see [`KeyAttestation`].
+    #[serde(default)]
+    pub key_attestation: Option<KeyAttestation>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -108,6 +125,24 @@ impl InstructionEndpoint for CheckPin {
     type Result = ();
 }
 
+impl InstructionEndpoint for ChangePin {
+    const ENDPOINT: &'static str = "change_pin";
+
+    type Result = WalletCertificate;
+}
+
+impl InstructionEndpoint for ChangePinCommit {
+    const ENDPOINT: &'static str = "change_pin_commit";
+
+    type Result = ();
+}
+
+impl InstructionEndpoint for ChangePinRollback {
+    const ENDPOINT: &'static str = "change_pin_rollback";
+
+    type Result = ();
+}
+
 impl InstructionEndpoint for GenerateKey {
     const ENDPOINT: &'static str = "generate_key";
 
@@ -20,6 +20,16 @@ pub struct WalletConfiguration {
     pub pid_issuance: PidIssuanceConfiguration,
     pub disclosure: DisclosureConfiguration,
     pub mdoc_trust_anchors: Vec<DerTrustAnchor>,
+    /// Whether the wallet rejects Credential Issuer metadata that is not signed by an issuer certificate chaining to
+    /// one of the `mdoc_trust_anchors`.
+    #[serde(default)]
+    pub require_signed_issuer_metadata: bool,
+    /// Whether the wallet obtains a wallet attestation from the Wallet Provider and sends it to issuers in its token
+    /// and credential requests.
+    #[serde(default)]
+    pub send_wallet_attestation: bool,
+    #[serde(default)]
+    pub mdoc_replenishment: MdocReplenishmentConfiguration,
     pub version: u64,
 }
 
@@ -61,6 +71,25 @@ impl Default for LockTimeoutConfiguration {
     }
 }
 
+/// Determines when the wallet replenishes the copies of its mdocs, of which it uses a different one for each disclosure
+/// so that relying parties cannot link its disclosures.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct MdocReplenishmentConfiguration {
+    /// The number of undisclosed copies of an mdoc below which the wallet obtains new copies
+    pub min_unused_copies: u8,
+    /// The number of disclosures after which a copy is pruned, as long as other copies of the mdoc remain
+    pub max_copy_usage_count: u32,
+}
+
+impl Default for MdocReplenishmentConfiguration {
+    fn default() -> Self {
+        Self {
+            min_unused_copies: 1,
+            max_copy_usage_count: 1,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct AccountServerConfiguration {
     // The base URL for the Account Server API
@@ -0,0 +1,236 @@
+use std::{collections::BTreeMap, error::Error, sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
+use futures::future::{self, BoxFuture};
+use serde::Serialize;
+use tokio::{
+    sync::Mutex,
+    time::{self, Instant},
+};
+use tracing::warn;
+
+/// The maximum duration of a single dependency check, after which the dependency is considered unavailable.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The duration for which a [`ReadinessReport`] is reused by the readiness endpoint, so that requests to that endpoint
+/// do not translate one-to-one into requests to the dependencies.
+const REPORT_CACHE_DURATION: Duration = Duration::from_secs(10);
+
+pub type HealthCheckError = Box<dyn Error + Send + Sync + 'static>;
+
+/// A dependency of a server that is checked by the readiness endpoint.
+pub trait HealthChecker: Send + Sync {
+    /// The name of the dependency, which is used as its key in the [`ReadinessReport`].
+    fn name(&self) -> &'static str;
+
+    fn check(&self) -> BoxFuture<'_, Result<(), HealthCheckError>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Readiness {
+    Ready,
+    NotReady,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckReport {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The response of the readiness endpoint, which contains the outcome of the check of every dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReadinessReport {
+    pub status: Readiness,
+    pub checks: BTreeMap<&'static str, CheckReport>,
+}
+
+impl ReadinessReport {
+    /// Run all `checkers` concurrently, each bounded by [`CHECK_TIMEOUT`].
+    pub async fn collect(checkers: &[Arc<dyn HealthChecker>]) -> Self {
+        let checks: BTreeMap<_, _> = future::join_all(checkers.iter().map(|checker| async move {
+            let result = match time::timeout(CHECK_TIMEOUT, checker.check()).await {
+                Ok(result) => result.map_err(|error| error.to_string()),
+                Err(_) => Err(format!("check timed out after {} seconds", CHECK_TIMEOUT.as_secs())),
+            };
+
+            let report = match result {
+                Ok(()) => CheckReport {
+                    status: CheckStatus::Ok,
+                    error: None,
+                },
+                Err(error) => {
+                    warn!("health check of {} failed: {}", checker.name(), error);
+
+                    CheckReport {
+                        status: CheckStatus::Error,
+                        error: Some(error),
+                    }
+                }
+            };
+
+            (checker.name(), report)
+        }))
+        .await
+        .into_iter()
+        .collect();
+
+        let status = if checks.values().all(|check| check.status == CheckStatus::Ok) {
+            Readiness::Ready
+        } else {
+            Readiness::NotReady
+        };
+
+        Self { status, checks }
+    }
+}
+
+/// Runs the checks of the readiness endpoint and caches the resulting [`ReadinessReport`].
+struct ReadinessChecks {
+    checkers: Vec<Arc<dyn HealthChecker>>,
+    cached_report: Mutex<Option<(Instant, ReadinessReport)>>,
+}
+
+impl ReadinessChecks {
+    fn new(checkers: Vec<Arc<dyn HealthChecker>>) -> Self {
+        Self {
+            checkers,
+            cached_report: Mutex::new(None),
+        }
+    }
+
+    /// Return the cached report if it is recent enough, otherwise run all checks again. The lock is held while the
+    /// checks run, so that concurrent requests share the outcome of a single run.
+    async fn report(&self) -> ReadinessReport {
+        let mut cached_report = self.cached_report.lock().await;
+
+        if let Some((_, report)) = cached_report
+            .as_ref()
+            .filter(|(collected_at, _)| collected_at.elapsed() < REPORT_CACHE_DURATION)
+        {
+            return report.clone();
+        }
+
+        let report = ReadinessReport::collect(&self.checkers).await;
+        cached_report.replace((Instant::now(), report.clone()));
+
+        report
+    }
+}
+
+/// Create a router with the `/health` endpoint, which only signals that the server is running, and the
+/// `/health/ready` endpoint, which responds with `503 Service Unavailable` if any of the `checkers` fails. The outcome
+/// of the checks is cached for [`REPORT_CACHE_DURATION`].
+pub fn health_router(checkers: Vec<Arc<dyn HealthChecker>>) -> Router {
+    Router::new()
+        .route("/health", get(|| async {}))
+        .route("/health/ready", get(readiness))
+        .with_state(Arc::new(ReadinessChecks::new(checkers)))
+}
+
+async fn readiness(State(checks): State<Arc<ReadinessChecks>>) -> (StatusCode, Json<ReadinessReport>) {
+    let report = checks.report().await;
+
+    let status_code = match report.status {
+        Readiness::Ready => StatusCode::OK,
+        Readiness::NotReady => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status_code, Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::FutureExt;
+
+    use super::*;
+
+    struct TestChecker(&'static str, Option<&'static str>);
+
+    impl HealthChecker for TestChecker {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn check(&self) -> BoxFuture<'_, Result<(), HealthCheckError>> {
+            let result = match self.1 {
+                Some(error) => Err(error.into()),
+                None => Ok(()),
+            };
+
+            future::ready(result).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readiness_report() {
+        let checkers: Vec<Arc<dyn HealthChecker>> = vec![Arc::new(TestChecker("database", None))];
+        let report = ReadinessReport::collect(&checkers).await;
+
+        assert_eq!(report.status, Readiness::Ready);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({ "status": "ready", "checks": { "database": { "status": "ok" } } })
+        );
+
+        let checkers: Vec<Arc<dyn HealthChecker>> = vec![
+            Arc::new(TestChecker("database", None)),
+            Arc::new(TestChecker("upstream", Some("connection refused"))),
+        ];
+        let report = ReadinessReport::collect(&checkers).await;
+
+        assert_eq!(report.status, Readiness::NotReady);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "status": "not_ready",
+                "checks": {
+                    "database": { "status": "ok" },
+                    "upstream": { "status": "error", "error": "connection refused" },
+                },
+            })
+        );
+    }
+
+    struct CountingChecker(AtomicUsize);
+
+    impl HealthChecker for CountingChecker {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn check(&self) -> BoxFuture<'_, Result<(), HealthCheckError>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+
+            future::ready(Ok(())).boxed()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_readiness_checks_cached() {
+        let checker = Arc::new(CountingChecker(AtomicUsize::new(0)));
+        let checks = ReadinessChecks::new(vec![Arc::clone(&checker) as Arc<dyn HealthChecker>]);
+
+        // Requesting the report twice in quick succession should only run the checks once.
+        assert_eq!(checks.report().await.status, Readiness::Ready);
+        assert_eq!(checks.report().await.status, Readiness::Ready);
+        assert_eq!(checker.0.load(Ordering::Relaxed), 1);
+
+        // After the cache duration has passed, the checks should be run again.
+        time::advance(REPORT_CACHE_DURATION).await;
+
+        assert_eq!(checks.report().await.status, Readiness::Ready);
+        assert_eq!(checker.0.load(Ordering::Relaxed), 2);
+    }
+}
@@ -1,9 +1,13 @@
 pub mod account;
 pub mod config;
 pub mod generator;
+#[cfg(feature = "health")]
+pub mod health;
 pub mod http_error;
 pub mod jwt;
 pub mod keys;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod nonempty;
 pub mod reqwest;
 #[cfg(feature = "sentry")]
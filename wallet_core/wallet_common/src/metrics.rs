@@ -0,0 +1,69 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::{self, Next},
+    response::Response,
+    routing::get,
+    Router,
+};
+use metrics::histogram;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+/// The buckets used for all histograms, in seconds, which are suitable for request and operation latencies.
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The route label used for requests that did not match any route, which prevents unbounded label cardinality.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Returns the handle to the global Prometheus recorder, which is installed on first use. All metrics recorded
+/// through the `metrics` crate before that are discarded.
+pub fn prometheus_handle() -> &'static PrometheusHandle {
+    PROMETHEUS_HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets(LATENCY_BUCKETS)
+            .expect("histogram buckets should not be empty")
+            .install_recorder()
+            .expect("no other metrics recorder should be installed")
+    })
+}
+
+/// Record the latency of every request to `router`, labeled by route, method and status code.
+pub fn record_metrics(router: Router) -> Router {
+    // Install the recorder before the first request arrives.
+    prometheus_handle();
+
+    router.layer(middleware::from_fn(record_request_metrics))
+}
+
+/// Create a router with the `/metrics` endpoint, which renders all metrics in the Prometheus text format. As these
+/// metrics reveal information about the usage of a server, this router should only be served internally.
+pub fn metrics_router() -> Router {
+    let handle = prometheus_handle();
+
+    Router::new().route("/metrics", get(move || async move { handle.render() }))
+}
+
+async fn record_request_metrics(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let method = request.method().to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    histogram!(
+        "http_request_duration_seconds",
+        "route" => route,
+        "method" => method,
+        "status" => response.status().as_u16().to_string(),
+    )
+    .record(start.elapsed());
+
+    response
+}
@@ -28,6 +28,7 @@ impl BaseUrl {
 pub const DEFAULT_UNIVERSAL_LINK_BASE: &str = "walletdebuginteraction://wallet.edi.rijksoverheid.nl/";
 const ISSUANCE_BASE_PATH: &str = "return-from-digid";
 const DISCLOSURE_BASE_PATH: &str = "disclosure";
+const CREDENTIAL_OFFER_BASE_PATH: &str = "credential-offer";
 
 #[inline]
 pub fn issuance_base_uri(universal_link_base: &BaseUrl) -> BaseUrl {
@@ -39,6 +40,11 @@ pub fn disclosure_base_uri(universal_link_base: &BaseUrl) -> BaseUrl {
     universal_link_base.join_base_url(DISCLOSURE_BASE_PATH)
 }
 
+#[inline]
+pub fn credential_offer_base_uri(universal_link_base: &BaseUrl) -> BaseUrl {
+    universal_link_base.join_base_url(CREDENTIAL_OFFER_BASE_PATH)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -16,6 +16,8 @@ pub struct WalletUser {
     pub wallet_id: WalletId,
     pub hw_pubkey: DerVerifyingKey,
     pub encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+    /// The PIN public key that was replaced by a PIN change that has not been committed or rolled back yet.
+    pub encrypted_previous_pin_pubkey: Option<Encrypted<VerifyingKey>>,
     pub unsuccessful_pin_entries: u8,
     pub last_unsuccessful_pin_entry: Option<DateTime<Local>>,
     pub instruction_challenge: Option<InstructionChallenge>,
@@ -90,6 +92,7 @@ SssTb0eI53lvfdvG/xkNcktwsXEIPL1y3lUKn1u1ZhFTnQn4QKmnvaN4uQ==
                 .unwrap(),
             ),
             encrypted_pin_pubkey: Encrypted::new(random_bytes(32), InitializationVector(random_bytes(32))),
+            encrypted_previous_pin_pubkey: None,
             unsuccessful_pin_entries: 0,
             last_unsuccessful_pin_entry: None,
             instruction_challenge: None,
@@ -1,7 +1,9 @@
 use chrono::{DateTime, Local};
+use p256::ecdsa::VerifyingKey;
 use std::collections::HashMap;
 
 use crate::model::{
+    encrypted::Encrypted,
     wallet_user::{InstructionChallenge, WalletUserCreate, WalletUserKeys, WalletUserQueryResult},
     wrapped_key::WrappedKey,
 };
@@ -50,6 +52,20 @@ pub trait WalletUserRepository {
 
     async fn save_keys(&self, transaction: &Self::TransactionType, keys: WalletUserKeys) -> Result<()>;
 
+    /// Replace the PIN public key of the wallet user, retaining the current one as the previous PIN public key.
+    async fn change_pin(
+        &self,
+        transaction: &Self::TransactionType,
+        wallet_id: &str,
+        new_encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+    ) -> Result<()>;
+
+    /// Discard the previous PIN public key of the wallet user, if any.
+    async fn commit_pin_change(&self, transaction: &Self::TransactionType, wallet_id: &str) -> Result<()>;
+
+    /// Restore the previous PIN public key of the wallet user, if any.
+    async fn rollback_pin_change(&self, transaction: &Self::TransactionType, wallet_id: &str) -> Result<()>;
+
     async fn find_keys_by_identifiers(
         &self,
         transaction: &Self::TransactionType,
@@ -138,6 +154,23 @@ pub mod mock {
             Ok(())
         }
 
+        async fn change_pin(
+            &self,
+            _transaction: &Self::TransactionType,
+            _wallet_id: &str,
+            _new_encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn commit_pin_change(&self, _transaction: &Self::TransactionType, _wallet_id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn rollback_pin_change(&self, _transaction: &Self::TransactionType, _wallet_id: &str) -> Result<()> {
+            Ok(())
+        }
+
         async fn find_keys_by_identifiers(
             &self,
             _transaction: &Self::TransactionType,
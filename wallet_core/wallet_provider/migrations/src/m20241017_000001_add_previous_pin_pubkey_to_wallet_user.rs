@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WalletUser::Table)
+                    .add_column(
+                        ColumnDef::new(WalletUser::EncryptedPreviousPinPubkeySec1)
+                            .binary()
+                            .null(),
+                    )
+                    .add_column(ColumnDef::new(WalletUser::PreviousPinPubkeyIv).binary().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum WalletUser {
+    Table,
+    EncryptedPreviousPinPubkeySec1,
+    PreviousPinPubkeyIv,
+}
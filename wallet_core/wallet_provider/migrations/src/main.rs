@@ -7,6 +7,7 @@ use wallet_provider_database_settings::Settings;
 mod m20230616_000001_create_wallet_user_table;
 mod m20230908_000001_create_wallet_user_key_table;
 mod m20230926_000001_create_wallet_user_challenge_instruction;
+mod m20241017_000001_add_previous_pin_pubkey_to_wallet_user;
 
 pub struct Migrator;
 
@@ -16,6 +17,7 @@ impl MigratorTrait for Migrator {
             Box::new(m20230616_000001_create_wallet_user_table::Migration),
             Box::new(m20230908_000001_create_wallet_user_key_table::Migration),
             Box::new(m20230926_000001_create_wallet_user_challenge_instruction::Migration),
+            Box::new(m20241017_000001_add_previous_pin_pubkey_to_wallet_user::Migration),
         ]
     }
 }
@@ -26,6 +26,11 @@ impl Db {
 
         Ok(Db(db))
     }
+
+    /// Check if the database can be reached.
+    pub async fn ping(&self) -> Result<(), PersistenceError> {
+        self.0.ping().await.map_err(|e| PersistenceError::Connection(e.into()))
+    }
 }
 
 impl PersistenceConnection<DatabaseConnection> for Db {
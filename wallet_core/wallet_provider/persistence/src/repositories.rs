@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
 use chrono::{DateTime, Local};
+use p256::ecdsa::VerifyingKey;
 use uuid::{self, Uuid};
 
 use wallet_provider_domain::{
     model::{
+        encrypted::Encrypted,
         wallet_user::{InstructionChallenge, WalletUserCreate, WalletUserKeys, WalletUserQueryResult},
         wrapped_key::WrappedKey,
     },
@@ -19,6 +21,10 @@ impl Repositories {
     pub fn new(db: Db) -> Self {
         Self(db)
     }
+
+    pub async fn ping(&self) -> Result<(), PersistenceError> {
+        self.0.ping().await
+    }
 }
 
 impl TransactionStarter for Repositories {
@@ -115,17 +121,44 @@ impl WalletUserRepository for Repositories {
     ) -> Result<HashMap<String, WrappedKey>, PersistenceError> {
         wallet_user_key::find_keys_by_identifiers(transaction, wallet_user_id, key_identifiers).await
     }
+
+    async fn change_pin(
+        &self,
+        transaction: &Self::TransactionType,
+        wallet_id: &str,
+        new_encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+    ) -> Result<(), PersistenceError> {
+        wallet_user::change_pin(transaction, wallet_id, new_encrypted_pin_pubkey).await
+    }
+
+    async fn commit_pin_change(
+        &self,
+        transaction: &Self::TransactionType,
+        wallet_id: &str,
+    ) -> Result<(), PersistenceError> {
+        wallet_user::commit_pin_change(transaction, wallet_id).await
+    }
+
+    async fn rollback_pin_change(
+        &self,
+        transaction: &Self::TransactionType,
+        wallet_id: &str,
+    ) -> Result<(), PersistenceError> {
+        wallet_user::rollback_pin_change(transaction, wallet_id).await
+    }
 }
 
 #[cfg(feature = "mock")]
 pub mod mock {
     use chrono::{DateTime, Local};
     use mockall;
+    use p256::ecdsa::VerifyingKey;
     use std::collections::HashMap;
     use uuid::Uuid;
 
     use wallet_provider_domain::{
         model::{
+            encrypted::Encrypted,
             wallet_user::{InstructionChallenge, WalletUserCreate, WalletUserKeys, WalletUserQueryResult},
             wrapped_key::WrappedKey,
         },
@@ -197,6 +230,25 @@ pub mod mock {
                 wallet_user_id: Uuid,
                 key_identifiers: &[String],
             ) -> Result<HashMap<String, WrappedKey>, PersistenceError>;
+
+            async fn change_pin(
+                &self,
+                _transaction: &MockTransaction,
+                wallet_id: &str,
+                new_encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+            ) -> Result<(), PersistenceError>;
+
+            async fn commit_pin_change(
+                &self,
+                _transaction: &MockTransaction,
+                wallet_id: &str,
+            ) -> Result<(), PersistenceError>;
+
+            async fn rollback_pin_change(
+                &self,
+                _transaction: &MockTransaction,
+                wallet_id: &str,
+            ) -> Result<(), PersistenceError>;
         }
 
         impl TransactionStarter for TransactionalWalletUserRepository {
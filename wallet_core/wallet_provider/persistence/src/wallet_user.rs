@@ -38,6 +38,8 @@ where
         hw_pubkey_der: Set(user.hw_pubkey.to_public_key_der()?.to_vec()),
         encrypted_pin_pubkey_sec1: Set(user.encrypted_pin_pubkey.data),
         pin_pubkey_iv: Set(user.encrypted_pin_pubkey.iv.0),
+        encrypted_previous_pin_pubkey_sec1: Set(None),
+        previous_pin_pubkey_iv: Set(None),
         instruction_sequence_number: Set(0),
         pin_entries: Set(0),
         last_unsuccessful_pin: Set(None),
@@ -73,6 +75,10 @@ where
                         wallet_user.encrypted_pin_pubkey_sec1,
                         InitializationVector(wallet_user.pin_pubkey_iv),
                     ),
+                    encrypted_previous_pin_pubkey: wallet_user
+                        .encrypted_previous_pin_pubkey_sec1
+                        .zip(wallet_user.previous_pin_pubkey_iv)
+                        .map(|(data, iv)| Encrypted::new(data, InitializationVector(iv))),
                     hw_pubkey: DerVerifyingKey(VerifyingKey::from_public_key_der(&wallet_user.hw_pubkey_der).unwrap()),
                     unsuccessful_pin_entries: wallet_user.pin_entries.try_into().ok().unwrap_or(u8::MAX),
                     last_unsuccessful_pin_entry: wallet_user.last_unsuccessful_pin.map(DateTime::<Local>::from),
@@ -217,6 +223,90 @@ where
     update_pin_entries(db, wallet_id, Expr::value(0), datetime, false).await
 }
 
+pub async fn change_pin<S, T>(db: &T, wallet_id: &str, new_encrypted_pin_pubkey: Encrypted<VerifyingKey>) -> Result<()>
+where
+    S: ConnectionTrait,
+    T: PersistenceConnection<S>,
+{
+    // The right-hand side of each assignment is evaluated against the row before the update.
+    update_fields(
+        db,
+        wallet_id,
+        vec![
+            (
+                wallet_user::Column::EncryptedPreviousPinPubkeySec1,
+                Expr::col(wallet_user::Column::EncryptedPinPubkeySec1).into(),
+            ),
+            (
+                wallet_user::Column::PreviousPinPubkeyIv,
+                Expr::col(wallet_user::Column::PinPubkeyIv).into(),
+            ),
+            (
+                wallet_user::Column::EncryptedPinPubkeySec1,
+                Expr::value(new_encrypted_pin_pubkey.data),
+            ),
+            (
+                wallet_user::Column::PinPubkeyIv,
+                Expr::value(new_encrypted_pin_pubkey.iv.0),
+            ),
+        ],
+    )
+    .await
+}
+
+pub async fn commit_pin_change<S, T>(db: &T, wallet_id: &str) -> Result<()>
+where
+    S: ConnectionTrait,
+    T: PersistenceConnection<S>,
+{
+    update_fields(
+        db,
+        wallet_id,
+        vec![
+            (
+                wallet_user::Column::EncryptedPreviousPinPubkeySec1,
+                Expr::value(Option::<Vec<u8>>::None),
+            ),
+            (
+                wallet_user::Column::PreviousPinPubkeyIv,
+                Expr::value(Option::<Vec<u8>>::None),
+            ),
+        ],
+    )
+    .await
+}
+
+pub async fn rollback_pin_change<S, T>(db: &T, wallet_id: &str) -> Result<()>
+where
+    S: ConnectionTrait,
+    T: PersistenceConnection<S>,
+{
+    wallet_user::Entity::update_many()
+        .col_expr(
+            wallet_user::Column::EncryptedPinPubkeySec1,
+            Expr::col(wallet_user::Column::EncryptedPreviousPinPubkeySec1).into(),
+        )
+        .col_expr(
+            wallet_user::Column::PinPubkeyIv,
+            Expr::col(wallet_user::Column::PreviousPinPubkeyIv).into(),
+        )
+        .col_expr(
+            wallet_user::Column::EncryptedPreviousPinPubkeySec1,
+            Expr::value(Option::<Vec<u8>>::None),
+        )
+        .col_expr(
+            wallet_user::Column::PreviousPinPubkeyIv,
+            Expr::value(Option::<Vec<u8>>::None),
+        )
+        .filter(wallet_user::Column::WalletId.eq(wallet_id))
+        .filter(wallet_user::Column::EncryptedPreviousPinPubkeySec1.is_not_null())
+        .filter(wallet_user::Column::PreviousPinPubkeyIv.is_not_null())
+        .exec(db.connection())
+        .await
+        .map(|_| ())
+        .map_err(|e| PersistenceError::Execution(e.into()))
+}
+
 async fn update_fields<S, T, C>(db: &T, wallet_id: &str, col_values: Vec<(C, SimpleExpr)>) -> Result<()>
 where
     S: ConnectionTrait,
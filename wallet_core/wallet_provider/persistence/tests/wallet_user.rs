@@ -1,10 +1,20 @@
 use uuid::Uuid;
 
-use wallet_common::{generator::Generator, utils::random_string};
-use wallet_provider_domain::{repository::Committable, EpochGenerator};
+use wallet_common::{
+    generator::Generator,
+    utils::{random_bytes, random_string},
+};
+use wallet_provider_domain::{
+    model::encrypted::{Encrypted, InitializationVector},
+    repository::Committable,
+    EpochGenerator,
+};
 use wallet_provider_persistence::{
     transaction,
-    wallet_user::{clear_instruction_challenge, register_unsuccessful_pin_entry},
+    wallet_user::{
+        change_pin, clear_instruction_challenge, commit_pin_change, register_unsuccessful_pin_entry,
+        rollback_pin_change,
+    },
 };
 
 pub mod common;
@@ -161,3 +171,76 @@ async fn test_register_unsuccessful_pin_entry() {
     assert_eq!(before.pin_entries + 1, after.pin_entries);
     assert_eq!(EpochGenerator.generate(), after.last_unsuccessful_pin.unwrap());
 }
+
+#[tokio::test]
+async fn test_change_pin_commit() {
+    let db = common::db_from_env().await.expect("Could not connect to database");
+
+    let wallet_user_id = Uuid::new_v4();
+    let wallet_id = random_string(32);
+
+    common::create_wallet_user_with_random_keys(&db, wallet_user_id, wallet_id.clone()).await;
+
+    let before = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+    assert!(before.encrypted_previous_pin_pubkey_sec1.is_none());
+
+    let new_encrypted_pin_pubkey = Encrypted::new(random_bytes(32), InitializationVector(random_bytes(32)));
+    change_pin(&db, &wallet_id, new_encrypted_pin_pubkey.clone())
+        .await
+        .expect("Could not change pin");
+
+    let changed = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+
+    assert_eq!(changed.encrypted_pin_pubkey_sec1, new_encrypted_pin_pubkey.data);
+    assert_eq!(changed.pin_pubkey_iv, new_encrypted_pin_pubkey.iv.0);
+    assert_eq!(
+        changed.encrypted_previous_pin_pubkey_sec1,
+        Some(before.encrypted_pin_pubkey_sec1)
+    );
+    assert_eq!(changed.previous_pin_pubkey_iv, Some(before.pin_pubkey_iv));
+
+    commit_pin_change(&db, &wallet_id)
+        .await
+        .expect("Could not commit pin change");
+
+    let committed = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+
+    assert_eq!(committed.encrypted_pin_pubkey_sec1, new_encrypted_pin_pubkey.data);
+    assert!(committed.encrypted_previous_pin_pubkey_sec1.is_none());
+    assert!(committed.previous_pin_pubkey_iv.is_none());
+}
+
+#[tokio::test]
+async fn test_change_pin_rollback() {
+    let db = common::db_from_env().await.expect("Could not connect to database");
+
+    let wallet_user_id = Uuid::new_v4();
+    let wallet_id = random_string(32);
+
+    common::create_wallet_user_with_random_keys(&db, wallet_user_id, wallet_id.clone()).await;
+
+    let before = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+
+    // Rolling back without a pin change in progress should leave the wallet user untouched.
+    rollback_pin_change(&db, &wallet_id)
+        .await
+        .expect("Could not roll back pin change");
+
+    let unchanged = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+    assert_eq!(unchanged, before);
+
+    change_pin(
+        &db,
+        &wallet_id,
+        Encrypted::new(random_bytes(32), InitializationVector(random_bytes(32))),
+    )
+    .await
+    .expect("Could not change pin");
+
+    rollback_pin_change(&db, &wallet_id)
+        .await
+        .expect("Could not roll back pin change");
+
+    let rolled_back = common::find_wallet_user(&db, wallet_user_id).await.unwrap();
+    assert_eq!(rolled_back, before);
+}
@@ -1,6 +1,7 @@
-use std::{sync::Arc, time::Duration};
+use std::{future::Future, iter, sync::Arc, time::Duration};
 
 use chrono::{DateTime, Local};
+use metrics::counter;
 use p256::{ecdsa::VerifyingKey, pkcs8::EncodePublicKey};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::{base64::Base64, serde_as};
@@ -11,10 +12,14 @@ use wallet_common::{
     account::{
         errors::Error as AccountError,
         messages::{
-            auth::{Registration, WalletCertificate, WalletCertificateClaims},
+            auth::{
+                KeyAttestationClaims, Registration, WalletAttestation, WalletAttestationClaims,
+                WalletAttestationRequestMessage, WalletCertificate, WalletCertificateClaims,
+            },
             errors::{IncorrectPinData, PinTimeoutData},
             instructions::{
-                Instruction, InstructionChallengeRequestMessage, InstructionResult, InstructionResultClaims,
+                ChangePin, ChangePinRollback, GenerateKey, GenerateKeyResult, Instruction,
+                InstructionChallengeRequestMessage, InstructionResult, InstructionResultClaims,
             },
         },
         signed::{ChallengeResponsePayload, SequenceNumberComparison, SignedDouble},
@@ -25,6 +30,7 @@ use wallet_common::{
 };
 use wallet_provider_domain::{
     model::{
+        encrypted::Encrypted,
         encrypter::{Decrypter, Encrypter},
         hsm::{Hsm, WalletUserHsm},
         pin_policy::{PinPolicyEvaluation, PinPolicyEvaluator},
@@ -74,6 +80,8 @@ pub enum WalletCertificateError {
     PinPubKeyMismatch,
     #[error("validation failed: {0}")]
     Validation(#[from] JwtError),
+    #[error("wallet certificate signing error: {0}")]
+    Signing(#[source] JwtError),
     #[error("no registered wallet user found")]
     UserNotRegistered,
     #[error("registered wallet user blocked")]
@@ -96,8 +104,6 @@ pub enum RegistrationError {
     MessageValidation(#[source] wallet_common::account::errors::Error),
     #[error("incorrect registration serial number (expected: {expected:?}, received: {received:?})")]
     SerialNumberMismatch { expected: u64, received: u64 },
-    #[error("registration JWT signing error: {0}")]
-    JwtSigning(#[source] JwtError),
     #[error("could not store certificate: {0}")]
     CertificateStorage(#[from] PersistenceError),
     #[error("registration PIN public key DER encoding error: {0}")]
@@ -120,6 +126,8 @@ pub enum InstructionError {
     PinTimeout(PinTimeoutData),
     #[error("account is blocked")]
     AccountBlocked,
+    #[error("a PIN change is already in progress")]
+    PinChangeInProgress,
     #[error("instruction result signing error: {0}")]
     Signing(#[source] JwtError),
     #[error("persistence error: {0}")]
@@ -128,6 +136,16 @@ pub enum InstructionError {
     HsmError(#[from] HsmError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum WalletAttestationError {
+    #[error("wallet certificate validation error: {0}")]
+    WalletCertificate(#[from] WalletCertificateError),
+    #[error("wallet attestation request validation error: {0}")]
+    Validation(#[source] JwtError),
+    #[error("wallet attestation signing error: {0}")]
+    Signing(#[source] JwtError),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum InstructionValidationError {
     #[error("instruction sequence number mismatch")]
@@ -138,8 +156,6 @@ pub enum InstructionValidationError {
     ChallengeTimeout,
     #[error("instruction verification failed: {0}")]
     VerificationFailed(#[source] AccountError),
-    #[error("hsm error: {0}")]
-    HsmError(#[from] HsmError),
 }
 
 impl From<PinPolicyEvaluation> for InstructionError {
@@ -163,8 +179,38 @@ impl From<PinPolicyEvaluation> for InstructionError {
     }
 }
 
+/// Determines which of the PIN public keys of a [`WalletUser`] a wallet certificate, and the instruction sent along
+/// with it, are checked against. While a PIN change is in progress, the wallet may still hold the wallet certificate
+/// for its previous PIN.
+#[derive(Debug, Clone, Copy)]
+enum PinPubkeySelection {
+    /// Only the current PIN public key.
+    Current,
+    /// The previous PIN public key if a PIN change is in progress, the current one otherwise.
+    PreviousIfChanging,
+    /// The current PIN public key or, if a PIN change is in progress, the previous one.
+    Any,
+}
+
+impl PinPubkeySelection {
+    fn encrypted_pin_pubkeys(self, wallet_user: &WalletUser) -> Vec<&Encrypted<VerifyingKey>> {
+        let current = &wallet_user.encrypted_pin_pubkey;
+        let previous = wallet_user.encrypted_previous_pin_pubkey.as_ref();
+
+        match self {
+            Self::Current => vec![current],
+            Self::PreviousIfChanging => vec![previous.unwrap_or(current)],
+            Self::Any => iter::once(current).chain(previous).collect(),
+        }
+    }
+}
+
 const WALLET_CERTIFICATE_VERSION: u32 = 0;
 
+/// Wallet and key attestations are short-lived, so that issuers stop accepting them soon after the wallet has been
+/// blocked.
+const WALLET_ATTESTATION_VALIDITY: Duration = Duration::from_secs(60 * 60);
+
 /// Used as the challenge in the challenge-response protocol during wallet registration.
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug)]
@@ -244,8 +290,15 @@ impl AccountServer {
     {
         debug!("Verifying certificate and retrieving wallet user");
 
-        let user = self
-            .verify_wallet_certificate(&challenge_request.certificate, repositories, hsm)
+        // During a PIN change, the wallet needs a challenge for either committing (using the certificate for its new
+        // PIN) or rolling back (using the certificate for its previous PIN) the change.
+        let (user, _) = self
+            .verify_wallet_certificate(
+                &challenge_request.certificate,
+                PinPubkeySelection::Any,
+                repositories,
+                hsm,
+            )
             .await?;
 
         debug!("Parsing and verifying challenge request for user {}", user.id);
@@ -288,6 +341,55 @@ impl AccountServer {
         Ok(challenge.bytes)
     }
 
+    /// Issue a [`WalletAttestation`] to a registered wallet that is not blocked, which is bound to its hardware key.
+    /// The attestation is signed with the certificate signing key, so issuers can verify it using the public key
+    /// with which the wallet itself verifies its certificate.
+    pub async fn wallet_attestation<T, R, H>(
+        &self,
+        attestation_request: WalletAttestationRequestMessage,
+        certificate_signing_key: &impl CertificateSigningKey,
+        repositories: &R,
+        hsm: &H,
+    ) -> Result<WalletAttestation, WalletAttestationError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+        H: Decrypter<VerifyingKey, Error = HsmError> + Hsm<Error = HsmError>,
+    {
+        debug!("Verifying certificate and retrieving wallet user");
+
+        let (user, _) = self
+            .verify_wallet_certificate(
+                &attestation_request.certificate,
+                PinPubkeySelection::Current,
+                repositories,
+                hsm,
+            )
+            .await?;
+
+        debug!("Parsing and verifying wallet attestation request for user {}", user.id);
+
+        attestation_request
+            .message
+            .parse_and_verify_with_sub(&(&user.hw_pubkey.0).into())
+            .map_err(WalletAttestationError::Validation)?;
+
+        debug!("Signing wallet attestation");
+
+        let iat = jsonwebtoken::get_current_timestamp();
+        let claims = WalletAttestationClaims {
+            wallet_id: user.wallet_id,
+            hw_pubkey: user.hw_pubkey,
+            iss: self.name.clone(),
+            iat,
+            exp: iat + WALLET_ATTESTATION_VALIDITY.as_secs(),
+        };
+
+        Jwt::sign_with_sub(&claims, certificate_signing_key)
+            .await
+            .map_err(WalletAttestationError::Signing)
+    }
+
     pub async fn handle_instruction<T, R, I, IR, G, H>(
         &self,
         instruction: Instruction<I>,
@@ -304,11 +406,209 @@ impl AccountServer {
         IR: Serialize + DeserializeOwned,
         G: Generator<Uuid> + Generator<DateTime<Local>>,
         H: WalletUserHsm<Error = HsmError> + Hsm<Error = HsmError> + Decrypter<VerifyingKey, Error = HsmError>,
+    {
+        self.verify_and_handle_instruction(
+            instruction,
+            PinPubkeySelection::Current,
+            instruction_result_signing_key,
+            generators,
+            repositories,
+            pin_policy,
+            wallet_user_hsm,
+            |instruction, wallet_user| async move {
+                instruction
+                    .handle(&wallet_user, generators, repositories, wallet_user_hsm)
+                    .await
+            },
+        )
+        .await
+    }
+
+    /// Handle the [`GenerateKey`] instruction. Along with the public keys of the generated keys, the result contains a
+    /// [`KeyAttestation`] for them, signed with the certificate signing key like a [`WalletAttestation`]. This allows
+    /// issuers to check that the keys to which they bind their attestations are held by the Wallet Provider.
+    pub async fn handle_generate_key_instruction<T, R, G, H>(
+        &self,
+        instruction: Instruction<GenerateKey>,
+        signing_keys: (&impl InstructionResultSigningKey, &impl CertificateSigningKey),
+        generators: &G,
+        repositories: &R,
+        pin_policy: &impl PinPolicyEvaluator,
+        wallet_user_hsm: &H,
+    ) -> Result<InstructionResult<GenerateKeyResult>, InstructionError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+        G: Generator<Uuid> + Generator<DateTime<Local>>,
+        H: WalletUserHsm<Error = HsmError> + Hsm<Error = HsmError> + Decrypter<VerifyingKey, Error = HsmError>,
+    {
+        let (instruction_result_signing_key, certificate_signing_key) = signing_keys;
+
+        self.verify_and_handle_instruction(
+            instruction,
+            PinPubkeySelection::Current,
+            instruction_result_signing_key,
+            generators,
+            repositories,
+            pin_policy,
+            wallet_user_hsm,
+            |instruction, wallet_user| async move {
+                let result = instruction
+                    .handle(&wallet_user, generators, repositories, wallet_user_hsm)
+                    .await?;
+
+                debug!("Signing key attestation for user {}", wallet_user.id);
+
+                let iat = jsonwebtoken::get_current_timestamp();
+                let claims = KeyAttestationClaims {
+                    wallet_id: wallet_user.wallet_id,
+                    attested_keys: result.public_keys.iter().map(|(_, key)| key.clone()).collect(),
+                    iss: self.name.clone(),
+                    iat,
+                    exp: iat + WALLET_ATTESTATION_VALIDITY.as_secs(),
+                };
+                let key_attestation = Jwt::sign_with_sub(&claims, certificate_signing_key)
+                    .await
+                    .map_err(InstructionError::Signing)?;
+
+                Ok(GenerateKeyResult {
+                    key_attestation: Some(key_attestation),
+                    ..result
+                })
+            },
+        )
+        .await
+    }
+
+    /// Handle the [`ChangePin`] instruction, which is signed with the current PIN of the wallet. The new PIN public
+    /// key replaces the current one, which is retained until the change is either committed or rolled back. The
+    /// result is a new [`WalletCertificate`] for the new PIN public key.
+    pub async fn handle_change_pin_instruction<T, R, G, H>(
+        &self,
+        instruction: Instruction<ChangePin>,
+        signing_keys: (&impl InstructionResultSigningKey, &impl CertificateSigningKey),
+        generators: &G,
+        repositories: &R,
+        pin_policy: &impl PinPolicyEvaluator,
+        hsm: &H,
+    ) -> Result<InstructionResult<WalletCertificate>, InstructionError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+        G: Generator<Uuid> + Generator<DateTime<Local>>,
+        H: WalletUserHsm<Error = HsmError>
+            + Hsm<Error = HsmError>
+            + Decrypter<VerifyingKey, Error = HsmError>
+            + Encrypter<VerifyingKey, Error = HsmError>,
+    {
+        let (instruction_result_signing_key, certificate_signing_key) = signing_keys;
+
+        self.verify_and_handle_instruction(
+            instruction,
+            PinPubkeySelection::Current,
+            instruction_result_signing_key,
+            generators,
+            repositories,
+            pin_policy,
+            hsm,
+            |instruction, wallet_user| async move {
+                if wallet_user.encrypted_previous_pin_pubkey.is_some() {
+                    return Err(InstructionError::PinChangeInProgress);
+                }
+
+                debug!("Encrypting new pin public key and generating new wallet certificate");
+
+                let pin_pubkey = instruction.pin_pubkey.0;
+                let encrypted_pin_pubkey = Encrypter::encrypt(hsm, &self.encryption_key_identifier, pin_pubkey).await?;
+                let certificate = self
+                    .new_wallet_certificate(
+                        certificate_signing_key,
+                        wallet_user.wallet_id.clone(),
+                        wallet_user.hw_pubkey.0,
+                        pin_pubkey,
+                        hsm,
+                    )
+                    .await?;
+
+                debug!("Storing new pin public key for user {}", wallet_user.id);
+
+                let tx = repositories.begin_transaction().await?;
+                repositories
+                    .change_pin(&tx, &wallet_user.wallet_id, encrypted_pin_pubkey)
+                    .await?;
+                tx.commit().await?;
+
+                Ok(certificate)
+            },
+        )
+        .await
+    }
+
+    /// Handle the [`ChangePinRollback`] instruction, which is signed with the PIN the wallet had before an
+    /// uncommitted [`ChangePin`] instruction, or with its current PIN if no PIN change is in progress. In the latter
+    /// case, this instruction has no effect.
+    pub async fn handle_change_pin_rollback_instruction<T, R, G, H>(
+        &self,
+        instruction: Instruction<ChangePinRollback>,
+        instruction_result_signing_key: &impl InstructionResultSigningKey,
+        generators: &G,
+        repositories: &R,
+        pin_policy: &impl PinPolicyEvaluator,
+        hsm: &H,
+    ) -> Result<InstructionResult<()>, InstructionError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+        G: Generator<Uuid> + Generator<DateTime<Local>>,
+        H: Hsm<Error = HsmError> + Decrypter<VerifyingKey, Error = HsmError>,
+    {
+        self.verify_and_handle_instruction(
+            instruction,
+            PinPubkeySelection::PreviousIfChanging,
+            instruction_result_signing_key,
+            generators,
+            repositories,
+            pin_policy,
+            hsm,
+            |_, wallet_user| async move {
+                debug!("Rolling back pin change for user {}", wallet_user.id);
+
+                let tx = repositories.begin_transaction().await?;
+                repositories.rollback_pin_change(&tx, &wallet_user.wallet_id).await?;
+                tx.commit().await?;
+
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn verify_and_handle_instruction<T, R, I, IR, G, H, F, Fut>(
+        &self,
+        instruction: Instruction<I>,
+        pin_pubkey_selection: PinPubkeySelection,
+        instruction_result_signing_key: &impl InstructionResultSigningKey,
+        generators: &G,
+        repositories: &R,
+        pin_policy: &impl PinPolicyEvaluator,
+        hsm: &H,
+        handle: F,
+    ) -> Result<InstructionResult<IR>, InstructionError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+        I: Serialize + DeserializeOwned,
+        IR: Serialize + DeserializeOwned,
+        G: Generator<Uuid> + Generator<DateTime<Local>>,
+        H: Hsm<Error = HsmError> + Decrypter<VerifyingKey, Error = HsmError>,
+        F: FnOnce(I, WalletUser) -> Fut,
+        Fut: Future<Output = Result<IR, InstructionError>>,
     {
         debug!("Verifying certificate and retrieving wallet user");
 
-        let wallet_user = self
-            .verify_wallet_certificate(&instruction.certificate, repositories, wallet_user_hsm)
+        let (wallet_user, pin_pubkey) = self
+            .verify_wallet_certificate(&instruction.certificate, pin_pubkey_selection, repositories, hsm)
             .await?;
 
         debug!(
@@ -335,16 +635,14 @@ impl AccountServer {
         // An evaluation result of blocked permanently can only occur once. This fact is stored in the database
         // for the wallet_user. Subsequent calls will verify if the user is blocked against the database.
         if matches!(pin_eval, PinPolicyEvaluation::InTimeout { timeout: _ }) {
+            counter!("pin_timeout_rejections_total").increment(1);
             tx.commit().await?;
             return Err(pin_eval.into());
         }
 
         debug!("Verifying instruction");
 
-        match self
-            .verify_instruction(instruction, &wallet_user, generators, wallet_user_hsm)
-            .await
-        {
+        match self.verify_instruction(instruction, &wallet_user, &pin_pubkey, generators) {
             Ok(payload) => {
                 debug!("Instruction successfully verified, resetting pin retries");
 
@@ -360,10 +658,7 @@ impl AccountServer {
 
                 tx.commit().await?;
 
-                let instruction_result = payload
-                    .payload
-                    .handle(&wallet_user, generators, repositories, wallet_user_hsm)
-                    .await?;
+                let instruction_result = handle(payload.payload, wallet_user).await?;
                 self.sign_instruction_result(instruction_result_signing_key, instruction_result)
                     .await
             }
@@ -371,14 +666,15 @@ impl AccountServer {
                 let error = if matches!(validation_error, InstructionValidationError::VerificationFailed(_)) {
                     debug!("Instruction validation failed, registering unsuccessful pin entry");
 
+                    let is_blocked = matches!(pin_eval, PinPolicyEvaluation::BlockedPermanently);
                     repositories
-                        .register_unsuccessful_pin_entry(
-                            &tx,
-                            &wallet_user.wallet_id,
-                            matches!(pin_eval, PinPolicyEvaluation::BlockedPermanently),
-                            generators.generate(),
-                        )
+                        .register_unsuccessful_pin_entry(&tx, &wallet_user.wallet_id, is_blocked, generators.generate())
                         .await?;
+
+                    counter!("pin_failures_total").increment(1);
+                    if is_blocked {
+                        counter!("pin_blocks_total").increment(1);
+                    }
                     Err(pin_eval.into())
                 } else {
                     Err(validation_error)?
@@ -464,7 +760,7 @@ impl AccountServer {
         wallet_hw_pubkey: VerifyingKey,
         wallet_pin_pubkey: VerifyingKey,
         hsm: &H,
-    ) -> Result<WalletCertificate, RegistrationError>
+    ) -> Result<WalletCertificate, WalletCertificateError>
     where
         H: Hsm<Error = HsmError>,
     {
@@ -487,7 +783,7 @@ impl AccountServer {
 
         Jwt::sign_with_sub(&cert, certificate_signing_key)
             .await
-            .map_err(RegistrationError::JwtSigning)
+            .map_err(WalletCertificateError::Signing)
     }
 
     fn verify_registration_challenge(
@@ -504,12 +800,15 @@ impl AccountServer {
         .map_err(RegistrationError::ChallengeValidation)
     }
 
+    /// Verify the wallet certificate and return the wallet user it belongs to, along with the PIN public key that
+    /// matches the certificate.
     async fn verify_wallet_certificate<T, R, H>(
         &self,
         certificate: &WalletCertificate,
+        pin_pubkey_selection: PinPubkeySelection,
         wallet_user_repository: &R,
         hsm: &H,
-    ) -> Result<WalletUser, WalletCertificateError>
+    ) -> Result<(WalletUser, VerifyingKey), WalletCertificateError>
     where
         T: Committable,
         R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
@@ -544,40 +843,45 @@ impl AccountServer {
 
                 let user = *user_boxed;
 
-                let pin_pubkey =
-                    Decrypter::decrypt(hsm, &self.encryption_key_identifier, user.encrypted_pin_pubkey.clone()).await?;
+                let mut matching_pin_pubkey = None;
+                for encrypted_pin_pubkey in pin_pubkey_selection.encrypted_pin_pubkeys(&user) {
+                    let pin_pubkey =
+                        Decrypter::decrypt(hsm, &self.encryption_key_identifier, encrypted_pin_pubkey.clone()).await?;
 
-                let pin_hash_verification = verify_pin_pubkey(
-                    pin_pubkey,
-                    cert_data.pin_pubkey_hash,
-                    &self.pin_public_disclosure_protection_key_identifier,
-                    hsm,
-                )
-                .await;
+                    let pin_hash_verification = verify_pin_pubkey(
+                        pin_pubkey,
+                        cert_data.pin_pubkey_hash.clone(),
+                        &self.pin_public_disclosure_protection_key_identifier,
+                        hsm,
+                    )
+                    .await;
+
+                    if pin_hash_verification.is_ok() {
+                        matching_pin_pubkey = Some(pin_pubkey);
+                        break;
+                    }
+                }
 
                 debug!("Verifying user matches the provided certificate");
 
-                if pin_hash_verification.is_err() {
-                    Err(WalletCertificateError::PinPubKeyMismatch)
-                } else if user.hw_pubkey != cert_data.hw_pubkey {
-                    Err(WalletCertificateError::HwPubKeyMismatch)
-                } else {
-                    Ok(user)
+                match matching_pin_pubkey {
+                    None => Err(WalletCertificateError::PinPubKeyMismatch),
+                    Some(_) if user.hw_pubkey != cert_data.hw_pubkey => Err(WalletCertificateError::HwPubKeyMismatch),
+                    Some(pin_pubkey) => Ok((user, pin_pubkey)),
                 }
             }
         }
     }
 
-    async fn verify_instruction<I, R, D>(
+    fn verify_instruction<I>(
         &self,
         instruction: Instruction<I>,
         wallet_user: &WalletUser,
+        pin_pubkey: &VerifyingKey,
         time_generator: &impl Generator<DateTime<Local>>,
-        verifying_key_decrypter: &D,
     ) -> Result<ChallengeResponsePayload<I>, InstructionValidationError>
     where
-        I: HandleInstruction<Result = R> + Serialize + DeserializeOwned,
-        D: Decrypter<VerifyingKey, Error = HsmError>,
+        I: Serialize + DeserializeOwned,
     {
         let challenge = wallet_user
             .instruction_challenge
@@ -588,20 +892,13 @@ impl AccountServer {
             return Err(InstructionValidationError::ChallengeTimeout);
         }
 
-        let pin_pubkey = verifying_key_decrypter
-            .decrypt(
-                &self.encryption_key_identifier,
-                wallet_user.encrypted_pin_pubkey.clone(),
-            )
-            .await?;
-
         let parsed = instruction
             .instruction
             .parse_and_verify(
                 &challenge.bytes,
                 SequenceNumberComparison::LargerThan(wallet_user.instruction_sequence_number),
                 &wallet_user.hw_pubkey.0,
-                &pin_pubkey,
+                pin_pubkey,
             )
             .map_err(InstructionValidationError::VerificationFailed)?;
 
@@ -704,7 +1001,10 @@ mod tests {
 
     use wallet_common::{
         account::{
-            messages::instructions::{CheckPin, InstructionChallengeRequest},
+            messages::{
+                auth::WalletAttestationRequest,
+                instructions::{ChangePin, ChangePinRollback, CheckPin, InstructionChallengeRequest},
+            },
             serialization::DerVerifyingKey,
         },
         keys::{software::SoftwareEcdsaKey, EcdsaKey},
@@ -785,6 +1085,7 @@ mod tests {
     struct WalletUserTestRepo {
         hw: VerifyingKey,
         pin: VerifyingKey,
+        previous_pin: Option<VerifyingKey>,
         challenge: Option<Vec<u8>>,
         instruction_sequence_number: u64,
     }
@@ -815,6 +1116,18 @@ mod tests {
                 )
                 .await
                 .unwrap(),
+                encrypted_previous_pin_pubkey: match self.previous_pin {
+                    Some(previous_pin) => Some(
+                        Encrypter::<VerifyingKey>::encrypt(
+                            &MockPkcs11Client::<HsmError>::default(),
+                            "encryption_key_1",
+                            previous_pin,
+                        )
+                        .await
+                        .unwrap(),
+                    ),
+                    None => None,
+                },
                 unsuccessful_pin_entries: 0,
                 last_unsuccessful_pin_entry: None,
                 instruction_challenge: self.challenge.clone().map(|c| InstructionChallenge {
@@ -887,6 +1200,28 @@ mod tests {
                 })
                 .collect())
         }
+        async fn change_pin(
+            &self,
+            _transaction: &Self::TransactionType,
+            _wallet_id: &str,
+            _new_encrypted_pin_pubkey: Encrypted<VerifyingKey>,
+        ) -> Result<(), PersistenceError> {
+            Ok(())
+        }
+        async fn commit_pin_change(
+            &self,
+            _transaction: &Self::TransactionType,
+            _wallet_id: &str,
+        ) -> Result<(), PersistenceError> {
+            Ok(())
+        }
+        async fn rollback_pin_change(
+            &self,
+            _transaction: &Self::TransactionType,
+            _wallet_id: &str,
+        ) -> Result<(), PersistenceError> {
+            Ok(())
+        }
     }
 
     impl TransactionStarter for WalletUserTestRepo {
@@ -897,6 +1232,80 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_wallet_attestation() {
+        let certificate_signing_key = SoftwareEcdsaKey::new_random("certificate_signing_key".to_string());
+        let certificate_signing_pubkey = certificate_signing_key.verifying_key().await.unwrap();
+
+        let (account_server, hsm) = mock::account_server_and_hsm((&certificate_signing_pubkey).into()).await;
+        let hw_privkey = SigningKey::random(&mut OsRng);
+        let pin_privkey = SigningKey::random(&mut OsRng);
+
+        let cert = do_registration(
+            &account_server,
+            &hsm,
+            &certificate_signing_key,
+            &hw_privkey,
+            &pin_privkey,
+        )
+        .await;
+
+        let deps = WalletUserTestRepo {
+            hw: *hw_privkey.verifying_key(),
+            pin: *pin_privkey.verifying_key(),
+            previous_pin: None,
+            challenge: None,
+            instruction_sequence_number: 0,
+        };
+
+        // A request that is not signed by the hardware key of the wallet is rejected.
+        let other_privkey = SigningKey::random(&mut OsRng);
+        let error = account_server
+            .wallet_attestation(
+                WalletAttestationRequestMessage {
+                    message: WalletAttestationRequest::new_signed("wallet", &other_privkey)
+                        .await
+                        .unwrap(),
+                    certificate: cert.clone(),
+                },
+                &certificate_signing_key,
+                &deps,
+                &hsm,
+            )
+            .await
+            .expect_err("should return wallet attestation request validation error");
+        assert_matches!(error, WalletAttestationError::Validation(_));
+
+        let attestation = account_server
+            .wallet_attestation(
+                WalletAttestationRequestMessage {
+                    message: WalletAttestationRequest::new_signed("wallet", &hw_privkey)
+                        .await
+                        .unwrap(),
+                    certificate: cert.clone(),
+                },
+                &certificate_signing_key,
+                &deps,
+                &hsm,
+            )
+            .await
+            .unwrap();
+
+        // The attestation is signed by the certificate signing key and bound to the hardware key of the wallet.
+        let cert_data = cert
+            .parse_and_verify_with_sub(&(&certificate_signing_pubkey).into())
+            .unwrap();
+        let attestation_data = attestation
+            .parse_and_verify_with_sub(&(&certificate_signing_pubkey).into())
+            .unwrap();
+        assert_eq!(attestation_data.wallet_id, cert_data.wallet_id);
+        assert_eq!(attestation_data.hw_pubkey.0, *hw_privkey.verifying_key());
+        assert_eq!(
+            attestation_data.exp,
+            attestation_data.iat + WALLET_ATTESTATION_VALIDITY.as_secs()
+        );
+    }
+
     #[tokio::test]
     async fn test_check_pin() {
         let certificate_signing_key = SoftwareEcdsaKey::new_random("certificate_signing_key".to_string());
@@ -922,6 +1331,7 @@ mod tests {
         let deps = WalletUserTestRepo {
             hw: hw_pubkey,
             pin: pin_pubkey,
+            previous_pin: None,
             challenge: None,
             instruction_sequence_number: 42,
         };
@@ -977,6 +1387,7 @@ mod tests {
                     &WalletUserTestRepo {
                         hw: hw_pubkey,
                         pin: pin_pubkey,
+                        previous_pin: None,
                         challenge: Some(challenge.clone()),
                         instruction_sequence_number: 43,
                     },
@@ -1001,6 +1412,7 @@ mod tests {
                 &WalletUserTestRepo {
                     hw: hw_pubkey,
                     pin: pin_pubkey,
+                    previous_pin: None,
                     challenge: Some(challenge),
                     instruction_sequence_number: 2,
                 },
@@ -1011,6 +1423,269 @@ mod tests {
             .expect("should return instruction result");
     }
 
+    #[tokio::test]
+    async fn test_generate_key_attestation() {
+        let certificate_signing_key = SoftwareEcdsaKey::new_random("certificate_signing_key".to_string());
+        let certificate_signing_pubkey = certificate_signing_key.verifying_key().await.unwrap();
+        let instruction_result_signing_key = SoftwareEcdsaKey::new_random("instruction_result_signing_key".to_string());
+        let instruction_result_pubkey = instruction_result_signing_key.verifying_key().await.unwrap();
+
+        let (account_server, hsm) = mock::account_server_and_hsm((&certificate_signing_pubkey).into()).await;
+        let hw_privkey = SigningKey::random(&mut OsRng);
+        let pin_privkey = SigningKey::random(&mut OsRng);
+
+        let cert = do_registration(
+            &account_server,
+            &hsm,
+            &certificate_signing_key,
+            &hw_privkey,
+            &pin_privkey,
+        )
+        .await;
+
+        let deps = WalletUserTestRepo {
+            hw: *hw_privkey.verifying_key(),
+            pin: *pin_privkey.verifying_key(),
+            previous_pin: None,
+            challenge: None,
+            instruction_sequence_number: 0,
+        };
+        let challenge = account_server
+            .instruction_challenge(
+                InstructionChallengeRequestMessage {
+                    message: InstructionChallengeRequest::new_signed(1, "wallet", &hw_privkey)
+                        .await
+                        .unwrap(),
+                    certificate: cert.clone(),
+                },
+                &deps,
+                &EpochGenerator,
+                &hsm,
+            )
+            .await
+            .unwrap();
+
+        let result = account_server
+            .handle_generate_key_instruction(
+                Instruction::new_signed(
+                    GenerateKey {
+                        identifiers: vec!["key1".to_string(), "key2".to_string()],
+                    },
+                    2,
+                    &hw_privkey,
+                    &pin_privkey,
+                    &challenge,
+                    cert.clone(),
+                )
+                .await
+                .unwrap(),
+                (&instruction_result_signing_key, &certificate_signing_key),
+                &MockGenerators,
+                &WalletUserTestRepo {
+                    challenge: Some(challenge.clone()),
+                    instruction_sequence_number: 1,
+                    ..deps
+                },
+                &TimeoutPinPolicy,
+                &hsm,
+            )
+            .await
+            .expect("should return instruction result")
+            .parse_and_verify_with_sub(&(&instruction_result_pubkey).into())
+            .unwrap()
+            .result;
+
+        // The key attestation is signed by the certificate signing key and attests all generated keys to belong to
+        // the wallet.
+        let cert_data = cert
+            .parse_and_verify_with_sub(&(&certificate_signing_pubkey).into())
+            .unwrap();
+        let attestation_data = result
+            .key_attestation
+            .expect("should contain key attestation")
+            .parse_and_verify_with_sub(&(&certificate_signing_pubkey).into())
+            .unwrap();
+        assert_eq!(attestation_data.wallet_id, cert_data.wallet_id);
+        assert_eq!(
+            attestation_data.attested_keys,
+            result.public_keys.into_iter().map(|(_, key)| key).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_pin() {
+        let certificate_signing_key = SoftwareEcdsaKey::new_random("certificate_signing_key".to_string());
+        let certificate_signing_pubkey = certificate_signing_key.verifying_key().await.unwrap();
+        let instruction_result_signing_key = SoftwareEcdsaKey::new_random("instruction_result_signing_key".to_string());
+        let instruction_result_pubkey = instruction_result_signing_key.verifying_key().await.unwrap();
+
+        let (account_server, hsm) = mock::account_server_and_hsm((&certificate_signing_pubkey).into()).await;
+        let hw_privkey = SigningKey::random(&mut OsRng);
+        let pin_privkey = SigningKey::random(&mut OsRng);
+        let new_pin_privkey = SigningKey::random(&mut OsRng);
+
+        let hw_pubkey = *hw_privkey.verifying_key();
+        let pin_pubkey = *pin_privkey.verifying_key();
+        let new_pin_pubkey = *new_pin_privkey.verifying_key();
+
+        let cert = do_registration(
+            &account_server,
+            &hsm,
+            &certificate_signing_key,
+            &hw_privkey,
+            &pin_privkey,
+        )
+        .await;
+
+        let challenge = account_server
+            .instruction_challenge(
+                InstructionChallengeRequestMessage {
+                    message: InstructionChallengeRequest::new_signed(1, "wallet", &hw_privkey)
+                        .await
+                        .unwrap(),
+                    certificate: cert.clone(),
+                },
+                &WalletUserTestRepo {
+                    hw: hw_pubkey,
+                    pin: pin_pubkey,
+                    previous_pin: None,
+                    challenge: None,
+                    instruction_sequence_number: 0,
+                },
+                &EpochGenerator,
+                &hsm,
+            )
+            .await
+            .unwrap();
+
+        // Changing the PIN, signed with the current PIN, results in a certificate for the new PIN.
+        let new_cert = account_server
+            .handle_change_pin_instruction(
+                Instruction::new_signed(
+                    ChangePin {
+                        pin_pubkey: new_pin_pubkey.into(),
+                    },
+                    2,
+                    &hw_privkey,
+                    &pin_privkey,
+                    &challenge,
+                    cert.clone(),
+                )
+                .await
+                .unwrap(),
+                (&instruction_result_signing_key, &certificate_signing_key),
+                &MockGenerators,
+                &WalletUserTestRepo {
+                    hw: hw_pubkey,
+                    pin: pin_pubkey,
+                    previous_pin: None,
+                    challenge: Some(challenge.clone()),
+                    instruction_sequence_number: 1,
+                },
+                &TimeoutPinPolicy,
+                &hsm,
+            )
+            .await
+            .expect("should return instruction result")
+            .parse_and_verify_with_sub(&(&instruction_result_pubkey).into())
+            .unwrap()
+            .result;
+
+        // While the PIN change is in progress, the new certificate is accepted for regular instructions...
+        let changing_repo = WalletUserTestRepo {
+            hw: hw_pubkey,
+            pin: new_pin_pubkey,
+            previous_pin: Some(pin_pubkey),
+            challenge: Some(challenge.clone()),
+            instruction_sequence_number: 2,
+        };
+        let (_, verified_pin_pubkey) = account_server
+            .verify_wallet_certificate(&new_cert, PinPubkeySelection::Current, &changing_repo, &hsm)
+            .await
+            .unwrap();
+        assert_eq!(verified_pin_pubkey, new_pin_pubkey);
+
+        // ...while the old certificate is only accepted for rolling back the change.
+        assert_matches!(
+            account_server
+                .verify_wallet_certificate(&cert, PinPubkeySelection::Current, &changing_repo, &hsm)
+                .await
+                .expect_err("should not validate old certificate"),
+            WalletCertificateError::PinPubKeyMismatch
+        );
+        let (_, verified_pin_pubkey) = account_server
+            .verify_wallet_certificate(&cert, PinPubkeySelection::PreviousIfChanging, &changing_repo, &hsm)
+            .await
+            .unwrap();
+        assert_eq!(verified_pin_pubkey, pin_pubkey);
+
+        // Another PIN change cannot be started before the current one is committed or rolled back.
+        assert_matches!(
+            account_server
+                .handle_change_pin_instruction(
+                    Instruction::new_signed(
+                        ChangePin {
+                            pin_pubkey: (*SigningKey::random(&mut OsRng).verifying_key()).into(),
+                        },
+                        3,
+                        &hw_privkey,
+                        &new_pin_privkey,
+                        &challenge,
+                        new_cert.clone(),
+                    )
+                    .await
+                    .unwrap(),
+                    (&instruction_result_signing_key, &certificate_signing_key),
+                    &MockGenerators,
+                    &changing_repo,
+                    &TimeoutPinPolicy,
+                    &hsm,
+                )
+                .await
+                .expect_err("should not allow concurrent pin changes"),
+            InstructionError::PinChangeInProgress
+        );
+
+        // Rolling back the change should be signed with the old PIN.
+        assert_matches!(
+            account_server
+                .handle_change_pin_rollback_instruction(
+                    Instruction::new_signed(
+                        ChangePinRollback,
+                        3,
+                        &hw_privkey,
+                        &new_pin_privkey,
+                        &challenge,
+                        cert.clone()
+                    )
+                    .await
+                    .unwrap(),
+                    &instruction_result_signing_key,
+                    &MockGenerators,
+                    &changing_repo,
+                    &FailingPinPolicy,
+                    &hsm,
+                )
+                .await
+                .expect_err("should not roll back with the new pin"),
+            InstructionError::IncorrectPin(_)
+        );
+
+        account_server
+            .handle_change_pin_rollback_instruction(
+                Instruction::new_signed(ChangePinRollback, 3, &hw_privkey, &pin_privkey, &challenge, cert)
+                    .await
+                    .unwrap(),
+                &instruction_result_signing_key,
+                &MockGenerators,
+                &changing_repo,
+                &TimeoutPinPolicy,
+                &hsm,
+            )
+            .await
+            .expect("should return instruction result");
+    }
+
     #[tokio::test]
     async fn valid_wallet_certificate_should_verify() {
         let certificate_signing_key = SoftwareEcdsaKey::new_random("certificate_signing_key".to_string());
@@ -1045,6 +1720,7 @@ mod tests {
                 &WalletUserTestRepo {
                     hw: hw_pubkey,
                     pin: pin_pubkey,
+                    previous_pin: None,
                     challenge: None,
                     instruction_sequence_number: 0,
                 },
@@ -1057,9 +1733,11 @@ mod tests {
         account_server
             .verify_wallet_certificate(
                 &cert,
+                PinPubkeySelection::Current,
                 &WalletUserTestRepo {
                     hw: hw_pubkey,
                     pin: pin_pubkey,
+                    previous_pin: None,
                     challenge: Some(challenge),
                     instruction_sequence_number: 0,
                 },
@@ -1092,9 +1770,11 @@ mod tests {
         account_server
             .verify_wallet_certificate(
                 &cert,
+                PinPubkeySelection::Current,
                 &WalletUserTestRepo {
                     hw: *SigningKey::random(&mut OsRng).verifying_key(),
                     pin: pin_pubkey,
+                    previous_pin: None,
                     challenge: None,
                     instruction_sequence_number: 0,
                 },
@@ -1126,9 +1806,11 @@ mod tests {
         account_server
             .verify_wallet_certificate(
                 &cert,
+                PinPubkeySelection::Current,
                 &WalletUserTestRepo {
                     hw: hw_pubkey,
                     pin: *SigningKey::random(&mut OsRng).verifying_key(),
+                    previous_pin: None,
                     challenge: None,
                     instruction_sequence_number: 0,
                 },
@@ -1162,6 +1844,7 @@ mod tests {
         let mut repo = WalletUserTestRepo {
             hw: hw_pubkey,
             pin: pin_pubkey,
+            previous_pin: None,
             challenge: None,
             instruction_sequence_number: 0,
         };
@@ -1192,10 +1875,9 @@ mod tests {
                         .await
                         .unwrap(),
                     &user,
+                    &pin_pubkey,
                     &EpochGenerator,
-                    &hsm,
                 )
-                .await
                 .is_ok()
         )
     }
@@ -1224,6 +1906,7 @@ mod tests {
         let mut repo = WalletUserTestRepo {
             hw: hw_pubkey,
             pin: pin_pubkey,
+            previous_pin: None,
             challenge: None,
             instruction_sequence_number: 0,
         };
@@ -1254,9 +1937,9 @@ mod tests {
                         .await
                         .unwrap(),
                     &user,
+                    &pin_pubkey,
                     &EpochGenerator,
-                    &hsm,
-                ).await,
+                ),
                 Err(InstructionValidationError::VerificationFailed(
                     wallet_common::account::errors::Error::ChallengeMismatch
                 ))
@@ -1296,6 +1979,7 @@ mod tests {
         let repo = WalletUserTestRepo {
             hw: hw_pubkey,
             pin: pin_pubkey,
+            previous_pin: None,
             challenge: None,
             instruction_sequence_number: 0,
         };
@@ -1323,16 +2007,14 @@ mod tests {
             });
 
             assert_matches!(
-                account_server
-                    .verify_instruction(
-                        Instruction::new_signed(CheckPin, 44, &hw_privkey, &pin_privkey, &challenge, cert.clone())
-                            .await
-                            .unwrap(),
-                        &user,
-                        &EpochGenerator,
-                        &hsm,
-                    )
-                    .await,
+                account_server.verify_instruction(
+                    Instruction::new_signed(CheckPin, 44, &hw_privkey, &pin_privkey, &challenge, cert.clone())
+                        .await
+                        .unwrap(),
+                    &user,
+                    &pin_pubkey,
+                    &EpochGenerator,
+                ),
                 Err(InstructionValidationError::ChallengeTimeout)
             );
         }
@@ -1,4 +1,8 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use cryptoki::{
     context::{CInitializeArgs, Pkcs11},
@@ -7,6 +11,7 @@ use cryptoki::{
     types::AuthPin,
 };
 use der::{asn1::OctetString, Decode, Encode};
+use metrics::histogram;
 use p256::{
     ecdsa::{Signature, VerifyingKey},
     pkcs8::AssociatedOid,
@@ -112,6 +117,24 @@ pub(crate) trait Pkcs11Client {
     ) -> Result<Vec<u8>>;
 }
 
+/// Run a blocking PKCS#11 `operation` on a separate thread, recording its latency in the metrics.
+async fn timed_blocking<F, R>(operation: &'static str, fun: F) -> Result<R>
+where
+    F: FnOnce() -> Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    let start = Instant::now();
+    let result = spawn::blocking(fun).await;
+    histogram!(
+        "hsm_operation_duration_seconds",
+        "operation" => operation,
+        "outcome" => if result.is_ok() { "success" } else { "error" },
+    )
+    .record(start.elapsed());
+
+    result
+}
+
 #[derive(Clone)]
 pub struct Pkcs11Hsm {
     pool: Pool,
@@ -151,11 +174,22 @@ impl Pkcs11Hsm {
         })
     }
 
+    /// Check if a session can be obtained from the pool, which verifies that the HSM can be reached.
+    pub async fn ping(&self) -> Result<()> {
+        let pool = self.pool.clone();
+
+        timed_blocking("ping", move || {
+            let _session = pool.get()?;
+            Ok(())
+        })
+        .await
+    }
+
     async fn get_key_handle(&self, identifier: &str, handle_type: HandleType) -> Result<ObjectHandle> {
         let pool = self.pool.clone();
         let identifier = String::from(identifier);
 
-        spawn::blocking(move || {
+        timed_blocking("get_key_handle", move || {
             let session = pool.get()?;
             let object_handles = session.find_objects(&[
                 Attribute::Private(matches!(handle_type, HandleType::Private)),
@@ -290,7 +324,7 @@ impl Pkcs11Client for Pkcs11Hsm {
         let pool = self.pool.clone();
         let identifier = String::from(identifier);
 
-        spawn::blocking(move || {
+        timed_blocking("generate_generic_secret_key", move || {
             let session = pool.get()?;
 
             let priv_key_template = &[
@@ -314,7 +348,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     async fn generate_session_signing_key_pair(&self) -> Result<(PublicKeyHandle, PrivateKeyHandle)> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("generate_session_signing_key_pair", move || {
             let session = pool.get()?;
 
             let mut oid = vec![];
@@ -341,7 +375,7 @@ impl Pkcs11Client for Pkcs11Hsm {
         let pool = self.pool.clone();
         let identifier = String::from(identifier);
 
-        spawn::blocking(move || {
+        timed_blocking("generate_signing_key_pair", move || {
             let session = pool.get()?;
 
             let mut oid = vec![];
@@ -381,7 +415,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     async fn get_verifying_key(&self, public_key_handle: PublicKeyHandle) -> Result<VerifyingKey> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("get_verifying_key", move || {
             let session = pool.get()?;
             let attr = session
                 .get_attributes(public_key_handle.0, &[AttributeType::EcPoint])?
@@ -404,7 +438,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     async fn wrap_key(&self, wrapping_key: PrivateKeyHandle, key: PrivateKeyHandle) -> Result<WrappedKey> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("wrap_key", move || {
             let session = pool.get()?;
             let wrapped_key_bytes = session.wrap_key(&Mechanism::AesKeyWrapPad, wrapping_key.0, key.0)?;
             Ok(WrappedKey::new(wrapped_key_bytes))
@@ -420,7 +454,7 @@ impl Pkcs11Client for Pkcs11Hsm {
         let pool = self.pool.clone();
         let wrapped_key: Vec<u8> = wrapped_key.into();
 
-        spawn::blocking(move || {
+        timed_blocking("unwrap_signing_key", move || {
             let session = pool.get()?;
 
             let result = session.unwrap_key(
@@ -443,7 +477,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     async fn delete_key(&self, private_key_handle: PrivateKeyHandle) -> Result<()> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("delete_key", move || {
             let session = pool.get()?;
             session.destroy_object(private_key_handle.0)?;
             Ok(())
@@ -459,7 +493,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     ) -> Result<Vec<u8>> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("sign", move || {
             let mechanism = match mechanism {
                 SigningMechanism::Ecdsa256 => Mechanism::Ecdsa,
                 SigningMechanism::Sha256Hmac => Mechanism::Sha256Hmac,
@@ -481,7 +515,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     ) -> Result<()> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("verify", move || {
             let mechanism = match mechanism {
                 SigningMechanism::Ecdsa256 => Mechanism::Ecdsa,
                 SigningMechanism::Sha256Hmac => Mechanism::Sha256Hmac,
@@ -498,7 +532,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     async fn random_bytes(&self, length: u32) -> Result<Vec<u8>> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("random_bytes", move || {
             let session = pool.get()?;
             let data = session.generate_random_vec(length)?;
             Ok(data)
@@ -514,7 +548,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     ) -> Result<(Vec<u8>, InitializationVector)> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("encrypt", move || {
             let session = pool.get()?;
             let gcm_params = GcmParams::new(&iv.0, &[], AES_AUTHENTICATION_TAG_BITS.into());
             let encrypted_data = session.encrypt(&Mechanism::AesGcm(gcm_params), key_handle.0, &data)?;
@@ -531,7 +565,7 @@ impl Pkcs11Client for Pkcs11Hsm {
     ) -> Result<Vec<u8>> {
         let pool = self.pool.clone();
 
-        spawn::blocking(move || {
+        timed_blocking("decrypt", move || {
             let session = pool.get()?;
             let gcm_params = GcmParams::new(&iv.0, &[], AES_AUTHENTICATION_TAG_BITS.into());
             let data = session.decrypt(&Mechanism::AesGcm(gcm_params), key_handle.0, &encrypted_data)?;
@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use wallet_common::{
     account::{
-        messages::instructions::{CheckPin, GenerateKey, GenerateKeyResult, Sign, SignResult},
+        messages::instructions::{ChangePinCommit, CheckPin, GenerateKey, GenerateKeyResult, Sign, SignResult},
         serialization::{DerSignature, DerVerifyingKey},
     },
     generator::Generator,
@@ -54,6 +54,30 @@ impl HandleInstruction for CheckPin {
     }
 }
 
+impl HandleInstruction for ChangePinCommit {
+    type Result = ();
+
+    async fn handle<T, R>(
+        self,
+        wallet_user: &WalletUser,
+        _uuid_generator: &impl Generator<Uuid>,
+        wallet_user_repository: &R,
+        _wallet_user_hsm: &impl WalletUserHsm<Error = HsmError>,
+    ) -> Result<(), InstructionError>
+    where
+        T: Committable,
+        R: TransactionStarter<TransactionType = T> + WalletUserRepository<TransactionType = T>,
+    {
+        let tx = wallet_user_repository.begin_transaction().await?;
+        wallet_user_repository
+            .commit_pin_change(&tx, &wallet_user.wallet_id)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
 impl HandleInstruction for GenerateKey {
     type Result = GenerateKeyResult;
 
@@ -97,7 +121,11 @@ impl HandleInstruction for GenerateKey {
             .await?;
         tx.commit().await?;
 
-        Ok(GenerateKeyResult { public_keys })
+        // The keys are attested by the account server, which holds the key with which attestations are signed.
+        Ok(GenerateKeyResult {
+            public_keys,
+            key_attestation: None,
+        })
     }
 }
 
@@ -152,7 +180,7 @@ mod tests {
     use rand::rngs::OsRng;
 
     use wallet_common::{
-        account::messages::instructions::{CheckPin, GenerateKey, Sign},
+        account::messages::instructions::{ChangePinCommit, CheckPin, GenerateKey, Sign},
         utils::random_bytes,
     };
     use wallet_provider_domain::{
@@ -180,6 +208,31 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn should_handle_change_pin_commit() {
+        let wallet_user = wallet_user::mock::wallet_user_1();
+
+        let mut wallet_user_repo = MockTransactionalWalletUserRepository::new();
+        wallet_user_repo
+            .expect_begin_transaction()
+            .returning(|| Ok(MockTransaction));
+        wallet_user_repo
+            .expect_commit_pin_change()
+            .withf(|_, wallet_id| wallet_id == "wallet_123")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        ChangePinCommit
+            .handle(
+                &wallet_user,
+                &FixedUuidGenerator,
+                &wallet_user_repo,
+                &MockPkcs11Client::default(),
+            )
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn should_handle_generate_key() {
         let wallet_user = wallet_user::mock::wallet_user_1();
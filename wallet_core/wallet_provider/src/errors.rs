@@ -7,7 +7,9 @@ use wallet_common::{
     http_error::{HttpJsonError, HttpJsonErrorType},
 };
 use wallet_provider_service::{
-    account_server::{ChallengeError, InstructionError, RegistrationError, WalletCertificateError},
+    account_server::{
+        ChallengeError, InstructionError, RegistrationError, WalletAttestationError, WalletCertificateError,
+    },
     hsm::HsmError,
 };
 
@@ -24,6 +26,8 @@ pub enum WalletProviderError {
     #[error("{0}")]
     Instruction(#[from] InstructionError),
     #[error("{0}")]
+    WalletAttestation(#[from] WalletAttestationError),
+    #[error("{0}")]
     Hsm(#[from] HsmError),
 }
 
@@ -70,7 +74,6 @@ impl From<WalletProviderError> for AccountError {
                 RegistrationError::MessageValidation(_) => Self::RegistrationParsing,
                 RegistrationError::SerialNumberMismatch { .. } => Self::RegistrationParsing,
                 RegistrationError::PinPubKeyEncoding(_) => Self::Unexpected,
-                RegistrationError::JwtSigning(_) => Self::Unexpected,
                 RegistrationError::CertificateStorage(_) => Self::Unexpected,
                 RegistrationError::WalletCertificate(_) => Self::Unexpected,
                 RegistrationError::HsmError(_) => Self::Unexpected,
@@ -79,12 +82,19 @@ impl From<WalletProviderError> for AccountError {
                 InstructionError::IncorrectPin(data) => Self::IncorrectPin(data),
                 InstructionError::PinTimeout(data) => Self::PinTimeout(data),
                 InstructionError::AccountBlocked => Self::AccountBlocked,
-                InstructionError::Validation(_) => Self::InstructionValidation,
+                InstructionError::Validation(_) | InstructionError::PinChangeInProgress => Self::InstructionValidation,
                 InstructionError::Signing(_)
                 | InstructionError::Storage(_)
                 | InstructionError::WalletCertificate(_)
                 | InstructionError::HsmError(_) => Self::Unexpected,
             },
+            WalletProviderError::WalletAttestation(error) => match error {
+                WalletAttestationError::WalletCertificate(WalletCertificateError::UserBlocked) => Self::AccountBlocked,
+                WalletAttestationError::WalletCertificate(_) | WalletAttestationError::Validation(_) => {
+                    Self::ChallengeValidation
+                }
+                WalletAttestationError::Signing(_) => Self::Unexpected,
+            },
             WalletProviderError::Hsm(_) => Self::Unexpected,
         }
     }
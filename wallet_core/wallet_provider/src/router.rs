@@ -7,6 +7,7 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use futures::{future::BoxFuture, FutureExt};
 use serde::Serialize;
 use tower_http::trace::TraceLayer;
 use tracing::info;
@@ -14,16 +15,21 @@ use tracing::info;
 use wallet_common::{
     account::{
         messages::{
-            auth::{Certificate, Challenge, Registration},
+            auth::{
+                Certificate, Challenge, Registration, WalletAttestationRequestMessage, WalletAttestationResponse,
+                WalletCertificate,
+            },
             instructions::{
-                CheckPin, GenerateKey, GenerateKeyResult, Instruction, InstructionChallengeRequestMessage,
-                InstructionEndpoint, InstructionResultMessage, Sign, SignResult,
+                ChangePin, ChangePinCommit, ChangePinRollback, CheckPin, GenerateKey, GenerateKeyResult, Instruction,
+                InstructionChallengeRequestMessage, InstructionEndpoint, InstructionResultMessage, Sign, SignResult,
             },
         },
         serialization::DerVerifyingKey,
         signed::SignedDouble,
     },
+    health::{health_router, HealthCheckError, HealthChecker},
     keys::EcdsaKey,
+    metrics,
 };
 
 use crate::{errors::WalletProviderError, router_state::RouterState};
@@ -43,15 +49,29 @@ type Result<T> = std::result::Result<T, WalletProviderError>;
 
 pub fn router(router_state: RouterState) -> Router {
     let state = Arc::new(router_state);
-    Router::new()
-        .nest("/", health_router())
+    let health_checkers: Vec<Arc<dyn HealthChecker>> = vec![
+        Arc::new(DatabaseHealthChecker(Arc::clone(&state))),
+        Arc::new(HsmHealthChecker(Arc::clone(&state))),
+    ];
+    let router = Router::new()
+        .nest("/", health_router(health_checkers))
         .nest(
             "/api/v1",
             Router::new()
                 .route("/enroll", post(enroll))
                 .route("/createwallet", post(create_wallet))
                 .route("/instructions/challenge", post(instruction_challenge))
+                .route("/wallet_attestation", post(wallet_attestation))
                 .route(&format!("/instructions/{}", CheckPin::ENDPOINT), post(check_pin))
+                .route(&format!("/instructions/{}", ChangePin::ENDPOINT), post(change_pin))
+                .route(
+                    &format!("/instructions/{}", ChangePinCommit::ENDPOINT),
+                    post(change_pin_commit),
+                )
+                .route(
+                    &format!("/instructions/{}", ChangePinRollback::ENDPOINT),
+                    post(change_pin_rollback),
+                )
                 .route(&format!("/instructions/{}", GenerateKey::ENDPOINT), post(generate_key))
                 .route(&format!("/instructions/{}", Sign::ENDPOINT), post(sign))
                 .layer(TraceLayer::new_for_http())
@@ -63,11 +83,43 @@ pub fn router(router_state: RouterState) -> Router {
                 .route("/public-keys", get(public_keys))
                 .layer(TraceLayer::new_for_http())
                 .with_state(Arc::clone(&state)),
-        )
+        );
+
+    metrics::record_metrics(router)
 }
 
-fn health_router() -> Router {
-    Router::new().route("/health", get(|| async {}))
+struct DatabaseHealthChecker(Arc<RouterState>);
+
+impl HealthChecker for DatabaseHealthChecker {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn check(&self) -> BoxFuture<'_, std::result::Result<(), HealthCheckError>> {
+        async move {
+            self.0.repositories.ping().await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+struct HsmHealthChecker(Arc<RouterState>);
+
+impl HealthChecker for HsmHealthChecker {
+    fn name(&self) -> &'static str {
+        "hsm"
+    }
+
+    fn check(&self) -> BoxFuture<'_, std::result::Result<(), HealthCheckError>> {
+        async move {
+            self.0.hsm.ping().await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
 }
 
 async fn enroll(State(state): State<Arc<RouterState>>) -> Result<(StatusCode, Json<Challenge>)> {
@@ -127,6 +179,24 @@ async fn instruction_challenge(
     Ok((StatusCode::OK, body.into()))
 }
 
+async fn wallet_attestation(
+    State(state): State<Arc<RouterState>>,
+    Json(payload): Json<WalletAttestationRequestMessage>,
+) -> Result<(StatusCode, Json<WalletAttestationResponse>)> {
+    info!("Received wallet attestation request, issuing wallet attestation");
+
+    let attestation = state
+        .account_server
+        .wallet_attestation(payload, &state.certificate_signing_key, &state.repositories, &state.hsm)
+        .await?;
+
+    let body = WalletAttestationResponse { attestation };
+
+    info!("Replying with the wallet attestation");
+
+    Ok((StatusCode::OK, body.into()))
+}
+
 async fn check_pin(
     State(state): State<Arc<RouterState>>,
     Json(payload): Json<Instruction<CheckPin>>,
@@ -136,12 +206,72 @@ async fn check_pin(
     Ok((StatusCode::OK, body.into()))
 }
 
+async fn change_pin(
+    State(state): State<Arc<RouterState>>,
+    Json(payload): Json<Instruction<ChangePin>>,
+) -> Result<(StatusCode, Json<InstructionResultMessage<WalletCertificate>>)> {
+    info!("Received change pin request, handling the ChangePin instruction");
+    let result = state
+        .account_server
+        .handle_change_pin_instruction(
+            payload,
+            (&state.instruction_result_signing_key, &state.certificate_signing_key),
+            state.as_ref(),
+            &state.repositories,
+            &state.pin_policy,
+            &state.hsm,
+        )
+        .await?;
+    let body = InstructionResultMessage { result };
+    Ok((StatusCode::OK, body.into()))
+}
+
+async fn change_pin_commit(
+    State(state): State<Arc<RouterState>>,
+    Json(payload): Json<Instruction<ChangePinCommit>>,
+) -> Result<(StatusCode, Json<InstructionResultMessage<()>>)> {
+    info!("Received change pin commit request, handling the ChangePinCommit instruction");
+    let body = state.handle_instruction(payload).await?;
+    Ok((StatusCode::OK, body.into()))
+}
+
+async fn change_pin_rollback(
+    State(state): State<Arc<RouterState>>,
+    Json(payload): Json<Instruction<ChangePinRollback>>,
+) -> Result<(StatusCode, Json<InstructionResultMessage<()>>)> {
+    info!("Received change pin rollback request, handling the ChangePinRollback instruction");
+    let result = state
+        .account_server
+        .handle_change_pin_rollback_instruction(
+            payload,
+            &state.instruction_result_signing_key,
+            state.as_ref(),
+            &state.repositories,
+            &state.pin_policy,
+            &state.hsm,
+        )
+        .await?;
+    let body = InstructionResultMessage { result };
+    Ok((StatusCode::OK, body.into()))
+}
+
 async fn generate_key(
     State(state): State<Arc<RouterState>>,
     Json(payload): Json<Instruction<GenerateKey>>,
 ) -> Result<(StatusCode, Json<InstructionResultMessage<GenerateKeyResult>>)> {
     info!("Received generate key request, handling the GenerateKey instruction");
-    let body = state.handle_instruction(payload).await?;
+    let result = state
+        .account_server
+        .handle_generate_key_instruction(
+            payload,
+            (&state.instruction_result_signing_key, &state.certificate_signing_key),
+            state.as_ref(),
+            &state.repositories,
+            &state.pin_policy,
+            &state.hsm,
+        )
+        .await?;
+    let body = InstructionResultMessage { result };
     Ok((StatusCode::OK, body.into()))
 }
 
@@ -3,12 +3,25 @@ use std::error::Error;
 use tokio::net::TcpListener;
 use tracing::debug;
 
+use wallet_common::metrics;
+
 use super::{router, router_state::RouterState, settings::Settings};
 
 pub async fn serve(settings: Settings) -> Result<(), Box<dyn Error>> {
     let listener = TcpListener::bind((settings.webserver.ip, settings.webserver.port)).await?;
     debug!("listening on {}:{}", settings.webserver.ip, settings.webserver.port);
 
+    if let Some(metrics_server) = &settings.metrics_server {
+        let metrics_listener = TcpListener::bind((metrics_server.ip, metrics_server.port)).await?;
+        debug!("listening for metrics on {}:{}", metrics_server.ip, metrics_server.port);
+
+        tokio::spawn(async move {
+            axum::serve(metrics_listener, metrics::metrics_router())
+                .await
+                .expect("metrics server should be started");
+        });
+    }
+
     let router_state = RouterState::new_from_settings(settings).await?;
 
     let app = router::router(router_state);
@@ -17,6 +17,9 @@ pub struct Settings {
     pub pin_public_disclosure_protection_key_identifier: String,
     pub database: Database,
     pub webserver: Webserver,
+    /// The server on which the metrics are served, which should only be reachable internally. If not configured,
+    /// the metrics are not served at all.
+    pub metrics_server: Option<Webserver>,
     pub hsm: Hsm,
     pub pin_policy: PinPolicySettings,
     pub structured_logging: bool,
@@ -3,12 +3,22 @@ use async_trait::async_trait;
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20241016_000001_create_session_state_notify_trigger;
+mod m20241017_000001_add_session_state_version;
+mod m20241018_000001_add_session_state_expiration;
+mod m20241019_000001_add_session_state_pending;
 
 pub struct Migrator;
 
 #[async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20241016_000001_create_session_state_notify_trigger::Migration),
+            Box::new(m20241017_000001_add_session_state_version::Migration),
+            Box::new(m20241018_000001_add_session_state_expiration::Migration),
+            Box::new(m20241019_000001_add_session_state_pending::Migration),
+        ]
     }
 }
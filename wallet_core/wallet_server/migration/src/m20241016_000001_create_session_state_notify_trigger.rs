@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Notify listeners on the "session_state" channel of every insert or update, using "<type>/<token>" as payload.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE OR REPLACE FUNCTION notify_session_state() RETURNS trigger AS $$
+                BEGIN
+                    PERFORM pg_notify('session_state', NEW.type || '/' || NEW.token);
+                    RETURN NEW;
+                END;
+                $$ LANGUAGE plpgsql",
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE TRIGGER session_state_notify
+                AFTER INSERT OR UPDATE ON session_state
+                FOR EACH ROW EXECUTE FUNCTION notify_session_state()",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TRIGGER IF EXISTS session_state_notify ON session_state")
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS notify_session_state")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The version is incremented on every update, which allows writes to detect concurrent updates.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .add_column(
+                        ColumnDef::new(SessionState::Version)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .drop_column(SessionState::Version)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionState {
+    Table,
+    Version,
+}
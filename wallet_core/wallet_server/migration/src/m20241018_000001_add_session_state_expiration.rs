@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Sessions that have their own expiration store the moment they expire, otherwise this column is null.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .add_column(
+                        ColumnDef::new(SessionState::ExpirationDateTime)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .drop_column(SessionState::ExpirationDateTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionState {
+    Table,
+    ExpirationDateTime,
+}
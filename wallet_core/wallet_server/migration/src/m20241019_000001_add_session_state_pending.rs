@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Marks the sessions for which something still has to be done, e.g. delivering a callback, so that these can
+        // be found without reading every session.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .add_column(
+                        ColumnDef::new(SessionState::Pending)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("session_state_pending_idx")
+                    .table(SessionState::Table)
+                    .col(SessionState::Type)
+                    .col(SessionState::Pending)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("session_state_pending_idx")
+                    .table(SessionState::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionState::Table)
+                    .drop_column(SessionState::Pending)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionState {
+    Table,
+    Type,
+    Pending,
+}
@@ -0,0 +1,21 @@
+use anyhow::Result;
+
+use wallet_server::{
+    server::{self, wallet_server_main},
+    settings::Settings,
+    store::SessionStoreVariant,
+};
+
+// Cannot use #[tokio::main], see: https://docs.sentry.io/platforms/rust/#async-main-function
+fn main() -> Result<()> {
+    wallet_server_main("generic_issuer.toml", "generic_issuer", async_main)
+}
+
+async fn async_main(settings: Settings) -> Result<()> {
+    let storage_settings = &settings.storage;
+    let sessions = SessionStoreVariant::new(storage_settings.url.clone(), storage_settings.into()).await?;
+    let health_checkers = vec![sessions.health_checker()];
+
+    // This will block until the server shuts down.
+    server::generic_issuer::serve(settings, sessions, health_checkers).await
+}
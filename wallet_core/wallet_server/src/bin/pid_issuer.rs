@@ -15,7 +15,11 @@ fn main() -> Result<()> {
 async fn async_main(settings: Settings) -> Result<()> {
     let storage_settings = &settings.storage;
     let sessions = SessionStoreVariant::new(storage_settings.url.clone(), storage_settings.into()).await?;
+    let attr_service = BrpPidAttributeService::try_from(&settings.issuer)?;
+
+    let mut health_checkers = vec![sessions.health_checker()];
+    health_checkers.extend(attr_service.health_checkers());
 
     // This will block until the server shuts down.
-    server::pid_issuer::serve(BrpPidAttributeService::try_from(&settings.issuer)?, settings, sessions).await
+    server::pid_issuer::serve(attr_service, settings, sessions, health_checkers).await
 }
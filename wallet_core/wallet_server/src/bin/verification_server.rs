@@ -14,7 +14,8 @@ fn main() -> Result<()> {
 async fn async_main(settings: Settings) -> Result<()> {
     let storage_settings = &settings.storage;
     let sessions = SessionStoreVariant::new(storage_settings.url.clone(), storage_settings.into()).await?;
+    let health_checkers = vec![sessions.health_checker()];
 
     // This will block until the server shuts down.
-    server::verification_server::serve(settings, sessions).await
+    server::verification_server::serve(settings, sessions, health_checkers).await
 }
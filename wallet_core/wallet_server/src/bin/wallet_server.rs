@@ -17,13 +17,18 @@ async fn async_main(settings: Settings) -> Result<()> {
     let disclosure_sessions = SessionStoreVariant::new(storage_settings.url.clone(), storage_settings.into()).await?;
     // Clone from `disclosure_sessions` so that database connection pool is reused when using PostgreSQL.
     let issuance_sessions = disclosure_sessions.clone_into();
+    let attr_service = BrpPidAttributeService::try_from(&settings.issuer)?;
+
+    let mut health_checkers = vec![disclosure_sessions.health_checker()];
+    health_checkers.extend(attr_service.health_checkers());
 
     // This will block until the server shuts down.
     server::wallet_server::serve(
-        BrpPidAttributeService::try_from(&settings.issuer)?,
+        attr_service,
         settings,
         disclosure_sessions,
         issuance_sessions,
+        health_checkers,
     )
     .await
 }
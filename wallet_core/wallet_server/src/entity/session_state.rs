@@ -12,6 +12,9 @@ pub struct Model {
     pub data: Json,
     pub status: String,
     pub last_active_date_time: DateTimeWithTimeZone,
+    pub version: i64,
+    pub expiration_date_time: Option<DateTimeWithTimeZone>,
+    pub pending: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,8 +1,9 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::Arc};
 
 use axum::{
-    extract::State,
-    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Uri},
+    extract::{Path, State},
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
     routing::{delete, get, post},
     Form, Json, Router,
 };
@@ -10,23 +11,45 @@ use axum_extra::{
     headers::{self, authorization::Credentials, Authorization, Header},
     TypedHeader,
 };
+use indexmap::IndexMap;
+use itertools::Itertools;
 use nutype::nutype;
-use serde::Serialize;
-
-use nl_wallet_mdoc::server_keys::{KeyPair, KeyRing};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use url::Url;
+
+use nl_wallet_mdoc::{
+    server_keys::{KeyPair, KeyRing},
+    unsigned::UnsignedMdoc,
+    utils::x509::Certificate,
+};
 use openid4vc::{
-    credential::{CredentialRequest, CredentialRequests, CredentialResponse, CredentialResponses},
+    credential::{
+        CredentialRequest, CredentialRequests, DeferredCredentialRequest, MaybeEncrypted, NotificationRequest,
+    },
+    credential_offer::{CredentialOffer, CredentialOfferContainer, Grants, PreAuthorizedCodeGrant, TxCode},
     dpop::{Dpop, DPOP_HEADER_NAME, DPOP_NONCE_HEADER_NAME},
     metadata::IssuerMetadata,
     oidc,
     server_state::SessionStore,
-    token::{AccessToken, TokenRequest, TokenResponseWithPreviews},
-    CredentialErrorCode, ErrorResponse, ErrorStatusCode, TokenErrorCode,
+    token::{AccessToken, AttestationPreview, AuthorizationCode, TokenRequest, TokenResponseWithPreviews},
+    wallet_attestation::{
+        WalletAttestationWithPop, CLIENT_ATTESTATION_CHALLENGE_HEADER_NAME, CLIENT_ATTESTATION_HEADER_NAME,
+        CLIENT_ATTESTATION_POP_HEADER_NAME,
+    },
+    CredentialErrorCode, ErrorResponse, ErrorStatusCode, NotificationErrorCode, TokenErrorCode,
+};
+use wallet_common::{
+    http_error::{HttpJsonError, HttpJsonErrorType},
+    urls::{self, BaseUrl},
 };
 
 use crate::settings::{self, Urls};
 
-use openid4vc::issuer::{AttributeService, IssuanceData, Issuer};
+use openid4vc::issuer::{
+    AttributeService, CredentialRequestError, IssuanceData, IssuanceError, Issuer, PreregisteredAttributeService,
+    TokenRequestError,
+};
 
 struct ApplicationState<A, K, S> {
     issuer: Issuer<A, K, S>,
@@ -59,7 +82,37 @@ impl TryFrom<HashMap<String, settings::KeyPair>> for IssuerKeyRing {
     }
 }
 
-pub fn create_issuance_router<A, S>(
+/// Construct the [`Issuer`], signing its metadata and requiring wallet attestations if configured to do so.
+async fn create_issuer<A, S>(
+    urls: &Urls,
+    issuer: settings::Issuer,
+    sessions: S,
+    attr_service: A,
+) -> anyhow::Result<Issuer<A, IssuerKeyRing, S>>
+where
+    A: AttributeService,
+    S: SessionStore<IssuanceData> + Send + Sync + 'static,
+{
+    let mut issuer_state = Issuer::new(
+        sessions,
+        attr_service,
+        IssuerKeyRing::try_from(issuer.private_keys)?,
+        &urls.public_url,
+        issuer.wallet_client_ids,
+    );
+
+    if let Some(key_id) = issuer.metadata_signing_key {
+        issuer_state.sign_metadata(&key_id).await?;
+    }
+
+    if let Some(wallet_provider_public_key) = issuer.wallet_provider_public_key {
+        issuer_state.require_wallet_attestation(wallet_provider_public_key.into());
+    }
+
+    Ok(issuer_state)
+}
+
+pub async fn create_issuance_router<A, S>(
     urls: &Urls,
     issuer: settings::Issuer,
     sessions: S,
@@ -70,16 +123,48 @@ where
     S: SessionStore<IssuanceData> + Send + Sync + 'static,
 {
     let application_state = Arc::new(ApplicationState {
-        issuer: Issuer::new(
-            sessions,
-            attr_service,
-            IssuerKeyRing::try_from(issuer.private_keys)?,
-            &urls.public_url,
-            issuer.wallet_client_ids,
-        ),
+        issuer: create_issuer(urls, issuer, sessions, attr_service).await?,
+    });
+
+    Ok(issuance_router(application_state))
+}
+
+/// Create the routers for the wallet and the issuing organisation of a generic issuer, respectively. The latter is
+/// used to register the attestations to be issued to the wallet and is not secured by itself, this is left to the
+/// caller.
+pub async fn create_generic_issuance_routers<S>(
+    urls: Urls,
+    issuer: settings::Issuer,
+    sessions: S,
+) -> anyhow::Result<(Router, Router)>
+where
+    S: SessionStore<IssuanceData> + Send + Sync + 'static,
+{
+    let certificates = issuer.certificates();
+    let application_state = Arc::new(ApplicationState {
+        issuer: create_issuer(&urls, issuer, sessions, PreregisteredAttributeService).await?,
+    });
+
+    let registration_state = Arc::new(RegistrationState {
+        application_state: Arc::clone(&application_state),
+        certificates,
+        universal_link_base_url: urls.universal_link_base_url,
     });
 
-    let issuance_router = Router::new()
+    let internal_router = Router::new()
+        .route("/sessions", post(start_issuance))
+        .route("/sessions/:pre_authorized_code/approve", post(approve_issuance))
+        .with_state(registration_state);
+
+    Ok((issuance_router(application_state), internal_router))
+}
+
+fn issuance_router<A, S>(application_state: Arc<ApplicationState<A, IssuerKeyRing, S>>) -> Router
+where
+    A: AttributeService + Send + Sync + 'static,
+    S: SessionStore<IssuanceData> + Send + Sync + 'static,
+{
+    Router::new()
         .route("/.well-known/openid-credential-issuer", get(metadata))
         .route("/.well-known/oauth-authorization-server", get(oauth_metadata))
         .route("/token", post(token))
@@ -87,9 +172,9 @@ where
         .route("/credential", delete(reject_issuance))
         .route("/batch_credential", post(batch_credential))
         .route("/batch_credential", delete(reject_issuance))
-        .with_state(application_state);
-
-    Ok(issuance_router)
+        .route("/deferred_credential", post(deferred_credential))
+        .route("/notification", post(notification))
+        .with_state(application_state)
 }
 
 // Although there is no standard here mandating what our error response looks like, we use `ErrorResponse`
@@ -122,14 +207,19 @@ async fn metadata<A, K, S>(State(state): State<Arc<ApplicationState<A, K, S>>>)
 async fn token<A, K, S>(
     State(state): State<Arc<ApplicationState<A, K, S>>>,
     TypedHeader(DpopHeader(dpop)): TypedHeader<DpopHeader>,
+    headers: HeaderMap,
     Form(token_request): Form<TokenRequest>,
-) -> Result<(HeaderMap, Json<TokenResponseWithPreviews>), ErrorResponse<TokenErrorCode>>
+) -> Result<(HeaderMap, Json<TokenResponseWithPreviews>), Response>
 where
     A: AttributeService,
     K: KeyRing,
     S: SessionStore<IssuanceData>,
 {
-    let (response, dpop_nonce) = state.issuer.process_token_request(token_request, dpop).await?;
+    let (response, dpop_nonce) = state
+        .issuer
+        .process_token_request(token_request, dpop, wallet_attestation(&headers))
+        .await
+        .map_err(token_error_response)?;
     let headers = HeaderMap::from_iter([(
         HeaderName::from_str(DPOP_NONCE_HEADER_NAME).unwrap(),
         HeaderValue::from_str(&dpop_nonce).unwrap(),
@@ -141,8 +231,9 @@ async fn credential<A, K, S>(
     State(state): State<Arc<ApplicationState<A, K, S>>>,
     TypedHeader(Authorization(authorization_header)): TypedHeader<Authorization<DpopBearer>>,
     TypedHeader(DpopHeader(dpop)): TypedHeader<DpopHeader>,
+    headers: HeaderMap,
     Json(credential_request): Json<CredentialRequest>,
-) -> Result<Json<CredentialResponse>, ErrorResponse<CredentialErrorCode>>
+) -> Result<Response, Response>
 where
     A: AttributeService,
     K: KeyRing,
@@ -151,17 +242,19 @@ where
     let access_token = authorization_header.into();
     let response = state
         .issuer
-        .process_credential(access_token, dpop, credential_request)
-        .await?;
-    Ok(Json(response))
+        .process_credential(access_token, dpop, credential_request, wallet_attestation(&headers))
+        .await
+        .map_err(credential_error_response)?;
+    Ok(credential_response(response))
 }
 
 async fn batch_credential<A, K, S>(
     State(state): State<Arc<ApplicationState<A, K, S>>>,
     TypedHeader(Authorization(authorization_header)): TypedHeader<Authorization<DpopBearer>>,
     TypedHeader(DpopHeader(dpop)): TypedHeader<DpopHeader>,
+    headers: HeaderMap,
     Json(credential_requests): Json<CredentialRequests>,
-) -> Result<Json<CredentialResponses>, ErrorResponse<CredentialErrorCode>>
+) -> Result<Response, Response>
 where
     A: AttributeService,
     K: KeyRing,
@@ -170,9 +263,100 @@ where
     let access_token = authorization_header.into();
     let response = state
         .issuer
-        .process_batch_credential(access_token, dpop, credential_requests)
+        .process_batch_credential(access_token, dpop, credential_requests, wallet_attestation(&headers))
+        .await
+        .map_err(credential_error_response)?;
+    Ok(credential_response(response))
+}
+
+async fn deferred_credential<A, K, S>(
+    State(state): State<Arc<ApplicationState<A, K, S>>>,
+    TypedHeader(Authorization(authorization_header)): TypedHeader<Authorization<DpopBearer>>,
+    TypedHeader(DpopHeader(dpop)): TypedHeader<DpopHeader>,
+    headers: HeaderMap,
+    Json(deferred_request): Json<DeferredCredentialRequest>,
+) -> Result<Response, Response>
+where
+    A: AttributeService,
+    K: KeyRing,
+    S: SessionStore<IssuanceData>,
+{
+    let access_token = authorization_header.into();
+    let response = state
+        .issuer
+        .process_deferred_credential(access_token, dpop, deferred_request, wallet_attestation(&headers))
+        .await
+        .map_err(credential_error_response)?;
+    Ok(credential_response(response))
+}
+
+/// Read the wallet attestation and its PoP from the request headers, if the wallet sent them. Whether they are required
+/// and valid is up to the issuer.
+fn wallet_attestation(headers: &HeaderMap) -> Option<WalletAttestationWithPop> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    Some(WalletAttestationWithPop {
+        attestation: header(CLIENT_ATTESTATION_HEADER_NAME)?.into(),
+        pop: header(CLIENT_ATTESTATION_POP_HEADER_NAME)?.into(),
+    })
+}
+
+/// Convert an error of the token or credential endpoints into a response. If the wallet has to include a challenge in
+/// its wallet attestation PoP, the challenge is sent in the `OAuth-Client-Attestation-Challenge` header.
+fn error_response<T>(error: impl Into<ErrorResponse<T>>, attestation_challenge: Option<String>) -> Response
+where
+    T: ErrorStatusCode + Serialize + Debug,
+{
+    let response = error.into().into_response();
+
+    match attestation_challenge {
+        Some(challenge) => (
+            [(
+                HeaderName::from_str(CLIENT_ATTESTATION_CHALLENGE_HEADER_NAME).unwrap(),
+                HeaderValue::from_str(&challenge).unwrap(),
+            )],
+            response,
+        )
+            .into_response(),
+        None => response,
+    }
+}
+
+fn token_error_response(error: TokenRequestError) -> Response {
+    let challenge = error.attestation_challenge().map(str::to_string);
+    error_response::<TokenErrorCode>(error, challenge)
+}
+
+fn credential_error_response(error: CredentialRequestError) -> Response {
+    let challenge = error.attestation_challenge().map(str::to_string);
+    error_response::<CredentialErrorCode>(error, challenge)
+}
+
+/// Send a response of one of the credential endpoints either as JSON, or as a JWE if the wallet asked for encryption.
+fn credential_response<T: Serialize>(response: MaybeEncrypted<T>) -> Response {
+    match response {
+        MaybeEncrypted::Plain(response) => Json(response).into_response(),
+        MaybeEncrypted::Encrypted(jwe) => ([(CONTENT_TYPE, "application/jwt")], jwe).into_response(),
+    }
+}
+
+async fn notification<A, K, S>(
+    State(state): State<Arc<ApplicationState<A, K, S>>>,
+    TypedHeader(Authorization(authorization_header)): TypedHeader<Authorization<DpopBearer>>,
+    TypedHeader(DpopHeader(dpop)): TypedHeader<DpopHeader>,
+    Json(notification_request): Json<NotificationRequest>,
+) -> Result<StatusCode, ErrorResponse<NotificationErrorCode>>
+where
+    A: AttributeService,
+    K: KeyRing,
+    S: SessionStore<IssuanceData>,
+{
+    let access_token = authorization_header.into();
+    state
+        .issuer
+        .process_notification(access_token, dpop, notification_request)
         .await?;
-    Ok(Json(response))
+    Ok(StatusCode::NO_CONTENT)
 }
 
 async fn reject_issuance<A, K, S>(
@@ -196,6 +380,225 @@ where
     Ok(StatusCode::NO_CONTENT)
 }
 
+struct RegistrationState<S> {
+    application_state: Arc<ApplicationState<PreregisteredAttributeService, IssuerKeyRing, S>>,
+    certificates: IndexMap<String, Certificate>,
+    universal_link_base_url: BaseUrl,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartIssuanceRequest {
+    pub attestations: Vec<UnsignedMdoc>,
+    /// Transaction Code that the issuing organisation delivers to the user out-of-band, and which the wallet has to
+    /// send to the issuer in order to receive the attestations.
+    #[serde(default)]
+    pub tx_code: Option<String>,
+    /// Human-readable description of the Transaction Code, e.g. how the user received it.
+    #[serde(default)]
+    pub tx_code_description: Option<String>,
+    /// Whether to defer issuance until the issuing organisation approves it, e.g. because some manual check still
+    /// has to be performed. Until then, the wallet receives a transaction ID with which it can retrieve the
+    /// attestations later.
+    #[serde(default)]
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartIssuanceResponse {
+    pub pre_authorized_code: AuthorizationCode,
+    pub credential_offer_url: Url,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StartIssuanceError {
+    #[error("no attestations to be issued")]
+    NoAttestations,
+    #[error("missing issuance private key for doctype {0}")]
+    MissingPrivateKey(String),
+    #[error("transaction code is empty")]
+    EmptyTxCode,
+    #[error("could not create issuance session: {0}")]
+    Session(#[from] IssuanceError),
+}
+
+/// Error codes sent to the issuing organisation when an error occurs when handling their request.
+#[derive(Debug, Clone, Copy, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum StartIssuanceErrorCode {
+    ServerError,
+    InvalidRequest,
+}
+
+impl HttpJsonErrorType for StartIssuanceErrorCode {
+    fn title(&self) -> String {
+        match self {
+            StartIssuanceErrorCode::ServerError => "Internal server error occurred".to_string(),
+            StartIssuanceErrorCode::InvalidRequest => "Invalid request".to_string(),
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            StartIssuanceErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            StartIssuanceErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<&StartIssuanceError> for StartIssuanceErrorCode {
+    fn from(error: &StartIssuanceError) -> Self {
+        match error {
+            StartIssuanceError::NoAttestations
+            | StartIssuanceError::MissingPrivateKey(_)
+            | StartIssuanceError::EmptyTxCode => Self::InvalidRequest,
+            StartIssuanceError::Session(_) => Self::ServerError,
+        }
+    }
+}
+
+impl From<StartIssuanceError> for HttpJsonError<StartIssuanceErrorCode> {
+    fn from(error: StartIssuanceError) -> Self {
+        HttpJsonError::from_error(&error)
+    }
+}
+
+/// Error codes sent to the issuing organisation when approving deferred issuance fails.
+#[derive(Debug, Clone, Copy, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ApproveIssuanceErrorCode {
+    ServerError,
+    UnknownSession,
+    UnexpectedState,
+}
+
+impl HttpJsonErrorType for ApproveIssuanceErrorCode {
+    fn title(&self) -> String {
+        match self {
+            ApproveIssuanceErrorCode::ServerError => "Internal server error occurred".to_string(),
+            ApproveIssuanceErrorCode::UnknownSession => "Unknown issuance session".to_string(),
+            ApproveIssuanceErrorCode::UnexpectedState => "Issuance session is already finished".to_string(),
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApproveIssuanceErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            ApproveIssuanceErrorCode::UnknownSession => StatusCode::NOT_FOUND,
+            ApproveIssuanceErrorCode::UnexpectedState => StatusCode::CONFLICT,
+        }
+    }
+}
+
+impl From<&IssuanceError> for ApproveIssuanceErrorCode {
+    fn from(error: &IssuanceError) -> Self {
+        match error {
+            IssuanceError::UnknownSession(_) => Self::UnknownSession,
+            IssuanceError::UnexpectedState => Self::UnexpectedState,
+            IssuanceError::SessionStore(_) | IssuanceError::DpopInvalid(_) => Self::ServerError,
+        }
+    }
+}
+
+async fn start_issuance<S>(
+    State(state): State<Arc<RegistrationState<S>>>,
+    Json(start_request): Json<StartIssuanceRequest>,
+) -> Result<Json<StartIssuanceResponse>, HttpJsonError<StartIssuanceErrorCode>>
+where
+    S: SessionStore<IssuanceData>,
+{
+    let response = state
+        .start_issuance(start_request)
+        .await
+        .inspect_err(|error| warn!("starting new issuance session failed: {error}"))?;
+
+    Ok(Json(response))
+}
+
+async fn approve_issuance<S>(
+    State(state): State<Arc<RegistrationState<S>>>,
+    Path(pre_authorized_code): Path<AuthorizationCode>,
+) -> Result<StatusCode, HttpJsonError<ApproveIssuanceErrorCode>>
+where
+    S: SessionStore<IssuanceData>,
+{
+    state
+        .application_state
+        .issuer
+        .approve_deferred_issuance(pre_authorized_code)
+        .await
+        .map_err(|error| {
+            warn!("approving deferred issuance failed: {error}");
+            HttpJsonError::from_error(&error)
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+impl<S> RegistrationState<S>
+where
+    S: SessionStore<IssuanceData>,
+{
+    async fn start_issuance(
+        &self,
+        StartIssuanceRequest {
+            attestations,
+            tx_code,
+            tx_code_description,
+            deferred,
+        }: StartIssuanceRequest,
+    ) -> Result<StartIssuanceResponse, StartIssuanceError> {
+        if tx_code.as_ref().is_some_and(String::is_empty) {
+            return Err(StartIssuanceError::EmptyTxCode);
+        }
+
+        let credential_configuration_ids = attestations
+            .iter()
+            .map(|unsigned_mdoc| unsigned_mdoc.doc_type.clone())
+            .unique()
+            .collect();
+
+        let previews = attestations
+            .into_iter()
+            .map(|unsigned_mdoc| {
+                let issuer = self
+                    .certificates
+                    .get(&unsigned_mdoc.doc_type)
+                    .ok_or_else(|| StartIssuanceError::MissingPrivateKey(unsigned_mdoc.doc_type.clone()))?
+                    .clone();
+
+                Ok(AttestationPreview::MsoMdoc { unsigned_mdoc, issuer })
+            })
+            .collect::<Result<Vec<_>, StartIssuanceError>>()?
+            .try_into()
+            .map_err(|_| StartIssuanceError::NoAttestations)?;
+
+        let issuer = &self.application_state.issuer;
+        let tx_code_description = tx_code
+            .as_ref()
+            .map(|tx_code| TxCode::new_for(tx_code, tx_code_description));
+        let pre_authorized_code = issuer.new_session(previews, tx_code, deferred).await?;
+
+        let offer = CredentialOffer {
+            credential_issuer: issuer.metadata.issuer_config.credential_issuer.clone(),
+            credential_configuration_ids,
+            grants: Some(Grants {
+                authorization_code: None,
+                pre_authorized_code: Some(PreAuthorizedCodeGrant {
+                    pre_authorized_code: pre_authorized_code.clone(),
+                    tx_code: tx_code_description,
+                }),
+            }),
+        };
+        let credential_offer_url = CredentialOfferContainer::Value(offer)
+            .to_uri(urls::credential_offer_base_uri(&self.universal_link_base_url).into_inner());
+
+        Ok(StartIssuanceResponse {
+            pre_authorized_code,
+            credential_offer_url,
+        })
+    }
+}
+
 static DPOP_HEADER_NAME_LOWERCASE: HeaderName = HeaderName::from_static("dpop");
 
 pub struct DpopHeader(Dpop);
@@ -261,3 +664,171 @@ impl ErrorStatusCode for MetadataError {
         StatusCode::INTERNAL_SERVER_ERROR
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use assert_matches::assert_matches;
+    use chrono::{Days, Utc};
+    use ciborium::Value;
+
+    use nl_wallet_mdoc::{unsigned::Entry, utils::issuer_auth::IssuerRegistration, Tdate};
+    use openid4vc::server_state::MemorySessionStore;
+
+    use super::*;
+
+    const DOCTYPE: &str = "com.example.diploma";
+
+    fn unsigned_mdoc(doc_type: &str) -> UnsignedMdoc {
+        UnsignedMdoc {
+            doc_type: doc_type.to_string(),
+            copy_count: NonZeroU8::new(1).unwrap(),
+            valid_from: Tdate::now(),
+            valid_until: (Utc::now() + Days::new(365)).into(),
+            attributes: IndexMap::from([(
+                doc_type.to_string(),
+                vec![Entry {
+                    name: "degree".to_string(),
+                    value: Value::Text("MSc".to_string()),
+                }],
+            )])
+            .try_into()
+            .unwrap(),
+        }
+    }
+
+    fn registration_state() -> RegistrationState<MemorySessionStore<IssuanceData>> {
+        let ca = KeyPair::generate_issuer_mock_ca().unwrap();
+        let key_pair = ca.generate_issuer_mock(IssuerRegistration::new_mock().into()).unwrap();
+        let certificates = IndexMap::from([(DOCTYPE.to_string(), key_pair.certificate().clone())]);
+
+        let issuer = Issuer::new(
+            MemorySessionStore::default(),
+            PreregisteredAttributeService,
+            IssuerKeyRing::from(HashMap::from([(DOCTYPE.to_string(), key_pair)])),
+            &"https://issuer.example.com/".parse().unwrap(),
+            vec![openid4vc::NL_WALLET_CLIENT_ID.to_string()],
+        );
+
+        RegistrationState {
+            application_state: Arc::new(ApplicationState { issuer }),
+            certificates,
+            universal_link_base_url: "https://app.example.com/".parse().unwrap(),
+        }
+    }
+
+    fn start_request(attestations: Vec<UnsignedMdoc>, tx_code: Option<String>) -> StartIssuanceRequest {
+        StartIssuanceRequest {
+            attestations,
+            tx_code,
+            tx_code_description: None,
+            deferred: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_start_issuance() {
+        let state = registration_state();
+
+        let response = state
+            .start_issuance(start_request(
+                vec![unsigned_mdoc(DOCTYPE), unsigned_mdoc(DOCTYPE)],
+                None,
+            ))
+            .await
+            .expect("starting issuance should succeed");
+
+        assert!(response
+            .credential_offer_url
+            .as_str()
+            .starts_with("https://app.example.com/credential-offer?credential_offer="));
+
+        // The offer in the URL should contain the pre-authorized code and each offered doctype once.
+        let CredentialOfferContainer::Value(offer) =
+            CredentialOfferContainer::from_uri(&response.credential_offer_url).unwrap()
+        else {
+            panic!("credential offer should be passed by value")
+        };
+
+        assert_eq!(
+            offer.credential_issuer.as_ref().as_str(),
+            "https://issuer.example.com/issuance/"
+        );
+        assert_eq!(offer.credential_configuration_ids, [DOCTYPE]);
+        let grant = offer.grants.unwrap().pre_authorized_code.unwrap();
+        assert_eq!(grant.pre_authorized_code, response.pre_authorized_code);
+        assert!(grant.tx_code.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_start_issuance_tx_code() {
+        let state = registration_state();
+
+        let response = state
+            .start_issuance(start_request(vec![unsigned_mdoc(DOCTYPE)], Some("123456".to_string())))
+            .await
+            .expect("starting issuance should succeed");
+
+        // The offer should describe the transaction code, without containing it.
+        let CredentialOfferContainer::Value(offer) =
+            CredentialOfferContainer::from_uri(&response.credential_offer_url).unwrap()
+        else {
+            panic!("credential offer should be passed by value")
+        };
+
+        assert_eq!(offer.tx_code(), Some(&TxCode::new_for("123456", None)));
+        assert!(!response.credential_offer_url.as_str().contains("123456"));
+    }
+
+    #[tokio::test]
+    async fn test_approve_deferred_issuance() {
+        let state = registration_state();
+        let issuer = &state.application_state.issuer;
+
+        let response = state
+            .start_issuance(StartIssuanceRequest {
+                deferred: true,
+                ..start_request(vec![unsigned_mdoc(DOCTYPE)], None)
+            })
+            .await
+            .expect("starting deferred issuance should succeed");
+
+        issuer
+            .approve_deferred_issuance(response.pre_authorized_code)
+            .await
+            .expect("approving deferred issuance should succeed");
+
+        let error = issuer
+            .approve_deferred_issuance("unknown".to_string().into())
+            .await
+            .expect_err("approving an unknown session should fail");
+        assert_matches!(
+            ApproveIssuanceErrorCode::from(&error),
+            ApproveIssuanceErrorCode::UnknownSession
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_issuance_error() {
+        let state = registration_state();
+
+        let error = state
+            .start_issuance(start_request(vec![], None))
+            .await
+            .expect_err("starting issuance without attestations should fail");
+        assert_matches!(error, StartIssuanceError::NoAttestations);
+
+        let error = state
+            .start_issuance(start_request(vec![unsigned_mdoc("com.example.unknown")], None))
+            .await
+            .expect_err("starting issuance of an unknown doctype should fail");
+        assert_matches!(error, StartIssuanceError::MissingPrivateKey(doc_type) if doc_type == "com.example.unknown");
+
+        let error = state
+            .start_issuance(start_request(vec![unsigned_mdoc(DOCTYPE)], Some(String::new())))
+            .await
+            .expect_err("starting issuance with an empty transaction code should fail");
+        assert_matches!(error, StartIssuanceError::EmptyTxCode);
+    }
+}
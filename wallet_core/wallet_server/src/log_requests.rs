@@ -5,7 +5,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use base64::prelude::*;
-use http::{HeaderMap, HeaderValue, Method, StatusCode, Uri, Version};
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri, Version};
 
 use openid4vc::disclosure_session::APPLICATION_OAUTH_AUTHZ_REQ_JWT;
 use wallet_common::http_error::APPLICATION_PROBLEM_JSON;
@@ -19,6 +19,17 @@ pub(crate) async fn log_request_response(req: Request, next: Next) -> Result<imp
 
     let res = next.run(req).await;
 
+    // Streaming responses cannot be buffered for logging, as they may never end.
+    if is_event_stream(res.headers()) {
+        tracing::debug!(
+            "response:\n{:?} {}\n{}\n\n[event stream]",
+            res.version(),
+            res.status(),
+            print_headers(res.headers())
+        );
+        return Ok(res);
+    }
+
     let (parts, body) = res.into_parts();
     let bytes = log_response(body, parts.status, &parts.headers, &parts.version).await?;
     let res = Response::from_parts(parts, Body::from(bytes));
@@ -32,6 +43,13 @@ async fn body_to_bytes(body: Body) -> Result<Bytes, (StatusCode, String)> {
         .map_err(|err| (StatusCode::BAD_REQUEST, format!("failed to read body: {}", err)))
 }
 
+fn is_event_stream(headers: &HeaderMap<HeaderValue>) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with(mime::TEXT_EVENT_STREAM.as_ref()))
+}
+
 fn print_headers(headers: &HeaderMap<HeaderValue>) -> String {
     headers
         .iter()
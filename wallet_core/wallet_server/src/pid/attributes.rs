@@ -1,3 +1,7 @@
+use std::sync::Arc;
+
+use aes_gcm::{aead::Aead, Aes256Gcm, Nonce};
+use base64::prelude::*;
 use indexmap::IndexMap;
 
 use nl_wallet_mdoc::{unsigned::UnsignedMdoc, utils::x509::Certificate};
@@ -8,9 +12,12 @@ use openid4vc::{
     token::{AttestationPreview, TokenRequest, TokenRequestGrantType},
     ErrorResponse, TokenErrorCode,
 };
-use wallet_common::{nonempty::NonEmpty, urls::BaseUrl};
+use wallet_common::{health::HealthChecker, nonempty::NonEmpty, urls::BaseUrl, utils::random_bytes};
 
-use crate::pid::brp::client::{BrpClient, BrpError, HttpBrpClient};
+use crate::pid::{
+    brp::client::{BrpClient, BrpError, HttpBrpClient},
+    constants::{MOCK_PID_DOCTYPE, PID_BSN},
+};
 
 use super::digid::{self, OpenIdClient};
 
@@ -32,6 +39,16 @@ pub enum Error {
     MissingCertificate(String),
     #[error("error retrieving from BRP: {0}")]
     Brp(#[from] BrpError),
+    #[error("DigiD settings are required for PID issuance")]
+    MissingDigidSettings,
+    #[error("the BRP server is required for PID issuance")]
+    MissingBrpSettings,
+    #[error("a refresh subject key is required for PID issuance")]
+    MissingRefreshSubjectKey,
+    #[error("could not decrypt refresh subject")]
+    RefreshSubjectDecryption,
+    #[error("token request contains no authorization code")]
+    MissingAuthorizationCode,
 }
 
 pub struct AttributeCertificates {
@@ -58,8 +75,9 @@ impl AttributeCertificates {
 
 pub struct BrpPidAttributeService {
     brp_client: HttpBrpClient,
-    openid_client: OpenIdClient,
+    openid_client: Arc<OpenIdClient>,
     certificates: AttributeCertificates,
+    refresh_subject_key: Aes256Gcm,
 }
 
 impl BrpPidAttributeService {
@@ -69,13 +87,36 @@ impl BrpPidAttributeService {
         bsn_privkey: String,
         trust_anchors: Vec<reqwest::Certificate>,
         certificates: IndexMap<String, Certificate>,
+        refresh_subject_key: Aes256Gcm,
     ) -> Result<Self, Error> {
         Ok(Self {
             brp_client,
-            openid_client: OpenIdClient::new(issuer_url, bsn_privkey, trust_anchors)?,
+            openid_client: Arc::new(OpenIdClient::new(issuer_url, bsn_privkey, trust_anchors)?),
             certificates: AttributeCertificates::new(certificates),
+            refresh_subject_key,
         })
     }
+
+    /// Return the [`HealthChecker`]s for the BRP and DigiD, which share the clients used by this service.
+    pub fn health_checkers(&self) -> Vec<Arc<dyn HealthChecker>> {
+        vec![Arc::new(self.brp_client.clone()), Arc::clone(&self.openid_client) as _]
+    }
+
+    async fn attributes_by_bsn(&self, bsn: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Error> {
+        let mut persons = self.brp_client.get_person_by_bsn(bsn).await?;
+
+        if persons.persons.len() != 1 {
+            return Err(Error::NoAttributesFound);
+        }
+
+        let person = persons.persons.remove(0);
+        let unsigned_mdocs: Vec<UnsignedMdoc> = person.into();
+        let previews = unsigned_mdocs
+            .into_iter()
+            .map(|unsigned| self.certificates.try_unsigned_mdoc_to_attestion_preview(unsigned))
+            .collect::<Result<Vec<AttestationPreview>, Error>>()?;
+        previews.try_into().map_err(|_| Error::NoAttributesFound)
+    }
 }
 
 impl AttributeService for BrpPidAttributeService {
@@ -88,25 +129,32 @@ impl AttributeService for BrpPidAttributeService {
     ) -> Result<NonEmpty<Vec<AttestationPreview>>, Error> {
         let openid_token_request = TokenRequest {
             grant_type: TokenRequestGrantType::AuthorizationCode {
-                code: token_request.code().clone(),
+                code: token_request.code().ok_or(Error::MissingAuthorizationCode)?.clone(),
             },
             ..token_request
         };
 
         let bsn = self.openid_client.bsn(openid_token_request).await?;
-        let mut persons = self.brp_client.get_person_by_bsn(&bsn).await?;
+        self.attributes_by_bsn(&bsn).await
+    }
 
-        if persons.persons.len() != 1 {
-            return Err(Error::NoAttributesFound);
-        }
+    /// The subject is the encrypted BSN contained in the PID, with which the current attributes are retrieved from the
+    /// BRP. The BSN is encrypted because the subject is kept for as long as the PID may be refreshed.
+    fn refresh_subject(&self, attestation_previews: &[AttestationPreview]) -> Option<String> {
+        attestation_previews
+            .iter()
+            .map(AsRef::<UnsignedMdoc>::as_ref)
+            .filter(|unsigned| unsigned.doc_type == MOCK_PID_DOCTYPE)
+            .filter_map(|unsigned| unsigned.attributes.as_ref().get(MOCK_PID_DOCTYPE))
+            .flatten()
+            .find(|entry| entry.name == PID_BSN)
+            .and_then(|entry| entry.value.as_text())
+            .and_then(|bsn| encrypt_bsn(&self.refresh_subject_key, bsn))
+    }
 
-        let person = persons.persons.remove(0);
-        let unsigned_mdocs: Vec<UnsignedMdoc> = person.into();
-        let previews = unsigned_mdocs
-            .into_iter()
-            .map(|unsigned| self.certificates.try_unsigned_mdoc_to_attestion_preview(unsigned))
-            .collect::<Result<Vec<AttestationPreview>, Error>>()?;
-        previews.try_into().map_err(|_| Error::NoAttributesFound)
+    async fn refreshed_attributes(&self, subject: &str) -> Result<NonEmpty<Vec<AttestationPreview>>, Error> {
+        let bsn = decrypt_bsn(&self.refresh_subject_key, subject)?;
+        self.attributes_by_bsn(&bsn).await
     }
 
     async fn oauth_metadata(&self, issuer_url: &BaseUrl) -> Result<oidc::Config, Error> {
@@ -115,3 +163,48 @@ impl AttributeService for BrpPidAttributeService {
         Ok(metadata)
     }
 }
+
+/// Encrypt the BSN using a random nonce, which is prepended to the ciphertext.
+fn encrypt_bsn(key: &Aes256Gcm, bsn: &str) -> Option<String> {
+    let nonce_bytes = random_bytes(12);
+    let ciphertext = key.encrypt(Nonce::from_slice(&nonce_bytes), bsn.as_bytes()).ok()?;
+
+    let subject = nonce_bytes.into_iter().chain(ciphertext).collect::<Vec<_>>();
+    Some(BASE64_URL_SAFE_NO_PAD.encode(subject))
+}
+
+fn decrypt_bsn(key: &Aes256Gcm, subject: &str) -> Result<String, Error> {
+    let subject = BASE64_URL_SAFE_NO_PAD
+        .decode(subject)
+        .map_err(|_| Error::RefreshSubjectDecryption)?;
+    if subject.len() < 12 {
+        return Err(Error::RefreshSubjectDecryption);
+    }
+
+    let (nonce, ciphertext) = subject.split_at(12);
+    let bsn = key
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::RefreshSubjectDecryption)?;
+    String::from_utf8(bsn).map_err(|_| Error::RefreshSubjectDecryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use aes_gcm::{aead::KeyInit, Aes256Gcm};
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_bsn() {
+        let key = Aes256Gcm::new_from_slice(&random_bytes(32)).unwrap();
+
+        let subject = encrypt_bsn(&key, "999991772").unwrap();
+        assert!(!subject.contains("999991772"));
+        assert_eq!(decrypt_bsn(&key, &subject).unwrap(), "999991772");
+
+        let other_key = Aes256Gcm::new_from_slice(&random_bytes(32)).unwrap();
+        assert_matches!(decrypt_bsn(&other_key, &subject), Err(Error::RefreshSubjectDecryption));
+        assert_matches!(decrypt_bsn(&key, "AAAA"), Err(Error::RefreshSubjectDecryption));
+    }
+}
@@ -1,9 +1,10 @@
-use futures::TryFutureExt;
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
 use reqwest::Response;
 use serde::Serialize;
 use url::ParseError;
 
 use wallet_common::{
+    health::{HealthCheckError, HealthChecker},
     http_error::HttpJsonErrorBody,
     reqwest::{default_reqwest_client_builder, is_problem_json_response},
     urls::BaseUrl,
@@ -27,6 +28,7 @@ pub trait BrpClient {
     async fn get_person_by_bsn(&self, bsn: &str) -> Result<BrpPersons, BrpError>;
 }
 
+#[derive(Clone)]
 pub struct HttpBrpClient {
     http_client: reqwest::Client,
     base_url: BaseUrl,
@@ -121,3 +123,23 @@ impl BrpClient for HttpBrpClient {
         Ok(body)
     }
 }
+
+/// Checks the availability of the BRP by calling the health endpoint of the server at the base URL.
+impl HealthChecker for HttpBrpClient {
+    fn name(&self) -> &'static str {
+        "brp"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), HealthCheckError>> {
+        async move {
+            self.http_client
+                .get(self.base_url.join("health"))
+                .send()
+                .await?
+                .error_for_status()?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -1,3 +1,4 @@
+use futures::{future::BoxFuture, FutureExt};
 use reqwest::Certificate;
 use serde::{Deserialize, Serialize};
 
@@ -10,7 +11,11 @@ use openid4vc::{
     },
     token::TokenRequest,
 };
-use wallet_common::{reqwest::trusted_reqwest_client_builder, urls::BaseUrl};
+use wallet_common::{
+    health::{HealthCheckError, HealthChecker},
+    reqwest::trusted_reqwest_client_builder,
+    urls::BaseUrl,
+};
 
 #[derive(Serialize, Deserialize)]
 struct UserInfo {
@@ -87,3 +92,19 @@ impl OpenIdClient {
         Ok(metadata)
     }
 }
+
+/// Checks the availability of the IdP by performing OpenID Connect discovery.
+impl HealthChecker for OpenIdClient {
+    fn name(&self) -> &'static str {
+        "digid"
+    }
+
+    fn check(&self) -> BoxFuture<'_, std::result::Result<(), HealthCheckError>> {
+        async move {
+            self.discover_metadata().await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
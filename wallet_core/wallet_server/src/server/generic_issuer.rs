@@ -0,0 +1,37 @@
+use anyhow::{anyhow, Result};
+
+use openid4vc::{issuer::IssuanceData, server_state::SessionStore};
+
+use super::*;
+use crate::{issuer::create_generic_issuance_routers, settings::Settings};
+
+pub async fn serve<IS>(
+    settings: Settings,
+    issuance_sessions: IS,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
+) -> Result<()>
+where
+    IS: SessionStore<IssuanceData> + Send + Sync + 'static,
+{
+    let log_requests = settings.log_requests;
+
+    let internal_server = settings
+        .issuer
+        .internal_server
+        .clone()
+        .ok_or_else(|| anyhow!("the internal server is required for generic issuance"))?;
+
+    let (wallet_issuance_router, internal_router) =
+        create_generic_issuance_routers(settings.urls, settings.issuer, issuance_sessions).await?;
+
+    listen(
+        settings.wallet_server,
+        internal_server,
+        Router::new().nest("/issuance", wallet_issuance_router),
+        Router::new().nest("/issuance", internal_router),
+        None,
+        health_checkers,
+        log_requests,
+    )
+    .await
+}
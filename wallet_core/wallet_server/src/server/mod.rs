@@ -1,40 +1,44 @@
-#[cfg(feature = "issuance")]
-pub mod pid_issuer;
-
 cfg_if::cfg_if! {
-    if #[cfg(feature = "disclosure")] {
-        pub mod verification_server;
-
-        use tower_http::validate_request::ValidateRequestHeaderLayer;
-
-        use crate::settings::{Authentication, RequesterAuth};
+    if #[cfg(feature = "issuance")] {
+        pub mod generic_issuer;
+        pub mod pid_issuer;
     }
 }
 
+#[cfg(feature = "disclosure")]
+pub mod verification_server;
+
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
+use crate::settings::{Authentication, RequesterAuth};
+
 #[cfg(all(feature = "disclosure", feature = "issuance"))]
 pub mod wallet_server;
 
-use std::{future::Future, io};
+use std::{future::Future, io, sync::Arc};
 
 use anyhow::Result;
-use axum::{routing::get, Router};
+use axum::Router;
 use http::{header, HeaderValue};
 use tokio::net::TcpListener;
 use tower_http::{set_header::SetResponseHeaderLayer, trace::TraceLayer};
 use tracing::{debug, level_filters::LevelFilter};
 use tracing_subscriber::EnvFilter;
 
+use wallet_common::{
+    health::{health_router, HealthChecker},
+    metrics,
+};
+
 use crate::{
     log_requests::log_request_response,
     settings::{Server, Settings},
 };
 
-fn health_router() -> Router {
-    Router::new().route("/health", get(|| async {}))
-}
-
-pub fn decorate_router(mut router: Router, log_requests: bool) -> Router {
-    router = router.merge(health_router());
+pub fn decorate_router(mut router: Router, health_checkers: Vec<Arc<dyn HealthChecker>>, log_requests: bool) -> Router {
+    router = metrics::record_metrics(router.merge(health_router(health_checkers)));
 
     router = router.layer(SetResponseHeaderLayer::overriding(
         header::CACHE_CONTROL,
@@ -54,7 +58,7 @@ async fn create_wallet_listener(wallet_server: Server) -> Result<TcpListener, io
 }
 
 /// Secure [requester_router] with an API key when required by [settings].
-#[cfg(feature = "disclosure")]
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
 fn secure_requester_router(requester_server: &RequesterAuth, requester_router: Router) -> Router {
     match requester_server {
         RequesterAuth::Authentication(Authentication::ApiKey(api_key))
@@ -66,8 +70,16 @@ fn secure_requester_router(requester_server: &RequesterAuth, requester_router: R
     }
 }
 
+/// Secure [admin_router] with the API key from [settings], which is always required.
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
+fn secure_admin_router(admin: &Authentication, admin_router: Router) -> Router {
+    match admin {
+        Authentication::ApiKey(api_key) => admin_router.layer(ValidateRequestHeaderLayer::bearer(api_key)),
+    }
+}
+
 /// Create Requester listener when required by [settings].
-#[cfg(feature = "disclosure")]
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
 async fn create_requester_listener(requester_server: &RequesterAuth) -> Result<Option<TcpListener>, io::Error> {
     match requester_server {
         RequesterAuth::Authentication(_) => None,
@@ -78,23 +90,32 @@ async fn create_requester_listener(requester_server: &RequesterAuth) -> Result<O
     .transpose()
 }
 
-#[cfg(feature = "disclosure")]
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
 async fn listen(
     wallet_server: Server,
     requester_server: RequesterAuth,
     mut wallet_router: Router,
     mut requester_router: Router,
+    admin: Option<(Authentication, Router)>,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
     log_requests: bool,
 ) -> Result<()> {
     let wallet_listener = create_wallet_listener(wallet_server).await?;
     let requester_listener = create_requester_listener(&requester_server).await?;
 
-    requester_router = secure_requester_router(&requester_server, requester_router);
+    // The metrics are served alongside the requester endpoints and are secured in the same way, so that these are
+    // never exposed publicly.
+    requester_router = secure_requester_router(&requester_server, requester_router.merge(metrics::metrics_router()));
+
+    // The admin endpoints are served alongside the requester endpoints, but are secured with their own API key.
+    if let Some((authentication, admin_router)) = admin {
+        requester_router = requester_router.merge(secure_admin_router(&authentication, admin_router));
+    }
 
     match requester_listener {
         Some(requester_listener) => {
-            wallet_router = decorate_router(wallet_router, log_requests);
-            requester_router = decorate_router(requester_router, log_requests);
+            wallet_router = decorate_router(wallet_router, health_checkers.clone(), log_requests);
+            requester_router = decorate_router(requester_router, health_checkers, log_requests);
 
             debug!(
                 "listening for requester on {}",
@@ -116,7 +137,7 @@ async fn listen(
             tokio::try_join!(requester_server, wallet_server)?;
         }
         None => {
-            wallet_router = decorate_router(wallet_router.merge(requester_router), log_requests);
+            wallet_router = decorate_router(wallet_router.merge(requester_router), health_checkers, log_requests);
             debug!(
                 "listening for wallet and requester on {}",
                 wallet_listener.local_addr().unwrap()
@@ -131,8 +152,13 @@ async fn listen(
 }
 
 #[cfg(feature = "issuance")]
-async fn listen_wallet_only(wallet_server: Server, mut wallet_router: Router, log_requests: bool) -> Result<()> {
-    wallet_router = decorate_router(wallet_router, log_requests);
+async fn listen_wallet_only(
+    wallet_server: Server,
+    mut wallet_router: Router,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
+    log_requests: bool,
+) -> Result<()> {
+    wallet_router = decorate_router(wallet_router, health_checkers, log_requests);
 
     let wallet_listener = create_wallet_listener(wallet_server).await?;
 
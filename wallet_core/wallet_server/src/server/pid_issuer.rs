@@ -5,20 +5,38 @@ use openid4vc::{issuer::AttributeService, server_state::SessionStore};
 use super::*;
 use crate::{issuer::create_issuance_router, settings::Settings};
 
-pub async fn serve<A, IS>(attr_service: A, settings: Settings, issuance_sessions: IS) -> Result<()>
+pub async fn serve<A, IS>(
+    attr_service: A,
+    settings: Settings,
+    issuance_sessions: IS,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
+) -> Result<()>
 where
     A: AttributeService + Send + Sync + 'static,
     IS: SessionStore<openid4vc::issuer::IssuanceData> + Send + Sync + 'static,
 {
     let log_requests = settings.log_requests;
 
+    let internal_server = settings.issuer.internal_server.clone();
     let wallet_issuance_router =
-        create_issuance_router(&settings.urls, settings.issuer, issuance_sessions, attr_service)?;
+        create_issuance_router(&settings.urls, settings.issuer, issuance_sessions, attr_service).await?;
+    let wallet_router = Router::new().nest("/issuance", wallet_issuance_router);
 
-    listen_wallet_only(
-        settings.wallet_server,
-        Router::new().nest("/issuance", wallet_issuance_router),
-        log_requests,
-    )
-    .await
+    // The PID issuer has no internal endpoints of its own, but when an internal server is configured the metrics are
+    // served on it.
+    match internal_server {
+        Some(internal_server) => {
+            listen(
+                settings.wallet_server,
+                internal_server,
+                wallet_router,
+                Router::new(),
+                None,
+                health_checkers,
+                log_requests,
+            )
+            .await
+        }
+        None => listen_wallet_only(settings.wallet_server, wallet_router, health_checkers, log_requests).await,
+    }
 }
@@ -5,13 +5,17 @@ use openid4vc::{server_state::SessionStore, verifier::DisclosureData};
 use super::*;
 use crate::{settings::Settings, verifier};
 
-pub async fn serve<S>(settings: Settings, disclosure_sessions: S) -> Result<()>
+pub async fn serve<S>(
+    settings: Settings,
+    disclosure_sessions: S,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
+) -> Result<()>
 where
     S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     let log_requests = settings.log_requests;
 
-    let (wallet_disclosure_router, requester_router) =
+    let (wallet_disclosure_router, requester_router, admin_router) =
         verifier::create_routers(settings.urls, settings.verifier, disclosure_sessions)?;
 
     listen(
@@ -19,6 +23,10 @@ where
         settings.requester_server,
         Router::new().nest("/disclosure", wallet_disclosure_router),
         Router::new().nest("/disclosure", requester_router),
+        settings
+            .admin
+            .map(|authentication| (authentication, Router::new().nest("/disclosure", admin_router))),
+        health_checkers,
         log_requests,
     )
     .await
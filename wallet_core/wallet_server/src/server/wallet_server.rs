@@ -10,6 +10,7 @@ pub async fn serve<A, DS, IS>(
     settings: Settings,
     disclosure_sessions: DS,
     issuance_sessions: IS,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
 ) -> Result<()>
 where
     A: AttributeService + Send + Sync + 'static,
@@ -19,8 +20,8 @@ where
     let log_requests = settings.log_requests;
 
     let wallet_issuance_router =
-        create_issuance_router(&settings.urls, settings.issuer, issuance_sessions, attr_service)?;
-    let (wallet_disclosure_router, requester_router) =
+        create_issuance_router(&settings.urls, settings.issuer, issuance_sessions, attr_service).await?;
+    let (wallet_disclosure_router, requester_router, admin_router) =
         verifier::create_routers(settings.urls, settings.verifier, disclosure_sessions)?;
 
     listen(
@@ -30,6 +31,10 @@ where
             .nest("/issuance", wallet_issuance_router)
             .nest("/disclosure", wallet_disclosure_router),
         Router::new().nest("/disclosure", requester_router),
+        settings
+            .admin
+            .map(|authentication| (authentication, Router::new().nest("/disclosure", admin_router))),
+        health_checkers,
         log_requests,
     )
     .await
@@ -5,7 +5,10 @@ use ring::hmac;
 use serde::Deserialize;
 use serde_with::{hex::Hex, serde_as};
 
-use openid4vc::verifier::{SessionTypeReturnUrl, UseCase, UseCases};
+use openid4vc::{
+    openid4vp::QueryLanguage,
+    verifier::{SessionTypeReturnUrl, UseCase, UseCases},
+};
 use wallet_common::trust_anchor::DerTrustAnchor;
 
 use super::*;
@@ -31,6 +34,8 @@ pub struct EhpemeralIdSecret(Vec<u8>);
 pub struct VerifierUseCase {
     #[serde(default)]
     pub session_type_return_url: SessionTypeReturnUrl,
+    #[serde(default)]
+    pub query_language: QueryLanguage,
     #[serde(flatten)]
     pub key_pair: KeyPair,
 }
@@ -58,7 +63,11 @@ impl TryFrom<&VerifierUseCase> for UseCase {
     type Error = anyhow::Error;
 
     fn try_from(value: &VerifierUseCase) -> Result<Self, Self::Error> {
-        let use_case = UseCase::try_new((&value.key_pair).try_into()?, value.session_type_return_url)?;
+        let use_case = UseCase::try_new(
+            (&value.key_pair).try_into()?,
+            value.session_type_return_url,
+            value.query_language,
+        )?;
 
         Ok(use_case)
     }
@@ -1,10 +1,13 @@
 use std::collections::HashMap;
 
+use aes_gcm::{aead::KeyInit, Aes256Gcm};
 use indexmap::IndexMap;
+use nutype::nutype;
 use serde::Deserialize;
+use serde_with::{hex::Hex, serde_as};
 
 use nl_wallet_mdoc::utils::x509::Certificate;
-use wallet_common::{reqwest::deserialize_certificates, urls::BaseUrl};
+use wallet_common::{account::serialization::DerVerifyingKey, reqwest::deserialize_certificates, urls::BaseUrl};
 
 use super::*;
 use crate::pid::{
@@ -12,11 +15,16 @@ use crate::pid::{
     brp::client::HttpBrpClient,
 };
 
+#[serde_as]
 #[derive(Clone, Deserialize)]
 pub struct Issuer {
     // Issuer private keys index per doctype
     pub private_keys: HashMap<String, KeyPair>,
 
+    /// Key in `private_keys` of the key pair with which the Credential Issuer metadata is signed. If absent, the
+    /// metadata is not signed.
+    pub metadata_signing_key: Option<String>,
+
     /// `client_id` values that this server accepts, identifying the wallet implementation (not individual instances,
     /// i.e., the `client_id` value of a wallet implementation will be constant across all wallets of that
     /// implementation).
@@ -24,9 +32,26 @@ pub struct Issuer {
     /// JWTs.
     pub wallet_client_ids: Vec<String>,
 
-    pub digid: Digid,
+    /// Public key of the Wallet Provider with which it signs wallet attestations. If present, wallets must include a
+    /// wallet attestation in their token and credential requests.
+    pub wallet_provider_public_key: Option<DerVerifyingKey>,
+
+    /// Required when issuing the PID using the [`BrpPidAttributeService`].
+    pub digid: Option<Digid>,
+
+    /// Required when issuing the PID using the [`BrpPidAttributeService`].
+    pub brp_server: Option<BaseUrl>,
 
-    pub brp_server: BaseUrl,
+    /// Hex-encoded 256-bit key with which the [`BrpPidAttributeService`] encrypts the BSN that it keeps for as long as
+    /// the PID may be refreshed. Required when issuing the PID using the [`BrpPidAttributeService`].
+    #[serde_as(as = "Option<Hex>")]
+    #[serde(default)]
+    pub refresh_subject_key: Option<RefreshSubjectKey>,
+
+    /// Server on which the issuing organisation registers the attestations to be issued. Required when running as a
+    /// generic issuer, in which case it is used like the `requester_server` of the verifier. When running as PID
+    /// issuer it is optional and only serves the metrics.
+    pub internal_server: Option<RequesterAuth>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -37,6 +62,15 @@ pub struct Digid {
     pub trust_anchors: Vec<reqwest::Certificate>,
 }
 
+#[nutype(validate(predicate = |v| v.len() == 32), derive(Clone, TryFrom, AsRef, Deserialize))]
+pub struct RefreshSubjectKey(Vec<u8>);
+
+impl From<&RefreshSubjectKey> for Aes256Gcm {
+    fn from(value: &RefreshSubjectKey) -> Self {
+        Aes256Gcm::new_from_slice(value.as_ref()).expect("refresh subject key should have a valid length")
+    }
+}
+
 impl Issuer {
     pub fn certificates(&self) -> IndexMap<String, Certificate> {
         self.private_keys
@@ -50,12 +84,20 @@ impl TryFrom<&Issuer> for BrpPidAttributeService {
     type Error = BrpError;
 
     fn try_from(issuer: &Issuer) -> Result<Self, Self::Error> {
+        let digid = issuer.digid.as_ref().ok_or(BrpError::MissingDigidSettings)?;
+        let brp_server = issuer.brp_server.as_ref().ok_or(BrpError::MissingBrpSettings)?;
+        let refresh_subject_key = issuer
+            .refresh_subject_key
+            .as_ref()
+            .ok_or(BrpError::MissingRefreshSubjectKey)?;
+
         BrpPidAttributeService::new(
-            HttpBrpClient::new(issuer.brp_server.clone()),
-            issuer.digid.issuer_url.clone(),
-            issuer.digid.bsn_privkey.clone(),
-            issuer.digid.trust_anchors.clone(),
+            HttpBrpClient::new(brp_server.clone()),
+            digid.issuer_url.clone(),
+            digid.bsn_privkey.clone(),
+            digid.trust_anchors.clone(),
             issuer.certificates(),
+            refresh_subject_key.into(),
         )
     }
 }
@@ -14,10 +14,12 @@ cfg_if::cfg_if! {
     if #[cfg(feature = "disclosure")] {
         mod disclosure;
         pub use disclosure::*;
-        use wallet_common::urls::DEFAULT_UNIVERSAL_LINK_BASE;
     }
 }
 
+#[cfg(any(feature = "disclosure", feature = "issuance"))]
+use wallet_common::urls::DEFAULT_UNIVERSAL_LINK_BASE;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "issuance")] {
         mod issuance;
@@ -30,7 +32,7 @@ pub struct Urls {
     // used by the wallet
     pub public_url: BaseUrl,
 
-    #[cfg(feature = "disclosure")]
+    #[cfg(any(feature = "disclosure", feature = "issuance"))]
     pub universal_link_base_url: BaseUrl,
 }
 
@@ -43,6 +45,10 @@ pub struct Settings {
     // if it conflicts with wallet_server, the application will crash on startup
     #[cfg(feature = "disclosure")]
     pub requester_server: RequesterAuth,
+    // used by operators to inspect and manage disclosure sessions, served next to the requester endpoints.
+    // these endpoints are only enabled when authentication is configured.
+    #[cfg(feature = "disclosure")]
+    pub admin: Option<Authentication>,
 
     #[serde(flatten)]
     pub urls: Urls,
@@ -89,7 +95,7 @@ pub enum Authentication {
 
 #[derive(Clone, Deserialize)]
 pub struct Storage {
-    /// Supported schemes are: `memory://` (default) and `postgres://`.
+    /// Supported schemes are: `memory://` (default), `postgres://` and `redis://` (or `rediss://` when using TLS).
     pub url: Url,
     pub expiration_minutes: NonZeroU64,
     pub successful_deletion_minutes: NonZeroU64,
@@ -156,16 +162,14 @@ impl Settings {
                 default_store_timeouts.failed_deletion.as_secs() / 60,
             )?;
 
-        #[cfg(feature = "disclosure")]
+        #[cfg(any(feature = "disclosure", feature = "issuance"))]
         let config_builder = config_builder.set_default("universal_link_base_url", DEFAULT_UNIVERSAL_LINK_BASE)?;
 
         #[cfg(feature = "issuance")]
-        let config_builder = config_builder
-            .set_default(
-                "issuer.wallet_client_ids",
-                vec![openid4vc::NL_WALLET_CLIENT_ID.to_string()],
-            )?
-            .set_default("issuer.brp_server", "http://localhost:3007/")?;
+        let config_builder = config_builder.set_default(
+            "issuer.wallet_client_ids",
+            vec![openid4vc::NL_WALLET_CLIENT_ID.to_string()],
+        )?;
 
         // Look for a config file that is in the same directory as Cargo.toml if run through cargo,
         // otherwise look in the current working directory.
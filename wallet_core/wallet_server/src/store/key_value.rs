@@ -0,0 +1,466 @@
+use std::{
+    future::Future,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use openid4vc::server_state::{
+    saturating_add, Expirable, HasProgress, Progress, ProgressStatus, SessionCounts, SessionState, SessionStore,
+    SessionStoreError, SessionStoreTimeouts, SessionToken,
+};
+use wallet_common::generator::{Generator, TimeGenerator};
+
+use super::{SessionDataType, SessionUpdates};
+
+/// The minimum time to live for a key, as a key-value store may reject a time to live of zero.
+const MIN_TTL: Duration = Duration::from_millis(1);
+
+/// The value of the keys that mark sessions as pending, see [`KeyValueSessionStore::list_pending()`].
+const PENDING_VALUE: &str = "pending";
+
+pub type KeyValueStoreError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The condition under which [`KeyValueStore::set()`] should write a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetCondition {
+    NotExists,
+    Equals(String),
+}
+
+/// A key-value store with support for expiring keys and publishing messages, e.g. Redis. Any implementation should
+/// perform [`KeyValueStore::set()`] and [`KeyValueStore::delete()`] atomically, so that these can be used to
+/// compare-and-set or compare-and-delete a value.
+pub trait KeyValueStore {
+    fn get(&self, key: &str) -> impl Future<Output = Result<Option<String>, KeyValueStoreError>> + Send;
+
+    /// Return all keys that start with `prefix` and have not expired.
+    fn keys(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>, KeyValueStoreError>> + Send;
+
+    /// Set the value of a key that is deleted after `ttl`, but only if `condition` holds for the current value. The
+    /// returned boolean indicates if the value was written.
+    fn set(
+        &self,
+        key: &str,
+        value: String,
+        ttl: Duration,
+        condition: SetCondition,
+    ) -> impl Future<Output = Result<bool, KeyValueStoreError>> + Send;
+
+    /// Delete a key, but only if its current value equals `expected`. The returned boolean indicates if the key was
+    /// deleted.
+    fn delete(&self, key: &str, expected: String) -> impl Future<Output = Result<bool, KeyValueStoreError>> + Send;
+
+    /// Publish a message to all subscribers, including those of other instances sharing the same store.
+    fn publish(&self, message: String) -> impl Future<Output = Result<(), KeyValueStoreError>> + Send;
+
+    fn subscribe(&self) -> broadcast::Receiver<String>;
+
+    /// Check if the store can be reached.
+    fn ping(&self) -> impl Future<Output = Result<(), KeyValueStoreError>> + Send;
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession<T> {
+    data: T,
+    last_active: DateTime<Utc>,
+    version: u64,
+}
+
+/// Implementation of [`SessionStore`] on top of a [`KeyValueStore`]. Instead of periodically cleaning up sessions,
+/// every session is written with a time to live that matches its state. Active sessions that should have expired are
+/// expired when they are read using [`SessionStore::get()`], or by [`SessionStore::cleanup()`]. Pending sessions are
+/// additionally marked by a separate key, so that these can be listed without reading every session.
+#[derive(Debug)]
+pub struct KeyValueSessionStore<K, G = TimeGenerator> {
+    pub timeouts: SessionStoreTimeouts,
+    time: G,
+    store: Arc<K>,
+    updates: Arc<SessionUpdates>,
+}
+
+// Implement `Clone` manually, as deriving it would require `K` to be `Clone`.
+impl<K, G: Clone> Clone for KeyValueSessionStore<K, G> {
+    fn clone(&self) -> Self {
+        Self {
+            timeouts: self.timeouts,
+            time: self.time.clone(),
+            store: Arc::clone(&self.store),
+            updates: Arc::clone(&self.updates),
+        }
+    }
+}
+
+impl<K: KeyValueStore> KeyValueSessionStore<K> {
+    pub fn new(store: K, timeouts: SessionStoreTimeouts) -> Self {
+        Self::new_with_time(store, timeouts, TimeGenerator)
+    }
+}
+
+impl<K: KeyValueStore, G> KeyValueSessionStore<K, G> {
+    pub fn new_with_time(store: K, timeouts: SessionStoreTimeouts, time: G) -> Self {
+        let updates = Arc::new(SessionUpdates::default());
+        tokio::spawn(forward_messages(store.subscribe(), Arc::downgrade(&updates)));
+
+        Self {
+            timeouts,
+            time,
+            store: Arc::new(store),
+            updates,
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), KeyValueStoreError> {
+        self.store.ping().await
+    }
+}
+
+/// Forward the messages published on the key-value store to the subscribers of the session type they contain. This
+/// stops once the session store, and all of its clones, have been dropped.
+async fn forward_messages(mut messages: broadcast::Receiver<String>, updates: Weak<SessionUpdates>) {
+    loop {
+        let message = messages.recv().await;
+
+        let Some(updates) = updates.upgrade() else {
+            break;
+        };
+
+        match message {
+            Ok(message) => updates.notify(&message),
+            Err(RecvError::Lagged(count)) => warn!("missed {} session state notifications", count),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+impl<K, G> KeyValueSessionStore<K, G>
+where
+    K: KeyValueStore,
+    G: Generator<DateTime<Utc>>,
+{
+    fn key_prefix<T: SessionDataType>() -> String {
+        format!("session_state:{}:", T::TYPE)
+    }
+
+    fn key<T: SessionDataType>(token: &SessionToken) -> String {
+        format!("{}{}", Self::key_prefix::<T>(), token)
+    }
+
+    fn pending_key_prefix<T: SessionDataType>() -> String {
+        format!("session_pending:{}:", T::TYPE)
+    }
+
+    fn pending_key<T: SessionDataType>(token: &SessionToken) -> String {
+        format!("{}{}", Self::pending_key_prefix::<T>(), token)
+    }
+
+    /// Determine how long a session should be kept, based on its state and the moment it was last active.
+    fn ttl<T: HasProgress + Expirable>(&self, session: &SessionState<T>) -> Duration {
+        let timeout = if session.data.is_expired() {
+            self.timeouts.failed_deletion
+        } else {
+            match session.data.progress() {
+                // An active session should be deleted once it would have been expired for the "failed_deletion"
+                // timeout.
+                Progress::Active => self
+                    .timeouts
+                    .expiration_for(&session.data)
+                    .saturating_add(self.timeouts.failed_deletion),
+                // A succeeded session with its own expiration is kept until it expires.
+                Progress::Finished { has_succeeded: true } => session
+                    .data
+                    .expiration()
+                    .map_or(self.timeouts.successful_deletion, |expiration| {
+                        expiration.max(self.timeouts.successful_deletion)
+                    }),
+                Progress::Finished { has_succeeded: false } => self.timeouts.failed_deletion,
+            }
+        };
+
+        (saturating_add(session.last_active, timeout) - self.time.generate())
+            .to_std()
+            .unwrap_or_default()
+            .max(MIN_TTL)
+    }
+
+    fn deserialize<T: DeserializeOwned>(value: &str) -> Result<StoredSession<T>, SessionStoreError> {
+        serde_json::from_str(value).map_err(|e| SessionStoreError::Deserialize(e.into()))
+    }
+
+    /// Whether an active session should have expired by now, in which case it still needs to be expired.
+    fn should_expire<T: HasProgress + Expirable>(&self, data: &T, last_active: DateTime<Utc>) -> bool {
+        !data.is_expired()
+            && matches!(data.progress(), Progress::Active)
+            && saturating_add(last_active, self.timeouts.expiration_for(data)) < self.time.generate()
+    }
+
+    /// Read all sessions of type `T` as they are stored, i.e. without expiring any of them. Sessions that were deleted
+    /// after listing the keys are simply skipped.
+    async fn read_all<T>(&self) -> Result<Vec<SessionState<T>>, SessionStoreError>
+    where
+        T: SessionDataType + DeserializeOwned,
+    {
+        let prefix = Self::key_prefix::<T>();
+        let keys = self.store.keys(&prefix).await.map_err(SessionStoreError::Other)?;
+
+        let mut sessions = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some(value) = self.store.get(&key).await.map_err(SessionStoreError::Other)? else {
+                continue;
+            };
+
+            let StoredSession {
+                data,
+                last_active,
+                version,
+            } = Self::deserialize::<T>(&value)?;
+            sessions.push(SessionState {
+                data,
+                token: SessionToken::from(key[prefix.len()..].to_string()),
+                last_active,
+                version,
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    /// Write the session to the store if `condition` holds and publish that it was updated. The returned boolean
+    /// indicates if the session was written.
+    async fn set<T>(&self, session: &SessionState<T>, condition: SetCondition) -> Result<bool, SessionStoreError>
+    where
+        T: HasProgress + Expirable + SessionDataType + Serialize + DeserializeOwned,
+    {
+        let value = serde_json::to_string(&StoredSession {
+            data: &session.data,
+            last_active: session.last_active,
+            version: session.version,
+        })
+        .map_err(|e| SessionStoreError::Serialize(Box::new(e)))?;
+
+        let is_written = self
+            .store
+            .set(&Self::key::<T>(&session.token), value, self.ttl(session), condition)
+            .await
+            .map_err(SessionStoreError::Other)?;
+
+        if is_written {
+            self.mark_pending(session).await?;
+
+            self.store
+                .publish(SessionUpdates::message(T::TYPE, &session.token))
+                .await
+                .map_err(SessionStoreError::Other)?;
+        }
+
+        Ok(is_written)
+    }
+
+    /// Create or delete the key that marks the session as pending, depending on whether it is. The key expires along
+    /// with the session, as both are written with a time to live based on the moment the session was last active.
+    async fn mark_pending<T>(&self, session: &SessionState<T>) -> Result<(), SessionStoreError>
+    where
+        T: HasProgress + Expirable + SessionDataType + DeserializeOwned,
+    {
+        let pending_key = Self::pending_key::<T>(&session.token);
+
+        if !session.data.is_pending() {
+            self.store
+                .delete(&pending_key, PENDING_VALUE.to_string())
+                .await
+                .map_err(SessionStoreError::Other)?;
+
+            // The session may have become pending again in the meantime, in which case the key that was just deleted
+            // should be present after all.
+            let current_value = self
+                .store
+                .get(&Self::key::<T>(&session.token))
+                .await
+                .map_err(SessionStoreError::Other)?;
+            match current_value {
+                Some(value) if Self::deserialize::<T>(&value)?.data.is_pending() => (),
+                _ => return Ok(()),
+            }
+        }
+
+        // If the key is already present, it does not need to be written again as its time to live is the same.
+        self.store
+            .set(
+                &pending_key,
+                PENDING_VALUE.to_string(),
+                self.ttl(session),
+                SetCondition::NotExists,
+            )
+            .await
+            .map_err(SessionStoreError::Other)?;
+
+        Ok(())
+    }
+}
+
+impl<T, K, G> SessionStore<T> for KeyValueSessionStore<K, G>
+where
+    T: HasProgress + Expirable + SessionDataType + Serialize + DeserializeOwned + Send + Sync,
+    K: KeyValueStore + Send + Sync,
+    G: Generator<DateTime<Utc>> + Send + Sync,
+{
+    async fn get(&self, token: &SessionToken) -> Result<Option<SessionState<T>>, SessionStoreError> {
+        let key = Self::key::<T>(token);
+
+        loop {
+            let Some(value) = self.store.get(&key).await.map_err(SessionStoreError::Other)? else {
+                return Ok(None);
+            };
+
+            let StoredSession {
+                mut data,
+                last_active,
+                version,
+            } = Self::deserialize::<T>(&value)?;
+
+            if !self.should_expire(&data, last_active) {
+                return Ok(Some(SessionState {
+                    data,
+                    token: token.clone(),
+                    last_active,
+                    version,
+                }));
+            }
+
+            // Expire the session, but only if it was not updated in the meantime. Otherwise, simply read it again.
+            data.expire();
+            let session = SessionState {
+                data,
+                token: token.clone(),
+                last_active: self.time.generate(),
+                version: version + 1,
+            };
+
+            if self.set(&session, SetCondition::Equals(value)).await? {
+                return Ok(Some(session));
+            }
+        }
+    }
+
+    async fn write(&self, session: SessionState<T>, is_new: bool) -> Result<(), SessionStoreError> {
+        if is_new {
+            if !self.set(&session, SetCondition::NotExists).await? {
+                return Err(SessionStoreError::DuplicateToken(session.token));
+            }
+
+            return Ok(());
+        }
+
+        // Only update the session if its version matches the one that is stored, which is then incremented. As the
+        // stored value is compared as a whole, this also fails if the session was updated in the meantime.
+        let current_value = self
+            .store
+            .get(&Self::key::<T>(&session.token))
+            .await
+            .map_err(SessionStoreError::Other)?;
+        let current_value = match current_value {
+            Some(value) if Self::deserialize::<T>(&value)?.version == session.version => value,
+            _ => return Err(SessionStoreError::Conflict(session.token)),
+        };
+
+        let session = SessionState {
+            version: session.version + 1,
+            ..session
+        };
+
+        if !self.set(&session, SetCondition::Equals(current_value)).await? {
+            return Err(SessionStoreError::Conflict(session.token));
+        }
+
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), SessionStoreError> {
+        // Sessions are deleted by the key-value store itself, once their time to live has passed. Active sessions that
+        // should have expired are expired by reading them again using `get()`, which does this.
+        for session in self.read_all::<T>().await? {
+            if self.should_expire(&session.data, session.last_active) {
+                SessionStore::<T>::get(self, &session.token).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, status: Option<ProgressStatus>) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        let sessions = self
+            .read_all::<T>()
+            .await?
+            .into_iter()
+            .filter(|session| status.map_or(true, |status| ProgressStatus::of(&session.data) == status))
+            .collect();
+
+        Ok(sessions)
+    }
+
+    async fn list_pending(&self) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        let prefix = Self::pending_key_prefix::<T>();
+        let keys = self.store.keys(&prefix).await.map_err(SessionStoreError::Other)?;
+
+        let mut sessions = Vec::with_capacity(keys.len());
+        for key in keys {
+            let token = SessionToken::from(key[prefix.len()..].to_string());
+
+            // The key marking a session as pending is written separately, so check that the session itself is pending.
+            if let Some(session) = SessionStore::<T>::get(self, &token).await? {
+                if session.data.is_pending() {
+                    sessions.push(session);
+                }
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    async fn count(&self) -> Result<SessionCounts, SessionStoreError> {
+        let counts = self
+            .read_all::<T>()
+            .await?
+            .iter()
+            .fold(SessionCounts::default(), |mut counts, session| {
+                counts.add(ProgressStatus::of(&session.data), 1);
+                counts
+            });
+
+        Ok(counts)
+    }
+
+    async fn purge_finished(&self) -> Result<u64, SessionStoreError> {
+        let prefix = Self::key_prefix::<T>();
+        let keys = self.store.keys(&prefix).await.map_err(SessionStoreError::Other)?;
+
+        let mut count = 0;
+        for key in keys {
+            let Some(value) = self.store.get(&key).await.map_err(SessionStoreError::Other)? else {
+                continue;
+            };
+
+            // Note that an active session that should have expired is not purged here, as it is only expired by
+            // `cleanup()`. Sessions that are still pending are kept as well.
+            let data = Self::deserialize::<T>(&value)?.data;
+            if !ProgressStatus::of(&data).is_finished() || data.is_pending() {
+                continue;
+            }
+
+            // Only delete the session if it was not updated in the meantime.
+            if self.store.delete(&key, value).await.map_err(SessionStoreError::Other)? {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SessionToken> {
+        self.updates.subscribe(T::TYPE)
+    }
+}
@@ -5,13 +5,53 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(feature = "redis")] {
+        pub mod key_value;
+        pub mod redis;
+        use key_value::KeyValueSessionStore;
+        use self::redis::RedisKeyValueStore;
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "redis"))]
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::{future::BoxFuture, FutureExt};
+
+#[cfg(any(feature = "postgres", feature = "redis"))]
+use parking_lot::Mutex;
 use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::broadcast;
+#[cfg(any(feature = "postgres", feature = "redis"))]
+use tracing::warn;
 use url::Url;
 
+#[cfg(any(feature = "postgres", feature = "redis"))]
+use openid4vc::server_state::SESSION_UPDATES_CAPACITY;
 use openid4vc::server_state::{
-    Expirable, HasProgress, MemorySessionStore, SessionState, SessionStore, SessionStoreError, SessionStoreTimeouts,
-    SessionToken,
+    Expirable, HasProgress, MemorySessionStore, ProgressStatus, SessionCounts, SessionState, SessionStore,
+    SessionStoreError, SessionStoreTimeouts, SessionToken,
 };
+use wallet_common::health::{HealthCheckError, HealthChecker};
+
+/// The channel on which changes to session state are published by the session stores that use external storage.
+/// Every message has the format `<session type>/<session token>`.
+#[cfg(any(feature = "postgres", feature = "redis"))]
+const SESSION_STATE_CHANNEL: &str = "session_state";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionStoreVariantError {
+    #[error("unsupported session store URL scheme: {0}")]
+    UnsupportedScheme(String),
+    #[cfg(feature = "postgres")]
+    #[error("could not connect to PostgreSQL: {0}")]
+    Postgres(#[from] sea_orm::DbErr),
+    #[cfg(feature = "redis")]
+    #[error("could not connect to Redis: {0}")]
+    Redis(#[from] ::redis::RedisError),
+}
 
 pub trait SessionDataType {
     const TYPE: &'static str;
@@ -35,24 +75,67 @@ cfg_if::cfg_if! {
     }
 }
 
+/// Distributes the session updates published on [`SESSION_STATE_CHANNEL`] to the subscribers of each session type.
+#[cfg(any(feature = "postgres", feature = "redis"))]
+#[derive(Debug, Default)]
+struct SessionUpdates(Mutex<HashMap<String, broadcast::Sender<SessionToken>>>);
+
+#[cfg(any(feature = "postgres", feature = "redis"))]
+impl SessionUpdates {
+    #[cfg(feature = "redis")]
+    fn message(session_type: &str, token: &SessionToken) -> String {
+        format!("{session_type}/{token}")
+    }
+
+    fn subscribe(&self, session_type: &str) -> broadcast::Receiver<SessionToken> {
+        self.0
+            .lock()
+            .entry(session_type.to_string())
+            .or_insert_with(|| broadcast::channel(SESSION_UPDATES_CAPACITY).0)
+            .subscribe()
+    }
+
+    fn notify(&self, message: &str) {
+        let Some((session_type, token)) = message.split_once('/') else {
+            warn!("received invalid session state notification: {}", message);
+            return;
+        };
+
+        if let Some(sender) = self.0.lock().get(session_type) {
+            // Sending only fails when there are no subscribers, which is fine.
+            let _ = sender.send(token.to_string().into());
+        }
+    }
+}
+
 /// This enum effectively switches between the different types that implement `DisclosureSessionStore`,
 /// by implementing this trait itself and forwarding the calls to the type contained in the invariant.
 pub enum SessionStoreVariant<T> {
     #[cfg(feature = "postgres")]
     Postgres(PostgresSessionStore),
+    #[cfg(feature = "redis")]
+    Redis(KeyValueSessionStore<RedisKeyValueStore>),
     Memory(MemorySessionStore<T>),
 }
 
 impl<T> SessionStoreVariant<T> {
-    pub async fn new(url: Url, timeouts: SessionStoreTimeouts) -> Result<SessionStoreVariant<T>, anyhow::Error> {
+    pub async fn new(
+        url: Url,
+        timeouts: SessionStoreTimeouts,
+    ) -> Result<SessionStoreVariant<T>, SessionStoreVariantError> {
         match url.scheme() {
             #[cfg(feature = "postgres")]
             "postgres" => {
                 let store = PostgresSessionStore::try_new(url, timeouts).await?;
                 Ok(SessionStoreVariant::Postgres(store))
             }
+            #[cfg(feature = "redis")]
+            "redis" | "rediss" => {
+                let store = RedisKeyValueStore::try_new(url).await?;
+                Ok(SessionStoreVariant::Redis(KeyValueSessionStore::new(store, timeouts)))
+            }
             "memory" => Ok(SessionStoreVariant::Memory(MemorySessionStore::new(timeouts))),
-            e => unimplemented!("{}", e),
+            scheme => Err(SessionStoreVariantError::UnsupportedScheme(scheme.to_string())),
         }
     }
 
@@ -63,11 +146,41 @@ impl<T> SessionStoreVariant<T> {
         match self {
             #[cfg(feature = "postgres")]
             SessionStoreVariant::Postgres(store) => SessionStoreVariant::Postgres(store.clone()),
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(store) => SessionStoreVariant::Redis(store.clone()),
             SessionStoreVariant::Memory(MemorySessionStore { timeouts, .. }) => {
                 SessionStoreVariant::Memory(MemorySessionStore::new(*timeouts))
             }
         }
     }
+
+    /// Return a [`HealthChecker`] that checks the connection to the external storage of this [SessionStoreVariant],
+    /// reusing its connection pool.
+    pub fn health_checker(&self) -> Arc<dyn HealthChecker> {
+        Arc::new(self.clone_into::<()>())
+    }
+}
+
+impl<T: Send + Sync> HealthChecker for SessionStoreVariant<T> {
+    fn name(&self) -> &'static str {
+        "session_store"
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), HealthCheckError>> {
+        async move {
+            match self {
+                #[cfg(feature = "postgres")]
+                SessionStoreVariant::Postgres(postgres) => postgres.ping().await?,
+                #[cfg(feature = "redis")]
+                SessionStoreVariant::Redis(redis) => redis.ping().await?,
+                // Sessions are stored in memory, which is always available.
+                SessionStoreVariant::Memory(_) => (),
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
 }
 
 impl<T> SessionStore<T> for SessionStoreVariant<T>
@@ -78,6 +191,8 @@ where
         match self {
             #[cfg(feature = "postgres")]
             SessionStoreVariant::Postgres(postgres) => postgres.get(token).await,
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => redis.get(token).await,
             SessionStoreVariant::Memory(memory) => memory.get(token).await,
         }
     }
@@ -86,6 +201,8 @@ where
         match self {
             #[cfg(feature = "postgres")]
             SessionStoreVariant::Postgres(postgres) => postgres.write(session, is_new).await,
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => redis.write(session, is_new).await,
             SessionStoreVariant::Memory(memory) => memory.write(session, is_new).await,
         }
     }
@@ -96,7 +213,92 @@ where
             SessionStoreVariant::Postgres(postgres) => {
                 <PostgresSessionStore as SessionStore<T>>::cleanup(postgres).await
             }
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => {
+                <KeyValueSessionStore<RedisKeyValueStore> as SessionStore<T>>::cleanup(redis).await
+            }
             SessionStoreVariant::Memory(memory) => memory.cleanup().await,
         }
     }
+
+    async fn list(&self, status: Option<ProgressStatus>) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            SessionStoreVariant::Postgres(postgres) => postgres.list(status).await,
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => redis.list(status).await,
+            SessionStoreVariant::Memory(memory) => memory.list(status).await,
+        }
+    }
+
+    async fn list_pending(&self) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            SessionStoreVariant::Postgres(postgres) => postgres.list_pending().await,
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => redis.list_pending().await,
+            SessionStoreVariant::Memory(memory) => memory.list_pending().await,
+        }
+    }
+
+    async fn count(&self) -> Result<SessionCounts, SessionStoreError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            SessionStoreVariant::Postgres(postgres) => <PostgresSessionStore as SessionStore<T>>::count(postgres).await,
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => {
+                <KeyValueSessionStore<RedisKeyValueStore> as SessionStore<T>>::count(redis).await
+            }
+            SessionStoreVariant::Memory(memory) => memory.count().await,
+        }
+    }
+
+    async fn purge_finished(&self) -> Result<u64, SessionStoreError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            SessionStoreVariant::Postgres(postgres) => {
+                <PostgresSessionStore as SessionStore<T>>::purge_finished(postgres).await
+            }
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => {
+                <KeyValueSessionStore<RedisKeyValueStore> as SessionStore<T>>::purge_finished(redis).await
+            }
+            SessionStoreVariant::Memory(memory) => memory.purge_finished().await,
+        }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SessionToken> {
+        match self {
+            #[cfg(feature = "postgres")]
+            SessionStoreVariant::Postgres(postgres) => <PostgresSessionStore as SessionStore<T>>::subscribe(postgres),
+            #[cfg(feature = "redis")]
+            SessionStoreVariant::Redis(redis) => {
+                <KeyValueSessionStore<RedisKeyValueStore> as SessionStore<T>>::subscribe(redis)
+            }
+            SessionStoreVariant::Memory(memory) => memory.subscribe(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_store_variant_unsupported_scheme() {
+        let error = SessionStoreVariant::<()>::new(
+            "unsupported://localhost".parse().unwrap(),
+            SessionStoreTimeouts::default(),
+        )
+        .await
+        .err()
+        .expect("should return error");
+
+        assert_matches!(
+            error,
+            SessionStoreVariantError::UnsupportedScheme(scheme) if scheme == "unsupported"
+        );
+    }
 }
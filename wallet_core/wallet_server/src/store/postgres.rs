@@ -1,56 +1,44 @@
-use std::time::Duration;
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
 use chrono::{DateTime, Utc};
 use sea_orm::{
-    sea_query::{Expr, OnConflict},
-    ActiveValue, ColumnTrait, ConnectOptions, Database, DatabaseConnection, DbErr, EntityTrait, QueryFilter, SqlErr,
-    TransactionTrait,
+    sea_query::Expr, ActiveValue, ColumnTrait, Condition, ConnectOptions, Database, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QuerySelect, RuntimeErr, SqlErr, TransactionTrait,
 };
 use serde::{de::DeserializeOwned, Serialize};
-use strum::{Display, EnumString};
-use tracing::log::LevelFilter;
+use sqlx::postgres::PgListener;
+use tokio::{sync::broadcast, time};
+use tracing::{log::LevelFilter, warn};
 use url::Url;
 
 use openid4vc::server_state::{
-    Expirable, HasProgress, Progress, SessionState, SessionStore, SessionStoreError, SessionStoreTimeouts, SessionToken,
+    saturating_add, Expirable, HasProgress, ProgressStatus, SessionCounts, SessionState, SessionStore,
+    SessionStoreError, SessionStoreTimeouts, SessionToken,
 };
 use wallet_common::generator::{Generator, TimeGenerator};
 
 use crate::entity::session_state;
 
-use super::SessionDataType;
+use super::{SessionDataType, SessionUpdates, SESSION_STATE_CHANNEL};
 
 const DB_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
-#[derive(Debug, Clone, Copy, Display, EnumString)]
-#[strum(serialize_all = "snake_case")]
-enum SessionStatus {
-    Active,
-    Succeeded,
-    Failed,
-    Expired,
-}
-
-impl From<Progress> for SessionStatus {
-    fn from(value: Progress) -> Self {
-        match value {
-            Progress::Active => Self::Active,
-            Progress::Finished { has_succeeded } if has_succeeded => Self::Succeeded,
-            Progress::Finished { .. } => Self::Failed,
-        }
-    }
-}
+const LISTENER_RETRY_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone)]
 pub struct PostgresSessionStore<G = TimeGenerator> {
     pub timeouts: SessionStoreTimeouts,
     time: G,
     connection: DatabaseConnection,
+    updates: Arc<SessionUpdates>,
 }
 
 impl<G> PostgresSessionStore<G> {
     pub async fn try_new_with_time(url: Url, timeouts: SessionStoreTimeouts, time: G) -> Result<Self, DbErr> {
-        let mut connection_options = ConnectOptions::new(url);
+        let mut connection_options = ConnectOptions::new(url.clone());
         connection_options
             .connect_timeout(DB_CONNECT_TIMEOUT)
             .sqlx_logging(true)
@@ -58,10 +46,22 @@ impl<G> PostgresSessionStore<G> {
 
         let connection = Database::connect(connection_options).await?;
 
+        let mut listener = PgListener::connect(url.as_str())
+            .await
+            .map_err(|e| DbErr::Conn(RuntimeErr::SqlxError(e)))?;
+        listener
+            .listen(SESSION_STATE_CHANNEL)
+            .await
+            .map_err(|e| DbErr::Conn(RuntimeErr::SqlxError(e)))?;
+
+        let updates = Arc::new(SessionUpdates::default());
+        tokio::spawn(forward_notifications(listener, Arc::downgrade(&updates)));
+
         let session_store = Self {
             timeouts,
             time,
             connection,
+            updates,
         };
 
         Ok(session_store)
@@ -74,6 +74,60 @@ impl PostgresSessionStore {
     }
 }
 
+/// Forward the notifications received from the database to the subscribers of the session type contained in the
+/// payload. This stops once the session store, and all of its clones, have been dropped.
+async fn forward_notifications(mut listener: PgListener, updates: Weak<SessionUpdates>) {
+    loop {
+        let notification = listener.recv().await;
+
+        let Some(updates) = updates.upgrade() else {
+            break;
+        };
+
+        match notification {
+            Ok(notification) => updates.notify(notification.payload()),
+            Err(error) => {
+                // The listener will try to reconnect on the next call to `recv()`.
+                warn!("error receiving session state notification: {}", error);
+                time::sleep(LISTENER_RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+impl<G> PostgresSessionStore<G> {
+    pub async fn ping(&self) -> Result<(), DbErr> {
+        self.connection.ping().await
+    }
+
+    fn decode_session_state<T>(state: session_state::Model) -> Result<SessionState<T>, SessionStoreError>
+    where
+        T: Expirable + DeserializeOwned,
+    {
+        // Decode both the status and data columns.
+        let status = state
+            .status
+            .parse::<ProgressStatus>()
+            .map_err(|e| SessionStoreError::Deserialize(e.into()))?;
+        let mut data = serde_json::from_value::<T>(state.data).map_err(|e| SessionStoreError::Deserialize(e.into()))?;
+
+        // If the status is expired, expire the data.
+        if matches!(status, ProgressStatus::Expired) {
+            data.expire();
+        }
+
+        // Otherwise, convert the remaining columns and return the session state.
+        let state = SessionState {
+            data,
+            token: state.token.into(),
+            last_active: state.last_active_date_time.into(),
+            version: state.version as u64,
+        };
+
+        Ok(state)
+    }
+}
+
 impl<T, G> SessionStore<T> for PostgresSessionStore<G>
 where
     T: HasProgress + Expirable + SessionDataType + Serialize + DeserializeOwned + Send,
@@ -86,67 +140,75 @@ where
             .await
             .map_err(|e| SessionStoreError::Other(e.into()))?;
 
-        state
-            .map(|state| {
-                // Decode both the status and data columns.
-                let status = state
-                    .status
-                    .parse::<SessionStatus>()
-                    .map_err(|e| SessionStoreError::Deserialize(e.into()))?;
-                let mut data =
-                    serde_json::from_value::<T>(state.data).map_err(|e| SessionStoreError::Deserialize(e.into()))?;
+        state.map(Self::decode_session_state).transpose()
+    }
 
-                // If the status is expired, expire the data.
-                if matches!(status, SessionStatus::Expired) {
-                    data.expire();
-                }
+    async fn write(&self, session: SessionState<T>, is_new: bool) -> Result<(), SessionStoreError> {
+        // Needed for potential SessionStoreError::DuplicateToken and SessionStoreError::Conflict.
+        let session_token = session.token.clone();
 
-                // Otherwise, convert the remaining columns and return the session state.
-                let state = SessionState {
-                    data,
-                    token: state.token.into(),
-                    last_active: state.last_active_date_time.into(),
-                };
+        let status = ProgressStatus::of(&session.data);
+        // Only store the moment of expiration if the session has its own expiration, otherwise the timeout is used.
+        let expiration_date_time = session
+            .data
+            .expiration()
+            .map(|expiration| saturating_add(session.last_active, expiration).fixed_offset());
+        let pending = session.data.is_pending();
+        let data = serde_json::to_value(session.data).map_err(|e| SessionStoreError::Serialize(Box::new(e)))?;
 
-                Ok(state)
+        if is_new {
+            // Insert new value, with data serialized to JSON.
+            session_state::Entity::insert(session_state::ActiveModel {
+                r#type: ActiveValue::set(T::TYPE.to_string()),
+                token: ActiveValue::set(session.token.into()),
+                data: ActiveValue::set(data),
+                status: ActiveValue::set(status.to_string()),
+                last_active_date_time: ActiveValue::set(session.last_active.into()),
+                version: ActiveValue::set(session.version as i64),
+                expiration_date_time: ActiveValue::set(expiration_date_time),
+                pending: ActiveValue::set(pending),
             })
-            .transpose()
-    }
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                // Handle a conflicting primary key, as updates are not allowed.
+                if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) {
+                    return SessionStoreError::DuplicateToken(session_token);
+                }
 
-    async fn write(&self, session: SessionState<T>, is_new: bool) -> Result<(), SessionStoreError> {
-        // Needed for potential SessionStoreError::DuplicateToken.
-        let session_token = session.token.clone();
+                SessionStoreError::Other(e.into())
+            })?;
 
-        // Insert new value, with data serialized to JSON.
-        let status = SessionStatus::from(session.data.progress()); // This cannot be `Expired`.
-        let query = session_state::Entity::insert(session_state::ActiveModel {
-            r#type: ActiveValue::set(T::TYPE.to_string()),
-            token: ActiveValue::set(session.token.into()),
-            data: ActiveValue::set(
-                serde_json::to_value(session.data).map_err(|e| SessionStoreError::Serialize(Box::new(e)))?,
-            ),
-            status: ActiveValue::set(status.to_string()),
-            last_active_date_time: ActiveValue::set(session.last_active.into()),
-        });
-
-        // If this is an existing session, an update is allowed.
-        let final_query = match is_new {
-            true => query,
-            false => query.on_conflict(
-                OnConflict::columns([session_state::PrimaryKey::Type, session_state::PrimaryKey::Token])
-                    .update_columns([session_state::Column::Data, session_state::Column::LastActiveDateTime])
-                    .to_owned(),
-            ),
-        };
+            return Ok(());
+        }
 
-        // Execute the query and handle a conflicting primary key when updates are not allowed.
-        final_query.exec(&self.connection).await.map_err(|e| {
-            if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) {
-                return SessionStoreError::DuplicateToken(session_token);
-            }
+        // Update the existing value, but only if it has not been updated since it was read.
+        let result = session_state::Entity::update_many()
+            .col_expr(session_state::Column::Data, Expr::value(data))
+            .col_expr(session_state::Column::Status, Expr::value(status.to_string()))
+            .col_expr(
+                session_state::Column::LastActiveDateTime,
+                Expr::value(session.last_active),
+            )
+            .col_expr(
+                session_state::Column::Version,
+                Expr::col(session_state::Column::Version).add(1),
+            )
+            .col_expr(
+                session_state::Column::ExpirationDateTime,
+                Expr::value(expiration_date_time),
+            )
+            .col_expr(session_state::Column::Pending, Expr::value(pending))
+            .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
+            .filter(session_state::Column::Token.eq(session_token.to_string()))
+            .filter(session_state::Column::Version.eq(session.version as i64))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| SessionStoreError::Other(e.into()))?;
 
-            SessionStoreError::Other(e.into())
-        })?;
+        if result.rows_affected == 0 {
+            return Err(SessionStoreError::Conflict(session_token));
+        }
 
         Ok(())
     }
@@ -160,11 +222,17 @@ where
         self.connection
             .transaction::<_, (), DbErr>(|transaction| {
                 Box::pin(async move {
-                    // Remove all succeeded sessions that are older than the "successful_deletion" timeout.
+                    // Remove all succeeded sessions that are older than the "successful_deletion" timeout, unless they
+                    // have their own expiration that has not yet passed.
                     session_state::Entity::delete_many()
                         .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
-                        .filter(session_state::Column::Status.eq(SessionStatus::Succeeded.to_string()))
+                        .filter(session_state::Column::Status.eq(ProgressStatus::Succeeded.to_string()))
                         .filter(session_state::Column::LastActiveDateTime.lt(succeeded_cutoff))
+                        .filter(
+                            Condition::any()
+                                .add(session_state::Column::ExpirationDateTime.is_null())
+                                .add(session_state::Column::ExpirationDateTime.lt(now)),
+                        )
                         .exec(transaction)
                         .await?;
 
@@ -173,23 +241,35 @@ where
                         .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
                         .filter(
                             session_state::Column::Status
-                                .is_in([SessionStatus::Failed.to_string(), SessionStatus::Expired.to_string()]),
+                                .is_in([ProgressStatus::Failed.to_string(), ProgressStatus::Expired.to_string()]),
                         )
                         .filter(session_state::Column::LastActiveDateTime.lt(failed_cutoff))
                         .exec(transaction)
                         .await?;
 
-                    // For all active sessions that are older than the "expiration" timeout,
-                    // update the last active time and set the status to expired.
+                    // For all active sessions that are older than the "expiration" timeout, or that have passed
+                    // their own expiration if set, update the last active time and set the status to expired.
                     session_state::Entity::update_many()
                         .col_expr(
                             session_state::Column::Status,
-                            Expr::value(SessionStatus::Expired.to_string()),
+                            Expr::value(ProgressStatus::Expired.to_string()),
                         )
                         .col_expr(session_state::Column::LastActiveDateTime, Expr::value(now))
+                        .col_expr(
+                            session_state::Column::Version,
+                            Expr::col(session_state::Column::Version).add(1),
+                        )
                         .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
-                        .filter(session_state::Column::Status.eq(SessionStatus::Active.to_string()))
-                        .filter(session_state::Column::LastActiveDateTime.lt(expiry_cutoff))
+                        .filter(session_state::Column::Status.eq(ProgressStatus::Active.to_string()))
+                        .filter(
+                            Condition::any()
+                                .add(
+                                    Condition::all()
+                                        .add(session_state::Column::ExpirationDateTime.is_null())
+                                        .add(session_state::Column::LastActiveDateTime.lt(expiry_cutoff)),
+                                )
+                                .add(session_state::Column::ExpirationDateTime.lt(now)),
+                        )
                         .exec(transaction)
                         .await?;
 
@@ -201,4 +281,75 @@ where
 
         Ok(())
     }
+
+    async fn list(&self, status: Option<ProgressStatus>) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        let mut query = session_state::Entity::find().filter(session_state::Column::Type.eq(T::TYPE.to_string()));
+        if let Some(status) = status {
+            query = query.filter(session_state::Column::Status.eq(status.to_string()));
+        }
+
+        query
+            .all(&self.connection)
+            .await
+            .map_err(|e| SessionStoreError::Other(e.into()))?
+            .into_iter()
+            .map(Self::decode_session_state)
+            .collect()
+    }
+
+    async fn list_pending(&self) -> Result<Vec<SessionState<T>>, SessionStoreError> {
+        session_state::Entity::find()
+            .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
+            .filter(session_state::Column::Pending.eq(true))
+            .all(&self.connection)
+            .await
+            .map_err(|e| SessionStoreError::Other(e.into()))?
+            .into_iter()
+            .map(Self::decode_session_state)
+            .collect()
+    }
+
+    async fn count(&self) -> Result<SessionCounts, SessionStoreError> {
+        let status_counts: Vec<(String, i64)> = session_state::Entity::find()
+            .select_only()
+            .column(session_state::Column::Status)
+            .column_as(session_state::Column::Token.count(), "count")
+            .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
+            .group_by(session_state::Column::Status)
+            .into_tuple()
+            .all(&self.connection)
+            .await
+            .map_err(|e| SessionStoreError::Other(e.into()))?;
+
+        status_counts
+            .into_iter()
+            .try_fold(SessionCounts::default(), |mut counts, (status, count)| {
+                let status = status
+                    .parse::<ProgressStatus>()
+                    .map_err(|e| SessionStoreError::Deserialize(e.into()))?;
+                counts.add(status, count as u64);
+
+                Ok(counts)
+            })
+    }
+
+    async fn purge_finished(&self) -> Result<u64, SessionStoreError> {
+        let result = session_state::Entity::delete_many()
+            .filter(session_state::Column::Type.eq(T::TYPE.to_string()))
+            .filter(session_state::Column::Status.is_in([
+                ProgressStatus::Succeeded.to_string(),
+                ProgressStatus::Failed.to_string(),
+                ProgressStatus::Expired.to_string(),
+            ]))
+            .filter(session_state::Column::Pending.eq(false))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| SessionStoreError::Other(e.into()))?;
+
+        Ok(result.rows_affected)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SessionToken> {
+        self.updates.subscribe(T::TYPE)
+    }
 }
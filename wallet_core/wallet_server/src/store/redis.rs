@@ -0,0 +1,208 @@
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use redis::{
+    aio::{ConnectionManager, PubSub},
+    Client, RedisError, Script, Value,
+};
+use tokio::{sync::broadcast, time};
+use tracing::warn;
+use url::Url;
+
+use openid4vc::server_state::SESSION_UPDATES_CAPACITY;
+
+use super::{
+    key_value::{KeyValueStore, KeyValueStoreError, SetCondition},
+    SESSION_STATE_CHANNEL,
+};
+
+const SUBSCRIBER_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sets the value of a key, but only if its current value equals the expected value.
+const COMPARE_AND_SET_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('SET', KEYS[1], ARGV[2], 'PX', ARGV[3])
+    return 1
+end
+return 0
+";
+
+/// Deletes a key, but only if its current value equals the expected value.
+const COMPARE_AND_DELETE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('DEL', KEYS[1])
+    return 1
+end
+return 0
+";
+
+/// The number of keys Redis should approximately return per iteration of the SCAN command.
+const SCAN_COUNT: usize = 100;
+
+/// Implementation of [`KeyValueStore`] using Redis, which publishes messages using Redis Pub/Sub.
+#[derive(Clone)]
+pub struct RedisKeyValueStore {
+    connection: ConnectionManager,
+    messages: Arc<broadcast::Sender<String>>,
+}
+
+impl RedisKeyValueStore {
+    pub async fn try_new(url: Url) -> Result<Self, RedisError> {
+        let client = Client::open(url.as_str())?;
+        let connection = ConnectionManager::new(client.clone()).await?;
+        let pubsub = subscribe(&client).await?;
+
+        let messages = Arc::new(broadcast::channel(SESSION_UPDATES_CAPACITY).0);
+        tokio::spawn(forward_messages(client, pubsub, Arc::downgrade(&messages)));
+
+        let store = Self { connection, messages };
+
+        Ok(store)
+    }
+}
+
+async fn subscribe(client: &Client) -> Result<PubSub, RedisError> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(SESSION_STATE_CHANNEL).await?;
+
+    Ok(pubsub)
+}
+
+/// Forward the messages published on Redis to the subscribers of the store, reconnecting when the connection is lost.
+/// This stops once the store, and all of its clones, have been dropped.
+async fn forward_messages(client: Client, mut pubsub: PubSub, messages: Weak<broadcast::Sender<String>>) {
+    loop {
+        {
+            let mut stream = pubsub.on_message();
+
+            while let Some(message) = stream.next().await {
+                let Some(messages) = messages.upgrade() else {
+                    return;
+                };
+
+                match message.get_payload::<String>() {
+                    // Sending only fails when there are no subscribers, which is fine.
+                    Ok(payload) => {
+                        let _ = messages.send(payload);
+                    }
+                    Err(error) => warn!("received invalid message from Redis: {}", error),
+                }
+            }
+        }
+
+        // The stream ends when the connection is lost, after which we try to reconnect.
+        warn!("lost connection to Redis while receiving session state notifications");
+
+        pubsub = loop {
+            time::sleep(SUBSCRIBER_RETRY_INTERVAL).await;
+
+            if messages.strong_count() == 0 {
+                return;
+            }
+
+            match subscribe(&client).await {
+                Ok(pubsub) => break pubsub,
+                Err(error) => warn!("could not reconnect to Redis: {}", error),
+            }
+        };
+    }
+}
+
+impl KeyValueStore for RedisKeyValueStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, KeyValueStoreError> {
+        let value = redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.connection.clone())
+            .await?;
+
+        Ok(value)
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, KeyValueStoreError> {
+        // Use SCAN instead of KEYS, as the latter blocks Redis while iterating over the entire key space.
+        let keys = redis::cmd("SCAN")
+            .cursor_arg(0)
+            .arg("MATCH")
+            .arg(format!("{}*", prefix))
+            .arg("COUNT")
+            .arg(SCAN_COUNT)
+            .clone()
+            .iter_async::<String>(&mut self.connection.clone())
+            .await?
+            .collect()
+            .await;
+
+        Ok(keys)
+    }
+
+    async fn set(
+        &self,
+        key: &str,
+        value: String,
+        ttl: Duration,
+        condition: SetCondition,
+    ) -> Result<bool, KeyValueStoreError> {
+        let mut connection = self.connection.clone();
+        let ttl_millis = ttl.as_millis() as u64;
+
+        let is_written = match condition {
+            SetCondition::NotExists => {
+                // The SET command returns nil if the key already exists.
+                let result: Value = redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("PX")
+                    .arg(ttl_millis)
+                    .arg("NX")
+                    .query_async(&mut connection)
+                    .await?;
+
+                !matches!(result, Value::Nil)
+            }
+            SetCondition::Equals(expected) => {
+                Script::new(COMPARE_AND_SET_SCRIPT)
+                    .key(key)
+                    .arg(expected)
+                    .arg(value)
+                    .arg(ttl_millis)
+                    .invoke_async(&mut connection)
+                    .await?
+            }
+        };
+
+        Ok(is_written)
+    }
+
+    async fn delete(&self, key: &str, expected: String) -> Result<bool, KeyValueStoreError> {
+        let is_deleted = Script::new(COMPARE_AND_DELETE_SCRIPT)
+            .key(key)
+            .arg(expected)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+
+        Ok(is_deleted)
+    }
+
+    async fn publish(&self, message: String) -> Result<(), KeyValueStoreError> {
+        redis::cmd("PUBLISH")
+            .arg(SESSION_STATE_CHANNEL)
+            .arg(message)
+            .exec_async(&mut self.connection.clone())
+            .await?;
+
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.messages.subscribe()
+    }
+
+    async fn ping(&self) -> Result<(), KeyValueStoreError> {
+        redis::cmd("PING").exec_async(&mut self.connection.clone()).await?;
+
+        Ok(())
+    }
+}
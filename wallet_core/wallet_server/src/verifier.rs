@@ -1,12 +1,18 @@
-use std::sync::Arc;
+use std::{mem, num::NonZeroU64, sync::Arc, time::Duration};
 
 use axum::{
     extract::{Path, Query, State},
+    response::sse::{Event, KeepAlive, Sse},
     routing::{delete, get, post},
     Form, Json, Router,
 };
-use http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use futures::{stream, Stream};
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
 use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time,
+};
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{info, warn};
 
@@ -15,8 +21,11 @@ use openid4vc::{
     disclosure_session::APPLICATION_OAUTH_AUTHZ_REQ_JWT,
     openid4vp::{VpResponse, WalletRequest},
     return_url::ReturnUrlTemplate,
-    server_state::{SessionStore, SessionToken},
-    verifier::{DisclosureData, SessionType, StatusResponse, Verifier, WalletAuthResponse},
+    server_state::{ProgressStatus, SessionCounts, SessionStore, SessionToken},
+    verifier::{
+        DisclosureData, SessionOptions, SessionSummary, SessionType, StatusResponse, Verifier, WalletAuthResponse,
+        EPHEMERAL_ID_VALIDITY_SECONDS,
+    },
     DisclosureErrorResponse, GetRequestErrorCode, PostAuthResponseErrorCode, VerificationErrorCode,
 };
 use wallet_common::{
@@ -27,6 +36,10 @@ use wallet_common::{
 
 use crate::settings::{self, Urls};
 
+/// The interval at which the status event stream re-reads the status of a session. This re-sends the status of a
+/// session that has not been started yet, so that the ephemeral ID in the universal link it contains remains valid.
+const STATUS_EVENTS_REFRESH_INTERVAL: Duration = Duration::from_secs(EPHEMERAL_ID_VALIDITY_SECONDS.as_secs() / 2);
+
 struct ApplicationState<S> {
     verifier: Verifier<S>,
     public_url: BaseUrl,
@@ -58,7 +71,13 @@ where
     Ok(application_state)
 }
 
-pub fn create_routers<S>(urls: Urls, verifier: settings::Verifier, sessions: S) -> anyhow::Result<(Router, Router)>
+/// Create the routers for the wallet, the requester and the operator (admin) endpoints, respectively. Note that the
+/// admin router is not secured by itself, this is left to the caller.
+pub fn create_routers<S>(
+    urls: Urls,
+    verifier: settings::Verifier,
+    sessions: S,
+) -> anyhow::Result<(Router, Router, Router)>
 where
     S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
@@ -67,6 +86,7 @@ where
     let wallet_web = Router::new()
         .route("/:session_token", get(status::<S>))
         .route("/:session_token", delete(cancel::<S>))
+        .route("/:session_token/events", get(status_events::<S>))
         // The CORS headers should be set for these routes, so that any web browser may call them.
         .layer(
             CorsLayer::new()
@@ -87,11 +107,19 @@ where
     let requester_router = Router::new()
         .route("/", post(start::<S>))
         .route("/:session_token/disclosed_attributes", get(disclosed_attributes::<S>))
+        .with_state(Arc::clone(&application_state));
+
+    let admin_router = Router::new()
+        .route("/", get(list_sessions::<S>))
+        .route("/count", get(count_sessions::<S>))
+        .route("/purge", post(purge_sessions::<S>))
+        .route("/:session_token/expire", post(expire_session::<S>))
         .with_state(application_state);
 
     Ok((
         Router::new().nest("/sessions", wallet_router),
         Router::new().nest("/sessions", requester_router),
+        Router::new().nest("/admin/sessions", admin_router),
     ))
 }
 
@@ -102,7 +130,7 @@ async fn retrieve_request<S>(
     Form(wallet_request): Form<WalletRequest>,
 ) -> Result<(HeaderMap, String), DisclosureErrorResponse<GetRequestErrorCode>>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     info!("process request for Authorization Request JWT");
 
@@ -136,7 +164,7 @@ async fn post_response<S>(
     Form(wallet_response): Form<WalletAuthResponse>,
 ) -> Result<Json<VpResponse>, DisclosureErrorResponse<PostAuthResponseErrorCode>>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     info!("process Verifiable Presentation");
 
@@ -156,19 +184,19 @@ pub struct StatusParams {
     pub session_type: SessionType,
 }
 
-async fn status<S>(
-    State(state): State<Arc<ApplicationState<S>>>,
-    Path(session_token): Path<SessionToken>,
-    query: Option<Query<StatusParams>>,
-) -> Result<Json<StatusResponse>, HttpJsonError<VerificationErrorCode>>
+async fn session_status<S>(
+    state: &ApplicationState<S>,
+    session_token: &SessionToken,
+    session_type: Option<SessionType>,
+) -> Result<StatusResponse, HttpJsonError<VerificationErrorCode>>
 where
     S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     let response = state
         .verifier
         .status_response(
-            &session_token,
-            query.map(|Query(params)| params.session_type),
+            session_token,
+            session_type,
             &urls::disclosure_base_uri(&state.universal_link_base_url),
             state
                 .public_url
@@ -178,15 +206,123 @@ where
         .await
         .inspect_err(|error| warn!("querying session status failed: {error}"))?;
 
+    Ok(response)
+}
+
+async fn status<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+    Path(session_token): Path<SessionToken>,
+    query: Option<Query<StatusParams>>,
+) -> Result<Json<StatusResponse>, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    let response = session_status(&state, &session_token, query.map(|Query(params)| params.session_type)).await?;
+
     Ok(Json(response))
 }
 
+/// Keeps track of the session status sent over a status event stream.
+struct StatusEvents<S> {
+    state: Arc<ApplicationState<S>>,
+    session_token: SessionToken,
+    session_type: Option<SessionType>,
+    updates: broadcast::Receiver<SessionToken>,
+    last_status: Option<StatusResponse>,
+}
+
+impl<S> StatusEvents<S>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    /// Wait for the next status that should be sent to the client. This returns `None` when the stream should end,
+    /// i.e. when the session has finished or when its status can no longer be determined.
+    async fn next_status(&mut self) -> Option<StatusResponse> {
+        loop {
+            if let Some(last_status) = &self.last_status {
+                match last_status {
+                    StatusResponse::Created { .. } | StatusResponse::WaitingForResponse => {}
+                    StatusResponse::Done
+                    | StatusResponse::Failed
+                    | StatusResponse::Cancelled
+                    | StatusResponse::Expired => return None,
+                };
+
+                match time::timeout(STATUS_EVENTS_REFRESH_INTERVAL, self.updates.recv()).await {
+                    Ok(Ok(token)) if token != self.session_token => continue,
+                    // When lagging behind, we may have missed an update for this session, so check its status.
+                    Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => {}
+                    Ok(Err(RecvError::Closed)) => return None,
+                    // Check the status periodically, which refreshes the universal link while the session has not
+                    // been started. Reading the session also expires it if the session store expires lazily, in
+                    // which case no update is published until the session is read.
+                    Err(_) => {}
+                }
+            }
+
+            let status = session_status(&self.state, &self.session_token, self.session_type)
+                .await
+                .ok()?;
+
+            let has_changed = self
+                .last_status
+                .as_ref()
+                .map(|last_status| mem::discriminant(last_status) != mem::discriminant(&status))
+                .unwrap_or(true);
+
+            if has_changed || matches!(status, StatusResponse::Created { .. }) {
+                self.last_status = Some(status.clone());
+
+                return Some(status);
+            }
+        }
+    }
+}
+
+async fn status_events<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+    Path(session_token): Path<SessionToken>,
+    query: Option<Query<StatusParams>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    let session_type = query.map(|Query(params)| params.session_type);
+
+    // Subscribe before fetching the initial status, so that no update can be missed in between.
+    let updates = state.verifier.subscribe();
+    let status = session_status(&state, &session_token, session_type).await?;
+
+    let events = StatusEvents {
+        state,
+        session_token,
+        session_type,
+        updates,
+        last_status: None,
+    };
+
+    // The initial status is sent immediately, after which every change in status results in a new event.
+    let stream = stream::unfold((Some(status), events), |(initial_status, mut events)| async move {
+        let status = match initial_status {
+            Some(status) => {
+                events.last_status = Some(status.clone());
+                status
+            }
+            None => events.next_status().await?,
+        };
+
+        Some((Event::default().json_data(status), (None, events)))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 async fn cancel<S>(
     State(state): State<Arc<ApplicationState<S>>>,
     Path(session_token): Path<SessionToken>,
 ) -> Result<StatusCode, HttpJsonError<VerificationErrorCode>>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     state
         .verifier
@@ -202,6 +338,9 @@ pub struct StartDisclosureRequest {
     pub usecase: String,
     pub items_requests: ItemsRequests,
     pub return_url_template: Option<ReturnUrlTemplate>,
+    pub callback_url: Option<BaseUrl>,
+    pub expiration_seconds: Option<NonZeroU64>,
+    pub correlation_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,7 +353,7 @@ async fn start<S>(
     Json(start_request): Json<StartDisclosureRequest>,
 ) -> Result<Json<StartDisclosureResponse>, HttpJsonError<VerificationErrorCode>>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     let session_token = state
         .verifier
@@ -222,6 +361,13 @@ where
             start_request.items_requests,
             start_request.usecase,
             start_request.return_url_template,
+            start_request.callback_url,
+            SessionOptions {
+                expiration: start_request
+                    .expiration_seconds
+                    .map(|seconds| Duration::from_secs(seconds.get())),
+                correlation_id: start_request.correlation_id,
+            },
         )
         .await
         .inspect_err(|error| warn!("starting new session failed: {error}"))?;
@@ -229,6 +375,10 @@ where
     Ok(Json(StartDisclosureResponse { session_token }))
 }
 
+/// The response header containing the correlation id the RP may have provided when starting the session, which is
+/// returned along with the disclosed attributes.
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisclosedAttributesParams {
     pub nonce: Option<String>,
@@ -238,15 +388,94 @@ async fn disclosed_attributes<S>(
     State(state): State<Arc<ApplicationState<S>>>,
     Path(session_token): Path<SessionToken>,
     Query(params): Query<DisclosedAttributesParams>,
-) -> Result<Json<DisclosedAttributes>, HttpJsonError<VerificationErrorCode>>
+) -> Result<(HeaderMap, Json<DisclosedAttributes>), HttpJsonError<VerificationErrorCode>>
 where
-    S: SessionStore<DisclosureData>,
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
-    let disclosed_attributes = state
+    let (disclosed_attributes, correlation_id) = state
         .verifier
         .disclosed_attributes(&session_token, params.nonce)
         .await
         .inspect_err(|error| warn!("fetching disclosed attributes failed: {error}"))?;
 
-    Ok(Json(disclosed_attributes))
+    // The correlation id is validated when starting the session, so it is a valid header value.
+    let headers = correlation_id
+        .and_then(|correlation_id| HeaderValue::try_from(correlation_id).ok())
+        .map(|correlation_id| HeaderMap::from_iter([(CORRELATION_ID_HEADER, correlation_id)]))
+        .unwrap_or_default();
+
+    Ok((headers, Json(disclosed_attributes)))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListSessionsParams {
+    pub status: Option<ProgressStatus>,
+    pub usecase: Option<String>,
+}
+
+async fn list_sessions<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+    Query(params): Query<ListSessionsParams>,
+) -> Result<Json<Vec<SessionSummary>>, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    let sessions = state
+        .verifier
+        .sessions(params.status, params.usecase.as_deref())
+        .await
+        .inspect_err(|error| warn!("listing sessions failed: {error}"))?;
+
+    Ok(Json(sessions))
+}
+
+async fn count_sessions<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+) -> Result<Json<SessionCounts>, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    let counts = state
+        .verifier
+        .session_counts()
+        .await
+        .inspect_err(|error| warn!("counting sessions failed: {error}"))?;
+
+    Ok(Json(counts))
+}
+
+async fn expire_session<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+    Path(session_token): Path<SessionToken>,
+) -> Result<StatusCode, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    state
+        .verifier
+        .expire(&session_token)
+        .await
+        .inspect_err(|error| warn!("expiring session failed: {error}"))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeSessionsResponse {
+    pub purged: u64,
+}
+
+async fn purge_sessions<S>(
+    State(state): State<Arc<ApplicationState<S>>>,
+) -> Result<Json<PurgeSessionsResponse>, HttpJsonError<VerificationErrorCode>>
+where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    let purged = state
+        .verifier
+        .purge_finished()
+        .await
+        .inspect_err(|error| warn!("purging sessions failed: {error}"))?;
+
+    Ok(Json(PurgeSessionsResponse { purged }))
 }
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use openid4vc::server_state::{test::RandomData, Expirable, HasProgress, Progress};
+use wallet_common::utils;
+use wallet_server::store::SessionDataType;
+
+/// A mock data type that adheres to all the trait bounds necessary for testing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockSessionData {
+    #[serde(with = "ProgressDef")]
+    progress: Progress,
+    is_expired: bool,
+    expiration: Option<Duration>,
+    is_pending: bool,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Progress")]
+pub enum ProgressDef {
+    Active,
+    Finished { has_succeeded: bool },
+}
+
+impl MockSessionData {
+    fn new(progress: Progress) -> Self {
+        Self {
+            progress,
+            is_expired: false,
+            expiration: None,
+            is_pending: false,
+            data: utils::random_bytes(32),
+        }
+    }
+
+    /// Create active session data that has its own expiration.
+    pub fn with_expiration(expiration: Duration) -> Self {
+        Self::with_progress_and_expiration(Progress::Active, expiration)
+    }
+
+    /// Create session data with the specified progress that has its own expiration.
+    pub fn with_progress_and_expiration(progress: Progress, expiration: Duration) -> Self {
+        Self {
+            expiration: Some(expiration),
+            ..Self::new(progress)
+        }
+    }
+
+    /// Create finished session data that is pending.
+    pub fn pending() -> Self {
+        Self {
+            is_pending: true,
+            ..Self::new(Progress::Finished { has_succeeded: true })
+        }
+    }
+}
+
+impl From<Progress> for MockSessionData {
+    fn from(value: Progress) -> Self {
+        Self::new(value)
+    }
+}
+
+impl HasProgress for MockSessionData {
+    fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    fn is_pending(&self) -> bool {
+        self.is_pending
+    }
+}
+
+impl Expirable for MockSessionData {
+    fn is_expired(&self) -> bool {
+        self.is_expired
+    }
+
+    fn expire(&mut self) {
+        self.is_expired = true
+    }
+
+    fn expiration(&self) -> Option<Duration> {
+        self.expiration
+    }
+}
+
+impl RandomData for MockSessionData {
+    fn new_random() -> Self {
+        Self::new(Progress::Active)
+    }
+}
+
+impl SessionDataType for MockSessionData {
+    const TYPE: &'static str = "mockdata";
+}
@@ -0,0 +1,293 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::broadcast;
+
+use nl_wallet_mdoc::utils::mock_time::MockTimeGenerator;
+use openid4vc::server_state::{
+    test, Progress, SessionState, SessionStore, SessionStoreTimeouts, SessionToken, SESSION_UPDATES_CAPACITY,
+};
+use wallet_common::generator::Generator;
+use wallet_server::store::key_value::{KeyValueSessionStore, KeyValueStore, KeyValueStoreError, SetCondition};
+
+use crate::common::MockSessionData;
+
+mod common;
+
+/// An in-process fake of a key-value store such as Redis, which expires keys based on the provided time generator.
+struct FakeKeyValueStore {
+    time: MockTimeGenerator,
+    entries: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+    messages: broadcast::Sender<String>,
+}
+
+impl FakeKeyValueStore {
+    fn new(time: MockTimeGenerator) -> Self {
+        Self {
+            time,
+            entries: Mutex::default(),
+            messages: broadcast::channel(SESSION_UPDATES_CAPACITY).0,
+        }
+    }
+}
+
+impl KeyValueStore for FakeKeyValueStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, KeyValueStoreError> {
+        let now = self.time.generate();
+        let value = self
+            .entries
+            .lock()
+            .get(key)
+            .filter(|(_, expires_at)| now <= *expires_at)
+            .map(|(value, _)| value.clone());
+
+        Ok(value)
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, KeyValueStoreError> {
+        let now = self.time.generate();
+        let keys = self
+            .entries
+            .lock()
+            .iter()
+            .filter(|(key, (_, expires_at))| key.starts_with(prefix) && now <= *expires_at)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        Ok(keys)
+    }
+
+    async fn set(
+        &self,
+        key: &str,
+        value: String,
+        ttl: Duration,
+        condition: SetCondition,
+    ) -> Result<bool, KeyValueStoreError> {
+        let now = self.time.generate();
+        let mut entries = self.entries.lock();
+
+        let current_value = entries
+            .get(key)
+            .filter(|(_, expires_at)| now <= *expires_at)
+            .map(|(value, _)| value);
+
+        let should_write = match condition {
+            SetCondition::NotExists => current_value.is_none(),
+            SetCondition::Equals(expected) => current_value == Some(&expected),
+        };
+
+        if should_write {
+            entries.insert(key.to_string(), (value, now + ttl));
+        }
+
+        Ok(should_write)
+    }
+
+    async fn delete(&self, key: &str, expected: String) -> Result<bool, KeyValueStoreError> {
+        let now = self.time.generate();
+        let mut entries = self.entries.lock();
+
+        let should_delete = entries
+            .get(key)
+            .is_some_and(|(value, expires_at)| now <= *expires_at && *value == expected);
+
+        if should_delete {
+            entries.remove(key);
+        }
+
+        Ok(should_delete)
+    }
+
+    async fn publish(&self, message: String) -> Result<(), KeyValueStoreError> {
+        let _ = self.messages.send(message);
+
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.messages.subscribe()
+    }
+
+    async fn ping(&self) -> Result<(), KeyValueStoreError> {
+        Ok(())
+    }
+}
+
+type SessionStoreWithMockTime = (
+    KeyValueSessionStore<FakeKeyValueStore, MockTimeGenerator>,
+    Arc<RwLock<DateTime<Utc>>>,
+);
+
+fn key_value_session_store_with_mock_time() -> SessionStoreWithMockTime {
+    let time_generator = MockTimeGenerator::default();
+    let mock_time = Arc::clone(&time_generator.time);
+
+    let store = FakeKeyValueStore::new(time_generator.clone());
+    let session_store = KeyValueSessionStore::new_with_time(store, SessionStoreTimeouts::default(), time_generator);
+
+    (session_store, mock_time)
+}
+
+#[tokio::test]
+async fn test_get_write() {
+    let (session_store, _) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_get_write::<MockSessionData>(&session_store).await;
+}
+
+#[tokio::test]
+async fn test_subscribe() {
+    let (session_store, _) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_subscribe::<MockSessionData>(&session_store).await;
+}
+
+#[tokio::test]
+async fn test_list_count_purge() {
+    let (session_store, _) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_list_count_purge::<MockSessionData>(&session_store).await;
+}
+
+#[tokio::test]
+async fn test_list_pending() {
+    let (session_store, _) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_list_pending(
+        &session_store,
+        MockSessionData::pending(),
+        MockSessionData::from(Progress::Finished { has_succeeded: true }),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cleanup_expiration() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_cleanup_expiration::<MockSessionData>(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cleanup_expires_without_reading() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+
+    let token = SessionToken::new_random();
+    session_store
+        .write(
+            SessionState::new(token.clone(), MockSessionData::from(Progress::Active)),
+            true,
+        )
+        .await
+        .expect("should succeed");
+
+    let t = *mock_time.read() + session_store.timeouts.expiration + chrono::Duration::milliseconds(1);
+    *mock_time.write() = t;
+
+    // Cleaning up should expire the session by itself, which is published to subscribers.
+    let mut updates = SessionStore::<MockSessionData>::subscribe(&session_store);
+    SessionStore::<MockSessionData>::cleanup(&session_store)
+        .await
+        .expect("should succeed");
+
+    let updated_token = tokio::time::timeout(Duration::from_secs(1), updates.recv())
+        .await
+        .expect("session should be expired by cleanup")
+        .expect("should receive update");
+
+    assert_eq!(updated_token, token);
+}
+
+#[tokio::test]
+async fn test_list_count_without_expiring() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+
+    let token = SessionToken::new_random();
+    session_store
+        .write(
+            SessionState::new(token.clone(), MockSessionData::from(Progress::Active)),
+            true,
+        )
+        .await
+        .expect("should succeed");
+
+    let t = *mock_time.read() + session_store.timeouts.expiration + chrono::Duration::milliseconds(1);
+    *mock_time.write() = t;
+
+    // Listing and counting the sessions should not expire the session that should have expired, as only reading it
+    // using `get()` and cleaning up do that.
+    let mut updates = SessionStore::<MockSessionData>::subscribe(&session_store);
+    let sessions = SessionStore::<MockSessionData>::list(&session_store, None)
+        .await
+        .expect("should succeed");
+    let counts = SessionStore::<MockSessionData>::count(&session_store)
+        .await
+        .expect("should succeed");
+
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].version, 0);
+    assert_eq!(counts.active, 1);
+    assert!(updates.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_cleanup_expiration_override() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+    let session_data = MockSessionData::with_expiration(session_store.timeouts.expiration * 2);
+
+    test::test_session_store_cleanup_expiration_override(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+        session_data,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cleanup_successful_deletion() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_cleanup_successful_deletion::<MockSessionData>(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cleanup_successful_deletion_override() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+    let session_data = MockSessionData::with_progress_and_expiration(
+        Progress::Finished { has_succeeded: true },
+        session_store.timeouts.successful_deletion * 2,
+    );
+
+    test::test_session_store_cleanup_successful_deletion_override(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+        session_data,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cleanup_failed_deletion() {
+    let (session_store, mock_time) = key_value_session_store_with_mock_time();
+
+    test::test_session_store_cleanup_failed_deletion::<MockSessionData>(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+    )
+    .await;
+}
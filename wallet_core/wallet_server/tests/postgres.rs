@@ -2,78 +2,19 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
-use serde::{Deserialize, Serialize};
 
 use serial_test::{parallel, serial};
 
 use nl_wallet_mdoc::utils::mock_time::MockTimeGenerator;
-use openid4vc::server_state::{
-    test::{self, RandomData},
-    Expirable, HasProgress, Progress, SessionStoreTimeouts,
-};
-use wallet_common::utils;
+use openid4vc::server_state::{test, Progress, SessionStoreTimeouts};
 use wallet_server::{
     settings::{Settings, Storage},
-    store::{postgres::PostgresSessionStore, SessionDataType},
+    store::postgres::PostgresSessionStore,
 };
 
-/// A mock data type that adheres to all the trait bounds necessary for testing.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-struct MockSessionData {
-    #[serde(with = "ProgressDef")]
-    progress: Progress,
-    is_expired: bool,
-    data: Vec<u8>,
-}
+use crate::common::MockSessionData;
 
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "Progress")]
-pub enum ProgressDef {
-    Active,
-    Finished { has_succeeded: bool },
-}
-
-impl MockSessionData {
-    fn new(progress: Progress) -> Self {
-        Self {
-            progress,
-            is_expired: false,
-            data: utils::random_bytes(32),
-        }
-    }
-}
-
-impl From<Progress> for MockSessionData {
-    fn from(value: Progress) -> Self {
-        Self::new(value)
-    }
-}
-
-impl HasProgress for MockSessionData {
-    fn progress(&self) -> Progress {
-        self.progress
-    }
-}
-
-impl Expirable for MockSessionData {
-    fn is_expired(&self) -> bool {
-        self.is_expired
-    }
-
-    fn expire(&mut self) {
-        self.is_expired = true
-    }
-}
-
-impl RandomData for MockSessionData {
-    fn new_random() -> Self {
-        Self::new(Progress::Active)
-    }
-}
-
-impl SessionDataType for MockSessionData {
-    const TYPE: &'static str = "mockdata";
-}
+mod common;
 
 fn storage_settings() -> Storage {
     Settings::new_custom("ws_integration_test.toml", "ws_integration_test")
@@ -106,6 +47,14 @@ async fn postgres_session_store_with_mock_time() -> SessionStoreWithMockTime {
     (session_store, mock_time)
 }
 
+#[tokio::test]
+#[parallel(cleanup)]
+async fn test_ping() {
+    let session_store = postgres_session_store().await;
+
+    session_store.ping().await.expect("database should be reachable");
+}
+
 #[tokio::test]
 #[parallel(cleanup)]
 async fn test_get_write() {
@@ -114,6 +63,35 @@ async fn test_get_write() {
     test::test_session_store_get_write::<MockSessionData>(&session_store).await;
 }
 
+#[tokio::test]
+#[parallel(cleanup)]
+async fn test_subscribe() {
+    let session_store = postgres_session_store().await;
+
+    test::test_session_store_subscribe::<MockSessionData>(&session_store).await;
+}
+
+#[tokio::test]
+#[serial(cleanup)]
+async fn test_list_count_purge() {
+    let session_store = postgres_session_store().await;
+
+    test::test_session_store_list_count_purge::<MockSessionData>(&session_store).await;
+}
+
+#[tokio::test]
+#[parallel(cleanup)]
+async fn test_list_pending() {
+    let session_store = postgres_session_store().await;
+
+    test::test_session_store_list_pending(
+        &session_store,
+        MockSessionData::pending(),
+        MockSessionData::from(Progress::Finished { has_succeeded: true }),
+    )
+    .await;
+}
+
 #[tokio::test]
 #[serial(cleanup)]
 async fn test_cleanup_expiration() {
@@ -127,6 +105,21 @@ async fn test_cleanup_expiration() {
     .await;
 }
 
+#[tokio::test]
+#[serial(cleanup)]
+async fn test_cleanup_expiration_override() {
+    let (session_store, mock_time) = postgres_session_store_with_mock_time().await;
+    let session_data = MockSessionData::with_expiration(session_store.timeouts.expiration * 2);
+
+    test::test_session_store_cleanup_expiration_override(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+        session_data,
+    )
+    .await;
+}
+
 #[tokio::test]
 #[serial(cleanup)]
 async fn test_cleanup_successful_deletion() {
@@ -140,6 +133,24 @@ async fn test_cleanup_successful_deletion() {
     .await;
 }
 
+#[tokio::test]
+#[serial(cleanup)]
+async fn test_cleanup_successful_deletion_override() {
+    let (session_store, mock_time) = postgres_session_store_with_mock_time().await;
+    let session_data = MockSessionData::with_progress_and_expiration(
+        Progress::Finished { has_succeeded: true },
+        session_store.timeouts.successful_deletion * 2,
+    );
+
+    test::test_session_store_cleanup_successful_deletion_override(
+        &session_store,
+        &session_store.timeouts,
+        mock_time.as_ref(),
+        session_data,
+    )
+    .await;
+}
+
 #[tokio::test]
 #[serial(cleanup)]
 async fn test_cleanup_failed_deletion() {
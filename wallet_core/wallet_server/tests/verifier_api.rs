@@ -30,31 +30,43 @@ use nl_wallet_mdoc::{
         issuer_auth::IssuerRegistration, mock_time::MockTimeGenerator, reader_auth::ReaderRegistration,
         serialization::TaggedBytes,
     },
-    verifier::DisclosedAttributes,
+    verifier::{DisclosedAttributes, ItemsRequests},
     DeviceResponse, IssuerSigned, ItemsRequest,
 };
 use openid4vc::{
     disclosure_session::{DisclosureSession, DisclosureUriSource, HttpVpMessageClient},
-    server_state::{MemorySessionStore, SessionStore, SessionStoreTimeouts, SessionToken, CLEANUP_INTERVAL_SECONDS},
-    verifier::{DisclosureData, SessionType, SessionTypeReturnUrl, StatusResponse, VerifierUrlParameters},
+    openid4vp::QueryLanguage,
+    server_state::{
+        MemorySessionStore, SessionCounts, SessionStore, SessionStoreTimeouts, SessionToken, CLEANUP_INTERVAL_SECONDS,
+    },
+    verifier::{
+        DisclosureData, SessionSummary, SessionType, SessionTypeReturnUrl, StatusResponse, VerifierUrlParameters,
+    },
     ErrorResponse,
 };
 use wallet_common::{
-    generator::TimeGenerator, http_error::HttpJsonErrorBody, keys::software::SoftwareEcdsaKey,
+    generator::TimeGenerator, health::HealthChecker, http_error::HttpJsonErrorBody, keys::software::SoftwareEcdsaKey,
     reqwest::default_reqwest_client_builder, trust_anchor::OwnedTrustAnchor, urls::BaseUrl, utils,
 };
 #[cfg(feature = "issuance")]
 use wallet_server::settings::{Digid, Issuer};
 use wallet_server::{
     settings::{Authentication, RequesterAuth, Server, Settings, Storage, Urls, Verifier, VerifierUseCase},
-    verifier::{StartDisclosureRequest, StartDisclosureResponse, StatusParams},
+    store::SessionStoreVariant,
+    verifier::{
+        PurgeSessionsResponse, StartDisclosureRequest, StartDisclosureResponse, StatusParams, CORRELATION_ID_HEADER,
+    },
 };
 
 const USECASE_NAME: &str = "usecase";
+const EXAMPLE_CORRELATION_ID: &str = "correlation-id";
 
 static EXAMPLE_START_DISCLOSURE_REQUEST: LazyLock<StartDisclosureRequest> = LazyLock::new(|| StartDisclosureRequest {
     usecase: USECASE_NAME.to_string(),
     return_url_template: Some("https://return.url/{session_token}".parse().unwrap()),
+    callback_url: None,
+    expiration_seconds: None,
+    correlation_id: Some(EXAMPLE_CORRELATION_ID.to_string()),
     items_requests: vec![ItemsRequest {
         doc_type: EXAMPLE_DOC_TYPE.to_string(),
         request_info: None,
@@ -84,13 +96,17 @@ fn fake_issuer_settings() -> Issuer {
 
     Issuer {
         private_keys: Default::default(),
+        metadata_signing_key: None,
         wallet_client_ids: Default::default(),
-        digid: Digid {
+        wallet_provider_public_key: None,
+        digid: Some(Digid {
             issuer_url: url.clone(),
             bsn_privkey: Default::default(),
             trust_anchors: Default::default(),
-        },
-        brp_server: url,
+        }),
+        brp_server: Some(url),
+        refresh_subject_key: None,
+        internal_server: None,
     }
 }
 
@@ -124,6 +140,7 @@ fn wallet_server_settings() -> (Settings, KeyPair, OwnedTrustAnchor) {
         USECASE_NAME.to_string(),
         VerifierUseCase {
             session_type_return_url: SessionTypeReturnUrl::SameDevice,
+            query_language: QueryLanguage::PresentationExchange,
             key_pair: wallet_server::settings::KeyPair {
                 certificate: usecase_keypair.certificate().as_bytes().to_vec(),
                 private_key: usecase_keypair
@@ -148,6 +165,7 @@ fn wallet_server_settings() -> (Settings, KeyPair, OwnedTrustAnchor) {
             ip: localhost,
             port: rp_port,
         }),
+        admin: None,
         urls: Urls {
             public_url: format!("http://localhost:{ws_port}/").parse().unwrap(),
             universal_link_base_url: "http://universal.link/".parse().unwrap(),
@@ -180,11 +198,23 @@ fn wallet_server_settings() -> (Settings, KeyPair, OwnedTrustAnchor) {
 async fn start_wallet_server<S>(settings: Settings, disclosure_sessions: S)
 where
     S: SessionStore<DisclosureData> + Send + Sync + 'static,
+{
+    start_wallet_server_with_health_checkers(settings, disclosure_sessions, vec![]).await
+}
+
+async fn start_wallet_server_with_health_checkers<S>(
+    settings: Settings,
+    disclosure_sessions: S,
+    health_checkers: Vec<Arc<dyn HealthChecker>>,
+) where
+    S: SessionStore<DisclosureData> + Send + Sync + 'static,
 {
     let public_url = settings.urls.public_url.clone();
 
     tokio::spawn(async move {
-        if let Err(error) = wallet_server::server::verification_server::serve(settings, disclosure_sessions).await {
+        if let Err(error) =
+            wallet_server::server::verification_server::serve(settings, disclosure_sessions, health_checkers).await
+        {
             println!("Could not start wallet_server: {error:?}");
 
             process::exit(1);
@@ -403,7 +433,7 @@ async fn test_new_session_parameters_error() {
 
     let no_items_request = {
         let mut request = EXAMPLE_START_DISCLOSURE_REQUEST.clone();
-        request.items_requests = vec![].into();
+        request.items_requests = ItemsRequests(vec![]);
         request
     };
 
@@ -590,6 +620,235 @@ async fn test_disclosure_cancel() {
     );
 }
 
+#[tokio::test]
+async fn test_metrics() {
+    let (settings, client, session_token, internal_url, _, _) = start_disclosure(MemorySessionStore::default()).await;
+
+    let status_url = format_status_url(&settings.urls.public_url, &session_token, None);
+    get_status_ok(&client, status_url).await;
+
+    // The metrics should only be available on the internal URL and contain the latency of the requests above, labeled
+    // by their route, as well as the created session.
+    let response = client.get(internal_url.join("metrics")).send().await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let metrics = response.text().await.unwrap();
+
+    assert!(metrics.contains(r#"http_request_duration_seconds_bucket{route="/disclosure/sessions/:session_token""#));
+    assert!(metrics
+        .contains(r#"session_state_transitions_total{session_type="disclosure",state="created",status="active"}"#));
+
+    let response = client
+        .get(settings.urls.public_url.join("metrics"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_readiness() {
+    let (settings, _, _) = wallet_server_settings();
+    let internal_url = internal_url(&settings.requester_server, &settings.urls.public_url);
+
+    let disclosure_sessions =
+        SessionStoreVariant::<DisclosureData>::new("memory://".parse().unwrap(), SessionStoreTimeouts::default())
+            .await
+            .unwrap();
+    let health_checkers = vec![disclosure_sessions.health_checker()];
+
+    start_wallet_server_with_health_checkers(settings.clone(), disclosure_sessions, health_checkers).await;
+
+    // The readiness endpoint should be available on both the public and internal URL and report on the session store.
+    let client = default_reqwest_client_builder().build().unwrap();
+    for base_url in [&settings.urls.public_url, &internal_url] {
+        let response = client.get(base_url.join("health/ready")).send().await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            serde_json::json!({ "status": "ready", "checks": { "session_store": { "status": "ok" } } })
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_admin_sessions() {
+    let (mut settings, _, _) = wallet_server_settings();
+    settings.admin = Some(Authentication::ApiKey(String::from("admin_key")));
+    let internal_url = internal_url(&settings.requester_server, &settings.urls.public_url);
+
+    start_wallet_server(settings.clone(), MemorySessionStore::default()).await;
+
+    let client = default_reqwest_client_builder().build().unwrap();
+    let response = client
+        .post(internal_url.join("disclosure/sessions"))
+        .json(LazyLock::force(&EXAMPLE_START_DISCLOSURE_REQUEST))
+        .send()
+        .await
+        .unwrap();
+    let session_token = response.json::<StartDisclosureResponse>().await.unwrap().session_token;
+
+    let admin_url = internal_url.join("disclosure/admin/sessions");
+    let count_url = internal_url.join("disclosure/admin/sessions/count");
+
+    // Using no token or the requester token should return a 401.
+    let response = client.get(admin_url.clone()).send().await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .get(admin_url.clone())
+        .bearer_auth("secret_key")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Listing the sessions should return the newly created session.
+    let list_sessions = |query: &'static str| {
+        let mut url = admin_url.clone();
+        url.set_query(Some(query));
+        let request = client.get(url).bearer_auth("admin_key");
+
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            response.json::<Vec<SessionSummary>>().await.unwrap()
+        }
+    };
+
+    let sessions = list_sessions("").await;
+
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_token, session_token);
+    assert_eq!(sessions[0].usecase_id, USECASE_NAME);
+    assert_matches!(sessions[0].status, StatusResponse::Created { ul: None });
+
+    // Filtering on a different status or use case should return no sessions.
+    assert_eq!(list_sessions("status=active").await.len(), 1);
+    assert!(list_sessions("status=succeeded").await.is_empty());
+    assert!(list_sessions("usecase=other_usecase").await.is_empty());
+
+    // The session should be counted as active.
+    let get_counts = || async {
+        let response = client
+            .get(count_url.clone())
+            .bearer_auth("admin_key")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        response.json::<SessionCounts>().await.unwrap()
+    };
+
+    assert_eq!(
+        get_counts().await,
+        SessionCounts {
+            active: 1,
+            ..Default::default()
+        }
+    );
+
+    // Expiring the session should return a 204, after which the session status is expired.
+    let expire_url = internal_url.join(&format!("disclosure/admin/sessions/{session_token}/expire"));
+    let response = client
+        .post(expire_url.clone())
+        .bearer_auth("admin_key")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let status_url = format_status_url(&settings.urls.public_url, &session_token, None);
+
+    assert_matches!(get_status_ok(&client, status_url).await, StatusResponse::Expired);
+    assert_eq!(
+        get_counts().await,
+        SessionCounts {
+            expired: 1,
+            ..Default::default()
+        }
+    );
+
+    // Expiring the session again should return a 400.
+    let response = client.post(expire_url).bearer_auth("admin_key").send().await.unwrap();
+
+    test_http_json_error_body(response, StatusCode::BAD_REQUEST, "session_state").await;
+
+    // Purging the finished sessions should remove the expired session.
+    let response = client
+        .post(internal_url.join("disclosure/admin/sessions/purge"))
+        .bearer_auth("admin_key")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<PurgeSessionsResponse>().await.unwrap().purged, 1);
+    assert_eq!(get_counts().await, SessionCounts::default());
+}
+
+/// Read the next event from a server-sent event stream of session statuses, buffering any partially received events.
+async fn next_status_event(response: &mut Response, buffer: &mut String) -> Option<StatusResponse> {
+    loop {
+        if let Some((event, remainder)) = buffer.split_once("\n\n") {
+            let data = event.lines().find_map(|line| line.strip_prefix("data:"));
+            let status = data.map(|data| serde_json::from_str(data.trim()).unwrap());
+            *buffer = remainder.to_string();
+
+            // Skip any comments, which are used for keep-alive messages.
+            match status {
+                Some(status) => return Some(status),
+                None => continue,
+            }
+        }
+
+        let chunk = time::timeout(Duration::from_secs(10), response.chunk())
+            .await
+            .expect("should receive event in time")
+            .unwrap()?;
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_disclosure_status_events() {
+    let (settings, client, session_token, _, _, _) = start_disclosure(MemorySessionStore::default()).await;
+
+    // Subscribing to status events should first result in the Created state.
+    let mut events_url = format_status_url(&settings.urls.public_url, &session_token, Some(SessionType::SameDevice));
+    events_url.set_path(&format!("{}/events", events_url.path()));
+
+    let mut response = client.get(events_url).send().await.unwrap();
+    let mut buffer = String::new();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(
+        next_status_event(&mut response, &mut buffer).await,
+        Some(StatusResponse::Created { ul: Some(_) })
+    );
+
+    // Cancelling the session should result in a Cancelled event, after which the stream ends.
+    let cancel_url = settings
+        .urls
+        .public_url
+        .join(&format!("disclosure/sessions/{session_token}"));
+    client.delete(cancel_url).send().await.unwrap();
+
+    assert_matches!(
+        next_status_event(&mut response, &mut buffer).await,
+        Some(StatusResponse::Cancelled)
+    );
+    assert_matches!(next_status_event(&mut response, &mut buffer).await, None);
+}
+
 async fn test_disclosure_expired<S>(
     settings: Settings,
     session_store: S,
@@ -826,7 +1085,11 @@ async fn perform_full_disclosure(session_type: SessionType) -> (Client, SessionT
     (client, session_token, internal_url, return_url)
 }
 
-fn check_example_disclosed_attributes(disclosed_attributes: &DisclosedAttributes) {
+async fn check_example_disclosed_attributes(response: reqwest::Response) {
+    let correlation_id = response.headers().get(CORRELATION_ID_HEADER).unwrap();
+    assert_eq!(correlation_id, EXAMPLE_CORRELATION_ID);
+
+    let disclosed_attributes = response.json::<DisclosedAttributes>().await.unwrap();
     itertools::assert_equal(disclosed_attributes.keys(), [EXAMPLE_DOC_TYPE]);
     let attributes = &disclosed_attributes.get(EXAMPLE_DOC_TYPE).unwrap().attributes;
     itertools::assert_equal(attributes.keys(), [EXAMPLE_NAMESPACE]);
@@ -849,10 +1112,8 @@ async fn test_disclosed_attributes_without_nonce() {
 
     assert_eq!(response.status(), StatusCode::OK);
 
-    // Check the disclosed attributes against the example attributes.
-    let disclosed_attributes = response.json::<DisclosedAttributes>().await.unwrap();
-
-    check_example_disclosed_attributes(&disclosed_attributes);
+    // Check the disclosed attributes and correlation id against the example.
+    check_example_disclosed_attributes(response).await;
 }
 
 #[tokio::test]
@@ -896,10 +1157,8 @@ async fn test_disclosed_attributes_with_nonce() {
 
     assert_eq!(response.status(), StatusCode::OK);
 
-    // Check the disclosed attributes against the example attributes.
-    let disclosed_attributes = response.json::<DisclosedAttributes>().await.unwrap();
-
-    check_example_disclosed_attributes(&disclosed_attributes);
+    // Check the disclosed attributes and correlation id against the example.
+    check_example_disclosed_attributes(response).await;
 }
 
 #[tokio::test]